@@ -0,0 +1,181 @@
+//! Static dependency analysis of parsed expressions.
+
+use std::collections::BTreeSet;
+
+use crate::ast::Expr;
+use crate::error::Error;
+use crate::options::NON_DETERMINISTIC_FUNCTIONS;
+use crate::runtime::function_dispatch::is_builtin;
+use crate::runtime::method_calls::LAMBDA_METHODS;
+
+/// Names an expression refers to, each list sorted and de-duplicated
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Dependencies {
+    /// Variables read from the caller (without the leading `:`). LET names and lambda
+    /// parameters, including the implicit `:x` and `:acc` of MAP, REDUCE etc., are omitted
+    pub variables: Vec<String>,
+    /// Functions called, upper case; parser internals such as `__TERNARY__` are omitted
+    pub functions: Vec<String>,
+    /// Method names called, lower case
+    pub methods: Vec<String>,
+}
+
+impl Dependencies {
+    /// Called functions that are not builtins (custom, JavaScript or unknown).
+    /// These are what `builtins_only` evaluation refuses to run.
+    pub fn non_builtin_functions(&self) -> Vec<String> {
+        self.functions.iter().filter(|f| !is_builtin(f)).cloned().collect()
+    }
+}
+
+#[derive(Default)]
+struct Collector {
+    variables: BTreeSet<String>,
+    functions: BTreeSet<String>,
+    methods: BTreeSet<String>,
+    /// Whether a call reads the clock or draws random numbers
+    nondeterministic: bool,
+}
+
+/// Collect the variables, functions and methods an expression uses
+pub fn analyze(expr: &Expr) -> Dependencies {
+    let mut c = Collector::default();
+    c.visit(expr);
+    Dependencies {
+        variables: c.variables.into_iter().collect(),
+        functions: c.functions.into_iter().collect(),
+        methods: c.methods.into_iter().collect(),
+    }
+}
+
+/// Whether evaluating `expr` twice with the same variables must give the same result:
+/// false when it calls NOW or FEATURE, DATE or TIME without arguments, or an unseeded CHOOSEWEIGHTED
+pub fn is_deterministic(expr: &Expr) -> bool {
+    let mut c = Collector::default();
+    c.visit(expr);
+    !c.nondeterministic
+}
+
+/// Reject an expression that calls anything but builtins, naming every offending function
+pub fn check_builtins_only(expr: &Expr) -> Result<(), Error> {
+    let blocked = analyze(expr).non_builtin_functions();
+    if blocked.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::new(
+            format!("Only builtin functions are allowed; blocked: {}", blocked.join(", ")),
+            None,
+        ))
+    }
+}
+
+impl Collector {
+    fn visit(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Number(_) | Expr::StringLit(_) | Expr::Null => {}
+            Expr::Variable(name) => {
+                self.variables.insert(name.clone());
+            }
+            Expr::Unary(_, inner) | Expr::Spread(inner) => self.visit(inner),
+            Expr::TypeCast { expr, .. } => self.visit(expr),
+            Expr::Assignment { value, .. } | Expr::Destructure { value, .. } => self.visit(value),
+            Expr::Binary(l, _, r) => {
+                self.visit(l);
+                self.visit(r);
+            }
+            Expr::PropertyAccess { target, .. } | Expr::SafePropertyAccess { target, .. } => self.visit(target),
+            Expr::Index { target, index } => {
+                self.visit(target);
+                self.visit(index);
+            }
+            Expr::Slice { target, start, end } => {
+                self.visit(target);
+                for e in [start, end].into_iter().flatten() {
+                    self.visit(e);
+                }
+            }
+            Expr::Array(items) | Expr::Sequence(items) => items.iter().for_each(|e| self.visit(e)),
+            Expr::ObjectLiteral(fields) => fields.iter().for_each(|(_, e)| self.visit(e)),
+            Expr::FunctionCall { name, args } if name == "LET" && args.len() >= 3 && args.len() % 2 == 1 => {
+                // LET(:a, x, :b, y, body): each name is bound for the values after it and the body
+                self.functions.insert(name.clone());
+                let mut bound: Vec<&str> = Vec::new();
+                for (i, arg) in args.iter().enumerate() {
+                    match arg {
+                        Expr::Variable(binding) if i % 2 == 0 && i + 1 < args.len() => bound.push(binding),
+                        _ => self.visit_scoped(arg, &bound),
+                    }
+                }
+            }
+            Expr::FunctionCall { name, args } => {
+                if !name.starts_with("__") {
+                    let upper = name.to_uppercase();
+                    self.nondeterministic |= NON_DETERMINISTIC_FUNCTIONS.contains(&upper.as_str())
+                        || (args.is_empty() && matches!(upper.as_str(), "DATE" | "TIME"))
+                        || (upper == "CHOOSEWEIGHTED" && args.len() < 3);
+                    self.functions.insert(upper);
+                }
+                self.visit_args(args, implicit_params(&name.to_uppercase()));
+            }
+            Expr::MethodCall { target, name, args, .. } | Expr::SafeMethodCall { target, name, args } => {
+                let lower = name.to_lowercase();
+                self.visit(target);
+                self.visit_args(args, implicit_method_params(&lower));
+                self.methods.insert(lower);
+            }
+            Expr::Lambda { params, body } => {
+                // Parameters are bound by the function calling the lambda, not read from the caller
+                self.visit_scoped(body, &params.iter().map(String::as_str).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    /// Visit call arguments. Those in `implicit` are bare-expression lambdas: the names
+    /// listed for them, and any parameter names passed as string literals, are bound there.
+    fn visit_args(&mut self, args: &[Expr], implicit: &[(usize, &[&str])]) {
+        for (i, arg) in args.iter().enumerate() {
+            match implicit.iter().find(|(at, _)| *at == i) {
+                Some((_, names)) if !matches!(arg, Expr::Lambda { .. }) => {
+                    let renamed = args.iter().filter_map(|a| match a {
+                        Expr::StringLit(s) => Some(s.as_str()),
+                        _ => None,
+                    });
+                    self.visit_scoped(arg, &names.iter().copied().chain(renamed).collect::<Vec<_>>());
+                }
+                _ => self.visit(arg),
+            }
+        }
+    }
+
+    /// Visit `expr`, leaving out reads of the locally bound names in `bound`
+    fn visit_scoped(&mut self, expr: &Expr, bound: &[&str]) {
+        let mut inner = Collector::default();
+        inner.visit(expr);
+        self.variables.extend(inner.variables.into_iter().filter(|v| !bound.contains(&v.as_str())));
+        self.functions.extend(inner.functions);
+        self.methods.extend(inner.methods);
+        self.nondeterministic |= inner.nondeterministic;
+    }
+}
+
+/// Argument positions a higher-order builtin evaluates per item, with the names it
+/// binds there (see `bind_lambda`)
+fn implicit_params(function: &str) -> &'static [(usize, &'static [&'static str])] {
+    match function {
+        "FILTER" | "MAP" | "FIND" | "SUMIF" | "AVGIF" | "COUNTIF" | "DISTINCTBY" => &[(1, &["x"])],
+        "REDUCE" => &[(1, &["acc", "x"])],
+        "ITERATE" => &[(1, &["x", "i"]), (3, &["x", "prev"])],
+        "GOALSEEK" | "SOLVE" => &[(0, &["x"])],
+        "JOINON" => &[(2, &["x"]), (3, &["x"])],
+        _ => &[],
+    }
+}
+
+/// [`implicit_params`] for the lambda methods
+fn implicit_method_params(method: &str) -> &'static [(usize, &'static [&'static str])] {
+    match method {
+        "reduce" => &[(0, &["acc", "x"])],
+        m if LAMBDA_METHODS.contains(&m) => &[(0, &["x"])],
+        _ => &[],
+    }
+}
@@ -0,0 +1,185 @@
+//! Apache Arrow interop (feature `arrow`).
+//!
+//! Converts Arrow columns to skillet arrays and back, and evaluates an expression
+//! for every row of a `RecordBatch` through [`Dataset`], so columnar data reaches
+//! the evaluator without a JSON round trip.
+//!
+//! Integers, floats and decimals become `Number`; dates and timestamps become
+//! `DateTime` (epoch seconds); Arrow nulls become `Null`; lists become arrays.
+
+use std::sync::Arc;
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::*;
+use arrow_array::{
+    Array, ArrayRef, BooleanArray, Float64Array, NullArray, RecordBatch, StringArray, TimestampSecondArray,
+};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+
+use crate::dataset::{evaluate_per_row, Dataset};
+use crate::error::Error;
+use crate::runtime::method_calls::conversion_methods::value_to_json_value;
+use crate::types::Value;
+
+/// Convert an Arrow array to skillet values, one per slot
+pub fn array_to_values(array: &dyn Array) -> Result<Vec<Value>, Error> {
+    macro_rules! numbers {
+        ($t:ty) => {
+            array.as_primitive::<$t>().iter().map(|v| v.map_or(Value::Null, |n| Value::Number(n as f64))).collect()
+        };
+    }
+    macro_rules! seconds {
+        ($t:ty, $per_second:expr) => {
+            array
+                .as_primitive::<$t>()
+                .iter()
+                .map(|v| v.map_or(Value::Null, |t| Value::DateTime((t as i64).div_euclid($per_second))))
+                .collect()
+        };
+    }
+
+    let values = match array.data_type() {
+        DataType::Null => vec![Value::Null; array.len()],
+        DataType::Boolean => array.as_boolean().iter().map(|v| v.map_or(Value::Null, Value::Boolean)).collect(),
+        DataType::Int8 => numbers!(Int8Type),
+        DataType::Int16 => numbers!(Int16Type),
+        DataType::Int32 => numbers!(Int32Type),
+        DataType::Int64 => numbers!(Int64Type),
+        DataType::UInt8 => numbers!(UInt8Type),
+        DataType::UInt16 => numbers!(UInt16Type),
+        DataType::UInt32 => numbers!(UInt32Type),
+        DataType::UInt64 => numbers!(UInt64Type),
+        DataType::Float32 => numbers!(Float32Type),
+        DataType::Float64 => numbers!(Float64Type),
+        DataType::Decimal128(_, scale) => {
+            let divisor = 10f64.powi(*scale as i32);
+            array
+                .as_primitive::<Decimal128Type>()
+                .iter()
+                .map(|v| v.map_or(Value::Null, |d| Value::Number(d as f64 / divisor)))
+                .collect()
+        }
+        DataType::Utf8 => strings(array.as_string::<i32>().iter()),
+        DataType::LargeUtf8 => strings(array.as_string::<i64>().iter()),
+        DataType::Utf8View => strings(array.as_string_view().iter()),
+        DataType::Date32 => array
+            .as_primitive::<Date32Type>()
+            .iter()
+            .map(|v| v.map_or(Value::Null, |days| Value::DateTime(days as i64 * 86_400)))
+            .collect(),
+        DataType::Date64 => seconds!(Date64Type, 1000),
+        DataType::Timestamp(TimeUnit::Second, _) => seconds!(TimestampSecondType, 1),
+        DataType::Timestamp(TimeUnit::Millisecond, _) => seconds!(TimestampMillisecondType, 1000),
+        DataType::Timestamp(TimeUnit::Microsecond, _) => seconds!(TimestampMicrosecondType, 1_000_000),
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => seconds!(TimestampNanosecondType, 1_000_000_000),
+        DataType::List(_) => lists(array.as_list::<i32>().iter())?,
+        DataType::LargeList(_) => lists(array.as_list::<i64>().iter())?,
+        other => return Err(Error::new(format!("Unsupported Arrow type: {}", other), None)),
+    };
+    Ok(values)
+}
+
+fn strings<'a>(iter: impl Iterator<Item = Option<&'a str>>) -> Vec<Value> {
+    iter.map(|v| v.map_or(Value::Null, |s| Value::String(s.to_string()))).collect()
+}
+
+fn lists(iter: impl Iterator<Item = Option<ArrayRef>>) -> Result<Vec<Value>, Error> {
+    iter.map(|v| match v {
+        Some(items) => array_to_values(items.as_ref()).map(Value::Array),
+        None => Ok(Value::Null),
+    })
+    .collect()
+}
+
+/// Build an Arrow array from skillet values. The type follows the non-null values:
+/// numbers and currency give `Float64`, booleans `Boolean`, strings `Utf8` (objects as
+/// JSON text), dates `Timestamp(Second)`; all nulls give a `Null` array. Mixed or nested
+/// values are rejected.
+pub fn values_to_array(values: &[Value]) -> Result<ArrayRef, Error> {
+    let first = values.iter().find(|v| !matches!(v, Value::Null)).unwrap_or(&Value::Null);
+    let mismatch = |v: &Value| Error::new(format!("Cannot mix {:?} with {:?} in an Arrow column", first, v), None);
+
+    let array: ArrayRef = match first {
+        Value::Null => Arc::new(NullArray::new(values.len())),
+        Value::Number(_) | Value::Currency(_) => Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    Value::Number(n) | Value::Currency(n) => Ok(Some(*n)),
+                    Value::Null => Ok(None),
+                    other => Err(mismatch(other)),
+                })
+                .collect::<Result<Float64Array, Error>>()?,
+        ),
+        Value::Boolean(_) => Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    Value::Boolean(b) => Ok(Some(*b)),
+                    Value::Null => Ok(None),
+                    other => Err(mismatch(other)),
+                })
+                .collect::<Result<BooleanArray, Error>>()?,
+        ),
+        Value::String(_) | Value::Json(_) | Value::Map(_) => Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    Value::String(s) | Value::Json(s) => Ok(Some(s.clone())),
+                    Value::Map(_) => Ok(Some(value_to_json_value(v)?.to_string())),
+                    Value::Null => Ok(None),
+                    other => Err(mismatch(other)),
+                })
+                .collect::<Result<StringArray, Error>>()?,
+        ),
+        Value::DateTime(_) => Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    Value::DateTime(ts) => Ok(Some(*ts)),
+                    Value::Null => Ok(None),
+                    other => Err(mismatch(other)),
+                })
+                .collect::<Result<TimestampSecondArray, Error>>()?,
+        ),
+        Value::Array(_) => {
+            return Err(Error::new("Nested arrays cannot be converted to an Arrow column", None))
+        }
+    };
+    Ok(array)
+}
+
+impl Dataset {
+    /// One column per field of `batch`
+    pub fn from_record_batch(batch: &RecordBatch) -> Result<Self, Error> {
+        let schema = batch.schema();
+        let mut dataset = Dataset::new();
+        for (field, column) in schema.fields().iter().zip(batch.columns()) {
+            let values = array_to_values(column.as_ref())
+                .map_err(|e| Error::new(format!("Column '{}': {}", field.name(), e.message), None))?;
+            dataset.add_column(field.name().clone(), values)?;
+        }
+        Ok(dataset)
+    }
+
+    /// Convert back to a `RecordBatch`; every column is nullable
+    pub fn to_record_batch(&self) -> Result<RecordBatch, Error> {
+        let mut fields = Vec::with_capacity(self.column_names().len());
+        let mut columns = Vec::with_capacity(self.column_names().len());
+        for name in self.column_names() {
+            let array = values_to_array(self.column(name).unwrap_or(&[]))
+                .map_err(|e| Error::new(format!("Column '{}': {}", name, e.message), None))?;
+            fields.push(Field::new(name.clone(), array.data_type().clone(), true));
+            columns.push(array);
+        }
+        RecordBatch::try_new(Arc::new(Schema::new(fields)), columns)
+            .map_err(|e| Error::new(format!("Arrow error: {}", e), None))
+    }
+}
+
+/// Evaluate `expr` for every row of `batch` (see [`evaluate_per_row`]) and return
+/// the results as an Arrow array
+pub fn evaluate_record_batch(expr: &str, batch: &RecordBatch) -> Result<ArrayRef, Error> {
+    let dataset = Dataset::from_record_batch(batch)?;
+    values_to_array(&evaluate_per_row(expr, &dataset)?)
+}
@@ -1,26 +1,51 @@
-use std::rc::Rc;
+use std::fmt;
+use std::sync::Arc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     Number(f64),
     StringLit(String),
     Null,
-    Unary(UnaryOp, Rc<Expr>),
-    Binary(Rc<Expr>, BinaryOp, Rc<Expr>),
+    Unary(UnaryOp, Arc<Expr>),
+    Binary(Arc<Expr>, BinaryOp, Arc<Expr>),
     Variable(String),
-    PropertyAccess { target: Rc<Expr>, property: String },
-    SafePropertyAccess { target: Rc<Expr>, property: String },
-    SafeMethodCall { target: Rc<Expr>, name: String, args: Vec<Expr> },
+    PropertyAccess { target: Arc<Expr>, property: String },
+    SafePropertyAccess { target: Arc<Expr>, property: String },
+    SafeMethodCall { target: Arc<Expr>, name: String, args: Vec<Expr> },
     FunctionCall { name: String, args: Vec<Expr> },
-    Spread(Rc<Expr>),
+    Spread(Arc<Expr>),
     Array(Vec<Expr>),
     ObjectLiteral(Vec<(String, Expr)>),
-    MethodCall { target: Rc<Expr>, name: String, args: Vec<Expr>, predicate: bool },
-    Index { target: Rc<Expr>, index: Rc<Expr> },
-    Slice { target: Rc<Expr>, start: Option<Rc<Expr>>, end: Option<Rc<Expr>> },
-    TypeCast { expr: Rc<Expr>, ty: TypeName },
-    Assignment { variable: String, value: Rc<Expr> },
+    MethodCall { target: Arc<Expr>, name: String, args: Vec<Expr>, predicate: bool },
+    Index { target: Arc<Expr>, index: Arc<Expr> },
+    Slice { target: Arc<Expr>, start: Option<Arc<Expr>>, end: Option<Arc<Expr>> },
+    TypeCast { expr: Arc<Expr>, ty: TypeName },
+    Assignment { variable: String, value: Arc<Expr> },
+    /// `[:a, :b] := value` or `{:lat, :lon} := value`: assigns the elements or
+    /// fields of `value` to several variables at once
+    Destructure { pattern: Pattern, value: Arc<Expr> },
     Sequence(Vec<Expr>),
+    /// `x -> body` or `(a, b) -> body`; only meaningful as an argument of a function
+    /// or method that calls it, which binds the parameters as variables
+    Lambda { params: Vec<String>, body: Arc<Expr> },
+}
+
+/// The left side of a destructuring assignment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pattern {
+    /// `[:a, :b]` takes array elements by position
+    Array(Vec<String>),
+    /// `{:lat, :lon}` takes object fields by name
+    Object(Vec<String>),
+}
+
+impl Pattern {
+    /// The variables the pattern assigns, in order
+    pub fn names(&self) -> &[String] {
+        match self {
+            Pattern::Array(names) | Pattern::Object(names) => names,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -35,6 +60,23 @@ pub enum TypeName {
     Json,
 }
 
+impl TypeName {
+    /// The type a `::Name` cast or `TRYCAST(v, "Name")` refers to, ignoring case
+    pub fn from_name(name: &str) -> Option<TypeName> {
+        Some(match name.to_lowercase().as_str() {
+            "integer" | "int" => TypeName::Integer,
+            "float" | "number" => TypeName::Float,
+            "string" => TypeName::String,
+            "boolean" | "bool" => TypeName::Boolean,
+            "array" => TypeName::Array,
+            "currency" => TypeName::Currency,
+            "datetime" | "date" => TypeName::DateTime,
+            "json" => TypeName::Json,
+            _ => return None,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum UnaryOp {
     Plus,
@@ -59,3 +101,234 @@ pub enum BinaryOp {
     And,
     Or,
 }
+
+// Source rendering. The output parses back to the same tree; parentheses are
+// added only where precedence requires them.
+
+impl BinaryOp {
+    pub(crate) fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "%",
+            BinaryOp::Pow => "^",
+            BinaryOp::Gt => ">",
+            BinaryOp::Lt => "<",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Le => "<=",
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "!=",
+            BinaryOp::And => "AND",
+            BinaryOp::Or => "OR",
+        }
+    }
+
+    /// Parser precedence level, higher binds tighter
+    fn precedence(self) -> u8 {
+        match self {
+            BinaryOp::Or => 1,
+            BinaryOp::And => 2,
+            BinaryOp::Eq | BinaryOp::Ne => 3,
+            BinaryOp::Gt | BinaryOp::Lt | BinaryOp::Ge | BinaryOp::Le => 4,
+            BinaryOp::Add | BinaryOp::Sub => 6,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => 7,
+            BinaryOp::Pow => 9,
+        }
+    }
+}
+
+const PREC_RANGE: u8 = 5;
+const PREC_UNARY: u8 = 8;
+const PREC_CAST: u8 = 10;
+const PREC_POSTFIX: u8 = 11;
+
+impl Expr {
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Binary(_, op, _) => op.precedence(),
+            Expr::Unary(..) => PREC_UNARY,
+            Expr::TypeCast { expr, ty: TypeName::DateTime } if matches!(**expr, Expr::Number(_)) => PREC_POSTFIX,
+            Expr::TypeCast { .. } => PREC_CAST,
+            Expr::FunctionCall { name, .. } => match name.as_str() {
+                "__TERNARY__" => 0,
+                "__RANGE__" | "__RANGE_INCLUSIVE__" => PREC_RANGE,
+                _ => PREC_POSTFIX,
+            },
+            Expr::Assignment { .. } | Expr::Destructure { .. } | Expr::Sequence(_) | Expr::Spread(_) | Expr::Lambda { .. } => 0,
+            // A negative literal prints with its sign
+            Expr::Number(n) if *n < 0.0 => PREC_UNARY,
+            _ => PREC_POSTFIX,
+        }
+    }
+
+    fn fmt_at(&self, f: &mut fmt::Formatter<'_>, min: u8) -> fmt::Result {
+        if self.precedence() < min {
+            write!(f, "(")?;
+            fmt::Display::fmt(self, f)?;
+            write!(f, ")")
+        } else {
+            fmt::Display::fmt(self, f)
+        }
+    }
+}
+
+fn fmt_list(f: &mut fmt::Formatter<'_>, items: &[Expr]) -> fmt::Result {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+fn fmt_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\t' => write!(f, "\\t")?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "{}", n),
+            Expr::StringLit(s) => fmt_string(f, s),
+            Expr::Null => write!(f, "NULL"),
+            Expr::Variable(name) => write!(f, ":{}", name),
+            Expr::Unary(op, inner) => {
+                write!(f, "{}", match op {
+                    UnaryOp::Plus => "+",
+                    UnaryOp::Minus => "-",
+                    UnaryOp::Not => "!",
+                })?;
+                inner.fmt_at(f, PREC_UNARY)
+            }
+            Expr::Binary(l, op, r) => {
+                let p = op.precedence();
+                let (left_min, right_min) = if *op == BinaryOp::Pow { (PREC_CAST, PREC_UNARY) } else { (p, p + 1) };
+                l.fmt_at(f, left_min)?;
+                write!(f, " {} ", op.symbol())?;
+                r.fmt_at(f, right_min)
+            }
+            Expr::PropertyAccess { target, property } => {
+                target.fmt_at(f, PREC_POSTFIX)?;
+                write!(f, ".{}", property)
+            }
+            Expr::SafePropertyAccess { target, property } => {
+                target.fmt_at(f, PREC_POSTFIX)?;
+                write!(f, "&.{}", property)
+            }
+            Expr::MethodCall { target, name, args, predicate } => {
+                target.fmt_at(f, PREC_POSTFIX)?;
+                if *predicate {
+                    return write!(f, ".{}?", name);
+                }
+                write!(f, ".{}(", name)?;
+                fmt_list(f, args)?;
+                write!(f, ")")
+            }
+            Expr::SafeMethodCall { target, name, args } => {
+                target.fmt_at(f, PREC_POSTFIX)?;
+                write!(f, "&.{}(", name)?;
+                fmt_list(f, args)?;
+                write!(f, ")")
+            }
+            Expr::FunctionCall { name, args } => match (name.as_str(), args.as_slice()) {
+                ("__TERNARY__", [cond, then_e, else_e]) => {
+                    cond.fmt_at(f, 1)?;
+                    write!(f, " ? {} : {}", then_e, else_e)
+                }
+                ("__CONST_TRUE__", []) => write!(f, "TRUE"),
+                ("__CONST_FALSE__", []) => write!(f, "FALSE"),
+                ("__RANGE__" | "__RANGE_INCLUSIVE__", [start, end]) => {
+                    start.fmt_at(f, PREC_RANGE + 1)?;
+                    write!(f, "{}", if name == "__RANGE__" { ".." } else { "..=" })?;
+                    end.fmt_at(f, PREC_RANGE + 1)
+                }
+                _ => {
+                    write!(f, "{}(", name)?;
+                    fmt_list(f, args)?;
+                    write!(f, ")")
+                }
+            },
+            Expr::Spread(inner) => write!(f, "...{}", inner),
+            Expr::Array(items) => {
+                write!(f, "[")?;
+                fmt_list(f, items)?;
+                write!(f, "]")
+            }
+            Expr::ObjectLiteral(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    if let Expr::Spread(_) = value {
+                        write!(f, "{}", value)?;
+                        continue;
+                    }
+                    fmt_string(f, key)?;
+                    write!(f, ": {}", value)?;
+                }
+                write!(f, "}}")
+            }
+            Expr::Index { target, index } => {
+                target.fmt_at(f, PREC_POSTFIX)?;
+                write!(f, "[{}]", index)
+            }
+            Expr::Slice { target, start, end } => {
+                target.fmt_at(f, PREC_POSTFIX)?;
+                write!(f, "[")?;
+                if let Some(start) = start {
+                    write!(f, "{}", start)?;
+                }
+                write!(f, ":")?;
+                if let Some(end) = end {
+                    write!(f, "{}", end)?;
+                }
+                write!(f, "]")
+            }
+            Expr::TypeCast { expr, ty } => {
+                // Datetime literals are parsed into a cast of epoch seconds
+                if let (Expr::Number(ts), TypeName::DateTime) = (expr.as_ref(), ty) {
+                    if let Some(dt) = chrono::DateTime::from_timestamp(*ts as i64, 0) {
+                        return write!(f, "d\"{}\"", dt.format("%Y-%m-%dT%H:%M:%SZ"));
+                    }
+                }
+                expr.fmt_at(f, PREC_POSTFIX)?;
+                write!(f, "::{:?}", ty)
+            }
+            Expr::Assignment { variable, value } => write!(f, ":{} := {}", variable, value),
+            Expr::Destructure { pattern, value } => {
+                let names: Vec<String> = pattern.names().iter().map(|n| format!(":{}", n)).collect();
+                match pattern {
+                    Pattern::Array(_) => write!(f, "[{}] := {}", names.join(", "), value),
+                    Pattern::Object(_) => write!(f, "{{{}}} := {}", names.join(", "), value),
+                }
+            }
+            Expr::Lambda { params, body } => match params.as_slice() {
+                [param] => write!(f, "{} -> {}", param, body),
+                _ => write!(f, "({}) -> {}", params.join(", "), body),
+            },
+            Expr::Sequence(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
@@ -1,5 +1,8 @@
 use serde_json;
 
+use super::types::EvalRequestOptions;
+use super::usage::USAGE;
+
 pub fn extract_auth_header(request: &str) -> Option<String> {
     // Look for Authorization header in request
     for line in request.lines() {
@@ -31,6 +34,18 @@ pub fn check_authentication(request: &str, server_token: &Option<String>) -> Opt
     None
 }
 
+/// Eval-endpoint check that also accepts tokens registered with `--tenant`. Other
+/// requests are checked like [`check_authentication`], so without `--token` the eval
+/// endpoints stay open and untokened requests count as the anonymous tenant.
+pub fn check_eval_authentication(request: &str, server_token: &Option<String>) -> Option<String> {
+    let tenant_token = extract_auth_header(request).is_some_and(|token| USAGE.tenant_for_token(&token).is_some());
+    if tenant_token {
+        None
+    } else {
+        check_authentication(request, server_token)
+    }
+}
+
 pub fn check_admin_authentication(request: &str, server_admin_token: &Option<String>) -> Option<String> {
     if let Some(cfg_admin_token) = server_admin_token {
         let auth_token = extract_auth_header(request);
@@ -46,6 +61,30 @@ pub fn check_admin_authentication(request: &str, server_admin_token: &Option<Str
     None
 }
 
+/// Eval-endpoint check that also lets the admin token through, for requests using
+/// [`check_privileged_options`]
+pub fn check_eval_or_admin_authentication(request: &str, server_token: &Option<String>, server_admin_token: &Option<String>) -> Option<String> {
+    let error_response = check_eval_authentication(request, server_token)?;
+    match server_admin_token {
+        Some(_) if check_admin_authentication(request, server_admin_token).is_none() => None,
+        _ => Some(error_response),
+    }
+}
+
+/// Evaluation options that only the admin token may set: a frozen clock would let
+/// any caller backdate time-dependent rules
+pub fn check_privileged_options(request: &str, options: Option<&EvalRequestOptions>, server_admin_token: &Option<String>) -> Option<String> {
+    let frozen_clock = options.is_some_and(|o| o.now_override.is_some());
+    if frozen_clock && check_admin_authentication(request, server_admin_token).is_some() {
+        let error_response = serde_json::json!({
+            "success": false,
+            "error": "Unauthorized: the now_override option requires the admin token"
+        });
+        return Some(error_response.to_string());
+    }
+    None
+}
+
 pub struct TokenConfig {
     pub auth_token: Option<String>,
     pub admin_token: Option<String>,
@@ -113,4 +152,29 @@ impl TokenConfig {
             eprintln!("⚠️  The same token has all privileges (eval and admin).");
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenant_tokens_keep_anonymous_fallback() {
+        USAGE.add_tenant("auth-test", "tok-auth-test");
+        let anonymous = "POST /eval HTTP/1.1\r\n\r\n{}";
+        let tenant = "POST /eval HTTP/1.1\r\nAuthorization: Bearer tok-auth-test\r\n\r\n{}";
+        let other = "POST /eval HTTP/1.1\r\nAuthorization: Bearer nope\r\n\r\n{}";
+
+        // No server token: open, as without tenants
+        assert!(check_eval_authentication(anonymous, &None).is_none());
+        assert!(check_eval_authentication(tenant, &None).is_none());
+        assert!(check_eval_authentication(other, &None).is_none());
+
+        // With a server token, only it and the tenant tokens are accepted
+        let server_token = Some("main".to_string());
+        assert!(check_eval_authentication(anonymous, &server_token).is_some());
+        assert!(check_eval_authentication(other, &server_token).is_some());
+        assert!(check_eval_authentication(tenant, &server_token).is_none());
+        assert!(check_eval_authentication("POST /eval HTTP/1.1\r\nAuthorization: Bearer main\r\n\r\n{}", &server_token).is_none());
+    }
+}
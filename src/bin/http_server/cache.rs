@@ -4,7 +4,7 @@ use lru::LruCache;
 use once_cell::sync::Lazy;
 use std::num::NonZeroUsize;
 
-use skillet::{Value, evaluate_with_assignments, evaluate_with_assignments_and_context};
+use skillet::{EvalOptions, Value, evaluate_with_assignments, evaluate_with_assignments_and_context};
 
 /// Cached expression result with optional variable context
 #[derive(Clone, Debug)]
@@ -94,8 +94,11 @@ impl ExpressionCache {
     }
 }
 
-/// Generate cache key from expression and variables
-fn generate_cache_key(expression: &str, variables: &HashMap<String, Value>) -> String {
+/// Generate cache key from expression, variables and any non-default options
+fn generate_cache_key(expression: &str, variables: &HashMap<String, Value>, options: &EvalOptions) -> String {
+    if *options != EvalOptions::default() {
+        return format!("{:?}#{}", options, generate_cache_key(expression, variables, &EvalOptions::default()));
+    }
     if variables.is_empty() {
         expression.to_string()
     } else {
@@ -114,18 +117,26 @@ pub fn evaluate_cached(
     expression: &str, 
     variables: &HashMap<String, Value>,
     include_variables: bool,
+    options: &EvalOptions,
 ) -> CachedResult {
-    let cache_key = generate_cache_key(expression, variables);
-    
+    let cache_key = generate_cache_key(expression, variables, options);
+    // Clock reads and unseeded random draws must run on every request, and so must custom
+    // and JavaScript functions, which can read the request id and the rest of the context
+    let cacheable = skillet::parse(expression)
+        .map(|expr| skillet::is_deterministic(&expr) && skillet::analyze(&expr).non_builtin_functions().is_empty())
+        .unwrap_or(true);
+
     // Try to get from cache first
-    if let Ok(mut cache) = EXPRESSION_CACHE.lock() {
-        if let Some(entry) = cache.get(&cache_key) {
-            return CachedResult {
-                result: Ok(entry.result.clone()),
-                variable_context: entry.variable_context.clone(),
-                execution_time_ms: entry.execution_time_ms,
-                cache_hit: true,
-            };
+    if cacheable {
+        if let Ok(mut cache) = EXPRESSION_CACHE.lock() {
+            if let Some(entry) = cache.get(&cache_key) {
+                return CachedResult {
+                    result: Ok(entry.result.clone()),
+                    variable_context: entry.variable_context.clone(),
+                    execution_time_ms: entry.execution_time_ms,
+                    cache_hit: true,
+                };
+            }
         }
     }
 
@@ -148,7 +159,7 @@ pub fn evaluate_cached(
     let execution_time_ms = start_time.elapsed().as_secs_f64() * 1000.0;
 
     // Store successful results in cache
-    if let Ok(ref value) = result {
+    if let (true, Ok(value)) = (cacheable, &result) {
         let cache_entry = CacheEntry {
             result: value.clone(),
             variable_context: variable_context.clone(),
@@ -287,12 +298,12 @@ mod tests {
         let vars = HashMap::new();
         
         // First evaluation should be a cache miss
-        let result1 = evaluate_cached("2+2", &vars, false);
+        let result1 = evaluate_cached("2+2", &vars, false, &EvalOptions::default());
         assert!(!result1.cache_hit);
         assert!(result1.result.is_ok());
         
         // Second evaluation should be a cache hit
-        let result2 = evaluate_cached("2+2", &vars, false);
+        let result2 = evaluate_cached("2+2", &vars, false, &EvalOptions::default());
         assert!(result2.cache_hit);
         assert!(result2.result.is_ok());
         
@@ -312,11 +323,52 @@ mod tests {
         vars2.insert("b".to_string(), Value::Number(2.0));
         vars2.insert("a".to_string(), Value::Number(1.0));
         
-        let key1 = generate_cache_key("test", &vars1);
-        let key2 = generate_cache_key("test", &vars2);
+        let key1 = generate_cache_key("test", &vars1, &EvalOptions::default());
+        let key2 = generate_cache_key("test", &vars2, &EvalOptions::default());
         
         // Should generate same key regardless of variable order
         assert_eq!(key1, key2);
+
+        // Different options must not share cached results
+        let strict = EvalOptions::new().with_strictness(skillet::Strictness::Strict);
+        assert_ne!(key1, generate_cache_key("test", &vars1, &strict));
+    }
+
+    #[test]
+    fn test_nondeterministic_results_are_not_cached() {
+        let vars = HashMap::new();
+        let options = EvalOptions::default();
+        let draw = "CHOOSEWEIGHTED(1..1000, MAP(1..1000, 1))";
+        let results: Vec<CachedResult> = (0..8).map(|_| evaluate_cached(draw, &vars, false, &options)).collect();
+        assert!(results.iter().all(|r| !r.cache_hit));
+        let first = results[0].result.clone().unwrap();
+        assert!(results.iter().any(|r| r.result.as_ref().unwrap() != &first));
+
+        for expr in ["NOW()", "DATE()", "TIME()"] {
+            evaluate_cached(expr, &vars, false, &options);
+            assert!(!evaluate_cached(expr, &vars, false, &options).cache_hit, "{}", expr);
+        }
+    }
+
+    struct Counter(std::sync::atomic::AtomicU64);
+
+    impl skillet::CustomFunction for Counter {
+        fn name(&self) -> &str { "CACHE_TEST_COUNTER" }
+        fn min_args(&self) -> usize { 0 }
+        fn max_args(&self) -> Option<usize> { Some(0) }
+        fn execute(&self, _args: Vec<Value>) -> Result<Value, skillet::Error> {
+            Ok(Value::Number(self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst) as f64))
+        }
+    }
+
+    #[test]
+    fn test_custom_function_results_are_not_cached() {
+        skillet::register_function(Box::new(Counter(std::sync::atomic::AtomicU64::new(0)))).unwrap();
+        let vars = HashMap::new();
+        let first = evaluate_cached("CACHE_TEST_COUNTER() + 1", &vars, false, &EvalOptions::default());
+        let second = evaluate_cached("CACHE_TEST_COUNTER() + 1", &vars, false, &EvalOptions::default());
+        assert!(!second.cache_hit);
+        assert_ne!(first.result, second.result);
     }
 
     #[test]
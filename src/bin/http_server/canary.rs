@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+use std::net::TcpStream;
+use std::sync::Arc;
+
+use skillet::corpus::Expected;
+use skillet::Value;
+
+use super::auth::check_admin_authentication;
+use super::eval::request_options;
+use super::types::{CanaryCaseRequest, CanaryDifferenceInfo, CanaryRequest, CanaryResponse};
+use super::utils::{parse_json_body, sanitize_json_key, send_http_error, send_http_response};
+
+/// `POST /canary`: evaluate stored formulas under the `baseline` and `candidate`
+/// options and report those whose outcome changes. Both sides get the server
+/// builtin policy, as `/eval` requests do.
+pub fn handle_canary(stream: &mut TcpStream, request: &str, server_admin_token: Arc<Option<String>>) {
+    if let Some(error_response) = check_admin_authentication(request, &server_admin_token) {
+        send_http_response(stream, 401, "application/json", &error_response);
+        return;
+    }
+
+    let canary: CanaryRequest = match parse_json_body(request) {
+        Ok(req) => req,
+        Err(e) => {
+            send_http_error(stream, 400, &e);
+            return;
+        }
+    };
+
+    match run_canary(canary) {
+        Ok(response) => {
+            let json = serde_json::to_string(&response).unwrap_or_default();
+            send_http_response(stream, 200, "application/json", &json);
+        }
+        Err(e) => {
+            let response = CanaryResponse { success: false, total_count: 0, agreed_count: 0, differences: Vec::new(), error: Some(e) };
+            let json = serde_json::to_string(&response).unwrap_or_default();
+            send_http_response(stream, 400, "application/json", &json);
+        }
+    }
+}
+
+fn run_canary(canary: CanaryRequest) -> Result<CanaryResponse, String> {
+    let baseline = request_options(canary.baseline.as_ref()).map_err(|e| format!("Invalid baseline options: {}", e))?;
+    let candidate = request_options(canary.candidate.as_ref()).map_err(|e| format!("Invalid candidate options: {}", e))?;
+    let mut cases = Vec::with_capacity(canary.cases.len());
+    for (i, case) in canary.cases.iter().enumerate() {
+        let vars = case_variables(case).map_err(|e| format!("Case {}: {}", i, e))?;
+        cases.push((case.expression.as_str(), vars));
+    }
+
+    let report = skillet::evaluate_canary(&cases, &baseline, &candidate).map_err(|e| e.to_string())?;
+    Ok(CanaryResponse {
+        success: report.is_success(),
+        total_count: cases.len(),
+        agreed_count: report.agreed,
+        differences: report
+            .differences
+            .into_iter()
+            .map(|d| CanaryDifferenceInfo {
+                index: d.index,
+                expression: d.expression,
+                baseline: outcome_json(&d.baseline),
+                candidate: outcome_json(&d.candidate),
+            })
+            .collect(),
+        error: None,
+    })
+}
+
+/// The variables of a case, converted like `/eval` arguments
+fn case_variables(case: &CanaryCaseRequest) -> Result<HashMap<String, Value>, String> {
+    let Some(arguments) = &case.arguments else {
+        return Ok(HashMap::new());
+    };
+    let mut vars = HashMap::with_capacity(arguments.len() + 1);
+    vars.insert("arguments".to_string(), Value::Json(serde_json::to_string(arguments).unwrap_or_default()));
+    for (key, value) in arguments {
+        let value = skillet::json_to_value(value.clone()).map_err(|e| format!("Error converting variable '{}': {}", key, e))?;
+        vars.insert(sanitize_json_key(key), value);
+    }
+    Ok(vars)
+}
+
+fn outcome_json(outcome: &Expected) -> serde_json::Value {
+    match outcome {
+        Expected::Value { value, type_name: Some(t) } => serde_json::json!({ "result": value, "type": t }),
+        Expected::Value { value, type_name: None } => serde_json::json!({ "result": value }),
+        Expected::Error(message) => serde_json::json!({ "error": message }),
+    }
+}
@@ -1,14 +1,68 @@
 use std::collections::HashMap;
 use std::net::TcpStream;
-use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
+use std::sync::{Arc, RwLock, atomic::{AtomicBool, AtomicU64, Ordering}};
 use std::time::Instant;
 use skillet::Value;
+use skillet::runtime::utils::format_number;
 
-use super::auth::check_authentication;
-use super::cache::{evaluate_cached, get_cache_stats, clear_cache};
-use super::types::{EvalRequest, EvalResponse, HealthResponse, IncludeVariables, CacheStatsResponse};
-use super::utils::{send_http_response, send_http_error, parse_json_body, sanitize_json_key};
+use super::auth::{check_authentication, check_eval_authentication, check_eval_or_admin_authentication, check_privileged_options};
+use super::cache::{evaluate_cached, get_cache_stats, clear_cache, CachedResult};
+use super::types::{EvalRequest, EvalRequestOptions, EvalResponse, HealthResponse, IncludeVariables, CacheStatsResponse};
+use super::logging;
+use super::utils::{send_http_response, send_http_response_with_headers, send_http_error, parse_json_body, sanitize_json_key};
 use super::stats::ServerStats;
+use super::usage::USAGE;
+
+/// Highest `estimate_cost` score accepted by /eval; 0 disables the check
+static MAX_COST: AtomicU64 = AtomicU64::new(0);
+
+pub fn set_max_cost(max_cost: u64) {
+    MAX_COST.store(max_cost, Ordering::Relaxed);
+}
+
+/// Force `builtins_only` on every /eval request
+static BUILTINS_ONLY: AtomicBool = AtomicBool::new(false);
+
+pub fn set_builtins_only(enabled: bool) {
+    BUILTINS_ONLY.store(enabled, Ordering::Relaxed);
+}
+
+/// Server-wide builtin allow/deny lists, combined with each request's own
+static ALLOWED_BUILTINS: RwLock<Option<Vec<String>>> = RwLock::new(None);
+static DENIED_BUILTINS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+pub fn set_allowed_builtins(names: Vec<String>) {
+    *ALLOWED_BUILTINS.write().unwrap() = Some(names.into_iter().map(|n| n.to_uppercase()).collect());
+}
+
+pub fn set_denied_builtins(names: Vec<String>) {
+    *DENIED_BUILTINS.write().unwrap() = names.into_iter().map(|n| n.to_uppercase()).collect();
+}
+
+/// A request may narrow the server policy but never widen it: denials are
+/// merged and, when both sides set an allow list, only names in both remain.
+fn apply_server_builtin_policy(options: &mut skillet::EvalOptions) {
+    for name in DENIED_BUILTINS.read().unwrap().iter() {
+        if !options.denied_builtins.contains(name) {
+            options.denied_builtins.push(name.clone());
+        }
+    }
+    if let Some(server_allowed) = ALLOWED_BUILTINS.read().unwrap().as_ref() {
+        options.allowed_builtins = Some(match options.allowed_builtins.take() {
+            Some(requested) => requested.into_iter().filter(|n| server_allowed.contains(n)).collect(),
+            None => server_allowed.clone(),
+        });
+    }
+}
+
+/// The options of a request, with the server policy applied on top
+pub fn request_options(requested: Option<&EvalRequestOptions>) -> Result<skillet::EvalOptions, skillet::Error> {
+    let mut options = requested.map(|o| o.to_eval_options()).transpose()?.unwrap_or_default();
+    // Server-wide setting; a request cannot opt out
+    options.builtins_only |= BUILTINS_ONLY.load(Ordering::Relaxed);
+    apply_server_builtin_policy(&mut options);
+    Ok(options)
+}
 
 pub fn handle_eval_post(
     stream: &mut TcpStream,
@@ -16,9 +70,11 @@ pub fn handle_eval_post(
     stats: Arc<ServerStats>,
     request_counter: Arc<AtomicU64>,
     server_token: Arc<Option<String>>,
+    server_admin_token: Arc<Option<String>>,
+    correlation_id: &str,
 ) {
     // Check authentication first
-    if let Some(error_response) = check_authentication(request, &server_token) {
+    if let Some(error_response) = check_eval_or_admin_authentication(request, &server_token, &server_admin_token) {
         send_http_response(stream, 401, "application/json", &error_response);
         return;
     }
@@ -30,10 +86,14 @@ pub fn handle_eval_post(
             return;
         }
     };
+    if let Some(error_response) = check_privileged_options(request, eval_request.options.as_ref(), &server_admin_token) {
+        send_http_response(stream, 401, "application/json", &error_response);
+        return;
+    }
 
-    let response = process_eval_request(eval_request, stats, request_counter);
-    let json = serde_json::to_string(&response).unwrap_or_default();
-    send_http_response(stream, if response.success { 200 } else { 400 }, "application/json", &json);
+    let response = process_eval_request(eval_request, stats, request_counter, correlation_id);
+    let bytes_out = send_eval_response(stream, &response);
+    record_usage(request, &server_token, &response, bytes_out);
 }
 
 pub fn handle_eval_get(
@@ -42,9 +102,10 @@ pub fn handle_eval_get(
     stats: Arc<ServerStats>,
     request_counter: Arc<AtomicU64>,
     server_token: Arc<Option<String>>,
+    correlation_id: &str,
 ) {
     // Check authentication first
-    if let Some(error_response) = check_authentication(request, &server_token) {
+    if let Some(error_response) = check_eval_authentication(request, &server_token) {
         send_http_response(stream, 401, "application/json", &error_response);
         return;
     }
@@ -98,15 +159,10 @@ pub fn handle_eval_get(
                     }
                 }
                 _ => {
-                    // Treat as variable
-                    if let Ok(num) = decoded_value.parse::<f64>() {
-                        variables.insert(key.to_string(), serde_json::json!(num));
-                    } else if decoded_value == "true" {
-                        variables.insert(key.to_string(), serde_json::json!(true));
-                    } else if decoded_value == "false" {
-                        variables.insert(key.to_string(), serde_json::json!(false));
-                    } else {
-                        variables.insert(key.to_string(), serde_json::json!(decoded_value.to_string()));
+                    let decoded_key = urlencoding::decode(key).unwrap_or_default();
+                    if let Err(e) = add_query_variable(&mut variables, &decoded_key, &decoded_value) {
+                        send_http_error(stream, 400, &e);
+                        return;
                     }
                 }
             }
@@ -123,11 +179,61 @@ pub fn handle_eval_get(
         arguments: if variables.is_empty() { None } else { Some(variables) },
         output_json: Some(output_json),
         include_variables: Some(include_variables),
+        options: None,
+        explain: None,
+        profile: None,
     };
 
-    let response = process_eval_request(eval_request, stats, request_counter);
-    let json = serde_json::to_string(&response).unwrap_or_default();
-    send_http_response(stream, if response.success { 200 } else { 400 }, "application/json", &json);
+    let response = process_eval_request(eval_request, stats, request_counter, correlation_id);
+    let bytes_out = send_eval_response(stream, &response);
+    record_usage(request, &server_token, &response, bytes_out);
+}
+
+/// Fold one GET query parameter into the variables: `vars=<json object>` merges
+/// its fields, `name[]=v` appends to an array, anything else is a scalar.
+fn add_query_variable(
+    variables: &mut HashMap<String, serde_json::Value>,
+    key: &str,
+    value: &str,
+) -> Result<(), String> {
+    if key == "vars" || key == "variables" {
+        let parsed: serde_json::Value =
+            serde_json::from_str(value).map_err(|e| format!("Invalid JSON in {} parameter: {}", key, e))?;
+        match parsed {
+            serde_json::Value::Object(map) => {
+                // Separate query parameters win over fields of the JSON object
+                for (k, v) in map {
+                    variables.entry(k).or_insert(v);
+                }
+                Ok(())
+            }
+            _ => Err(format!("{} parameter must be a JSON object", key)),
+        }
+    } else if let Some(name) = key.strip_suffix("[]") {
+        let entry = variables.entry(name.to_string()).or_insert_with(|| serde_json::json!([]));
+        match entry {
+            serde_json::Value::Array(items) => {
+                items.push(query_scalar(value));
+                Ok(())
+            }
+            _ => Err(format!("Variable {} is given both as an array and a scalar", name)),
+        }
+    } else {
+        variables.insert(key.to_string(), query_scalar(value));
+        Ok(())
+    }
+}
+
+fn query_scalar(value: &str) -> serde_json::Value {
+    if let Ok(num) = value.parse::<f64>() {
+        serde_json::json!(num)
+    } else if value == "true" {
+        serde_json::json!(true)
+    } else if value == "false" {
+        serde_json::json!(false)
+    } else {
+        serde_json::json!(value)
+    }
 }
 
 pub fn handle_health(
@@ -182,14 +288,88 @@ pub fn handle_cache_clear(
     send_http_response(stream, 200, "application/json", &response.to_string());
 }
 
-fn process_eval_request(
+/// Sends the response and returns the size of the body written
+fn send_eval_response(stream: &mut TcpStream, response: &EvalResponse) -> usize {
+    let json = serde_json::to_string(response).unwrap_or_default();
+    send_http_response_with_headers(
+        stream,
+        if response.success { 200 } else { 400 },
+        "application/json",
+        &json,
+        &[("X-Request-Id", &response.correlation_id)],
+    );
+    json.len()
+}
+
+fn record_usage(request: &str, server_token: &Option<String>, response: &EvalResponse, bytes_out: usize) {
+    let tenant = USAGE.resolve_tenant(request, server_token);
+    USAGE.record(
+        &tenant,
+        response.success,
+        (response.execution_time_ms * 1000.0) as u64,
+        request.len(),
+        bytes_out,
+    );
+}
+
+pub(super) fn process_eval_request(
     req: EvalRequest,
     stats: Arc<ServerStats>,
     request_counter: Arc<AtomicU64>,
+    correlation_id: &str,
 ) -> EvalResponse {
     let request_id = request_counter.fetch_add(1, Ordering::Relaxed);
+    let correlation_id = correlation_id.to_string();
     let start_time = Instant::now();
 
+    let options = match request_options(req.options.as_ref()) {
+        Ok(options) => options,
+        Err(e) => {
+            return EvalResponse {
+                success: false,
+                result: None,
+                variables: None,
+                error: Some(format!("Invalid options: {}", e)),
+                execution_time_ms: start_time.elapsed().as_secs_f64() * 1000.0,
+                request_id,
+                correlation_id,
+                explanation: None,
+                profile: None,
+            };
+        }
+    };
+
+    // Reject pathological or disallowed expressions before doing any work on them.
+    // Parse errors are left for the evaluation to report.
+    let max_cost = MAX_COST.load(Ordering::Relaxed);
+    if max_cost > 0 || options.builtins_only {
+        if let Ok(expr) = skillet::parse(&req.expression) {
+            let report = skillet::estimate_cost(&expr);
+            let rejection = if options.builtins_only {
+                skillet::analysis::check_builtins_only(&expr).err().map(|e| e.message)
+            } else {
+                None
+            };
+            let rejection = rejection.or_else(|| (max_cost > 0 && report.score > max_cost).then(|| format!(
+                "Expression too complex: cost {} exceeds limit {} ({} nodes, {} complexity)",
+                report.score, max_cost, report.node_count, report.complexity
+            )));
+            if let Some(error) = rejection {
+                return EvalResponse {
+                    success: false,
+                    result: None,
+                    variables: None,
+                    error: Some(error),
+                    execution_time_ms: start_time.elapsed().as_secs_f64() * 1000.0,
+                    request_id,
+                    correlation_id,
+                    explanation: None,
+                    profile: None,
+                };
+            }
+        }
+    }
+
     // Convert JSON variables to Skillet values with key sanitization
     let vars = match req.arguments {
         Some(json_vars) => {
@@ -215,6 +395,9 @@ fn process_eval_request(
                             error: Some(format!("Error converting variable '{}': {}", key, e)),
                             execution_time_ms: start_time.elapsed().as_secs_f64() * 1000.0,
                             request_id,
+                            correlation_id,
+                            explanation: None,
+                            profile: None,
                         };
                     }
                 }
@@ -226,10 +409,47 @@ fn process_eval_request(
 
     // Evaluate expression with caching
     let include_variables = matches!(req.include_variables, Some(IncludeVariables::All) | Some(IncludeVariables::Selected(_)));
-    let cached_result = evaluate_cached(&req.expression, &vars, include_variables);
+    let eval_ctx = skillet::EvalContext::new()
+        .with_request_id(correlation_id.clone())
+        .with_options(options.clone());
+    let explain = req.explain.unwrap_or(false);
+    let run = || {
+        if !explain {
+            return (evaluate_cached(&req.expression, &vars, include_variables, &options), None);
+        }
+        // Explained evaluations bypass the cache; the result comes from the tree root
+        match skillet::evaluate_explained(&req.expression, &vars, EXPLAIN_MAX_DEPTH) {
+            Ok(tree) => {
+                let result = CachedResult {
+                    result: tree.result.clone(),
+                    variable_context: None,
+                    execution_time_ms: tree.duration.as_secs_f64() * 1000.0,
+                    cache_hit: false,
+                };
+                (result, Some(explanation_json(&tree, &options)))
+            }
+            Err(e) => {
+                let result = CachedResult {
+                    result: Err(e.to_string()),
+                    variable_context: None,
+                    execution_time_ms: 0.0,
+                    cache_hit: false,
+                };
+                (result, None)
+            }
+        }
+    };
+    let ((cached_result, explanation), profile) = skillet::context::with_context(eval_ctx, || {
+        if req.profile.unwrap_or(false) {
+            let (outcome, times) = skillet::context::profile_categories(run);
+            (outcome, Some(profile_json(&times)))
+        } else {
+            (run(), None)
+        }
+    });
 
     let (result, variable_context) = match cached_result.result {
-        Ok(value) => (Ok(value), cached_result.variable_context),
+        Ok(value) => (Ok(options.apply_precision(value)), cached_result.variable_context),
         Err(error_msg) => (Err(skillet::Error::new(error_msg, None)), None),
     };
 
@@ -262,7 +482,7 @@ fn process_eval_request(
                         };
 
                         if should_include {
-                            json_vars.insert(key, format_simple_output(&value));
+                            json_vars.insert(key, format_simple_output(&options.apply_precision(value)));
                         }
                     }
                 }
@@ -278,25 +498,79 @@ fn process_eval_request(
                 error: None,
                 execution_time_ms,
                 request_id,
+                correlation_id,
+                explanation,
+                profile,
+            }
+        }
+        Err(e) => {
+            logging::warn("evaluation failed", serde_json::json!({
+                "request_id": correlation_id,
+                "expression": req.expression,
+                "error": e.to_string(),
+            }));
+            EvalResponse {
+                success: false,
+                result: None,
+                variables: None,
+                error: Some(e.to_string()),
+                execution_time_ms,
+                request_id,
+                correlation_id,
+                explanation,
+                profile,
             }
         }
-        Err(e) => EvalResponse {
-            success: false,
-            result: None,
-            variables: None,
-            error: Some(e.to_string()),
-            execution_time_ms,
-            request_id,
-        },
     }
 }
 
+/// `{"statistical": {"calls": 3, "elapsed_ms": 0.4, "budget_ms": 50}, ...}`
+fn profile_json(times: &skillet::context::CategoryTimes) -> serde_json::Value {
+    let categories = times.iter().map(|(category, time)| {
+        let mut entry = serde_json::json!({
+            "calls": time.calls,
+            "elapsed_ms": time.elapsed.as_secs_f64() * 1000.0,
+        });
+        if let Some(budget) = time.budget {
+            entry["budget_ms"] = serde_json::json!(budget.as_millis() as u64);
+        }
+        (category.clone(), entry)
+    });
+    serde_json::Value::Object(categories.collect())
+}
+
+/// Levels of sub-expressions recorded when a request sets `explain`
+const EXPLAIN_MAX_DEPTH: usize = 32;
+
+fn explanation_json(node: &skillet::ExplainNode, options: &skillet::EvalOptions) -> serde_json::Value {
+    let mut out = serde_json::json!({
+        "source": node.source,
+        "duration_us": node.duration.as_micros() as u64,
+    });
+    match &node.result {
+        Ok(value) => out["value"] = format_simple_output(&options.apply_precision(value.clone())),
+        Err(e) => out["error"] = serde_json::json!(e),
+    }
+    if !node.children.is_empty() {
+        out["children"] = node.children.iter().map(|c| explanation_json(c, options)).collect();
+    }
+    if node.truncated {
+        out["truncated"] = serde_json::json!(true);
+    }
+    out
+}
+
+/// Numbers go out the way `to_s` renders them, without float artifacts
+fn json_number(n: f64) -> serde_json::Value {
+    serde_json::json!(format_number(n).parse::<f64>().unwrap_or(n))
+}
+
 fn format_structured_output(val: &Value, execution_time_ms: f64) -> serde_json::Value {
     let (result_value, type_name) = match val {
-        Value::Number(n) => (serde_json::json!(n), "Number"),
+        Value::Number(n) => (json_number(*n), "Number"),
         Value::String(s) => (serde_json::json!(s), "String"),
         Value::Boolean(b) => (serde_json::json!(b), "Boolean"),
-        Value::Currency(c) => (serde_json::json!(c), "Currency"),
+        Value::Currency(c) => (json_number(*c), "Currency"),
         Value::DateTime(dt) => (serde_json::json!(dt), "DateTime"),
         Value::Array(arr) => {
             let json_arr: Vec<serde_json::Value> = arr.iter().map(format_simple_output).collect();
@@ -309,6 +583,7 @@ fn format_structured_output(val: &Value, execution_time_ms: f64) -> serde_json::
                 Err(_) => (serde_json::json!(s), "Json")
             }
         }
+        Value::Map(_) => (val.to_json_value().unwrap_or_else(|e| serde_json::json!(e.message)), "Map"),
     };
 
     serde_json::json!({
@@ -320,10 +595,10 @@ fn format_structured_output(val: &Value, execution_time_ms: f64) -> serde_json::
 
 fn format_simple_output(val: &Value) -> serde_json::Value {
     match val {
-        Value::Number(n) => serde_json::json!(n),
+        Value::Number(n) => json_number(*n),
         Value::String(s) => serde_json::json!(s),
         Value::Boolean(b) => serde_json::json!(b),
-        Value::Currency(c) => serde_json::json!(c),
+        Value::Currency(c) => json_number(*c),
         Value::DateTime(dt) => serde_json::json!(dt.to_string()),
         Value::Array(arr) => {
             let json_arr: Vec<serde_json::Value> = arr.iter().map(format_simple_output).collect();
@@ -331,5 +606,29 @@ fn format_simple_output(val: &Value) -> serde_json::Value {
         },
         Value::Null => serde_json::json!(null),
         Value::Json(s) => serde_json::from_str(s).unwrap_or_else(|_| serde_json::json!(s)),
+        Value::Map(_) => val.to_json_value().unwrap_or_else(|e| serde_json::json!(e.message)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_variables_arrays_and_json() {
+        let mut vars = HashMap::new();
+        add_query_variable(&mut vars, "arr[]", "1").unwrap();
+        add_query_variable(&mut vars, "arr[]", "two").unwrap();
+        add_query_variable(&mut vars, "rate", "0.5").unwrap();
+        add_query_variable(&mut vars, "vars", r#"{"rate": 9, "user": {"age": 30}, "tags": ["a"]}"#).unwrap();
+
+        assert_eq!(vars["arr"], serde_json::json!([1.0, "two"]));
+        assert_eq!(vars["rate"], serde_json::json!(0.5));
+        assert_eq!(vars["user"], serde_json::json!({"age": 30}));
+        assert_eq!(vars["tags"], serde_json::json!(["a"]));
+
+        assert!(add_query_variable(&mut vars, "vars", "[1, 2]").is_err());
+        assert!(add_query_variable(&mut vars, "vars", "{oops").is_err());
+        assert!(add_query_variable(&mut vars, "rate[]", "1").is_err());
     }
 }
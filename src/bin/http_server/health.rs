@@ -0,0 +1,178 @@
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::Lazy;
+use skillet::js_plugin::JavaScriptFunction;
+use skillet::{CustomFunction, Value};
+
+use super::types::{BuildInfo, HealthCheck, ProbeResponse};
+use super::utils::send_http_response;
+
+const JS_NOT_STARTED: u8 = 0;
+const JS_WARM: u8 = 1;
+const JS_FAILED: u8 = 2;
+
+/// Startup progress tracked for the readiness probe
+pub struct Readiness {
+    started_at: AtomicU64,
+    hooks_loaded: AtomicBool,
+    hooks_count: AtomicU64,
+    hooks_error: Mutex<Option<String>>,
+    js_engine: AtomicU8,
+    js_engine_error: Mutex<Option<String>>,
+}
+
+pub static READINESS: Lazy<Readiness> = Lazy::new(|| Readiness {
+    started_at: AtomicU64::new(unix_now()),
+    hooks_loaded: AtomicBool::new(false),
+    hooks_count: AtomicU64::new(0),
+    hooks_error: Mutex::new(None),
+    js_engine: AtomicU8::new(JS_NOT_STARTED),
+    js_engine_error: Mutex::new(None),
+});
+
+impl Readiness {
+    /// Record the outcome of loading the hooks directory
+    pub fn mark_hooks(&self, result: Result<usize, String>) {
+        match result {
+            Ok(count) => {
+                self.hooks_count.store(count as u64, Ordering::Relaxed);
+                *self.hooks_error.lock().unwrap_or_else(|e| e.into_inner()) = None;
+            }
+            Err(e) => *self.hooks_error.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(e),
+        }
+        self.hooks_loaded.store(true, Ordering::Release);
+    }
+
+    /// Run a trivial script through the JS engine so the first real call doesn't pay start-up cost
+    pub fn warm_js_engine(&self) {
+        let probe = "// @name: __WARMUP__\n// @min_args: 1\n// @max_args: 1\nfunction execute(args) { return args[0] + 1; }";
+        let result = JavaScriptFunction::parse_js_function(probe)
+            .and_then(|f| f.execute(vec![Value::Number(1.0)]));
+        match result {
+            Ok(Value::Number(2.0)) => self.js_engine.store(JS_WARM, Ordering::Release),
+            Ok(other) => self.fail_js(format!("unexpected warm-up result: {:?}", other)),
+            Err(e) => self.fail_js(e.to_string()),
+        }
+    }
+
+    fn fail_js(&self, msg: String) {
+        *self.js_engine_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(msg);
+        self.js_engine.store(JS_FAILED, Ordering::Release);
+    }
+
+    fn checks(&self) -> Vec<HealthCheck> {
+        let mut checks = Vec::new();
+
+        checks.push(if !self.hooks_loaded.load(Ordering::Acquire) {
+            HealthCheck::fail("hooks", "hooks directory not loaded yet")
+        } else if let Some(e) = self.hooks_error.lock().unwrap_or_else(|e| e.into_inner()).clone() {
+            HealthCheck::fail("hooks", &e)
+        } else {
+            HealthCheck::ok("hooks", &format!("{} function(s) loaded", self.hooks_count.load(Ordering::Relaxed)))
+        });
+
+        // Every loaded hook must be visible through the global registry
+        let registered = skillet::list_custom_functions().len() as u64;
+        let expected = self.hooks_count.load(Ordering::Relaxed);
+        checks.push(if registered >= expected {
+            HealthCheck::ok("registry", &format!("{} custom function(s) registered", registered))
+        } else {
+            HealthCheck::fail("registry", &format!("expected {} custom function(s), found {}", expected, registered))
+        });
+
+        checks.push(match skillet::evaluate("1 + 1") {
+            Ok(Value::Number(2.0)) => HealthCheck::ok("evaluator", "smoke expression evaluated"),
+            Ok(other) => HealthCheck::fail("evaluator", &format!("unexpected result: {:?}", other)),
+            Err(e) => HealthCheck::fail("evaluator", &e.to_string()),
+        });
+
+        checks.push(match self.js_engine.load(Ordering::Acquire) {
+            JS_WARM => HealthCheck::ok("js_engine", "warm"),
+            JS_FAILED => HealthCheck::fail(
+                "js_engine",
+                &self.js_engine_error.lock().unwrap_or_else(|e| e.into_inner()).clone().unwrap_or_default(),
+            ),
+            // Warm-up is optional; a cold engine still serves requests
+            _ => HealthCheck::skipped("js_engine", "not warmed up"),
+        });
+
+        checks
+    }
+
+    fn build_info(&self) -> BuildInfo {
+        let started_at = self.started_at.load(Ordering::Relaxed);
+        BuildInfo {
+            name: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_sha: option_env!("SKILLET_GIT_SHA").map(|s| s.to_string()),
+            profile: if cfg!(debug_assertions) { "debug" } else { "release" }.to_string(),
+            target: format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS),
+            started_at,
+            uptime_secs: unix_now().saturating_sub(started_at),
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Liveness: the process is up and able to answer HTTP
+pub fn handle_healthz(stream: &mut TcpStream) {
+    let response = ProbeResponse {
+        status: "ok".to_string(),
+        checks: Vec::new(),
+        build: READINESS.build_info(),
+    };
+    let json = serde_json::to_string(&response).unwrap_or_default();
+    send_http_response(stream, 200, "application/json", &json);
+}
+
+/// Readiness: every dependency check passes; 503 otherwise so probes hold traffic back
+pub fn handle_readyz(stream: &mut TcpStream) {
+    let checks = READINESS.checks();
+    let ready = checks.iter().all(|c| c.status != "fail");
+    let response = ProbeResponse {
+        status: if ready { "ready" } else { "not_ready" }.to_string(),
+        checks,
+        build: READINESS.build_info(),
+    };
+    let json = serde_json::to_string(&response).unwrap_or_default();
+    send_http_response(stream, if ready { 200 } else { 503 }, "application/json", &json);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readiness_requires_hooks() {
+        let readiness = Readiness {
+            started_at: AtomicU64::new(unix_now()),
+            hooks_loaded: AtomicBool::new(false),
+            hooks_count: AtomicU64::new(0),
+            hooks_error: Mutex::new(None),
+            js_engine: AtomicU8::new(JS_NOT_STARTED),
+            js_engine_error: Mutex::new(None),
+        };
+        let hooks = |r: &Readiness| r.checks().into_iter().find(|c| c.name == "hooks").unwrap();
+
+        assert_eq!(hooks(&readiness).status, "fail");
+        readiness.mark_hooks(Ok(3));
+        assert_eq!(hooks(&readiness).status, "ok");
+        readiness.mark_hooks(Err("bad hook".to_string()));
+        assert_eq!(hooks(&readiness).status, "fail");
+
+        let js = readiness.checks().into_iter().find(|c| c.name == "js_engine").unwrap();
+        assert_eq!(js.status, "skipped");
+        readiness.warm_js_engine();
+        let js = readiness.checks().into_iter().find(|c| c.name == "js_engine").unwrap();
+        assert_eq!(js.status, "ok");
+    }
+}
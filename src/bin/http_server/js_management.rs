@@ -4,7 +4,8 @@ use std::fs;
 use skillet::{JSPluginLoader, CustomFunction, Value};
 use skillet::js_plugin::JavaScriptFunction;
 
-use super::auth::check_admin_authentication;
+use super::auth::{check_admin_authentication, check_eval_authentication};
+use super::health::READINESS;
 use super::types::*;
 use super::utils::{send_http_response, send_http_error, parse_json_body};
 use super::multipart::{is_multipart_request, extract_boundary_from_content_type, parse_multipart_data};
@@ -46,6 +47,40 @@ pub fn handle_list_js(
     }
 }
 
+/// `GET /functions`: the registered custom functions with their documentation, plus
+/// the builtin names, for formula builders. Open to anyone who may call `/eval`.
+pub fn handle_list_functions(stream: &mut TcpStream, request: &str, server_token: Arc<Option<String>>) {
+    if let Some(error_response) = check_eval_authentication(request, &server_token) {
+        send_http_response(stream, 401, "application/json", &error_response);
+        return;
+    }
+
+    // Only what an /eval request could call under the server policy
+    let options = super::eval::request_options(None).unwrap_or_default();
+    let ctx = skillet::EvalContext::new().with_options(options);
+    let (functions, builtins) = skillet::context::with_context(ctx, || {
+        use skillet::runtime::function_dispatch::{callable_function_names, is_callable};
+        let registry = skillet::registry_snapshot();
+        let mut names = registry.list_functions();
+        names.sort_unstable();
+        let functions: Vec<FunctionInfo> = names
+            .into_iter()
+            .filter(|name| is_callable(name, Some(&registry)))
+            .filter_map(|name| registry.get(name))
+            .map(FunctionInfo::from_function)
+            .collect();
+        (functions, callable_function_names(None))
+    });
+    let response = ListFunctionsResponse {
+        success: true,
+        total_count: functions.len(),
+        functions,
+        builtins,
+    };
+    let json = serde_json::to_string(&response).unwrap_or_default();
+    send_http_response(stream, 200, "application/json", &json);
+}
+
 pub fn handle_update_js(
     stream: &mut TcpStream,
     request: &str,
@@ -321,7 +356,10 @@ pub fn handle_reload_hooks(
     let hooks_dir = std::env::var("SKILLET_HOOKS_DIR").unwrap_or_else(|_| "hooks".to_string());
     let js_loader = JSPluginLoader::new(hooks_dir);
 
-    match js_loader.auto_register() {
+    let result = js_loader.auto_register();
+    READINESS.mark_hooks(result.as_ref().map(|c| *c).map_err(|e| e.to_string()));
+
+    match result {
         Ok(count) => {
             let response = ReloadHooksResponse {
                 success: true,
@@ -345,6 +383,101 @@ pub fn handle_reload_hooks(
     }
 }
 
+/// `POST /hooks/validate-all`: re-validate every hook file as an upload would, in
+/// parallel, and report per file. The live registry is left alone, so operators can
+/// check the hooks against a new engine before calling `/reload-hooks`.
+pub fn handle_validate_all_hooks(
+    stream: &mut TcpStream,
+    request: &str,
+    server_admin_token: Arc<Option<String>>,
+) {
+    if let Some(error_response) = check_admin_authentication(request, &server_admin_token) {
+        send_http_response(stream, 401, "application/json", &error_response);
+        return;
+    }
+
+    let hooks_dir = std::env::var("SKILLET_HOOKS_DIR").unwrap_or_else(|_| "hooks".to_string());
+    let hooks_path = std::path::Path::new(&hooks_dir);
+    let mut paths = Vec::new();
+    if hooks_path.exists() {
+        if let Err(e) = collect_js_files(hooks_path, &mut paths) {
+            let response = ValidateAllResponse { success: false, total_count: 0, valid_count: 0, files: Vec::new(), error: Some(e) };
+            let json = serde_json::to_string(&response).unwrap_or_default();
+            send_http_response(stream, 500, "application/json", &json);
+            return;
+        }
+    }
+    paths.sort();
+
+    let workers = std::thread::available_parallelism().map_or(4, |n| n.get()).min(paths.len().max(1));
+    let chunk_size = paths.len().div_ceil(workers).max(1);
+    let files: Vec<HookValidationReport> = std::thread::scope(|scope| {
+        let handles: Vec<_> = paths
+            .chunks(chunk_size)
+            .map(|chunk| (chunk, scope.spawn(move || chunk.iter().map(|path| validate_hook_file(hooks_path, path)).collect::<Vec<_>>())))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|(chunk, handle)| {
+                // A crashing validation still gets a line per file in the report
+                handle.join().unwrap_or_else(|_| {
+                    chunk
+                        .iter()
+                        .map(|path| HookValidationReport {
+                            filename: path.strip_prefix(hooks_path).unwrap_or(path).to_string_lossy().to_string(),
+                            function_name: None,
+                            valid: false,
+                            validation_results: None,
+                            error: Some("Validation panicked".to_string()),
+                        })
+                        .collect()
+                })
+            })
+            .collect()
+    });
+
+    let valid_count = files.iter().filter(|f| f.valid).count();
+    let response = ValidateAllResponse {
+        success: valid_count == files.len(),
+        total_count: files.len(),
+        valid_count,
+        files,
+        error: None,
+    };
+    let json = serde_json::to_string(&response).unwrap_or_default();
+    send_http_response(stream, 200, "application/json", &json);
+}
+
+fn collect_js_files(dir: &std::path::Path, out: &mut Vec<std::path::PathBuf>) -> Result<(), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("Failed to read directory: {}", e))?;
+    for entry in entries {
+        let path = entry.map_err(|e| format!("Failed to read directory entry: {}", e))?.path();
+        if path.is_dir() {
+            collect_js_files(&path, out)?;
+        } else if path.extension().and_then(|s| s.to_str()) == Some("js") {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn validate_hook_file(hooks_root: &std::path::Path, path: &std::path::Path) -> HookValidationReport {
+    let filename = path.strip_prefix(hooks_root).unwrap_or(path).to_string_lossy().to_string();
+    let validated = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read file: {}", e))
+        .and_then(|content| validate_js_function(&content));
+    match validated {
+        Ok((js_func, results)) => HookValidationReport {
+            filename,
+            function_name: Some(js_func.name().to_string()),
+            valid: results.example_test_passed,
+            validation_results: Some(results),
+            error: None,
+        },
+        Err(e) => HookValidationReport { filename, function_name: None, valid: false, validation_results: None, error: Some(e) },
+    }
+}
+
 fn validate_js_function(js_code: &str) -> Result<(JavaScriptFunction, ValidationResults), String> {
     let mut validation_results = ValidationResults {
         syntax_valid: false,
@@ -354,8 +487,8 @@ fn validate_js_function(js_code: &str) -> Result<(JavaScriptFunction, Validation
         example_error: None,
     };
 
-    // Step 1: Parse the JS function (validates syntax and structure)
-    let js_func = match JavaScriptFunction::parse_js_function(js_code) {
+    // Step 1: Parse the header and compile the source (validates syntax and structure)
+    let js_func = match JavaScriptFunction::parse_js_function(js_code).and_then(|func| func.compile().map(|_| func)) {
         Ok(func) => {
             validation_results.syntax_valid = true;
             validation_results.structure_valid = true;
@@ -472,6 +605,7 @@ fn format_value_for_comparison(value: &Value) -> String {
         Value::Currency(c) => format!("{}", c),
         Value::DateTime(dt) => dt.to_string(),
         Value::Json(json) => json.clone(),
+        Value::Map(_) => value.to_json_value().map(|json| json.to_string()).unwrap_or_default(),
     }
 }
 
@@ -573,35 +707,29 @@ fn scan_directory_for_js(
                 .unwrap_or_else(|_| "Unknown".to_string());
 
             // Try to parse the JS function to get metadata
-            let (function_name, description, example, min_args, max_args, is_valid, validation_error) =
-                match fs::read_to_string(&path) {
-                    Ok(content) => {
-                        match JavaScriptFunction::parse_js_function(&content) {
-                            Ok(js_func) => (
-                                Some(js_func.name().to_string()),
-                                js_func.description().map(|s| s.to_string()),
-                                js_func.example().map(|s| s.to_string()),
-                                Some(js_func.min_args()),
-                                js_func.max_args(),
-                                true,
-                                None,
-                            ),
-                            Err(e) => (None, None, None, None, None, false, Some(e.to_string())),
-                        }
-                    }
-                    Err(e) => (None, None, None, None, None, false, Some(format!("Failed to read file: {}", e))),
-                };
+            let parsed = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read file: {}", e))
+                .and_then(|content| JavaScriptFunction::parse_js_function(&content).map_err(|e| e.to_string()));
+            let (js_func, validation_error) = match parsed {
+                Ok(js_func) => (Some(js_func), None),
+                Err(e) => (None, Some(e)),
+            };
+            let js_func = js_func.as_ref().map(|f| f as &dyn CustomFunction);
 
             functions.push(JSFunctionInfo {
                 filename,
-                function_name,
-                description,
-                example,
-                min_args,
-                max_args,
+                function_name: js_func.map(|f| f.name().to_string()),
+                description: js_func.and_then(|f| f.description()).map(str::to_string),
+                example: js_func.and_then(|f| f.example()).map(str::to_string),
+                min_args: js_func.map(|f| f.min_args()),
+                max_args: js_func.and_then(|f| f.max_args()),
+                category: js_func.and_then(|f| f.category()).map(str::to_string),
+                params: js_func.map(param_infos).unwrap_or_default(),
+                returns: js_func.and_then(|f| f.returns()).map(str::to_string),
+                ui: js_func.map(ui_hints).unwrap_or_default(),
                 file_size,
                 last_modified,
-                is_valid,
+                is_valid: js_func.is_some(),
                 validation_error,
             });
         }
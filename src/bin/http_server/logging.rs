@@ -0,0 +1,288 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use once_cell::sync::OnceCell;
+use serde_json::{json, Map, Value as JsonValue};
+
+/// Log severity, ordered from most to least severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Some(LogLevel::Error),
+            "warn" | "warning" => Some(LogLevel::Warn),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+        }
+    }
+}
+
+/// Time-based rotation schedule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RotateInterval {
+    Never,
+    Hourly,
+    Daily,
+}
+
+impl RotateInterval {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "never" | "none" => Some(RotateInterval::Never),
+            "hourly" => Some(RotateInterval::Hourly),
+            "daily" => Some(RotateInterval::Daily),
+            _ => None,
+        }
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        match self {
+            RotateInterval::Never => None,
+            RotateInterval::Hourly => Some(Duration::from_secs(3600)),
+            RotateInterval::Daily => Some(Duration::from_secs(86400)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    pub path: Option<String>,
+    pub level: LogLevel,
+    /// Rotate once the active file reaches this many bytes (0 disables size rotation)
+    pub max_bytes: u64,
+    /// Number of rotated files to keep (`file.1` .. `file.N`)
+    pub max_files: usize,
+    pub rotate: RotateInterval,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            level: LogLevel::Info,
+            max_bytes: 10 * 1024 * 1024,
+            max_files: 5,
+            rotate: RotateInterval::Never,
+        }
+    }
+}
+
+struct FileLogger {
+    path: PathBuf,
+    level: LogLevel,
+    max_bytes: u64,
+    max_files: usize,
+    interval: Option<Duration>,
+    file: File,
+    size: u64,
+    opened_at: Instant,
+}
+
+impl FileLogger {
+    fn open(config: &LogConfig, path: &str) -> std::io::Result<Self> {
+        // Resolve relative paths now so rotation keeps working regardless of cwd
+        let path = std::env::current_dir()
+            .map(|cwd| cwd.join(path))
+            .unwrap_or_else(|_| PathBuf::from(path));
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        let file = open_append(&path)?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self {
+            path,
+            level: config.level,
+            max_bytes: config.max_bytes,
+            max_files: config.max_files,
+            interval: config.rotate.duration(),
+            file,
+            size,
+            opened_at: Instant::now(),
+        })
+    }
+
+    fn write_line(&mut self, line: &str) {
+        if self.should_rotate(line.len() as u64 + 1) {
+            // If rotation fails keep writing to the current file rather than dropping logs
+            let _ = self.rotate();
+        }
+        if writeln!(self.file, "{}", line).is_ok() {
+            self.size += line.len() as u64 + 1;
+        }
+    }
+
+    fn should_rotate(&self, incoming: u64) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        if self.max_bytes > 0 && self.size + incoming > self.max_bytes {
+            return true;
+        }
+        matches!(self.interval, Some(d) if self.opened_at.elapsed() >= d)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let _ = self.file.flush();
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(rotated_path(&self.path, self.max_files));
+            for n in (1..self.max_files).rev() {
+                let from = rotated_path(&self.path, n);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, n + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        self.opened_at = Instant::now();
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut s = path.as_os_str().to_owned();
+    s.push(format!(".{}", n));
+    PathBuf::from(s)
+}
+
+static LOGGER: OnceCell<Mutex<FileLogger>> = OnceCell::new();
+
+/// Open the log file described by `config`. Does nothing when no path is set.
+pub fn init(config: &LogConfig) -> Result<(), String> {
+    let Some(path) = config.path.as_deref() else {
+        return Ok(());
+    };
+    let logger = FileLogger::open(config, path)
+        .map_err(|e| format!("Failed to open log file '{}': {}", path, e))?;
+    LOGGER
+        .set(Mutex::new(logger))
+        .map_err(|_| "Logger already initialized".to_string())
+}
+
+/// Whether a message at `level` would be written
+pub fn enabled(level: LogLevel) -> bool {
+    LOGGER
+        .get()
+        .and_then(|l| l.lock().ok().map(|l| level <= l.level))
+        .unwrap_or(false)
+}
+
+/// Write one JSON line: `{"ts", "level", "msg", ...fields}`
+pub fn log(level: LogLevel, msg: &str, fields: JsonValue) {
+    let Some(logger) = LOGGER.get() else { return };
+    let Ok(mut logger) = logger.lock() else { return };
+    if level > logger.level {
+        return;
+    }
+    let line = format_entry(level, msg, fields);
+    logger.write_line(&line);
+}
+
+fn format_entry(level: LogLevel, msg: &str, fields: JsonValue) -> String {
+    let mut entry = Map::new();
+    entry.insert(
+        "ts".to_string(),
+        json!(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)),
+    );
+    entry.insert("level".to_string(), json!(level.as_str()));
+    entry.insert("msg".to_string(), json!(msg));
+    if let JsonValue::Object(extra) = fields {
+        for (k, v) in extra {
+            entry.entry(k).or_insert(v);
+        }
+    }
+    JsonValue::Object(entry).to_string()
+}
+
+pub fn error(msg: &str, fields: JsonValue) {
+    log(LogLevel::Error, msg, fields);
+}
+
+pub fn warn(msg: &str, fields: JsonValue) {
+    log(LogLevel::Warn, msg, fields);
+}
+
+pub fn info(msg: &str, fields: JsonValue) {
+    log(LogLevel::Info, msg, fields);
+}
+
+pub fn debug(msg: &str, fields: JsonValue) {
+    log(LogLevel::Debug, msg, fields);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(dir: &Path, max_bytes: u64, max_files: usize) -> LogConfig {
+        LogConfig {
+            path: Some(dir.join("server.log").to_string_lossy().to_string()),
+            level: LogLevel::Info,
+            max_bytes,
+            max_files,
+            rotate: RotateInterval::Never,
+        }
+    }
+
+    #[test]
+    fn test_level_parse_and_order() {
+        assert_eq!(LogLevel::parse("WARNING"), Some(LogLevel::Warn));
+        assert_eq!(LogLevel::parse("verbose"), None);
+        assert!(LogLevel::Error < LogLevel::Debug);
+    }
+
+    #[test]
+    fn test_entry_is_json_with_fields() {
+        let line = format_entry(LogLevel::Warn, "hello", json!({"path": "/eval", "msg": "ignored"}));
+        let v: JsonValue = serde_json::from_str(&line).unwrap();
+        assert_eq!(v["level"], "warn");
+        assert_eq!(v["msg"], "hello");
+        assert_eq!(v["path"], "/eval");
+        assert!(v["ts"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[test]
+    fn test_size_rotation_keeps_max_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = config(dir.path(), 64, 2);
+        let mut logger = FileLogger::open(&cfg, cfg.path.as_deref().unwrap()).unwrap();
+        for i in 0..20 {
+            logger.write_line(&format!("{{\"n\":{},\"pad\":\"xxxxxxxxxxxxxxxxxxxxxxxx\"}}", i));
+        }
+        let base = dir.path().join("server.log");
+        assert!(base.exists());
+        assert!(rotated_path(&base, 1).exists());
+        assert!(rotated_path(&base, 2).exists());
+        assert!(!rotated_path(&base, 3).exists());
+        assert!(fs::metadata(&base).unwrap().len() <= 64);
+    }
+}
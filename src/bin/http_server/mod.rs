@@ -1,9 +1,15 @@
 pub mod auth;
 pub mod cache;
+pub mod canary;
 pub mod daemon;
 pub mod eval;
+pub mod health;
 pub mod js_management;
+pub mod logging;
 pub mod multipart;
+pub mod snapshot;
 pub mod stats;
+pub mod stream;
 pub mod types;
+pub mod usage;
 pub mod utils;
\ No newline at end of file
@@ -0,0 +1,22 @@
+use std::net::TcpStream;
+use std::sync::Arc;
+
+use super::auth::check_admin_authentication;
+use super::eval::request_options;
+use super::utils::{send_http_error, send_http_response};
+
+/// `GET /snapshot`: the engine state `/eval` requests run under (server builtin
+/// policy, constants, cash increments, registered functions) as a manifest that
+/// `sk --snapshot` restores locally
+pub fn handle_snapshot(stream: &mut TcpStream, request: &str, server_admin_token: Arc<Option<String>>) {
+    if let Some(error_response) = check_admin_authentication(request, &server_admin_token) {
+        send_http_response(stream, 401, "application/json", &error_response);
+        return;
+    }
+
+    let manifest = request_options(None).and_then(|options| skillet::EngineSnapshot::capture().with_options(options).to_json());
+    match manifest {
+        Ok(json) => send_http_response(stream, 200, "application/json", &json),
+        Err(e) => send_http_error(stream, 500, &e.to_string()),
+    }
+}
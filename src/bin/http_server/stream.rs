@@ -0,0 +1,231 @@
+//! `POST /eval-stream`: evaluate one expression against every line of an NDJSON body.
+//!
+//! The expression comes from the query string (`expr`, plus optional URL-encoded
+//! `options` JSON); each non-blank body line is a JSON object of variables. The body
+//! is read line by line instead of being buffered, so it is not subject to the 1MB
+//! request limit, and results are written back as chunked NDJSON in input order as
+//! soon as they are ready.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+
+use super::auth::{check_eval_or_admin_authentication, check_privileged_options};
+use super::eval::process_eval_request;
+use super::stats::ServerStats;
+use super::types::{EvalRequest, EvalRequestOptions};
+use super::usage::USAGE;
+use super::utils::{parse_content_length, resolve_request_id, send_http_error, send_http_response};
+
+/// Longest accepted input line
+const MAX_LINE_BYTES: usize = 1024 * 1024;
+
+/// Results buffered before a chunk is written even if more input is ready
+const MAX_BATCH_LINES: usize = 256;
+
+struct StreamParams {
+    expression: String,
+    output_json: bool,
+    options: Option<EvalRequestOptions>,
+}
+
+fn parse_stream_query(head: &str) -> Result<StreamParams, String> {
+    let target = head.lines().next().and_then(|l| l.split_whitespace().nth(1)).unwrap_or("");
+    let (_, query) = target.split_once('?').unwrap_or(("", ""));
+
+    let mut params = StreamParams { expression: String::new(), output_json: false, options: None };
+    for param in query.split('&') {
+        if let Some((key, value)) = param.split_once('=') {
+            let value = urlencoding::decode(value).unwrap_or_default();
+            match key {
+                "expr" | "expression" => params.expression = value.to_string(),
+                "output_json" => params.output_json = value == "true",
+                "options" => {
+                    params.options = Some(
+                        serde_json::from_str(&value).map_err(|e| format!("Invalid options: {}", e))?,
+                    )
+                }
+                _ => {}
+            }
+        }
+    }
+    if params.expression.is_empty() {
+        return Err("Missing expr parameter".to_string());
+    }
+    Ok(params)
+}
+
+/// One output line for one input line
+fn evaluate_line(
+    params: &StreamParams,
+    line_no: usize,
+    line: &[u8],
+    stats: &Arc<ServerStats>,
+    request_counter: &Arc<AtomicU64>,
+    correlation_id: &str,
+) -> (bool, f64, String) {
+    let arguments = match serde_json::from_slice::<HashMap<String, serde_json::Value>>(line) {
+        Ok(vars) => vars,
+        Err(e) => {
+            let out = serde_json::json!({
+                "line": line_no,
+                "success": false,
+                "error": format!("Line is not a JSON object: {}", e),
+            });
+            return (false, 0.0, out.to_string());
+        }
+    };
+
+    let request = EvalRequest {
+        expression: params.expression.clone(),
+        arguments: Some(arguments),
+        output_json: Some(params.output_json),
+        include_variables: None,
+        options: params.options.clone(),
+        explain: None,
+        profile: None,
+    };
+    let response = process_eval_request(request, stats.clone(), request_counter.clone(), correlation_id);
+    let out = if response.success {
+        serde_json::json!({ "line": line_no, "success": true, "result": response.result })
+    } else {
+        serde_json::json!({ "line": line_no, "success": false, "error": response.error })
+    };
+    (response.success, response.execution_time_ms, out.to_string())
+}
+
+fn write_chunk(stream: &mut TcpStream, data: &str) -> std::io::Result<()> {
+    write!(stream, "{:x}\r\n{}\r\n", data.len(), data)
+}
+
+pub fn handle_eval_stream(
+    stream: &mut TcpStream,
+    head: &str,
+    leftover: Vec<u8>,
+    stats: Arc<ServerStats>,
+    request_counter: Arc<AtomicU64>,
+    server_token: Arc<Option<String>>,
+    server_admin_token: Arc<Option<String>>,
+) {
+    let correlation_id = &resolve_request_id(head);
+    if let Some(error_response) = check_eval_or_admin_authentication(head, &server_token, &server_admin_token) {
+        send_http_response(stream, 401, "application/json", &error_response);
+        return;
+    }
+
+    let params = match parse_stream_query(head) {
+        Ok(params) => params,
+        Err(e) => {
+            send_http_error(stream, 400, &e);
+            return;
+        }
+    };
+    if let Some(error_response) = check_privileged_options(head, params.options.as_ref(), &server_admin_token) {
+        send_http_response(stream, 401, "application/json", &error_response);
+        return;
+    }
+
+    // The body is read as it arrives, so its end has to be known up front
+    let content_length = parse_content_length(head);
+    let remaining = content_length.saturating_sub(leftover.len()) as u64;
+    let socket = match stream.try_clone() {
+        Ok(s) => s,
+        Err(e) => {
+            send_http_error(stream, 500, &format!("Cannot read request body: {}", e));
+            return;
+        }
+    };
+    let body = std::io::Cursor::new(leftover).take(content_length as u64).chain(socket.take(remaining));
+    let mut reader = BufReader::new(body);
+
+    let header = format!(
+        "HTTP/1.1 200 OK\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Expose-Headers: X-Request-Id\r\n\
+         Content-Type: application/x-ndjson\r\n\
+         Transfer-Encoding: chunked\r\n\
+         X-Request-Id: {}\r\n\
+         Connection: close\r\n\r\n",
+        correlation_id
+    );
+    if stream.write_all(header.as_bytes()).is_err() {
+        return;
+    }
+
+    let tenant = USAGE.resolve_tenant(head, &server_token);
+    let mut batch = String::new();
+    let mut batch_lines = 0;
+    let mut line = Vec::new();
+    let mut line_no = 0;
+    loop {
+        line.clear();
+        // Cap each read so a single runaway line cannot exhaust memory
+        let read = match reader.by_ref().take(MAX_LINE_BYTES as u64 + 1).read_until(b'\n', &mut line) {
+            Ok(n) => n,
+            Err(_) => break,
+        };
+        if read == 0 {
+            break;
+        }
+        line_no += 1;
+
+        let (success, time_ms, out) = if line.len() > MAX_LINE_BYTES && line.last() != Some(&b'\n') {
+            // Drop the rest of the oversized line
+            let mut rest = Vec::new();
+            while matches!(reader.by_ref().take(MAX_LINE_BYTES as u64).read_until(b'\n', &mut rest), Ok(n) if n > 0)
+                && rest.last() != Some(&b'\n')
+            {
+                rest.clear();
+            }
+            let out = serde_json::json!({
+                "line": line_no,
+                "success": false,
+                "error": format!("Line exceeds {} bytes", MAX_LINE_BYTES),
+            });
+            (false, 0.0, out.to_string())
+        } else {
+            if line.trim_ascii().is_empty() {
+                continue;
+            }
+            evaluate_line(&params, line_no, &line, &stats, &request_counter, correlation_id)
+        };
+
+        USAGE.record(&tenant, success, (time_ms * 1000.0) as u64, line.len(), out.len() + 1);
+        batch.push_str(&out);
+        batch.push('\n');
+        batch_lines += 1;
+
+        // Flush whenever the next line would have to wait on the client
+        if batch_lines >= MAX_BATCH_LINES || reader.buffer().is_empty() {
+            if write_chunk(stream, &batch).is_err() {
+                return;
+            }
+            batch.clear();
+            batch_lines = 0;
+        }
+    }
+
+    if !batch.is_empty() && write_chunk(stream, &batch).is_err() {
+        return;
+    }
+    let _ = stream.write_all(b"0\r\n\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stream_query_parsing() {
+        let head = "POST /eval-stream?expr=%3Ascore%20*%202&options=%7B%22precision%22%3A2%7D HTTP/1.1\r\n\r\n";
+        let params = parse_stream_query(head).unwrap();
+        assert_eq!(params.expression, ":score * 2");
+        assert_eq!(params.options.unwrap().precision, Some(2));
+        assert!(!params.output_json);
+
+        assert!(parse_stream_query("POST /eval-stream HTTP/1.1\r\n\r\n").is_err());
+        assert!(parse_stream_query("POST /eval-stream?expr=1&options=%7Bbad HTTP/1.1\r\n\r\n").is_err());
+    }
+}
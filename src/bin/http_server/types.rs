@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Clone)]
 pub enum IncludeVariables {
@@ -72,6 +72,91 @@ pub struct EvalRequest {
     pub arguments: Option<HashMap<String, serde_json::Value>>,
     pub output_json: Option<bool>,
     pub include_variables: Option<IncludeVariables>,
+    #[serde(default)]
+    pub options: Option<EvalRequestOptions>,
+    /// Return the evaluation tree with every sub-expression's value
+    #[serde(default)]
+    pub explain: Option<bool>,
+    /// Return the calls and time spent per function category
+    #[serde(default)]
+    pub profile: Option<bool>,
+}
+
+/// Per-request evaluation options, mapped onto `skillet::EvalOptions`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EvalRequestOptions {
+    pub timeout_ms: Option<u64>,
+    pub locale: Option<String>,
+    pub max_array_size: Option<usize>,
+    /// Approximate bytes of values the evaluation may allocate
+    pub max_memory_bytes: Option<usize>,
+    /// Longest string REPT, CONCAT, JOIN or `text * n` may produce
+    pub max_string_length: Option<usize>,
+    /// "lenient", "standard" or "strict"
+    pub strictness: Option<String>,
+    pub deterministic: Option<bool>,
+    /// Maximum decimal places in numeric output
+    pub precision: Option<u32>,
+    /// Significant digits in numeric output
+    pub significant_digits: Option<u32>,
+    /// ISO 4217 code for rendering currency values, e.g. "MXN"
+    pub currency: Option<String>,
+    /// Refuse expressions that call custom or JavaScript functions
+    pub builtins_only: Option<bool>,
+    /// Only these builtin functions may be called
+    pub allowed_builtins: Option<Vec<String>>,
+    /// Builtin functions that may not be called
+    pub denied_builtins: Option<Vec<String>>,
+    /// Tolerance for `==` between numbers
+    pub epsilon: Option<f64>,
+    /// Error instead of producing infinity or saturating integer conversions
+    pub overflow_check: Option<bool>,
+    /// Arithmetic with null gives null instead of an error
+    pub null_propagation: Option<bool>,
+    /// ISO 8601 timestamp NOW, DATE() and TIME() return instead of the clock; admin only
+    pub now_override: Option<String>,
+    /// Milliseconds each function category may take in total, e.g. {"javascript": 20}
+    pub category_budgets: Option<HashMap<String, u64>>,
+}
+
+impl EvalRequestOptions {
+    pub fn to_eval_options(&self) -> Result<skillet::EvalOptions, skillet::Error> {
+        let mut options = skillet::EvalOptions::new();
+        options.timeout_ms = self.timeout_ms;
+        options.locale = self.locale.clone();
+        options.max_array_size = self.max_array_size;
+        options.max_memory_bytes = self.max_memory_bytes;
+        options.max_string_length = self.max_string_length;
+        if let Some(s) = &self.strictness {
+            options.strictness = skillet::Strictness::parse(s)?;
+        }
+        options.deterministic = self.deterministic.unwrap_or(false);
+        options.precision = self.precision;
+        options.significant_digits = self.significant_digits;
+        options.currency = self.currency.clone();
+        options.builtins_only = self.builtins_only.unwrap_or(false);
+        options.epsilon = self.epsilon;
+        options.overflow_check = self.overflow_check.unwrap_or(false);
+        options.null_propagation = self.null_propagation.unwrap_or(false);
+        if let Some(names) = &self.allowed_builtins {
+            options = options.with_allowed_builtins(names);
+        }
+        if let Some(names) = &self.denied_builtins {
+            options = options.with_denied_builtins(names);
+        }
+        if let Some(now) = &self.now_override {
+            let timestamp = skillet::runtime::datetime::parse_iso_datetime(now).ok_or_else(|| {
+                skillet::Error::new(format!("Invalid now_override '{}': expected an ISO 8601 timestamp such as 2024-01-31T23:59:59Z", now), None)
+            })?;
+            options = options.with_now_override(timestamp);
+        }
+        for (category, ms) in self.category_budgets.iter().flatten() {
+            options = options.with_category_budget(category, *ms);
+        }
+        options.validate()?;
+        Ok(options)
+    }
 }
 
 fn deserialize_expression<'de, D>(deserializer: D) -> Result<String, D::Error>
@@ -137,6 +222,14 @@ pub struct EvalResponse {
     pub error: Option<String>,
     pub execution_time_ms: f64,
     pub request_id: u64,
+    /// Caller-supplied `X-Request-Id` (or a generated one), echoed back for correlation
+    pub correlation_id: String,
+    /// Evaluation tree, when the request set `explain`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<serde_json::Value>,
+    /// Calls and time per function category, when the request set `profile`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Serialize)]
@@ -148,6 +241,103 @@ pub struct HealthResponse {
     pub cache_stats: Option<CacheStatsResponse>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct ProbeResponse {
+    pub status: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub checks: Vec<HealthCheck>,
+    pub build: BuildInfo,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthCheck {
+    pub name: String,
+    /// "ok", "fail" or "skipped"
+    pub status: String,
+    pub detail: String,
+}
+
+impl HealthCheck {
+    pub fn ok(name: &str, detail: &str) -> Self {
+        Self { name: name.to_string(), status: "ok".to_string(), detail: detail.to_string() }
+    }
+
+    pub fn fail(name: &str, detail: &str) -> Self {
+        Self { name: name.to_string(), status: "fail".to_string(), detail: detail.to_string() }
+    }
+
+    pub fn skipped(name: &str, detail: &str) -> Self {
+        Self { name: name.to_string(), status: "skipped".to_string(), detail: detail.to_string() }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BuildInfo {
+    pub name: String,
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_sha: Option<String>,
+    pub profile: String,
+    pub target: String,
+    pub started_at: u64,
+    pub uptime_secs: u64,
+}
+
+/// `POST /canary`: formulas to evaluate under two sets of options
+#[derive(Debug, Deserialize)]
+pub struct CanaryRequest {
+    pub cases: Vec<CanaryCaseRequest>,
+    /// Options currently in use; server defaults when absent
+    #[serde(default)]
+    pub baseline: Option<EvalRequestOptions>,
+    /// Options being rolled out
+    #[serde(default)]
+    pub candidate: Option<EvalRequestOptions>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CanaryCaseRequest {
+    #[serde(deserialize_with = "deserialize_expression")]
+    pub expression: String,
+    pub arguments: Option<HashMap<String, serde_json::Value>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CanaryDifferenceInfo {
+    pub index: usize,
+    pub expression: String,
+    /// `{"result": ..., "type": ...}` or `{"error": ...}`
+    pub baseline: serde_json::Value,
+    pub candidate: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CanaryResponse {
+    /// Every formula has the same outcome under both configurations
+    pub success: bool,
+    pub total_count: usize,
+    pub agreed_count: usize,
+    pub differences: Vec<CanaryDifferenceInfo>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageResponse {
+    pub success: bool,
+    pub tenants: Vec<TenantUsage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TenantUsage {
+    pub tenant: String,
+    pub requests: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    pub total_eval_time_ms: f64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+}
+
 #[derive(Debug, Serialize)]
 pub struct CacheStatsResponse {
     pub hits: u64,
@@ -214,12 +404,74 @@ pub struct JSFunctionInfo {
     pub example: Option<String>,
     pub min_args: Option<usize>,
     pub max_args: Option<usize>,
+    pub category: Option<String>,
+    pub params: Vec<FunctionParamInfo>,
+    pub returns: Option<String>,
+    pub ui: BTreeMap<String, String>,
     pub file_size: u64,
     pub last_modified: String,
     pub is_valid: bool,
     pub validation_error: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct FunctionParamInfo {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// A registered custom function and its documentation, as listed by `/functions`
+#[derive(Debug, Serialize)]
+pub struct FunctionInfo {
+    pub name: String,
+    pub min_args: usize,
+    pub max_args: Option<usize>,
+    pub description: Option<String>,
+    pub example: Option<String>,
+    pub category: Option<String>,
+    pub params: Vec<FunctionParamInfo>,
+    pub returns: Option<String>,
+    pub ui: BTreeMap<String, String>,
+}
+
+impl FunctionInfo {
+    pub fn from_function(function: &dyn skillet::CustomFunction) -> Self {
+        Self {
+            name: function.name().to_uppercase(),
+            min_args: function.min_args(),
+            max_args: function.max_args(),
+            description: function.description().map(str::to_string),
+            example: function.example().map(str::to_string),
+            category: function.category().map(str::to_string),
+            params: param_infos(function),
+            returns: function.returns().map(str::to_string),
+            ui: ui_hints(function),
+        }
+    }
+}
+
+pub fn param_infos(function: &dyn skillet::CustomFunction) -> Vec<FunctionParamInfo> {
+    function
+        .params()
+        .iter()
+        .map(|p| FunctionParamInfo { name: p.name.clone(), description: p.description.clone() })
+        .collect()
+}
+
+pub fn ui_hints(function: &dyn skillet::CustomFunction) -> BTreeMap<String, String> {
+    function.ui_hints().iter().cloned().collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListFunctionsResponse {
+    pub success: bool,
+    /// Registered custom functions (JavaScript hooks and Rust plugins), by name
+    pub functions: Vec<FunctionInfo>,
+    /// Names of the builtins this server allows
+    pub builtins: Vec<String>,
+    pub total_count: usize,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ListJSResponse {
     pub success: bool,
@@ -236,6 +488,26 @@ pub struct ReloadHooksResponse {
     pub error: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct HookValidationReport {
+    pub filename: String,
+    pub function_name: Option<String>,
+    /// Syntax, structure and example all passed
+    pub valid: bool,
+    pub validation_results: Option<ValidationResults>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidateAllResponse {
+    /// Every hook file is valid
+    pub success: bool,
+    pub total_count: usize,
+    pub valid_count: usize,
+    pub files: Vec<HookValidationReport>,
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ValidationResults {
     pub syntax_valid: bool,
@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+
+use super::auth::{check_admin_authentication, extract_auth_header};
+use super::types::{TenantUsage, UsageResponse};
+use super::utils::send_http_response;
+
+/// Tenant used for requests authenticated with the main `--token`
+pub const DEFAULT_TENANT: &str = "default";
+/// Tenant used when the server runs without authentication
+pub const ANONYMOUS_TENANT: &str = "anonymous";
+
+/// Name, type, help text and value of an exported Prometheus metric
+type Metric = (&'static str, &'static str, &'static str, fn(&TenantUsage) -> f64);
+
+#[derive(Default)]
+struct Counters {
+    requests: AtomicU64,
+    errors: AtomicU64,
+    eval_time_us: AtomicU64,
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+}
+
+/// Per-tenant request accounting
+pub struct UsageTracker {
+    /// token -> tenant name, from `--tenant name:token`
+    tenants: RwLock<HashMap<String, String>>,
+    counters: RwLock<HashMap<String, Arc<Counters>>>,
+    metrics_enabled: AtomicBool,
+}
+
+pub static USAGE: Lazy<UsageTracker> = Lazy::new(UsageTracker::new);
+
+impl UsageTracker {
+    fn new() -> Self {
+        Self {
+            tenants: RwLock::new(HashMap::new()),
+            counters: RwLock::new(HashMap::new()),
+            metrics_enabled: AtomicBool::new(false),
+        }
+    }
+
+    /// Register an extra eval token billed to `tenant`
+    pub fn add_tenant(&self, tenant: &str, token: &str) {
+        if let Ok(mut tenants) = self.tenants.write() {
+            tenants.insert(token.to_string(), tenant.to_string());
+        }
+    }
+
+    pub fn tenant_for_token(&self, token: &str) -> Option<String> {
+        self.tenants.read().ok().and_then(|t| t.get(token).cloned())
+    }
+
+    pub fn enable_metrics(&self) {
+        self.metrics_enabled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn metrics_enabled(&self) -> bool {
+        self.metrics_enabled.load(Ordering::Relaxed)
+    }
+
+    /// Attribute an authenticated request to a tenant name
+    pub fn resolve_tenant(&self, request: &str, server_token: &Option<String>) -> String {
+        match extract_auth_header(request) {
+            Some(token) => {
+                if let Some(tenant) = self.tenant_for_token(&token) {
+                    tenant
+                } else if server_token.as_deref() == Some(token.as_str()) {
+                    DEFAULT_TENANT.to_string()
+                } else {
+                    ANONYMOUS_TENANT.to_string()
+                }
+            }
+            None if server_token.is_some() => DEFAULT_TENANT.to_string(),
+            None => ANONYMOUS_TENANT.to_string(),
+        }
+    }
+
+    fn counters(&self, tenant: &str) -> Arc<Counters> {
+        if let Some(c) = self.counters.read().ok().and_then(|m| m.get(tenant).cloned()) {
+            return c;
+        }
+        let mut map = match self.counters.write() {
+            Ok(map) => map,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        map.entry(tenant.to_string()).or_default().clone()
+    }
+
+    pub fn record(&self, tenant: &str, success: bool, eval_time_us: u64, bytes_in: usize, bytes_out: usize) {
+        let c = self.counters(tenant);
+        c.requests.fetch_add(1, Ordering::Relaxed);
+        if !success {
+            c.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        c.eval_time_us.fetch_add(eval_time_us, Ordering::Relaxed);
+        c.bytes_in.fetch_add(bytes_in as u64, Ordering::Relaxed);
+        c.bytes_out.fetch_add(bytes_out as u64, Ordering::Relaxed);
+    }
+
+    /// Snapshot of all tenants, sorted by name
+    pub fn snapshot(&self) -> Vec<TenantUsage> {
+        let map = match self.counters.read() {
+            Ok(map) => map,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let mut out: Vec<TenantUsage> = map
+            .iter()
+            .map(|(tenant, c)| {
+                let requests = c.requests.load(Ordering::Relaxed);
+                let errors = c.errors.load(Ordering::Relaxed);
+                TenantUsage {
+                    tenant: tenant.clone(),
+                    requests,
+                    errors,
+                    error_rate: if requests > 0 { errors as f64 / requests as f64 } else { 0.0 },
+                    total_eval_time_ms: c.eval_time_us.load(Ordering::Relaxed) as f64 / 1000.0,
+                    bytes_in: c.bytes_in.load(Ordering::Relaxed),
+                    bytes_out: c.bytes_out.load(Ordering::Relaxed),
+                }
+            })
+            .collect();
+        out.sort_by(|a, b| a.tenant.cmp(&b.tenant));
+        out
+    }
+
+    /// Prometheus text exposition of the per-tenant counters
+    pub fn prometheus(&self) -> String {
+        let usage = self.snapshot();
+        let mut out = String::new();
+        let metrics: [Metric; 5] = [
+            ("skillet_tenant_requests_total", "counter", "Evaluation requests per tenant", |u| u.requests as f64),
+            ("skillet_tenant_errors_total", "counter", "Failed evaluation requests per tenant", |u| u.errors as f64),
+            ("skillet_tenant_eval_seconds_total", "counter", "Total evaluation time per tenant", |u| u.total_eval_time_ms / 1000.0),
+            ("skillet_tenant_bytes_in_total", "counter", "Request bytes received per tenant", |u| u.bytes_in as f64),
+            ("skillet_tenant_bytes_out_total", "counter", "Response bytes sent per tenant", |u| u.bytes_out as f64),
+        ];
+        for (name, kind, help, value) in metrics {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            for u in &usage {
+                let _ = writeln!(out, "{}{{tenant=\"{}\"}} {}", name, escape_label(&u.tenant), value(u));
+            }
+        }
+        out
+    }
+}
+
+fn escape_label(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// `GET /usage`: admins see every tenant, tenant tokens only see themselves
+pub fn handle_usage(
+    stream: &mut TcpStream,
+    request: &str,
+    server_token: Arc<Option<String>>,
+    server_admin_token: Arc<Option<String>>,
+) {
+    let is_admin = server_admin_token.is_some()
+        && check_admin_authentication(request, &server_admin_token).is_none();
+    let tenant = extract_auth_header(request).and_then(|t| USAGE.tenant_for_token(&t));
+
+    let tenants = if is_admin || (server_admin_token.is_none() && server_token.is_none()) {
+        USAGE.snapshot()
+    } else if let Some(tenant) = tenant {
+        USAGE.snapshot().into_iter().filter(|u| u.tenant == tenant).collect()
+    } else {
+        let error = serde_json::json!({
+            "success": false,
+            "error": "Unauthorized: usage requires an admin or tenant token"
+        });
+        send_http_response(stream, 401, "application/json", &error.to_string());
+        return;
+    };
+
+    let response = UsageResponse { success: true, tenants };
+    let json = serde_json::to_string(&response).unwrap_or_default();
+    send_http_response(stream, 200, "application/json", &json);
+}
+
+/// `GET /metrics`: Prometheus scrape endpoint, enabled with `--metrics`
+pub fn handle_metrics(stream: &mut TcpStream, request: &str, server_admin_token: Arc<Option<String>>) {
+    if !USAGE.metrics_enabled() {
+        super::utils::send_http_error(stream, 404, "Not Found");
+        return;
+    }
+    if let Some(error_response) = check_admin_authentication(request, &server_admin_token) {
+        send_http_response(stream, 401, "application/json", &error_response);
+        return;
+    }
+    send_http_response(stream, 200, "text/plain; version=0.0.4", &USAGE.prometheus());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usage_accounting_per_tenant() {
+        let tracker = UsageTracker::new();
+        tracker.add_tenant("billing", "tok-b");
+
+        let req = "POST /eval HTTP/1.1\r\nAuthorization: Bearer tok-b\r\n\r\n{}";
+        let tenant = tracker.resolve_tenant(req, &Some("main".to_string()));
+        assert_eq!(tenant, "billing");
+
+        tracker.record(&tenant, true, 1500, 100, 50);
+        tracker.record(&tenant, false, 500, 20, 10);
+        tracker.record(DEFAULT_TENANT, true, 10, 1, 1);
+
+        let usage = tracker.snapshot();
+        assert_eq!(usage.len(), 2);
+        let billing = usage.iter().find(|u| u.tenant == "billing").unwrap();
+        assert_eq!(billing.requests, 2);
+        assert_eq!(billing.errors, 1);
+        assert!((billing.error_rate - 0.5).abs() < 1e-9);
+        assert!((billing.total_eval_time_ms - 2.0).abs() < 1e-9);
+        assert_eq!(billing.bytes_in, 120);
+
+        let text = tracker.prometheus();
+        assert!(text.contains("skillet_tenant_requests_total{tenant=\"billing\"} 2"));
+        assert!(text.contains("# TYPE skillet_tenant_errors_total counter"));
+    }
+}
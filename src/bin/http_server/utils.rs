@@ -23,29 +23,39 @@ pub fn sanitize_json_key(key: &str) -> String {
         .collect()
 }
 
-pub fn read_complete_http_request(stream: &mut TcpStream) -> Result<String, std::io::Error> {
+// Maximum request size limit (1MB)
+const MAX_REQUEST_SIZE: usize = 1024 * 1024;
+
+/// Read up to the end of the headers. Returns the head (including the blank
+/// line) and any body bytes that arrived with it, so streaming handlers can
+/// consume the body themselves.
+pub fn read_http_head(stream: &mut TcpStream) -> Result<(String, Vec<u8>), std::io::Error> {
     // Set socket timeouts to prevent hanging connections
     stream.set_read_timeout(Some(std::time::Duration::from_secs(30)))?;
     stream.set_write_timeout(Some(std::time::Duration::from_secs(10)))?;
-    
+
     let mut buffer = get_pooled_buffer();
     let mut temp_buffer = [0; 4096]; // Increased buffer size for better performance
-    let mut headers_complete = false;
-    let mut content_length: usize = 0;
-    let mut headers_end_pos = 0;
-    
-    // Maximum request size limit (1MB)
-    const MAX_REQUEST_SIZE: usize = 1024 * 1024;
-
-    // First, read until we have complete headers
-    while !headers_complete {
+
+    // Read until we have complete headers
+    loop {
         let bytes_read = stream.read(&mut temp_buffer)?;
         if bytes_read == 0 {
             break;
         }
 
         buffer.extend_from_slice(&temp_buffer[..bytes_read]);
-        
+
+        // Check if we have complete headers (ending with \r\n\r\n)
+        if let Some(pos) = find_headers_end(&buffer) {
+            let body = buffer.split_off(pos + 4);
+            let head = String::from_utf8(buffer).map_err(|e| {
+                return_pooled_buffer(e.into_bytes());
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid UTF-8")
+            })?;
+            return Ok((head, body));
+        }
+
         // Check request size limit
         if buffer.len() > MAX_REQUEST_SIZE {
             return Err(std::io::Error::new(
@@ -53,34 +63,36 @@ pub fn read_complete_http_request(stream: &mut TcpStream) -> Result<String, std:
                 "Request too large (max 1MB)"
             ));
         }
+    }
 
-        // Check if we have complete headers (ending with \r\n\r\n)
-        if let Some(pos) = find_headers_end(&buffer) {
-            headers_complete = true;
-            headers_end_pos = pos + 4;
-
-            // Parse the headers to get Content-Length
-            let headers_str = String::from_utf8_lossy(&buffer[..pos]);
-            content_length = parse_content_length(&headers_str);
-            
-            // Validate content length
-            if content_length > MAX_REQUEST_SIZE {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "Request body too large (max 1MB)"
-                ));
-            }
-        }
+    // Connection closed before the headers ended; let the router reject it
+    let head = String::from_utf8(buffer).map_err(|e| {
+        return_pooled_buffer(e.into_bytes());
+        std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid UTF-8")
+    })?;
+    Ok((head, Vec::new()))
+}
+
+/// Read the rest of a `Content-Length` body after [`read_http_head`] and
+/// return the complete request
+pub fn read_http_body(stream: &mut TcpStream, head: String, mut body: Vec<u8>) -> Result<String, std::io::Error> {
+    let content_length = parse_content_length(&head);
+
+    // Validate content length
+    if content_length > MAX_REQUEST_SIZE {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Request body too large (max 1MB)"
+        ));
     }
 
     // Now read the remaining body if needed
-    let body_bytes_read = buffer.len() - headers_end_pos;
-    let remaining_bytes = content_length.saturating_sub(body_bytes_read);
+    let remaining_bytes = content_length.saturating_sub(body.len());
 
     if remaining_bytes > 0 {
         // Reserve space in buffer to avoid multiple reallocations
-        buffer.reserve(remaining_bytes);
-        
+        body.reserve(remaining_bytes);
+
         let mut temp_body_buffer = [0; 8192]; // Larger read buffer
         let mut total_read = 0;
 
@@ -90,20 +102,15 @@ pub fn read_complete_http_request(stream: &mut TcpStream) -> Result<String, std:
             if bytes_read == 0 {
                 break; // Connection closed by client
             }
-            
-            buffer.extend_from_slice(&temp_body_buffer[..bytes_read]);
+
+            body.extend_from_slice(&temp_body_buffer[..bytes_read]);
             total_read += bytes_read;
-            
-            // Additional safety check
-            if buffer.len() > MAX_REQUEST_SIZE {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "Request exceeded size limit during body read"
-                ));
-            }
         }
     }
 
+    let mut buffer = head.into_bytes();
+    buffer.extend_from_slice(&body);
+
     // Convert buffer to string without cloning (consume the buffer)
     String::from_utf8(buffer).map_err(|e| {
         // If conversion fails, we can't return the buffer to pool (it was consumed)
@@ -119,7 +126,7 @@ fn find_headers_end(buffer: &[u8]) -> Option<usize> {
     buffer.windows(pattern.len()).position(|window| window == pattern)
 }
 
-fn parse_content_length(headers: &str) -> usize {
+pub fn parse_content_length(headers: &str) -> usize {
     for line in headers.lines() {
         if line.to_lowercase().starts_with("content-length:") {
             if let Some(value) = line.split(':').nth(1) {
@@ -131,6 +138,16 @@ fn parse_content_length(headers: &str) -> usize {
 }
 
 pub fn send_http_response(stream: &mut TcpStream, status: u16, content_type: &str, body: &str) {
+    send_http_response_with_headers(stream, status, content_type, body, &[]);
+}
+
+pub fn send_http_response_with_headers(
+    stream: &mut TcpStream,
+    status: u16,
+    content_type: &str,
+    body: &str,
+    extra_headers: &[(&str, &str)],
+) {
     let status_text = match status {
         200 => "OK",
         400 => "Bad Request",
@@ -139,6 +156,7 @@ pub fn send_http_response(stream: &mut TcpStream, status: u16, content_type: &st
         413 => "Payload Too Large",
         404 => "Not Found",
         500 => "Internal Server Error",
+        503 => "Service Unavailable",
         _ => "Unknown",
     };
 
@@ -152,14 +170,18 @@ pub fn send_http_response(stream: &mut TcpStream, status: u16, content_type: &st
         "HTTP/1.1 {} {}\r\n\
          Access-Control-Allow-Origin: *\r\n\
          Access-Control-Allow-Methods: GET, POST, PUT, DELETE, OPTIONS\r\n\
-         Access-Control-Allow-Headers: Content-Type, Authorization\r\n\
+         Access-Control-Allow-Headers: Content-Type, Authorization, X-Request-Id\r\n\
+         Access-Control-Expose-Headers: X-Request-Id\r\n\
          Content-Type: {}\r\n\
          Content-Length: {}\r\n\
-         Connection: close\r\n\
-         \r\n\
-         {}",
-        status, status_text, content_type, body.len(), body
+         Connection: close\r\n",
+        status, status_text, content_type, body.len()
     );
+    for (name, value) in extra_headers {
+        let _ = write!(&mut response, "{}: {}\r\n", name, value);
+    }
+    response.push_str("\r\n");
+    response.push_str(body);
 
     let _ = stream.write_all(response.as_bytes());
 }
@@ -176,12 +198,53 @@ pub fn handle_cors_preflight(stream: &mut TcpStream) {
     let response = "HTTP/1.1 200 OK\r\n\
         Access-Control-Allow-Origin: *\r\n\
         Access-Control-Allow-Methods: GET, POST, PUT, DELETE, OPTIONS\r\n\
-        Access-Control-Allow-Headers: Content-Type, Authorization\r\n\
+        Access-Control-Allow-Headers: Content-Type, Authorization, X-Request-Id\r\n\
         Content-Length: 0\r\n\
         \r\n";
     let _ = stream.write_all(response.as_bytes());
 }
 
+/// Value of the first header named `name` (case-insensitive), ignoring the body
+pub fn extract_header(request: &str, name: &str) -> Option<String> {
+    for line in request.lines().skip(1) {
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            if key.trim().eq_ignore_ascii_case(name) {
+                return Some(value.trim().to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Use the caller's `X-Request-Id` when it is sane, otherwise mint a new one
+pub fn resolve_request_id(request: &str) -> String {
+    match extract_header(request, "x-request-id") {
+        Some(id) if is_valid_request_id(&id) => id,
+        _ => generate_request_id(),
+    }
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 128
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':' | '/' | '+' | '='))
+}
+
+pub fn generate_request_id() -> String {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static SEQ: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+    let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+    format!("{:016x}-{:04x}-{:06x}", nanos, std::process::id() & 0xffff, seq & 0xff_ffff)
+}
+
 pub fn parse_json_body<T: DeserializeOwned>(request: &str) -> Result<T, String> {
     // Find the JSON body after headers
     let body_start = match request.find("\r\n\r\n") {
@@ -208,4 +271,26 @@ pub fn load_html_file() -> String {
 </html>"#.to_string()
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_id_header_is_honored() {
+        let request = "POST /eval HTTP/1.1\r\nHost: x\r\nx-request-id: abc-123\r\n\r\n{\"x-request-id\": \"body\"}";
+        assert_eq!(extract_header(request, "X-Request-Id").as_deref(), Some("abc-123"));
+        assert_eq!(resolve_request_id(request), "abc-123");
+    }
+
+    #[test]
+    fn test_request_id_generated_when_missing_or_invalid() {
+        let missing = "GET /eval HTTP/1.1\r\nHost: x\r\n\r\n";
+        let invalid = "GET /eval HTTP/1.1\r\nX-Request-Id: has spaces<script>\r\n\r\n";
+        let a = resolve_request_id(missing);
+        let b = resolve_request_id(invalid);
+        assert!(is_valid_request_id(&a));
+        assert!(is_valid_request_id(&b));
+        assert_ne!(a, b);
+    }
+}
@@ -1,4 +1,4 @@
-use skillet::{evaluate_with_custom, evaluate_with_assignments, Value, JSPluginLoader};
+use skillet::{evaluate_with_custom, evaluate_with_assignments, EngineSnapshot, Value, JSPluginLoader};
 use std::collections::HashMap;
 use std::time::Instant;
 use serde_json::json;
@@ -43,6 +43,11 @@ fn main() {
         eprintln!("Options:");
         eprintln!("  --output-json    Output result in JSON format with type and timing");
         eprintln!("  --json JSON      Use JSON string for variable values");
+        eprintln!("  --snapshot FILE  Restore an engine snapshot (options, constants, functions) before evaluating");
+        eprintln!();
+        eprintln!("Formula bundles:");
+        eprintln!("  sk --compile formulas.txt bundle.skb   Parse one formula per line into a binary bundle");
+        eprintln!("  sk --bundle bundle.skb [var=value ...]  Evaluate every formula in a bundle without re-parsing");
         eprintln!("");
         eprintln!("Examples:");
         eprintln!("  # Basic usage:");
@@ -61,10 +66,33 @@ fn main() {
         std::process::exit(1);
     }
 
+    if args[0] == "--compile" {
+        compile_bundle(&args[1..]);
+        return;
+    }
+    let bundle_path = if args[0] == "--bundle" {
+        match args.get(1) {
+            Some(path) => Some(path.clone()),
+            None => {
+                eprintln!("Error: --bundle requires a bundle file");
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+    let args: Vec<String> = if bundle_path.is_some() {
+        // Keep the expression slot so variable parsing below is unchanged
+        std::iter::once(String::new()).chain(args[2..].iter().cloned()).collect()
+    } else {
+        args
+    };
+
     // Parse arguments and flags
     let mut expr = "";
     let mut json_input = None;
     let mut output_json = false;
+    let mut snapshot = None;
     let mut vars = HashMap::new();
     let mut i = 0;
 
@@ -85,6 +113,13 @@ fn main() {
             i += 1; // Skip the JSON string argument
         } else if arg == "--output-json" {
             output_json = true;
+        } else if arg == "--snapshot" {
+            let Some(path) = args.get(i + 1) else {
+                eprintln!("Error: --snapshot requires a snapshot file");
+                std::process::exit(1);
+            };
+            snapshot = Some(load_snapshot(path));
+            i += 1;
         } else if let Some((name, value_str)) = arg.split_once('=') {
             // Variable assignment
             let value = parse_value(value_str);
@@ -97,10 +132,33 @@ fn main() {
         i += 1;
     }
 
+    if let Some(path) = bundle_path {
+        if let Some(json_str) = &json_input {
+            let map = match serde_json::from_str(json_str) {
+                Ok(serde_json::Value::Object(map)) => map,
+                _ => {
+                    eprintln!("Error: --json must be a JSON object");
+                    std::process::exit(1);
+                }
+            };
+            for (key, value) in map {
+                match skillet::json_to_value(value) {
+                    Ok(v) => { vars.insert(sanitize_json_key(&key), v); }
+                    Err(e) => {
+                        eprintln!("Error converting JSON: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+        in_snapshot(snapshot.as_ref(), || run_bundle(&path, &vars, output_json));
+        return;
+    }
+
     // Measure execution time
     let start_time = Instant::now();
 
-    let result = if let Some(json_str) = json_input {
+    let result = in_snapshot(snapshot.as_ref(), || if let Some(json_str) = json_input {
         // For JSON input, first check if expression contains assignments/sequences
         if expr.contains(";") || expr.contains(":=") {
             // Need to parse JSON and pass to assignment evaluator
@@ -144,6 +202,10 @@ fn main() {
         evaluate_with_assignments(expr, &vars)
     } else {
         evaluate_with_custom(expr, &vars)
+    });
+    let result = match &snapshot {
+        Some(snapshot) => result.map(|v| snapshot.options.apply_precision(v)),
+        None => result,
     };
 
     let execution_time = start_time.elapsed();
@@ -164,6 +226,96 @@ fn main() {
     }
 }
 
+/// Read an engine snapshot and make the global state match it
+fn load_snapshot(path: &str) -> EngineSnapshot {
+    let text = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Error: cannot read {}: {}", path, e);
+        std::process::exit(1);
+    });
+    let snapshot = EngineSnapshot::from_json(&text).and_then(|s| s.restore().map(|_| s)).unwrap_or_else(|e| {
+        eprintln!("Error: {}: {}", path, e);
+        std::process::exit(1);
+    });
+    if snapshot.skillet_version != env!("CARGO_PKG_VERSION") {
+        eprintln!("Warning: snapshot was taken with skillet {}, this is {}", snapshot.skillet_version, env!("CARGO_PKG_VERSION"));
+    }
+    snapshot
+}
+
+/// Run `f` under the options and request id of `snapshot`, if any
+fn in_snapshot<R>(snapshot: Option<&EngineSnapshot>, f: impl FnOnce() -> R) -> R {
+    match snapshot {
+        Some(snapshot) => skillet::context::with_context(snapshot.context(), f),
+        None => f(),
+    }
+}
+
+/// `sk --compile <formulas> <bundle>`: one formula per line, blank lines and `#` comments skipped
+fn compile_bundle(args: &[String]) {
+    let (input, output) = match args {
+        [input, output] => (input, output),
+        _ => {
+            eprintln!("Usage: sk --compile <formulas.txt> <bundle.skb>");
+            std::process::exit(1);
+        }
+    };
+    let text = std::fs::read_to_string(input).unwrap_or_else(|e| {
+        eprintln!("Error: cannot read {}: {}", input, e);
+        std::process::exit(1);
+    });
+
+    let mut formulas = Vec::new();
+    for (line_no, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match skillet::CompiledExpr::compile(line) {
+            Ok(f) => formulas.push(f),
+            Err(e) => {
+                eprintln!("Error: {}:{}: {}", input, line_no + 1, e);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if let Err(e) = std::fs::write(output, skillet::serialize::encode_bundle(&formulas)) {
+        eprintln!("Error: cannot write {}: {}", output, e);
+        std::process::exit(1);
+    }
+    eprintln!("Compiled {} formula(s) into {}", formulas.len(), output);
+}
+
+/// `sk --bundle <bundle>`: evaluate each formula, one result per line
+fn run_bundle(path: &str, vars: &HashMap<String, Value>, output_json: bool) {
+    let bytes = std::fs::read(path).unwrap_or_else(|e| {
+        eprintln!("Error: cannot read {}: {}", path, e);
+        std::process::exit(1);
+    });
+    let formulas = skillet::serialize::decode_bundle(&bytes).unwrap_or_else(|e| {
+        eprintln!("Error: {}: {}", path, e);
+        std::process::exit(2);
+    });
+
+    let mut failed = false;
+    for formula in &formulas {
+        let start_time = Instant::now();
+        match formula.evaluate_with_custom(vars) {
+            Ok(val) if output_json => {
+                println!("{}", format_json_output(&val, start_time.elapsed().as_secs_f64() * 1000.0))
+            }
+            Ok(val) => println!("{} => {:?}", formula.source(), val),
+            Err(e) => {
+                eprintln!("{} => Error: {}", formula.source(), e);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(2);
+    }
+}
+
 fn format_json_output(value: &Value, execution_time_ms: f64) -> String {
     let (result_value, type_name) = match value {
         Value::Number(n) => (json!(n), "Number"),
@@ -181,6 +333,7 @@ fn format_json_output(value: &Value, execution_time_ms: f64) -> String {
                 Value::Null => json!(null),
                 Value::Array(_) => json!(format!("{:?}", v)), // Nested arrays as debug string for now
                 Value::Json(s) => serde_json::from_str(s).unwrap_or_else(|_| json!(s)),
+                Value::Map(_) => v.to_json_value().unwrap_or_else(|e| json!(e.message)),
             }).collect();
             (json!(json_arr), "Array")
         },
@@ -191,6 +344,7 @@ fn format_json_output(value: &Value, execution_time_ms: f64) -> String {
                 Err(_) => (json!(s), "Json")
             }
         }
+        Value::Map(_) => (value.to_json_value().unwrap_or_else(|e| json!(e.message)), "Map"),
     };
 
     let output = json!({
@@ -7,11 +7,17 @@ use std::sync::{Arc, atomic::{AtomicU64, Ordering}};
 use threadpool::ThreadPool;
 
 use http_server::auth::TokenConfig;
+use http_server::canary::handle_canary;
 use http_server::daemon::{setup_signal_handlers, write_pid_file};
-use http_server::eval::{handle_eval_post, handle_eval_get, handle_health, handle_cache_clear};
-use http_server::js_management::{handle_list_js, handle_update_js, handle_delete_js, handle_upload_js, handle_reload_hooks};
+use http_server::eval::{handle_eval_post, handle_eval_get, handle_health, handle_cache_clear, set_builtins_only, set_max_cost, set_allowed_builtins, set_denied_builtins};
+use http_server::health::{handle_healthz, handle_readyz, READINESS};
+use http_server::js_management::{handle_list_functions, handle_list_js, handle_update_js, handle_delete_js, handle_upload_js, handle_reload_hooks, handle_validate_all_hooks};
+use http_server::logging::{self, LogConfig, LogLevel, RotateInterval};
+use http_server::snapshot::handle_snapshot;
 use http_server::stats::ServerStats;
-use http_server::utils::{read_complete_http_request, send_http_response, send_http_error, handle_cors_preflight, load_html_file};
+use http_server::stream::handle_eval_stream;
+use http_server::usage::{handle_metrics, handle_usage, USAGE};
+use http_server::utils::{read_http_head, read_http_body, send_http_response, send_http_error, handle_cors_preflight, load_html_file, resolve_request_id};
 
 #[cfg(unix)]
 use http_server::daemon::daemonize;
@@ -26,27 +32,18 @@ fn handle_http_request(
     server_token: Arc<Option<String>>,
     server_admin_token: Arc<Option<String>>,
 ) {
-    // Read the complete HTTP request properly
-    let request = match read_complete_http_request(&mut stream) {
+    // Read the headers first: /eval-stream consumes its body incrementally
+    let (head, leftover) = match read_http_head(&mut stream) {
+        Ok(parts) => parts,
+        Err(e) => return send_read_error(&mut stream, e),
+    };
+    if head.starts_with("POST /eval-stream ") || head.starts_with("POST /eval-stream?") {
+        handle_eval_stream(&mut stream, &head, leftover, stats, request_counter, server_token, server_admin_token);
+        return;
+    }
+    let request = match read_http_body(&mut stream, head, leftover) {
         Ok(req) => req,
-        Err(e) => {
-            // Log error for debugging but don't panic
-            eprintln!("HTTP request read error: {}", e);
-            // Send proper HTTP error response
-            let error_msg = match e.kind() {
-                std::io::ErrorKind::InvalidData => {
-                    if e.to_string().contains("too large") {
-                        "413 Payload Too Large"
-                    } else {
-                        "400 Bad Request"
-                    }
-                }
-                std::io::ErrorKind::TimedOut => "408 Request Timeout",
-                _ => "500 Internal Server Error",
-            };
-            send_http_error(&mut stream, 400, error_msg);
-            return;
-        }
+        Err(e) => return send_read_error(&mut stream, e),
     };
 
     // Parse HTTP request
@@ -67,23 +64,61 @@ fn handle_http_request(
 
     // Handle paths that might have query parameters
     let path_only = path.split('?').next().unwrap_or(path);
+    let started = std::time::Instant::now();
+    let correlation_id = resolve_request_id(&request);
 
     match (method, path_only) {
         ("GET", "/health") => handle_health(&mut stream, &stats, &request, server_token),
+        ("GET", "/healthz") => handle_healthz(&mut stream),
+        ("GET", "/readyz") => handle_readyz(&mut stream),
         ("GET", "/") => handle_root(&mut stream),
         ("GET", "/docs") => handle_api_docs(&mut stream),
         ("GET", "/openapi.yml") => handle_openapi_spec(&mut stream),
-        ("POST", "/eval") => handle_eval_post(&mut stream, &request, stats, request_counter, server_token),
-        ("GET", "/eval") => handle_eval_get(&mut stream, &request, stats, request_counter, server_token),
+        ("POST", "/eval") => handle_eval_post(&mut stream, &request, stats, request_counter, server_token, server_admin_token, &correlation_id),
+        ("GET", "/eval") => handle_eval_get(&mut stream, &request, stats, request_counter, server_token, &correlation_id),
         ("POST", "/upload-js") => handle_upload_js(&mut stream, &request, server_admin_token),
         ("PUT", "/update-js") => handle_update_js(&mut stream, &request, server_admin_token),
         ("DELETE", "/delete-js") => handle_delete_js(&mut stream, &request, server_admin_token),
         ("GET", "/list-js") => handle_list_js(&mut stream, &request, server_admin_token),
+        ("GET", "/functions") => handle_list_functions(&mut stream, &request, server_token),
         ("POST", "/reload-hooks") => handle_reload_hooks(&mut stream, &request, server_admin_token),
+        ("POST", "/hooks/validate-all") => handle_validate_all_hooks(&mut stream, &request, server_admin_token),
+        ("POST", "/canary") => handle_canary(&mut stream, &request, server_admin_token),
+        ("GET", "/snapshot") => handle_snapshot(&mut stream, &request, server_admin_token),
         ("DELETE", "/cache") => handle_cache_clear(&mut stream, &request, server_admin_token),
+        ("GET", "/usage") => handle_usage(&mut stream, &request, server_token, server_admin_token),
+        ("GET", "/metrics") => handle_metrics(&mut stream, &request, server_admin_token),
         ("OPTIONS", _) => handle_cors_preflight(&mut stream),
         _ => send_http_error(&mut stream, 404, "Not Found"),
     }
+
+    if logging::enabled(LogLevel::Debug) {
+        logging::debug("request", serde_json::json!({
+            "request_id": correlation_id,
+            "method": method,
+            "path": path_only,
+            "duration_us": started.elapsed().as_micros() as u64,
+        }));
+    }
+}
+
+fn send_read_error(stream: &mut TcpStream, e: std::io::Error) {
+    // Log error for debugging but don't panic
+    eprintln!("HTTP request read error: {}", e);
+    logging::warn("HTTP request read error", serde_json::json!({ "error": e.to_string() }));
+    // Send proper HTTP error response
+    let error_msg = match e.kind() {
+        std::io::ErrorKind::InvalidData => {
+            if e.to_string().contains("too large") {
+                "413 Payload Too Large"
+            } else {
+                "400 Bad Request"
+            }
+        }
+        std::io::ErrorKind::TimedOut => "408 Request Timeout",
+        _ => "500 Internal Server Error",
+    };
+    send_http_error(stream, 400, error_msg);
 }
 
 fn handle_root(stream: &mut TcpStream) {
@@ -97,6 +132,7 @@ fn handle_api_docs(stream: &mut TcpStream) {
         Ok(html) => html,
         Err(e) => {
             eprintln!("Error generating documentation: {}", e);
+            logging::error("Error generating documentation", serde_json::json!({ "error": e.to_string() }));
             format!("<!DOCTYPE html><html><head><title>Documentation Error</title></head><body><h1>Error</h1><p>Failed to generate documentation: {}</p></body></html>", e)
         }
     };
@@ -124,7 +160,7 @@ fn main() {
     });
 
     // Parse command line arguments
-    let (mut auth_token, mut admin_token, daemon_mode, pid_file, bind_host, thread_count) = parse_args(&args[2..]);
+    let (mut auth_token, mut admin_token, daemon_mode, pid_file, bind_host, thread_count, log_config) = parse_args(&args[2..]);
 
     // Apply intelligent token logic
     let token_config = TokenConfig::new(auth_token, admin_token);
@@ -136,11 +172,18 @@ fn main() {
         handle_daemon_mode(port, &bind_host, &pid_file, &token_config, thread_count);
     }
 
+    // Open the log file after daemonizing so the descriptor survives the forks
+    if let Err(e) = logging::init(&log_config) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+
     // Setup signal handlers
     let running = setup_signal_handlers();
 
     // Load JavaScript functions
     load_js_functions(daemon_mode);
+    READINESS.warm_js_engine();
 
     // Start server
     let listener = start_server(port, &bind_host);
@@ -154,6 +197,16 @@ fn main() {
 
     // Print startup messages
     print_startup_messages(daemon_mode, port, &bind_host, &auth_token, &admin_token, &token_config, thread_count);
+    logging::info("server started", serde_json::json!({
+        "host": bind_host,
+        "port": port,
+        "threads": thread_count,
+        "daemon": daemon_mode,
+        "pid": std::process::id(),
+        "eval_auth": auth_token.is_some(),
+        "admin_auth": admin_token.is_some(),
+        "version": env!("CARGO_PKG_VERSION"),
+    }));
 
     // Accept loop
     while running.load(Ordering::Relaxed) {
@@ -175,6 +228,7 @@ fn main() {
                 if !daemon_mode {
                     eprintln!("Error accepting connection: {}", e);
                 }
+                logging::error("Error accepting connection", serde_json::json!({ "error": e.to_string() }));
                 std::thread::sleep(std::time::Duration::from_millis(10));
             }
         }
@@ -183,6 +237,7 @@ fn main() {
     if !daemon_mode {
         eprintln!("Server shutdown complete.");
     }
+    logging::info("server shutdown complete", serde_json::json!({}));
 }
 
 fn print_usage() {
@@ -193,9 +248,19 @@ fn print_usage() {
     eprintln!("  -H, --host <addr>    Bind host/interface (default: 127.0.0.1)");
     eprintln!("  -t, --threads <num>  Number of worker threads (default: CPU count)");
     eprintln!("  --pid-file <file>    Write PID to file (default: skillet-http-server.pid)");
-    eprintln!("  --log-file <file>    Write logs to file (daemon mode only)");
+    eprintln!("  --log-file <file>    Write JSON-structured logs to file");
+    eprintln!("  --log-level <level>  Log level: error, warn, info, debug (default: info)");
+    eprintln!("  --log-max-size <mb>  Rotate log file when it exceeds this size in MB (default: 10, 0 = off)");
+    eprintln!("  --log-max-files <n>  Number of rotated log files to keep (default: 5)");
+    eprintln!("  --log-rotate <when>  Time-based rotation: never, hourly, daily (default: never)");
     eprintln!("  --token <value>      Require token for eval requests");
     eprintln!("  --admin-token <val>  Require admin token for JS function management");
+    eprintln!("  --tenant <name:tok>  Accept an extra eval token, accounted as tenant <name> (repeatable)");
+    eprintln!("  --metrics            Expose per-tenant usage counters at GET /metrics (Prometheus)");
+    eprintln!("  --max-cost <score>   Reject expressions whose estimated cost exceeds this score (default: off)");
+    eprintln!("  --builtins-only      Refuse expressions that call custom or JavaScript functions");
+    eprintln!("  --allow-builtins <list>  Comma-separated builtins that may be called; all others are refused");
+    eprintln!("  --deny-builtins <list>   Comma-separated builtins that may not be called (e.g. NOW,RAND)");
     eprintln!("");
     eprintln!("Examples:");
     eprintln!("  sk_http_server 5074");
@@ -203,25 +268,32 @@ fn print_usage() {
     eprintln!("  sk_http_server 5074 --host 0.0.0.0 --token secret123");
     eprintln!("  sk_http_server 5074 --admin-token admin456 --threads 16");
     eprintln!("  sk_http_server 5074 --token secret123 --admin-token admin456");
+    eprintln!("  sk_http_server 5074 --admin-token admin456 --tenant billing:tok1 --tenant search:tok2 --metrics");
     eprintln!("  sk_http_server 5074 -d --pid-file /var/run/skillet-http.pid --threads 12");
     eprintln!("  sk_http_server 5074 -d --host 0.0.0.0 --token secret123 --admin-token admin456");
+    eprintln!("  sk_http_server 5074 -d --log-file /var/log/skillet.log --log-level debug --log-rotate daily");
     eprintln!("");
     eprintln!("Endpoints:");
     eprintln!("  GET  /health          - Health check with cache stats");
+    eprintln!("  GET  /healthz         - Liveness probe");
+    eprintln!("  GET  /readyz          - Readiness probe (hooks, registry, evaluator, JS engine)");
     eprintln!("  GET  /                - API documentation");
     eprintln!("  POST /eval            - Evaluate expressions (JSON)");
     eprintln!("  GET  /eval?expr=...   - Evaluate expressions (query params)");
+    eprintln!("  POST /eval-stream?expr=... - Evaluate per NDJSON line, streaming NDJSON results");
     eprintln!("  DELETE /cache         - Clear expression cache (admin token required)");
+    eprintln!("  GET  /usage           - Per-tenant usage counters (admin or tenant token)");
+    eprintln!("  GET  /metrics         - Prometheus usage metrics (with --metrics, admin token required)");
 }
 
-fn parse_args(args: &[String]) -> (Option<String>, Option<String>, bool, String, String, usize) {
+fn parse_args(args: &[String]) -> (Option<String>, Option<String>, bool, String, String, usize, LogConfig) {
     let mut auth_token: Option<String> = None;
     let mut admin_token: Option<String> = None;
     let mut daemon_mode = false;
     let mut pid_file = "skillet-http-server.pid".to_string();
     let mut bind_host = "127.0.0.1".to_string();
     let mut thread_count = num_cpus::get();
-    let mut _log_file: Option<String> = None;
+    let mut log_config = LogConfig::default();
     let mut i = 0;
 
     while i < args.len() {
@@ -263,13 +335,62 @@ fn parse_args(args: &[String]) -> (Option<String>, Option<String>, bool, String,
             }
             "--log-file" => {
                 if i + 1 < args.len() {
-                    _log_file = Some(args[i + 1].clone());
+                    log_config.path = Some(args[i + 1].clone());
                     i += 1;
                 } else {
                     eprintln!("Error: --log-file requires a filename");
                     std::process::exit(1);
                 }
             }
+            "--log-level" => {
+                if i + 1 < args.len() {
+                    log_config.level = LogLevel::parse(&args[i + 1]).unwrap_or_else(|| {
+                        eprintln!("Error: Invalid log level (expected error, warn, info or debug)");
+                        std::process::exit(1);
+                    });
+                    i += 1;
+                } else {
+                    eprintln!("Error: --log-level requires a level");
+                    std::process::exit(1);
+                }
+            }
+            "--log-max-size" => {
+                if i + 1 < args.len() {
+                    let mb: u64 = args[i + 1].parse().unwrap_or_else(|_| {
+                        eprintln!("Error: Invalid log size");
+                        std::process::exit(1);
+                    });
+                    log_config.max_bytes = mb * 1024 * 1024;
+                    i += 1;
+                } else {
+                    eprintln!("Error: --log-max-size requires a number");
+                    std::process::exit(1);
+                }
+            }
+            "--log-max-files" => {
+                if i + 1 < args.len() {
+                    log_config.max_files = args[i + 1].parse().unwrap_or_else(|_| {
+                        eprintln!("Error: Invalid log file count");
+                        std::process::exit(1);
+                    });
+                    i += 1;
+                } else {
+                    eprintln!("Error: --log-max-files requires a number");
+                    std::process::exit(1);
+                }
+            }
+            "--log-rotate" => {
+                if i + 1 < args.len() {
+                    log_config.rotate = RotateInterval::parse(&args[i + 1]).unwrap_or_else(|| {
+                        eprintln!("Error: Invalid rotation (expected never, hourly or daily)");
+                        std::process::exit(1);
+                    });
+                    i += 1;
+                } else {
+                    eprintln!("Error: --log-rotate requires a value");
+                    std::process::exit(1);
+                }
+            }
             "--token" => {
                 if i + 1 < args.len() {
                     auth_token = Some(args[i + 1].clone());
@@ -279,6 +400,48 @@ fn parse_args(args: &[String]) -> (Option<String>, Option<String>, bool, String,
                     std::process::exit(1);
                 }
             }
+            "--tenant" => {
+                match args.get(i + 1).and_then(|v| v.split_once(':')) {
+                    Some((name, token)) if !name.is_empty() && !token.is_empty() => {
+                        USAGE.add_tenant(name, token);
+                        i += 1;
+                    }
+                    _ => {
+                        eprintln!("Error: --tenant requires a value of the form <name>:<token>");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--metrics" => USAGE.enable_metrics(),
+            "--builtins-only" => set_builtins_only(true),
+            "--allow-builtins" | "--deny-builtins" => {
+                match args.get(i + 1) {
+                    Some(list) => {
+                        let names: Vec<String> = list.split(',').map(|n| n.trim().to_string()).filter(|n| !n.is_empty()).collect();
+                        if args[i] == "--allow-builtins" { set_allowed_builtins(names) } else { set_denied_builtins(names) }
+                        i += 1;
+                    }
+                    None => {
+                        eprintln!("Error: {} requires a comma-separated list of function names", args[i]);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--max-cost" => {
+                if i + 1 < args.len() {
+                    match args[i + 1].parse::<u64>() {
+                        Ok(n) => set_max_cost(n),
+                        Err(_) => {
+                            eprintln!("Error: --max-cost must be a number");
+                            std::process::exit(1);
+                        }
+                    }
+                    i += 1;
+                } else {
+                    eprintln!("Error: --max-cost requires a number");
+                    std::process::exit(1);
+                }
+            }
             "--admin-token" => {
                 if i + 1 < args.len() {
                     admin_token = Some(args[i + 1].clone());
@@ -296,7 +459,7 @@ fn parse_args(args: &[String]) -> (Option<String>, Option<String>, bool, String,
         i += 1;
     }
 
-    (auth_token, admin_token, daemon_mode, pid_file, bind_host, thread_count)
+    (auth_token, admin_token, daemon_mode, pid_file, bind_host, thread_count, log_config)
 }
 
 #[cfg(unix)]
@@ -337,11 +500,15 @@ fn load_js_functions(daemon_mode: bool) {
             if count > 0 && !daemon_mode {
                 eprintln!("Loaded {} custom JavaScript function(s)", count);
             }
+            logging::info("loaded JavaScript functions", serde_json::json!({ "count": count }));
+            READINESS.mark_hooks(Ok(count));
         }
         Err(e) => {
             if !daemon_mode {
                 eprintln!("Warning: Failed to load JavaScript functions: {}", e);
             }
+            logging::warn("Failed to load JavaScript functions", serde_json::json!({ "error": e.to_string() }));
+            READINESS.mark_hooks(Err(e.to_string()));
         }
     }
 }
@@ -0,0 +1,488 @@
+//! Language server for skillet expressions, speaking LSP (JSON-RPC) over stdio.
+//!
+//! Each open document holds one formula. The server publishes parse errors and
+//! calls to unknown functions as diagnostics, completes via [`skillet::complete`],
+//! shows hover docs for functions (custom function descriptions come from the
+//! registry, so JavaScript hooks are documented too), and formats by re-rendering
+//! the parsed expression.
+//!
+//! Variables for completion and hover can be passed as `initializationOptions`:
+//! `{"variables": {"price": 9.99, "tags": ["a"]}}` (sample values; only the type matters).
+
+use serde_json::{json, Value as Json};
+use skillet::lexer::{Lexer, Token};
+use skillet::runtime::function_dispatch::{builtin_category, is_builtin};
+use skillet::{complete, json_to_value, CompletionKind, JSPluginLoader, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+fn print_usage() {
+    eprintln!("Usage: sk_lsp [--hooks DIR]");
+    eprintln!();
+    eprintln!("Language server for skillet expressions over stdin/stdout.");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --hooks DIR   Load JavaScript functions from DIR (default: $SKILLET_HOOKS_DIR or hooks)");
+    eprintln!("  -h, --help    Show this help");
+}
+
+fn main() {
+    let mut hooks_dir = std::env::var("SKILLET_HOOKS_DIR").unwrap_or_else(|_| "hooks".to_string());
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--hooks" => match args.next() {
+                Some(dir) => hooks_dir = dir,
+                None => {
+                    print_usage();
+                    std::process::exit(1);
+                }
+            },
+            "-h" | "--help" => {
+                print_usage();
+                return;
+            }
+            other => {
+                eprintln!("Unknown argument: {}", other);
+                print_usage();
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // stdout carries the protocol, so all logging goes to stderr
+    if let Err(e) = JSPluginLoader::new(hooks_dir).auto_register() {
+        eprintln!("Warning: Failed to load JavaScript functions: {}", e);
+    }
+
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    let mut server = Server::default();
+    loop {
+        let message = match read_message(&mut input) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("sk_lsp: {}", e);
+                break;
+            }
+        };
+        for reply in server.handle(&message) {
+            if write_message(&mut output, &reply).is_err() {
+                return;
+            }
+        }
+        if server.exit {
+            break;
+        }
+    }
+    std::process::exit(if server.shutdown { 0 } else { 1 });
+}
+
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Json>> {
+    let mut content_length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = content_length.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body).map(Some).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message(output: &mut impl Write, message: &Json) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<String, String>,
+    schema: HashMap<String, Value>,
+    shutdown: bool,
+    exit: bool,
+}
+
+impl Server {
+    /// Replies and notifications to send for one incoming message
+    fn handle(&mut self, message: &Json) -> Vec<Json> {
+        let method = message["method"].as_str().unwrap_or("");
+        let params = &message["params"];
+        let id = message.get("id").cloned();
+
+        let result = match method {
+            "initialize" => {
+                if let Some(vars) = params["initializationOptions"]["variables"].as_object() {
+                    self.schema = vars
+                        .iter()
+                        .filter_map(|(k, v)| json_to_value(v.clone()).ok().map(|v| (k.clone(), v)))
+                        .collect();
+                }
+                json!({
+                    "capabilities": {
+                        "textDocumentSync": 1,
+                        "completionProvider": { "triggerCharacters": [":", "."] },
+                        "hoverProvider": true,
+                        "documentFormattingProvider": true,
+                    },
+                    "serverInfo": { "name": "sk_lsp", "version": env!("CARGO_PKG_VERSION") },
+                })
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Json::Null
+            }
+            "exit" => {
+                self.exit = true;
+                return vec![];
+            }
+            "textDocument/didOpen" => {
+                let doc = &params["textDocument"];
+                return self.update(doc["uri"].as_str().unwrap_or(""), doc["text"].as_str().unwrap_or(""));
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change holds the whole text
+                let text = params["contentChanges"].as_array().and_then(|c| c.last()).and_then(|c| c["text"].as_str());
+                return match text {
+                    Some(text) => self.update(params["textDocument"]["uri"].as_str().unwrap_or(""), text),
+                    None => vec![],
+                };
+            }
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or("");
+                self.documents.remove(uri);
+                return vec![publish(uri, vec![])];
+            }
+            "textDocument/completion" => self.with_document(params, |server, text, offset| server.completion(text, offset)),
+            "textDocument/hover" => self.with_document(params, |server, text, offset| server.hover(text, offset)),
+            "textDocument/formatting" => self.with_document(params, |_, text, _| formatting(text)),
+            _ => {
+                // Unknown notifications are ignored; unknown requests get an error
+                return match id {
+                    Some(id) => vec![json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": -32601, "message": format!("Method not found: {}", method) },
+                    })],
+                    None => vec![],
+                };
+            }
+        };
+        match id {
+            Some(id) => vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })],
+            None => vec![],
+        }
+    }
+
+    fn update(&mut self, uri: &str, text: &str) -> Vec<Json> {
+        self.documents.insert(uri.to_string(), text.to_string());
+        vec![publish(uri, diagnostics(text))]
+    }
+
+    fn with_document(&self, params: &Json, f: impl FnOnce(&Self, &str, usize) -> Json) -> Json {
+        let Some(text) = params["textDocument"]["uri"].as_str().and_then(|uri| self.documents.get(uri)) else {
+            return Json::Null;
+        };
+        let position = &params["position"];
+        let offset = position_to_offset(
+            text,
+            position["line"].as_u64().unwrap_or(0) as usize,
+            position["character"].as_u64().unwrap_or(0) as usize,
+        );
+        f(self, text, offset)
+    }
+
+    fn completion(&self, text: &str, offset: usize) -> Json {
+        let completions = complete(text, offset, &self.schema);
+        let range = json!({
+            "start": offset_to_position(text, completions.replace_from),
+            "end": offset_to_position(text, offset),
+        });
+        let items: Vec<Json> = completions
+            .items
+            .into_iter()
+            .map(|c| {
+                let kind = match c.kind {
+                    CompletionKind::Method => 2,
+                    CompletionKind::Function => 3,
+                    CompletionKind::Variable => 6,
+                    CompletionKind::Type => 7,
+                    CompletionKind::Property => 10,
+                    CompletionKind::Keyword => 14,
+                };
+                json!({
+                    "label": c.label,
+                    "kind": kind,
+                    "detail": c.detail,
+                    "textEdit": { "range": range, "newText": c.label },
+                })
+            })
+            .collect();
+        json!({ "isIncomplete": false, "items": items })
+    }
+
+    fn hover(&self, text: &str, offset: usize) -> Json {
+        let bytes = text.as_bytes();
+        let is_word = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+        let mut start = offset.min(bytes.len());
+        while start > 0 && is_word(bytes[start - 1]) {
+            start -= 1;
+        }
+        let mut end = offset.min(bytes.len());
+        while end < bytes.len() && is_word(bytes[end]) {
+            end += 1;
+        }
+        if start == end {
+            return Json::Null;
+        }
+        let word = &text[start..end];
+
+        let markdown = match bytes[..start].last() {
+            Some(b':') => match self.schema.get(word) {
+                Some(value) => format!("`:{}`: {}", word, type_name(value)),
+                None => format!("`:{}`: variable", word),
+            },
+            Some(b'.') => format!("`.{}()`: method", word),
+            _ => match function_docs(&word.to_uppercase()) {
+                Some(docs) => docs,
+                None => return Json::Null,
+            },
+        };
+        json!({
+            "contents": { "kind": "markdown", "value": markdown },
+            "range": { "start": offset_to_position(text, start), "end": offset_to_position(text, end) },
+        })
+    }
+}
+
+fn publish(uri: &str, diagnostics: Vec<Json>) -> Json {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+fn diagnostic(text: &str, start: usize, end: usize, severity: u8, message: &str) -> Json {
+    json!({
+        "range": { "start": offset_to_position(text, start), "end": offset_to_position(text, end) },
+        "severity": severity,
+        "source": "skillet",
+        "message": message,
+    })
+}
+
+fn diagnostics(text: &str) -> Vec<Json> {
+    if text.trim().is_empty() {
+        return vec![];
+    }
+    if let Err(e) = skillet::parse(text) {
+        let start = e.position.unwrap_or(0).min(text.len());
+        let end = text[start..].chars().next().map_or(start, |c| start + c.len_utf8());
+        return vec![diagnostic(text, start, end, 1, &e.message)];
+    }
+
+    // Calls to functions that are neither builtins nor registered
+    let registry = skillet::registry_snapshot();
+    let tokens = lex(text);
+    let mut out = Vec::new();
+    for (i, (token, start, end)) in tokens.iter().enumerate() {
+        let Token::Identifier(name) = token else { continue };
+        let is_call = matches!(tokens.get(i + 1), Some((Token::LParen, _, _)));
+        let is_method = i > 0 && matches!(tokens[i - 1].0, Token::Dot | Token::SafeNavigation | Token::QuestionDot);
+        let upper = name.to_uppercase();
+        if is_call && !is_method && !is_builtin(&upper) && !registry.has_function(&upper) {
+            out.push(diagnostic(text, *start, *end, 2, &format!("Unknown function: {}", upper)));
+        }
+    }
+    out
+}
+
+fn lex(text: &str) -> Vec<(Token, usize, usize)> {
+    let mut lexer = Lexer::new(text);
+    let mut tokens = Vec::new();
+    while let Ok(token) = lexer.next_token() {
+        if token == Token::Eof {
+            break;
+        }
+        tokens.push((token, lexer.last_start(), lexer.last_end()));
+    }
+    tokens
+}
+
+/// Whole-document edit with the canonical rendering. Documents with comments or
+/// parse errors are left alone, since rendering would drop the comments.
+fn formatting(text: &str) -> Json {
+    let Ok(expr) = skillet::parse(text) else { return json!([]) };
+    let mut previous_end = 0;
+    for (_, start, end) in lex(text) {
+        let gap = text.get(previous_end..start).unwrap_or("");
+        if !gap.trim().is_empty() && gap.trim() != "=" {
+            return json!([]);
+        }
+        previous_end = end;
+    }
+    if !text[previous_end..].trim().is_empty() {
+        return json!([]);
+    }
+
+    let prefix = if text.trim_start().starts_with('=') { "=" } else { "" };
+    let formatted = format!("{}{}", prefix, expr);
+    if formatted == text {
+        return json!([]);
+    }
+    json!([{
+        "range": { "start": offset_to_position(text, 0), "end": offset_to_position(text, text.len()) },
+        "newText": formatted,
+    }])
+}
+
+fn function_docs(name: &str) -> Option<String> {
+    let registry = skillet::registry_snapshot();
+    if let Some(function) = registry.get(name) {
+        let arity = match function.max_args() {
+            Some(max) if max == function.min_args() => format!("{} argument(s)", max),
+            Some(max) => format!("{} to {} arguments", function.min_args(), max),
+            None => format!("at least {} argument(s)", function.min_args()),
+        };
+        let mut docs = format!("**{}** (custom function, {})", name, arity);
+        if let Some(description) = function.description() {
+            docs.push_str(&format!("\n\n{}", description));
+        }
+        if let Some(example) = function.example() {
+            docs.push_str(&format!("\n\nExample: `{}`", example));
+        }
+        return Some(docs);
+    }
+    if !is_builtin(name) || name.starts_with("__") {
+        return None;
+    }
+    Some(match builtin_category(name) {
+        Some(category) => format!("**{}** (builtin {} function)", name, category),
+        None => format!("**{}** (builtin function)", name),
+    })
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Number(_) => "Number",
+        Value::Currency(_) => "Currency",
+        Value::String(_) => "String",
+        Value::Boolean(_) => "Boolean",
+        Value::Array(_) => "Array",
+        Value::Json(_) => "Json",
+        Value::Map(_) => "Map",
+        Value::DateTime(_) => "DateTime",
+        Value::Null => "Null",
+    }
+}
+
+/// LSP positions count UTF-16 code units within a line
+fn offset_to_position(text: &str, offset: usize) -> Json {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+    json!({ "line": line, "character": character })
+}
+
+fn position_to_offset(text: &str, line: usize, character: usize) -> usize {
+    let line_start = if line == 0 {
+        0
+    } else {
+        match text.match_indices('\n').nth(line - 1) {
+            Some((i, _)) => i + 1,
+            None => return text.len(),
+        }
+    };
+    let mut units = 0;
+    for (i, c) in text[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(id: u64, method: &str, params: Json) -> Json {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+
+    fn open(server: &mut Server, text: &str) -> Vec<Json> {
+        server.handle(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": "file:///f.sk", "text": text } },
+        }))
+    }
+
+    #[test]
+    fn test_positions_round_trip() {
+        let text = "1 +\n'é😀' + :x";
+        assert_eq!(offset_to_position(text, 4), json!({ "line": 1, "character": 0 }));
+        assert_eq!(offset_to_position(text, text.len()), json!({ "line": 1, "character": 10 }));
+        assert_eq!(position_to_offset(text, 1, 10), text.len());
+        assert_eq!(position_to_offset(text, 1, 4), 4 + 1 + 2 + 4);
+        assert_eq!(position_to_offset(text, 9, 0), text.len());
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let mut server = Server::default();
+        let out = open(&mut server, "1 + (2");
+        assert_eq!(out[0]["params"]["diagnostics"][0]["severity"], 1);
+
+        let out = open(&mut server, "SUM(1) + NOPE(2)");
+        let diagnostics = out[0]["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0]["message"], "Unknown function: NOPE");
+        assert_eq!(diagnostics[0]["range"]["start"]["character"], 9);
+    }
+
+    #[test]
+    fn test_completion_hover_and_formatting() {
+        let mut server = Server::default();
+        server.handle(&request(1, "initialize", json!({ "initializationOptions": { "variables": { "tags": ["a"] } } })));
+        open(&mut server, ":tags.so");
+        let doc = json!({ "uri": "file:///f.sk" });
+
+        let reply = server.handle(&request(2, "textDocument/completion", json!({ "textDocument": doc, "position": { "line": 0, "character": 8 } })));
+        let items = reply[0]["result"]["items"].as_array().unwrap();
+        assert_eq!(items[0]["label"], "sort");
+        assert_eq!(items[0]["textEdit"]["range"]["start"]["character"], 6);
+
+        let reply = server.handle(&request(3, "textDocument/hover", json!({ "textDocument": doc, "position": { "line": 0, "character": 2 } })));
+        assert_eq!(reply[0]["result"]["contents"]["value"], "`:tags`: Array");
+
+        open(&mut server, "=sum( 1,2 )*3");
+        let reply = server.handle(&request(4, "textDocument/hover", json!({ "textDocument": doc, "position": { "line": 0, "character": 2 } })));
+        assert!(reply[0]["result"]["contents"]["value"].as_str().unwrap().contains("arithmetic"));
+        let reply = server.handle(&request(5, "textDocument/formatting", json!({ "textDocument": doc, "options": {} })));
+        assert_eq!(reply[0]["result"][0]["newText"], "=SUM(1, 2) * 3");
+
+        open(&mut server, "1+2 // keep me");
+        let reply = server.handle(&request(6, "textDocument/formatting", json!({ "textDocument": doc, "options": {} })));
+        assert_eq!(reply[0]["result"], json!([]));
+
+        let reply = server.handle(&request(7, "workspace/symbol", json!({})));
+        assert_eq!(reply[0]["error"]["code"], -32601);
+    }
+}
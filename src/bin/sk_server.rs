@@ -179,6 +179,7 @@ fn process_request(req: EvalRequest, request_id: u64) -> EvalResponse {
                             Value::Null => serde_json::json!(null),
                             Value::Array(_) => serde_json::json!(format!("{:?}", v)),
                             Value::Json(s) => serde_json::from_str(&s).unwrap_or_else(|_| serde_json::json!(s)),
+                            Value::Map(_) => v.to_json_value().unwrap_or_else(|e| serde_json::json!(e.message)),
                         }).collect();
                         (serde_json::json!(json_arr), "Array")
                     },
@@ -189,6 +190,7 @@ fn process_request(req: EvalRequest, request_id: u64) -> EvalResponse {
                             Err(_) => (serde_json::json!(s), "Json")
                         }
                     }
+                    Value::Map(_) => (val.to_json_value().unwrap_or_else(|e| serde_json::json!(e.message)), "Map"),
                 };
                 
                 serde_json::json!({
@@ -214,11 +216,13 @@ fn process_request(req: EvalRequest, request_id: u64) -> EvalResponse {
                             Value::Null => serde_json::json!(null),
                             Value::Array(_) => serde_json::json!(format!("{:?}", v)),
                             Value::Json(s) => serde_json::from_str(&s).unwrap_or_else(|_| serde_json::json!(s)),
+                            Value::Map(_) => v.to_json_value().unwrap_or_else(|e| serde_json::json!(e.message)),
                         }).collect();
                         serde_json::json!(json_arr)
                     },
                     Value::Null => serde_json::json!(null),
                     Value::Json(s) => serde_json::from_str(&s).unwrap_or_else(|_| serde_json::json!(s)),
+                    Value::Map(_) => val.to_json_value().unwrap_or_else(|e| serde_json::json!(e.message)),
                 }
             };
             
@@ -0,0 +1,103 @@
+//! Canary evaluation: the same formulas under two sets of [`EvalOptions`], with a
+//! report of where they disagree. Meant for rolling out a semantic change (strict
+//! mode, null propagation, a tolerance) across stored formulas: run them under the
+//! current and the proposed options and review the differences first.
+//!
+//! Outcomes are compared like [corpus](crate::corpus) expectations: results as JSON
+//! with numbers equal within a relative 1e-9, currency and dates also by type, and
+//! errors by their message. A formula that fails under one configuration only is a
+//! difference.
+
+use std::collections::HashMap;
+
+use crate::corpus::{describe, evaluate_case, json_matches, outcome, Expected};
+use crate::error::Error;
+use crate::options::EvalOptions;
+use crate::types::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanaryDifference {
+    /// Position of the formula in the input
+    pub index: usize,
+    pub expression: String,
+    pub baseline: Expected,
+    pub candidate: Expected,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CanaryReport {
+    /// Formulas with the same outcome under both configurations
+    pub agreed: usize,
+    pub differences: Vec<CanaryDifference>,
+}
+
+impl CanaryReport {
+    pub fn is_success(&self) -> bool {
+        self.differences.is_empty()
+    }
+
+    /// One line per difference: `#N: expr: baseline X, candidate Y`
+    pub fn summary(&self) -> String {
+        let mut out = format!("{} agreed, {} differ", self.agreed, self.differences.len());
+        for d in &self.differences {
+            out.push_str(&format!(
+                "\n#{}: {}: baseline {}, candidate {}",
+                d.index,
+                d.expression,
+                describe(&d.baseline),
+                describe(&d.candidate)
+            ));
+        }
+        out
+    }
+}
+
+/// Evaluate every `(expression, variables)` pair under `baseline` and under
+/// `candidate` and report the formulas whose outcome changes. Formulas run like
+/// [`evaluate_with_assignments`](crate::evaluate_with_assignments) with custom
+/// functions; the request id of an enclosing [`EvalContext`](crate::EvalContext) is
+/// kept. Invalid options are an error, before anything is evaluated.
+pub fn evaluate_canary(
+    cases: &[(&str, HashMap<String, Value>)],
+    baseline: &EvalOptions,
+    candidate: &EvalOptions,
+) -> Result<CanaryReport, Error> {
+    baseline.validate()?;
+    candidate.validate()?;
+    let mut report = CanaryReport::default();
+    for (index, (expression, variables)) in cases.iter().enumerate() {
+        let before = outcome(evaluate_under(expression, variables, baseline));
+        let after = outcome(evaluate_under(expression, variables, candidate));
+        if same_outcome(&before, &after) {
+            report.agreed += 1;
+        } else {
+            report.differences.push(CanaryDifference {
+                index,
+                expression: expression.to_string(),
+                baseline: before,
+                candidate: after,
+            });
+        }
+    }
+    Ok(report)
+}
+
+fn evaluate_under(expression: &str, variables: &HashMap<String, Value>, options: &EvalOptions) -> Result<Value, Error> {
+    if options.builtins_only {
+        crate::analysis::check_builtins_only(&crate::parse(expression)?)?;
+    }
+    let mut ctx = crate::context::current().map(|c| (*c).clone()).unwrap_or_default();
+    ctx.options = options.clone();
+    let value = crate::context::with_context(ctx, || evaluate_case(expression, variables))?;
+    Ok(options.apply_precision(value))
+}
+
+fn same_outcome(a: &Expected, b: &Expected) -> bool {
+    match (a, b) {
+        (Expected::Error(a), Expected::Error(b)) => a == b,
+        (Expected::Value { value: a, type_name: ta }, Expected::Value { value: b, type_name: tb }) => {
+            ta == tb && json_matches(a, b)
+        }
+        _ => false,
+    }
+}
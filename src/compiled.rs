@@ -0,0 +1,49 @@
+//! Parsed expressions that can be shared across threads.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::ast::Expr;
+use crate::error::Error;
+use crate::types::Value;
+
+/// An expression parsed once and evaluated many times.
+///
+/// Cloning is cheap (the AST is behind an `Arc`) and the type is `Send + Sync`,
+/// so a single instance can be stored in shared server state and evaluated
+/// concurrently from any number of workers.
+#[derive(Debug, Clone)]
+pub struct CompiledExpr {
+    source: Arc<str>,
+    expr: Arc<Expr>,
+}
+
+impl CompiledExpr {
+    /// Parse `input` (optional leading `=`) into a reusable expression
+    pub fn compile(input: &str) -> Result<Self, Error> {
+        let expr = crate::parse(input)?;
+        Ok(Self { source: Arc::from(input), expr: Arc::new(expr) })
+    }
+
+    pub(crate) fn from_parts(source: String, expr: Expr) -> Self {
+        Self { source: Arc::from(source), expr: Arc::new(expr) }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    /// Evaluate with built-in functions only
+    pub fn evaluate(&self, vars: &HashMap<String, Value>) -> Result<Value, Error> {
+        crate::runtime::evaluator::eval_with_vars(&self.expr, vars)
+    }
+
+    /// Evaluate with custom functions from the global registry
+    pub fn evaluate_with_custom(&self, vars: &HashMap<String, Value>) -> Result<Value, Error> {
+        crate::runtime::evaluator::eval_with_vars_and_custom(&self.expr, vars, &crate::registry_snapshot())
+    }
+}
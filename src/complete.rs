@@ -0,0 +1,413 @@
+//! Completion candidates for formula editors.
+//!
+//! [`complete`] lexes the text before the cursor and decides what kind of name is
+//! being typed: a variable after `:`, a method or property after `.` / `&.`, a type
+//! after `::`, or a function anywhere an operand can start. For methods the
+//! receiver (everything the `.` applies to) is parsed on its own and its type
+//! inferred from the schema, so `:tags.` offers array methods and `:name.` string
+//! methods. Text that does not lex, and positions inside strings or comments, get
+//! no candidates.
+
+use std::collections::HashMap;
+
+use crate::ast::{BinaryOp, Expr, TypeName};
+use crate::lexer::{Lexer, Token};
+use crate::runtime::function_dispatch::{builtin_function_names, is_arithmetic_builtin};
+use crate::runtime::method_calls::{
+    ARRAY_METHODS, CONVERSION_METHODS, JSON_METHODS, LAMBDA_METHODS, NUMBER_METHODS, PREDICATES, STRING_METHODS,
+};
+use crate::types::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Variable,
+    Function,
+    Method,
+    /// Key of a JSON object
+    Property,
+    Type,
+    Keyword,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    /// Text to insert in place of `input[replace_from..cursor]`
+    pub label: String,
+    pub kind: CompletionKind,
+    /// Type of a variable or property, or a custom function's description
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Completions {
+    /// Start of the partial name before the cursor
+    pub replace_from: usize,
+    /// Candidates starting with the partial name (case-insensitive), sorted by label
+    pub items: Vec<Completion>,
+}
+
+/// Completion candidates at byte offset `cursor` of `input`.
+///
+/// `schema` maps variable names to sample values; only their types matter,
+/// except JSON samples whose keys are offered as properties.
+pub fn complete(input: &str, cursor: usize, schema: &HashMap<String, Value>) -> Completions {
+    let mut cursor = cursor.min(input.len());
+    while !input.is_char_boundary(cursor) {
+        cursor -= 1;
+    }
+    let text = &input[..cursor];
+    let Some(tokens) = lex(text) else { return Completions { replace_from: cursor, ..Default::default() } };
+
+    // The partial name under the cursor, if any
+    let (word, word_start, context) = match tokens.last() {
+        Some(last) if last.end == cursor && is_word(&text[last.start..last.end]) => {
+            (&text[last.start..], last.start, tokens.len() - 1)
+        }
+        Some(last) if !text[last.end..].trim().is_empty() => {
+            // Inside a comment
+            return Completions { replace_from: cursor, ..Default::default() };
+        }
+        _ => ("", cursor, tokens.len()),
+    };
+    let before = context.checked_sub(1).map(|i| &tokens[i]);
+
+    let mut items = Vec::new();
+    match before.map(|t| &t.token) {
+        Some(Token::Colon) if before.is_some_and(|t| t.end == word_start) => {
+            variables(schema, &mut items);
+            lambda_parameters(&tokens[..context - 1], &mut items);
+        }
+        Some(Token::Dot | Token::SafeNavigation | Token::QuestionDot) => {
+            // A leading `.` has no receiver, so there are no members to offer
+            if let Some(last) = context.checked_sub(2) {
+                let receiver = chain_start(&tokens, last)
+                    .and_then(|first| crate::parse(&text[tokens[first].start..tokens[context - 1].start]).ok());
+                match receiver {
+                    Some(expr) => members(&expr, schema, &mut items),
+                    None => members_of(Kind::Unknown, &mut items),
+                }
+            }
+        }
+        Some(Token::DoubleColon) => {
+            for ty in ["Integer", "Float", "String", "Boolean", "Array", "Currency", "DateTime", "Json"] {
+                items.push(item(ty, CompletionKind::Type, None));
+            }
+        }
+        Some(t) if ends_operand(t) => {}
+        _ => {
+            for name in builtin_function_names() {
+                items.push(item(name, CompletionKind::Function, None));
+            }
+            let registry = crate::registry_snapshot();
+            for name in registry.list_functions() {
+                let detail = registry.get(name).and_then(|f| f.description()).map(str::to_string);
+                items.push(item(&name.to_uppercase(), CompletionKind::Function, detail));
+            }
+            for keyword in ["TRUE", "FALSE", "NULL"] {
+                items.push(item(keyword, CompletionKind::Keyword, None));
+            }
+        }
+    }
+
+    let lower = word.to_lowercase();
+    items.retain(|c| c.label.to_lowercase().starts_with(&lower));
+    items.sort_by(|a, b| a.label.cmp(&b.label));
+    items.dedup_by(|a, b| a.label == b.label);
+    Completions { replace_from: word_start, items }
+}
+
+struct Lexed {
+    token: Token,
+    start: usize,
+    end: usize,
+}
+
+fn lex(text: &str) -> Option<Vec<Lexed>> {
+    let mut lexer = Lexer::new(text);
+    let mut tokens = Vec::new();
+    loop {
+        match lexer.next_token().ok()? {
+            Token::Eof => return Some(tokens),
+            token => tokens.push(Lexed { token, start: lexer.last_start(), end: lexer.last_end() }),
+        }
+    }
+}
+
+/// Identifiers, including ones that lex as keywords while still being typed (`TRUE`, `OR`)
+fn is_word(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') && s.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
+fn ends_operand(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Number(_)
+            | Token::String(_)
+            | Token::DateTime(_)
+            | Token::Identifier(_)
+            | Token::True
+            | Token::False
+            | Token::Null
+            | Token::RParen
+            | Token::RBracket
+            | Token::RBrace
+    )
+}
+
+/// Index of the first token of the postfix chain (`:a.b(1)[0]`) ending at `last`
+fn chain_start(tokens: &[Lexed], last: usize) -> Option<usize> {
+    let mut k = last;
+    loop {
+        match &tokens.get(k)?.token {
+            Token::RParen | Token::RBracket | Token::RBrace => {
+                k = matching_open(tokens, k)?;
+                let call_or_index = k > 0
+                    && match tokens[k].token {
+                        Token::LParen => matches!(tokens[k - 1].token, Token::Identifier(_)),
+                        Token::LBracket => ends_operand(&tokens[k - 1].token),
+                        _ => false,
+                    };
+                if call_or_index {
+                    k -= 1;
+                    continue;
+                }
+            }
+            Token::Identifier(_) if k > 0 && matches!(tokens[k - 1].token, Token::Colon) => k -= 1,
+            t if ends_operand(t) => {}
+            _ => return None,
+        }
+        // Continue through `.`, `&.`, `?.` and `::`
+        match k.checked_sub(1).map(|i| &tokens[i].token) {
+            Some(Token::Dot | Token::SafeNavigation | Token::QuestionDot | Token::DoubleColon) if k >= 2 => k -= 2,
+            _ => return Some(k),
+        }
+    }
+}
+
+fn matching_open(tokens: &[Lexed], close: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for i in (0..=close).rev() {
+        match tokens[i].token {
+            Token::RParen | Token::RBracket | Token::RBrace => depth += 1,
+            Token::LParen | Token::LBracket | Token::LBrace => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `:x` (and `:acc` for reduce) inside the arguments of an unclosed lambda call
+fn lambda_parameters(tokens: &[Lexed], items: &mut Vec<Completion>) {
+    let mut depth = 0usize;
+    for i in (0..tokens.len()).rev() {
+        match tokens[i].token {
+            Token::RParen | Token::RBracket | Token::RBrace => depth += 1,
+            Token::LParen | Token::LBracket | Token::LBrace if depth > 0 => depth -= 1,
+            Token::LParen if i > 0 => {
+                if let Token::Identifier(name) = &tokens[i - 1].token {
+                    let name = name.to_lowercase();
+                    if matches!(name.as_str(), "filter" | "map" | "find" | "reduce" | "partition" | "split_when" | "unique_by" | "sort_by" | "sumif" | "avgif" | "countif" | "distinctby" | "joinon" | "firstmatch") {
+                        items.push(item("x", CompletionKind::Variable, Some("lambda parameter".to_string())));
+                    }
+                    if name == "reduce" {
+                        items.push(item("acc", CompletionKind::Variable, Some("lambda parameter".to_string())));
+                    }
+                    if name == "split_when" {
+                        items.push(item("prev", CompletionKind::Variable, Some("lambda parameter".to_string())));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn variables(schema: &HashMap<String, Value>, items: &mut Vec<Completion>) {
+    for (name, value) in schema {
+        items.push(item(name, CompletionKind::Variable, Some(Kind::of(value).name().to_string())));
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Number,
+    String,
+    Boolean,
+    Array,
+    Json,
+    DateTime,
+    Null,
+    Unknown,
+}
+
+impl Kind {
+    fn of(value: &Value) -> Kind {
+        match value {
+            Value::Number(_) | Value::Currency(_) => Kind::Number,
+            Value::String(_) => Kind::String,
+            Value::Boolean(_) => Kind::Boolean,
+            Value::Array(_) => Kind::Array,
+            Value::Json(_) | Value::Map(_) => Kind::Json,
+            Value::DateTime(_) => Kind::DateTime,
+            Value::Null => Kind::Null,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Number => "Number",
+            Kind::String => "String",
+            Kind::Boolean => "Boolean",
+            Kind::Array => "Array",
+            Kind::Json => "Json",
+            Kind::DateTime => "DateTime",
+            Kind::Null => "Null",
+            Kind::Unknown => "Unknown",
+        }
+    }
+}
+
+/// Builtins whose result type is known without evaluating them
+const ARRAY_BUILTINS: &[&str] = &["ARRAY", "FILTER", "MAP", "SORT", "UNIQUE", "FLATTEN", "SPLIT", "SPLITLINES", "KEYS", "VALUES", "MODE.MULT", "MODEMULT", "MODE_MULT", "AMORTIZE", "TOKENIZE", "NGRAMS", "OFFSETLIMIT", "HISTOGRAM", "ZSCORE", "MINMAXSCALE", "WINSORIZE", "MOVINGAVG", "EMA", "JOINON", "DISTINCTBY", "ORDERBY", "FUNCTIONS", "RANGE"];
+const STRING_BUILTINS: &[&str] = &["CONCAT", "UPPER", "LOWER", "TRIM", "TRIMSTART", "TRIMEND", "REPT", "SUBSTRING", "REPLACE", "LEFT", "RIGHT", "MID", "JOIN", "FORMAT", "SOUNDEX", "METAPHONE", "MASK", "REDACT", "REDACTEMAIL"];
+
+fn infer(expr: &Expr, schema: &HashMap<String, Value>) -> Kind {
+    match expr {
+        Expr::Number(_) => Kind::Number,
+        Expr::StringLit(_) => Kind::String,
+        Expr::Null => Kind::Null,
+        Expr::Array(_) => Kind::Array,
+        Expr::ObjectLiteral(_) => Kind::Json,
+        Expr::Variable(_) | Expr::PropertyAccess { .. } | Expr::SafePropertyAccess { .. } => {
+            sample(expr, schema).map_or(Kind::Unknown, |v| Kind::of(&v))
+        }
+        Expr::Unary(crate::ast::UnaryOp::Not, _) => Kind::Boolean,
+        Expr::Unary(_, _) => Kind::Number,
+        Expr::Binary(l, op, r) => match op {
+            BinaryOp::Add if infer(l, schema) == Kind::String || infer(r, schema) == Kind::String => Kind::String,
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod | BinaryOp::Pow => {
+                Kind::Number
+            }
+            _ => Kind::Boolean,
+        },
+        Expr::TypeCast { ty, .. } => match ty {
+            TypeName::Integer | TypeName::Float | TypeName::Currency => Kind::Number,
+            TypeName::String => Kind::String,
+            TypeName::Boolean => Kind::Boolean,
+            TypeName::Array => Kind::Array,
+            TypeName::DateTime => Kind::DateTime,
+            TypeName::Json => Kind::Json,
+        },
+        Expr::FunctionCall { name, args } => match name.as_str() {
+            "__TERNARY__" if args.len() == 3 => {
+                let kind = infer(&args[1], schema);
+                if kind == infer(&args[2], schema) { kind } else { Kind::Unknown }
+            }
+            "IFERROR" if args.len() == 2 => {
+                let kind = infer(&args[0], schema);
+                if kind == infer(&args[1], schema) { kind } else { Kind::Unknown }
+            }
+            "__CONST_TRUE__" | "__CONST_FALSE__" => Kind::Boolean,
+            "__RANGE__" | "__RANGE_INCLUSIVE__" => Kind::Array,
+            "WHY" | "TRY" => Kind::Json,
+            "ISFUNCTION" => Kind::Boolean,
+            "LET" => args.last().map_or(Kind::Unknown, |body| infer(body, schema)),
+            "SWITCH" if args.len() >= 3 => {
+                // Results sit at even positions after the value, plus an optional default
+                let mut results = args.iter().skip(2).step_by(2).chain(args.len().is_multiple_of(2).then(|| &args[args.len() - 1]));
+                let kind = results.next().map_or(Kind::Unknown, |e| infer(e, schema));
+                if results.all(|e| infer(e, schema) == kind) { kind } else { Kind::Unknown }
+            }
+            n if ARRAY_BUILTINS.contains(&n) => Kind::Array,
+            n if STRING_BUILTINS.contains(&n) => Kind::String,
+            n if is_arithmetic_builtin(n) => Kind::Number,
+            _ => Kind::Unknown,
+        },
+        Expr::Slice { target, .. } => match infer(target, schema) {
+            Kind::String => Kind::String,
+            _ => Kind::Array,
+        },
+        Expr::MethodCall { predicate: true, .. } => Kind::Boolean,
+        Expr::MethodCall { target, name, .. } | Expr::SafeMethodCall { target, name, .. } => {
+            method_result(infer(target, schema), &name.to_lowercase())
+        }
+        Expr::Sequence(items) => items.last().map_or(Kind::Unknown, |e| infer(e, schema)),
+        Expr::Assignment { value, .. } | Expr::Destructure { value, .. } => infer(value, schema),
+        Expr::Index { .. } | Expr::Spread(_) | Expr::Lambda { .. } => Kind::Unknown,
+    }
+}
+
+fn method_result(receiver: Kind, name: &str) -> Kind {
+    match name {
+        "to_s" | "to_string" | "join" | "upper" | "upcase" | "lower" | "downcase" | "trim" | "trim_start" | "trim_end"
+        | "replace" | "substring" | "substr" => Kind::String,
+        "to_i" | "to_int" | "to_f" | "to_float" | "to_currency" | "length" | "len" | "count" | "size" | "sum" | "avg"
+        | "average" | "abs" | "ceil" | "ceiling" | "floor" | "round" | "sqrt" | "sin" | "cos" | "tan" | "int" => {
+            Kind::Number
+        }
+        "min" | "max" if receiver == Kind::Array => Kind::Number,
+        "to_a" | "to_array" | "split" | "unique" | "sort" | "flatten" | "compact" | "merge" | "filter" | "map"
+        | "partition" | "split_when" | "unique_by" | "sort_by" | "keys" | "values" | "push" | "insert" | "remove_at" | "replace_at" | "set" => Kind::Array,
+        "reverse" => receiver,
+        "to_json" | "tally" => Kind::Json,
+        "to_bool" | "to_boolean" | "includes" | "contains" | "startswith" | "starts_with" | "endswith" | "ends_with"
+        | "has" | "has_key" | "between" => Kind::Boolean,
+        _ => Kind::Unknown,
+    }
+}
+
+/// The schema's sample for a variable or a property path on one
+fn sample(expr: &Expr, schema: &HashMap<String, Value>) -> Option<Value> {
+    match expr {
+        Expr::Variable(name) => schema.get(name).cloned(),
+        Expr::PropertyAccess { target, property } | Expr::SafePropertyAccess { target, property } => {
+            match sample(target, schema)? {
+                Value::Json(s) => {
+                    let json: serde_json::Value = serde_json::from_str(&s).ok()?;
+                    crate::json_to_value(json.get(property)?.clone()).ok()
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn members(receiver: &Expr, schema: &HashMap<String, Value>, items: &mut Vec<Completion>) {
+    let kind = infer(receiver, schema);
+    if let Some(Value::Json(s)) = sample(receiver, schema) {
+        if let Ok(serde_json::Value::Object(map)) = serde_json::from_str::<serde_json::Value>(&s) {
+            for (key, value) in map {
+                let detail = crate::json_to_value(value).ok().map(|v| Kind::of(&v).name().to_string());
+                items.push(item(&key, CompletionKind::Property, detail));
+            }
+        }
+    }
+    members_of(kind, items);
+}
+
+fn members_of(kind: Kind, items: &mut Vec<Completion>) {
+    let groups: &[&[&str]] = match kind {
+        Kind::Number => &[NUMBER_METHODS],
+        Kind::String => &[STRING_METHODS],
+        Kind::Array => &[ARRAY_METHODS, LAMBDA_METHODS],
+        Kind::Json => &[JSON_METHODS],
+        Kind::Boolean | Kind::DateTime | Kind::Null => &[],
+        Kind::Unknown => &[NUMBER_METHODS, STRING_METHODS, ARRAY_METHODS, LAMBDA_METHODS, JSON_METHODS],
+    };
+    for name in groups.iter().copied().flatten().chain(CONVERSION_METHODS) {
+        items.push(item(name, CompletionKind::Method, None));
+    }
+    for name in PREDICATES {
+        items.push(item(&format!("{}?", name), CompletionKind::Method, None));
+    }
+}
+
+fn item(label: &str, kind: CompletionKind, detail: Option<String>) -> Completion {
+    Completion { label: label.to_string(), kind, detail }
+}
@@ -66,7 +66,9 @@ impl ConcurrentFunctionRegistry {
                     }
                 }
                 
-                function.execute(args)
+                crate::context::timed_call(function.budget_category(), || {
+                    function.execute_with_context(args, &crate::context::CallContext::new())
+                })
             }
             None => Err(Error::new(format!("Unknown custom function: {}", name), None)),
         }
@@ -161,7 +163,9 @@ impl ThreadLocalFunctionCache {
                     }
                 }
                 
-                function.execute(args)
+                crate::context::timed_call(function.budget_category(), || {
+                    function.execute_with_context(args, &crate::context::CallContext::new())
+                })
             }
             None => Err(Error::new(format!("Unknown custom function: {}", name), None)),
         }
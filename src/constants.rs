@@ -0,0 +1,105 @@
+//! Read-only constants supplied by the host (deployment region, feature flags,
+//! tax tables) and visible to every evaluation without being merged into each
+//! request's variables.
+//!
+//! Expressions read a constant with `CONST("TAX_RATE")`, or all of them through the
+//! reserved variable `:ENV`, a JSON object (`:ENV.region`). Like the function
+//! registry, the set is published as immutable snapshots, so updating it never
+//! blocks evaluations in flight.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+use crate::error::Error;
+use crate::types::Value;
+
+/// Name of the variable exposing every constant; it cannot be assigned or shadowed
+pub const ENV: &str = "ENV";
+
+#[derive(Debug, Clone, Default)]
+struct Constants {
+    values: HashMap<String, Value>,
+    /// `:ENV`, rendered once per update
+    env: String,
+}
+
+impl Constants {
+    fn with_values(values: HashMap<String, Value>) -> Self {
+        let object: serde_json::Map<String, serde_json::Value> = values
+            .iter()
+            // Values are checked to convert before they are stored
+            .filter_map(|(k, v)| to_json(v).ok().map(|j| (k.clone(), j)))
+            .collect();
+        let env = serde_json::Value::Object(object).to_string();
+        Self { values, env }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref CONSTANTS: ArcSwap<Constants> = ArcSwap::from_pointee(Constants::with_values(HashMap::new()));
+}
+
+fn to_json(value: &Value) -> Result<serde_json::Value, Error> {
+    crate::runtime::method_calls::conversion_methods::value_to_json_value(value)
+}
+
+fn update(mut f: impl FnMut(&mut HashMap<String, Value>)) {
+    CONSTANTS.rcu(|current| {
+        let mut values = current.values.clone();
+        f(&mut values);
+        Constants::with_values(values)
+    });
+}
+
+/// Define or replace a constant. Names follow variable naming; the value must be
+/// representable as JSON (no infinite numbers).
+pub fn set_constant(name: &str, value: Value) -> Result<(), Error> {
+    let valid = !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if !valid {
+        return Err(Error::new(format!("Invalid constant name '{}'", name), None));
+    }
+    to_json(&value).map_err(|e| Error::new(format!("Constant {}: {}", name, e.message), None))?;
+    update(|values| {
+        values.insert(name.to_string(), value.clone());
+    });
+    Ok(())
+}
+
+/// Remove a constant; false if it was not defined
+pub fn remove_constant(name: &str) -> bool {
+    let mut removed = false;
+    update(|values| removed = values.remove(name).is_some());
+    removed
+}
+
+pub fn clear_constants() {
+    CONSTANTS.store(Arc::new(Constants::with_values(HashMap::new())));
+}
+
+pub fn constant(name: &str) -> Option<Value> {
+    CONSTANTS.load().values.get(name).cloned()
+}
+
+/// Names of the defined constants, sorted
+pub fn constant_names() -> Vec<String> {
+    let mut names: Vec<String> = CONSTANTS.load().values.keys().cloned().collect();
+    names.sort();
+    names
+}
+
+/// The value of `:ENV`
+pub(crate) fn env_value() -> Value {
+    Value::Json(CONSTANTS.load().env.clone())
+}
+
+/// CONST(name) -> the constant's value
+pub(crate) fn exec_const(args: &[Value]) -> Result<Value, Error> {
+    match args {
+        [Value::String(name)] => constant(name).ok_or_else(|| Error::new(format!("Unknown constant: {}", name), None)),
+        _ => Err(Error::new("CONST expects a constant name", None)),
+    }
+}
@@ -0,0 +1,526 @@
+//! Per-evaluation context visible to custom functions.
+//!
+//! The host installs an [`EvalContext`] around an evaluation with [`with_context`];
+//! custom functions (Rust or JavaScript) running inside that evaluation can read it
+//! through [`current`] without it being threaded through every builtin. Functions
+//! that implement [`CustomFunction::execute_with_context`](crate::custom::CustomFunction::execute_with_context)
+//! receive it directly in a [`CallContext`], together with the caller's variables.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::ast::Expr;
+use crate::custom::FunctionRegistry;
+use crate::error::Error;
+use crate::options::{EvalOptions, Strictness, NON_DETERMINISTIC_FUNCTIONS};
+use crate::types::Value;
+
+/// Information about the evaluation currently running on this thread
+#[derive(Debug, Clone, Default)]
+pub struct EvalContext {
+    /// Correlation id supplied by the caller (e.g. an `X-Request-Id` header)
+    pub request_id: Option<String>,
+    pub options: EvalOptions,
+}
+
+impl EvalContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    pub fn with_options(mut self, options: EvalOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+/// What a custom function sees of the formula calling it: the formula's variables
+/// and the [`EvalContext`] of the evaluation. Handed to
+/// [`CustomFunction::execute_with_context`](crate::custom::CustomFunction::execute_with_context).
+///
+/// It can also call back into the engine, with [`evaluate`](CallContext::evaluate)
+/// and [`call`](CallContext::call), which lets a plugin wrap other functions (a
+/// MEMOIZE or RETRY). Those run under the same options, deadline and memory budget
+/// as the calling formula.
+#[derive(Clone)]
+pub struct CallContext<'a> {
+    variables: Option<&'a HashMap<String, Value>>,
+    registry: Option<&'a Arc<FunctionRegistry>>,
+    eval: Option<Arc<EvalContext>>,
+}
+
+impl std::fmt::Debug for CallContext<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallContext").field("variables", &self.variables).field("eval", &self.eval).finish()
+    }
+}
+
+/// How deeply custom functions may nest calls back into the engine
+const MAX_REENTRY: usize = 32;
+
+impl<'a> CallContext<'a> {
+    /// A context with no variables and the evaluation context of this thread, as
+    /// used when a function is called outside of a formula
+    pub fn new() -> Self {
+        Self { variables: None, registry: None, eval: current() }
+    }
+
+    /// A context exposing `variables`
+    pub fn with_variables(variables: &'a HashMap<String, Value>) -> Self {
+        Self { variables: Some(variables), registry: None, eval: current() }
+    }
+
+    /// Resolve custom functions in [`evaluate`](CallContext::evaluate) and
+    /// [`call`](CallContext::call) from `registry` instead of the global one
+    pub fn with_registry(mut self, registry: &'a Arc<FunctionRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// The variables of the calling formula, without the `:` sigil
+    pub fn variables(&self) -> &HashMap<String, Value> {
+        static EMPTY: OnceLock<HashMap<String, Value>> = OnceLock::new();
+        self.variables.unwrap_or_else(|| EMPTY.get_or_init(HashMap::new))
+    }
+
+    /// A single variable of the calling formula
+    pub fn variable(&self, name: &str) -> Option<&Value> {
+        self.variables.and_then(|vars| vars.get(name))
+    }
+
+    /// The evaluation context installed by the host (defaults when none is)
+    pub fn eval(&self) -> &EvalContext {
+        static DEFAULT: OnceLock<EvalContext> = OnceLock::new();
+        self.eval.as_deref().unwrap_or_else(|| DEFAULT.get_or_init(EvalContext::default))
+    }
+
+    pub fn request_id(&self) -> Option<&str> {
+        self.eval().request_id.as_deref()
+    }
+
+    /// The `locale` option, e.g. `"es-MX"`
+    pub fn locale(&self) -> Option<&str> {
+        self.eval().options.locale.as_deref()
+    }
+
+    pub fn options(&self) -> &EvalOptions {
+        &self.eval().options
+    }
+
+    /// Evaluate the formula `input` with the caller's variables
+    pub fn evaluate(&self, input: &str) -> Result<Value, Error> {
+        let expr = crate::parse(input)?;
+        self.reenter(|vars, registry| crate::runtime::evaluator::eval_with_vars_and_custom(&expr, vars, registry))
+    }
+
+    /// Call the builtin or custom function `name` with already evaluated `args`
+    pub fn call(&self, name: &str, args: Vec<Value>) -> Result<Value, Error> {
+        let mut vars = self.variables().clone();
+        let mut params = Vec::with_capacity(args.len());
+        for (i, arg) in args.into_iter().enumerate() {
+            let param = format!("__arg{}", i);
+            vars.insert(param.clone(), arg);
+            params.push(Expr::Variable(param));
+        }
+        let expr = Expr::FunctionCall { name: name.to_uppercase(), args: params };
+        self.reenter(|_, registry| crate::runtime::evaluator::eval_with_vars_and_custom(&expr, &vars, registry))
+    }
+
+    fn reenter(&self, f: impl FnOnce(&HashMap<String, Value>, &Arc<FunctionRegistry>) -> Result<Value, Error>) -> Result<Value, Error> {
+        let depth = REENTRY.with(|d| d.get());
+        if depth >= MAX_REENTRY {
+            return Err(Error::new(format!("Custom functions called back into the engine more than {} levels deep", MAX_REENTRY), None));
+        }
+        REENTRY.with(|d| d.set(depth + 1));
+        struct Leave;
+        impl Drop for Leave {
+            fn drop(&mut self) {
+                REENTRY.with(|d| d.set(d.get().saturating_sub(1)));
+            }
+        }
+        let _leave = Leave;
+        let global;
+        let registry = match self.registry {
+            Some(registry) => registry,
+            None => {
+                global = crate::registry_snapshot();
+                &global
+            }
+        };
+        f(self.variables(), registry)
+    }
+}
+
+impl Default for CallContext<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<EvalContext>>> = const { RefCell::new(None) };
+    // Kept separately so the per-call deadline check is a plain Cell read
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+    static MEMORY: Cell<Option<MemoryBudget>> = const { Cell::new(None) };
+    static REENTRY: Cell<usize> = const { Cell::new(0) };
+    static STOPWATCH: RefCell<Option<CategoryTimes>> = const { RefCell::new(None) };
+}
+
+/// Calls made to one function category and the time they took
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CategoryTime {
+    pub calls: u64,
+    pub elapsed: Duration,
+    /// Limit from `category_budgets` on the total `elapsed`
+    pub budget: Option<Duration>,
+}
+
+/// Time charged to each function category, by [`FUNCTION_CATEGORIES`](crate::runtime::function_dispatch::FUNCTION_CATEGORIES) name
+pub type CategoryTimes = BTreeMap<String, CategoryTime>;
+
+/// Bytes charged so far against `max_memory_bytes`
+#[derive(Debug, Clone, Copy)]
+struct MemoryBudget {
+    used: usize,
+    limit: usize,
+}
+
+/// Restores the previously installed context when dropped, so nested and
+/// panicking evaluations leave the thread in a clean state.
+struct ContextGuard {
+    previous: Option<Arc<EvalContext>>,
+    previous_deadline: Option<Instant>,
+    previous_memory: Option<MemoryBudget>,
+    previous_times: Option<CategoryTimes>,
+}
+
+impl Drop for ContextGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|c| *c.borrow_mut() = previous);
+        DEADLINE.with(|d| d.set(self.previous_deadline));
+        // What a nested evaluation allocated stays charged to its parent
+        let used = MEMORY.with(|m| m.get()).map(|m| m.used);
+        let previous_memory = self.previous_memory.map(|p| MemoryBudget { used: used.unwrap_or(p.used), ..p });
+        MEMORY.with(|m| m.set(previous_memory));
+        // Likewise the time spent in each category, under the parent's budgets
+        let times = STOPWATCH.with(|s| s.borrow_mut().take());
+        let previous_times = self.previous_times.take().map(|mut previous| {
+            for (category, time) in times.into_iter().flatten() {
+                let entry = previous.entry(category).or_default();
+                entry.calls = time.calls;
+                entry.elapsed = time.elapsed;
+            }
+            previous
+        });
+        STOPWATCH.with(|s| *s.borrow_mut() = previous_times);
+    }
+}
+
+/// Run `f` with `ctx` installed as the current evaluation context
+pub fn with_context<R>(ctx: EvalContext, f: impl FnOnce() -> R) -> R {
+    let deadline = ctx.options.timeout_ms.map(|ms| Instant::now() + Duration::from_millis(ms));
+    let max_memory = ctx.options.max_memory_bytes;
+    let previous_times = STOPWATCH.with(|s| {
+        let mut times = s.borrow_mut();
+        let prev = times.clone();
+        if !ctx.options.category_budgets.is_empty() {
+            let times = times.get_or_insert_with(CategoryTimes::new);
+            for (category, ms) in &ctx.options.category_budgets {
+                let entry = times.entry(category.clone()).or_default();
+                let limit = entry.elapsed + Duration::from_millis(*ms);
+                entry.budget = Some(entry.budget.map_or(limit, |b| b.min(limit)));
+            }
+        }
+        prev
+    });
+    let previous = CURRENT.with(|c| c.borrow_mut().replace(Arc::new(ctx)));
+    let previous_deadline = DEADLINE.with(|d| {
+        let prev = d.get();
+        // A nested evaluation can never outlive its parent's budget
+        d.set(match (prev, deadline) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        });
+        prev
+    });
+    let previous_memory = MEMORY.with(|m| {
+        let prev = m.get();
+        m.set(match (prev, max_memory) {
+            (Some(p), Some(limit)) => Some(MemoryBudget { used: p.used, limit: p.limit.min(p.used.saturating_add(limit)) }),
+            (Some(p), None) => Some(p),
+            (None, Some(limit)) => Some(MemoryBudget { used: 0, limit }),
+            (None, None) => None,
+        });
+        prev
+    });
+    let _guard = ContextGuard { previous, previous_deadline, previous_memory, previous_times };
+    f()
+}
+
+/// The context of the evaluation running on this thread, if any
+pub fn current() -> Option<Arc<EvalContext>> {
+    CURRENT.with(|c| c.borrow().clone())
+}
+
+/// Shorthand for the current request id
+pub fn current_request_id() -> Option<String> {
+    current().and_then(|ctx| ctx.request_id.clone())
+}
+
+/// Read the options of the current evaluation (defaults when none is installed)
+pub fn with_options<R>(f: impl FnOnce(&EvalOptions) -> R) -> R {
+    CURRENT.with(|c| match c.borrow().as_deref() {
+        Some(ctx) => f(&ctx.options),
+        None => f(&EvalOptions::default()),
+    })
+}
+
+pub(crate) fn strictness() -> Strictness {
+    CURRENT.with(|c| c.borrow().as_ref().map(|ctx| ctx.options.strictness).unwrap_or_default())
+}
+
+pub(crate) fn epsilon() -> Option<f64> {
+    CURRENT.with(|c| c.borrow().as_ref().and_then(|ctx| ctx.options.epsilon))
+}
+
+pub(crate) fn deterministic() -> bool {
+    CURRENT.with(|c| c.borrow().as_ref().map(|ctx| ctx.options.deterministic).unwrap_or(false))
+}
+
+pub(crate) fn now_override() -> Option<i64> {
+    CURRENT.with(|c| c.borrow().as_ref().and_then(|ctx| ctx.options.now_override))
+}
+
+pub(crate) fn null_propagation() -> bool {
+    CURRENT.with(|c| c.borrow().as_ref().map(|ctx| ctx.options.null_propagation).unwrap_or(false))
+}
+
+pub(crate) fn overflow_check() -> bool {
+    CURRENT.with(|c| c.borrow().as_ref().map(|ctx| ctx.options.overflow_check).unwrap_or(false))
+}
+
+/// Under `overflow_check`, reject a non-finite `result` computed from finite `inputs`
+pub(crate) fn check_overflow(result: f64, inputs: &[f64], what: &str) -> Result<f64, Error> {
+    if !result.is_finite() && inputs.iter().all(|n| n.is_finite()) && overflow_check() {
+        return Err(Error::new(format!("Numeric overflow in {}", what), None));
+    }
+    Ok(result)
+}
+
+/// Under `overflow_check`, reject a zero `result` computed from non-zero finite `inputs`
+pub(crate) fn check_underflow(result: f64, inputs: &[f64], what: &str) -> Result<f64, Error> {
+    if result == 0.0 && inputs.iter().all(|n| *n != 0.0 && n.is_finite()) && overflow_check() {
+        return Err(Error::new(format!("Numeric underflow in {}", what), None));
+    }
+    Ok(result)
+}
+
+/// Largest magnitude at which every integer is exactly representable (2^53 - 1)
+pub(crate) const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+/// Under `overflow_check`, reject converting `n` to an integer when it is not
+/// finite or beyond ±[`MAX_SAFE_INTEGER`], where the result would be saturated or inexact
+pub(crate) fn check_integer(n: f64, what: &str) -> Result<f64, Error> {
+    if !(n.is_finite() && n.abs() <= MAX_SAFE_INTEGER) && overflow_check() {
+        return Err(Error::new(
+            format!("{} is out of the safe integer range in {}", crate::runtime::utils::format_number(n), what),
+            None,
+        ));
+    }
+    Ok(n)
+}
+
+/// Fail once the evaluation has used up its `timeout_ms`
+pub fn check_deadline() -> Result<(), Error> {
+    match DEADLINE.with(|d| d.get()) {
+        Some(deadline) if Instant::now() >= deadline => {
+            Err(Error::new("Evaluation timed out", None))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Whether the evaluation has run into its `timeout_ms`, `max_memory_bytes` or a
+/// category budget. Error handlers such as IFERROR let those errors through.
+pub(crate) fn limit_reached() -> bool {
+    check_deadline().is_err()
+        || MEMORY.with(|m| m.get()).is_some_and(|m| m.used > m.limit)
+        || STOPWATCH.with(|s| s.borrow().as_ref().is_some_and(|times| times.values().any(|t| t.budget.is_some_and(|b| t.elapsed >= b))))
+}
+
+/// Enforce `max_array_size` on an array about to be produced
+pub(crate) fn check_array_len(len: usize) -> Result<(), Error> {
+    CURRENT.with(|c| match c.borrow().as_ref().and_then(|ctx| ctx.options.max_array_size) {
+        Some(max) if len > max => Err(Error::new(
+            format!("Array of {} elements exceeds max_array_size of {}", len, max),
+            None,
+        )),
+        _ => Ok(()),
+    })
+}
+
+/// Enforce `max_string_length` on a string about to be produced
+pub(crate) fn check_string_len(len: usize) -> Result<(), Error> {
+    CURRENT.with(|c| match c.borrow().as_ref().and_then(|ctx| ctx.options.max_string_length) {
+        Some(max) if len > max => Err(Error::new(
+            format!("String of {} characters exceeds max_string_length of {}", len, max),
+            None,
+        )),
+        _ => Ok(()),
+    })
+}
+
+/// Approximate heap footprint of a value, including its own slot
+fn value_size(value: &Value) -> usize {
+    std::mem::size_of::<Value>()
+        + match value {
+            Value::String(s) | Value::Json(s) => s.len(),
+            Value::Array(items) => items.iter().map(value_size).sum(),
+            Value::Map(map) => map.iter().map(|(k, v)| k.len() + value_size(v)).sum(),
+            _ => 0,
+        }
+}
+
+/// Charge a freshly produced value against `max_memory_bytes`, failing once the
+/// evaluation has allocated more than its budget
+pub(crate) fn charge_memory(value: &Value) -> Result<(), Error> {
+    MEMORY.with(|m| match m.get() {
+        None => Ok(()),
+        Some(mut budget) => {
+            budget.used = budget.used.saturating_add(value_size(value));
+            m.set(Some(budget));
+            if budget.used > budget.limit {
+                return Err(Error::new(
+                    format!(
+                        "Evaluation exceeded max_memory_bytes of {} (about {} bytes allocated)",
+                        budget.limit, budget.used
+                    ),
+                    None,
+                ));
+            }
+            Ok(())
+        }
+    })
+}
+
+/// Run `f` timing every function call by category, and return the time per
+/// category along with its result. Run inside [`with_context`] the times include the
+/// `category_budgets` of the evaluation.
+pub fn profile_categories<R>(f: impl FnOnce() -> R) -> (R, CategoryTimes) {
+    struct Uninstall(bool);
+    impl Drop for Uninstall {
+        fn drop(&mut self) {
+            if self.0 {
+                STOPWATCH.with(|s| *s.borrow_mut() = None);
+            }
+        }
+    }
+    let installed = STOPWATCH.with(|s| {
+        let mut times = s.borrow_mut();
+        if times.is_some() {
+            return false;
+        }
+        *times = Some(CategoryTimes::new());
+        true
+    });
+    let _uninstall = Uninstall(installed);
+    let result = f();
+    let times = STOPWATCH.with(|s| s.borrow().clone()).unwrap_or_default();
+    (result, times)
+}
+
+/// Run a call to a function of `category`, charging its time to the category.
+/// Fails without calling `f` once the category's budget is used up, and after
+/// `f` when the call went over it.
+pub(crate) fn timed_call<R>(category: &str, f: impl FnOnce() -> Result<R, Error>) -> Result<R, Error> {
+    // None when nothing is being timed, Some(None) while the category has time left
+    let exhausted = STOPWATCH.with(|s| {
+        s.borrow().as_ref().map(|times| times.get(category).and_then(|t| t.budget.filter(|b| t.elapsed >= *b).map(|b| (b, t.elapsed))))
+    });
+    let Some(exhausted) = exhausted else { return f() };
+    if let Some((budget, used)) = exhausted {
+        return Err(budget_exceeded(category, budget, used));
+    }
+    let started = Instant::now();
+    let result = f();
+    let elapsed = started.elapsed();
+    let over = STOPWATCH.with(|s| {
+        let mut times = s.borrow_mut();
+        let time = times.as_mut()?.entry(category.to_string()).or_default();
+        time.calls += 1;
+        time.elapsed += elapsed;
+        time.budget.filter(|b| time.elapsed > *b).map(|b| (b, time.elapsed))
+    });
+    match over {
+        Some((budget, used)) => Err(budget_exceeded(category, budget, used)),
+        None => result,
+    }
+}
+
+fn budget_exceeded(category: &str, budget: Duration, used: Duration) -> Error {
+    Error::new(
+        format!(
+            "Time budget for {} functions exceeded: {:.1} ms used of {} ms",
+            category,
+            used.as_secs_f64() * 1000.0,
+            budget.as_millis()
+        ),
+        None,
+    )
+}
+
+/// Gate run before a custom function: refused under `builtins_only`
+pub(crate) fn check_custom(name: &str) -> Result<(), Error> {
+    check_deadline()?;
+    let builtins_only = CURRENT.with(|c| c.borrow().as_ref().map(|ctx| ctx.options.builtins_only).unwrap_or(false));
+    if builtins_only {
+        return Err(Error::new(
+            format!("Custom function {} is not allowed: only builtin functions are enabled", name),
+            None,
+        ));
+    }
+    Ok(())
+}
+
+/// Enforce `allowed_builtins` / `denied_builtins`
+pub(crate) fn check_builtin_policy(name: &str) -> Result<(), Error> {
+    CURRENT.with(|c| match c.borrow().as_ref().and_then(|ctx| ctx.options.builtin_policy_violation(name)) {
+        Some(msg) => Err(Error::new(msg, None)),
+        None => Ok(()),
+    })
+}
+
+/// Gate run before every builtin: deadline, policy and deterministic-mode checks
+pub(crate) fn check_builtin(name: &str) -> Result<(), Error> {
+    check_deadline()?;
+    check_builtin_policy(name)?;
+    // A frozen clock makes NOW reproducible
+    if deterministic() && NON_DETERMINISTIC_FUNCTIONS.contains(&name) && !(name == "NOW" && now_override().is_some()) {
+        return Err(Error::new(
+            format!("{} is not allowed in deterministic mode", name),
+            None,
+        ));
+    }
+    Ok(())
+}
+
+/// [`check_builtin`] for a call with `args`: DATE and TIME without arguments read the
+/// clock like NOW, so deterministic mode needs `now_override` for them too
+pub(crate) fn check_builtin_call(name: &str, args: &[Value]) -> Result<(), Error> {
+    check_builtin(name)?;
+    if args.is_empty() && matches!(name, "DATE" | "TIME") && deterministic() && now_override().is_none() {
+        return Err(Error::new(
+            format!("{}() reads the clock and is not allowed in deterministic mode; pass a date or set now_override", name),
+            None,
+        ));
+    }
+    Ok(())
+}
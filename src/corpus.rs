@@ -0,0 +1,270 @@
+//! Golden-result corpora: expressions with their expected results, for checking
+//! that an upgrade does not change what stored formulas evaluate to.
+//!
+//! A corpus is JSON Lines, one case per line; blank lines and lines starting with
+//! `#` are skipped:
+//!
+//! ```text
+//! {"expr": "SUM(:a, 2)", "vars": {"a": 1}, "expect": 3}
+//! {"expr": "'5'::Currency", "expect": 5, "type": "Currency"}
+//! {"expr": "1 / 0", "error": "Division by zero"}
+//! ```
+//!
+//! `vars` is optional and converted like [`json_to_value`](crate::json_to_value).
+//! `expect` is the result as JSON (currency and dates as numbers, JSON objects
+//! inline, `"NaN"` / `"Infinity"` / `"-Infinity"` for non-finite numbers); numbers match within a relative 1e-9. `type`, when present, must match
+//! the result's type name. `error` matches when the error message contains it.
+//!
+//! [`Corpus::run`] evaluates every case like
+//! [`evaluate_with_assignments`](crate::evaluate_with_assignments) with custom
+//! functions; [`Corpus::run_with`] takes any evaluation function, so downstream
+//! suites can apply their own options. [`Corpus::bless`] records the current
+//! results as the expectations.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde_json::{json, Map, Value as Json};
+
+use crate::error::Error;
+use crate::runtime::evaluator::{eval_with_assignments_context, VariableContext};
+use crate::types::Value;
+
+/// What a case must produce
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expected {
+    /// The result as JSON, plus the required type name if any
+    Value { value: Json, type_name: Option<String> },
+    /// A substring of the error message
+    Error(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorpusCase {
+    /// 1-based line in the corpus text
+    pub line: usize,
+    pub expression: String,
+    pub variables: HashMap<String, Value>,
+    /// `vars` as written, kept so [`Corpus::to_jsonl`] reproduces it
+    raw_variables: Option<Json>,
+    pub expected: Expected,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Corpus {
+    pub cases: Vec<CorpusCase>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CorpusFailure {
+    pub line: usize,
+    pub expression: String,
+    pub expected: Expected,
+    /// What the evaluation produced, rendered like an expectation
+    pub actual: Expected,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CorpusReport {
+    pub passed: usize,
+    pub failures: Vec<CorpusFailure>,
+}
+
+impl CorpusReport {
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// One line per failure: `line N: expr: expected X, got Y`
+    pub fn summary(&self) -> String {
+        let mut out = format!("{} passed, {} failed", self.passed, self.failures.len());
+        for failure in &self.failures {
+            out.push_str(&format!(
+                "\nline {}: {}: expected {}, got {}",
+                failure.line,
+                failure.expression,
+                describe(&failure.expected),
+                describe(&failure.actual)
+            ));
+        }
+        out
+    }
+}
+
+impl Corpus {
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut cases = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let case = parse_case(i + 1, line).map_err(|e| Error::new(format!("Line {}: {}", i + 1, e), None))?;
+            cases.push(case);
+        }
+        Ok(Self { cases })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| Error::new(format!("Cannot read {}: {}", path.display(), e), None))?;
+        Self::parse(&text).map_err(|e| Error::new(format!("{}: {}", path.display(), e.message), None))
+    }
+
+    pub fn run(&self) -> CorpusReport {
+        self.run_with(evaluate_case)
+    }
+
+    /// Run every case through `eval(expression, variables)`
+    pub fn run_with(&self, mut eval: impl FnMut(&str, &HashMap<String, Value>) -> Result<Value, Error>) -> CorpusReport {
+        let mut report = CorpusReport::default();
+        for case in &self.cases {
+            let actual = outcome(eval(&case.expression, &case.variables));
+            if matches_expected(&case.expected, &actual) {
+                report.passed += 1;
+            } else {
+                report.failures.push(CorpusFailure {
+                    line: case.line,
+                    expression: case.expression.clone(),
+                    expected: case.expected.clone(),
+                    actual,
+                });
+            }
+        }
+        report
+    }
+
+    /// Replace every expectation with what the case evaluates to now
+    pub fn bless(&mut self) {
+        for case in &mut self.cases {
+            case.expected = outcome(evaluate_case(&case.expression, &case.variables));
+        }
+    }
+
+    /// The corpus in its text format, one case per line
+    pub fn to_jsonl(&self) -> String {
+        let mut out = String::new();
+        for case in &self.cases {
+            // Built by hand to keep the fields in reading order
+            out.push_str(&format!("{{\"expr\": {}", Json::String(case.expression.clone())));
+            if let Some(vars) = &case.raw_variables {
+                out.push_str(&format!(", \"vars\": {}", vars));
+            }
+            match &case.expected {
+                Expected::Value { value, type_name } => {
+                    out.push_str(&format!(", \"expect\": {}", value));
+                    if let Some(type_name) = type_name {
+                        out.push_str(&format!(", \"type\": {}", Json::String(type_name.clone())));
+                    }
+                }
+                Expected::Error(message) => out.push_str(&format!(", \"error\": {}", Json::String(message.clone()))),
+            }
+            out.push_str("}\n");
+        }
+        out
+    }
+}
+
+fn parse_case(line: usize, text: &str) -> Result<CorpusCase, String> {
+    let object: Map<String, Json> = serde_json::from_str(text).map_err(|e| format!("Invalid JSON: {}", e))?;
+    let expression = object.get("expr").and_then(Json::as_str).ok_or("Missing \"expr\"")?.to_string();
+
+    let raw_variables = object.get("vars").cloned();
+    let variables = match &raw_variables {
+        None => HashMap::new(),
+        Some(Json::Object(vars)) => vars
+            .iter()
+            .map(|(k, v)| crate::json_to_value(v.clone()).map(|v| (k.clone(), v)).map_err(|e| e.message))
+            .collect::<Result<_, _>>()?,
+        Some(_) => return Err("\"vars\" must be an object".to_string()),
+    };
+
+    let expected = match (object.get("expect"), object.get("error")) {
+        (Some(value), None) => Expected::Value {
+            value: value.clone(),
+            type_name: object.get("type").and_then(Json::as_str).map(str::to_string),
+        },
+        (None, Some(Json::String(message))) => Expected::Error(message.clone()),
+        _ => return Err("Needs exactly one of \"expect\" or \"error\" (a string)".to_string()),
+    };
+    Ok(CorpusCase { line, expression, variables, raw_variables, expected })
+}
+
+pub(crate) fn evaluate_case(expression: &str, variables: &HashMap<String, Value>) -> Result<Value, Error> {
+    let expr = crate::parse(expression)?;
+    let registry = crate::registry_snapshot();
+    let mut context = VariableContext::with_custom(variables, &registry);
+    eval_with_assignments_context(&expr, &mut context)
+}
+
+pub(crate) fn outcome(result: Result<Value, Error>) -> Expected {
+    match result {
+        Ok(value) => {
+            // Only name the type when the JSON alone would read back as something else
+            let type_name = match value {
+                Value::Currency(_) => Some("Currency"),
+                Value::DateTime(_) => Some("DateTime"),
+                _ => None,
+            };
+            Expected::Value { value: to_json(&value), type_name: type_name.map(str::to_string) }
+        }
+        Err(e) => Expected::Error(e.message),
+    }
+}
+
+fn to_json(value: &Value) -> Json {
+    match value {
+        // Whole numbers are written without a fraction, as a person would
+        Value::Number(n) | Value::Currency(n) if n.fract() == 0.0 && n.abs() < 9e15 => json!(*n as i64),
+        Value::Number(n) | Value::Currency(n) if n.is_nan() => json!("NaN"),
+        Value::Number(n) | Value::Currency(n) if n.is_infinite() => json!(if *n > 0.0 { "Infinity" } else { "-Infinity" }),
+        Value::Number(n) | Value::Currency(n) => json!(n),
+        Value::DateTime(ts) => json!(ts),
+        Value::String(s) => Json::String(s.clone()),
+        Value::Boolean(b) => Json::Bool(*b),
+        Value::Null => Json::Null,
+        Value::Array(items) => Json::Array(items.iter().map(to_json).collect()),
+        Value::Json(s) => serde_json::from_str(s).unwrap_or_else(|_| Json::String(s.clone())),
+        Value::Map(map) => Json::Object(map.iter().map(|(k, v)| (k.clone(), to_json(v))).collect()),
+    }
+}
+
+fn matches_expected(expected: &Expected, actual: &Expected) -> bool {
+    match (expected, actual) {
+        (Expected::Error(want), Expected::Error(got)) => got.contains(want.as_str()),
+        (
+            Expected::Value { value: want, type_name: want_type },
+            Expected::Value { value: got, type_name: got_type },
+        ) => {
+            let type_ok = match want_type {
+                Some(t) => got_type.as_deref() == Some(t.as_str()),
+                None => got_type.is_none(),
+            };
+            type_ok && json_matches(want, got)
+        }
+        _ => false,
+    }
+}
+
+pub(crate) fn json_matches(want: &Json, got: &Json) -> bool {
+    match (want, got) {
+        (Json::Number(a), Json::Number(b)) => {
+            let (a, b) = (a.as_f64().unwrap_or(f64::NAN), b.as_f64().unwrap_or(f64::NAN));
+            a == b || (a - b).abs() <= 1e-9 * a.abs().max(b.abs())
+        }
+        (Json::Array(a), Json::Array(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| json_matches(a, b)),
+        (Json::Object(a), Json::Object(b)) => {
+            a.len() == b.len() && a.iter().all(|(k, v)| b.get(k).is_some_and(|w| json_matches(v, w)))
+        }
+        _ => want == got,
+    }
+}
+
+pub(crate) fn describe(expected: &Expected) -> String {
+    match expected {
+        Expected::Value { value, type_name: Some(t) } => format!("{} ({})", value, t),
+        Expected::Value { value, type_name: None } => value.to_string(),
+        Expected::Error(message) => format!("error \"{}\"", message),
+    }
+}
@@ -0,0 +1,203 @@
+//! Static cost estimation for parsed expressions.
+//!
+//! Gives hosts that accept user-authored formulas a way to reject pathological ones
+//! before running them. The estimate looks only at the AST: collection sizes are
+//! unknown, so every iteration is assumed to visit [`NOMINAL_ITEMS`] elements.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::ast::Expr;
+
+/// Collection size assumed for each loop when computing [`CostReport::score`]
+pub const NOMINAL_ITEMS: u64 = 100;
+
+/// Functions whose later arguments are evaluated once per element of the first
+const LAMBDA_FUNCTIONS: &[&str] = &["FILTER", "MAP", "FIND", "REDUCE", "SUMIF", "AVGIF", "COUNTIF", "ITERATE", "DISTINCTBY"];
+const LAMBDA_METHODS: &[&str] = &["filter", "map", "find", "reduce", "partition", "split_when", "unique_by", "sort_by"];
+
+/// Functions that walk an entire collection argument
+const TRAVERSAL_FUNCTIONS: &[&str] = &[
+    "SUM", "AVG", "AVERAGE", "MIN", "MAX", "PRODUCT", "MULTIPLY", "COUNT", "UNIQUE", "SORT", "REVERSE",
+    "JOIN", "FLATTEN", "MERGE", "CONTAINS", "IN", "CONCAT", "MEDIAN", "MODE.SNGL", "MODESNGL", "MODE_SNGL",
+    "MODE.MULT", "MODEMULT", "MODE_MULT", "STDEV.P", "STDEVP", "STDEV_P", "VAR.P", "VARP", "VAR_P",
+    "PERCENTILE.INC", "PERCENTILEINC", "PERCENTILE_INC", "QUARTILE.INC", "QUARTILEINC", "QUARTILE_INC",
+    "AVGRATIO", "CHOOSEWEIGHTED", "JQ", "NGRAMS", "TFCOUNT", "PAGE", "OFFSETLIMIT", "ORDINAL", "HISTOGRAM", "ZSCORE", "MINMAXSCALE", "WINSORIZE", "MOVINGAVG", "EMA", "JOINON", "ORDERBY", "FIRSTMATCH", "DTABLE", "RANGE", "__RANGE__", "__RANGE_INCLUSIVE__",
+];
+const TRAVERSAL_METHODS: &[&str] = &[
+    "unique", "sort", "sum", "avg", "average", "min", "max", "join", "contains", "includes", "flatten",
+    "compact", "merge", "reverse", "push", "insert", "remove_at", "replace_at", "set", "tally",
+];
+
+/// Worst-case growth of evaluation time in the size `n` of the input collections
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Complexity {
+    Constant,
+    Linear,
+    Quadratic,
+    /// `O(n^k)` for `k >= 3`
+    Polynomial(u32),
+}
+
+impl Complexity {
+    fn from_degree(degree: u32) -> Self {
+        match degree {
+            0 => Complexity::Constant,
+            1 => Complexity::Linear,
+            2 => Complexity::Quadratic,
+            k => Complexity::Polynomial(k),
+        }
+    }
+}
+
+impl fmt::Display for Complexity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Complexity::Constant => write!(f, "O(1)"),
+            Complexity::Linear => write!(f, "O(n)"),
+            Complexity::Quadratic => write!(f, "O(n^2)"),
+            Complexity::Polynomial(k) => write!(f, "O(n^{})", k),
+        }
+    }
+}
+
+/// Result of [`estimate_cost`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CostReport {
+    /// Total AST nodes
+    pub node_count: usize,
+    /// Deepest nesting of AST nodes
+    pub max_depth: usize,
+    /// Number of per-element iterations (FILTER, `.map`, ...)
+    pub loop_count: usize,
+    /// Deepest nesting of per-element iterations
+    pub max_loop_nesting: u32,
+    /// Variables that are iterated over, sorted
+    pub iterated_variables: Vec<String>,
+    pub complexity: Complexity,
+    /// Comparable cost: each node weighs `NOMINAL_ITEMS^k` where `k` is the number of
+    /// enclosing loops, and collection walks add one more level
+    pub score: u64,
+}
+
+#[derive(Default)]
+struct Walker {
+    node_count: usize,
+    max_depth: usize,
+    loop_count: usize,
+    max_loop_nesting: u32,
+    degree: u32,
+    iterated: BTreeSet<String>,
+    score: u64,
+}
+
+/// Estimate the evaluation cost of an expression without running it
+pub fn estimate_cost(expr: &Expr) -> CostReport {
+    let mut w = Walker::default();
+    w.visit(expr, 1, 0);
+    CostReport {
+        node_count: w.node_count,
+        max_depth: w.max_depth,
+        loop_count: w.loop_count,
+        max_loop_nesting: w.max_loop_nesting,
+        iterated_variables: w.iterated.into_iter().collect(),
+        complexity: Complexity::from_degree(w.degree),
+        score: w.score,
+    }
+}
+
+fn weight(loops: u32) -> u64 {
+    NOMINAL_ITEMS.saturating_pow(loops)
+}
+
+impl Walker {
+    fn visit(&mut self, expr: &Expr, depth: usize, loops: u32) {
+        self.node_count += 1;
+        self.max_depth = self.max_depth.max(depth);
+        self.max_loop_nesting = self.max_loop_nesting.max(loops);
+        self.degree = self.degree.max(loops);
+        self.score = self.score.saturating_add(weight(loops));
+        let d = depth + 1;
+
+        match expr {
+            Expr::Number(_) | Expr::StringLit(_) | Expr::Null | Expr::Variable(_) => {}
+            Expr::Unary(_, inner) | Expr::Spread(inner) => self.visit(inner, d, loops),
+            Expr::TypeCast { expr, .. } => self.visit(expr, d, loops),
+            Expr::Assignment { value, .. } | Expr::Destructure { value, .. } => self.visit(value, d, loops),
+            Expr::Lambda { body, .. } => self.visit(body, d, loops),
+            Expr::Binary(l, _, r) => {
+                self.visit(l, d, loops);
+                self.visit(r, d, loops);
+            }
+            Expr::PropertyAccess { target, .. } | Expr::SafePropertyAccess { target, .. } => {
+                self.visit(target, d, loops)
+            }
+            Expr::Index { target, index } => {
+                self.visit(target, d, loops);
+                self.visit(index, d, loops);
+            }
+            Expr::Slice { target, start, end } => {
+                self.visit(target, d, loops);
+                for e in [start, end].into_iter().flatten() {
+                    self.visit(e, d, loops);
+                }
+            }
+            Expr::Array(items) | Expr::Sequence(items) => {
+                for it in items {
+                    self.visit(it, d, loops);
+                }
+            }
+            Expr::ObjectLiteral(fields) => {
+                for (_, v) in fields {
+                    self.visit(v, d, loops);
+                }
+            }
+            Expr::FunctionCall { name, args } => {
+                let upper = name.to_uppercase();
+                if LAMBDA_FUNCTIONS.contains(&upper.as_str()) && !args.is_empty() {
+                    self.iteration(&args[0], &args[1..], d, loops);
+                } else {
+                    if TRAVERSAL_FUNCTIONS.contains(&upper.as_str()) {
+                        self.traversal(loops);
+                    }
+                    for a in args {
+                        self.visit(a, d, loops);
+                    }
+                }
+            }
+            Expr::MethodCall { target, name, args, .. } | Expr::SafeMethodCall { target, name, args } => {
+                let lower = name.to_lowercase();
+                if LAMBDA_METHODS.contains(&lower.as_str()) {
+                    self.iteration(target, args, d, loops);
+                } else {
+                    if TRAVERSAL_METHODS.contains(&lower.as_str()) {
+                        self.traversal(loops);
+                    }
+                    self.visit(target, d, loops);
+                    for a in args {
+                        self.visit(a, d, loops);
+                    }
+                }
+            }
+        }
+    }
+
+    /// `collection` is evaluated once, `body` once per element
+    fn iteration(&mut self, collection: &Expr, body: &[Expr], depth: usize, loops: u32) {
+        self.loop_count += 1;
+        if let Expr::Variable(name) = collection {
+            self.iterated.insert(name.clone());
+        }
+        self.visit(collection, depth, loops);
+        // The loop itself is at least linear even with an empty body
+        self.traversal(loops);
+        for b in body {
+            self.visit(b, depth, loops + 1);
+        }
+    }
+
+    fn traversal(&mut self, loops: u32) {
+        self.degree = self.degree.max(loops + 1);
+        self.score = self.score.saturating_add(weight(loops + 1));
+    }
+}
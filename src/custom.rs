@@ -1,6 +1,8 @@
+use crate::context::CallContext;
 use crate::error::Error;
 use crate::types::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Trait for implementing custom functions in skillet
 /// 
@@ -23,6 +25,66 @@ use std::collections::HashMap;
 ///     }
 /// }
 /// ```
+///
+/// # Returning several values
+///
+/// A function with several results returns them as one value: a JSON object when
+/// the results have names, built with [`record!`](crate::record), or an array when
+/// only their order matters, built with [`tuple!`](crate::tuple). Formulas take
+/// them apart with a destructuring assignment, `{:lat, :lon} := GEOCODE(:address)`
+/// or `[:low, :high] := BOUNDS(:xs)`, or read single fields with `.lat` or `[0]`.
+///
+/// ```rust
+/// use skillet::custom::CustomFunction;
+/// use skillet::{record, Value, Error};
+///
+/// struct Geocode;
+///
+/// impl CustomFunction for Geocode {
+///     fn name(&self) -> &str { "GEOCODE" }
+///     fn min_args(&self) -> usize { 1 }
+///     fn max_args(&self) -> Option<usize> { Some(1) }
+///
+///     fn execute(&self, _args: Vec<Value>) -> Result<Value, Error> {
+///         record! {
+///             "lat" => Value::Number(52.37),
+///             "lon" => Value::Number(4.89),
+///             "accuracy" => Value::String("street".to_string()),
+///         }
+///     }
+/// }
+/// ```
+///
+/// # Reading the calling context
+///
+/// A function whose result depends on who is asking, such as a per-tenant rate
+/// table, overrides [`execute_with_context`](CustomFunction::execute_with_context).
+/// The [`CallContext`] carries the formula's variables and the host's
+/// [`EvalContext`](crate::EvalContext): request id, locale and options.
+///
+/// ```rust
+/// use skillet::custom::CustomFunction;
+/// use skillet::{CallContext, Value, Error};
+///
+/// struct TenantRate;
+///
+/// impl CustomFunction for TenantRate {
+///     fn name(&self) -> &str { "TENANTRATE" }
+///     fn min_args(&self) -> usize { 0 }
+///     fn max_args(&self) -> Option<usize> { Some(0) }
+///
+///     fn execute(&self, args: Vec<Value>) -> Result<Value, Error> {
+///         self.execute_with_context(args, &CallContext::new())
+///     }
+///
+///     fn execute_with_context(&self, _args: Vec<Value>, ctx: &CallContext) -> Result<Value, Error> {
+///         match ctx.variable("tenant") {
+///             Some(Value::String(t)) if t == "acme" => Ok(Value::Number(0.07)),
+///             _ => Ok(Value::Number(0.1)),
+///         }
+///     }
+/// }
+/// ```
 pub trait CustomFunction: Send + Sync {
     /// The name of the function (case-insensitive)
     fn name(&self) -> &str;
@@ -35,18 +97,51 @@ pub trait CustomFunction: Send + Sync {
     
     /// Execute the function with the given arguments
     fn execute(&self, args: Vec<Value>) -> Result<Value, Error>;
+
+    /// Execute the function with the context of the calling formula. Formulas call
+    /// this; the default ignores the context and calls [`execute`](CustomFunction::execute)
+    fn execute_with_context(&self, args: Vec<Value>, ctx: &CallContext) -> Result<Value, Error> {
+        let _ = ctx;
+        self.execute(args)
+    }
     
     /// Optional: Description of the function for documentation
     fn description(&self) -> Option<&str> { None }
     
     /// Optional: Example usage for documentation
     fn example(&self) -> Option<&str> { None }
+
+    /// Optional: Group the function is listed under, e.g. "Finance"
+    fn category(&self) -> Option<&str> { None }
+
+    /// Optional: Documentation of each parameter, in order
+    fn params(&self) -> &[ParamDoc] { &[] }
+
+    /// Optional: Description of the result
+    fn returns(&self) -> Option<&str> { None }
+
+    /// Optional: Free-form `key=value` hints for formula builders (widget, unit, ...)
+    fn ui_hints(&self) -> &[(String, String)] { &[] }
+
+    /// Category the function's calls are timed under for
+    /// [`EvalOptions::category_budgets`](crate::EvalOptions::category_budgets)
+    fn budget_category(&self) -> &'static str { "custom" }
+}
+
+/// Documentation of one parameter of a custom function
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamDoc {
+    pub name: String,
+    pub description: Option<String>,
 }
 
 /// Registry for custom functions
-#[derive(Default)]
+///
+/// Functions are reference counted, so cloning a registry is cheap; the global
+/// registry relies on this to publish updated copies instead of locking readers.
+#[derive(Default, Clone)]
 pub struct FunctionRegistry {
-    functions: HashMap<String, Box<dyn CustomFunction>>,
+    functions: HashMap<String, Arc<dyn CustomFunction>>,
 }
 
 impl FunctionRegistry {
@@ -70,10 +165,17 @@ impl FunctionRegistry {
             return Err(Error::new("min_args cannot be greater than max_args", None));
         }
         
-        self.functions.insert(name, function);
+        self.functions.insert(name, Arc::from(function));
         Ok(())
     }
     
+    /// Copy every function of `other` into this registry, replacing same-named ones
+    pub(crate) fn merge(&mut self, other: &FunctionRegistry) {
+        for (name, function) in &other.functions {
+            self.functions.insert(name.clone(), Arc::clone(function));
+        }
+    }
+
     /// Get a function by name (case-insensitive)
     pub fn get(&self, name: &str) -> Option<&dyn CustomFunction> {
         self.functions.get(&name.to_uppercase()).map(|f| f.as_ref())
@@ -96,6 +198,11 @@ impl FunctionRegistry {
     
     /// Validate and execute a function
     pub fn execute(&self, name: &str, args: Vec<Value>) -> Result<Value, Error> {
+        self.execute_with_context(name, args, &CallContext::new())
+    }
+
+    /// Validate and execute a function, passing it `ctx`
+    pub fn execute_with_context(&self, name: &str, args: Vec<Value>, ctx: &CallContext) -> Result<Value, Error> {
         let function = self.get(name)
             .ok_or_else(|| Error::new(format!("Unknown custom function: {}", name), None))?;
         
@@ -120,10 +227,252 @@ impl FunctionRegistry {
         }
         
         // Execute the function
-        function.execute(args)
+        crate::context::timed_call(function.budget_category(), || function.execute_with_context(args, ctx))
+    }
+}
+
+/// A custom function made from a closure over the raw arguments, usually built by
+/// [`custom_fn!`](crate::custom_fn) rather than by hand
+pub struct FnFunction<F> {
+    name: String,
+    min_args: usize,
+    max_args: usize,
+    description: Option<String>,
+    example: Option<String>,
+    function: F,
+}
+
+impl<F> FnFunction<F>
+where
+    F: Fn(Vec<Value>) -> Result<Value, Error> + Send + Sync,
+{
+    /// A function taking `min_args` to `max_args` arguments
+    pub fn new(name: impl Into<String>, min_args: usize, max_args: usize, function: F) -> Self {
+        Self { name: name.into(), min_args, max_args, description: None, example: None, function }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn with_example(mut self, example: impl Into<String>) -> Self {
+        self.example = Some(example.into());
+        self
+    }
+}
+
+impl<F> CustomFunction for FnFunction<F>
+where
+    F: Fn(Vec<Value>) -> Result<Value, Error> + Send + Sync,
+{
+    fn name(&self) -> &str { &self.name }
+    fn min_args(&self) -> usize { self.min_args }
+    fn max_args(&self) -> Option<usize> { Some(self.max_args) }
+    fn execute(&self, args: Vec<Value>) -> Result<Value, Error> { (self.function)(args) }
+    fn description(&self) -> Option<&str> { self.description.as_deref() }
+    fn example(&self) -> Option<&str> { self.example.as_deref() }
+}
+
+/// Conversion of an argument to a Rust type for [`custom_fn!`](crate::custom_fn)
+pub trait FromValue<'a>: Sized {
+    /// Whether the argument may be left out, as for `Option`
+    const OPTIONAL: bool = false;
+
+    /// The converted argument, or a description of what was expected such as
+    /// `"a number"`
+    fn from_value(value: &'a Value) -> Result<Self, &'static str>;
+}
+
+impl<'a> FromValue<'a> for Value {
+    fn from_value(value: &'a Value) -> Result<Self, &'static str> { Ok(value.clone()) }
+}
+
+impl<'a> FromValue<'a> for f64 {
+    fn from_value(value: &'a Value) -> Result<Self, &'static str> {
+        match value {
+            Value::Number(n) | Value::Currency(n) => Ok(*n),
+            _ => Err("a number"),
+        }
+    }
+}
+
+impl<'a> FromValue<'a> for i64 {
+    fn from_value(value: &'a Value) -> Result<Self, &'static str> {
+        match value {
+            Value::Number(n) if n.fract() == 0.0 && n.abs() < 9e15 => Ok(*n as i64),
+            _ => Err("a whole number"),
+        }
     }
 }
 
+impl<'a> FromValue<'a> for bool {
+    fn from_value(value: &'a Value) -> Result<Self, &'static str> {
+        value.as_bool().ok_or("a boolean")
+    }
+}
+
+impl<'a> FromValue<'a> for &'a str {
+    fn from_value(value: &'a Value) -> Result<Self, &'static str> {
+        match value {
+            Value::String(s) => Ok(s),
+            _ => Err("text"),
+        }
+    }
+}
+
+impl<'a> FromValue<'a> for String {
+    fn from_value(value: &'a Value) -> Result<Self, &'static str> {
+        <&str>::from_value(value).map(str::to_string)
+    }
+}
+
+impl<'a, T: FromValue<'a>> FromValue<'a> for Vec<T> {
+    fn from_value(value: &'a Value) -> Result<Self, &'static str> {
+        match value {
+            Value::Array(items) => items.iter().map(T::from_value).collect(),
+            _ => Err("an array"),
+        }
+    }
+}
+
+impl<'a, T: FromValue<'a>> FromValue<'a> for Option<T> {
+    const OPTIONAL: bool = true;
+
+    fn from_value(value: &'a Value) -> Result<Self, &'static str> {
+        match value {
+            Value::Null => Ok(None),
+            other => T::from_value(other).map(Some),
+        }
+    }
+}
+
+/// Conversion of a result to a [`Value`] for [`custom_fn!`](crate::custom_fn)
+pub trait IntoValue {
+    fn into_value(self) -> Result<Value, Error>;
+}
+
+impl IntoValue for Value {
+    fn into_value(self) -> Result<Value, Error> { Ok(self) }
+}
+
+impl IntoValue for f64 {
+    fn into_value(self) -> Result<Value, Error> { Ok(Value::Number(self)) }
+}
+
+impl IntoValue for i64 {
+    fn into_value(self) -> Result<Value, Error> { Ok(Value::Number(self as f64)) }
+}
+
+impl IntoValue for bool {
+    fn into_value(self) -> Result<Value, Error> { Ok(Value::Boolean(self)) }
+}
+
+impl IntoValue for String {
+    fn into_value(self) -> Result<Value, Error> { Ok(Value::String(self)) }
+}
+
+impl IntoValue for &str {
+    fn into_value(self) -> Result<Value, Error> { Ok(Value::String(self.to_string())) }
+}
+
+impl<T: IntoValue> IntoValue for Vec<T> {
+    fn into_value(self) -> Result<Value, Error> {
+        self.into_iter().map(T::into_value).collect::<Result<_, _>>().map(Value::Array)
+    }
+}
+
+impl<T: IntoValue> IntoValue for Option<T> {
+    fn into_value(self) -> Result<Value, Error> { self.map_or(Ok(Value::Null), T::into_value) }
+}
+
+impl<T: IntoValue> IntoValue for Result<T, Error> {
+    fn into_value(self) -> Result<Value, Error> { self.and_then(T::into_value) }
+}
+
+/// The error for an argument `custom_fn!` could not convert
+#[doc(hidden)]
+pub fn argument_error(function: &str, position: usize, name: &str, expected: &str, got: &Value) -> Error {
+    Error::new(format!("{} argument {} ({}) must be {}, got {:?}", function, position, name, expected, got), None)
+}
+
+/// Define a custom function from a closure with typed parameters. Arguments are
+/// converted with [`FromValue`] (`f64`, `i64`, `bool`, `&str`, `String`, `Value`,
+/// `Vec<T>`; trailing `Option<T>` parameters may be left out or null) and the
+/// result with [`IntoValue`], which includes `Result<T, Error>` for functions that
+/// can fail. The argument count is checked from the parameter list.
+///
+/// ```rust
+/// use skillet::{custom_fn, register_function, evaluate_with_custom};
+/// use std::collections::HashMap;
+///
+/// let taxrate = custom_fn!("TAXRATE", |amount: f64, region: &str| -> f64 {
+///     if region == "EU" { amount * 0.21 } else { amount * 0.07 }
+/// })
+/// .with_description("Tax for an amount in a region");
+/// register_function(Box::new(taxrate)).unwrap();
+///
+/// let tax = evaluate_with_custom("TAXRATE(100, 'EU')", &HashMap::new()).unwrap();
+/// assert_eq!(tax, skillet::Value::Number(21.0));
+/// ```
+#[macro_export]
+macro_rules! custom_fn {
+    ($name:expr, |$($arg:ident : $ty:ty),* $(,)?| -> $ret:ty { $($body:tt)* }) => {
+        $crate::custom_fn!(@build $name, [$($arg : $ty),*], || -> $ret { $($body)* })
+    };
+    ($name:expr, |$($arg:ident : $ty:ty),* $(,)?| $body:expr) => {
+        $crate::custom_fn!(@build $name, [$($arg : $ty),*], || $body)
+    };
+    (@build $name:expr, [$($arg:ident : $ty:ty),*], $call:expr) => {{
+        let name: ::std::string::String = ::std::convert::Into::into($name);
+        let optional: &[bool] = &[$(<$ty as $crate::custom::FromValue>::OPTIONAL),*];
+        let max_args = optional.len();
+        let min_args = optional.iter().rposition(|o| !o).map_or(0, |i| i + 1);
+        $crate::custom::FnFunction::new(name.clone(), min_args, max_args, move |args: ::std::vec::Vec<$crate::Value>| {
+            let mut args = args.iter();
+            let mut position = 0usize;
+            $(
+                position += 1;
+                let value = args.next().unwrap_or(&$crate::Value::Null);
+                let $arg: $ty = $crate::custom::FromValue::from_value(value).map_err(|expected| {
+                    $crate::custom::argument_error(&name, position, stringify!($arg), expected, value)
+                })?;
+            )*
+            let _ = position;
+            $crate::custom::IntoValue::into_value(($call)())
+        })
+    }};
+}
+
+/// A JSON object value with the given fields, the convention for functions that
+/// return several named results. See [`record!`](crate::record) for the shorthand.
+pub fn record<K: Into<String>>(fields: impl IntoIterator<Item = (K, Value)>) -> Result<Value, Error> {
+    let mut object = serde_json::Map::new();
+    for (key, value) in fields {
+        object.insert(key.into(), crate::runtime::method_calls::conversion_methods::value_to_json_value(&value)?);
+    }
+    Ok(Value::Json(serde_json::Value::Object(object).to_string()))
+}
+
+/// Build the object result of a function with several named results:
+/// `record! { "lat" => Value::Number(52.37), "lon" => Value::Number(4.89) }`
+/// gives `Ok` of a JSON object value, ready to return from `execute`.
+#[macro_export]
+macro_rules! record {
+    ($($key:expr => $value:expr),* $(,)?) => {
+        $crate::custom::record([$(($key, $value)),*])
+    };
+}
+
+/// Build the array result of a function with several positional results:
+/// `tuple![Value::Number(1.0), Value::Number(9.0)]`.
+#[macro_export]
+macro_rules! tuple {
+    ($($value:expr),* $(,)?) => {
+        $crate::Value::Array(vec![$($value),*])
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,228 @@
+//! Column-oriented input for row-wise evaluation.
+//!
+//! A [`Dataset`] holds named columns of equal length. [`evaluate_per_row`] compiles
+//! the expression once and runs it for every row with `:column` bound to that row's
+//! cell, reusing a single variable map instead of building one per row.
+//!
+//! Inside a per-row expression, `:column.sum()` and the other aggregate methods
+//! (`avg`, `average`, `min`, `max`, `count`) refer to the whole column. They are
+//! computed once before the first row and the row expression reads the result, so
+//! `:amount / :amount.sum()` costs one pass over the column, not one per row.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::ast::Expr;
+use crate::error::Error;
+use crate::runtime::evaluator::eval_with_vars_and_custom;
+use crate::types::Value;
+
+/// Array methods that summarize a whole column when called on one in a row expression
+const AGGREGATE_METHODS: &[&str] = &["sum", "avg", "average", "min", "max", "count"];
+
+/// Named columns of equal length
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Dataset {
+    names: Vec<String>,
+    columns: Vec<Vec<Value>>,
+}
+
+impl Dataset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a column, builder style. See [`Dataset::add_column`].
+    pub fn with_column(mut self, name: impl Into<String>, values: Vec<Value>) -> Result<Self, Error> {
+        self.add_column(name, values)?;
+        Ok(self)
+    }
+
+    /// Add or replace a column. Every column must have the same number of rows.
+    pub fn add_column(&mut self, name: impl Into<String>, values: Vec<Value>) -> Result<(), Error> {
+        let name = name.into();
+        let existing = self.names.iter().position(|n| *n == name);
+        let others = self.columns.iter().enumerate().find(|(i, _)| Some(*i) != existing);
+        if let Some((_, other)) = others {
+            if other.len() != values.len() {
+                return Err(Error::new(
+                    format!("Column '{}' has {} values, expected {}", name, values.len(), other.len()),
+                    None,
+                ));
+            }
+        }
+        match existing {
+            Some(i) => self.columns[i] = values,
+            None => {
+                self.names.push(name);
+                self.columns.push(values);
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of rows
+    pub fn len(&self) -> usize {
+        self.columns.first().map_or(0, Vec::len)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Column names in the order they were added
+    pub fn column_names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn column(&self, name: &str) -> Option<&[Value]> {
+        self.names.iter().position(|n| n == name).map(|i| self.columns[i].as_slice())
+    }
+
+    /// Row `index` as a variable map
+    pub fn row(&self, index: usize) -> Option<HashMap<String, Value>> {
+        if index >= self.len() {
+            return None;
+        }
+        Some(self.names.iter().cloned().zip(self.columns.iter().map(|c| c[index].clone())).collect())
+    }
+
+    /// Evaluate `expr` once with every column bound to its whole array, e.g.
+    /// `:amount.sum() / :qty.sum()`
+    pub fn aggregate(&self, expr: &str) -> Result<Value, Error> {
+        let parsed = crate::parse(expr)?;
+        let vars: HashMap<String, Value> =
+            self.names.iter().cloned().zip(self.columns.iter().map(|c| Value::Array(c.clone()))).collect();
+        eval_with_vars_and_custom(&parsed, &vars, &crate::registry_snapshot())
+    }
+}
+
+/// Evaluate `expr` for every row of `dataset`, in row order
+pub fn evaluate_per_row(expr: &str, dataset: &Dataset) -> Result<Vec<Value>, Error> {
+    evaluate_per_row_with(expr, dataset, &HashMap::new())
+}
+
+/// Like [`evaluate_per_row`], with `vars` visible to every row. Columns shadow
+/// variables of the same name.
+pub fn evaluate_per_row_with(
+    expr: &str,
+    dataset: &Dataset,
+    vars: &HashMap<String, Value>,
+) -> Result<Vec<Value>, Error> {
+    let parsed = crate::parse(expr)?;
+    let registry = crate::registry_snapshot();
+
+    // Compute column aggregates once and bind them to hidden variables
+    let mut aggregates = Vec::new();
+    let row_expr = hoist_aggregates(&parsed, dataset, &mut aggregates);
+    let mut scope = vars.clone();
+    if !aggregates.is_empty() {
+        let columns = whole_columns(dataset, vars);
+        for (i, aggregate) in aggregates.iter().enumerate() {
+            let value = eval_with_vars_and_custom(aggregate, &columns, &registry)?;
+            scope.insert(aggregate_var(i), value);
+        }
+    }
+
+    for name in &dataset.names {
+        scope.insert(name.clone(), Value::Null);
+    }
+    let mut results = Vec::with_capacity(dataset.len());
+    for row in 0..dataset.len() {
+        for (name, column) in dataset.names.iter().zip(&dataset.columns) {
+            if let Some(slot) = scope.get_mut(name) {
+                *slot = column[row].clone();
+            }
+        }
+        let value = eval_with_vars_and_custom(&row_expr, &scope, &registry)
+            .map_err(|e| Error::new(format!("Row {}: {}", row, e.message), e.position))?;
+        results.push(value);
+    }
+    Ok(results)
+}
+
+fn aggregate_var(index: usize) -> String {
+    format!("__AGGREGATE_{}", index)
+}
+
+/// The column an aggregate method call reads, if `expr` is one
+fn aggregate_column<'a>(expr: &'a Expr, dataset: &Dataset) -> Option<&'a str> {
+    let (target, name) = match expr {
+        Expr::MethodCall { target, name, predicate: false, .. } => (target, name),
+        Expr::SafeMethodCall { target, name, .. } => (target, name),
+        _ => return None,
+    };
+    match target.as_ref() {
+        Expr::Variable(col)
+            if AGGREGATE_METHODS.contains(&name.to_lowercase().as_str()) && dataset.column(col).is_some() =>
+        {
+            Some(col)
+        }
+        _ => None,
+    }
+}
+
+/// Variables for evaluating the hoisted aggregates: `vars` with every column bound to
+/// its whole array, as in [`Dataset::aggregate`], so arguments can read other columns
+fn whole_columns(dataset: &Dataset, vars: &HashMap<String, Value>) -> HashMap<String, Value> {
+    let mut scope = vars.clone();
+    for (name, column) in dataset.names.iter().zip(&dataset.columns) {
+        scope.insert(name.clone(), Value::Array(column.clone()));
+    }
+    scope
+}
+
+/// Replace every aggregate method call on a column with a variable holding its
+/// result, collecting the calls in `found`
+fn hoist_aggregates(expr: &Expr, dataset: &Dataset, found: &mut Vec<Expr>) -> Expr {
+    if aggregate_column(expr, dataset).is_some() {
+        let index = found.iter().position(|e| e == expr).unwrap_or_else(|| {
+            found.push(expr.clone());
+            found.len() - 1
+        });
+        return Expr::Variable(aggregate_var(index));
+    }
+
+    let mut go = |e: &Expr| hoist_aggregates(e, dataset, found);
+    match expr {
+        Expr::Number(_) | Expr::StringLit(_) | Expr::Null | Expr::Variable(_) => expr.clone(),
+        Expr::Unary(op, inner) => Expr::Unary(*op, Arc::new(go(inner))),
+        Expr::Binary(l, op, r) => Expr::Binary(Arc::new(go(l)), *op, Arc::new(go(r))),
+        Expr::Spread(inner) => Expr::Spread(Arc::new(go(inner))),
+        Expr::TypeCast { expr, ty } => Expr::TypeCast { expr: Arc::new(go(expr)), ty: ty.clone() },
+        Expr::Assignment { variable, value } => {
+            Expr::Assignment { variable: variable.clone(), value: Arc::new(go(value)) }
+        }
+        Expr::Destructure { pattern, value } => Expr::Destructure { pattern: pattern.clone(), value: Arc::new(go(value)) },
+        Expr::PropertyAccess { target, property } => {
+            Expr::PropertyAccess { target: Arc::new(go(target)), property: property.clone() }
+        }
+        Expr::SafePropertyAccess { target, property } => {
+            Expr::SafePropertyAccess { target: Arc::new(go(target)), property: property.clone() }
+        }
+        Expr::Index { target, index } => Expr::Index { target: Arc::new(go(target)), index: Arc::new(go(index)) },
+        Expr::Slice { target, start, end } => Expr::Slice {
+            target: Arc::new(go(target)),
+            start: start.as_ref().map(|e| Arc::new(go(e))),
+            end: end.as_ref().map(|e| Arc::new(go(e))),
+        },
+        Expr::Array(items) => Expr::Array(items.iter().map(go).collect()),
+        Expr::Sequence(items) => Expr::Sequence(items.iter().map(go).collect()),
+        Expr::Lambda { params, body } => Expr::Lambda { params: params.clone(), body: Arc::new(go(body)) },
+        Expr::ObjectLiteral(fields) => Expr::ObjectLiteral(fields.iter().map(|(k, v)| (k.clone(), go(v))).collect()),
+        Expr::FunctionCall { name, args } => {
+            Expr::FunctionCall { name: name.clone(), args: args.iter().map(go).collect() }
+        }
+        Expr::MethodCall { target, name, args, predicate } => Expr::MethodCall {
+            target: Arc::new(go(target)),
+            name: name.clone(),
+            args: args.iter().map(&mut go).collect(),
+            predicate: *predicate,
+        },
+        Expr::SafeMethodCall { target, name, args } => Expr::SafeMethodCall {
+            target: Arc::new(go(target)),
+            name: name.clone(),
+            args: args.iter().map(&mut go).collect(),
+        },
+    }
+}
@@ -0,0 +1,249 @@
+//! Step-through debugging for embedders.
+//!
+//! A [`DebugSession`] parses an expression and numbers its nodes in pre-order
+//! ([`NodeId`]). [`DebugSession::run`] evaluates it and calls the [`Debugger`]
+//! before any node where execution pauses: at a breakpoint, or wherever the
+//! previous [`DebugAction`] asked to stop. The callback sees the node, its depth
+//! and the variables in scope, and answers with the next action.
+//!
+//! The callback runs on the evaluating thread. An interactive front end would
+//! typically run the session on a worker thread and have the callback block on
+//! a channel until the user picks an action.
+
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::ast::Expr;
+use crate::error::Error;
+use crate::runtime::evaluator::{eval_with_assignments_context, EvaluationContext, VariableContext};
+use crate::runtime::observer::{with_observer, Observer};
+use crate::types::Value;
+
+/// Pre-order position of a node in the session's expression; the root is 0
+pub type NodeId = usize;
+
+/// What to do after a pause
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugAction {
+    /// Run until the next breakpoint
+    Continue,
+    /// Pause before the next node, descending into sub-expressions
+    Step,
+    /// Pause before the next node that is not inside the current one
+    Next,
+    /// Finish the enclosing node and pause before whatever runs after it
+    StepOut,
+    /// Stop evaluating; `run` returns an error
+    Abort,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseReason {
+    /// First node, when the session stops on entry
+    Entry,
+    Breakpoint,
+    /// Requested by the previous action
+    Step,
+}
+
+/// State visible to the debugger while execution is paused
+pub struct Pause<'a> {
+    /// `None` for nodes evaluated outside the session's tree (e.g. by a nested evaluation)
+    pub node: Option<NodeId>,
+    pub expr: &'a Expr,
+    /// Nesting level of the node being evaluated; the root is 1
+    pub depth: usize,
+    pub reason: PauseReason,
+    env: &'a dyn EvaluationContext,
+}
+
+impl Pause<'_> {
+    /// The node rendered back to source
+    pub fn source(&self) -> String {
+        self.expr.to_string()
+    }
+
+    /// A variable as the node will see it, including lambda parameters and earlier assignments
+    pub fn variable(&self, name: &str) -> Option<&Value> {
+        self.env.get_variable(name)
+    }
+
+    /// Every variable in scope
+    pub fn variables(&self) -> HashMap<String, Value> {
+        self.env.clone_variables()
+    }
+}
+
+/// Callbacks driven by [`DebugSession::run`]
+pub trait Debugger {
+    /// Execution is paused before `pause.expr` is evaluated
+    fn on_pause(&mut self, pause: &Pause<'_>) -> DebugAction;
+
+    /// A node finished evaluating. Called for every node, paused or not.
+    fn on_result(&mut self, _node: Option<NodeId>, _expr: &Expr, _result: &Result<Value, Error>) {}
+}
+
+/// A parsed expression with breakpoints
+#[derive(Debug, Clone)]
+pub struct DebugSession {
+    expr: Arc<Expr>,
+    /// Node address -> id; the tree is shared behind `Arc`, so addresses are stable
+    ids: HashMap<usize, NodeId>,
+    sources: Vec<String>,
+    breakpoints: BTreeSet<NodeId>,
+    stop_on_entry: bool,
+}
+
+impl DebugSession {
+    pub fn new(input: &str) -> Result<Self, Error> {
+        let expr = Arc::new(crate::parse(input)?);
+        let mut session = Self {
+            expr: expr.clone(),
+            ids: HashMap::new(),
+            sources: Vec::new(),
+            breakpoints: BTreeSet::new(),
+            stop_on_entry: false,
+        };
+        session.number(&expr);
+        Ok(session)
+    }
+
+    fn number(&mut self, expr: &Expr) {
+        self.ids.insert(expr as *const Expr as usize, self.sources.len());
+        self.sources.push(expr.to_string());
+        for child in children(expr) {
+            self.number(child);
+        }
+    }
+
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    /// Source of every node, indexed by [`NodeId`]
+    pub fn nodes(&self) -> &[String] {
+        &self.sources
+    }
+
+    /// Nodes whose source is exactly `source` (as rendered by [`nodes`](Self::nodes))
+    pub fn find(&self, source: &str) -> Vec<NodeId> {
+        self.sources.iter().enumerate().filter(|(_, s)| *s == source).map(|(i, _)| i).collect()
+    }
+
+    pub fn add_breakpoint(&mut self, node: NodeId) -> Result<(), Error> {
+        if node >= self.sources.len() {
+            return Err(Error::new(format!("No node {} (expression has {})", node, self.sources.len()), None));
+        }
+        self.breakpoints.insert(node);
+        Ok(())
+    }
+
+    pub fn remove_breakpoint(&mut self, node: NodeId) -> bool {
+        self.breakpoints.remove(&node)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    /// Pause before the first node, without needing a breakpoint
+    pub fn with_stop_on_entry(mut self, stop: bool) -> Self {
+        self.stop_on_entry = stop;
+        self
+    }
+
+    /// Evaluate with `vars` (assignments and custom functions supported), pausing
+    /// into `debugger`. The debugger is handed back with the result.
+    pub fn run<D: Debugger + 'static>(&self, vars: &HashMap<String, Value>, debugger: D) -> (Result<Value, Error>, D) {
+        let stepper = Rc::new(RefCell::new(Stepper {
+            debugger,
+            ids: self.ids.clone(),
+            breakpoints: self.breakpoints.clone(),
+            mode: if self.stop_on_entry { Mode::Entry } else { Mode::Run },
+            depth: 0,
+        }));
+        let registry = crate::registry_snapshot();
+        let result = with_observer(Box::new(stepper.clone()), || {
+            let mut context = VariableContext::with_custom(vars, &registry);
+            eval_with_assignments_context(&self.expr, &mut context)
+        });
+        let stepper = match Rc::try_unwrap(stepper) {
+            Ok(cell) => cell.into_inner(),
+            Err(_) => unreachable!("observer released when the evaluation ends"),
+        };
+        (result, stepper.debugger)
+    }
+}
+
+enum Mode {
+    Entry,
+    Run,
+    Step,
+    /// Pause at depth <= n
+    Within(usize),
+}
+
+struct Stepper<D> {
+    debugger: D,
+    ids: HashMap<usize, NodeId>,
+    breakpoints: BTreeSet<NodeId>,
+    mode: Mode,
+    depth: usize,
+}
+
+impl<D: Debugger> Observer for Stepper<D> {
+    fn enter(&mut self, expr: &Expr, context: &dyn EvaluationContext) -> Result<(), Error> {
+        self.depth += 1;
+        let node = self.ids.get(&(expr as *const Expr as usize)).copied();
+        let reason = match self.mode {
+            _ if node.is_some_and(|n| self.breakpoints.contains(&n)) => Some(PauseReason::Breakpoint),
+            Mode::Entry => Some(PauseReason::Entry),
+            Mode::Step => Some(PauseReason::Step),
+            Mode::Within(depth) if self.depth <= depth => Some(PauseReason::Step),
+            _ => None,
+        };
+        let Some(reason) = reason else { return Ok(()) };
+
+        let pause = Pause { node, expr, depth: self.depth, reason, env: context };
+        self.mode = match self.debugger.on_pause(&pause) {
+            DebugAction::Continue => Mode::Run,
+            DebugAction::Step => Mode::Step,
+            DebugAction::Next => Mode::Within(self.depth),
+            DebugAction::StepOut => Mode::Within(self.depth.saturating_sub(1)),
+            DebugAction::Abort => return Err(Error::new("Evaluation aborted by debugger", None)),
+        };
+        Ok(())
+    }
+
+    fn exit(&mut self, expr: &Expr, result: &Result<Value, Error>, _elapsed: std::time::Duration) {
+        self.depth = self.depth.saturating_sub(1);
+        let node = self.ids.get(&(expr as *const Expr as usize)).copied();
+        self.debugger.on_result(node, expr, result);
+    }
+}
+
+/// Direct sub-expressions in evaluation order
+fn children(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::Number(_) | Expr::StringLit(_) | Expr::Null | Expr::Variable(_) => vec![],
+        Expr::Unary(_, inner) | Expr::Spread(inner) => vec![inner.as_ref()],
+        Expr::TypeCast { expr, .. } => vec![expr.as_ref()],
+        Expr::Assignment { value, .. } | Expr::Destructure { value, .. } => vec![value.as_ref()],
+        Expr::Lambda { body, .. } => vec![body.as_ref()],
+        Expr::Binary(l, _, r) => vec![l.as_ref(), r.as_ref()],
+        Expr::PropertyAccess { target, .. } | Expr::SafePropertyAccess { target, .. } => vec![target.as_ref()],
+        Expr::Index { target, index } => vec![target.as_ref(), index.as_ref()],
+        Expr::Slice { target, start, end } => {
+            let mut out = vec![target.as_ref()];
+            out.extend(start.iter().chain(end.iter()).map(|e| e.as_ref()));
+            out
+        }
+        Expr::Array(items) | Expr::Sequence(items) | Expr::FunctionCall { args: items, .. } => items.iter().collect(),
+        Expr::ObjectLiteral(fields) => fields.iter().map(|(_, v)| v).collect(),
+        Expr::MethodCall { target, args, .. } | Expr::SafeMethodCall { target, args, .. } => {
+            std::iter::once(target.as_ref()).chain(args.iter()).collect()
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Weighted term dictionaries for keyword scoring.
+//!
+//! The host registers a [`DictionaryProvider`] with [`set_dictionary_provider`];
+//! `SCOREDICT(text, "sentiment")` asks it for the `sentiment` dictionary and adds up
+//! the weights of the terms found in the text. Word lists stay in the host, where
+//! they can be edited or reloaded, instead of being spelled out in formulas.
+//!
+//! Text and terms are split into words with `TOKENIZE`'s rules, so matching ignores
+//! case and punctuation and only whole words match. A term of several words
+//! ("not working") matches that exact sequence. Every occurrence of every term
+//! counts, so "password reset" and "reset" both score in "password reset".
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arc_swap::ArcSwapOption;
+
+use crate::error::Error;
+use crate::types::Value;
+
+/// Term weights of one dictionary
+pub type Dictionary = HashMap<String, f64>;
+
+/// Supplies dictionaries by name for `SCOREDICT`
+pub trait DictionaryProvider: Send + Sync {
+    /// The dictionary called `name`, or `None` when there is no such dictionary
+    fn dictionary(&self, name: &str) -> Option<Arc<Dictionary>>;
+}
+
+/// Fixed dictionaries, e.g. loaded from files at startup
+impl DictionaryProvider for HashMap<String, Arc<Dictionary>> {
+    fn dictionary(&self, name: &str) -> Option<Arc<Dictionary>> {
+        self.get(name).cloned()
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref PROVIDER: ArcSwapOption<Box<dyn DictionaryProvider>> = ArcSwapOption::empty();
+}
+
+/// Install the provider consulted by SCOREDICT, replacing any previous one
+pub fn set_dictionary_provider(provider: Box<dyn DictionaryProvider>) {
+    PROVIDER.store(Some(Arc::new(provider)));
+}
+
+pub fn clear_dictionary_provider() {
+    PROVIDER.store(None);
+}
+
+/// Sum of `weight * occurrences` for every dictionary term found in `tokens`
+fn score(tokens: &[String], dictionary: &Dictionary, locale: &str) -> f64 {
+    // Terms grouped by their first word, so each position only tries likely terms
+    let mut by_first: HashMap<String, Vec<(Vec<String>, f64)>> = HashMap::new();
+    for (term, weight) in dictionary {
+        let words = crate::runtime::tokens::tokenize(term, locale);
+        if let Some(first) = words.first() {
+            by_first.entry(first.clone()).or_default().push((words, *weight));
+        }
+    }
+    let mut total = 0.0;
+    for (i, token) in tokens.iter().enumerate() {
+        for (words, weight) in by_first.get(token).into_iter().flatten() {
+            if tokens[i..].starts_with(words) {
+                total += weight;
+            }
+        }
+    }
+    total
+}
+
+/// SCOREDICT(text, dictionary, [locale]) -> total weight of the dictionary's terms
+/// in `text`, which may also be an array of tokens
+pub(crate) fn exec_score_dict(args: &[Value]) -> Result<Value, Error> {
+    let (text, name) = match args {
+        [text, Value::String(name)] | [text, Value::String(name), _] => (text, name),
+        _ => return Err(Error::new("SCOREDICT expects text, dictionary name, [locale]", None)),
+    };
+    let locale = crate::runtime::tokens::locale_arg("SCOREDICT", args.get(2))?;
+    let tokens = match text {
+        Value::String(text) => crate::runtime::tokens::tokenize(text, &locale),
+        other => crate::runtime::tokens::token_list("SCOREDICT", other)?,
+    };
+    let provider = PROVIDER.load();
+    let provider = provider.as_ref().ok_or_else(|| {
+        Error::new("SCOREDICT needs a dictionary provider; the host registers one with set_dictionary_provider", None)
+    })?;
+    let dictionary = provider
+        .dictionary(name)
+        .ok_or_else(|| Error::new(format!("SCOREDICT: no dictionary named '{}'", name), None))?;
+    Ok(Value::Number(score(&tokens, &dictionary, &locale)))
+}
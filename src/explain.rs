@@ -0,0 +1,128 @@
+//! Explain mode: evaluate an expression and record every sub-expression's value.
+//!
+//! [`evaluate_explained`] returns a tree that mirrors the evaluation: each node has
+//! the sub-expression's source text, the value it produced (or the error it
+//! raised) and how long it took. Lambda bodies appear once per element they ran
+//! for. Nodes below `max_depth`, or beyond [`MAX_EXPLAIN_NODES`] in total, are still
+//! evaluated but not recorded; their parent is marked `truncated`.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::ast::Expr;
+use crate::error::Error;
+use crate::runtime::evaluator::{eval_with_assignments_context, EvaluationContext, VariableContext};
+use crate::runtime::observer::{with_observer, Observer};
+use crate::types::Value;
+
+/// Upper bound on recorded nodes, so explaining a loop over a large array stays cheap
+pub const MAX_EXPLAIN_NODES: usize = 10_000;
+
+/// One evaluated sub-expression
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExplainNode {
+    /// The sub-expression, rendered back to source
+    pub source: String,
+    /// `Ok` with the value produced, or the error message
+    pub result: Result<Value, String>,
+    pub duration: Duration,
+    /// Sub-expressions in evaluation order
+    pub children: Vec<ExplainNode>,
+    /// Some descendants were evaluated but not recorded
+    pub truncated: bool,
+}
+
+#[derive(Default)]
+struct Tracer {
+    max_depth: usize,
+    recorded: usize,
+    /// Nodes currently being evaluated; `None` for ones that are not recorded
+    open: Vec<Option<ExplainNode>>,
+    root: Option<ExplainNode>,
+}
+
+impl Observer for Tracer {
+    fn enter(&mut self, expr: &Expr, _context: &dyn EvaluationContext) -> Result<(), Error> {
+        let parent_recorded = self.open.last().is_none_or(|p| p.is_some());
+        let record = parent_recorded && self.open.len() < self.max_depth && self.recorded < MAX_EXPLAIN_NODES;
+        if record {
+            self.recorded += 1;
+            self.open.push(Some(ExplainNode {
+                source: expr.to_string(),
+                result: Ok(Value::Null),
+                duration: Duration::ZERO,
+                children: Vec::new(),
+                truncated: false,
+            }));
+        } else {
+            if let Some(Some(parent)) = self.open.last_mut() {
+                parent.truncated = true;
+            }
+            self.open.push(None);
+        }
+        Ok(())
+    }
+
+    fn exit(&mut self, _expr: &Expr, result: &Result<Value, Error>, elapsed: Duration) {
+        let Some(Some(mut node)) = self.open.pop() else { return };
+        node.result = result.clone().map_err(|e| e.message);
+        node.duration = elapsed;
+        match self.open.last_mut() {
+            Some(Some(parent)) => parent.children.push(node),
+            _ => self.root = Some(node),
+        }
+    }
+}
+
+/// Evaluate `input` like [`evaluate_with_assignments`](crate::evaluate_with_assignments)
+/// (custom functions included) and return the evaluation tree, recording at most
+/// `max_depth` levels. Parse errors are returned as `Err`; evaluation errors are
+/// part of the tree.
+pub fn evaluate_explained(
+    input: &str,
+    vars: &HashMap<String, Value>,
+    max_depth: usize,
+) -> Result<ExplainNode, Error> {
+    let expr = crate::parse(input)?;
+    let tracer = Rc::new(RefCell::new(Tracer { max_depth: max_depth.max(1), ..Tracer::default() }));
+    let registry = crate::registry_snapshot();
+    let result = with_observer(Box::new(tracer.clone()), || {
+        let mut context = VariableContext::with_custom(vars, &registry);
+        eval_with_assignments_context(&expr, &mut context)
+    });
+
+    let root = tracer.borrow_mut().root.take();
+    Ok(root.unwrap_or_else(|| ExplainNode {
+        source: expr.to_string(),
+        result: result.map_err(|e| e.message),
+        duration: Duration::ZERO,
+        children: Vec::new(),
+        truncated: false,
+    }))
+}
+
+impl ExplainNode {
+    /// Indented text rendering, one node per line: `source => value (duration)`
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        self.write_text(&mut out, 0);
+        out
+    }
+
+    fn write_text(&self, out: &mut String, depth: usize) {
+        use std::fmt::Write;
+        let outcome = match &self.result {
+            Ok(v) => format!("{:?}", v),
+            Err(e) => format!("error: {}", e),
+        };
+        let _ = writeln!(out, "{}{} => {} ({:?})", "  ".repeat(depth), self.source, outcome, self.duration);
+        for child in &self.children {
+            child.write_text(out, depth + 1);
+        }
+        if self.truncated {
+            let _ = writeln!(out, "{}...", "  ".repeat(depth + 1));
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! Feature flags for formulas.
+//!
+//! The host registers a [`FeatureProvider`] with [`set_feature_provider`];
+//! `FEATURE("new_pricing")` asks it whether the flag is on for the current
+//! evaluation. The provider is only consulted when a `FEATURE` call is actually
+//! evaluated, so a flag in an untaken `IF` branch costs nothing. Each call shows up
+//! in [`evaluate_explained`](crate::evaluate_explained) traces with its result.
+
+use std::sync::Arc;
+
+use arc_swap::ArcSwapOption;
+
+use crate::context::EvalContext;
+use crate::error::Error;
+use crate::types::Value;
+
+/// Answers flag lookups for `FEATURE(name)`
+pub trait FeatureProvider: Send + Sync {
+    /// Whether `name` is enabled. `context` is the evaluation's [`EvalContext`], when
+    /// the host installed one, for per-request targeting. `None` means the provider
+    /// does not know the flag, and FEATURE falls back to its default.
+    fn is_enabled(&self, name: &str, context: Option<&EvalContext>) -> Option<bool>;
+}
+
+/// Fixed flags, e.g. loaded from configuration at startup
+impl FeatureProvider for std::collections::HashMap<String, bool> {
+    fn is_enabled(&self, name: &str, _context: Option<&EvalContext>) -> Option<bool> {
+        self.get(name).copied()
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref PROVIDER: ArcSwapOption<Box<dyn FeatureProvider>> = ArcSwapOption::empty();
+}
+
+/// Install the provider consulted by FEATURE, replacing any previous one
+pub fn set_feature_provider(provider: Box<dyn FeatureProvider>) {
+    PROVIDER.store(Some(Arc::new(provider)));
+}
+
+pub fn clear_feature_provider() {
+    PROVIDER.store(None);
+}
+
+/// FEATURE(name, [default]) -> boolean. Without a provider, or for flags the
+/// provider does not know, the default (FALSE unless given) is returned.
+pub(crate) fn exec_feature(args: &[Value]) -> Result<Value, Error> {
+    let (name, default) = match args {
+        [Value::String(name)] => (name, false),
+        [Value::String(name), Value::Boolean(default)] => (name, *default),
+        _ => return Err(Error::new("FEATURE expects a flag name and an optional boolean default", None)),
+    };
+    let enabled = PROVIDER.load().as_ref().and_then(|provider| {
+        let context = crate::context::current();
+        provider.is_enabled(name, context.as_deref())
+    });
+    Ok(Value::Boolean(enabled.unwrap_or(default)))
+}
@@ -0,0 +1,190 @@
+//! Dependency graph over a set of named formulas.
+//!
+//! A formula refers to another one by using its name as a variable (`:subtotal`).
+//! [`FormulaGraph::build`] parses every formula, links those references, rejects
+//! circular ones and fixes an evaluation order in which each formula comes after
+//! everything it reads. Names that are not formulas are inputs supplied by the caller.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::analysis::analyze;
+use crate::compiled::CompiledExpr;
+use crate::error::Error;
+use crate::types::Value;
+
+#[derive(Debug, Clone)]
+struct Node {
+    formula: CompiledExpr,
+    /// Formulas this one reads, sorted
+    dependencies: Vec<String>,
+    /// Formulas that read this one, sorted
+    dependents: Vec<String>,
+    /// Non-formula variables this one reads, sorted
+    inputs: Vec<String>,
+}
+
+/// Parsed formulas with their references resolved and a topological order
+#[derive(Debug, Clone)]
+pub struct FormulaGraph {
+    nodes: BTreeMap<String, Node>,
+    order: Vec<String>,
+    inputs: Vec<String>,
+}
+
+impl FormulaGraph {
+    /// Parse `name -> expression` pairs and order them. Fails on the first parse
+    /// error (naming the formula) or on a circular reference (naming the cycle).
+    pub fn build<I, K, S>(formulas: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = (K, S)>,
+        K: Into<String>,
+        S: AsRef<str>,
+    {
+        let mut parsed = BTreeMap::new();
+        for (name, src) in formulas {
+            let name = name.into();
+            let formula = CompiledExpr::compile(src.as_ref())
+                .map_err(|e| Error::new(format!("Formula '{}': {}", name, e.message), e.position))?;
+            parsed.insert(name, formula);
+        }
+
+        let mut inputs = BTreeSet::new();
+        let mut nodes: BTreeMap<String, Node> = BTreeMap::new();
+        for (name, formula) in &parsed {
+            let (dependencies, reads): (Vec<String>, Vec<String>) =
+                analyze(formula.expr()).variables.into_iter().partition(|v| parsed.contains_key(v));
+            inputs.extend(reads.iter().cloned());
+            let node = Node { formula: formula.clone(), dependencies, dependents: Vec::new(), inputs: reads };
+            nodes.insert(name.clone(), node);
+        }
+        let edges: Vec<(String, String)> = nodes
+            .iter()
+            .flat_map(|(name, node)| node.dependencies.iter().map(move |dep| (dep.clone(), name.clone())))
+            .collect();
+        for (dep, name) in edges {
+            if let Some(node) = nodes.get_mut(&dep) {
+                node.dependents.push(name);
+            }
+        }
+
+        let order = topological_order(&nodes)?;
+        Ok(Self { nodes, order, inputs: inputs.into_iter().collect() })
+    }
+
+    /// Every formula name, each after the formulas it depends on
+    pub fn order(&self) -> &[String] {
+        &self.order
+    }
+
+    /// Variables referenced by some formula that are not formulas themselves
+    pub fn inputs(&self) -> &[String] {
+        &self.inputs
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.nodes.contains_key(name)
+    }
+
+    pub fn formula(&self, name: &str) -> Option<&CompiledExpr> {
+        self.nodes.get(name).map(|n| &n.formula)
+    }
+
+    /// Formulas `name` reads directly
+    pub fn dependencies(&self, name: &str) -> &[String] {
+        self.nodes.get(name).map(|n| n.dependencies.as_slice()).unwrap_or(&[])
+    }
+
+    /// Formulas that read `name` directly
+    pub fn dependents(&self, name: &str) -> &[String] {
+        self.nodes.get(name).map(|n| n.dependents.as_slice()).unwrap_or(&[])
+    }
+
+    /// Formulas to recompute after `changed` (formulas or inputs) change, in
+    /// evaluation order. Changed formulas are included; changed inputs are not.
+    pub fn recalc_order<S: AsRef<str>>(&self, changed: &[S]) -> Vec<String> {
+        let mut dirty = BTreeSet::new();
+        let mut stack: Vec<&str> = Vec::new();
+        for c in changed {
+            let c = c.as_ref();
+            if self.nodes.contains_key(c) {
+                stack.push(c);
+            } else {
+                // An input dirties every formula that reads it
+                let readers = self.nodes.iter().filter(|(_, n)| n.inputs.iter().any(|i| i == c));
+                stack.extend(readers.map(|(k, _)| k.as_str()));
+            }
+        }
+        while let Some(name) = stack.pop() {
+            if dirty.insert(name.to_string()) {
+                stack.extend(self.dependents(name).iter().map(String::as_str));
+            }
+        }
+        self.order.iter().filter(|n| dirty.contains(*n)).cloned().collect()
+    }
+
+    /// Evaluate every formula in order. Each result is visible to later formulas
+    /// under its name; `vars` supplies the inputs.
+    pub fn evaluate(&self, vars: &HashMap<String, Value>) -> Result<HashMap<String, Value>, Error> {
+        let mut scope = vars.clone();
+        let mut results = HashMap::with_capacity(self.order.len());
+        for name in &self.order {
+            let value = self.nodes[name]
+                .formula
+                .evaluate_with_custom(&scope)
+                .map_err(|e| Error::new(format!("Formula '{}': {}", name, e.message), e.position))?;
+            scope.insert(name.clone(), value.clone());
+            results.insert(name.clone(), value);
+        }
+        Ok(results)
+    }
+}
+
+/// Kahn's algorithm, taking ready formulas alphabetically so the order is stable
+fn topological_order(nodes: &BTreeMap<String, Node>) -> Result<Vec<String>, Error> {
+    let mut pending: BTreeMap<&str, usize> =
+        nodes.iter().map(|(name, n)| (name.as_str(), n.dependencies.len())).collect();
+    let mut ready: BTreeSet<&str> = pending.iter().filter(|(_, &d)| d == 0).map(|(&n, _)| n).collect();
+    let mut order = Vec::with_capacity(nodes.len());
+
+    while let Some(name) = ready.pop_first() {
+        pending.remove(name);
+        order.push(name.to_string());
+        for dependent in &nodes[name].dependents {
+            if let Some(count) = pending.get_mut(dependent.as_str()) {
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert(dependent.as_str());
+                }
+            }
+        }
+    }
+
+    if pending.is_empty() {
+        Ok(order)
+    } else {
+        let cycle = find_cycle(nodes, &pending.keys().copied().collect());
+        Err(Error::new(format!("Circular reference: {}", cycle.join(" -> ")), None))
+    }
+}
+
+/// Follow dependencies among the unresolved formulas until one repeats.
+/// Every unresolved formula still waits on another unresolved one, so the walk
+/// always closes a loop.
+fn find_cycle(nodes: &BTreeMap<String, Node>, unresolved: &BTreeSet<&str>) -> Vec<String> {
+    let mut path: Vec<&str> = Vec::new();
+    let mut current = *unresolved.iter().next().expect("at least one unresolved formula");
+    loop {
+        if let Some(start) = path.iter().position(|&n| n == current) {
+            let mut cycle: Vec<String> = path[start..].iter().map(|s| s.to_string()).collect();
+            cycle.push(current.to_string());
+            return cycle;
+        }
+        path.push(current);
+        current = nodes[current]
+            .dependencies
+            .iter()
+            .map(String::as_str)
+            .find(|d| unresolved.contains(d))
+            .expect("unresolved formula has an unresolved dependency");
+    }
+}
@@ -1,7 +1,7 @@
-use crate::custom::CustomFunction;
+use crate::custom::{CustomFunction, ParamDoc};
 use crate::error::Error;
 use crate::types::Value;
-use rquickjs::{Runtime, Function as JsFunction, FromJs, IntoJs, Ctx};
+use rquickjs::{Runtime, Function as JsFunction, FromJs, IntoJs, Ctx, CatchResultExt};
 use std::fs;
 use std::path::Path;
 
@@ -12,6 +12,10 @@ pub struct JavaScriptFunction {
     max_args: Option<usize>,
     description: Option<String>,
     example: Option<String>,
+    category: Option<String>,
+    params: Vec<ParamDoc>,
+    returns: Option<String>,
+    ui_hints: Vec<(String, String)>,
     js_code: String,
 }
 
@@ -31,6 +35,10 @@ impl JavaScriptFunction {
             max_args,
             description,
             example,
+            category: None,
+            params: Vec::new(),
+            returns: None,
+            ui_hints: Vec::new(),
             js_code,
         })
     }
@@ -56,17 +64,28 @@ impl JavaScriptFunction {
     /// // @max_args: 2
     /// // @description: My custom function
     /// // @example: MYFUNCTION(5) returns 10
+    /// // @category: Math
+    /// // @param: value - The number to double
+    /// // @param: factor - Optional multiplier
+    /// // @returns: The doubled number
+    /// // @ui: widget=number, unit=EUR
     /// function execute(args) {
     ///     // Implementation here
     ///     return args[0] * 2;
     /// }
     /// ```
+    /// `@param` and `@ui` may repeat; a parameter's description after its name is
+    /// optional.
     fn parse_js_function_internal(js_code: &str) -> Result<Self, Error> {
         let mut name = None;
         let mut min_args = 1;
         let mut max_args = None;
         let mut description = None;
         let mut example = None;
+        let mut category = None;
+        let mut params = Vec::new();
+        let mut returns = None;
+        let mut ui_hints = Vec::new();
 
         // Parse metadata from comments
         for line in js_code.lines() {
@@ -87,12 +106,45 @@ impl JavaScriptFunction {
                 description = Some(rest.trim().to_string());
             } else if let Some(rest) = line.strip_prefix("// @example:") {
                 example = Some(rest.trim().to_string());
+            } else if let Some(rest) = line.strip_prefix("// @category:") {
+                category = Some(rest.trim().to_string());
+            } else if let Some(rest) = line.strip_prefix("// @param:") {
+                params.push(Self::parse_param(rest)?);
+            } else if let Some(rest) = line.strip_prefix("// @returns:") {
+                returns = Some(rest.trim().to_string());
+            } else if let Some(rest) = line.strip_prefix("// @ui:") {
+                for hint in rest.split(',').map(str::trim).filter(|h| !h.is_empty()) {
+                    let (key, value) = hint.split_once('=')
+                        .ok_or_else(|| Error::new(format!("Invalid @ui hint '{}', expected key=value", hint), None))?;
+                    ui_hints.push((key.trim().to_string(), value.trim().to_string()));
+                }
             }
         }
 
         let name = name.ok_or_else(|| Error::new("JavaScript function must have @name annotation", None))?;
 
-        Self::new(name, min_args, max_args, description, example, js_code.to_string())
+        let mut function = Self::new(name, min_args, max_args, description, example, js_code.to_string())?;
+        function.category = category;
+        function.params = params;
+        function.returns = returns;
+        function.ui_hints = ui_hints;
+        Ok(function)
+    }
+
+    /// `name - description` or just `name`
+    fn parse_param(text: &str) -> Result<ParamDoc, Error> {
+        let text = text.trim();
+        let (name, description) = match text.split_once(char::is_whitespace) {
+            Some((name, rest)) => (name, Some(rest.trim().trim_start_matches('-').trim())),
+            None => (text, None),
+        };
+        if name.is_empty() {
+            return Err(Error::new("@param needs a parameter name", None));
+        }
+        Ok(ParamDoc {
+            name: name.to_string(),
+            description: description.filter(|d| !d.is_empty()).map(str::to_string),
+        })
     }
 
     /// Convert Skillet Value to JavaScript value
@@ -120,6 +172,18 @@ impl JavaScriptFunction {
                 // For JSON, we'll just convert to string for now
                 json_str.clone().into_js(ctx).map_err(|e| Error::new(format!("JS conversion error: {}", e), None))
             }
+            Value::Map(map) => {
+                let js_object = rquickjs::Object::new(ctx.clone())
+                    .map_err(|e| Error::new(format!("Failed to create JS object: {}", e), None))?;
+
+                for (key, item) in map {
+                    let js_val = Self::value_to_js(ctx, item)?;
+                    js_object.set(key.as_str(), js_val)
+                        .map_err(|e| Error::new(format!("Failed to set object field: {}", e), None))?;
+                }
+
+                js_object.into_js(ctx).map_err(|e| Error::new(format!("JS conversion error: {}", e), None))
+            }
         }
     }
 
@@ -148,6 +212,19 @@ impl JavaScriptFunction {
             
         Ok(())
     }
+
+    /// Expose the current evaluation context as a global `skilletContext` object
+    fn add_context_object(ctx: &Ctx) -> Result<(), Error> {
+        let obj = rquickjs::Object::new(ctx.clone())
+            .map_err(|e| Error::new(format!("Failed to create context object: {}", e), None))?;
+        if let Some(request_id) = crate::context::current_request_id() {
+            obj.set("requestId", request_id)
+                .map_err(|e| Error::new(format!("Failed to set requestId: {}", e), None))?;
+        }
+        ctx.globals().set("skilletContext", obj)
+            .map_err(|e| Error::new(format!("Failed to set context object: {}", e), None))?;
+        Ok(())
+    }
     
 
     /// Convert JavaScript value to Skillet Value
@@ -208,6 +285,38 @@ impl JavaScriptFunction {
     }
 }
 
+impl JavaScriptFunction {
+    /// Compile the source in a fresh runtime and check that it defines `execute`,
+    /// without calling it. Top-level statements of the file do run.
+    pub fn compile(&self) -> Result<(), Error> {
+        let runtime = Runtime::new()
+            .map_err(|e| Error::new(format!("Failed to create JS runtime: {}", e), None))?;
+
+        let ctx = rquickjs::Context::full(&runtime)
+            .map_err(|e| Error::new(format!("Failed to create JS context: {}", e), None))?;
+
+        ctx.with(|ctx| self.load(&ctx).map(|_| ()))
+    }
+
+    /// Run the source in `ctx` and return its `execute` function
+    fn load<'js>(&self, ctx: &Ctx<'js>) -> Result<JsFunction<'js>, Error> {
+        // Add HTTP functionality to the JavaScript context
+        Self::add_http_functions(ctx)?;
+        Self::add_context_object(ctx)?;
+
+        // SQLite integration removed
+
+        // Execute the JavaScript code
+        ctx.eval::<(), _>(self.js_code.as_bytes())
+            .catch(ctx)
+            .map_err(|e| Error::new(format!("JS execution error: {}", e), None))?;
+
+        // Get the execute function
+        ctx.globals().get("execute")
+            .map_err(|e| Error::new(format!("Function 'execute' not found in JS code: {}", e), None))
+    }
+}
+
 impl CustomFunction for JavaScriptFunction {
     fn name(&self) -> &str {
         &self.name
@@ -230,18 +339,7 @@ impl CustomFunction for JavaScriptFunction {
             .map_err(|e| Error::new(format!("Failed to create JS context: {}", e), None))?;
 
         ctx.with(|ctx| {
-            // Add HTTP functionality to the JavaScript context
-            Self::add_http_functions(&ctx)?;
-            
-            // SQLite integration removed
-            
-            // Execute the JavaScript code
-            ctx.eval::<(), _>(self.js_code.as_bytes())
-                .map_err(|e| Error::new(format!("JS execution error: {}", e), None))?;
-
-            // Get the execute function
-            let execute_fn: JsFunction = ctx.globals().get("execute")
-                .map_err(|e| Error::new(format!("Function 'execute' not found in JS code: {}", e), None))?;
+            let execute_fn = self.load(&ctx)?;
 
             // Convert Skillet values to JavaScript values
             let js_args = rquickjs::Array::new(ctx.clone())
@@ -269,6 +367,26 @@ impl CustomFunction for JavaScriptFunction {
     fn example(&self) -> Option<&str> {
         self.example.as_deref()
     }
+
+    fn category(&self) -> Option<&str> {
+        self.category.as_deref()
+    }
+
+    fn budget_category(&self) -> &'static str {
+        "javascript"
+    }
+
+    fn params(&self) -> &[ParamDoc] {
+        &self.params
+    }
+
+    fn returns(&self) -> Option<&str> {
+        self.returns.as_deref()
+    }
+
+    fn ui_hints(&self) -> &[(String, String)] {
+        &self.ui_hints
+    }
 }
 
 /// JavaScript plugin loader
@@ -364,6 +482,54 @@ mod tests {
         assert_eq!(js_func.max_args(), Some(1));
         assert_eq!(js_func.description(), Some("Doubles a number"));
         assert_eq!(js_func.example(), Some("DOUBLE(5) returns 10"));
+        assert_eq!(js_func.category(), None);
+        assert!(js_func.params().is_empty());
+    }
+
+    #[test]
+    fn test_js_function_compile() {
+        let valid = JavaScriptFunction::parse_js_function("// @name: OK\nfunction execute(args) { return 1; }").unwrap();
+        assert!(valid.compile().is_ok());
+
+        let broken = JavaScriptFunction::parse_js_function("// @name: BROKEN\nfunction execute(args) { return 1;").unwrap();
+        assert!(broken.compile().unwrap_err().message.contains("JS execution error"));
+
+        let missing = JavaScriptFunction::parse_js_function("// @name: MISSING\nfunction run(args) { return 1; }").unwrap();
+        assert!(missing.compile().unwrap_err().message.contains("'execute' not found"));
+    }
+
+    #[test]
+    fn test_js_function_metadata_parsing() {
+        let js_code = r#"
+            // @name: WITHTAX
+            // @min_args: 1
+            // @max_args: 2
+            // @category: Finance
+            // @param: amount - Net amount
+            // @param: rate
+            // @returns: Gross amount
+            // @ui: widget=currency, unit=EUR
+            // @ui: color = green
+
+            function execute(args) {
+                return args[0] * (1 + (args[1] || 0.21));
+            }
+        "#;
+
+        let js_func = JavaScriptFunction::parse_js_function_internal(js_code).unwrap();
+        assert_eq!(js_func.category(), Some("Finance"));
+        assert_eq!(js_func.params(), &[
+            ParamDoc { name: "amount".to_string(), description: Some("Net amount".to_string()) },
+            ParamDoc { name: "rate".to_string(), description: None },
+        ]);
+        assert_eq!(js_func.returns(), Some("Gross amount"));
+        let hints: Vec<(&str, &str)> = js_func.ui_hints().iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+        assert_eq!(hints, vec![("widget", "currency"), ("unit", "EUR"), ("color", "green")]);
+
+        let Err(err) = JavaScriptFunction::parse_js_function_internal("// @name: X\n// @ui: widget") else {
+            panic!("a @ui hint without '=' should be rejected");
+        };
+        assert!(err.message.contains("key=value"), "{}", err.message);
     }
 
     #[test]
@@ -407,4 +573,24 @@ mod tests {
             _ => panic!("Expected string result"),
         }
     }
+
+    #[test]
+    fn test_js_sees_request_id() {
+        let js_code = r#"
+            // @name: REQID
+            // @min_args: 0
+            // @max_args: 0
+
+            function execute(args) {
+                return skilletContext.requestId || null;
+            }
+        "#;
+
+        let js_func = JavaScriptFunction::parse_js_function_internal(js_code).unwrap();
+        assert_eq!(js_func.execute(vec![]).unwrap(), Value::Null);
+
+        let ctx = crate::EvalContext::new().with_request_id("req-42");
+        let result = crate::context::with_context(ctx, || js_func.execute(vec![])).unwrap();
+        assert_eq!(result, Value::String("req-42".to_string()));
+    }
 }
@@ -5,6 +5,7 @@ pub enum Token {
     Number(f64),
     Identifier(String),
     String(String),
+    DateTime(String), // d"2024-05-01T10:00:00Z"
     True,
     False,
     Null,
@@ -17,7 +18,10 @@ pub enum Token {
     Bang,
     Dot,
     SafeNavigation, // &.
+    QuestionDot,    // ?. (safe navigation, or a predicate method followed by '.')
     Ellipsis,
+    DotDot,   // ..
+    DotDotEq, // ..=
     LParen,
     RParen,
     Comma,
@@ -37,6 +41,8 @@ pub enum Token {
     Or,
     AndAnd,
     OrOr,
+    Pipe,  // |>
+    Arrow, // ->
     QMark,
     Semicolon,
     ColonEquals,
@@ -277,6 +283,16 @@ impl<'a> Lexer<'a> {
 
         let tok = match ch {
             b'0'..=b'9' => return self.number(ch),
+            b'd' | b'D' if matches!(self.peek(), Some(b'"' | b'\'')) => {
+                let quote = self.bump().unwrap();
+                let start = self.pos - 2;
+                let tok = match self.string(quote)? {
+                    Token::String(s) => Token::DateTime(s),
+                    other => other,
+                };
+                self.last_start = start;
+                return Ok(tok);
+            }
             b'a'..=b'z' | b'A'..=b'Z' | b'_' => return self.identifier(ch),
             b'.' => {
                 // Ellipsis '...'
@@ -288,12 +304,16 @@ impl<'a> Lexer<'a> {
                         self.last_start = self.pos - 3;
                         self.last_end = self.pos;
                         Token::Ellipsis
+                    } else if matches!(self.peek(), Some(b'=')) {
+                        self.bump();
+                        self.last_start = save - 1;
+                        self.last_end = self.pos;
+                        Token::DotDotEq
                     } else {
-                        // Was just two dots, backtrack second
-                        self.pos = save;
-                        self.last_start = self.pos - 1;
+                        // Range operator '..'
+                        self.last_start = save - 1;
                         self.last_end = self.pos;
-                        Token::Dot
+                        Token::DotDot
                     }
                 } else if matches!(self.peek(), Some(b'0'..=b'9')) {
                     return self.number(ch);
@@ -304,7 +324,14 @@ impl<'a> Lexer<'a> {
                 }
             }
             b'+' => Token::Plus,
-            b'-' => Token::Minus,
+            b'-' => {
+                if matches!(self.peek(), Some(b'>')) {
+                    self.bump();
+                    Token::Arrow
+                } else {
+                    Token::Minus
+                }
+            }
             b'*' => Token::Star,
             b'/' => Token::Slash,
             b'%' => Token::Percent,
@@ -319,7 +346,16 @@ impl<'a> Lexer<'a> {
                     Token::Bang
                 }
             }
-            b'?' => Token::QMark,
+            b'?' => {
+                // `?.5` stays a ternary with a decimal operand
+                let next = self.input.get(self.pos + 1).copied();
+                if matches!(self.peek(), Some(b'.')) && next.is_some_and(|c| c.is_ascii_alphabetic() || c == b'_') {
+                    self.bump();
+                    Token::QuestionDot
+                } else {
+                    Token::QMark
+                }
+            }
             b'(' => Token::LParen,
             b')' => Token::RParen,
             b'[' => Token::LBracket,
@@ -360,7 +396,9 @@ impl<'a> Lexer<'a> {
                     self.bump();
                     Token::EqEq
                 } else {
-                    Token::EqEq
+                    self.last_start = self.pos - 1;
+                    self.last_end = self.pos;
+                    return Ok(Token::EqEq);
                 }
             }
             b'&' => {
@@ -378,6 +416,9 @@ impl<'a> Lexer<'a> {
                 if matches!(self.peek(), Some(b'|')) {
                     self.bump();
                     Token::OrOr
+                } else if matches!(self.peek(), Some(b'>')) {
+                    self.bump();
+                    Token::Pipe
                 } else {
                     return Err(Error::new("Unexpected '|'", Some(self.pos - 1)));
                 }
@@ -420,7 +461,10 @@ impl<'a> Lexer<'a> {
                 | Token::NotEq
                 | Token::AndAnd
                 | Token::OrOr
+                | Token::Pipe
+                | Token::Arrow
                 | Token::SafeNavigation
+                | Token::QuestionDot
         ) {
             self.last_start = self.pos - 2;
             self.last_end = self.pos;
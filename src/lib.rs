@@ -1,24 +1,63 @@
+pub mod analysis;
+#[cfg(feature = "arrow")]
+pub mod arrow;
 pub mod ast;
+pub mod canary;
+pub mod compiled;
+pub mod complete;
 pub mod concurrent_registry;
+pub mod constants;
+pub mod context;
+pub mod corpus;
+pub mod cost;
 pub mod custom;
+pub mod dataset;
+pub mod debugger;
+pub mod dictionaries;
 pub mod error;
+pub mod explain;
+pub mod features;
+pub mod graph;
 #[cfg(feature = "plugins")]
 pub mod js_plugin;
 pub mod lexer;
 pub mod memory_pool;
+pub mod options;
 pub mod parser;
 pub mod runtime;
+pub mod serialize;
+pub mod snapshot;
 pub mod traits;
 pub mod types;
+pub mod workbook;
 
+pub use analysis::{analyze, is_deterministic, Dependencies};
 pub use ast::Expr;
+pub use canary::{evaluate_canary, CanaryReport};
+pub use compiled::CompiledExpr;
+pub use complete::{complete, Completion, CompletionKind, Completions};
+pub use constants::{clear_constants, constant, remove_constant, set_constant};
+pub use context::{CallContext, EvalContext};
+pub use corpus::{Corpus, CorpusReport};
+pub use cost::{estimate_cost, Complexity, CostReport};
 pub use custom::{CustomFunction, FunctionRegistry};
+pub use dataset::{evaluate_per_row, evaluate_per_row_with, Dataset};
+pub use debugger::{DebugAction, DebugSession, Debugger, Pause};
+pub use dictionaries::{clear_dictionary_provider, set_dictionary_provider, Dictionary, DictionaryProvider};
 pub use error::Error;
+pub use explain::{evaluate_explained, ExplainNode};
+pub use features::{clear_feature_provider, set_feature_provider, FeatureProvider};
+pub use graph::FormulaGraph;
+pub use options::{EvalOptions, Strictness};
+pub use runtime::currency::{clear_cash_increments, set_cash_increment};
+pub use snapshot::EngineSnapshot;
 #[cfg(feature = "plugins")]
 pub use js_plugin::{JavaScriptFunction, JSPluginLoader};
 pub use types::Value;
+pub use workbook::Workbook;
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use arc_swap::ArcSwap;
+use std::sync::Arc;
 
 /// Sanitize JSON keys by replacing special characters with underscores
 fn sanitize_json_key(key: &str) -> String {
@@ -33,9 +72,17 @@ fn sanitize_json_key(key: &str) -> String {
         .collect()
 }
 
-// Global function registry
+// Global function registry, published as immutable snapshots. Evaluations load the
+// current snapshot once and never take a lock; (un)registration builds an updated copy
+// and swaps it in, so a slow plugin upload cannot stall evaluations in flight.
 lazy_static::lazy_static! {
-    static ref GLOBAL_REGISTRY: Arc<RwLock<FunctionRegistry>> = Arc::new(RwLock::new(FunctionRegistry::new()));
+    static ref GLOBAL_REGISTRY: ArcSwap<FunctionRegistry> = ArcSwap::from_pointee(FunctionRegistry::new());
+}
+
+/// Snapshot of the global registry as of now. Functions registered later are not
+/// visible through it, which gives a long evaluation a consistent view.
+pub fn registry_snapshot() -> Arc<FunctionRegistry> {
+    GLOBAL_REGISTRY.load_full()
 }
 
 /// Parse an arithmetic expression (optional leading '=') into an AST.
@@ -120,42 +167,109 @@ pub fn json_to_value(json: serde_json::Value) -> Result<Value, Error> {
 
 /// Register a custom function globally
 pub fn register_function(function: Box<dyn CustomFunction>) -> Result<(), Error> {
-    let mut registry = GLOBAL_REGISTRY.write()
-        .map_err(|_| Error::new("Failed to acquire registry lock", None))?;
-    registry.register(function)
+    // Validate outside the update so a failing registration publishes nothing
+    let mut staged = FunctionRegistry::new();
+    staged.register(function)?;
+    GLOBAL_REGISTRY.rcu(|current| {
+        let mut next = FunctionRegistry::clone(current);
+        next.merge(&staged);
+        next
+    });
+    Ok(())
 }
 
 /// Unregister a custom function by name
 pub fn unregister_function(name: &str) -> bool {
-    if let Ok(mut registry) = GLOBAL_REGISTRY.write() {
-        registry.unregister(name)
-    } else {
-        false
-    }
+    let mut removed = false;
+    GLOBAL_REGISTRY.rcu(|current| {
+        let mut next = FunctionRegistry::clone(current);
+        removed = next.unregister(name);
+        next
+    });
+    removed
 }
 
 /// List all registered custom functions
 pub fn list_custom_functions() -> Vec<String> {
-    if let Ok(registry) = GLOBAL_REGISTRY.read() {
-        registry.list_functions().iter().map(|s| s.to_string()).collect()
-    } else {
-        Vec::new()
-    }
+    GLOBAL_REGISTRY.load().list_functions().iter().map(|s| s.to_string()).collect()
 }
 
 /// Check if a custom function is registered
 pub fn has_custom_function(name: &str) -> bool {
-    if let Ok(registry) = GLOBAL_REGISTRY.read() {
-        registry.has_function(name)
-    } else {
-        false
-    }
+    GLOBAL_REGISTRY.load().has_function(name)
 }
 
 /// Evaluate with custom functions support
 pub fn evaluate_with_custom(input: &str, vars: &HashMap<String, Value>) -> Result<Value, Error> {
     let expr = parse(input)?;
-    runtime::evaluator::eval_with_vars_and_custom(&expr, vars, &GLOBAL_REGISTRY)
+    runtime::evaluator::eval_with_vars_and_custom(&expr, vars, &registry_snapshot())
+}
+
+/// Evaluate with custom functions support under per-call [`EvalOptions`].
+/// The request id of an enclosing [`EvalContext`] is kept; the output precision
+/// is applied to the returned value.
+pub fn evaluate_with_options(input: &str, vars: &HashMap<String, Value>, options: &EvalOptions) -> Result<Value, Error> {
+    options.validate()?;
+    if options.builtins_only {
+        analysis::check_builtins_only(&parse(input)?)?;
+    }
+    let mut ctx = context::current().map(|c| (*c).clone()).unwrap_or_default();
+    ctx.options = options.clone();
+    let value = context::with_context(ctx, || evaluate_with_custom(input, vars))?;
+    Ok(options.apply_precision(value))
+}
+
+/// Evaluate a batch of independent expressions, each with its own variables, on a
+/// bounded set of worker threads (at most one per CPU). Results come back in input
+/// order. Custom functions are resolved through the global registry and the caller's
+/// [`EvalContext`], if any, is installed on every worker.
+pub fn evaluate_all_parallel(pairs: &[(&str, HashMap<String, Value>)]) -> Vec<Result<Value, Error>> {
+    let workers = num_cpus::get().min(pairs.len());
+    if workers <= 1 {
+        return pairs.iter().map(|(expr, vars)| evaluate_with_custom(expr, vars)).collect();
+    }
+
+    let ctx = context::current();
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let mut results: Vec<Option<Result<Value, Error>>> = (0..pairs.len()).map(|_| None).collect();
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                let ctx = ctx.clone();
+                let next = &next;
+                scope.spawn(move || {
+                    let run = || {
+                        let mut done = Vec::new();
+                        loop {
+                            let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                            let Some((expr, vars)) = pairs.get(i) else { break };
+                            let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| evaluate_with_custom(expr, vars)))
+                                .unwrap_or_else(|_| Err(Error::new("Evaluation panicked", None)));
+                            done.push((i, result));
+                        }
+                        done
+                    };
+                    match ctx {
+                        Some(ctx) => context::with_context((*ctx).clone(), run),
+                        None => run(),
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            if let Ok(done) = handle.join() {
+                for (i, result) in done {
+                    results[i] = Some(result);
+                }
+            }
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|r| r.unwrap_or_else(|| Err(Error::new("Evaluation worker failed", None))))
+        .collect()
 }
 
 /// Evaluate with JSON and custom functions support
@@ -185,16 +299,19 @@ pub fn evaluate_with_json_custom(input: &str, json_vars: &str) -> Result<Value,
 }
 
 
-/// Evaluate with assignments and sequences - handles complex expressions with variable assignments
+/// Evaluate with assignments and sequences - handles complex expressions with variable assignments.
+/// Custom functions are available, so their results can be destructured.
 pub fn evaluate_with_assignments(input: &str, vars: &HashMap<String, Value>) -> Result<Value, Error> {
-    let expr = parse(input)?;
-    runtime::evaluator::eval_with_assignments(&expr, vars)
+    evaluate_with_assignments_and_context(input, vars).map(|(value, _)| value)
 }
 
 /// Evaluate with assignments and sequences, returning both result and variable context
 pub fn evaluate_with_assignments_and_context(input: &str, vars: &HashMap<String, Value>) -> Result<(Value, HashMap<String, Value>), Error> {
     let expr = parse(input)?;
-    runtime::evaluator::eval_with_assignments_and_context(&expr, vars)
+    let registry = registry_snapshot();
+    let mut context = runtime::evaluator::VariableContext::with_custom(vars, &registry);
+    let result = runtime::evaluator::eval_with_assignments_context(&expr, &mut context)?;
+    Ok((result, context.into_variables()))
 }
 
 #[cfg(test)]
@@ -0,0 +1,313 @@
+//! Per-evaluation options.
+//!
+//! Options travel with the [`EvalContext`](crate::context::EvalContext) installed for an
+//! evaluation, so builtins and the evaluators can consult them without extra parameters.
+
+use std::collections::BTreeMap;
+
+use crate::error::Error;
+use crate::runtime::function_dispatch::FUNCTION_CATEGORIES;
+use crate::runtime::utils::DISPLAY_SIGNIFICANT_DIGITS;
+use crate::types::Value;
+
+/// How forgiving the evaluator is about questionable input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Strictness {
+    /// Missing variables evaluate to null and ordering comparisons between
+    /// incompatible types are false instead of errors
+    Lenient,
+    /// Historical behavior
+    #[default]
+    Standard,
+    /// Equality between incompatible types, division by zero and non-finite
+    /// arithmetic results are errors
+    Strict,
+}
+
+impl Strictness {
+    pub fn parse(s: &str) -> Result<Self, Error> {
+        match s.to_ascii_lowercase().as_str() {
+            "lenient" => Ok(Strictness::Lenient),
+            "standard" | "default" => Ok(Strictness::Standard),
+            "strict" => Ok(Strictness::Strict),
+            _ => Err(Error::new(format!("Unknown strictness '{}': expected lenient, standard or strict", s), None)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Strictness::Lenient => "lenient",
+            Strictness::Standard => "standard",
+            Strictness::Strict => "strict",
+        }
+    }
+}
+
+/// Options controlling a single evaluation. `Default` reproduces the behavior
+/// of the plain `evaluate*` functions.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EvalOptions {
+    /// Abort the evaluation once it has run this long
+    pub timeout_ms: Option<u64>,
+    /// BCP 47 style locale tag (e.g. `en-US`, `de-DE`) for locale-aware functions
+    pub locale: Option<String>,
+    /// Largest array any expression may produce
+    pub max_array_size: Option<usize>,
+    /// Approximate cap on the bytes of strings, arrays and JSON an evaluation may
+    /// produce; counted as values are built, so garbage still counts
+    pub max_memory_bytes: Option<usize>,
+    /// Longest string, in characters, that REPT, CONCAT, JOIN or `text * n` may produce
+    pub max_string_length: Option<usize>,
+    pub strictness: Strictness,
+    /// Reject functions whose result depends on the wall clock or other ambient state
+    pub deterministic: bool,
+    /// Round numeric output to at most this many decimal places
+    pub precision: Option<u32>,
+    /// Round numeric output to this many significant digits (applied before `precision`)
+    pub significant_digits: Option<u32>,
+    /// ISO 4217 code used to render `Currency` values as text and as CURRENCY()'s default
+    pub currency: Option<String>,
+    /// Refuse to call custom (Rust or JavaScript) functions
+    pub builtins_only: bool,
+    /// When set, only these builtins may be called (upper case)
+    pub allowed_builtins: Option<Vec<String>>,
+    /// Builtins that may not be called (upper case); checked before `allowed_builtins`
+    pub denied_builtins: Vec<String>,
+    /// Tolerance for `==` / `!=` between numbers and APPROXEQ's default
+    pub epsilon: Option<f64>,
+    /// Fail instead of letting arithmetic overflow to infinity, underflow to zero,
+    /// or a number saturate when converted to an integer
+    pub overflow_check: bool,
+    /// Arithmetic with a null operand gives null instead of an error, as in SQL
+    pub null_propagation: bool,
+    /// Frozen clock: NOW, DATE() and TIME() read this Unix timestamp (seconds, UTC)
+    /// instead of the system clock
+    pub now_override: Option<i64>,
+    /// Total milliseconds the calls of a function category may take, keyed by a
+    /// [`FUNCTION_CATEGORIES`] name; a call that exhausts its category's budget fails
+    pub category_budgets: BTreeMap<String, u64>,
+}
+
+/// Builtins whose result is not a pure function of their arguments
+pub const NON_DETERMINISTIC_FUNCTIONS: &[&str] = &["NOW", "FEATURE"];
+
+impl EvalOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout_ms(mut self, ms: u64) -> Self {
+        self.timeout_ms = Some(ms);
+        self
+    }
+
+    pub fn with_locale(mut self, locale: impl Into<String>) -> Self {
+        self.locale = Some(locale.into());
+        self
+    }
+
+    pub fn with_max_array_size(mut self, size: usize) -> Self {
+        self.max_array_size = Some(size);
+        self
+    }
+
+    pub fn with_max_memory_bytes(mut self, bytes: usize) -> Self {
+        self.max_memory_bytes = Some(bytes);
+        self
+    }
+
+    pub fn with_max_string_length(mut self, length: usize) -> Self {
+        self.max_string_length = Some(length);
+        self
+    }
+
+    pub fn with_strictness(mut self, strictness: Strictness) -> Self {
+        self.strictness = strictness;
+        self
+    }
+
+    pub fn with_deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    pub fn with_precision(mut self, decimals: u32) -> Self {
+        self.precision = Some(decimals);
+        self
+    }
+
+    pub fn with_significant_digits(mut self, digits: u32) -> Self {
+        self.significant_digits = Some(digits);
+        self
+    }
+
+    pub fn with_currency(mut self, code: impl Into<String>) -> Self {
+        self.currency = Some(code.into());
+        self
+    }
+
+    pub fn with_builtins_only(mut self, builtins_only: bool) -> Self {
+        self.builtins_only = builtins_only;
+        self
+    }
+
+    pub fn with_epsilon(mut self, epsilon: f64) -> Self {
+        self.epsilon = Some(epsilon);
+        self
+    }
+
+    pub fn with_overflow_check(mut self, overflow_check: bool) -> Self {
+        self.overflow_check = overflow_check;
+        self
+    }
+
+    pub fn with_null_propagation(mut self, null_propagation: bool) -> Self {
+        self.null_propagation = null_propagation;
+        self
+    }
+
+    pub fn with_now_override(mut self, timestamp: i64) -> Self {
+        self.now_override = Some(timestamp);
+        self
+    }
+
+    /// Cap the time spent in calls of `category` (e.g. `"javascript"`, `"statistical"`)
+    pub fn with_category_budget(mut self, category: impl AsRef<str>, ms: u64) -> Self {
+        let category = category.as_ref();
+        let name = FUNCTION_CATEGORIES.iter().find(|c| c.eq_ignore_ascii_case(category)).copied().unwrap_or(category);
+        self.category_budgets.insert(name.to_string(), ms);
+        self
+    }
+
+    pub fn with_allowed_builtins<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.allowed_builtins = Some(names.into_iter().map(|n| n.as_ref().to_uppercase()).collect());
+        self
+    }
+
+    pub fn with_denied_builtins<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.denied_builtins = names.into_iter().map(|n| n.as_ref().to_uppercase()).collect();
+        self
+    }
+
+    /// Why the builtin `name` may not be called under these options, if it may not
+    pub fn builtin_policy_violation(&self, name: &str) -> Option<String> {
+        // `__`-prefixed names are parser internals (ranges, ternaries, constants), not user calls
+        if name.starts_with("__") {
+            return None;
+        }
+        let listed = |list: &[String]| list.iter().any(|n| n.eq_ignore_ascii_case(name));
+        if listed(&self.denied_builtins) {
+            return Some(format!("Function {} is blocked by the denied_builtins policy", name));
+        }
+        match &self.allowed_builtins {
+            Some(allowed) if !listed(allowed) => {
+                Some(format!("Function {} is not in the allowed_builtins policy", name))
+            }
+            _ => None,
+        }
+    }
+
+    /// Check option values that can be rejected up front
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(locale) = &self.locale {
+            let valid = !locale.is_empty()
+                && locale.split(['-', '_']).all(|part| !part.is_empty() && part.len() <= 8 && part.chars().all(|c| c.is_ascii_alphanumeric()));
+            if !valid {
+                return Err(Error::new(format!("Invalid locale '{}'", locale), None));
+            }
+        }
+        if let Some(p) = self.precision {
+            if p > 15 {
+                return Err(Error::new("precision must be between 0 and 15", None));
+            }
+        }
+        if let Some(d) = self.significant_digits {
+            // Text rendering never shows more digits than this
+            if !(1..=DISPLAY_SIGNIFICANT_DIGITS).contains(&d) {
+                return Err(Error::new(format!("significant_digits must be between 1 and {}", DISPLAY_SIGNIFICANT_DIGITS), None));
+            }
+        }
+        if let Some(code) = &self.currency {
+            if crate::runtime::currency::lookup(code).is_none() {
+                return Err(Error::new(format!("Unknown currency code '{}'", code), None));
+            }
+        }
+        if let Some(ts) = self.now_override {
+            if chrono::DateTime::from_timestamp(ts, 0).is_none() {
+                return Err(Error::new(format!("now_override {} is out of the supported date range", ts), None));
+            }
+        }
+        for (category, ms) in &self.category_budgets {
+            if !FUNCTION_CATEGORIES.contains(&category.as_str()) {
+                return Err(Error::new(
+                    format!("Unknown function category '{}' in category_budgets; expected one of {}", category, FUNCTION_CATEGORIES.join(", ")),
+                    None,
+                ));
+            }
+            if *ms == 0 {
+                return Err(Error::new(format!("category_budgets for {} must be at least 1 ms", category), None));
+            }
+        }
+        if let Some(eps) = self.epsilon {
+            if !eps.is_finite() || eps < 0.0 {
+                return Err(Error::new("epsilon must be a non-negative number", None));
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the output precision to a result, recursing into arrays
+    pub fn apply_precision(&self, value: Value) -> Value {
+        if self.precision.is_none() && self.significant_digits.is_none() {
+            return value;
+        }
+        self.round_value(value)
+    }
+
+    /// Round a single number per `significant_digits` and `precision`
+    pub fn round_number(&self, n: f64) -> f64 {
+        let n = match self.significant_digits {
+            Some(d) => round_significant(n, d),
+            None => n,
+        };
+        match self.precision {
+            Some(p) => round_to(n, p),
+            None => n,
+        }
+    }
+
+    fn round_value(&self, value: Value) -> Value {
+        match value {
+            Value::Number(n) => Value::Number(self.round_number(n)),
+            Value::Currency(n) => Value::Currency(self.round_number(n)),
+            Value::Array(items) => Value::Array(items.into_iter().map(|v| self.round_value(v)).collect()),
+            other => other,
+        }
+    }
+}
+
+/// Round to `digits` significant digits. Goes through the decimal representation,
+/// which stays exact for very large and very small magnitudes.
+pub(crate) fn round_significant(n: f64, digits: u32) -> f64 {
+    if !n.is_finite() || n == 0.0 || digits == 0 {
+        return n;
+    }
+    format!("{:.*e}", (digits - 1) as usize, n).parse().unwrap_or(n)
+}
+
+pub(crate) fn round_to(n: f64, decimals: u32) -> f64 {
+    if !n.is_finite() {
+        return n;
+    }
+    let factor = 10f64.powi(decimals as i32);
+    let rounded = (n * factor).round() / factor;
+    if rounded.is_finite() { rounded } else { n }
+}
@@ -1,21 +1,34 @@
-use crate::ast::{BinaryOp, Expr, TypeName, UnaryOp};
+use crate::ast::{BinaryOp, Expr, Pattern, TypeName, UnaryOp};
 use crate::error::Error;
 use crate::lexer::{Lexer, Token};
-use std::rc::Rc;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
 
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
     lookahead: Token,
     lookahead2: Option<Token>,
     look_pos: usize,
+    /// How many `|>` right-hand sides are being parsed, where `_` is the piped value
+    pipe_depth: usize,
+    /// Parameters of the enclosing arrow lambdas, usable as bare names in their bodies
+    lambda_params: Vec<String>,
 }
 
+/// Stand-in for `_` until the enclosing pipe is lowered
+const PIPE_PLACEHOLDER: &str = "__PIPE_ARG__";
+/// Local that holds the piped value when `_` appears more than once
+const PIPE_VALUE: &str = "__pipe";
+/// A `let name := value` statement until the sequence is lowered to `LET`
+const LET_STATEMENT: &str = "__LET_STATEMENT__";
+
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Self {
         let mut lexer = Lexer::new(input);
         let lookahead = lexer.next_token().unwrap_or(Token::Eof);
         let look_pos = lexer.last_start();
-        Self { lexer, lookahead, lookahead2: None, look_pos }
+        Self { lexer, lookahead, lookahead2: None, look_pos, pipe_depth: 0, lambda_params: Vec::new() }
     }
 
     fn bump(&mut self) -> Result<(), Error> {
@@ -47,11 +60,88 @@ impl<'a> Parser<'a> {
 
     fn err_here<T>(&self, msg: &str) -> Result<T, Error> { Err(Error::new(msg, Some(self.look_pos))) }
 
+    /// The parameter names when the tokens ahead open an arrow lambda: `x ->`,
+    /// `() ->` or `(a, b) ->`
+    fn lambda_ahead(&self) -> Option<Vec<String>> {
+        let mut tokens = self.lexer.clone();
+        let mut next = || tokens.next_token().unwrap_or(Token::Eof);
+        match &self.lookahead {
+            Token::Identifier(name) => matches!(next(), Token::Arrow).then(|| vec![name.clone()]),
+            Token::LParen => {
+                let mut params = Vec::new();
+                match next() {
+                    Token::RParen => {}
+                    Token::Identifier(name) => {
+                        params.push(name);
+                        loop {
+                            match next() {
+                                Token::RParen => break,
+                                Token::Comma => match next() {
+                                    Token::Identifier(name) => params.push(name),
+                                    _ => return None,
+                                },
+                                _ => return None,
+                            }
+                        }
+                    }
+                    _ => return None,
+                }
+                matches!(next(), Token::Arrow).then_some(params)
+            }
+            _ => None,
+        }
+    }
+
+    /// The pattern of a destructuring assignment starting at the lookahead, such as
+    /// `[:a, :b] :=` or `{:lat, :lon} :=`
+    fn destructure_ahead(&self) -> Option<Pattern> {
+        let close = match self.lookahead {
+            Token::LBracket => Token::RBracket,
+            Token::LBrace => Token::RBrace,
+            _ => return None,
+        };
+        let mut tokens = self.lexer.clone();
+        let mut next = || tokens.next_token().unwrap_or(Token::Eof);
+        let mut names = Vec::new();
+        loop {
+            match (next(), next()) {
+                (Token::Colon, Token::Identifier(name)) => names.push(name),
+                _ => return None,
+            }
+            match next() {
+                Token::Comma => {}
+                t if t == close => break,
+                _ => return None,
+            }
+        }
+        if !matches!(next(), Token::ColonEquals) {
+            return None;
+        }
+        Some(if close == Token::RBracket { Pattern::Array(names) } else { Pattern::Object(names) })
+    }
+
+    fn parse_lambda(&mut self, params: Vec<String>) -> Result<Expr, Error> {
+        for (i, param) in params.iter().enumerate() {
+            if params[..i].contains(param) {
+                return self.err_here(&format!("Duplicate lambda parameter '{}'", param));
+            }
+        }
+        while !matches!(self.lookahead, Token::Arrow) {
+            self.bump()?;
+        }
+        self.bump()?; // consume '->'
+        let scope = self.lambda_params.len();
+        self.lambda_params.extend(params.iter().cloned());
+        let body = self.parse_expr();
+        self.lambda_params.truncate(scope);
+        Ok(Expr::Lambda { params, body: Arc::new(body?) })
+    }
+
     pub fn parse(&mut self) -> Result<Expr, Error> {
         let mut exprs = Vec::new();
         
         // Parse first expression
-        exprs.push(self.parse_expr()?);
+        exprs.push(self.parse_statement()?);
         
         // Parse semicolon-separated expressions
         while matches!(self.lookahead, Token::Semicolon) {
@@ -59,9 +149,11 @@ impl<'a> Parser<'a> {
             if matches!(self.lookahead, Token::Eof) {
                 break; // Allow trailing semicolon
             }
-            exprs.push(self.parse_expr()?);
+            exprs.push(self.parse_statement()?);
         }
         
+        let exprs = expand_defs(exprs)?;
+        let exprs = lower_lets(exprs)?;
         // If only one expression, return it directly; otherwise wrap in sequence
         if exprs.len() == 1 {
             exprs.into_iter().next()
@@ -71,6 +163,76 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// A statement of a sequence: `let name := value`, `fn name(a, b) := body` or an
+    /// expression. The name of a `let` may be written bare in the statements after it,
+    /// like a lambda parameter.
+    fn parse_statement(&mut self) -> Result<Expr, Error> {
+        if self.at_keyword("fn") && matches!(self.peek_ahead2()?, (Token::Identifier(_), Token::LParen)) {
+            return self.parse_fn();
+        }
+        let is_let = self.at_keyword("let") && matches!(self.peek_ahead2()?.0, Token::Identifier(_) | Token::Colon);
+        if !is_let {
+            return self.parse_expr();
+        }
+        self.bump()?; // consume 'let'
+        if matches!(self.lookahead, Token::Colon) {
+            self.bump()?;
+        }
+        let Token::Identifier(name) = self.lookahead.clone() else {
+            return self.err_here("Expected a name after 'let'");
+        };
+        if name == crate::constants::ENV {
+            return self.err_here(":ENV holds the host's constants and cannot be assigned");
+        }
+        self.bump()?;
+        if !matches!(self.lookahead, Token::ColonEquals) {
+            return self.err_here("Expected ':=' after the let name");
+        }
+        self.bump()?; // consume ':='
+        let value = self.parse_ternary()?;
+        self.lambda_params.push(name.clone());
+        Ok(Expr::FunctionCall { name: LET_STATEMENT.to_string(), args: vec![Expr::Variable(name), value] })
+    }
+
+    /// `fn name(a, b) := body`, after which `name(...)` can be called like a function
+    /// declared with `DEF(:name, [:a, :b], body)`. Parameters may be written bare or as
+    /// `:a` in the body.
+    fn parse_fn(&mut self) -> Result<Expr, Error> {
+        self.bump()?; // consume 'fn'
+        let Token::Identifier(name) = self.lookahead.clone() else {
+            return self.err_here("Expected a function name after 'fn'");
+        };
+        self.bump()?;
+        self.bump()?; // consume '('
+        let mut params = Vec::new();
+        while !matches!(self.lookahead, Token::RParen) {
+            if matches!(self.lookahead, Token::Colon) {
+                self.bump()?;
+            }
+            let Token::Identifier(param) = self.lookahead.clone() else {
+                return self.err_here("Expected a parameter name in fn declaration");
+            };
+            self.bump()?;
+            params.push(param);
+            match self.lookahead {
+                Token::Comma => self.bump()?,
+                Token::RParen => {}
+                _ => return self.err_here("Expected ',' or ')' in fn parameters"),
+            }
+        }
+        self.bump()?; // consume ')'
+        if !matches!(self.lookahead, Token::ColonEquals) {
+            return self.err_here("Expected ':=' after the fn parameters");
+        }
+        self.bump()?; // consume ':='
+        let scope = self.lambda_params.len();
+        self.lambda_params.extend(params.iter().cloned());
+        let body = self.parse_ternary();
+        self.lambda_params.truncate(scope);
+        let params = params.into_iter().map(Expr::Variable).collect();
+        Ok(Expr::FunctionCall { name: "DEF".to_string(), args: vec![Expr::Variable(name), Expr::Array(params), body?] })
+    }
+
     fn parse_expr(&mut self) -> Result<Expr, Error> {
         self.parse_assignment()
     }
@@ -88,20 +250,40 @@ impl<'a> Parser<'a> {
                     let var_name = var_name.clone();
                     self.bump()?; // consume identifier
                     if matches!(self.lookahead, Token::ColonEquals) {
+                        if var_name == crate::constants::ENV {
+                            return self.err_here(":ENV holds the host's constants and cannot be assigned");
+                        }
                         self.bump()?; // consume ':='
                         let value = self.parse_ternary()?;
-                        return Ok(Expr::Assignment { variable: var_name, value: Rc::new(value) });
+                        return Ok(Expr::Assignment { variable: var_name, value: Arc::new(value) });
                     }
                 }
                 return self.err_here("Invalid assignment syntax");
             }
         }
-        
+        if let Some(pattern) = self.destructure_ahead() {
+            let names = pattern.names();
+            for (i, name) in names.iter().enumerate() {
+                if name == crate::constants::ENV {
+                    return self.err_here(":ENV holds the host's constants and cannot be assigned");
+                }
+                if names[..i].contains(name) {
+                    return self.err_here(&format!("Variable '{}' appears twice in the destructuring pattern", name));
+                }
+            }
+            while !matches!(self.lookahead, Token::ColonEquals) {
+                self.bump()?;
+            }
+            self.bump()?; // consume ':='
+            let value = self.parse_ternary()?;
+            return Ok(Expr::Destructure { pattern, value: Arc::new(value) });
+        }
+
         self.parse_ternary()
     }
 
     fn parse_ternary(&mut self) -> Result<Expr, Error> {
-        let cond = self.parse_or()?;
+        let cond = self.parse_pipe()?;
         if let Token::QMark = self.lookahead {
             self.bump()?; // '?'
             let then_e = self.parse_expr()?;
@@ -121,6 +303,39 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// `value |> rhs` is lowered here: every `_` in `rhs` becomes `value` (a `LET`
+    /// local when there are several, so `value` is still evaluated once), and a
+    /// function call without `_` gets `value` as its first argument. Left associative,
+    /// so `a |> f(_) |> g(_)` is `g(f(a))`.
+    fn parse_pipe(&mut self) -> Result<Expr, Error> {
+        let mut node = self.parse_or()?;
+        while let Token::Pipe = self.lookahead {
+            self.bump()?;
+            let rhs_pos = self.look_pos;
+            self.pipe_depth += 1;
+            let rhs = self.parse_or();
+            self.pipe_depth -= 1;
+            let rhs = rhs?;
+            let mut used = 0;
+            let lowered = fill_placeholder(&rhs, &node, &mut used);
+            node = match (used, lowered) {
+                (0, Expr::FunctionCall { name, mut args }) => {
+                    args.insert(0, node);
+                    Expr::FunctionCall { name, args }
+                }
+                (0, _) => return Err(Error::new("Right side of |> must be a function call or use _ for the piped value", Some(rhs_pos))),
+                (1, lowered) => lowered,
+                _ => {
+                    // Several `_`: bind the piped value once instead of copying it into each
+                    let value = Expr::Variable(PIPE_VALUE.to_string());
+                    let body = fill_placeholder(&rhs, &value, &mut used);
+                    Expr::FunctionCall { name: "LET".to_string(), args: vec![value, node, body] }
+                }
+            };
+        }
+        Ok(node)
+    }
+
     fn parse_or(&mut self) -> Result<Expr, Error> {
         let mut node = self.parse_and()?;
         loop {
@@ -128,7 +343,7 @@ impl<'a> Parser<'a> {
                 Token::Or | Token::OrOr => {
                     self.bump()?;
                     let rhs = self.parse_and()?;
-                    node = Expr::Binary(Rc::new(node), BinaryOp::Or, Rc::new(rhs));
+                    node = Expr::Binary(Arc::new(node), BinaryOp::Or, Arc::new(rhs));
                 }
                 _ => break,
             }
@@ -143,7 +358,7 @@ impl<'a> Parser<'a> {
                 Token::And | Token::AndAnd => {
                     self.bump()?;
                     let rhs = self.parse_equality()?;
-                    node = Expr::Binary(Rc::new(node), BinaryOp::And, Rc::new(rhs));
+                    node = Expr::Binary(Arc::new(node), BinaryOp::And, Arc::new(rhs));
                 }
                 _ => break,
             }
@@ -155,8 +370,8 @@ impl<'a> Parser<'a> {
         let mut node = self.parse_relational()?;
         loop {
             match self.lookahead {
-                Token::EqEq => { self.bump()?; let rhs = self.parse_relational()?; node = Expr::Binary(Rc::new(node), BinaryOp::Eq, Rc::new(rhs)); }
-                Token::NotEq => { self.bump()?; let rhs = self.parse_relational()?; node = Expr::Binary(Rc::new(node), BinaryOp::Ne, Rc::new(rhs)); }
+                Token::EqEq => { self.bump()?; let rhs = self.parse_relational()?; node = Expr::Binary(Arc::new(node), BinaryOp::Eq, Arc::new(rhs)); }
+                Token::NotEq => { self.bump()?; let rhs = self.parse_relational()?; node = Expr::Binary(Arc::new(node), BinaryOp::Ne, Arc::new(rhs)); }
                 _ => break,
             }
         }
@@ -164,19 +379,32 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_relational(&mut self) -> Result<Expr, Error> {
-        let mut node = self.parse_additive()?;
+        let mut node = self.parse_range()?;
         loop {
             match self.lookahead {
-                Token::Greater => { self.bump()?; let rhs = self.parse_additive()?; node = Expr::Binary(Rc::new(node), BinaryOp::Gt, Rc::new(rhs)); }
-                Token::Less => { self.bump()?; let rhs = self.parse_additive()?; node = Expr::Binary(Rc::new(node), BinaryOp::Lt, Rc::new(rhs)); }
-                Token::Ge => { self.bump()?; let rhs = self.parse_additive()?; node = Expr::Binary(Rc::new(node), BinaryOp::Ge, Rc::new(rhs)); }
-                Token::Le => { self.bump()?; let rhs = self.parse_additive()?; node = Expr::Binary(Rc::new(node), BinaryOp::Le, Rc::new(rhs)); }
+                Token::Greater => { self.bump()?; let rhs = self.parse_range()?; node = Expr::Binary(Arc::new(node), BinaryOp::Gt, Arc::new(rhs)); }
+                Token::Less => { self.bump()?; let rhs = self.parse_range()?; node = Expr::Binary(Arc::new(node), BinaryOp::Lt, Arc::new(rhs)); }
+                Token::Ge => { self.bump()?; let rhs = self.parse_range()?; node = Expr::Binary(Arc::new(node), BinaryOp::Ge, Arc::new(rhs)); }
+                Token::Le => { self.bump()?; let rhs = self.parse_range()?; node = Expr::Binary(Arc::new(node), BinaryOp::Le, Arc::new(rhs)); }
                 _ => break,
             }
         }
         Ok(node)
     }
 
+    fn parse_range(&mut self) -> Result<Expr, Error> {
+        // `start..end` (exclusive) / `start..=end` (inclusive); not chainable
+        let start = self.parse_additive()?;
+        let name = match self.lookahead {
+            Token::DotDot => "__RANGE__",
+            Token::DotDotEq => "__RANGE_INCLUSIVE__",
+            _ => return Ok(start),
+        };
+        self.bump()?;
+        let end = self.parse_additive()?;
+        Ok(Expr::FunctionCall { name: name.to_string(), args: vec![start, end] })
+    }
+
     fn parse_additive(&mut self) -> Result<Expr, Error> {
         let mut node = self.parse_multiplicative()?;
         loop {
@@ -184,12 +412,12 @@ impl<'a> Parser<'a> {
                 Token::Plus => {
                     self.bump()?;
                     let rhs = self.parse_multiplicative()?;
-                    node = Expr::Binary(Rc::new(node), BinaryOp::Add, Rc::new(rhs));
+                    node = Expr::Binary(Arc::new(node), BinaryOp::Add, Arc::new(rhs));
                 }
                 Token::Minus => {
                     self.bump()?;
                     let rhs = self.parse_multiplicative()?;
-                    node = Expr::Binary(Rc::new(node), BinaryOp::Sub, Rc::new(rhs));
+                    node = Expr::Binary(Arc::new(node), BinaryOp::Sub, Arc::new(rhs));
                 }
                 _ => break,
             }
@@ -204,17 +432,17 @@ impl<'a> Parser<'a> {
                 Token::Star => {
                     self.bump()?;
                     let rhs = self.parse_unary()?;
-                    node = Expr::Binary(Rc::new(node), BinaryOp::Mul, Rc::new(rhs));
+                    node = Expr::Binary(Arc::new(node), BinaryOp::Mul, Arc::new(rhs));
                 }
                 Token::Slash => {
                     self.bump()?;
                     let rhs = self.parse_unary()?;
-                    node = Expr::Binary(Rc::new(node), BinaryOp::Div, Rc::new(rhs));
+                    node = Expr::Binary(Arc::new(node), BinaryOp::Div, Arc::new(rhs));
                 }
                 Token::Percent => {
                     self.bump()?;
                     let rhs = self.parse_unary()?;
-                    node = Expr::Binary(Rc::new(node), BinaryOp::Mod, Rc::new(rhs));
+                    node = Expr::Binary(Arc::new(node), BinaryOp::Mod, Arc::new(rhs));
                 }
                 _ => break,
             }
@@ -228,7 +456,7 @@ impl<'a> Parser<'a> {
         if let Token::Caret = self.lookahead {
             self.bump()?;
             let right = self.parse_unary()?; // exponent can be unary like -2
-            Ok(Expr::Binary(Rc::new(left), BinaryOp::Pow, Rc::new(right)))
+            Ok(Expr::Binary(Arc::new(left), BinaryOp::Pow, Arc::new(right)))
         } else {
             Ok(left)
         }
@@ -239,23 +467,63 @@ impl<'a> Parser<'a> {
             Token::Plus => {
                 self.bump()?;
                 let expr = self.parse_unary()?;
-                Ok(Expr::Unary(UnaryOp::Plus, Rc::new(expr)))
+                Ok(Expr::Unary(UnaryOp::Plus, Arc::new(expr)))
             }
             Token::Minus => {
                 self.bump()?;
                 let expr = self.parse_unary()?;
-                Ok(Expr::Unary(UnaryOp::Minus, Rc::new(expr)))
+                Ok(Expr::Unary(UnaryOp::Minus, Arc::new(expr)))
             }
             Token::Bang => {
                 self.bump()?;
                 let expr = self.parse_unary()?;
-                Ok(Expr::Unary(UnaryOp::Not, Rc::new(expr)))
+                Ok(Expr::Unary(UnaryOp::Not, Arc::new(expr)))
             }
             _ => self.parse_power(),
         }
     }
 
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(&self.lookahead, Token::Identifier(name) if name.eq_ignore_ascii_case(keyword))
+    }
+
+    /// `case [value] when a then x [when b then y ...] [else z] end`, after `case`.
+    /// With a value it is `SWITCH(value, a, x, b, y, [z])`; without one each `when`
+    /// holds a condition and it is `SWITCH(true, a, x, b, y, [z])`.
+    fn parse_case(&mut self) -> Result<Expr, Error> {
+        let subject = if self.at_keyword("when") {
+            Expr::FunctionCall { name: "__CONST_TRUE__".to_string(), args: vec![] }
+        } else {
+            self.parse_expr()?
+        };
+        let mut args = vec![subject];
+        while self.at_keyword("when") {
+            self.bump()?;
+            args.push(self.parse_expr()?);
+            if !self.at_keyword("then") {
+                return self.err_here("Expected 'then' after the case condition");
+            }
+            self.bump()?;
+            args.push(self.parse_expr()?);
+        }
+        if args.len() == 1 {
+            return self.err_here("Expected 'when' in case expression");
+        }
+        if self.at_keyword("else") {
+            self.bump()?;
+            args.push(self.parse_expr()?);
+        }
+        if !self.at_keyword("end") {
+            return self.err_here("Expected 'when', 'else' or 'end' in case expression");
+        }
+        self.bump()?;
+        Ok(Expr::FunctionCall { name: "SWITCH".to_string(), args })
+    }
+
     fn parse_atom(&mut self) -> Result<Expr, Error> {
+        if let Some(params) = self.lambda_ahead() {
+            return self.parse_lambda(params);
+        }
         match self.lookahead.clone() {
             Token::Number(n) => {
                 self.bump()?;
@@ -273,6 +541,15 @@ impl<'a> Parser<'a> {
                 }
             }
             Token::String(s) => { self.bump()?; Ok(Expr::StringLit(s)) }
+            Token::DateTime(s) => {
+                // Fold the literal to epoch seconds now; the cast turns it into a DateTime value
+                let ts = match crate::runtime::datetime::parse_iso_datetime(&s) {
+                    Some(ts) => ts,
+                    None => return self.err_here(&format!("Invalid datetime literal: {}", s)),
+                };
+                self.bump()?;
+                Ok(Expr::TypeCast { expr: Arc::new(Expr::Number(ts as f64)), ty: TypeName::DateTime })
+            }
             Token::Null => { self.bump()?; Ok(Expr::Null) }
             Token::Colon => {
                 // Variable: ':' identifier (let postfix handle property chains and method calls)
@@ -287,6 +564,21 @@ impl<'a> Parser<'a> {
             }
             Token::True => { self.bump()?; Ok(Expr::FunctionCall { name: "__CONST_TRUE__".to_string(), args: vec![] }) }
             Token::False => { self.bump()?; Ok(Expr::FunctionCall { name: "__CONST_FALSE__".to_string(), args: vec![] }) }
+            Token::Identifier(name) if self.lambda_params.contains(&name) && !matches!(self.peek_ahead2()?.0, Token::LParen) => {
+                self.bump()?;
+                Ok(Expr::Variable(name))
+            }
+            Token::Identifier(name) if name.eq_ignore_ascii_case("case") && !matches!(self.peek_ahead2()?.0, Token::LParen) => {
+                self.bump()?;
+                self.parse_case()
+            }
+            Token::Identifier(name) if name == "_" => {
+                if self.pipe_depth == 0 {
+                    return self.err_here("'_' stands for the piped value and is only allowed right of |>");
+                }
+                self.bump()?;
+                Ok(Expr::FunctionCall { name: PIPE_PLACEHOLDER.to_string(), args: vec![] })
+            }
             Token::Identifier(name) => {
                 // Function call: IDENT '(' args? ')'
                 let func_name = name;
@@ -299,7 +591,7 @@ impl<'a> Parser<'a> {
                             // empty args
                         } else {
                             loop {
-                                let arg = if let Token::Ellipsis = self.lookahead { self.bump()?; Expr::Spread(Rc::new(self.parse_expr()?)) } else { self.parse_expr()? };
+                                let arg = if let Token::Ellipsis = self.lookahead { self.bump()?; Expr::Spread(Arc::new(self.parse_expr()?)) } else { self.parse_expr()? };
                                 args.push(arg);
                                 match self.lookahead {
                                     Token::Comma => { self.bump()?; }
@@ -316,14 +608,14 @@ impl<'a> Parser<'a> {
                 }
             }
             Token::LBracket => {
-                // Array literal: [ expr (, expr)* ]
+                // Array literal: [ expr (, expr)* ], where `...arr` splices in an array
                 self.bump()?; // consume '['
                 let mut items = Vec::new();
                 if let Token::RBracket = self.lookahead {
                     // empty
                 } else {
                     loop {
-                        let item = self.parse_expr()?;
+                        let item = if let Token::Ellipsis = self.lookahead { self.bump()?; Expr::Spread(Arc::new(self.parse_expr()?)) } else { self.parse_expr()? };
                         items.push(item);
                         match self.lookahead {
                             Token::Comma => { self.bump()?; }
@@ -343,30 +635,37 @@ impl<'a> Parser<'a> {
                     // empty object
                 } else {
                     loop {
-                        // Parse key (can be identifier or string)
-                        let key = match self.lookahead.clone() {
-                            Token::Identifier(s) => {
-                                self.bump()?;
-                                s
-                            }
-                            Token::String(s) => {
-                                self.bump()?;
-                                s
+                        // `...obj` copies the fields of another object; it is stored under
+                        // an empty key since the evaluator only looks at the Spread
+                        if let Token::Ellipsis = self.lookahead {
+                            self.bump()?;
+                            pairs.push((String::new(), Expr::Spread(Arc::new(self.parse_expr()?))));
+                        } else {
+                            // Parse key (can be identifier or string)
+                            let key = match self.lookahead.clone() {
+                                Token::Identifier(s) => {
+                                    self.bump()?;
+                                    s
+                                }
+                                Token::String(s) => {
+                                    self.bump()?;
+                                    s
+                                }
+                                _ => return self.err_here("Expected identifier or string key in object literal"),
+                            };
+                        
+                            // Expect colon
+                            if matches!(self.lookahead, Token::Colon) {
+                                self.bump()?; // consume ':'
+                            } else {
+                                return self.err_here("Expected ':' after object key");
                             }
-                            _ => return self.err_here("Expected identifier or string key in object literal"),
-                        };
                         
-                        // Expect colon
-                        if matches!(self.lookahead, Token::Colon) {
-                            self.bump()?; // consume ':'
-                        } else {
-                            return self.err_here("Expected ':' after object key");
+                            // Parse value
+                            let value = self.parse_expr()?;
+                            pairs.push((key, value));
                         }
                         
-                        // Parse value
-                        let value = self.parse_expr()?;
-                        pairs.push((key, value));
-                        
                         match self.lookahead {
                             Token::Comma => { 
                                 self.bump()?; 
@@ -394,21 +693,11 @@ impl<'a> Parser<'a> {
             let tname = match self.lookahead.clone() {
                 Token::Identifier(s) => {
                     self.bump()?;
-                    match s.to_lowercase().as_str() {
-                        "integer" | "int" => TypeName::Integer,
-                        "float" | "number" => TypeName::Float,
-                        "string" => TypeName::String,
-                        "boolean" | "bool" => TypeName::Boolean,
-                        "array" => TypeName::Array,
-                        "currency" => TypeName::Currency,
-                        "datetime" | "date" => TypeName::DateTime,
-                        "json" => TypeName::Json,
-                        _ => return Err(Error::new("Unknown cast type", None)),
-                    }
+                    TypeName::from_name(&s).ok_or_else(|| Error::new("Unknown cast type", None))?
                 }
                 _ => return Err(Error::new("Expected type name after '::'", None)),
             };
-            node = Expr::TypeCast { expr: Rc::new(node), ty: tname };
+            node = Expr::TypeCast { expr: Arc::new(node), ty: tname };
         }
         Ok(node)
     }
@@ -426,7 +715,13 @@ impl<'a> Parser<'a> {
                     // Predicate style: name?
                     if let Token::QMark = self.lookahead {
                         self.bump()?; // consume '?'
-                        node = Expr::MethodCall { target: Rc::new(node), name: name.to_lowercase(), args: vec![], predicate: true };
+                        node = Expr::MethodCall { target: Arc::new(node), name: name.to_lowercase(), args: vec![], predicate: true };
+                        continue;
+                    }
+                    // `name?.next` is the predicate followed by a plain '.'
+                    if let Token::QuestionDot = self.lookahead {
+                        self.lookahead = Token::Dot;
+                        node = Expr::MethodCall { target: Arc::new(node), name: name.to_lowercase(), args: vec![], predicate: true };
                         continue;
                     }
                     // Check for method call: '(' args ')'
@@ -438,7 +733,7 @@ impl<'a> Parser<'a> {
                                 // empty
                             } else {
                                 loop {
-                                    let arg = if let Token::Ellipsis = self.lookahead { self.bump()?; Expr::Spread(Rc::new(self.parse_expr()?)) } else { self.parse_expr()? };
+                                    let arg = if let Token::Ellipsis = self.lookahead { self.bump()?; Expr::Spread(Arc::new(self.parse_expr()?)) } else { self.parse_expr()? };
                                     args.push(arg);
                                     match self.lookahead {
                                         Token::Comma => { self.bump()?; }
@@ -448,16 +743,16 @@ impl<'a> Parser<'a> {
                                 }
                             }
                             self.bump()?; // ')'
-                            node = Expr::MethodCall { target: Rc::new(node), name: name.to_lowercase(), args, predicate: false };
+                            node = Expr::MethodCall { target: Arc::new(node), name: name.to_lowercase(), args, predicate: false };
                         }
                         _ => {
                             // No parentheses, treat as property access
-                            node = Expr::PropertyAccess { target: Rc::new(node), property: name };
+                            node = Expr::PropertyAccess { target: Arc::new(node), property: name };
                         }
                     }
                 }
-                Token::SafeNavigation => {
-                    self.bump()?; // '&.'
+                Token::SafeNavigation | Token::QuestionDot => {
+                    self.bump()?; // '&.' or '?.'
                     let name = match self.lookahead.clone() {
                         Token::Identifier(s) => { self.bump()?; s }
                         _ => return self.err_here("Expected property name after '&.'"),
@@ -474,7 +769,7 @@ impl<'a> Parser<'a> {
                                 loop {
                                     let arg = if let Token::Ellipsis = self.lookahead { 
                                         self.bump()?; 
-                                        Expr::Spread(Rc::new(self.parse_expr()?))}
+                                        Expr::Spread(Arc::new(self.parse_expr()?))}
                                     else { 
                                         self.parse_expr()? 
                                     };
@@ -487,11 +782,11 @@ impl<'a> Parser<'a> {
                                 }
                             }
                             self.bump()?; // ')'
-                            node = Expr::SafeMethodCall { target: Rc::new(node), name: name.to_lowercase(), args };
+                            node = Expr::SafeMethodCall { target: Arc::new(node), name: name.to_lowercase(), args };
                         }
                         _ => {
                             // Safe property access
-                            node = Expr::SafePropertyAccess { target: Rc::new(node), property: name };
+                            node = Expr::SafePropertyAccess { target: Arc::new(node), property: name };
                         }
                     }
                 }
@@ -511,7 +806,7 @@ impl<'a> Parser<'a> {
                             Token::Colon => { start = Some(first); }
                             Token::RBracket => {
                                 self.bump()?; // ']'
-                                node = Expr::Index { target: Rc::new(node), index: Rc::new(first) };
+                                node = Expr::Index { target: Arc::new(node), index: Arc::new(first) };
                                 continue;
                             }
                             _ => return self.err_here("Expected ':' or ']' in indexing"),
@@ -530,7 +825,7 @@ impl<'a> Parser<'a> {
                     Token::RBracket => { self.bump()?; }
                     _ => return self.err_here("Expected ']' to close slice"),
                 }
-                node = Expr::Slice { target: Rc::new(node), start: start.map(Rc::new), end: end.map(Rc::new) };
+                node = Expr::Slice { target: Arc::new(node), start: start.map(Arc::new), end: end.map(Arc::new) };
             }
             _ => break,
         }
@@ -538,3 +833,196 @@ impl<'a> Parser<'a> {
     Ok(node)
 }
 }
+
+/// `expr` with each pipe placeholder replaced by `value`, counting the replacements
+fn fill_placeholder(expr: &Expr, value: &Expr, used: &mut usize) -> Expr {
+    match expr {
+        Expr::FunctionCall { name, args } if name == PIPE_PLACEHOLDER && args.is_empty() => {
+            *used += 1;
+            value.clone()
+        }
+        _ => map_children(expr, &mut |e| Ok::<_, Infallible>(fill_placeholder(e, value, used))).unwrap_or_else(|never| match never {}),
+    }
+}
+
+/// `expr` with `f` applied to each of its direct children
+fn map_children<E>(expr: &Expr, f: &mut impl FnMut(&Expr) -> Result<Expr, E>) -> Result<Expr, E> {
+    let all = |items: &[Expr], f: &mut dyn FnMut(&Expr) -> Result<Expr, E>| items.iter().map(f).collect::<Result<Vec<_>, E>>();
+    Ok(match expr {
+        Expr::Number(_) | Expr::StringLit(_) | Expr::Null | Expr::Variable(_) => expr.clone(),
+        Expr::Unary(op, inner) => Expr::Unary(*op, Arc::new(f(inner)?)),
+        Expr::Binary(l, op, r) => Expr::Binary(Arc::new(f(l)?), *op, Arc::new(f(r)?)),
+        Expr::Spread(inner) => Expr::Spread(Arc::new(f(inner)?)),
+        Expr::TypeCast { expr, ty } => Expr::TypeCast { expr: Arc::new(f(expr)?), ty: ty.clone() },
+        Expr::Assignment { variable, value } => Expr::Assignment { variable: variable.clone(), value: Arc::new(f(value)?) },
+        Expr::Destructure { pattern, value } => Expr::Destructure { pattern: pattern.clone(), value: Arc::new(f(value)?) },
+        Expr::PropertyAccess { target, property } => Expr::PropertyAccess { target: Arc::new(f(target)?), property: property.clone() },
+        Expr::SafePropertyAccess { target, property } => {
+            Expr::SafePropertyAccess { target: Arc::new(f(target)?), property: property.clone() }
+        }
+        Expr::Index { target, index } => Expr::Index { target: Arc::new(f(target)?), index: Arc::new(f(index)?) },
+        Expr::Slice { target, start, end } => Expr::Slice {
+            target: Arc::new(f(target)?),
+            start: start.as_ref().map(|e| f(e).map(Arc::new)).transpose()?,
+            end: end.as_ref().map(|e| f(e).map(Arc::new)).transpose()?,
+        },
+        Expr::Array(items) => Expr::Array(all(items, f)?),
+        Expr::Sequence(items) => Expr::Sequence(all(items, f)?),
+        Expr::ObjectLiteral(fields) => {
+            Expr::ObjectLiteral(fields.iter().map(|(k, v)| Ok((k.clone(), f(v)?))).collect::<Result<_, E>>()?)
+        }
+        Expr::FunctionCall { name, args } => Expr::FunctionCall { name: name.clone(), args: all(args, f)? },
+        Expr::MethodCall { target, name, args, predicate } => Expr::MethodCall {
+            target: Arc::new(f(target)?),
+            name: name.clone(),
+            args: all(args, f)?,
+            predicate: *predicate,
+        },
+        Expr::SafeMethodCall { target, name, args } => Expr::SafeMethodCall {
+            target: Arc::new(f(target)?),
+            name: name.clone(),
+            args: all(args, f)?,
+        },
+        Expr::Lambda { params, body } => Expr::Lambda { params: params.clone(), body: Arc::new(f(body)?) },
+    })
+}
+
+/// A function declared with `DEF(:name, [:param, ...], body)`
+struct LocalFunction {
+    params: Vec<String>,
+    body: Expr,
+}
+
+const DEF_USAGE: &str = "DEF expects (:name, [:param, ...], body), e.g. DEF(:double, [:x], :x * 2); double(21)";
+
+/// Take the `DEF` statements out of a sequence and inline calls to the functions
+/// they declare in the statements after them. A call becomes a `LET` binding the
+/// arguments to the parameters, so each argument is evaluated once, in the
+/// caller's scope. Scoping is dynamic: the body's other variables are looked up
+/// where it is called, so it sees the variables assigned before the call and a
+/// `LET` around the call can rebind them.
+fn expand_defs(statements: Vec<Expr>) -> Result<Vec<Expr>, Error> {
+    let is_def = |e: &Expr| matches!(e, Expr::FunctionCall { name, .. } if name == "DEF");
+    if !statements.iter().any(is_def) {
+        return Ok(statements);
+    }
+    let mut defs: HashMap<String, LocalFunction> = HashMap::new();
+    let mut out = Vec::with_capacity(statements.len());
+    for statement in statements {
+        match statement {
+            Expr::FunctionCall { name, args } if name == "DEF" => {
+                let (name, function) = local_function(&args, &defs)?;
+                defs.insert(name, function);
+            }
+            other => out.push(inline_calls(&other, &defs)?),
+        }
+    }
+    if out.is_empty() {
+        return Err(Error::new(format!("DEF must be followed by an expression; {}", DEF_USAGE), None));
+    }
+    Ok(out)
+}
+
+fn local_function(args: &[Expr], defs: &HashMap<String, LocalFunction>) -> Result<(String, LocalFunction), Error> {
+    let [name, params, body] = args else {
+        return Err(Error::new(DEF_USAGE, None));
+    };
+    let binding_name = |e: &Expr| match e {
+        Expr::Variable(n) => Some(n.clone()),
+        Expr::StringLit(n) if !n.is_empty() => Some(n.trim_start_matches(':').to_string()),
+        _ => None,
+    };
+    let name = binding_name(name)
+        .map(|n| n.to_uppercase())
+        .ok_or_else(|| Error::new(format!("DEF names must be like :double, got {}", name), None))?;
+    if crate::runtime::function_dispatch::is_builtin(&name) {
+        return Err(Error::new(format!("DEF cannot redefine the builtin function {}", name), None));
+    }
+    if defs.contains_key(&name) {
+        return Err(Error::new(format!("DEF {} is already defined", name), None));
+    }
+    let Expr::Array(items) = params else {
+        return Err(Error::new(format!("DEF {} parameters must be a list like [:a, :b]", name), None));
+    };
+    let mut param_names: Vec<String> = Vec::with_capacity(items.len());
+    for item in items {
+        let param = binding_name(item).ok_or_else(|| Error::new(format!("DEF {} parameters must be variables like :a, got {}", name, item), None))?;
+        if param_names.contains(&param) {
+            return Err(Error::new(format!("DEF {} has the parameter :{} twice", name, param), None));
+        }
+        param_names.push(param);
+    }
+    let body = inline_calls(body, defs)?;
+    if crate::analysis::analyze(&body).functions.contains(&name) {
+        return Err(Error::new(format!("DEF {} calls itself; recursive functions are not supported", name), None));
+    }
+    Ok((name, LocalFunction { params: param_names, body }))
+}
+
+fn inline_calls(expr: &Expr, defs: &HashMap<String, LocalFunction>) -> Result<Expr, Error> {
+    match expr {
+        Expr::FunctionCall { name, .. } if name == "DEF" => {
+            Err(Error::new(format!("DEF must be a statement of its own, separated by ';'. {}", DEF_USAGE), None))
+        }
+        Expr::FunctionCall { name, args } if defs.contains_key(name) => {
+            let function = &defs[name];
+            if args.iter().any(|a| matches!(a, Expr::Spread(_))) {
+                return Err(Error::new(format!("{} is declared with DEF and cannot take spread arguments", name), None));
+            }
+            if args.len() != function.params.len() {
+                return Err(Error::new(format!("{} expects {} argument(s), got {}", name, function.params.len(), args.len()), None));
+            }
+            let args = args.iter().map(|a| inline_calls(a, defs)).collect::<Result<Vec<_>, _>>()?;
+            let mut bindings = Vec::with_capacity(args.len() * 4 + 1);
+            if let [value] = args.as_slice() {
+                bindings.extend([Expr::Variable(function.params[0].clone()), value.clone()]);
+            } else {
+                // Evaluate every argument before any parameter is bound, so `f(:b, :a)`
+                // with parameters [:a, :b] does not see its own :a
+                for (i, value) in args.into_iter().enumerate() {
+                    bindings.extend([Expr::Variable(format!("__def{}", i)), value]);
+                }
+                for (i, param) in function.params.iter().enumerate() {
+                    bindings.extend([Expr::Variable(param.clone()), Expr::Variable(format!("__def{}", i))]);
+                }
+            }
+            if bindings.is_empty() {
+                return Ok(function.body.clone());
+            }
+            bindings.push(function.body.clone());
+            Ok(Expr::FunctionCall { name: "LET".to_string(), args: bindings })
+        }
+        _ => map_children(expr, &mut |e| inline_calls(e, defs)),
+    }
+}
+
+/// Lower the `let` statements of a sequence. Everything from the first `let` on
+/// becomes one `LET` whose body is the last statement: each `let` and each `:=`
+/// after it is a binding, so those names stay local to the expression, and other
+/// statements in between are still evaluated in order.
+fn lower_lets(statements: Vec<Expr>) -> Result<Vec<Expr>, Error> {
+    let is_let = |e: &Expr| matches!(e, Expr::FunctionCall { name, .. } if name == LET_STATEMENT);
+    let Some(first) = statements.iter().position(is_let) else {
+        return Ok(statements);
+    };
+    let mut out = statements;
+    let mut scoped = out.split_off(first);
+    let body = match scoped.pop() {
+        Some(last) if !is_let(&last) => last,
+        _ => return Err(Error::new("let must be followed by an expression that uses it, e.g. let x := 2; x * 3", None)),
+    };
+    let mut args = Vec::with_capacity(scoped.len() * 2 + 1);
+    for statement in scoped {
+        match statement {
+            Expr::FunctionCall { name, args: binding } if name == LET_STATEMENT => args.extend(binding),
+            Expr::Assignment { variable, value } => args.extend([Expr::Variable(variable), value.as_ref().clone()]),
+            Expr::Destructure { .. } => {
+                return Err(Error::new("Destructuring assignments cannot follow let; bind each name with let instead", None));
+            }
+            other => args.extend([Expr::Variable("__let".to_string()), other]),
+        }
+    }
+    args.push(body);
+    out.push(Expr::FunctionCall { name: "LET".to_string(), args });
+    Ok(out)
+}
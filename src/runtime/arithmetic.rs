@@ -1,9 +1,139 @@
 use crate::types::Value;
 use crate::error::Error;
 
+/// Smallest or largest value across arguments and nested arrays. Numbers and
+/// currency compare as numbers; dates compare as dates and give a DateTime
+/// result; mixing the two is an error. MIN/MAX skip booleans and text, while
+/// MINA/MAXA (`count_text`) count TRUE as 1 and FALSE and text as 0, like Excel.
+/// NaN is skipped, as `f64::min`/`f64::max` do. With nothing to compare the result is 0.
+fn extreme(name: &str, args: &[Value], max: bool, count_text: bool) -> Result<Value, Error> {
+    #[derive(Default)]
+    struct Acc {
+        number: Option<f64>,
+        date: Option<i64>,
+    }
+    fn pick<T: PartialOrd + Copy>(cur: Option<T>, v: T, max: bool) -> Option<T> {
+        Some(match cur {
+            Some(c) if (max && c >= v) || (!max && c <= v) => c,
+            _ => v,
+        })
+    }
+    fn visit(v: &Value, acc: &mut Acc, max: bool, count_text: bool) {
+        match v {
+            Value::Number(n) | Value::Currency(n) if !n.is_nan() => acc.number = pick(acc.number, *n, max),
+            Value::DateTime(ts) => acc.date = pick(acc.date, *ts, max),
+            Value::Boolean(b) if count_text => acc.number = pick(acc.number, if *b { 1.0 } else { 0.0 }, max),
+            Value::String(_) if count_text => acc.number = pick(acc.number, 0.0, max),
+            Value::Array(items) => for it in items { visit(it, acc, max, count_text); },
+            _ => {}
+        }
+    }
+
+    let mut acc = Acc::default();
+    for a in args { visit(a, &mut acc, max, count_text); }
+    match (acc.number, acc.date) {
+        (Some(_), Some(_)) => Err(Error::new(format!("{} cannot compare dates with numbers", name), None)),
+        (None, Some(ts)) => Ok(Value::DateTime(ts)),
+        (n, None) => Ok(Value::Number(n.unwrap_or(0.0))),
+    }
+}
+
+/// What SUM/AVG/MIN/MAX do with an item that is not a number
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum NonNumeric {
+    Ignore,
+    Zero,
+    Error,
+}
+
+impl NonNumeric {
+    fn parse(name: &str, key: &str, value: &serde_json::Value) -> Result<NonNumeric, Error> {
+        match value.as_str() {
+            Some("ignore") => Ok(NonNumeric::Ignore),
+            Some("zero") => Ok(NonNumeric::Zero),
+            Some("error") => Ok(NonNumeric::Error),
+            _ => Err(Error::new(format!("{} option {} must be \"ignore\", \"zero\" or \"error\"", name, key), None)),
+        }
+    }
+}
+
+/// Split a trailing options object such as `{'non_numeric': 'error', 'nulls': 'zero'}`
+/// off the arguments of an aggregate and apply it: the returned arguments hold only
+/// numbers (and dates, when `dates` is set), nested arrays included. `non_numeric`
+/// covers text, booleans, objects and nulls; `nulls` overrides it for nulls. Both
+/// default to "ignore", which is what the aggregates always did. Objects are never
+/// aggregated, so a trailing object cannot be mistaken for data.
+fn aggregate_args<'a>(name: &str, args: &'a [Value], dates: bool) -> Result<std::borrow::Cow<'a, [Value]>, Error> {
+    let options = match args.last() {
+        Some(Value::Json(s)) => match serde_json::from_str::<serde_json::Value>(s) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => return Ok(std::borrow::Cow::Borrowed(args)),
+        },
+        _ => return Ok(std::borrow::Cow::Borrowed(args)),
+    };
+    let mut non_numeric = NonNumeric::Ignore;
+    let mut nulls = None;
+    for (key, value) in &options {
+        match key.as_str() {
+            "non_numeric" => non_numeric = NonNumeric::parse(name, key, value)?,
+            "nulls" => nulls = Some(NonNumeric::parse(name, key, value)?),
+            other => return Err(Error::new(format!("{} has no option '{}'; use non_numeric or nulls", name, other), None)),
+        }
+    }
+    let nulls = nulls.unwrap_or(non_numeric);
+
+    fn clean(name: &str, v: &Value, dates: bool, non_numeric: NonNumeric, nulls: NonNumeric) -> Result<Option<Value>, Error> {
+        let policy = match v {
+            Value::Number(_) | Value::Currency(_) => return Ok(Some(v.clone())),
+            Value::DateTime(_) if dates => return Ok(Some(v.clone())),
+            Value::Array(items) => {
+                let mut out = Vec::with_capacity(items.len());
+                for item in items {
+                    out.extend(clean(name, item, dates, non_numeric, nulls)?);
+                }
+                return Ok(Some(Value::Array(out)));
+            }
+            Value::Null => nulls,
+            _ => non_numeric,
+        };
+        match policy {
+            NonNumeric::Ignore => Ok(None),
+            NonNumeric::Zero => Ok(Some(Value::Number(0.0))),
+            NonNumeric::Error => Err(Error::new(format!("{} found a non-numeric value: {:?}", name, v), None)),
+        }
+    }
+    let data = &args[..args.len() - 1];
+    let mut out = Vec::with_capacity(data.len());
+    for v in data {
+        out.extend(clean(name, v, dates, non_numeric, nulls)?);
+    }
+    Ok(std::borrow::Cow::Owned(out))
+}
+
+/// CHECKEDADD/SUB/MUL(a, b): the result, or null when it leaves the range where
+/// integers are exact (±2^53) or is not finite. Under `overflow_check` the overflow
+/// is an error instead. A null operand gives null.
+fn checked(name: &str, args: &[Value], op: fn(f64, f64) -> f64) -> Result<Value, Error> {
+    let (a, b, currency) = match args {
+        [Value::Null, _] | [_, Value::Null] => return Ok(Value::Null),
+        [Value::Number(a), Value::Number(b)] => (*a, *b, false),
+        [Value::Number(a) | Value::Currency(a), Value::Number(b) | Value::Currency(b)] => (*a, *b, true),
+        _ => return Err(Error::new(format!("{} expects two numbers", name), None)),
+    };
+    let result = op(a, b);
+    if !(result.is_finite() && result.abs() <= crate::context::MAX_SAFE_INTEGER) {
+        if crate::context::overflow_check() {
+            return Err(Error::new(format!("Numeric overflow in {}", name), None));
+        }
+        return Ok(Value::Null);
+    }
+    Ok(if currency { Value::Currency(result) } else { Value::Number(result) })
+}
+
 pub fn exec_arithmetic(name: &str, args: &[Value]) -> Result<Value, Error> {
     match name {
         "SUM" => {
+            let args = &*aggregate_args(name, args, false)?;
             let mut acc = 0.0;
             fn sum_value(v: &Value, acc: &mut f64) {
                 match v {
@@ -16,7 +146,7 @@ pub fn exec_arithmetic(name: &str, args: &[Value]) -> Result<Value, Error> {
                     Value::Null => {}
                     Value::Currency(n) => *acc += *n,
                     Value::DateTime(_) => {}
-                    Value::Json(_) => {}
+                    Value::Json(_) | Value::Map(_) => {}
                 }
             }
             for a in args { sum_value(a, &mut acc); }
@@ -65,6 +195,7 @@ pub fn exec_arithmetic(name: &str, args: &[Value]) -> Result<Value, Error> {
             Ok(Value::Number(n.ceil()))
         }
         "AVG" | "AVERAGE" => {
+            let args = &*aggregate_args(name, args, false)?;
             let mut acc = 0.0;
             let mut count = 0usize;
             fn visit(v: &Value, acc: &mut f64, count: &mut usize) {
@@ -76,47 +207,17 @@ pub fn exec_arithmetic(name: &str, args: &[Value]) -> Result<Value, Error> {
                     Value::Null => {}
                     Value::Currency(n) => { *acc += *n; *count += 1; }
                     Value::DateTime(_) => {}
-                    Value::Json(_) => {}
+                    Value::Json(_) | Value::Map(_) => {}
                 }
             }
             for a in args { visit(a, &mut acc, &mut count); }
             let avg = if count == 0 { 0.0 } else { acc / count as f64 };
             Ok(Value::Number(avg))
         }
-        "MIN" => {
-            let mut cur: Option<f64> = None;
-            fn visit(v: &Value, cur: &mut Option<f64>) {
-                match v {
-                    Value::Number(n) => { *cur = Some(cur.map_or(*n, |c| c.min(*n))); }
-                    Value::Array(items) => for it in items { visit(it, cur); },
-                    Value::Boolean(_) => {}
-                    Value::String(_) => {}
-                    Value::Null => {}
-                    Value::Currency(n) => { *cur = Some(cur.map_or(*n, |c| c.min(*n))); }
-                    Value::DateTime(_) => {}
-                    Value::Json(_) => {}
-                }
-            }
-            for a in args { visit(a, &mut cur); }
-            Ok(Value::Number(cur.unwrap_or(0.0)))
-        }
-        "MAX" => {
-            let mut cur: Option<f64> = None;
-            fn visit(v: &Value, cur: &mut Option<f64>) {
-                match v {
-                    Value::Number(n) => { *cur = Some(cur.map_or(*n, |c| c.max(*n))); }
-                    Value::Array(items) => for it in items { visit(it, cur); },
-                    Value::Boolean(_) => {}
-                    Value::String(_) => {}
-                    Value::Null => {}
-                    Value::Currency(n) => { *cur = Some(cur.map_or(*n, |c| c.max(*n))); }
-                    Value::DateTime(_) => {}
-                    Value::Json(_) => {}
-                }
-            }
-            for a in args { visit(a, &mut cur); }
-            Ok(Value::Number(cur.unwrap_or(0.0)))
-        }
+        "MIN" => extreme(name, &aggregate_args(name, args, true)?, false, false),
+        "MAX" => extreme(name, &aggregate_args(name, args, true)?, true, false),
+        "MINA" => extreme(name, args, false, true),
+        "MAXA" => extreme(name, args, true, true),
         "PRODUCT" | "MULTIPLY" => {
             let mut acc = 1.0;
             fn multiply_value(v: &Value, acc: &mut f64) {
@@ -130,12 +231,66 @@ pub fn exec_arithmetic(name: &str, args: &[Value]) -> Result<Value, Error> {
                     Value::Null => {}
                     Value::Currency(n) => *acc *= *n,
                     Value::DateTime(_) => {}
-                    Value::Json(_) => {}
+                    Value::Json(_) | Value::Map(_) => {}
                 }
             }
             for a in args { multiply_value(a, &mut acc); }
             Ok(Value::Number(acc))
         }
+        "CHECKEDADD" => checked(name, args, |a, b| a + b),
+        "CHECKEDSUB" => checked(name, args, |a, b| a - b),
+        "CHECKEDMUL" => checked(name, args, |a, b| a * b),
+        "CLAMP" => {
+            // CLAMP(x, lo, hi) -> x limited to [lo, hi]; null passes through
+            match args {
+                [Value::Null, _, _] => Ok(Value::Null),
+                [Value::Number(x) | Value::Currency(x), Value::Number(lo) | Value::Currency(lo), Value::Number(hi) | Value::Currency(hi)] => {
+                    if lo > hi || lo.is_nan() || hi.is_nan() {
+                        return Err(Error::new("CLAMP lower bound must not exceed the upper bound", None));
+                    }
+                    let clamped = x.clamp(*lo, *hi);
+                    Ok(if matches!(args[0], Value::Currency(_)) { Value::Currency(clamped) } else { Value::Number(clamped) })
+                }
+                _ => Err(Error::new("CLAMP expects x, lo, hi", None)),
+            }
+        }
+        "LERP" => super::interpolation::exec_lerp(args),
+        "INTERP" => super::interpolation::exec_interp(args),
+        "ROUNDCASH" => super::currency::exec_round_cash(args),
+        "PERCENTOF" => {
+            // PERCENTOF(part, whole) -> ratio (0.25 for 25%); null when whole is 0
+            match (args.first(), args.get(1)) {
+                (Some(Value::Number(part) | Value::Currency(part)), Some(Value::Number(whole) | Value::Currency(whole))) => {
+                    if *whole == 0.0 { Ok(Value::Null) } else { Ok(Value::Number(part / whole)) }
+                }
+                _ => Err(Error::new("PERCENTOF expects part, whole", None)),
+            }
+        }
+        "AVGRATIO" => {
+            // AVGRATIO(parts, wholes) -> SUM(parts) / SUM(wholes). Averaging the individual
+            // percentages instead over-weights small groups.
+            let (parts, wholes) = match (args.first(), args.get(1)) {
+                (Some(Value::Array(p)), Some(Value::Array(w))) if p.len() == w.len() => (p, w),
+                (Some(Value::Array(_)), Some(Value::Array(_))) => {
+                    return Err(Error::new("AVGRATIO expects arrays of the same length", None))
+                }
+                _ => return Err(Error::new("AVGRATIO expects parts array, wholes array", None)),
+            };
+            let mut part_sum = 0.0;
+            let mut whole_sum = 0.0;
+            for (p, w) in parts.iter().zip(wholes) {
+                match (p, w) {
+                    (Value::Number(p) | Value::Currency(p), Value::Number(w) | Value::Currency(w)) => {
+                        part_sum += p;
+                        whole_sum += w;
+                    }
+                    // Rows with a missing part or whole are skipped as a pair
+                    (Value::Null, _) | (_, Value::Null) => {}
+                    _ => return Err(Error::new("AVGRATIO expects numeric arrays", None)),
+                }
+            }
+            if whole_sum == 0.0 { Ok(Value::Null) } else { Ok(Value::Number(part_sum / whole_sum)) }
+        }
         _ => Err(Error::new(format!("Unknown arithmetic function: {}", name), None)),
     }
 }
\ No newline at end of file
@@ -1,11 +1,222 @@
 use crate::types::Value;
 use crate::error::Error;
-use crate::runtime::utils::values_equal;
+use crate::runtime::method_calls::conversion_methods::value_to_json_value;
+use crate::runtime::utils::{format_number, to_index, values_equal};
 use std::collections::BTreeSet;
 
+/// Longest array a range literal may produce when no `max_array_size` is set
+pub const MAX_RANGE_LEN: usize = 10_000_000;
+
+/// `start..end` / `start..=end`: numbers from `start` in steps of 1.
+/// A range whose end is not past its start is empty.
+fn exec_range(args: &[Value], inclusive: bool) -> Result<Value, Error> {
+    let bound = |v: Option<&Value>| match v {
+        Some(Value::Number(n)) if n.is_finite() => Ok(*n),
+        Some(Value::Currency(n)) if n.is_finite() => Ok(*n),
+        _ => Err(Error::new("Range bounds must be finite numbers", None)),
+    };
+    let (start, end) = (bound(args.first())?, bound(args.get(1))?);
+    let span = (end - start).floor();
+    let len = if span < 0.0 || (!inclusive && end - start <= 0.0) {
+        0.0
+    } else if inclusive || (end - start) > span {
+        span + 1.0
+    } else {
+        span
+    };
+    // Check the size before allocating anything
+    if len > MAX_RANGE_LEN as f64 {
+        return Err(Error::new(format!("Range of {} elements exceeds the limit of {}", len, MAX_RANGE_LEN), None));
+    }
+    let len = len as usize;
+    crate::context::check_array_len(len)?;
+    Ok(Value::Array((0..len).map(|i| Value::Number(start + i as f64)).collect()))
+}
+
+/// RANGE(start, end, [step]) -> numbers from `start` towards `end` in steps of `step`
+/// (default 1), including `end` when a step lands on it: `RANGE(0, 1, 0.25)` gives
+/// five numbers and `RANGE(10, 1, -3)` gives `[10, 7, 4, 1]`. A step pointing away
+/// from `end` gives an empty array.
+fn exec_range_fn(args: &[Value]) -> Result<Value, Error> {
+    let number = |what: &str, v: &Value| match v {
+        Value::Number(n) | Value::Currency(n) if n.is_finite() => Ok(*n),
+        other => Err(Error::new(format!("RANGE {} must be a finite number, got {:?}", what, other), None)),
+    };
+    let (start, end, step) = match args {
+        [start, end] => (number("start", start)?, number("end", end)?, 1.0),
+        [start, end, step] => (number("start", start)?, number("end", end)?, number("step", step)?),
+        _ => return Err(Error::new("RANGE expects start, end, [step]", None)),
+    };
+    if step == 0.0 {
+        return Err(Error::new("RANGE step cannot be 0", None));
+    }
+    // Tolerate rounding in fractional steps such as 0.1 so the end is still reached
+    let steps = (end - start) / step;
+    let len = if steps < 0.0 { 0.0 } else { (steps + 1e-9).floor() + 1.0 };
+    if len > MAX_RANGE_LEN as f64 {
+        return Err(Error::new(format!("Range of {} elements exceeds the limit of {}", len, MAX_RANGE_LEN), None));
+    }
+    let len = len as usize;
+    crate::context::check_array_len(len)?;
+    // Multiply instead of adding up steps, so errors do not accumulate
+    Ok(Value::Array((0..len).map(|i| Value::Number(start + i as f64 * step)).collect()))
+}
+
+/// A whole-number size or position argument for PAGE / OFFSETLIMIT
+fn count_arg(name: &str, what: &str, arg: &Value, min: usize) -> Result<usize, Error> {
+    let invalid = || Error::new(format!("{} {} must be a whole number of at least {}, got {:?}", name, what, min, arg), None);
+    let n = match arg {
+        Value::Number(n) => to_index(*n, &format!("{} {}", name, what))?,
+        _ => return Err(invalid()),
+    };
+    if n < min as isize {
+        return Err(invalid());
+    }
+    Ok(n as usize)
+}
+
+/// ORDINAL(value, [categories]) -> 0-based position of `value` in the list, so
+/// `ORDINAL('gold', ['bronze', 'silver', 'gold'])` is 2; null when it is not listed
+fn exec_ordinal(args: &[Value]) -> Result<Value, Error> {
+    match args {
+        [Value::Null, Value::Array(_)] => Ok(Value::Null),
+        [value, Value::Array(categories)] => Ok(categories
+            .iter()
+            .position(|c| values_equal(c, value))
+            .map_or(Value::Null, |i| Value::Number(i as f64))),
+        _ => Err(Error::new("ORDINAL expects value, array of categories", None)),
+    }
+}
+
+/// NTH(arr, i, [out_of_range]) -> the item at 0-based `i`, negative counting from the
+/// end like `arr[i]`. Past the ends `out_of_range` decides: "error" (default), "null",
+/// "clamp" to the first or last item, or "wrap" around.
+fn exec_nth(args: &[Value]) -> Result<Value, Error> {
+    let (items, i, mode) = match args {
+        [Value::Array(items), Value::Number(i)] => (items, *i, "error"),
+        [Value::Array(items), Value::Number(i), Value::String(mode)] => (items, *i, mode.as_str()),
+        _ => return Err(Error::new("NTH expects array, index, [out_of_range]", None)),
+    };
+    let i = crate::runtime::utils::to_index(i, "NTH index")?;
+    if let Some(pos) = crate::runtime::utils::clamp_index(items.len(), i) {
+        return Ok(items[pos].clone());
+    }
+    let len = items.len() as isize;
+    match mode {
+        "null" => Ok(Value::Null),
+        "clamp" | "wrap" if items.is_empty() => Err(Error::new("NTH on empty array", None)),
+        "clamp" => Ok(items[if i < 0 { 0 } else { items.len() - 1 }].clone()),
+        "wrap" => Ok(items[i.rem_euclid(len) as usize].clone()),
+        "error" => Err(Error::new(format!("NTH index {} is out of bounds for {} items", i, len), None)),
+        other => Err(Error::new(format!("NTH out_of_range must be \"error\", \"null\", \"clamp\" or \"wrap\", got '{}'", other), None)),
+    }
+}
+
+/// OFFSETLIMIT(arr, offset, limit) -> up to `limit` items after skipping `offset`
+fn exec_offset_limit(args: &[Value]) -> Result<Value, Error> {
+    let (items, offset, limit) = match args {
+        [Value::Array(items), offset, limit] => {
+            (items, count_arg("OFFSETLIMIT", "offset", offset, 0)?, count_arg("OFFSETLIMIT", "limit", limit, 0)?)
+        }
+        _ => return Err(Error::new("OFFSETLIMIT expects array, offset, limit", None)),
+    };
+    Ok(Value::Array(items.iter().skip(offset).take(limit).cloned().collect()))
+}
+
+/// PAGE(arr, page, per_page) -> `{"items", "total", "pages", "page", "per_page"}` for
+/// the 1-based `page`; a page past the end has no items
+fn exec_page(args: &[Value]) -> Result<Value, Error> {
+    let (items, page, per_page) = match args {
+        [Value::Array(items), page, per_page] => {
+            (items, count_arg("PAGE", "page", page, 1)?, count_arg("PAGE", "per_page", per_page, 1)?)
+        }
+        _ => return Err(Error::new("PAGE expects array, page, per_page", None)),
+    };
+    let start = (page - 1).saturating_mul(per_page);
+    let slice = items
+        .iter()
+        .skip(start)
+        .take(per_page)
+        .map(crate::runtime::method_calls::conversion_methods::value_to_json_value)
+        .collect::<Result<Vec<_>, Error>>()?;
+    let result = serde_json::json!({
+        "items": slice,
+        "total": items.len(),
+        "pages": items.len().div_ceil(per_page),
+        "page": page,
+        "per_page": per_page,
+    });
+    Ok(Value::Json(result.to_string()))
+}
+
+/// One `field [ASC|DESC]` term of an ORDERBY order. Fields are names or dotted
+/// paths into nested objects; nothing in the order is evaluated.
+fn order_term(term: &str) -> Result<(Vec<&str>, bool), Error> {
+    let invalid = || Error::new(format!("ORDERBY order '{}' must read like \"field [ASC|DESC]\"", term.trim()), None);
+    let mut words = term.split_whitespace();
+    let field = words.next().ok_or_else(invalid)?;
+    let descending = match words.next() {
+        None => false,
+        Some(dir) if dir.eq_ignore_ascii_case("ASC") => false,
+        Some(dir) if dir.eq_ignore_ascii_case("DESC") => true,
+        Some(_) => return Err(invalid()),
+    };
+    let path: Vec<&str> = field.split('.').collect();
+    let is_name = |s: &&str| !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_');
+    if words.next().is_some() || !path.iter().all(is_name) {
+        return Err(invalid());
+    }
+    Ok((path, descending))
+}
+
+/// The value at `path` in an object item; null where the path runs out
+fn field_at(item: &Value, path: &[&str]) -> Result<Value, Error> {
+    if !matches!(item, Value::Map(_) | Value::Json(_)) {
+        return Err(Error::new("ORDERBY expects an array of objects", None));
+    }
+    let mut current = item.clone();
+    for key in path {
+        current = match current {
+            Value::Map(mut map) => map.swap_remove(*key).unwrap_or(Value::Null),
+            Value::Json(s) => {
+                let mut json: serde_json::Value = serde_json::from_str(&s).map_err(|e| Error::new(format!("Invalid JSON: {}", e), None))?;
+                json.get_mut(*key).map_or(Ok(Value::Null), |v| crate::json_to_value(v.take()))?
+            }
+            _ => Value::Null,
+        };
+    }
+    Ok(current)
+}
+
+/// ORDERBY(arr, order) -> the objects of `arr` sorted SQL-style, e.g.
+/// `ORDERBY(:sales, "amount DESC, name ASC")`. Sorts like `.sort_by`: stable, later
+/// fields break ties and nulls come last (first when descending).
+fn exec_orderby(args: &[Value]) -> Result<Value, Error> {
+    let [Value::Array(items), Value::String(order)] = args else {
+        return Err(Error::new("ORDERBY expects array, order like \"amount DESC, name\"", None));
+    };
+    let terms = order.split(',').map(order_term).collect::<Result<Vec<_>, Error>>()?;
+    let descending = terms.iter().map(|(_, desc)| *desc).collect::<Vec<_>>();
+    let mut rows = Vec::with_capacity(items.len());
+    for item in items {
+        let keys = terms.iter().map(|(path, _)| field_at(item, path)).collect::<Result<Vec<_>, Error>>()?;
+        rows.push((keys, item));
+    }
+    crate::runtime::utils::sort_by_keys(&mut rows, &descending, "ORDERBY")?;
+    Ok(Value::Array(rows.into_iter().map(|(_, item)| item.clone()).collect()))
+}
+
 pub fn exec_array(name: &str, args: &[Value]) -> Result<Value, Error> {
     match name {
+        "__RANGE__" => exec_range(args, false),
+        "__RANGE_INCLUSIVE__" => exec_range(args, true),
+        "RANGE" => exec_range_fn(args),
         "ARRAY" => Ok(Value::Array(args.to_vec())),
+        "PAGE" => exec_page(args),
+        "OFFSETLIMIT" => exec_offset_limit(args),
+        "ORDINAL" => exec_ordinal(args),
+        "NTH" => exec_nth(args),
+        "ORDERBY" => exec_orderby(args),
         "FLATTEN" => {
             fn flatten(v: &Value, out: &mut Vec<Value>) {
                 match v {
@@ -78,16 +289,19 @@ pub fn exec_array(name: &str, args: &[Value]) -> Result<Value, Error> {
                 for it in items {
                     match it {
                         Value::String(s) => parts.push(s.clone()),
-                        Value::Number(n) => parts.push(n.to_string()),
+                        Value::Number(n) => parts.push(format_number(*n)),
                         Value::Boolean(b) => parts.push(if *b {"TRUE".into()} else {"FALSE".into()}),
                         Value::Null => parts.push(String::new()),
-                        Value::Currency(n) => parts.push(format!("{:.4}", n)),
+                        Value::Currency(n) => parts.push(super::currency::display(*n)),
                         Value::DateTime(ts) => parts.push(ts.to_string()),
                         Value::Json(s) => parts.push(s.clone()),
+                        Value::Map(_) => parts.push(value_to_json_value(it)?.to_string()),
                         Value::Array(_) => return Err(Error::new("JOIN does not flatten nested arrays", None)),
                     }
                 }
-                Ok(Value::String(parts.join(sep)))
+                let joined = parts.join(sep);
+                crate::context::check_string_len(joined.chars().count())?;
+                Ok(Value::String(joined))
             }
             _ => Err(Error::new("JOIN expects array, [separator]", None))
         },
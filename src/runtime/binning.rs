@@ -0,0 +1,62 @@
+//! Score banding and distribution summaries over a list of bucket edges.
+//!
+//! Edges are ascending numbers; `n` edges make `n + 1` buckets. Bucket `0` holds
+//! values below the first edge, bucket `i` values from edge `i - 1` (inclusive) up
+//! to edge `i`, and bucket `n` values from the last edge on. With edges
+//! `[50, 80]` a score of 49 is in bucket 0, 50 in bucket 1 and 80 in bucket 2.
+
+use crate::error::Error;
+use crate::types::Value;
+
+fn edges_arg(name: &str, arg: &Value) -> Result<Vec<f64>, Error> {
+    let Value::Array(items) = arg else {
+        return Err(Error::new(format!("{} expects an array of bucket edges", name), None));
+    };
+    let edges = items
+        .iter()
+        .map(|v| match v {
+            Value::Number(n) | Value::Currency(n) if n.is_finite() => Ok(*n),
+            other => Err(Error::new(format!("{} edges must be finite numbers, got {:?}", name, other), None)),
+        })
+        .collect::<Result<Vec<f64>, Error>>()?;
+    if edges.windows(2).any(|w| w[0] >= w[1]) {
+        return Err(Error::new(format!("{} edges must be in increasing order", name), None));
+    }
+    Ok(edges)
+}
+
+/// Bucket of `x`: the number of edges at or below it
+fn bucket(x: f64, edges: &[f64]) -> usize {
+    edges.partition_point(|edge| *edge <= x)
+}
+
+/// BIN(value, edges) -> bucket index of `value`; null passes through
+pub fn exec_bin(args: &[Value]) -> Result<Value, Error> {
+    let [value, edges] = args else {
+        return Err(Error::new("BIN expects value, edges", None));
+    };
+    let edges = edges_arg("BIN", edges)?;
+    match value {
+        Value::Null => Ok(Value::Null),
+        Value::Number(n) | Value::Currency(n) if !n.is_nan() => Ok(Value::Number(bucket(*n, &edges) as f64)),
+        other => Err(Error::new(format!("BIN expects a number, got {:?}", other), None)),
+    }
+}
+
+/// HISTOGRAM(arr, edges) -> count of values per bucket, `edges + 1` counts in all.
+/// Nulls are skipped.
+pub fn exec_histogram(args: &[Value]) -> Result<Value, Error> {
+    let (items, edges) = match args {
+        [Value::Array(items), edges] => (items, edges_arg("HISTOGRAM", edges)?),
+        _ => return Err(Error::new("HISTOGRAM expects array, edges", None)),
+    };
+    let mut counts = vec![0usize; edges.len() + 1];
+    for item in items {
+        match item {
+            Value::Null => {}
+            Value::Number(n) | Value::Currency(n) if !n.is_nan() => counts[bucket(*n, &edges)] += 1,
+            other => return Err(Error::new(format!("HISTOGRAM expects numbers, got {:?}", other), None)),
+        }
+    }
+    Ok(Value::Array(counts.into_iter().map(|c| Value::Number(c as f64)).collect()))
+}
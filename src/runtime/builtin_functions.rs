@@ -9,6 +9,10 @@ use super::financial;
 use super::statistical;
 
 pub fn exec_builtin(name: &str, args: &[Value]) -> Result<Value, Error> {
+    super::function_dispatch::exec_builtin_checked(name, args, |args| exec_builtin_unchecked(name, args))
+}
+
+fn exec_builtin_unchecked(name: &str, args: &[Value]) -> Result<Value, Error> {
     // Try arithmetic functions first
     if let Ok(result) = arithmetic::exec_arithmetic(name, args) {
         return Ok(result);
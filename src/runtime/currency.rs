@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+use crate::error::Error;
+use crate::types::Value;
+
+/// Display rules for an ISO 4217 currency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CurrencyInfo {
+    pub code: &'static str,
+    pub symbol: &'static str,
+    /// Minor units shown (JPY 0, USD 2, KWD 3)
+    pub decimals: u32,
+    /// `$1.00` vs `1.00 kr`
+    pub symbol_first: bool,
+    /// Space between symbol and amount (`CHF 1.00`)
+    pub spaced: bool,
+}
+
+const fn info(code: &'static str, symbol: &'static str, decimals: u32, symbol_first: bool, spaced: bool) -> CurrencyInfo {
+    CurrencyInfo { code, symbol, decimals, symbol_first, spaced }
+}
+
+pub const CURRENCIES: &[CurrencyInfo] = &[
+    info("USD", "$", 2, true, false),
+    info("EUR", "€", 2, true, false),
+    info("GBP", "£", 2, true, false),
+    info("MXN", "$", 2, true, false),
+    info("CAD", "CA$", 2, true, false),
+    info("AUD", "A$", 2, true, false),
+    info("BRL", "R$", 2, true, false),
+    info("ARS", "$", 2, true, false),
+    info("COP", "$", 2, true, false),
+    info("CLP", "$", 0, true, false),
+    info("JPY", "¥", 0, true, false),
+    info("CNY", "CN¥", 2, true, false),
+    info("KRW", "₩", 0, true, false),
+    info("INR", "₹", 2, true, false),
+    info("CHF", "CHF", 2, true, true),
+    info("KWD", "KD", 3, true, true),
+    info("SEK", "kr", 2, false, true),
+    info("NOK", "kr", 2, false, true),
+    info("DKK", "kr", 2, false, true),
+    info("PLN", "zł", 2, false, true),
+];
+
+/// Smallest circulating denomination where it is coarser than the minor unit
+const CASH_INCREMENTS: &[(&str, f64)] = &[
+    ("CHF", 0.05),
+    ("CAD", 0.05),
+    ("AUD", 0.05),
+    ("DKK", 0.5),
+    ("SEK", 1.0),
+    ("NOK", 1.0),
+];
+
+lazy_static::lazy_static! {
+    /// Host overrides of [`CASH_INCREMENTS`], e.g. a chain rounding MXN to 0.50
+    static ref CASH_OVERRIDES: ArcSwap<HashMap<String, f64>> = ArcSwap::from_pointee(HashMap::new());
+}
+
+/// Set the cash rounding increment ROUNDCASH uses for `code`, replacing the built-in one
+pub fn set_cash_increment(code: &str, increment: f64) -> Result<(), Error> {
+    let currency = lookup_or_err(code)?;
+    if !(increment.is_finite() && increment > 0.0) {
+        return Err(Error::new(format!("Cash increment for {} must be a positive number", currency.code), None));
+    }
+    CASH_OVERRIDES.rcu(|current| {
+        let mut overrides = HashMap::clone(current);
+        overrides.insert(currency.code.to_string(), increment);
+        overrides
+    });
+    Ok(())
+}
+
+/// Drop every [`set_cash_increment`] override
+pub fn clear_cash_increments() {
+    CASH_OVERRIDES.store(Arc::new(HashMap::new()));
+}
+
+/// The [`set_cash_increment`] overrides in effect, by currency code
+pub fn cash_increment_overrides() -> HashMap<String, f64> {
+    HashMap::clone(&CASH_OVERRIDES.load())
+}
+
+/// The increment cash amounts in `currency` round to: a host override, the
+/// built-in table, or else the minor unit
+pub fn cash_increment(currency: &CurrencyInfo) -> f64 {
+    if let Some(increment) = CASH_OVERRIDES.load().get(currency.code) {
+        return *increment;
+    }
+    CASH_INCREMENTS
+        .iter()
+        .find(|(code, _)| *code == currency.code)
+        .map(|(_, increment)| *increment)
+        .unwrap_or_else(|| 10f64.powi(-(currency.decimals as i32)))
+}
+
+/// Round `amount` to a multiple of `increment`, half away from zero like a till.
+/// The result is cleaned up to the currency's minor units, so 0.05 steps give
+/// `1.05` rather than `1.0500000000000003`.
+pub fn round_cash(amount: f64, increment: f64, decimals: u32) -> f64 {
+    // Amounts are decimal; trim binary noise so 1.025 / 0.05 counts as a tie
+    let steps = ((amount / increment) * 1e9).round() / 1e9;
+    let factor = 10f64.powi(decimals as i32);
+    ((steps.round() * increment) * factor).round() / factor
+}
+
+/// Look up a currency by ISO code (case-insensitive)
+pub fn lookup(code: &str) -> Option<&'static CurrencyInfo> {
+    CURRENCIES.iter().find(|c| c.code.eq_ignore_ascii_case(code))
+}
+
+fn lookup_or_err(code: &str) -> Result<&'static CurrencyInfo, Error> {
+    lookup(code).ok_or_else(|| Error::new(format!("Unknown currency code '{}'", code), None))
+}
+
+/// Render `amount` with the currency's symbol, position and minor units, e.g. `-$1,234.50`
+pub fn format_currency(amount: f64, currency: &CurrencyInfo) -> String {
+    // Money rounds half away from zero; `{:.N}` alone would round half to even
+    let factor = 10f64.powi(currency.decimals as i32);
+    let fixed = format!("{:.*}", currency.decimals as usize, (amount.abs() * factor).round() / factor);
+    let (int_part, frac_part) = match fixed.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (fixed.as_str(), None),
+    };
+
+    let mut grouped = String::with_capacity(int_part.len() + int_part.len() / 3);
+    for (i, ch) in int_part.chars().enumerate() {
+        if i > 0 && (int_part.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(ch);
+    }
+    if let Some(f) = frac_part {
+        grouped.push('.');
+        grouped.push_str(f);
+    }
+
+    let is_negative = amount < 0.0 && grouped.chars().any(|c| c.is_ascii_digit() && c != '0');
+    let sign = if is_negative { "-" } else { "" };
+    let space = if currency.spaced { " " } else { "" };
+    if currency.symbol_first {
+        format!("{}{}{}{}", sign, currency.symbol, space, grouped)
+    } else {
+        format!("{}{}{}{}", sign, grouped, space, currency.symbol)
+    }
+}
+
+/// Text form of a `Currency` value: formatted with the evaluation's `currency`
+/// option when one is set, otherwise a plain amount with two decimals.
+pub fn display(amount: f64) -> String {
+    let code = crate::context::with_options(|opts| opts.currency.clone());
+    match code.as_deref().and_then(lookup) {
+        Some(currency) => format_currency(amount, currency),
+        None => format!("{:.2}", amount),
+    }
+}
+
+/// CURRENCY(amount, [code]) -> formatted string. The code defaults to the
+/// evaluation's `currency` option, then USD.
+pub fn exec_currency(args: &[Value]) -> Result<Value, Error> {
+    let amount = match args.first() {
+        Some(Value::Number(n)) | Some(Value::Currency(n)) => *n,
+        _ => return Err(Error::new("CURRENCY expects amount, [currency_code]", None)),
+    };
+    if !amount.is_finite() {
+        return Err(Error::new(format!("CURRENCY amount must be a finite number, got {}", amount), None));
+    }
+    let currency = match args.get(1) {
+        Some(Value::String(code)) => lookup_or_err(code)?,
+        Some(_) => return Err(Error::new("CURRENCY currency_code must be a string like \"MXN\"", None)),
+        None => {
+            let code = crate::context::with_options(|opts| opts.currency.clone());
+            lookup_or_err(code.as_deref().unwrap_or("USD"))?
+        }
+    };
+    Ok(Value::String(format_currency(amount, currency)))
+}
+
+/// ROUNDCASH(amount, [code], [increment]) -> amount rounded to the currency's
+/// smallest circulating denomination. The code defaults like CURRENCY's; an explicit
+/// increment overrides the currency's.
+pub fn exec_round_cash(args: &[Value]) -> Result<Value, Error> {
+    let (amount, is_currency) = match args.first() {
+        Some(Value::Number(n)) => (*n, false),
+        Some(Value::Currency(n)) => (*n, true),
+        _ => return Err(Error::new("ROUNDCASH expects amount, [currency_code], [increment]", None)),
+    };
+    let currency = match args.get(1) {
+        Some(Value::String(code)) => lookup_or_err(code)?,
+        Some(Value::Null) | None => {
+            let code = crate::context::with_options(|opts| opts.currency.clone());
+            lookup_or_err(code.as_deref().unwrap_or("USD"))?
+        }
+        Some(_) => return Err(Error::new("ROUNDCASH currency_code must be a string like \"CHF\"", None)),
+    };
+    let increment = match args.get(2) {
+        Some(Value::Number(n)) if n.is_finite() && *n > 0.0 => *n,
+        Some(_) => return Err(Error::new("ROUNDCASH increment must be a positive number", None)),
+        None => cash_increment(currency),
+    };
+    // An explicit increment may be finer than the minor unit (fuel prices)
+    let decimals = currency.decimals.max(decimal_places(increment));
+    let rounded = round_cash(amount, increment, decimals);
+    Ok(if is_currency { Value::Currency(rounded) } else { Value::Number(rounded) })
+}
+
+/// Decimal places needed to write `n` (up to 9)
+fn decimal_places(n: f64) -> u32 {
+    (0..9)
+        .find(|d| {
+            let scaled = n * 10f64.powi(*d as i32);
+            (scaled - scaled.round()).abs() < 1e-9
+        })
+        .unwrap_or(9)
+}
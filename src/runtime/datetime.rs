@@ -3,19 +3,21 @@ use crate::error::Error;
 use chrono::{DateTime, Local, NaiveDate, Utc, Datelike, Timelike};
 
 pub fn is_datetime_function(name: &str) -> bool {
-    matches!(name, "NOW" | "DATE" | "TIME" | "YEAR" | "MONTH" | "DAY" | "DATEADD" | "DATEDIFF")
+    matches!(name, "NOW" | "DATE" | "TIME" | "YEAR" | "MONTH" | "DAY" | "DATEADD" | "DATEDIFF" | "HUMANIZE" | "HUMANIZEDURATION" | "DAYSINPERIOD" | "YEARFRAC")
 }
 
 pub fn exec_datetime(name: &str, args: &[Value]) -> Result<Value, Error> {
     match name {
-        "NOW" => {
-            let now = Utc::now();
-            Ok(Value::DateTime(now.timestamp()))
-        }
+        "DAYSINPERIOD" => super::daycount::exec_days_in_period(args),
+        "YEARFRAC" => super::daycount::exec_year_frac(args),
+        "NOW" => Ok(Value::DateTime(crate::context::now_override().unwrap_or_else(|| Utc::now().timestamp()))),
         "DATE" => {
             if args.is_empty() {
-                // No arguments - return today's date
-                let today = Local::now().date_naive();
+                // No arguments - return today's date, in UTC under a frozen clock
+                let today = match frozen_now() {
+                    Some(now) => now.date_naive(),
+                    None => Local::now().date_naive(),
+                };
                 let timestamp = today.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
                 Ok(Value::DateTime(timestamp))
             } else if args.len() == 3 {
@@ -43,7 +45,10 @@ pub fn exec_datetime(name: &str, args: &[Value]) -> Result<Value, Error> {
             }
         }
         "TIME" => {
-            let now = Local::now().time();
+            let now = match frozen_now() {
+                Some(now) => now.time(),
+                None => Local::now().time(),
+            };
             let seconds_since_midnight = now.num_seconds_from_midnight() as f64;
             Ok(Value::Number(seconds_since_midnight))
         }
@@ -168,6 +173,76 @@ pub fn exec_datetime(name: &str, args: &[Value]) -> Result<Value, Error> {
             
             Ok(Value::Number(diff))
         }
+        "HUMANIZE" => {
+            let seconds = match args.first() {
+                Some(Value::Number(n)) if n.is_finite() => *n,
+                _ => return Err(Error::new("HUMANIZE expects seconds, [locale]", None)),
+            };
+            humanize(seconds, args.get(1), "HUMANIZE")
+        }
+        "HUMANIZEDURATION" => {
+            let (from, to) = match (args.first(), args.get(1)) {
+                (Some(Value::DateTime(a)), Some(Value::DateTime(b))) => (*a, *b),
+                _ => return Err(Error::new("HUMANIZEDURATION expects date1, date2, [locale]", None)),
+            };
+            humanize(to.saturating_sub(from) as f64, args.get(2), "HUMANIZEDURATION")
+        }
         _ => Err(Error::new(format!("Unknown datetime function: {}", name), None)),
     }
-}
\ No newline at end of file
+}
+
+/// The `now_override` instant, if the clock is frozen
+fn frozen_now() -> Option<DateTime<Utc>> {
+    crate::context::now_override().and_then(|ts| DateTime::from_timestamp(ts, 0))
+}
+
+/// "2 days 3 hours" style text for a number of seconds; zero units are left out
+/// and fractions of a second dropped. The locale defaults to the `locale` option.
+fn humanize(seconds: f64, locale: Option<&Value>, name: &str) -> Result<Value, Error> {
+    let tag = match locale {
+        Some(Value::String(tag)) => tag.clone(),
+        Some(_) => return Err(Error::new(format!("{} locale must be a string like \"es-MX\"", name), None)),
+        None => crate::context::with_options(|opts| opts.locale.clone()).unwrap_or_else(|| "en".to_string()),
+    };
+    let units = crate::runtime::locale::duration_units(&tag)
+        .ok_or_else(|| Error::new(format!("{} has no unit names for locale '{}'", name, tag), None))?;
+
+    let total = seconds.abs().trunc();
+    let amounts = [
+        (total / 86_400.0).trunc(),
+        (total % 86_400.0 / 3_600.0).trunc(),
+        (total % 3_600.0 / 60.0).trunc(),
+        total % 60.0,
+    ];
+    let parts: Vec<String> = amounts
+        .iter()
+        .zip(units.iter())
+        .filter(|(amount, _)| **amount > 0.0)
+        .map(|(amount, (one, many))| format!("{} {}", amount, if *amount == 1.0 { one } else { many }))
+        .collect();
+    let text = if parts.is_empty() {
+        format!("0 {}", units[3].1)
+    } else {
+        parts.join(" ")
+    };
+    Ok(Value::String(if seconds <= -1.0 { format!("-{}", text) } else { text }))
+}
+
+/// Parse an ISO 8601 / RFC 3339 timestamp into epoch seconds. Accepts
+/// `2024-05-01T10:00:00Z` (any offset), `2024-05-01T10:00:00`, `2024-05-01 10:00`
+/// and plain `2024-05-01`; forms without an offset are taken as UTC.
+pub fn parse_iso_datetime(s: &str) -> Option<i64> {
+    use chrono::NaiveDateTime;
+    let s = s.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(dt.timestamp());
+    }
+    for fmt in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
+            return Some(dt.and_utc().timestamp());
+        }
+    }
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp())
+}
@@ -0,0 +1,130 @@
+//! DTABLE: decision tables stored as data, in the spirit of DMN.
+//!
+//! A table is a JSON object:
+//!
+//! ```json
+//! {"inputs": ["age", "score"], "outputs": ["rate"], "hit_policy": "FIRST",
+//!  "rules": [[">=18", ">700", 0.05], [">=18", "-", 0.08], ["-", "-", null]]}
+//! ```
+//!
+//! Each rule row holds one condition per input followed by one value per output. A
+//! condition is `"-"` or null (any value), a SUMIF-style criterion such as `">=18"`
+//! or `"<>closed"`, a number, text or boolean that must be equal, or an array of
+//! alternatives. Hit policies: `FIRST` (default) gives the first matching row's
+//! output, `ANY` requires all matching rows to agree and `COLLECT` gives an array of
+//! every match. No match gives null (`[]` for `COLLECT`).
+
+use serde_json::Value as Json;
+
+use crate::error::Error;
+use crate::runtime::method_calls::conversion_methods::value_to_json_value;
+use crate::types::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum HitPolicy {
+    First,
+    Any,
+    Collect,
+}
+
+fn object_arg(what: &str, arg: &Value) -> Result<serde_json::Map<String, Json>, Error> {
+    let json = match arg {
+        Value::String(s) => serde_json::from_str(s).map_err(|e| Error::new(format!("DTABLE {} is not valid JSON: {}", what, e), None))?,
+        other => value_to_json_value(other)?,
+    };
+    match json {
+        Json::Object(map) => Ok(map),
+        _ => Err(Error::new(format!("DTABLE {} must be an object", what), None)),
+    }
+}
+
+fn names(table: &serde_json::Map<String, Json>, key: &str) -> Result<Vec<String>, Error> {
+    match table.get(key) {
+        Some(Json::Array(items)) => items
+            .iter()
+            .map(|v| v.as_str().map(str::to_string))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| Error::new(format!("DTABLE {} must be column names", key), None)),
+        _ => Err(Error::new(format!("DTABLE table needs an \"{}\" array of column names", key), None)),
+    }
+}
+
+/// Whether the condition cell `cell` accepts the input `input`
+fn cell_matches(cell: &Json, input: &Value) -> Result<bool, Error> {
+    Ok(match cell {
+        Json::Null => true,
+        Json::String(s) if s == "-" => true,
+        Json::Array(alternatives) => {
+            for alternative in alternatives {
+                if cell_matches(alternative, input)? {
+                    return Ok(true);
+                }
+            }
+            false
+        }
+        Json::Bool(b) => matches!(input, Value::Boolean(i) if i == b),
+        cell => crate::runtime::utils::meets_criteria(input, &crate::json_to_value(cell.clone())?),
+    })
+}
+
+/// DTABLE(inputs, table) -> the output of the decision table for the `inputs` object
+pub fn exec_dtable(args: &[Value]) -> Result<Value, Error> {
+    let [inputs, table] = args else {
+        return Err(Error::new("DTABLE expects inputs object, table", None));
+    };
+    let inputs = object_arg("inputs", inputs)?;
+    let table = object_arg("table", table)?;
+    let input_names = names(&table, "inputs")?;
+    let output_names = names(&table, "outputs")?;
+    if output_names.is_empty() {
+        return Err(Error::new("DTABLE table needs at least one output column", None));
+    }
+    let policy = match table.get("hit_policy").and_then(Json::as_str).map(str::to_ascii_uppercase).as_deref() {
+        None | Some("FIRST") => HitPolicy::First,
+        Some("ANY") => HitPolicy::Any,
+        Some("COLLECT") => HitPolicy::Collect,
+        Some(other) => return Err(Error::new(format!("DTABLE hit_policy must be FIRST, ANY or COLLECT, got '{}'", other), None)),
+    };
+    let Some(Json::Array(rules)) = table.get("rules") else {
+        return Err(Error::new("DTABLE table needs a \"rules\" array", None));
+    };
+    let values = input_names
+        .iter()
+        .map(|name| inputs.get(name).cloned().map_or(Ok(Value::Null), crate::json_to_value))
+        .collect::<Result<Vec<Value>, Error>>()?;
+
+    let width = input_names.len() + output_names.len();
+    let mut matches: Vec<Json> = Vec::new();
+    for (i, rule) in rules.iter().enumerate() {
+        let cells = match rule {
+            Json::Array(cells) if cells.len() == width => cells,
+            _ => return Err(Error::new(format!("DTABLE rule {} must have {} cells, one per input and output", i, width), None)),
+        };
+        let mut hit = true;
+        for (cell, value) in cells.iter().zip(&values) {
+            if !cell_matches(cell, value)? {
+                hit = false;
+                break;
+            }
+        }
+        if !hit {
+            continue;
+        }
+        let outputs = &cells[input_names.len()..];
+        let output = match outputs {
+            [single] => single.clone(),
+            _ => Json::Object(output_names.iter().cloned().zip(outputs.iter().cloned()).collect()),
+        };
+        if policy == HitPolicy::First {
+            return crate::json_to_value(output);
+        }
+        if policy == HitPolicy::Any && matches.first().is_some_and(|first| *first != output) {
+            return Err(Error::new(format!("DTABLE hit policy ANY: rule {} disagrees with an earlier matching rule", i), None));
+        }
+        matches.push(output);
+    }
+    match policy {
+        HitPolicy::Collect => crate::json_to_value(Json::Array(matches)),
+        _ => matches.into_iter().next().map_or(Ok(Value::Null), crate::json_to_value),
+    }
+}
@@ -1,4 +1,4 @@
-use crate::ast::Expr;
+use crate::ast::{Expr, Pattern};
 use crate::error::Error;
 use crate::types::Value;
 use super::core::eval_with_vars;
@@ -20,6 +20,33 @@ pub fn eval_with_assignments_and_context(expr: &Expr, vars: &HashMap<String, Val
     Ok((result, context))
 }
 
+/// The variables a destructuring assignment of `value` sets. Arrays, and JSON
+/// arrays, fill `[:a, :b]` by position; maps and JSON objects fill `{:lat, :lon}`
+/// by field name. Missing elements and fields assign null.
+pub(crate) fn destructure(pattern: &Pattern, value: &Value) -> Result<Vec<(String, Value)>, Error> {
+    let items: Vec<Value> = match (pattern, value) {
+        (Pattern::Array(_), Value::Array(items)) => items.clone(),
+        (Pattern::Array(_), Value::Json(s)) => match serde_json::from_str(s) {
+            Ok(serde_json::Value::Array(items)) => items.into_iter().map(crate::json_to_value).collect::<Result<_, _>>()?,
+            _ => return Err(Error::new("Destructuring [...] expects an array, got a JSON value that is not one", None)),
+        },
+        (Pattern::Object(names), Value::Map(fields)) => {
+            names.iter().map(|name| fields.get(name).cloned().unwrap_or(Value::Null)).collect()
+        }
+        (Pattern::Object(names), Value::Json(s)) => match serde_json::from_str(s) {
+            Ok(serde_json::Value::Object(mut fields)) => names
+                .iter()
+                .map(|name| fields.remove(name).map_or(Ok(Value::Null), crate::json_to_value))
+                .collect::<Result<_, _>>()?,
+            _ => return Err(Error::new("Destructuring {...} expects an object, got a JSON value that is not one", None)),
+        },
+        (Pattern::Array(_), other) => return Err(Error::new(format!("Destructuring [...] expects an array, got {:?}", other), None)),
+        (Pattern::Object(_), other) => return Err(Error::new(format!("Destructuring {{...}} expects an object, got {:?}", other), None)),
+    };
+    let mut items = items.into_iter();
+    Ok(pattern.names().iter().map(|name| (name.clone(), items.next().unwrap_or(Value::Null))).collect())
+}
+
 fn eval_with_assignments_context(expr: &Expr, context: &mut HashMap<String, Value>) -> Result<Value, Error> {
     match expr {
         Expr::Assignment { variable, value } => {
@@ -27,6 +54,11 @@ fn eval_with_assignments_context(expr: &Expr, context: &mut HashMap<String, Valu
             context.insert(variable.clone(), result.clone());
             Ok(result)
         }
+        Expr::Destructure { pattern, value } => {
+            let result = eval_with_assignments_context(value, context)?;
+            context.extend(destructure(pattern, &result)?);
+            Ok(result)
+        }
         Expr::Sequence(exprs) => {
             let mut last_result = Value::Null;
             for expr in exprs {
@@ -1,17 +1,20 @@
-use crate::ast::{BinaryOp, Expr, UnaryOp};
+use crate::ast::{BinaryOp, Expr};
 use crate::error::Error;
 use crate::types::Value;
 use crate::custom::FunctionRegistry;
+use crate::options::Strictness;
 use crate::runtime::{
+    evaluator::Evaluator,
     builtin_functions::exec_builtin,
     method_calls::{exec_method, exec_method_with_custom},
     type_casting::cast_value,
-    utils::{index_array, slice_array}
+    utils::{index_array, slice_array, to_index}
 };
 use super::higher_order;
 
+use indexmap::IndexMap;
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 
 /// Convert a Skillet Value to a serde_json::Value
 fn value_to_json(value: &Value) -> Result<serde_json::Value, Error> {
@@ -34,6 +37,7 @@ fn value_to_json(value: &Value) -> Result<serde_json::Value, Error> {
             serde_json::from_str(s)
                 .map_err(|e| Error::new(format!("Invalid JSON: {}", e), None))
         }
+        Value::Map(_) => crate::runtime::method_calls::conversion_methods::value_to_json_value(value),
     }
 }
 
@@ -45,24 +49,14 @@ pub fn eval(expr: &Expr) -> Result<Value, Error> {
         
         Expr::Unary(op, e) => {
             let v = eval(e)?;
-            match op {
-                UnaryOp::Plus => Ok(Value::Number(v.as_number().ok_or_else(|| Error::new("Unary '+' on non-number", None))?)),
-                UnaryOp::Minus => Ok(Value::Number(-v.as_number().ok_or_else(|| Error::new("Unary '-' on non-number", None))?)),
-                UnaryOp::Not => Ok(Value::Boolean(!v.as_bool().ok_or_else(|| Error::new("Unary '!' on non-boolean", None))?)),
-            }
+            Evaluator::eval_unary_op(*op, v)
         }
         
         Expr::Binary(l, op, r) => eval_binary_op(l, op, r, None),
         
-        Expr::Array(items) => {
-            let mut out = Vec::with_capacity(items.len());
-            for e in items { 
-                out.push(eval(e)?); 
-            }
-            Ok(Value::Array(out))
-        }
+        Expr::Array(items) => eval_array_literal(items, eval),
         
-        Expr::ObjectLiteral(pairs) => eval_object_literal(pairs, None),
+        Expr::ObjectLiteral(pairs) => eval_object_literal(pairs, eval),
         
         Expr::TypeCast { expr, ty } => {
             let v = eval(expr)?;
@@ -72,7 +66,7 @@ pub fn eval(expr: &Expr) -> Result<Value, Error> {
         Expr::Index { target, index } => {
             let recv = eval(target)?;
             let idx_v = eval(index)?;
-            let idx = idx_v.as_number().ok_or_else(|| Error::new("Index must be number", None))? as isize;
+            let idx = to_index(idx_v.as_number().ok_or_else(|| Error::new("Index must be number", None))?, "Index")?;
             match recv {
                 Value::Array(items) => index_array(items, idx),
                 _ => Err(Error::new("Indexing only supported on arrays", None)),
@@ -103,7 +97,8 @@ pub fn eval(expr: &Expr) -> Result<Value, Error> {
         Expr::SafePropertyAccess { .. } => Err(Error::new("Use eval_with_vars for safe property access", None)),
         Expr::SafeMethodCall { .. } => Err(Error::new("Use eval_with_vars for safe method calls", None)),
         Expr::Spread(_) => Err(Error::new("Spread not allowed here", None)),
-        Expr::Assignment { .. } => Err(Error::new("Use eval_with_vars for assignments", None)),
+        Expr::Lambda { .. } => Err(Error::new("Lambda not allowed here; pass it to a function like MAP or FILTER", None)),
+        Expr::Assignment { .. } | Expr::Destructure { .. } => Err(Error::new("Use eval_with_vars for assignments", None)),
         Expr::Sequence(_) => Err(Error::new("Use eval_with_vars for sequences", None)),
     }
 }
@@ -116,19 +111,17 @@ pub fn eval_with_vars(expr: &Expr, vars: &HashMap<String, Value>) -> Result<Valu
         
         Expr::Unary(op, e) => {
             let v = eval_with_vars(e, vars)?;
-            match op {
-                UnaryOp::Plus => Ok(Value::Number(v.as_number().ok_or_else(|| Error::new("Unary '+' on non-number", None))?)),
-                UnaryOp::Minus => Ok(Value::Number(-v.as_number().ok_or_else(|| Error::new("Unary '-' on non-number", None))?)),
-                UnaryOp::Not => Ok(Value::Boolean(!v.as_bool().ok_or_else(|| Error::new("Unary '!' on non-boolean", None))?)),
-            }
+            Evaluator::eval_unary_op(*op, v)
         }
         
         Expr::Binary(l, op, r) => eval_binary_op(l, op, r, Some(vars)),
         
-        Expr::Variable(name) => vars
-            .get(name)
-            .cloned()
-            .ok_or_else(|| Error::new(format!("Missing variable: :{}", name), None)),
+        Expr::Variable(name) if name == crate::constants::ENV => Ok(crate::constants::env_value()),
+        Expr::Variable(name) => match vars.get(name) {
+            Some(v) => Ok(v.clone()),
+            None if crate::context::strictness() == Strictness::Lenient => Ok(Value::Null),
+            None => Err(Error::new(format!("Missing variable: :{}", name), None)),
+        },
         
         Expr::PropertyAccess { target, property } => eval_property_access(target, property, vars, false),
         Expr::SafePropertyAccess { target, property } => eval_property_access(target, property, vars, true),
@@ -141,15 +134,9 @@ pub fn eval_with_vars(expr: &Expr, vars: &HashMap<String, Value>) -> Result<Valu
             exec_method(name, false, &target_value, args, Some(vars))
         }
         
-        Expr::Array(items) => {
-            let mut out = Vec::with_capacity(items.len());
-            for e in items { 
-                out.push(eval_with_vars(e, vars)?); 
-            }
-            Ok(Value::Array(out))
-        }
+        Expr::Array(items) => eval_array_literal(items, |e| eval_with_vars(e, vars)),
         
-        Expr::ObjectLiteral(pairs) => eval_object_literal(pairs, Some(vars)),
+        Expr::ObjectLiteral(pairs) => eval_object_literal(pairs, |e| eval_with_vars(e, vars)),
         
         Expr::TypeCast { expr, ty } => {
             let v = eval_with_vars(expr, vars)?;
@@ -159,7 +146,7 @@ pub fn eval_with_vars(expr: &Expr, vars: &HashMap<String, Value>) -> Result<Valu
         Expr::Index { target, index } => {
             let recv = eval_with_vars(target, vars)?;
             let idx_v = eval_with_vars(index, vars)?;
-            let idx = idx_v.as_number().ok_or_else(|| Error::new("Index must be number", None))? as isize;
+            let idx = to_index(idx_v.as_number().ok_or_else(|| Error::new("Index must be number", None))?, "Index")?;
             match recv {
                 Value::Array(items) => index_array(items, idx),
                 _ => Err(Error::new("Index on non-array", None)),
@@ -185,8 +172,9 @@ pub fn eval_with_vars(expr: &Expr, vars: &HashMap<String, Value>) -> Result<Valu
         }
         
         Expr::Spread(_) => Err(Error::new("Spread not allowed here", None)),
+        Expr::Lambda { .. } => Err(Error::new("Lambda not allowed here; pass it to a function like MAP or FILTER", None)),
         
-        Expr::Assignment { variable: _, value } => {
+        Expr::Assignment { variable: _, value } | Expr::Destructure { value, .. } => {
             let result = eval_with_vars(value, vars)?;
             // For assignments, we need a mutable variables map, but the current API doesn't support that
             // This is a limitation - assignments need to be handled at a higher level
@@ -207,7 +195,7 @@ pub fn eval_with_vars(expr: &Expr, vars: &HashMap<String, Value>) -> Result<Valu
 pub fn eval_with_vars_and_custom(
     expr: &Expr,
     vars: &HashMap<String, Value>,
-    custom_registry: &Arc<RwLock<FunctionRegistry>>
+    custom_registry: &Arc<FunctionRegistry>
 ) -> Result<Value, Error> {
     match expr {
         Expr::Number(n) => Ok(Value::Number(*n)),
@@ -216,18 +204,17 @@ pub fn eval_with_vars_and_custom(
         
         Expr::Unary(op, e) => {
             let v = eval_with_vars_and_custom(e, vars, custom_registry)?;
-            match op {
-                UnaryOp::Plus => Ok(Value::Number(v.as_number().ok_or_else(|| Error::new("Unary '+' on non-number", None))?)),
-                UnaryOp::Minus => Ok(Value::Number(-v.as_number().ok_or_else(|| Error::new("Unary '-' on non-number", None))?)),
-                UnaryOp::Not => Ok(Value::Boolean(!v.as_bool().ok_or_else(|| Error::new("Unary '!' on non-boolean", None))?)),
-            }
+            Evaluator::eval_unary_op(*op, v)
         }
         
         Expr::Binary(l, op, r) => eval_binary_op_with_custom(l, op, r, vars, custom_registry),
         
-        Expr::Variable(name) => {
-            vars.get(name).cloned().ok_or_else(|| Error::new(format!("Undefined variable: {}", name), None))
-        }
+        Expr::Variable(name) if name == crate::constants::ENV => Ok(crate::constants::env_value()),
+        Expr::Variable(name) => match vars.get(name) {
+            Some(v) => Ok(v.clone()),
+            None if crate::context::strictness() == Strictness::Lenient => Ok(Value::Null),
+            None => Err(Error::new(format!("Undefined variable: {}", name), None)),
+        },
         
         Expr::PropertyAccess { target, property } => eval_property_access_with_custom(target, property, vars, custom_registry, false),
         Expr::SafePropertyAccess { target, property } => eval_property_access_with_custom(target, property, vars, custom_registry, true),
@@ -240,15 +227,9 @@ pub fn eval_with_vars_and_custom(
             exec_method_with_custom(name, false, &target_value, args, Some(vars), custom_registry)
         }
         
-        Expr::Array(exprs) => {
-            let mut items = Vec::new();
-            for e in exprs {
-                items.push(eval_with_vars_and_custom(e, vars, custom_registry)?);
-            }
-            Ok(Value::Array(items))
-        }
+        Expr::Array(exprs) => eval_array_literal(exprs, |e| eval_with_vars_and_custom(e, vars, custom_registry)),
         
-        Expr::ObjectLiteral(pairs) => eval_object_literal_with_custom(pairs, vars, custom_registry),
+        Expr::ObjectLiteral(pairs) => eval_object_literal(pairs, |e| eval_with_vars_and_custom(e, vars, custom_registry)),
         
         Expr::Index { target, index } => eval_index_with_custom(target, index, vars, custom_registry),
         Expr::Slice { target, start, end } => eval_slice_with_custom(target, start, end, vars, custom_registry),
@@ -266,8 +247,9 @@ pub fn eval_with_vars_and_custom(
         }
         
         Expr::Spread(_) => Err(Error::new("Spread not allowed here", None)),
+        Expr::Lambda { .. } => Err(Error::new("Lambda not allowed here; pass it to a function like MAP or FILTER", None)),
         
-        Expr::Assignment { variable: _, value } => {
+        Expr::Assignment { variable: _, value } | Expr::Destructure { value, .. } => {
             let result = eval_with_vars_and_custom(value, vars, custom_registry)?;
             // For assignments, we need a mutable variables map, but the current API doesn't support that
             // This is a limitation - assignments need to be handled at a higher level
@@ -285,85 +267,13 @@ pub fn eval_with_vars_and_custom(
     }
 }
 
-// Helper functions for binary operations
+// Helper functions for binary operations; the operator semantics are shared with the unified evaluator
 fn eval_binary_op(l: &Expr, op: &BinaryOp, r: &Expr, vars: Option<&HashMap<String, Value>>) -> Result<Value, Error> {
     let (a, b) = match vars {
         Some(v) => (eval_with_vars(l, v)?, eval_with_vars(r, v)?),
         None => (eval(l)?, eval(r)?)
     };
-    
-    match op {
-        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod | BinaryOp::Pow => {
-            let an = a.as_number().ok_or_else(|| Error::new("Arithmetic op on non-number", None))?;
-            let bn = b.as_number().ok_or_else(|| Error::new("Arithmetic op on non-number", None))?;
-            match op {
-                BinaryOp::Add => Ok(Value::Number(an + bn)),
-                BinaryOp::Sub => Ok(Value::Number(an - bn)),
-                BinaryOp::Mul => Ok(Value::Number(an * bn)),
-                BinaryOp::Div => Ok(Value::Number(an / bn)),
-                BinaryOp::Mod => Ok(Value::Number(an % bn)),
-                BinaryOp::Pow => Ok(Value::Number(an.powf(bn))),
-                _ => unreachable!(),
-            }
-        }
-        BinaryOp::Gt | BinaryOp::Lt | BinaryOp::Ge | BinaryOp::Le | BinaryOp::Eq | BinaryOp::Ne => {
-            if vars.is_some() {
-                // Enhanced comparison for eval_with_vars
-                match (a, b) {
-                    (Value::Number(x), Value::Number(y)) => Ok(Value::Boolean(match op {
-                        BinaryOp::Eq => x == y,
-                        BinaryOp::Ne => x != y,
-                        BinaryOp::Lt => x < y,
-                        BinaryOp::Le => x <= y,
-                        BinaryOp::Gt => x > y,
-                        BinaryOp::Ge => x >= y,
-                        _ => unreachable!()
-                    })),
-                    (Value::String(x), Value::String(y)) => Ok(Value::Boolean(match op {
-                        BinaryOp::Eq => x == y,
-                        BinaryOp::Ne => x != y,
-                        BinaryOp::Lt => x < y,
-                        BinaryOp::Le => x <= y,
-                        BinaryOp::Gt => x > y,
-                        BinaryOp::Ge => x >= y,
-                        _ => unreachable!()
-                    })),
-                    (Value::Boolean(x), Value::Boolean(y)) => Ok(Value::Boolean(match op {
-                        BinaryOp::Eq => x == y,
-                        BinaryOp::Ne => x != y,
-                        _ => false
-                    })),
-                    _ => match op {
-                        BinaryOp::Eq => Ok(Value::Boolean(false)),
-                        BinaryOp::Ne => Ok(Value::Boolean(true)),
-                        _ => Err(Error::new("Comparison of incompatible types", None))
-                    }
-                }
-            } else {
-                // Simple numeric comparison for eval
-                let an = a.as_number().ok_or_else(|| Error::new("Comparison on non-number", None))?;
-                let bn = b.as_number().ok_or_else(|| Error::new("Comparison on non-number", None))?;
-                Ok(Value::Boolean(match op {
-                    BinaryOp::Gt => an > bn,
-                    BinaryOp::Lt => an < bn,
-                    BinaryOp::Ge => an >= bn,
-                    BinaryOp::Le => an <= bn,
-                    BinaryOp::Eq => an == bn,
-                    BinaryOp::Ne => an != bn,
-                    _ => unreachable!(),
-                }))
-            }
-        }
-        BinaryOp::And | BinaryOp::Or => {
-            let ab = a.as_bool().ok_or_else(|| Error::new("Logical op on non-boolean", None))?;
-            let bb = b.as_bool().ok_or_else(|| Error::new("Logical op on non-boolean", None))?;
-            Ok(Value::Boolean(match op { 
-                BinaryOp::And => ab && bb, 
-                BinaryOp::Or => ab || bb, 
-                _ => unreachable!() 
-            }))
-        }
-    }
+    Evaluator::eval_binary_op(*op, a, b)
 }
 
 fn eval_binary_op_with_custom(
@@ -371,68 +281,11 @@ fn eval_binary_op_with_custom(
     op: &BinaryOp, 
     r: &Expr, 
     vars: &HashMap<String, Value>, 
-    custom_registry: &Arc<RwLock<FunctionRegistry>>
+    custom_registry: &Arc<FunctionRegistry>
 ) -> Result<Value, Error> {
     let a = eval_with_vars_and_custom(l, vars, custom_registry)?;
     let b = eval_with_vars_and_custom(r, vars, custom_registry)?;
-    
-    match op {
-        BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod | BinaryOp::Pow => {
-            let an = a.as_number().ok_or_else(|| Error::new("Arithmetic op on non-number", None))?;
-            let bn = b.as_number().ok_or_else(|| Error::new("Arithmetic op on non-number", None))?;
-            match op {
-                BinaryOp::Add => Ok(Value::Number(an + bn)),
-                BinaryOp::Sub => Ok(Value::Number(an - bn)),
-                BinaryOp::Mul => Ok(Value::Number(an * bn)),
-                BinaryOp::Div => Ok(Value::Number(an / bn)),
-                BinaryOp::Mod => Ok(Value::Number(an % bn)),
-                BinaryOp::Pow => Ok(Value::Number(an.powf(bn))),
-                _ => unreachable!(),
-            }
-        }
-        BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
-            let result = match (a, b) {
-                (Value::Number(x), Value::Number(y)) => match op {
-                    BinaryOp::Eq => x == y,
-                    BinaryOp::Ne => x != y,
-                    BinaryOp::Lt => x < y,
-                    BinaryOp::Le => x <= y,
-                    BinaryOp::Gt => x > y,
-                    BinaryOp::Ge => x >= y,
-                    _ => unreachable!(),
-                },
-                (Value::String(x), Value::String(y)) => match op {
-                    BinaryOp::Eq => x == y,
-                    BinaryOp::Ne => x != y,
-                    BinaryOp::Lt => x < y,
-                    BinaryOp::Le => x <= y,
-                    BinaryOp::Gt => x > y,
-                    BinaryOp::Ge => x >= y,
-                    _ => unreachable!(),
-                },
-                (Value::Boolean(x), Value::Boolean(y)) => match op {
-                    BinaryOp::Eq => x == y,
-                    BinaryOp::Ne => x != y,
-                    _ => false,
-                },
-                _ => match op {
-                    BinaryOp::Eq => false,
-                    BinaryOp::Ne => true,
-                    _ => return Err(Error::new("Comparison of incompatible types", None)),
-                }
-            };
-            Ok(Value::Boolean(result))
-        }
-        BinaryOp::And | BinaryOp::Or => {
-            let ab = a.as_bool().ok_or_else(|| Error::new("Logical op on non-boolean", None))?;
-            let bb = b.as_bool().ok_or_else(|| Error::new("Logical op on non-boolean", None))?;
-            Ok(Value::Boolean(match op { 
-                BinaryOp::And => ab && bb, 
-                BinaryOp::Or => ab || bb, 
-                _ => unreachable!() 
-            }))
-        }
-    }
+    Evaluator::eval_binary_op(*op, a, b)
 }
 
 // Helper functions for property access
@@ -450,6 +303,11 @@ fn eval_property_access(target: &Expr, property: &str, vars: &HashMap<String, Va
                 Err(Error::new(format!("Property '{}' not found in JSON object", property), None))
             }
         }
+        Value::Map(mut map) => match map.swap_remove(property) {
+            Some(value) => Ok(value),
+            None if safe => Ok(Value::Null),
+            None => Err(Error::new(format!("Property '{}' not found in object", property), None)),
+        },
         Value::Null if safe => Ok(Value::Null), // Safe navigation on null returns null
         _ if safe => Err(Error::new("Property access requires JSON object", None)),
         _ => Err(Error::new("Property access requires JSON object", None))
@@ -460,7 +318,7 @@ fn eval_property_access_with_custom(
     target: &Expr, 
     property: &str, 
     vars: &HashMap<String, Value>, 
-    custom_registry: &Arc<RwLock<FunctionRegistry>>, 
+    custom_registry: &Arc<FunctionRegistry>, 
     safe: bool
 ) -> Result<Value, Error> {
     let target_value = eval_with_vars_and_custom(target, vars, custom_registry)?;
@@ -476,43 +334,67 @@ fn eval_property_access_with_custom(
                 Err(Error::new(format!("Property '{}' not found in JSON object", property), None))
             }
         }
+        Value::Map(mut map) => match map.swap_remove(property) {
+            Some(value) => Ok(value),
+            None if safe => Ok(Value::Null),
+            None => Err(Error::new(format!("Property '{}' not found in object", property), None)),
+        },
         Value::Null if safe => Ok(Value::Null), // Safe navigation on null returns null
         _ => Err(Error::new(format!("Property access only supported on JSON objects, got {:?}", target_value), None)),
     }
 }
 
 // Helper functions for object literals
-fn eval_object_literal(pairs: &[(String, Expr)], vars: Option<&HashMap<String, Value>>) -> Result<Value, Error> {
-    let mut json_map = serde_json::Map::new();
-    for (key, value_expr) in pairs {
-        let value = match vars {
-            Some(v) => eval_with_vars(value_expr, v)?,
-            None => eval(value_expr)?
-        };
-        let json_value = value_to_json(&value)?;
-        json_map.insert(key.clone(), json_value);
+/// An array literal; `...arr` items splice in the elements of `arr`
+pub(crate) fn eval_array_literal<F>(items: &[Expr], mut eval: F) -> Result<Value, Error>
+where
+    F: FnMut(&Expr) -> Result<Value, Error>,
+{
+    crate::context::check_array_len(items.len())?;
+    let mut out = Vec::with_capacity(items.len());
+    for e in items {
+        match e {
+            Expr::Spread(inner) => match eval(inner)? {
+                Value::Array(values) => out.extend(values),
+                _ => return Err(Error::new("Spread expects array", None)),
+            },
+            e => out.push(eval(e)?),
+        }
     }
-    let json_obj = serde_json::Value::Object(json_map);
-    let json_str = serde_json::to_string(&json_obj)
-        .map_err(|e| Error::new(format!("Failed to serialize object: {}", e), None))?;
-    Ok(Value::Json(json_str))
+    crate::context::check_array_len(out.len())?;
+    let array = Value::Array(out);
+    crate::context::charge_memory(&array)?;
+    Ok(array)
 }
 
-fn eval_object_literal_with_custom(
-    pairs: &[(String, Expr)], 
-    vars: &HashMap<String, Value>, 
-    custom_registry: &Arc<RwLock<FunctionRegistry>>
-) -> Result<Value, Error> {
-    let mut json_map = serde_json::Map::new();
+/// An object literal; `...obj` entries copy the fields of `obj`, and a later field
+/// replaces an earlier one with the same key
+pub(crate) fn eval_object_literal<F>(pairs: &[(String, Expr)], mut eval: F) -> Result<Value, Error>
+where
+    F: FnMut(&Expr) -> Result<Value, Error>,
+{
+    let mut map = IndexMap::with_capacity(pairs.len());
     for (key, value_expr) in pairs {
-        let value = eval_with_vars_and_custom(value_expr, vars, custom_registry)?;
-        let json_value = value_to_json(&value)?;
-        json_map.insert(key.clone(), json_value);
+        if let Expr::Spread(inner) = value_expr {
+            match eval(inner)? {
+                Value::Map(fields) => map.extend(fields),
+                json @ Value::Json(_) => match value_to_json(&json)? {
+                    serde_json::Value::Object(fields) => {
+                        for (k, v) in fields {
+                            map.insert(k, crate::json_to_value(v)?);
+                        }
+                    }
+                    _ => return Err(Error::new("Object spread expects an object", None)),
+                },
+                _ => return Err(Error::new("Object spread expects an object", None)),
+            }
+            continue;
+        }
+        map.insert(key.clone(), eval(value_expr)?);
     }
-    let json_obj = serde_json::Value::Object(json_map);
-    let json_str = serde_json::to_string(&json_obj)
-        .map_err(|e| Error::new(format!("Failed to serialize object: {}", e), None))?;
-    Ok(Value::Json(json_str))
+    let object = Value::Map(map);
+    crate::context::charge_memory(&object)?;
+    Ok(object)
 }
 
 // Helper functions for indexing and slicing with custom
@@ -520,20 +402,14 @@ fn eval_index_with_custom(
     target: &Expr, 
     index: &Expr, 
     vars: &HashMap<String, Value>, 
-    custom_registry: &Arc<RwLock<FunctionRegistry>>
+    custom_registry: &Arc<FunctionRegistry>
 ) -> Result<Value, Error> {
     let arr = eval_with_vars_and_custom(target, vars, custom_registry)?;
     let idx = eval_with_vars_and_custom(index, vars, custom_registry)?;
     match arr {
         Value::Array(items) => {
-            let i = idx.as_number().ok_or_else(|| Error::new("Index must be number", None))? as i32;
-            let len = items.len() as i32;
-            let real_i = if i < 0 { len + i } else { i };
-            if real_i < 0 || real_i >= len {
-                Err(Error::new("Index out of bounds", None))
-            } else {
-                Ok(items[real_i as usize].clone())
-            }
+            let i = to_index(idx.as_number().ok_or_else(|| Error::new("Index must be number", None))?, "Index")?;
+            index_array(items, i)
         }
         _ => Err(Error::new("Index on non-array", None)),
     }
@@ -541,28 +417,23 @@ fn eval_index_with_custom(
 
 fn eval_slice_with_custom(
     target: &Expr, 
-    start: &Option<std::rc::Rc<Expr>>, 
-    end: &Option<std::rc::Rc<Expr>>, 
+    start: &Option<std::sync::Arc<Expr>>, 
+    end: &Option<std::sync::Arc<Expr>>, 
     vars: &HashMap<String, Value>, 
-    custom_registry: &Arc<RwLock<FunctionRegistry>>
+    custom_registry: &Arc<FunctionRegistry>
 ) -> Result<Value, Error> {
     let arr = eval_with_vars_and_custom(target, vars, custom_registry)?;
     match arr {
         Value::Array(items) => {
-            let len = items.len() as i32;
-            let start_i = if let Some(s) = start {
-                let si = eval_with_vars_and_custom(s, vars, custom_registry)?.as_number().ok_or_else(|| Error::new("Slice start must be number", None))? as i32;
-                if si < 0 { (len + si).max(0) } else { si.min(len) }
-            } else { 0 };
-            let end_i = if let Some(e) = end {
-                let ei = eval_with_vars_and_custom(e, vars, custom_registry)?.as_number().ok_or_else(|| Error::new("Slice end must be number", None))? as i32;
-                if ei < 0 { (len + ei).max(0) } else { ei.min(len) }
-            } else { len };
-            if start_i <= end_i {
-                Ok(Value::Array(items[(start_i as usize)..(end_i as usize)].to_vec()))
-            } else {
-                Ok(Value::Array(vec![]))
-            }
+            let bound = |e: &Expr, what: &str| -> Result<Value, Error> {
+                let n = eval_with_vars_and_custom(e, vars, custom_registry)?
+                    .as_number()
+                    .ok_or_else(|| Error::new(format!("{} must be number", what), None))?;
+                Ok(Value::Number(n))
+            };
+            let start_val = start.as_ref().map(|s| bound(s, "Slice start")).transpose()?;
+            let end_val = end.as_ref().map(|e| bound(e, "Slice end")).transpose()?;
+            slice_array(items, start_val, end_val)
         }
         _ => Err(Error::new("Slice on non-array", None)),
     }
@@ -594,7 +465,8 @@ fn eval_function_call(name: &str, args: &[Expr], vars: Option<&HashMap<String, V
         }
         
         // Higher-order functions
-        "FILTER" | "FIND" | "MAP" | "REDUCE" | "SUMIF" | "AVGIF" | "COUNTIF" => {
+        "FILTER" | "FIND" | "MAP" | "REDUCE" | "SUMIF" | "AVGIF" | "COUNTIF" | "ITERATE" | "GOALSEEK" | "SOLVE" | "JOINON" | "DISTINCTBY" | "WHY" | "FIRSTMATCH" | "LET" | "SWITCH" | "IFERROR" | "TRY" => {
+            crate::context::check_builtin_policy(name)?;
             match vars {
                 Some(v) => higher_order::eval_higher_order_function(name, args, v),
                 None => Err(Error::new(format!("{} requires variable context", name), None))
@@ -635,7 +507,7 @@ fn eval_function_call_with_custom(
     name: &str, 
     args: &[Expr], 
     vars: &HashMap<String, Value>, 
-    custom_registry: &Arc<RwLock<FunctionRegistry>>
+    custom_registry: &Arc<FunctionRegistry>
 ) -> Result<Value, Error> {
     match name {
         "__TERNARY__" => {
@@ -654,29 +526,33 @@ fn eval_function_call_with_custom(
         
         _ => {
             // Check custom functions first
-            if let Ok(registry) = custom_registry.read() {
-                if registry.has_function(name) {
-                    let mut ev_args = Vec::new();
-                    for a in args {
-                        match a {
-                            Expr::Spread(inner) => {
-                                let v = eval_with_vars_and_custom(inner, vars, custom_registry)?;
-                                if let Value::Array(items) = v { 
-                                    ev_args.extend(items); 
-                                } else { 
-                                    return Err(Error::new("Spread expects array", None)); 
-                                }
+            if custom_registry.has_function(name) {
+                let mut ev_args = Vec::new();
+                for a in args {
+                    match a {
+                        Expr::Spread(inner) => {
+                            let v = eval_with_vars_and_custom(inner, vars, custom_registry)?;
+                            if let Value::Array(items) = v { 
+                                ev_args.extend(items); 
+                            } else { 
+                                return Err(Error::new("Spread expects array", None)); 
                             }
-                            _ => ev_args.push(eval_with_vars_and_custom(a, vars, custom_registry)?),
                         }
+                        _ => ev_args.push(eval_with_vars_and_custom(a, vars, custom_registry)?),
                     }
-                    return registry.execute(name, ev_args);
                 }
+                crate::context::check_custom(name)?;
+                let ev_args = crate::runtime::utils::maps_as_json(&ev_args)?.unwrap_or(ev_args);
+                let call = crate::context::CallContext::with_variables(vars).with_registry(custom_registry);
+                let result = custom_registry.execute_with_context(name, ev_args, &call)?;
+                crate::context::charge_memory(&result)?;
+                return Ok(result);
             }
             
             // Higher-order functions with custom support
             match name {
-                "FILTER" | "FIND" | "MAP" | "REDUCE" | "SUMIF" | "AVGIF" | "COUNTIF" => {
+                "FILTER" | "FIND" | "MAP" | "REDUCE" | "SUMIF" | "AVGIF" | "COUNTIF" | "ITERATE" | "GOALSEEK" | "SOLVE" | "JOINON" | "DISTINCTBY" | "WHY" | "FIRSTMATCH" | "LET" | "SWITCH" | "IFERROR" | "TRY" => {
+                    crate::context::check_builtin_policy(name)?;
                     higher_order::eval_higher_order_function_with_custom(name, args, vars, custom_registry)
                 }
                 _ => {
@@ -5,7 +5,7 @@ use crate::custom::FunctionRegistry;
 use super::core::{eval_with_vars, eval_with_vars_and_custom};
 
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 
 pub fn eval_higher_order_function(
     name: &str, 
@@ -20,6 +20,16 @@ pub fn eval_higher_order_function(
         "SUMIF" => eval_sumif(args, vars),
         "AVGIF" => eval_avgif(args, vars),
         "COUNTIF" => eval_countif(args, vars),
+        "ITERATE" => iterate(args, vars, eval_with_vars),
+        "GOALSEEK" | "SOLVE" => goal_seek(args, vars, eval_with_vars),
+        "JOINON" => join_on(args, vars, eval_with_vars),
+        "DISTINCTBY" => distinct_by(args, vars, eval_with_vars),
+        "WHY" => super::why::why(args, vars, eval_with_vars),
+        "FIRSTMATCH" => first_match(args, vars, eval_with_vars),
+        "LET" => let_in(args, vars, eval_with_vars),
+        "SWITCH" => switch(args, |e| eval_with_vars(e, vars)),
+        "IFERROR" => if_error(args, |e| eval_with_vars(e, vars)),
+        "TRY" => try_eval(args, |e| eval_with_vars(e, vars)),
         _ => Err(Error::new(format!("Unknown higher-order function: {}", name), None)),
     }
 }
@@ -28,7 +38,7 @@ pub fn eval_higher_order_function_with_custom(
     name: &str, 
     args: &[Expr], 
     vars: &HashMap<String, Value>,
-    custom_registry: &Arc<RwLock<FunctionRegistry>>
+    custom_registry: &Arc<FunctionRegistry>
 ) -> Result<Value, Error> {
     match name {
         "FILTER" => eval_filter_with_custom(args, vars, custom_registry),
@@ -38,6 +48,16 @@ pub fn eval_higher_order_function_with_custom(
         "SUMIF" => eval_sumif_with_custom(args, vars, custom_registry),
         "AVGIF" => eval_avgif_with_custom(args, vars, custom_registry),
         "COUNTIF" => eval_countif_with_custom(args, vars, custom_registry),
+        "ITERATE" => iterate(args, vars, |e, env| eval_with_vars_and_custom(e, env, custom_registry)),
+        "GOALSEEK" | "SOLVE" => goal_seek(args, vars, |e, env| eval_with_vars_and_custom(e, env, custom_registry)),
+        "JOINON" => join_on(args, vars, |e, env| eval_with_vars_and_custom(e, env, custom_registry)),
+        "DISTINCTBY" => distinct_by(args, vars, |e, env| eval_with_vars_and_custom(e, env, custom_registry)),
+        "WHY" => super::why::why(args, vars, |e, env| eval_with_vars_and_custom(e, env, custom_registry)),
+        "FIRSTMATCH" => first_match(args, vars, |e, env| eval_with_vars_and_custom(e, env, custom_registry)),
+        "LET" => let_in(args, vars, |e, env| eval_with_vars_and_custom(e, env, custom_registry)),
+        "SWITCH" => switch(args, |e| eval_with_vars_and_custom(e, vars, custom_registry)),
+        "IFERROR" => if_error(args, |e| eval_with_vars_and_custom(e, vars, custom_registry)),
+        "TRY" => try_eval(args, |e| eval_with_vars_and_custom(e, vars, custom_registry)),
         _ => Err(Error::new(format!("Unknown higher-order function: {}", name), None)),
     }
 }
@@ -51,17 +71,18 @@ fn eval_filter(args: &[Expr], vars: &HashMap<String, Value>) -> Result<Value, Er
     let arr_v = eval_with_vars(&args[0], vars)?;
     let lambda = &args[1];
     let param_name = get_param_name(args.get(2), vars)?;
+    check_lambda("FILTER", lambda, 2)?;
 
     match arr_v {
         Value::Array(items) => {
             let mut out = Vec::with_capacity(items.len());
             let mut env = vars.clone();
-            for it in items {
-                env.insert(param_name.clone(), it.clone());
+            for (i, it) in items.into_iter().enumerate() {
+                let body = bind_lambda(lambda, &[&param_name], [it.clone(), Value::Number(i as f64)], &mut env);
                 if let Expr::Spread(_) = lambda {
                     return Err(Error::new("Invalid lambda", None));
                 }
-                if let Value::Boolean(true) = eval_with_vars(lambda, &env)? {
+                if let Value::Boolean(true) = eval_with_vars(body, &env)? {
                     out.push(it);
                 }
             }
@@ -74,7 +95,7 @@ fn eval_filter(args: &[Expr], vars: &HashMap<String, Value>) -> Result<Value, Er
 fn eval_filter_with_custom(
     args: &[Expr],
     vars: &HashMap<String, Value>,
-    custom_registry: &Arc<RwLock<FunctionRegistry>>
+    custom_registry: &Arc<FunctionRegistry>
 ) -> Result<Value, Error> {
     if args.len() < 2 {
         return Err(Error::new("FILTER expects (array, expr)", None));
@@ -82,14 +103,15 @@ fn eval_filter_with_custom(
 
     let arr_v = eval_with_vars_and_custom(&args[0], vars, custom_registry)?;
     let lambda = &args[1];
+    check_lambda("FILTER", lambda, 2)?;
 
     match arr_v {
         Value::Array(items) => {
             let mut out = Vec::with_capacity(items.len());
             let mut env = vars.clone();
-            for it in items {
-                env.insert("x".into(), it.clone());
-                if let Value::Boolean(true) = eval_with_vars_and_custom(lambda, &env, custom_registry)? {
+            for (i, it) in items.into_iter().enumerate() {
+                let body = bind_lambda(lambda, &["x"], [it.clone(), Value::Number(i as f64)], &mut env);
+                if let Value::Boolean(true) = eval_with_vars_and_custom(body, &env, custom_registry)? {
                     out.push(it);
                 }
             }
@@ -108,16 +130,17 @@ fn eval_find(args: &[Expr], vars: &HashMap<String, Value>) -> Result<Value, Erro
     let arr_v = eval_with_vars(&args[0], vars)?;
     let lambda = &args[1];
     let param_name = get_param_name(args.get(2), vars)?;
+    check_lambda("FIND", lambda, 2)?;
 
     match arr_v {
         Value::Array(items) => {
             let mut env = vars.clone();
-            for it in items {
-                env.insert(param_name.clone(), it.clone());
+            for (i, it) in items.into_iter().enumerate() {
+                let body = bind_lambda(lambda, &[&param_name], [it.clone(), Value::Number(i as f64)], &mut env);
                 if let Expr::Spread(_) = lambda {
                     return Err(Error::new("Invalid lambda", None));
                 }
-                if let Value::Boolean(true) = eval_with_vars(lambda, &env)? {
+                if let Value::Boolean(true) = eval_with_vars(body, &env)? {
                     return Ok(it);
                 }
             }
@@ -130,7 +153,7 @@ fn eval_find(args: &[Expr], vars: &HashMap<String, Value>) -> Result<Value, Erro
 fn eval_find_with_custom(
     args: &[Expr],
     vars: &HashMap<String, Value>,
-    custom_registry: &Arc<RwLock<FunctionRegistry>>
+    custom_registry: &Arc<FunctionRegistry>
 ) -> Result<Value, Error> {
     if args.len() < 2 {
         return Err(Error::new("FIND expects (array, expr)", None));
@@ -138,13 +161,14 @@ fn eval_find_with_custom(
 
     let arr_v = eval_with_vars_and_custom(&args[0], vars, custom_registry)?;
     let lambda = &args[1];
+    check_lambda("FIND", lambda, 2)?;
 
     match arr_v {
         Value::Array(items) => {
             let mut env = vars.clone();
-            for it in items {
-                env.insert("x".into(), it.clone());
-                if let Value::Boolean(true) = eval_with_vars_and_custom(lambda, &env, custom_registry)? {
+            for (i, it) in items.into_iter().enumerate() {
+                let body = bind_lambda(lambda, &["x"], [it.clone(), Value::Number(i as f64)], &mut env);
+                if let Value::Boolean(true) = eval_with_vars_and_custom(body, &env, custom_registry)? {
                     return Ok(it);
                 }
             }
@@ -163,17 +187,18 @@ fn eval_map(args: &[Expr], vars: &HashMap<String, Value>) -> Result<Value, Error
     let arr_v = eval_with_vars(&args[0], vars)?;
     let lambda = &args[1];
     let param_name = get_param_name(args.get(2), vars)?;
+    check_lambda("MAP", lambda, 2)?;
 
     match arr_v {
         Value::Array(items) => {
             let mut out = Vec::with_capacity(items.len());
             let mut env = vars.clone();
-            for it in items {
-                env.insert(param_name.clone(), it.clone());
+            for (i, it) in items.into_iter().enumerate() {
+                let body = bind_lambda(lambda, &[&param_name], [it, Value::Number(i as f64)], &mut env);
                 if let Expr::Spread(_) = lambda {
                     return Err(Error::new("Invalid lambda", None));
                 }
-                out.push(eval_with_vars(lambda, &env)?);
+                out.push(eval_with_vars(body, &env)?);
             }
             Ok(Value::Array(out))
         }
@@ -184,7 +209,7 @@ fn eval_map(args: &[Expr], vars: &HashMap<String, Value>) -> Result<Value, Error
 fn eval_map_with_custom(
     args: &[Expr],
     vars: &HashMap<String, Value>,
-    custom_registry: &Arc<RwLock<FunctionRegistry>>
+    custom_registry: &Arc<FunctionRegistry>
 ) -> Result<Value, Error> {
     if args.len() < 2 {
         return Err(Error::new("MAP expects (array, expr)", None));
@@ -192,14 +217,15 @@ fn eval_map_with_custom(
 
     let arr_v = eval_with_vars_and_custom(&args[0], vars, custom_registry)?;
     let lambda = &args[1];
+    check_lambda("MAP", lambda, 2)?;
 
     match arr_v {
         Value::Array(items) => {
             let mut out = Vec::with_capacity(items.len());
             let mut env = vars.clone();
-            for it in items {
-                env.insert("x".into(), it);
-                out.push(eval_with_vars_and_custom(lambda, &env, custom_registry)?);
+            for (i, it) in items.into_iter().enumerate() {
+                let body = bind_lambda(lambda, &["x"], [it, Value::Number(i as f64)], &mut env);
+                out.push(eval_with_vars_and_custom(body, &env, custom_registry)?);
             }
             Ok(Value::Array(out))
         }
@@ -219,17 +245,17 @@ fn eval_reduce(args: &[Expr], vars: &HashMap<String, Value>) -> Result<Value, Er
 
     let val_param = get_param_name(args.get(3), vars).unwrap_or_else(|_| "x".into());
     let acc_param = get_param_name(args.get(4), vars).unwrap_or_else(|_| "acc".into());
+    check_lambda("REDUCE", lambda, 3)?;
 
     match arr_v {
         Value::Array(items) => {
             let mut env = vars.clone();
-            for it in items {
-                env.insert(val_param.clone(), it.clone());
-                env.insert(acc_param.clone(), acc);
+            for (i, it) in items.into_iter().enumerate() {
+                let body = bind_lambda(lambda, &[&acc_param, &val_param], [acc, it, Value::Number(i as f64)], &mut env);
                 if let Expr::Spread(_) = lambda {
                     return Err(Error::new("Invalid lambda", None));
                 }
-                acc = eval_with_vars(lambda, &env)?;
+                acc = eval_with_vars(body, &env)?;
             }
             Ok(acc)
         }
@@ -240,7 +266,7 @@ fn eval_reduce(args: &[Expr], vars: &HashMap<String, Value>) -> Result<Value, Er
 fn eval_reduce_with_custom(
     args: &[Expr],
     vars: &HashMap<String, Value>,
-    custom_registry: &Arc<RwLock<FunctionRegistry>>
+    custom_registry: &Arc<FunctionRegistry>
 ) -> Result<Value, Error> {
     if args.len() < 3 {
         return Err(Error::new("REDUCE expects (array, expr, initial)", None));
@@ -249,14 +275,14 @@ fn eval_reduce_with_custom(
     let arr_v = eval_with_vars_and_custom(&args[0], vars, custom_registry)?;
     let lambda = &args[1];
     let mut acc = eval_with_vars_and_custom(&args[2], vars, custom_registry)?;
+    check_lambda("REDUCE", lambda, 3)?;
 
     match arr_v {
         Value::Array(items) => {
             let mut env = vars.clone();
-            for it in items {
-                env.insert("acc".into(), acc);
-                env.insert("x".into(), it);
-                acc = eval_with_vars_and_custom(lambda, &env, custom_registry)?;
+            for (i, it) in items.into_iter().enumerate() {
+                let body = bind_lambda(lambda, &["acc", "x"], [acc, it, Value::Number(i as f64)], &mut env);
+                acc = eval_with_vars_and_custom(body, &env, custom_registry)?;
             }
             Ok(acc)
         }
@@ -272,14 +298,15 @@ fn eval_sumif(args: &[Expr], vars: &HashMap<String, Value>) -> Result<Value, Err
 
     let arr_v = eval_with_vars(&args[0], vars)?;
     let lambda = &args[1];
+    check_lambda("SUMIF", lambda, 2)?;
 
     match arr_v {
         Value::Array(items) => {
             let mut acc = 0.0;
             let mut env = vars.clone();
-            for it in items {
-                env.insert("x".into(), it.clone());
-                if let Value::Boolean(true) = eval_with_vars(lambda, &env)? {
+            for (i, it) in items.into_iter().enumerate() {
+                let body = bind_lambda(lambda, &["x"], [it.clone(), Value::Number(i as f64)], &mut env);
+                if let Value::Boolean(true) = eval_with_vars(body, &env)? {
                     match it {
                         Value::Number(n) => acc += n,
                         Value::Currency(n) => acc += n,
@@ -296,7 +323,7 @@ fn eval_sumif(args: &[Expr], vars: &HashMap<String, Value>) -> Result<Value, Err
 fn eval_sumif_with_custom(
     args: &[Expr],
     vars: &HashMap<String, Value>,
-    custom_registry: &Arc<RwLock<FunctionRegistry>>
+    custom_registry: &Arc<FunctionRegistry>
 ) -> Result<Value, Error> {
     if args.len() != 2 {
         return Err(Error::new("SUMIF expects (array, expr)", None));
@@ -304,14 +331,15 @@ fn eval_sumif_with_custom(
 
     let arr_v = eval_with_vars_and_custom(&args[0], vars, custom_registry)?;
     let lambda = &args[1];
+    check_lambda("SUMIF", lambda, 2)?;
 
     match arr_v {
         Value::Array(items) => {
             let mut acc = 0.0;
             let mut env = vars.clone();
-            for it in items {
-                env.insert("x".into(), it.clone());
-                if let Value::Boolean(true) = eval_with_vars_and_custom(lambda, &env, custom_registry)? {
+            for (i, it) in items.into_iter().enumerate() {
+                let body = bind_lambda(lambda, &["x"], [it.clone(), Value::Number(i as f64)], &mut env);
+                if let Value::Boolean(true) = eval_with_vars_and_custom(body, &env, custom_registry)? {
                     match it {
                         Value::Number(n) | Value::Currency(n) => acc += n,
                         _ => {}
@@ -332,15 +360,16 @@ fn eval_avgif(args: &[Expr], vars: &HashMap<String, Value>) -> Result<Value, Err
 
     let arr_v = eval_with_vars(&args[0], vars)?;
     let lambda = &args[1];
+    check_lambda("AVGIF", lambda, 2)?;
 
     match arr_v {
         Value::Array(items) => {
             let mut acc = 0.0;
             let mut count = 0usize;
             let mut env = vars.clone();
-            for it in items {
-                env.insert("x".into(), it.clone());
-                if let Value::Boolean(true) = eval_with_vars(lambda, &env)? {
+            for (i, it) in items.into_iter().enumerate() {
+                let body = bind_lambda(lambda, &["x"], [it.clone(), Value::Number(i as f64)], &mut env);
+                if let Value::Boolean(true) = eval_with_vars(body, &env)? {
                     match it {
                         Value::Number(n) | Value::Currency(n) => {
                             acc += n;
@@ -359,7 +388,7 @@ fn eval_avgif(args: &[Expr], vars: &HashMap<String, Value>) -> Result<Value, Err
 fn eval_avgif_with_custom(
     args: &[Expr],
     vars: &HashMap<String, Value>,
-    custom_registry: &Arc<RwLock<FunctionRegistry>>
+    custom_registry: &Arc<FunctionRegistry>
 ) -> Result<Value, Error> {
     if args.len() != 2 {
         return Err(Error::new("AVGIF expects (array, expr)", None));
@@ -367,15 +396,16 @@ fn eval_avgif_with_custom(
 
     let arr_v = eval_with_vars_and_custom(&args[0], vars, custom_registry)?;
     let lambda = &args[1];
+    check_lambda("AVGIF", lambda, 2)?;
 
     match arr_v {
         Value::Array(items) => {
             let mut acc = 0.0;
             let mut count = 0usize;
             let mut env = vars.clone();
-            for it in items {
-                env.insert("x".into(), it.clone());
-                if let Value::Boolean(true) = eval_with_vars_and_custom(lambda, &env, custom_registry)? {
+            for (i, it) in items.into_iter().enumerate() {
+                let body = bind_lambda(lambda, &["x"], [it.clone(), Value::Number(i as f64)], &mut env);
+                if let Value::Boolean(true) = eval_with_vars_and_custom(body, &env, custom_registry)? {
                     match it {
                         Value::Number(n) | Value::Currency(n) => {
                             acc += n;
@@ -399,14 +429,15 @@ fn eval_countif(args: &[Expr], vars: &HashMap<String, Value>) -> Result<Value, E
 
     let arr_v = eval_with_vars(&args[0], vars)?;
     let lambda = &args[1];
+    check_lambda("COUNTIF", lambda, 2)?;
 
     match arr_v {
         Value::Array(items) => {
             let mut count = 0usize;
             let mut env = vars.clone();
-            for it in items {
-                env.insert("x".into(), it.clone());
-                if let Value::Boolean(true) = eval_with_vars(lambda, &env)? {
+            for (i, it) in items.into_iter().enumerate() {
+                let body = bind_lambda(lambda, &["x"], [it.clone(), Value::Number(i as f64)], &mut env);
+                if let Value::Boolean(true) = eval_with_vars(body, &env)? {
                     count += 1;
                 }
             }
@@ -419,7 +450,7 @@ fn eval_countif(args: &[Expr], vars: &HashMap<String, Value>) -> Result<Value, E
 fn eval_countif_with_custom(
     args: &[Expr],
     vars: &HashMap<String, Value>,
-    custom_registry: &Arc<RwLock<FunctionRegistry>>
+    custom_registry: &Arc<FunctionRegistry>
 ) -> Result<Value, Error> {
     if args.len() != 2 {
         return Err(Error::new("COUNTIF expects (array, expr)", None));
@@ -427,14 +458,15 @@ fn eval_countif_with_custom(
 
     let arr_v = eval_with_vars_and_custom(&args[0], vars, custom_registry)?;
     let lambda = &args[1];
+    check_lambda("COUNTIF", lambda, 2)?;
 
     match arr_v {
         Value::Array(items) => {
             let mut count = 0usize;
             let mut env = vars.clone();
-            for it in items {
-                env.insert("x".into(), it.clone());
-                if let Value::Boolean(true) = eval_with_vars_and_custom(lambda, &env, custom_registry)? {
+            for (i, it) in items.into_iter().enumerate() {
+                let body = bind_lambda(lambda, &["x"], [it.clone(), Value::Number(i as f64)], &mut env);
+                if let Value::Boolean(true) = eval_with_vars_and_custom(body, &env, custom_registry)? {
                     count += 1;
                 }
             }
@@ -444,6 +476,350 @@ fn eval_countif_with_custom(
     }
 }
 
+/// Upper bound on ITERATE's max_iterations argument
+pub const MAX_ITERATIONS: usize = 1_000_000;
+
+/// ITERATE(initial, step, max_iterations, [until]): apply `step` to `:x` (the
+/// current value, `:i` the 0-based step number) up to `max_iterations` times.
+/// When `until` is given it is checked after each step with `:x` the new value
+/// and `:prev` the previous one; iteration stops once it is true, and running
+/// out of iterations first is an error. Shared by every evaluator, which pass
+/// their own way of evaluating a sub-expression.
+pub(crate) fn iterate<F>(args: &[Expr], vars: &HashMap<String, Value>, mut eval: F) -> Result<Value, Error>
+where
+    F: FnMut(&Expr, &HashMap<String, Value>) -> Result<Value, Error>,
+{
+    if args.len() < 3 || args.len() > 4 {
+        return Err(Error::new("ITERATE expects (initial, step, max_iterations, [until])", None));
+    }
+    let mut x = eval(&args[0], vars)?;
+    let max_iter = match eval(&args[2], vars)? {
+        Value::Number(n) if n >= 0.0 && n.fract() == 0.0 && n <= MAX_ITERATIONS as f64 => n as usize,
+        _ => {
+            return Err(Error::new(
+                format!("ITERATE max_iterations must be a whole number from 0 to {}", MAX_ITERATIONS),
+                None,
+            ))
+        }
+    };
+    let (step, until) = (&args[1], args.get(3));
+    check_lambda("ITERATE", step, 2)?;
+    if let Some(until) = until {
+        check_lambda("ITERATE", until, 2)?;
+    }
+
+    let mut env = vars.clone();
+    for i in 0..max_iter {
+        crate::context::check_deadline()?;
+        let body = bind_lambda(step, &["x", "i"], [x.clone(), Value::Number(i as f64)], &mut env);
+        let next = eval(body, &env)?;
+        let prev = std::mem::replace(&mut x, next);
+        if let Some(until) = until {
+            let body = bind_lambda(until, &["x", "prev"], [x.clone(), prev], &mut env);
+            match eval(body, &env)? {
+                Value::Boolean(true) => return Ok(x),
+                Value::Boolean(false) => {}
+                _ => return Err(Error::new("ITERATE until expression must return a boolean", None)),
+            }
+        }
+    }
+    if until.is_some() {
+        return Err(Error::new(format!("ITERATE did not converge within {} iterations", max_iter), None));
+    }
+    Ok(x)
+}
+
+/// GOALSEEK(expr, target, guess, [tolerance], [max_iterations]): find `:x` such that
+/// `expr` evaluates to `target`. Newton steps with a numeric derivative are tried
+/// first; if they stall, the search brackets a sign change around `guess` and
+/// bisects it. Succeeds once `|expr - target| <= tolerance` (default 1e-9).
+pub(crate) fn goal_seek<F>(args: &[Expr], vars: &HashMap<String, Value>, mut eval: F) -> Result<Value, Error>
+where
+    F: FnMut(&Expr, &HashMap<String, Value>) -> Result<Value, Error>,
+{
+    if args.len() < 3 || args.len() > 5 {
+        return Err(Error::new("GOALSEEK expects (expr, target, guess, [tolerance], [max_iterations])", None));
+    }
+    let mut number = |e: &Expr, what: &str| match eval(e, vars)? {
+        Value::Number(n) | Value::Currency(n) if n.is_finite() => Ok(n),
+        _ => Err(Error::new(format!("GOALSEEK {} must be a finite number", what), None)),
+    };
+    let target = number(&args[1], "target")?;
+    let guess = number(&args[2], "guess")?;
+    let tolerance = match args.get(3) {
+        Some(e) => number(e, "tolerance")?,
+        None => 1e-9,
+    };
+    let max_iter = match args.get(4) {
+        Some(e) => number(e, "max_iterations")?,
+        None => 100.0,
+    };
+    if tolerance <= 0.0 {
+        return Err(Error::new("GOALSEEK tolerance must be positive", None));
+    }
+    if max_iter < 1.0 || max_iter.fract() != 0.0 || max_iter > MAX_ITERATIONS as f64 {
+        return Err(Error::new(
+            format!("GOALSEEK max_iterations must be a whole number from 1 to {}", MAX_ITERATIONS),
+            None,
+        ));
+    }
+    let max_iter = max_iter as usize;
+
+    let lambda = &args[0];
+    check_lambda("GOALSEEK", lambda, 1)?;
+    let mut env = vars.clone();
+    // Distance from the target at `x`; NaN where the expression is undefined
+    let mut gap = |x: f64| -> Result<f64, Error> {
+        crate::context::check_deadline()?;
+        let body = bind_lambda(lambda, &["x"], [Value::Number(x)], &mut env);
+        match eval(body, &env)? {
+            Value::Number(y) | Value::Currency(y) => Ok(y - target),
+            other => Err(Error::new(format!("GOALSEEK expression must return a number, got {:?}", other), None)),
+        }
+    };
+
+    let mut x = guess;
+    let mut g = gap(x)?;
+    for _ in 0..max_iter {
+        if g.abs() <= tolerance {
+            return Ok(Value::Number(x));
+        }
+        let h = 1e-7 * x.abs().max(1.0);
+        let slope = (gap(x + h)? - g) / h;
+        if !slope.is_finite() || slope == 0.0 {
+            break;
+        }
+        let next = x - g / slope;
+        let next_gap = gap(next)?;
+        if !next.is_finite() || !next_gap.is_finite() || next_gap.abs() >= g.abs() {
+            break;
+        }
+        x = next;
+        g = next_gap;
+    }
+    if g.abs() <= tolerance {
+        return Ok(Value::Number(x));
+    }
+
+    // Newton stalled: widen a window around the guess until the sign changes, then bisect
+    let (mut lo, mut hi) = (guess, guess);
+    let mut step = 0.1 * guess.abs().max(1.0);
+    let mut bracket = None;
+    let g0 = gap(guess)?;
+    for _ in 0..64 {
+        lo -= step;
+        hi += step;
+        step *= 2.0;
+        let (g_lo, g_hi) = (gap(lo)?, gap(hi)?);
+        if g_lo.is_finite() && g_lo.signum() != g0.signum() {
+            bracket = Some((lo, guess, g_lo));
+            break;
+        }
+        if g_hi.is_finite() && g_hi.signum() != g0.signum() {
+            bracket = Some((guess, hi, g0));
+            break;
+        }
+    }
+    if let Some((mut a, mut b, mut g_a)) = bracket {
+        for _ in 0..max_iter.max(200) {
+            let mid = (a + b) / 2.0;
+            let g_mid = gap(mid)?;
+            if g_mid.abs() <= tolerance {
+                return Ok(Value::Number(mid));
+            }
+            if g_mid.signum() == g_a.signum() {
+                a = mid;
+                g_a = g_mid;
+            } else {
+                b = mid;
+            }
+        }
+    }
+    Err(Error::new(
+        format!("GOALSEEK could not reach target {} within tolerance {}", crate::runtime::utils::format_number(target), tolerance),
+        None,
+    ))
+}
+
+/// JOINON(left, right, left_key, right_key, [type]): join two arrays of objects on
+/// keys computed by the lambdas (`:x` is the item). Matching rows are merged into
+/// one object, the right row's fields winning on name clashes. `type` is `"inner"`
+/// (default) or `"left"`, which keeps unmatched left rows as they are. Null keys
+/// never match. The right side is hashed once, so the cost is linear in the inputs
+/// plus the number of matches.
+pub(crate) fn join_on<F>(args: &[Expr], vars: &HashMap<String, Value>, mut eval: F) -> Result<Value, Error>
+where
+    F: FnMut(&Expr, &HashMap<String, Value>) -> Result<Value, Error>,
+{
+    if args.len() < 4 || args.len() > 5 {
+        return Err(Error::new("JOINON expects (left, right, left_key, right_key, [type])", None));
+    }
+    let mut rows = |e: &Expr, side: &str| match eval(e, vars)? {
+        Value::Array(items) => items
+            .iter()
+            .map(|item| match crate::runtime::method_calls::conversion_methods::value_to_json_value(item)? {
+                serde_json::Value::Object(map) => Ok((item.clone(), map)),
+                _ => Err(Error::new(format!("JOINON {} rows must be objects", side), None)),
+            })
+            .collect::<Result<Vec<_>, Error>>(),
+        _ => Err(Error::new(format!("JOINON {} must be an array of objects", side), None)),
+    };
+    let left = rows(&args[0], "left")?;
+    let right = rows(&args[1], "right")?;
+    let keep_unmatched = match args.get(4).map(|e| eval(e, vars)).transpose()? {
+        None => false,
+        Some(Value::String(t)) if t.eq_ignore_ascii_case("inner") => false,
+        Some(Value::String(t)) if t.eq_ignore_ascii_case("left") => true,
+        Some(other) => return Err(Error::new(format!("JOINON type must be \"inner\" or \"left\", got {:?}", other), None)),
+    };
+
+    check_lambda("JOINON", &args[2], 1)?;
+    check_lambda("JOINON", &args[3], 1)?;
+    let mut env = vars.clone();
+    let mut key_of = |lambda: &Expr, item: &Value| -> Result<Option<String>, Error> {
+        crate::context::check_deadline()?;
+        let body = bind_lambda(lambda, &["x"], [item.clone()], &mut env);
+        match eval(body, &env)? {
+            Value::Null => Ok(None),
+            key => {
+                let mut text = String::new();
+                crate::runtime::random::canonical_text(&key, &mut text)?;
+                Ok(Some(text))
+            }
+        }
+    };
+    let mut index: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, (item, _)) in right.iter().enumerate() {
+        if let Some(key) = key_of(&args[3], item)? {
+            index.entry(key).or_default().push(i);
+        }
+    }
+
+    let mut out = Vec::new();
+    for (item, fields) in &left {
+        let matches = match key_of(&args[2], item)? {
+            Some(key) => index.get(&key).map(Vec::as_slice).unwrap_or_default(),
+            None => &[],
+        };
+        if matches.is_empty() && keep_unmatched {
+            out.push(Value::Json(serde_json::Value::Object(fields.clone()).to_string()));
+        }
+        for &i in matches {
+            let mut merged = fields.clone();
+            merged.extend(right[i].1.clone());
+            out.push(Value::Json(serde_json::Value::Object(merged).to_string()));
+            crate::context::check_array_len(out.len())?;
+        }
+    }
+    Ok(Value::Array(out))
+}
+
+/// DISTINCTBY(array, key, [param]): the first item for each distinct value of the
+/// `key` lambda, in the original order; the function form of `.unique_by(key)`
+pub(crate) fn distinct_by<F>(args: &[Expr], vars: &HashMap<String, Value>, mut eval: F) -> Result<Value, Error>
+where
+    F: FnMut(&Expr, &HashMap<String, Value>) -> Result<Value, Error>,
+{
+    if args.len() < 2 || args.len() > 3 {
+        return Err(Error::new("DISTINCTBY expects (array, expr, [param])", None));
+    }
+    let items = match eval(&args[0], vars)? {
+        Value::Array(items) => items,
+        _ => return Err(Error::new("DISTINCTBY first arg must be array", None)),
+    };
+    let param_name = match args.get(2).map(|e| eval(e, vars)).transpose()? {
+        Some(Value::String(s)) => s,
+        _ => "x".to_string(),
+    };
+    check_lambda("DISTINCTBY", &args[1], 1)?;
+    let mut env = vars.clone();
+    let unique = crate::runtime::method_calls::lambda_methods::distinct_by_key(&items, |item| {
+        let body = bind_lambda(&args[1], &[&param_name], [item.clone()], &mut env);
+        eval(body, &env)
+    })?;
+    Ok(Value::Array(unique))
+}
+
+/// Whether a rule's evaluated `when` accepts `value`: a boolean decides by itself
+/// (TRUE makes a default row), text and numbers are SUMIF-style criteria such as
+/// `">=100"`, null never matches and anything else must equal `value`
+fn rule_matches(when: &Value, value: &Value) -> bool {
+    match when {
+        Value::Boolean(b) => *b,
+        Value::Null => false,
+        Value::String(_) | Value::Number(_) => crate::runtime::utils::meets_criteria(value, when),
+        other => crate::runtime::utils::values_equal(other, value),
+    }
+}
+
+/// FIRSTMATCH(rules, value, [with_index]): the `then` of the first rule whose `when`
+/// accepts `value`, or null. Rules are `{'when': ..., 'then': ...}` objects. Written
+/// inline, `when` may be a lambda over `:x` (`{'when': :x > 100, 'then': 'gold'}`) and
+/// only the chosen `then` is evaluated; rules passed as data use criteria instead.
+/// With `with_index` TRUE the result is `{"index", "then"}`, the index 0-based and
+/// null when nothing matched.
+pub(crate) fn first_match<F>(args: &[Expr], vars: &HashMap<String, Value>, mut eval: F) -> Result<Value, Error>
+where
+    F: FnMut(&Expr, &HashMap<String, Value>) -> Result<Value, Error>,
+{
+    if args.len() < 2 || args.len() > 3 {
+        return Err(Error::new("FIRSTMATCH expects (rules, value, [with_index])", None));
+    }
+    let value = eval(&args[1], vars)?;
+    let with_index = match args.get(2).map(|e| eval(e, vars)).transpose()? {
+        None => false,
+        Some(Value::Boolean(b)) => b,
+        Some(_) => return Err(Error::new("FIRSTMATCH with_index must be a boolean", None)),
+    };
+    let missing = |i: usize, key: &str| Error::new(format!("FIRSTMATCH rule {} has no '{}'", i, key), None);
+
+    let mut found: Option<(usize, Value)> = None;
+    if let Expr::Array(rules) = &args[0] {
+        // Inline rules: evaluate `when` lazily with :x bound to the value
+        let mut env = vars.clone();
+        env.insert("x".into(), value.clone());
+        for (i, rule) in rules.iter().enumerate() {
+            let Expr::ObjectLiteral(fields) = rule else {
+                return Err(Error::new(format!("FIRSTMATCH rule {} must be an object like {{'when': ..., 'then': ...}}", i), None));
+            };
+            let field = |key: &str| fields.iter().find(|(k, _)| k == key).map(|(_, e)| e).ok_or_else(|| missing(i, key));
+            let (when, then) = (field("when")?, field("then")?);
+            check_lambda("FIRSTMATCH", when, 1)?;
+            let when = bind_lambda(when, &["x"], [value.clone()], &mut env);
+            if rule_matches(&eval(when, &env)?, &value) {
+                found = Some((i, eval(then, &env)?));
+                break;
+            }
+        }
+    } else {
+        let rules = match eval(&args[0], vars)? {
+            Value::Array(rules) => rules,
+            _ => return Err(Error::new("FIRSTMATCH rules must be an array of {when, then} objects", None)),
+        };
+        for (i, rule) in rules.iter().enumerate() {
+            let mut fields = match crate::runtime::method_calls::conversion_methods::value_to_json_value(rule)? {
+                serde_json::Value::Object(map) => map,
+                _ => return Err(Error::new(format!("FIRSTMATCH rule {} must be an object like {{'when': ..., 'then': ...}}", i), None)),
+            };
+            let when = crate::json_to_value(fields.remove("when").ok_or_else(|| missing(i, "when"))?)?;
+            let then = fields.remove("then").ok_or_else(|| missing(i, "then"))?;
+            if rule_matches(&when, &value) {
+                found = Some((i, crate::json_to_value(then)?));
+                break;
+            }
+        }
+    }
+
+    if !with_index {
+        return Ok(found.map_or(Value::Null, |(_, then)| then));
+    }
+    let (index, then) = match &found {
+        Some((i, then)) => (serde_json::json!(i), crate::runtime::method_calls::conversion_methods::value_to_json_value(then)?),
+        None => (serde_json::Value::Null, serde_json::Value::Null),
+    };
+    Ok(Value::Json(serde_json::json!({ "index": index, "then": then }).to_string()))
+}
+
 // Helper function to extract parameter name
 fn get_param_name(arg: Option<&Expr>, vars: &HashMap<String, Value>) -> Result<String, Error> {
     match arg {
@@ -456,4 +832,139 @@ fn get_param_name(arg: Option<&Expr>, vars: &HashMap<String, Value>) -> Result<S
         }
         None => Ok("x".into())
     }
-}
\ No newline at end of file
+}
+
+/// Reject an arrow lambda with more parameters than `name` passes to it
+pub(crate) fn check_lambda(name: &str, lambda: &Expr, max: usize) -> Result<(), Error> {
+    match lambda {
+        Expr::Lambda { params, .. } if params.len() > max => Err(Error::new(
+            format!("{} passes at most {} argument(s) to its lambda, got {} parameters", name, max, params.len()),
+            None,
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Bind the arguments of one lambda call in `env` and return the expression to
+/// evaluate. An arrow lambda `(a, b) -> body` binds its own parameters to `values` in
+/// order; a bare expression binds the `implicit` names (`:x`, `:acc`, ...) instead.
+/// Values left without a name are not bound.
+pub(crate) fn bind_lambda<'a, const N: usize>(
+    lambda: &'a Expr,
+    implicit: &[&str],
+    values: [Value; N],
+    env: &mut HashMap<String, Value>,
+) -> &'a Expr {
+    match lambda {
+        Expr::Lambda { params, body } => {
+            for (name, value) in params.iter().zip(values) {
+                env.insert(name.clone(), value);
+            }
+            body
+        }
+        other => {
+            for (name, value) in implicit.iter().zip(values) {
+                env.insert(name.to_string(), value);
+            }
+            other
+        }
+    }
+}
+/// LET(:name1, value1, [:name2, value2, ...], body): `body` evaluated with each name
+/// bound to its value. Values are evaluated once, in order, and may use the names
+/// bound before them. The bindings are local: they shadow outer variables of the same
+/// name only inside the LET and never reach the surrounding assignments.
+pub(crate) fn let_in<F>(args: &[Expr], vars: &HashMap<String, Value>, mut eval: F) -> Result<Value, Error>
+where
+    F: FnMut(&Expr, &HashMap<String, Value>) -> Result<Value, Error>,
+{
+    if args.len() < 3 || args.len().is_multiple_of(2) {
+        return Err(Error::new("LET expects (:name1, value1, [:name2, value2, ...], body)", None));
+    }
+    let (body, bindings) = args.split_last().expect("LET has at least three arguments");
+    let mut env = vars.clone();
+    for pair in bindings.chunks(2) {
+        let name = match &pair[0] {
+            Expr::Variable(name) => name.clone(),
+            Expr::StringLit(name) if !name.is_empty() => name.trim_start_matches(':').to_string(),
+            other => return Err(Error::new(format!("LET names must be variables like :total, got {}", other), None)),
+        };
+        let value = eval(&pair[1], &env)?;
+        env.insert(name, value);
+    }
+    eval(body, &env)
+}
+
+/// SWITCH(value, case1, result1, [case2, result2, ...], [default]): the result of the
+/// first case equal to `value` (compared like `==`), else `default`, else null. Cases
+/// are evaluated in order up to the match, and only the chosen result is evaluated.
+pub(crate) fn switch<F>(args: &[Expr], mut eval: F) -> Result<Value, Error>
+where
+    F: FnMut(&Expr) -> Result<Value, Error>,
+{
+    if args.len() < 3 {
+        return Err(Error::new("SWITCH expects (value, case1, result1, [case2, result2, ...], [default])", None));
+    }
+    let value = eval(&args[0])?;
+    let mut rest = args[1..].chunks_exact(2);
+    for pair in rest.by_ref() {
+        let case = eval(&pair[0])?;
+        if crate::runtime::evaluator::Evaluator::eval_binary_op(crate::ast::BinaryOp::Eq, value.clone(), case)? == Value::Boolean(true) {
+            return eval(&pair[1]);
+        }
+    }
+    match rest.remainder() {
+        [default] => eval(default),
+        _ => Ok(Value::Null),
+    }
+}
+
+/// Evaluate `expr`, passing on errors from the evaluation's limits (timeout, memory,
+/// category budgets), which IFERROR and TRY must not hide
+fn catch_errors<F>(expr: &Expr, eval: &mut F) -> Result<Result<Value, Error>, Error>
+where
+    F: FnMut(&Expr) -> Result<Value, Error>,
+{
+    match eval(expr) {
+        Err(e) if crate::context::limit_reached() => Err(e),
+        result => Ok(result),
+    }
+}
+
+/// IFERROR(expr, fallback): `expr`'s value, or `fallback` evaluated when `expr` fails
+pub(crate) fn if_error<F>(args: &[Expr], mut eval: F) -> Result<Value, Error>
+where
+    F: FnMut(&Expr) -> Result<Value, Error>,
+{
+    let [expr, fallback] = args else {
+        return Err(Error::new("IFERROR expects (expr, fallback)", None));
+    };
+    match catch_errors(expr, &mut eval)? {
+        Ok(value) => Ok(value),
+        Err(_) => eval(fallback),
+    }
+}
+
+/// TRY(expr): `{"ok": true, "value": ...}`, or `{"ok": false, "value": null,
+/// "error": message}` when `expr` fails
+pub(crate) fn try_eval<F>(args: &[Expr], mut eval: F) -> Result<Value, Error>
+where
+    F: FnMut(&Expr) -> Result<Value, Error>,
+{
+    let [expr] = args else {
+        return Err(Error::new("TRY expects one expression", None));
+    };
+    let mut out = indexmap::IndexMap::new();
+    match catch_errors(expr, &mut eval)? {
+        Ok(value) => {
+            out.insert("ok".to_string(), Value::Boolean(true));
+            out.insert("value".to_string(), value);
+        }
+        Err(e) => {
+            out.insert("ok".to_string(), Value::Boolean(false));
+            out.insert("value".to_string(), Value::Null);
+            out.insert("error".to_string(), Value::String(e.message));
+        }
+    }
+    Ok(Value::Map(out))
+}
@@ -0,0 +1,98 @@
+//! WHY: evaluate a rule and report which of its clauses held, so a rules UI can
+//! show the reasons behind a decision.
+//!
+//! The rule is split along `&&`, `||` and `!` and the `AND`, `OR` and `NOT`
+//! functions; every other sub-expression is a leaf clause. Each node of the result
+//! reads `{"expr": <source>, "value": <value>}`, and the logical ones add
+//! `"op": "and" | "or" | "not"` and `"clauses"`. Chains like `a && b && c` form
+//! one node with three clauses. All clauses are evaluated, also those that could not
+//! change the outcome, since a declined customer wants to see every failed check.
+
+use crate::ast::{BinaryOp, Expr, UnaryOp};
+use crate::error::Error;
+use crate::runtime::evaluator::Evaluator;
+use crate::runtime::method_calls::conversion_methods::value_to_json_value;
+use crate::types::Value;
+
+use std::collections::HashMap;
+
+/// The operands of a chain of `op`, left to right
+fn chain<'a>(expr: &'a Expr, op: BinaryOp, out: &mut Vec<&'a Expr>) {
+    match expr {
+        Expr::Binary(a, o, b) if *o == op => {
+            chain(a, op, out);
+            chain(b, op, out);
+        }
+        other => out.push(other),
+    }
+}
+
+fn trace<F>(expr: &Expr, vars: &HashMap<String, Value>, eval: &mut F) -> Result<(Value, serde_json::Value), Error>
+where
+    F: FnMut(&Expr, &HashMap<String, Value>) -> Result<Value, Error>,
+{
+    crate::context::check_deadline()?;
+    let mut clauses = Vec::new();
+    let op = match expr {
+        Expr::Binary(_, op @ (BinaryOp::And | BinaryOp::Or), _) => {
+            chain(expr, *op, &mut clauses);
+            if *op == BinaryOp::And { "and" } else { "or" }
+        }
+        Expr::Unary(UnaryOp::Not, inner) => {
+            clauses.push(inner.as_ref());
+            "not"
+        }
+        Expr::FunctionCall { name, args }
+            if matches!(name.as_str(), "AND" | "OR" | "NOT") && !args.iter().any(|a| matches!(a, Expr::Spread(_))) =>
+        {
+            clauses.extend(args);
+            if name == "AND" { "and" } else if name == "OR" { "or" } else { "not" }
+        }
+        _ => {
+            let value = eval(expr, vars)?;
+            let node = serde_json::json!({ "expr": expr.to_string(), "value": value_to_json_value(&value)? });
+            return Ok((value, node));
+        }
+    };
+
+    let mut values = Vec::with_capacity(clauses.len());
+    let mut nodes = Vec::with_capacity(clauses.len());
+    for clause in clauses {
+        let (value, node) = trace(clause, vars, eval)?;
+        values.push(value);
+        nodes.push(node);
+    }
+    // Combine the clause values with the same rules as the expression itself
+    let value = match expr {
+        Expr::FunctionCall { name, .. } => crate::runtime::logical::exec_logical(name, &values)?,
+        Expr::Unary(op, _) => Evaluator::eval_unary_op(*op, values.remove(0))?,
+        Expr::Binary(_, op, _) => {
+            let mut values = values.into_iter();
+            let first = values.next().unwrap_or(Value::Null);
+            values.try_fold(first, |acc, v| Evaluator::eval_binary_op(*op, acc, v))?
+        }
+        _ => unreachable!("only logical expressions have clauses"),
+    };
+    let node = serde_json::json!({
+        "expr": expr.to_string(),
+        "value": value_to_json_value(&value)?,
+        "op": op,
+        "clauses": nodes,
+    });
+    Ok((value, node))
+}
+
+/// WHY(rule) -> the clause tree of a boolean `rule` with each clause's value
+pub(crate) fn why<F>(args: &[Expr], vars: &HashMap<String, Value>, mut eval: F) -> Result<Value, Error>
+where
+    F: FnMut(&Expr, &HashMap<String, Value>) -> Result<Value, Error>,
+{
+    let [rule] = args else {
+        return Err(Error::new("WHY expects one boolean expression", None));
+    };
+    let (value, node) = trace(rule, vars, &mut eval)?;
+    if !matches!(value, Value::Boolean(_)) {
+        return Err(Error::new(format!("WHY expects a boolean expression, got {:?}", value), None));
+    }
+    Ok(Value::Json(node.to_string()))
+}
@@ -2,21 +2,28 @@ use crate::ast::{BinaryOp, Expr, UnaryOp};
 use crate::error::Error;
 use crate::types::Value;
 use crate::custom::FunctionRegistry;
+use crate::options::Strictness;
 use crate::runtime::{
+    evaluation::higher_order::{bind_lambda, check_lambda},
     function_dispatch::exec_builtin_fast,
     method_calls::{exec_method, exec_method_with_custom},
     type_casting::cast_value,
-    utils::{index_array, slice_array}
+    utils::{index_array, meets_criteria, slice_array, to_index}
 };
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 use std::borrow::Cow;
 
 /// Evaluation context that provides access to variables and custom functions
 pub trait EvaluationContext {
     fn get_variable(&self, name: &str) -> Option<&Value>;
-    fn get_custom_registry(&self) -> Option<&Arc<RwLock<FunctionRegistry>>>;
+    fn get_custom_registry(&self) -> Option<&Arc<FunctionRegistry>>;
     fn clone_variables(&self) -> HashMap<String, Value>;
+
+    /// The variables as a map, for contexts that keep them in one
+    fn variables(&self) -> Option<&HashMap<String, Value>> {
+        None
+    }
 }
 
 /// Empty context for basic evaluation without variables
@@ -27,7 +34,7 @@ impl EvaluationContext for EmptyContext {
         None
     }
     
-    fn get_custom_registry(&self) -> Option<&Arc<RwLock<FunctionRegistry>>> {
+    fn get_custom_registry(&self) -> Option<&Arc<FunctionRegistry>> {
         None
     }
     
@@ -39,7 +46,7 @@ impl EvaluationContext for EmptyContext {
 /// Variable context with optional custom function support
 pub struct VariableContext<'a> {
     variables: Cow<'a, HashMap<String, Value>>,
-    custom_registry: Option<&'a Arc<RwLock<FunctionRegistry>>>,
+    custom_registry: Option<&'a Arc<FunctionRegistry>>,
 }
 
 impl<'a> VariableContext<'a> {
@@ -50,7 +57,7 @@ impl<'a> VariableContext<'a> {
         }
     }
     
-    pub fn with_custom(vars: &'a HashMap<String, Value>, registry: &'a Arc<RwLock<FunctionRegistry>>) -> Self {
+    pub fn with_custom(vars: &'a HashMap<String, Value>, registry: &'a Arc<FunctionRegistry>) -> Self {
         Self {
             variables: Cow::Borrowed(vars),
             custom_registry: Some(registry),
@@ -80,13 +87,17 @@ impl<'a> EvaluationContext for VariableContext<'a> {
         self.variables.get(name)
     }
     
-    fn get_custom_registry(&self) -> Option<&Arc<RwLock<FunctionRegistry>>> {
+    fn get_custom_registry(&self) -> Option<&Arc<FunctionRegistry>> {
         self.custom_registry
     }
     
     fn clone_variables(&self) -> HashMap<String, Value> {
         self.variables.as_ref().clone()
     }
+
+    fn variables(&self) -> Option<&HashMap<String, Value>> {
+        Some(self.variables.as_ref())
+    }
 }
 
 /// Unified evaluator that handles all expression types efficiently
@@ -95,6 +106,10 @@ pub struct Evaluator;
 impl Evaluator {
     /// Evaluate expression with any context type
     pub fn eval<C: EvaluationContext>(expr: &Expr, context: &C) -> Result<Value, Error> {
+        crate::runtime::observer::observe(expr, context, || Self::eval_node(expr, context))
+    }
+
+    fn eval_node<C: EvaluationContext>(expr: &Expr, context: &C) -> Result<Value, Error> {
         match expr {
             Expr::Number(n) => Ok(Value::Number(*n)),
             Expr::StringLit(s) => Ok(Value::String(s.clone())),
@@ -111,11 +126,12 @@ impl Evaluator {
                 Self::eval_binary_op(*op, a, b)
             }
             
-            Expr::Variable(name) => {
-                context.get_variable(name)
-                    .cloned()
-                    .ok_or_else(|| Error::new(format!("Missing variable: :{}", name), None))
-            }
+            Expr::Variable(name) if name == crate::constants::ENV => Ok(crate::constants::env_value()),
+            Expr::Variable(name) => match context.get_variable(name) {
+                Some(v) => Ok(v.clone()),
+                None if crate::context::strictness() == Strictness::Lenient => Ok(Value::Null),
+                None => Err(Error::new(format!("Missing variable: :{}", name), None)),
+            },
             
             Expr::PropertyAccess { target, property } => {
                 let target_value = Self::eval(target, context)?;
@@ -139,26 +155,9 @@ impl Evaluator {
                 }
             }
             
-            Expr::Array(items) => {
-                let mut out = Vec::with_capacity(items.len());
-                for e in items { 
-                    out.push(Self::eval(e, context)?); 
-                }
-                Ok(Value::Array(out))
-            }
+            Expr::Array(items) => crate::runtime::evaluation::core::eval_array_literal(items, |e| Self::eval(e, context)),
             
-            Expr::ObjectLiteral(pairs) => {
-                let mut json_map = serde_json::Map::new();
-                for (key, value_expr) in pairs {
-                    let value = Self::eval(value_expr, context)?;
-                    let json_value = Self::value_to_json(&value)?;
-                    json_map.insert(key.clone(), json_value);
-                }
-                let json_obj = serde_json::Value::Object(json_map);
-                let json_str = serde_json::to_string(&json_obj)
-                    .map_err(|e| Error::new(format!("Failed to serialize object: {}", e), None))?;
-                Ok(Value::Json(json_str))
-            }
+            Expr::ObjectLiteral(pairs) => crate::runtime::evaluation::core::eval_object_literal(pairs, |e| Self::eval(e, context)),
             
             Expr::TypeCast { expr, ty } => {
                 let v = Self::eval(expr, context)?;
@@ -168,7 +167,7 @@ impl Evaluator {
             Expr::Index { target, index } => {
                 let recv = Self::eval(target, context)?;
                 let idx_v = Self::eval(index, context)?;
-                let idx = idx_v.as_number().ok_or_else(|| Error::new("Index must be number", None))? as isize;
+                let idx = to_index(idx_v.as_number().ok_or_else(|| Error::new("Index must be number", None))?, "Index")?;
                 match recv {
                     Value::Array(items) => index_array(items, idx),
                     _ => Err(Error::new("Index on non-array", None)),
@@ -201,8 +200,9 @@ impl Evaluator {
             }
             
             Expr::Spread(_) => Err(Error::new("Spread not allowed here", None)),
+            Expr::Lambda { .. } => Err(Error::new("Lambda not allowed here; pass it to a function like MAP or FILTER", None)),
             
-            Expr::Assignment { variable: _, value } => {
+            Expr::Assignment { variable: _, value } | Expr::Destructure { value, .. } => {
                 // For now, return the value - assignments need mutable context
                 Self::eval(value, context)
             }
@@ -218,7 +218,10 @@ impl Evaluator {
     }
     
     /// Evaluate unary operations
-    fn eval_unary_op(op: UnaryOp, v: Value) -> Result<Value, Error> {
+    pub(crate) fn eval_unary_op(op: UnaryOp, v: Value) -> Result<Value, Error> {
+        if matches!(v, Value::Null) && op != UnaryOp::Not && crate::context::null_propagation() {
+            return Ok(Value::Null);
+        }
         match op {
             UnaryOp::Plus => Ok(Value::Number(v.as_number().ok_or_else(|| Error::new("Unary '+' on non-number", None))?)),
             UnaryOp::Minus => Ok(Value::Number(-v.as_number().ok_or_else(|| Error::new("Unary '-' on non-number", None))?)),
@@ -227,12 +230,27 @@ impl Evaluator {
     }
     
     /// Evaluate binary operations
-    fn eval_binary_op(op: BinaryOp, a: Value, b: Value) -> Result<Value, Error> {
+    pub(crate) fn eval_binary_op(op: BinaryOp, a: Value, b: Value) -> Result<Value, Error> {
+        let strictness = crate::context::strictness();
+        match (op, &a, &b) {
+            (BinaryOp::Mul, Value::String(s), Value::Number(n)) | (BinaryOp::Mul, Value::Number(n), Value::String(s)) => {
+                return Ok(Value::String(crate::runtime::string::repeat(s, *n, "String repetition")?));
+            }
+            _ => {}
+        }
         match op {
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod | BinaryOp::Pow
+                if (matches!(a, Value::Null) || matches!(b, Value::Null)) && crate::context::null_propagation() =>
+            {
+                Ok(Value::Null)
+            }
             BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod | BinaryOp::Pow => {
                 let an = a.as_number().ok_or_else(|| Error::new("Arithmetic op on non-number", None))?;
                 let bn = b.as_number().ok_or_else(|| Error::new("Arithmetic op on non-number", None))?;
-                Ok(Value::Number(match op {
+                if matches!(op, BinaryOp::Div | BinaryOp::Mod) && bn == 0.0 && (strictness == Strictness::Strict || crate::context::overflow_check()) {
+                    return Err(Error::new("Division by zero", None));
+                }
+                let result = match op {
                     BinaryOp::Add => an + bn,
                     BinaryOp::Sub => an - bn,
                     BinaryOp::Mul => an * bn,
@@ -240,11 +258,38 @@ impl Evaluator {
                     BinaryOp::Mod => an % bn,
                     BinaryOp::Pow => an.powf(bn),
                     _ => unreachable!(),
-                }))
+                };
+                if !result.is_finite() || result == 0.0 {
+                    use crate::runtime::utils::format_number;
+                    let what = format!("{} {} {}", format_number(an), op.symbol(), format_number(bn));
+                    crate::context::check_overflow(result, &[an, bn], &what)?;
+                    if matches!(op, BinaryOp::Mul | BinaryOp::Div | BinaryOp::Pow) {
+                        crate::context::check_underflow(result, &[an, bn], &what)?;
+                    }
+                }
+                if strictness == Strictness::Strict && !result.is_finite() {
+                    return Err(Error::new("Arithmetic result is not a finite number", None));
+                }
+                Ok(Value::Number(result))
             }
             BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
+                let involves_null = matches!(a, Value::Null) || matches!(b, Value::Null);
                 let result = match (a, b) {
                     (Value::Number(x), Value::Number(y)) => match op {
+                        BinaryOp::Eq | BinaryOp::Ne => {
+                            let equal = match crate::context::epsilon() {
+                                Some(eps) => crate::runtime::utils::approx_eq(x, y, eps),
+                                None => x == y,
+                            };
+                            equal == (op == BinaryOp::Eq)
+                        }
+                        BinaryOp::Lt => x < y,
+                        BinaryOp::Le => x <= y,
+                        BinaryOp::Gt => x > y,
+                        BinaryOp::Ge => x >= y,
+                        _ => unreachable!(),
+                    },
+                    (Value::String(x), Value::String(y)) => match op {
                         BinaryOp::Eq => x == y,
                         BinaryOp::Ne => x != y,
                         BinaryOp::Lt => x < y,
@@ -253,7 +298,7 @@ impl Evaluator {
                         BinaryOp::Ge => x >= y,
                         _ => unreachable!(),
                     },
-                    (Value::String(x), Value::String(y)) => match op {
+                    (Value::DateTime(x), Value::DateTime(y)) => match op {
                         BinaryOp::Eq => x == y,
                         BinaryOp::Ne => x != y,
                         BinaryOp::Lt => x < y,
@@ -267,9 +312,14 @@ impl Evaluator {
                         BinaryOp::Ne => x != y,
                         _ => false,
                     },
-                    _ => match op {
-                        BinaryOp::Eq => false,
-                        BinaryOp::Ne => true,
+                    _ => match (op, strictness) {
+                        // Null checks stay legal in strict mode
+                        (BinaryOp::Eq | BinaryOp::Ne, Strictness::Strict) if !involves_null => {
+                            return Err(Error::new("Comparison of incompatible types", None))
+                        }
+                        (BinaryOp::Eq, _) => false,
+                        (BinaryOp::Ne, _) => true,
+                        (_, Strictness::Lenient) => false,
                         _ => return Err(Error::new("Comparison of incompatible types", None)),
                     }
                 };
@@ -301,6 +351,11 @@ impl Evaluator {
                     Err(Error::new(format!("Property '{}' not found in JSON object", property), None))
                 }
             }
+            Value::Map(mut map) => match map.swap_remove(property) {
+                Some(value) => Ok(value),
+                None if safe => Ok(Value::Null),
+                None => Err(Error::new(format!("Property '{}' not found in object", property), None)),
+            },
             Value::Null if safe => Ok(Value::Null),
             _ => Err(Error::new("Property access requires JSON object", None))
         }
@@ -329,30 +384,40 @@ impl Evaluator {
         
         // Check custom functions first
         if let Some(registry) = context.get_custom_registry() {
-            if let Ok(reg) = registry.read() {
-                if reg.has_function(name) {
-                    let mut ev_args = Vec::new();
-                    for a in args {
-                        match a {
-                            Expr::Spread(inner) => {
-                                let v = Self::eval(inner, context)?;
-                                if let Value::Array(items) = v { 
-                                    ev_args.extend(items); 
-                                } else { 
-                                    return Err(Error::new("Spread expects array", None)); 
-                                }
-                            }
-                            _ => {
-                                let val = Self::eval(a, context)?;
-                                ev_args.push(val);
+            if registry.has_function(name) {
+                let mut ev_args = Vec::new();
+                for a in args {
+                    match a {
+                        Expr::Spread(inner) => {
+                            let v = Self::eval(inner, context)?;
+                            if let Value::Array(items) = v { 
+                                ev_args.extend(items); 
+                            } else { 
+                                return Err(Error::new("Spread expects array", None)); 
                             }
                         }
+                        _ => {
+                            let val = Self::eval(a, context)?;
+                            ev_args.push(val);
+                        }
                     }
-                    return reg.execute(name, ev_args);
                 }
+                crate::context::check_custom(name)?;
+                let ev_args = crate::runtime::utils::maps_as_json(&ev_args)?.unwrap_or(ev_args);
+                let call = match context.variables() {
+                    Some(vars) => crate::context::CallContext::with_variables(vars),
+                    None => crate::context::CallContext::new(),
+                }
+                .with_registry(registry);
+                let result = registry.execute_with_context(name, ev_args, &call)?;
+                crate::context::charge_memory(&result)?;
+                return Ok(result);
             }
         }
         
+        // Builtins below this point are not all routed through the dispatch table
+        crate::context::check_builtin_policy(name)?;
+
         // Handle higher-order functions
         match name {
             "FILTER" => Self::eval_filter(args, context),
@@ -362,6 +427,34 @@ impl Evaluator {
             "SUMIF" => Self::eval_sumif(args, context),
             "AVGIF" => Self::eval_avgif(args, context),
             "COUNTIF" => Self::eval_countif(args, context),
+            "SWITCH" | "IFERROR" | "TRY" => {
+                // These bind no names, so their arguments see the caller's context as is
+                use crate::runtime::evaluation::higher_order::{if_error, switch, try_eval};
+                let eval = |e: &Expr| Self::eval(e, context);
+                match name {
+                    "SWITCH" => switch(args, eval),
+                    "IFERROR" => if_error(args, eval),
+                    _ => try_eval(args, eval),
+                }
+            }
+            "ITERATE" | "GOALSEEK" | "SOLVE" | "JOINON" | "DISTINCTBY" | "WHY" | "FIRSTMATCH" | "LET" => {
+                use crate::runtime::evaluation::higher_order::{distinct_by, first_match, goal_seek, iterate, join_on, let_in};
+                let vars = context.clone_variables();
+                let registry = context.get_custom_registry();
+                let eval = |e: &Expr, env: &HashMap<String, Value>| match registry {
+                    Some(r) => Self::eval(e, &VariableContext::with_custom(env, r)),
+                    None => Self::eval(e, &VariableContext::new(env)),
+                };
+                match name {
+                    "ITERATE" => iterate(args, &vars, eval),
+                    "JOINON" => join_on(args, &vars, eval),
+                    "DISTINCTBY" => distinct_by(args, &vars, eval),
+                    "WHY" => crate::runtime::evaluation::why::why(args, &vars, eval),
+                    "FIRSTMATCH" => first_match(args, &vars, eval),
+                    "LET" => let_in(args, &vars, eval),
+                    _ => goal_seek(args, &vars, eval),
+                }
+            }
             "JQ" => {
                 if args.len() != 2 {
                     return Err(Error::new("JQ expects exactly 2 arguments: json_data, jsonpath_expression", None));
@@ -381,6 +474,26 @@ impl Evaluator {
 
                 crate::runtime::jsonpath::apply_jsonpath(&json_data, &path)
             }
+            "FUNCTIONS" => {
+                if !args.is_empty() {
+                    return Err(Error::new("FUNCTIONS expects no arguments", None));
+                }
+                let registry = context.get_custom_registry().map(|r| r.as_ref());
+                let names = crate::runtime::function_dispatch::callable_function_names(registry);
+                Ok(Value::Array(names.into_iter().map(Value::String).collect()))
+            }
+            "ISFUNCTION" => {
+                let [arg] = args else {
+                    return Err(Error::new("ISFUNCTION expects a function name", None));
+                };
+                match Self::eval(arg, context)? {
+                    Value::String(name) => {
+                        let registry = context.get_custom_registry().map(|r| r.as_ref());
+                        Ok(Value::Boolean(crate::runtime::function_dispatch::is_callable(&name, registry)))
+                    }
+                    other => Err(Error::new(format!("ISFUNCTION expects a function name as text, got {:?}", other), None)),
+                }
+            }
             _ => {
                 // Evaluate arguments for built-in functions
                 let mut ev_args = Vec::new();
@@ -412,6 +525,7 @@ impl Evaluator {
         }
         let arr_v = Self::eval(&args[0], context)?;
         let lambda = &args[1];
+        check_lambda("FILTER", lambda, 2)?;
         let param_name = if args.len() > 2 { 
             if let Value::String(s) = Self::eval(&args[2], context)? { s } else { "x".into() }
         } else { "x".into() };
@@ -420,10 +534,10 @@ impl Evaluator {
             Value::Array(items) => {
                 let mut out = Vec::with_capacity(items.len());
                 let mut env = context.clone_variables();
-                for it in items {
-                    env.insert(param_name.clone(), it.clone());
+                for (i, it) in items.into_iter().enumerate() {
+                    let body = bind_lambda(lambda, &[&param_name], [it.clone(), Value::Number(i as f64)], &mut env);
                     let var_context = VariableContext::with_owned(env);
-                    let matches = matches!(Self::eval(lambda, &var_context)?, Value::Boolean(true));
+                    let matches = matches!(Self::eval(body, &var_context)?, Value::Boolean(true));
                     env = var_context.into_variables();
                     if matches {
                         out.push(it);
@@ -441,6 +555,7 @@ impl Evaluator {
         }
         let arr_v = Self::eval(&args[0], context)?;
         let lambda = &args[1];
+        check_lambda("FIND", lambda, 2)?;
         let param_name = if args.len() > 2 {
             if let Value::String(s) = Self::eval(&args[2], context)? { s } else { "x".into() }
         } else { "x".into() };
@@ -448,10 +563,10 @@ impl Evaluator {
         match arr_v {
             Value::Array(items) => {
                 let mut env = context.clone_variables();
-                for it in items {
-                    env.insert(param_name.clone(), it.clone());
+                for (i, it) in items.into_iter().enumerate() {
+                    let body = bind_lambda(lambda, &[&param_name], [it.clone(), Value::Number(i as f64)], &mut env);
                     let var_context = VariableContext::with_owned(env);
-                    let matches = matches!(Self::eval(lambda, &var_context)?, Value::Boolean(true));
+                    let matches = matches!(Self::eval(body, &var_context)?, Value::Boolean(true));
                     env = var_context.into_variables();
                     if matches {
                         return Ok(it);
@@ -469,6 +584,7 @@ impl Evaluator {
         }
         let arr_v = Self::eval(&args[0], context)?;
         let lambda = &args[1];
+        check_lambda("MAP", lambda, 2)?;
         let param_name = if args.len() > 2 {
             if let Value::String(s) = Self::eval(&args[2], context)? { s } else { "x".into() }
         } else { "x".into() };
@@ -477,10 +593,10 @@ impl Evaluator {
             Value::Array(items) => {
                 let mut out = Vec::with_capacity(items.len());
                 let mut env = context.clone_variables();
-                for it in items {
-                    env.insert(param_name.clone(), it);
+                for (i, it) in items.into_iter().enumerate() {
+                    let body = bind_lambda(lambda, &[&param_name], [it, Value::Number(i as f64)], &mut env);
                     let var_context = VariableContext::with_owned(env);
-                    let result = Self::eval(lambda, &var_context)?;
+                    let result = Self::eval(body, &var_context)?;
                     env = var_context.into_variables();
                     out.push(result);
                 }
@@ -496,6 +612,7 @@ impl Evaluator {
         }
         let arr_v = Self::eval(&args[0], context)?;
         let lambda = &args[1];
+        check_lambda("REDUCE", lambda, 3)?;
         let mut acc = Self::eval(&args[2], context)?;
         let val_param = if args.len() > 3 {
             if let Value::String(s) = Self::eval(&args[3], context)? { s } else { "x".into() }
@@ -507,11 +624,10 @@ impl Evaluator {
         match arr_v {
             Value::Array(items) => {
                 let mut env = context.clone_variables();
-                for it in items {
-                    env.insert(val_param.clone(), it);
-                    env.insert(acc_param.clone(), acc);
+                for (i, it) in items.into_iter().enumerate() {
+                    let body = bind_lambda(lambda, &[&acc_param, &val_param], [acc, it, Value::Number(i as f64)], &mut env);
                     let var_context = VariableContext::with_owned(env);
-                    acc = Self::eval(lambda, &var_context)?;
+                    acc = Self::eval(body, &var_context)?;
                     env = var_context.into_variables();
                 }
                 Ok(acc)
@@ -545,10 +661,11 @@ impl Evaluator {
             Value::Array(items) => {
                 let mut acc = 0.0;
                 let mut env = context.clone_variables();
-                for it in items {
-                    env.insert("x".into(), it.clone());
+                check_lambda("SUMIF", criteria_expr, 2)?;
+                for (i, it) in items.into_iter().enumerate() {
+                    let body = bind_lambda(criteria_expr, &["x"], [it.clone(), Value::Number(i as f64)], &mut env);
                     let var_context = VariableContext::with_owned(env);
-                    let matches = matches!(Self::eval(criteria_expr, &var_context)?, Value::Boolean(true));
+                    let matches = matches!(Self::eval(body, &var_context)?, Value::Boolean(true));
                     env = var_context.into_variables();
                     if matches {
                         match it {
@@ -564,91 +681,6 @@ impl Evaluator {
     }
     
     fn eval_sumif_excel_style(range: &Value, criteria: &Value, sum_range: &Value) -> Result<Value, Error> {
-        fn meets_criteria(value: &Value, criteria: &Value) -> bool {
-            match criteria {
-                Value::String(crit) => {
-                    if let Some(stripped) = crit.strip_prefix(">=") {
-                        if let Ok(threshold) = stripped.parse::<f64>() {
-                            match value {
-                                Value::Number(n) => *n >= threshold,
-                                Value::Currency(n) => *n >= threshold,
-                                _ => false,
-                            }
-                        } else { false }
-                    } else if let Some(stripped) = crit.strip_prefix("<=") {
-                        if let Ok(threshold) = stripped.parse::<f64>() {
-                            match value {
-                                Value::Number(n) => *n <= threshold,
-                                Value::Currency(n) => *n <= threshold,
-                                _ => false,
-                            }
-                        } else { false }
-                    } else if let Some(stripped) = crit.strip_prefix("<>") {
-                        if let Ok(threshold) = stripped.parse::<f64>() {
-                            match value {
-                                Value::Number(n) => *n != threshold,
-                                Value::Currency(n) => *n != threshold,
-                                _ => true,
-                            }
-                        } else { 
-                            match value {
-                                Value::String(s) => s != stripped,
-                                _ => true,
-                            }
-                        }
-                    } else if let Some(stripped) = crit.strip_prefix('>') {
-                        if let Ok(threshold) = stripped.parse::<f64>() {
-                            match value {
-                                Value::Number(n) => *n > threshold,
-                                Value::Currency(n) => *n > threshold,
-                                _ => false,
-                            }
-                        } else { false }
-                    } else if let Some(stripped) = crit.strip_prefix('<') {
-                        if let Ok(threshold) = stripped.parse::<f64>() {
-                            match value {
-                                Value::Number(n) => *n < threshold,
-                                Value::Currency(n) => *n < threshold,
-                                _ => false,
-                            }
-                        } else { false }
-                    } else if let Some(stripped) = crit.strip_prefix('=') {
-                        if let Ok(threshold) = stripped.parse::<f64>() {
-                            match value {
-                                Value::Number(n) => *n == threshold,
-                                Value::Currency(n) => *n == threshold,
-                                _ => false,
-                            }
-                        } else {
-                            match value {
-                                Value::String(s) => s == stripped,
-                                _ => false,
-                            }
-                        }
-                    } else if let Ok(threshold) = crit.parse::<f64>() {
-                        match value {
-                            Value::Number(n) => *n == threshold,
-                            Value::Currency(n) => *n == threshold,
-                            _ => false,
-                        }
-                    } else {
-                        match value {
-                            Value::String(s) => s == crit,
-                            _ => false,
-                        }
-                    }
-                }
-                Value::Number(threshold) => {
-                    match value {
-                        Value::Number(n) => *n == *threshold,
-                        Value::Currency(n) => *n == *threshold,
-                        _ => false,
-                    }
-                }
-                _ => false,
-            }
-        }
-        
         fn sum_if_helper(range_val: &Value, sum_val: &Value, criteria: &Value) -> f64 {
             match (range_val, sum_val) {
                 (Value::Array(range_items), Value::Array(sum_items)) => {
@@ -689,16 +721,17 @@ impl Evaluator {
         }
         let arr_v = Self::eval(&args[0], context)?;
         let lambda = &args[1];
+        check_lambda("AVGIF", lambda, 2)?;
         
         match arr_v {
             Value::Array(items) => {
                 let mut acc = 0.0;
                 let mut count = 0usize;
                 let mut env = context.clone_variables();
-                for it in items {
-                    env.insert("x".into(), it.clone());
+                for (i, it) in items.into_iter().enumerate() {
+                    let body = bind_lambda(lambda, &["x"], [it.clone(), Value::Number(i as f64)], &mut env);
                     let var_context = VariableContext::with_owned(env);
-                    let matches = matches!(Self::eval(lambda, &var_context)?, Value::Boolean(true));
+                    let matches = matches!(Self::eval(body, &var_context)?, Value::Boolean(true));
                     env = var_context.into_variables();
                     if matches {
                         match it {
@@ -719,15 +752,16 @@ impl Evaluator {
         }
         let arr_v = Self::eval(&args[0], context)?;
         let lambda = &args[1];
+        check_lambda("COUNTIF", lambda, 2)?;
 
         match arr_v {
             Value::Array(items) => {
                 let mut count = 0usize;
                 let mut env = context.clone_variables();
-                for it in items {
-                    env.insert("x".into(), it);
+                for (i, it) in items.into_iter().enumerate() {
+                    let body = bind_lambda(lambda, &["x"], [it, Value::Number(i as f64)], &mut env);
                     let var_context = VariableContext::with_owned(env);
-                    let matches = matches!(Self::eval(lambda, &var_context)?, Value::Boolean(true));
+                    let matches = matches!(Self::eval(body, &var_context)?, Value::Boolean(true));
                     env = var_context.into_variables();
                     if matches {
                         count += 1;
@@ -738,29 +772,6 @@ impl Evaluator {
             _ => Err(Error::new("COUNTIF first arg must be array", None)),
         }
     }
-    
-    /// Helper to convert Value to JSON
-    fn value_to_json(value: &Value) -> Result<serde_json::Value, Error> {
-        match value {
-            Value::Number(n) => Ok(serde_json::json!(n)),
-            Value::String(s) => Ok(serde_json::json!(s)),
-            Value::Boolean(b) => Ok(serde_json::json!(b)),
-            Value::Currency(c) => Ok(serde_json::json!(c)),
-            Value::DateTime(dt) => Ok(serde_json::json!(dt)),
-            Value::Null => Ok(serde_json::json!(null)),
-            Value::Array(arr) => {
-                let mut json_arr = Vec::new();
-                for item in arr {
-                    json_arr.push(Self::value_to_json(item)?);
-                }
-                Ok(serde_json::Value::Array(json_arr))
-            }
-            Value::Json(s) => {
-                serde_json::from_str(s)
-                    .map_err(|e| Error::new(format!("Invalid JSON: {}", e), None))
-            }
-        }
-    }
 }
 
 // Convenience functions for backward compatibility
@@ -774,7 +785,7 @@ pub fn eval_with_vars(expr: &Expr, vars: &HashMap<String, Value>) -> Result<Valu
     Evaluator::eval(expr, &context)
 }
 
-pub fn eval_with_vars_and_custom(expr: &Expr, vars: &HashMap<String, Value>, custom_registry: &Arc<RwLock<FunctionRegistry>>) -> Result<Value, Error> {
+pub fn eval_with_vars_and_custom(expr: &Expr, vars: &HashMap<String, Value>, custom_registry: &Arc<FunctionRegistry>) -> Result<Value, Error> {
     let context = VariableContext::with_custom(vars, custom_registry);
     Evaluator::eval(expr, &context)
 }
@@ -793,21 +804,39 @@ pub fn eval_with_assignments_and_context(expr: &Expr, vars: &HashMap<String, Val
     Ok((result, final_vars))
 }
 
-fn eval_with_assignments_context(expr: &Expr, context: &mut VariableContext) -> Result<Value, Error> {
+pub(crate) fn eval_with_assignments_context(expr: &Expr, context: &mut VariableContext) -> Result<Value, Error> {
+    use crate::runtime::observer;
     match expr {
         Expr::Assignment { variable, value } => {
-            let result = Evaluator::eval(value, context)?;
+            let started = observer::enter(expr, context)?;
+            let result = Evaluator::eval(value, context);
+            observer::exit(expr, &result, started);
+            let result = result?;
             context.make_mut().insert(variable.clone(), result.clone());
             Ok(result)
         }
+        Expr::Destructure { pattern, value } => {
+            let started = observer::enter(expr, context)?;
+            let result = Evaluator::eval(value, context);
+            observer::exit(expr, &result, started);
+            let result = result?;
+            let assigned = crate::runtime::evaluation::assignments::destructure(pattern, &result)?;
+            context.make_mut().extend(assigned);
+            Ok(result)
+        }
         Expr::Sequence(exprs) => {
-            let mut last_result = Value::Null;
+            let started = observer::enter(expr, context)?;
+            let mut result = Ok(Value::Null);
             for expr in exprs {
-                last_result = eval_with_assignments_context(expr, context)?;
+                result = eval_with_assignments_context(expr, context);
+                if result.is_err() {
+                    break;
+                }
             }
-            Ok(last_result)
+            observer::exit(expr, &result, started);
+            result
         }
         // For all other expressions, delegate to unified evaluator
         _ => Evaluator::eval(expr, context)
     }
-}
\ No newline at end of file
+}
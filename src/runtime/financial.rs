@@ -1,8 +1,15 @@
 use crate::types::Value;
 use crate::error::Error;
 
+/// Most rows AMORTIZE will build (a century of monthly payments is 1,200)
+const MAX_AMORTIZE_PERIODS: usize = 100_000;
+
 pub fn exec_financial(name: &str, args: &[Value]) -> Result<Value, Error> {
     match name {
+        "BRACKET" => super::tiers::exec_bracket(args),
+        "TIERPRICE" => super::tiers::exec_tier_price(args),
+        "PRORATE" => super::daycount::exec_prorate(args),
+        "ACCRINT" => super::daycount::exec_accrint(args),
         "PMT" => {
             if args.len() < 3 || args.len() > 5 {
                 return Err(Error::new("PMT expects 3-5 arguments: rate, nper, pv, [fv], [type]", None));
@@ -44,30 +51,121 @@ pub fn exec_financial(name: &str, args: &[Value]) -> Result<Value, Error> {
             if cost < 0.0 || salvage < 0.0 || life <= 0.0 || period < 0.0 {
                 return Err(Error::new("DB arguments must be non-negative (life must be positive)", None));
             }
-            if period > life {
+            if !(1.0..=12.0).contains(&month) {
+                return Err(Error::new("DB month must be between 1 and 12", None));
+            }
+            // A partial first year leaves the rest of it for an extra period at the end
+            let last_period = if month < 12.0 { life + 1.0 } else { life };
+            check_period("DB", period, last_period)?;
+            if cost == 0.0 {
                 return Ok(Value::Number(0.0));
             }
-            
-            let rate = 1.0 - (salvage / cost).powf(1.0 / life);
-            
-            if period == 1.0 {
-                let depreciation = cost * rate * month / 12.0;
-                Ok(Value::Number(depreciation))
-            } else {
-                let mut book_value = cost;
-                book_value -= cost * rate * month / 12.0;
-                for _p in 2..=(period as i32 - 1) {
-                    book_value -= book_value * rate;
-                }
-                
-                let current_depreciation = if period as i32 == life as i32 && month < 12.0 {
-                    book_value * rate * (12.0 - month) / 12.0
+
+            // Excel rounds the fixed rate to three decimals
+            let rate = ((1.0 - (salvage / cost).powf(1.0 / life)) * 1000.0).round() / 1000.0;
+            let mut depreciated = cost * rate * month / 12.0;
+            let mut current = depreciated;
+            for p in 2..=(period as i64) {
+                current = if p as f64 == life + 1.0 {
+                    (cost - depreciated) * rate * (12.0 - month) / 12.0
                 } else {
-                    book_value * rate
+                    (cost - depreciated) * rate
                 };
-                
-                Ok(Value::Number(current_depreciation.max(0.0)))
+                depreciated += current;
+            }
+            Ok(Value::Number(current))
+        }
+        "SLN" => {
+            if args.len() != 3 {
+                return Err(Error::new("SLN expects 3 arguments: cost, salvage, life", None));
+            }
+            let cost = args[0].as_number().ok_or_else(|| Error::new("SLN cost must be a number", None))?;
+            let salvage = args[1].as_number().ok_or_else(|| Error::new("SLN salvage must be a number", None))?;
+            let life = args[2].as_number().ok_or_else(|| Error::new("SLN life must be a number", None))?;
+            if life <= 0.0 {
+                return Err(Error::new("SLN life must be positive", None));
             }
+            Ok(Value::Number((cost - salvage) / life))
+        }
+        "SYD" => {
+            if args.len() != 4 {
+                return Err(Error::new("SYD expects 4 arguments: cost, salvage, life, period", None));
+            }
+            let cost = args[0].as_number().ok_or_else(|| Error::new("SYD cost must be a number", None))?;
+            let salvage = args[1].as_number().ok_or_else(|| Error::new("SYD salvage must be a number", None))?;
+            let life = args[2].as_number().ok_or_else(|| Error::new("SYD life must be a number", None))?;
+            let period = args[3].as_number().ok_or_else(|| Error::new("SYD period must be a number", None))?;
+            if cost < 0.0 || salvage < 0.0 || life <= 0.0 {
+                return Err(Error::new("SYD arguments must be non-negative (life must be positive)", None));
+            }
+            check_period("SYD", period, life)?;
+            Ok(Value::Number((cost - salvage) * (life - period + 1.0) * 2.0 / (life * (life + 1.0))))
+        }
+        "DDB" => {
+            if args.len() < 4 || args.len() > 5 {
+                return Err(Error::new("DDB expects 4-5 arguments: cost, salvage, life, period, [factor]", None));
+            }
+            let cost = args[0].as_number().ok_or_else(|| Error::new("DDB cost must be a number", None))?;
+            let salvage = args[1].as_number().ok_or_else(|| Error::new("DDB salvage must be a number", None))?;
+            let life = args[2].as_number().ok_or_else(|| Error::new("DDB life must be a number", None))?;
+            let period = args[3].as_number().ok_or_else(|| Error::new("DDB period must be a number", None))?;
+            let factor = args.get(4).and_then(|v| v.as_number()).unwrap_or(2.0);
+            if cost < 0.0 || salvage < 0.0 || life <= 0.0 || factor <= 0.0 {
+                return Err(Error::new("DDB arguments must be non-negative (life and factor must be positive)", None));
+            }
+            check_period("DDB", period, life)?;
+
+            // Book value at the start of the period, never written down below salvage
+            let rate = (factor / life).min(1.0);
+            let book_value = cost * (1.0 - rate).powf(period - 1.0);
+            let depreciation = (book_value * rate).min(book_value - salvage).max(0.0);
+            Ok(Value::Number(depreciation))
+        }
+        "AMORTIZE" => {
+            if args.len() != 3 {
+                return Err(Error::new("AMORTIZE expects 3 arguments: rate, nper, pv", None));
+            }
+            let rate = args[0].as_number().ok_or_else(|| Error::new("AMORTIZE rate must be a number", None))?;
+            let nper = args[1].as_number().ok_or_else(|| Error::new("AMORTIZE nper must be a number", None))?;
+            let pv = args[2].as_number().ok_or_else(|| Error::new("AMORTIZE pv must be a number", None))?;
+            if nper.fract() != 0.0 || !(1.0..=MAX_AMORTIZE_PERIODS as f64).contains(&nper) {
+                return Err(Error::new(
+                    format!("AMORTIZE nper must be a whole number from 1 to {}", MAX_AMORTIZE_PERIODS),
+                    None,
+                ));
+            }
+            if !rate.is_finite() || rate <= -1.0 {
+                return Err(Error::new("AMORTIZE rate must be greater than -1", None));
+            }
+            let periods = nper as usize;
+            crate::context::check_array_len(periods)?;
+
+            // Same payment as PMT, reported as a positive amount paid each period
+            let payment = if rate == 0.0 {
+                pv / nper
+            } else {
+                pv * rate / (1.0 - (1.0 + rate).powf(-nper))
+            };
+            let mut balance = pv;
+            let mut rows = Vec::with_capacity(periods);
+            for period in 1..=periods {
+                let interest = balance * rate;
+                let principal = payment - interest;
+                balance -= principal;
+                // Floating-point dust left after the final payment
+                if period == periods {
+                    balance = 0.0;
+                }
+                let row = serde_json::json!({
+                    "period": period,
+                    "payment": payment,
+                    "interest": interest,
+                    "principal": principal,
+                    "balance": balance,
+                });
+                rows.push(Value::Json(row.to_string()));
+            }
+            Ok(Value::Array(rows))
         }
         "FV" => {
             if args.len() < 3 || args.len() > 5 {
@@ -138,4 +236,15 @@ pub fn exec_financial(name: &str, args: &[Value]) -> Result<Value, Error> {
         }
         _ => Err(Error::new(format!("Unknown financial function: {}", name), None)),
     }
-}
\ No newline at end of file
+}
+
+/// Depreciation periods are whole numbers from 1 to `last`
+fn check_period(name: &str, period: f64, last: f64) -> Result<(), Error> {
+    if period.fract() != 0.0 || period < 1.0 || period > last {
+        return Err(Error::new(
+            format!("{} period must be a whole number from 1 to {}", name, crate::runtime::utils::format_number(last)),
+            None,
+        ));
+    }
+    Ok(())
+}
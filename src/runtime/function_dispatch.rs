@@ -1,6 +1,7 @@
 use crate::types::Value;
 use crate::error::Error;
 use std::collections::HashSet;
+use crate::custom::FunctionRegistry;
 use super::{arithmetic, logical, string, array, datetime, financial, statistical};
 
 /// Optimized function dispatch using static hash sets for O(1) category lookup
@@ -24,6 +25,8 @@ impl FunctionDispatch {
         arithmetic_functions.insert("AVERAGE");
         arithmetic_functions.insert("MIN");
         arithmetic_functions.insert("MAX");
+        arithmetic_functions.insert("MINA");
+        arithmetic_functions.insert("MAXA");
         arithmetic_functions.insert("ROUND");
         arithmetic_functions.insert("CEIL");
         arithmetic_functions.insert("CEILING");
@@ -36,6 +39,15 @@ impl FunctionDispatch {
         arithmetic_functions.insert("INT");
         arithmetic_functions.insert("PRODUCT");
         arithmetic_functions.insert("MULTIPLY");
+        arithmetic_functions.insert("PERCENTOF");
+        arithmetic_functions.insert("AVGRATIO");
+        arithmetic_functions.insert("ROUNDCASH");
+        arithmetic_functions.insert("CHECKEDADD");
+        arithmetic_functions.insert("CHECKEDSUB");
+        arithmetic_functions.insert("CHECKEDMUL");
+        arithmetic_functions.insert("CLAMP");
+        arithmetic_functions.insert("LERP");
+        arithmetic_functions.insert("INTERP");
         
         let mut logical_functions = HashSet::new();
         logical_functions.insert("AND");
@@ -44,6 +56,11 @@ impl FunctionDispatch {
         logical_functions.insert("XOR");
         logical_functions.insert("IF");
         logical_functions.insert("IFS");
+        logical_functions.insert("APPROXEQ");
+        logical_functions.insert("BETWEEN");
+        logical_functions.insert("FEATURE");
+        logical_functions.insert("COALESCE");
+        logical_functions.insert("DTABLE");
         
         let mut string_functions = HashSet::new();
         string_functions.insert("LENGTH");
@@ -51,8 +68,12 @@ impl FunctionDispatch {
         string_functions.insert("UPPER");
         string_functions.insert("LOWER");
         string_functions.insert("TRIM");
+        string_functions.insert("TRIMSTART");
+        string_functions.insert("TRIMEND");
+        string_functions.insert("REPT");
         string_functions.insert("SUBSTRING");
         string_functions.insert("SPLIT");
+        string_functions.insert("SPLITLINES");
         string_functions.insert("REPLACE");
         string_functions.insert("SUBSTITUTE");
         string_functions.insert("SUBSTITUTEM");
@@ -64,6 +85,24 @@ impl FunctionDispatch {
         string_functions.insert("LEFT");
         string_functions.insert("RIGHT");
         string_functions.insert("MID");
+        string_functions.insert("FORMAT");
+        string_functions.insert("CURRENCY");
+        string_functions.insert("PARSENUMBER");
+        string_functions.insert("PERCENT");
+        string_functions.insert("LEVENSHTEIN");
+        string_functions.insert("SIMILARITY");
+        string_functions.insert("JARO");
+        string_functions.insert("SOUNDEX");
+        string_functions.insert("METAPHONE");
+        string_functions.insert("TOKENIZE");
+        string_functions.insert("NGRAMS");
+        string_functions.insert("TFCOUNT");
+        string_functions.insert("SCOREDICT");
+        string_functions.insert("MASK");
+        string_functions.insert("REDACT");
+        string_functions.insert("REDACTEMAIL");
+        string_functions.insert("PARSEPHONE");
+        string_functions.insert("TRYCAST");
         
         let mut array_functions = HashSet::new();
         array_functions.insert("ARRAY");
@@ -78,6 +117,15 @@ impl FunctionDispatch {
         array_functions.insert("REVERSE");
         array_functions.insert("JOIN");
         array_functions.insert("MERGE");
+        array_functions.insert("PAGE");
+        array_functions.insert("OFFSETLIMIT");
+        array_functions.insert("ORDINAL");
+        array_functions.insert("NTH");
+        array_functions.insert("ORDERBY");
+        // Range literals `a..b` / `a..=b`
+        array_functions.insert("__RANGE__");
+        array_functions.insert("__RANGE_INCLUSIVE__");
+        array_functions.insert("RANGE");
         
         let mut datetime_functions = HashSet::new();
         datetime_functions.insert("NOW");
@@ -88,18 +136,44 @@ impl FunctionDispatch {
         datetime_functions.insert("DAY");
         datetime_functions.insert("DATEADD");
         datetime_functions.insert("DATEDIFF");
+        datetime_functions.insert("HUMANIZE");
+        datetime_functions.insert("HUMANIZEDURATION");
+        datetime_functions.insert("DAYSINPERIOD");
+        datetime_functions.insert("YEARFRAC");
         
         let mut financial_functions = HashSet::new();
         financial_functions.insert("PMT");
         financial_functions.insert("DB");
+        financial_functions.insert("SLN");
+        financial_functions.insert("SYD");
+        financial_functions.insert("DDB");
+        financial_functions.insert("AMORTIZE");
         financial_functions.insert("FV");
         financial_functions.insert("IPMT");
+        financial_functions.insert("BRACKET");
+        financial_functions.insert("TIERPRICE");
+        financial_functions.insert("PRORATE");
+        financial_functions.insert("ACCRINT");
         
         let mut statistical_functions = HashSet::new();
         statistical_functions.insert("MEDIAN");
+        statistical_functions.insert("CHOOSEWEIGHTED");
+        statistical_functions.insert("PROBABILITY");
+        statistical_functions.insert("HASHBUCKET");
+        statistical_functions.insert("FINGERPRINT");
+        statistical_functions.insert("BIN");
+        statistical_functions.insert("HISTOGRAM");
+        statistical_functions.insert("ZSCORE");
+        statistical_functions.insert("MINMAXSCALE");
+        statistical_functions.insert("WINSORIZE");
+        statistical_functions.insert("MOVINGAVG");
+        statistical_functions.insert("EMA");
         statistical_functions.insert("MODE.SNGL");
         statistical_functions.insert("MODESNGL");
         statistical_functions.insert("MODE_SNGL");
+        statistical_functions.insert("MODE.MULT");
+        statistical_functions.insert("MODEMULT");
+        statistical_functions.insert("MODE_MULT");
         statistical_functions.insert("STDEV.P");
         statistical_functions.insert("STDEVP");
         statistical_functions.insert("STDEV_P");
@@ -115,6 +189,7 @@ impl FunctionDispatch {
         
         let mut json_functions = HashSet::new();
         json_functions.insert("DIG");
+        json_functions.insert("CONST");
         
         Self {
             arithmetic_functions,
@@ -179,6 +254,41 @@ impl FunctionDispatch {
         self.json_functions.contains(name)
     }
     
+    /// Every registered function name, unordered
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.arithmetic_functions.iter()
+            .chain(&self.logical_functions)
+            .chain(&self.string_functions)
+            .chain(&self.array_functions)
+            .chain(&self.datetime_functions)
+            .chain(&self.financial_functions)
+            .chain(&self.statistical_functions)
+            .chain(&self.json_functions)
+            .copied()
+    }
+
+    /// Whether `name` is one of the arithmetic functions, which all return numbers
+    pub fn is_arithmetic(&self, name: &str) -> bool {
+        self.arithmetic_functions.contains(name)
+    }
+
+    /// Category of a registered function, e.g. `"string"`
+    pub fn category(&self, name: &str) -> Option<&'static str> {
+        [
+            (&self.arithmetic_functions, "arithmetic"),
+            (&self.logical_functions, "logical"),
+            (&self.string_functions, "string"),
+            (&self.array_functions, "array"),
+            (&self.datetime_functions, "date/time"),
+            (&self.financial_functions, "financial"),
+            (&self.statistical_functions, "statistical"),
+            (&self.json_functions, "JSON"),
+        ]
+        .into_iter()
+        .find(|(set, _)| set.contains(name))
+        .map(|(_, category)| category)
+    }
+
     /// Get the total number of registered functions
     pub fn count(&self) -> usize {
         self.arithmetic_functions.len() +
@@ -205,7 +315,35 @@ lazy_static::lazy_static! {
 
 /// Optimized builtin function execution using category-based dispatch
 pub fn exec_builtin_fast(name: &str, args: &[Value]) -> Result<Value, Error> {
-    GLOBAL_DISPATCH.execute(name, args)
+    exec_builtin_checked(name, args, |args| GLOBAL_DISPATCH.execute(name, args))
+}
+
+/// Run the builtin `name` through `exec` with every per-call check: the builtin gate,
+/// category timing, array length, overflow and memory limits. Both the dispatch table
+/// and the legacy [`exec_builtin`](super::builtin_functions::exec_builtin) go through here.
+pub(crate) fn exec_builtin_checked(
+    name: &str,
+    args: &[Value],
+    exec: impl FnOnce(&[Value]) -> Result<Value, Error>,
+) -> Result<Value, Error> {
+    crate::context::check_builtin_call(name, args)?;
+    let lowered = crate::runtime::utils::maps_as_json(args)?;
+    let args = lowered.as_deref().unwrap_or(args);
+    let result = match GLOBAL_DISPATCH.category(name) {
+        Some(category) => crate::context::timed_call(category, || exec(args))?,
+        None => exec(args)?,
+    };
+    match &result {
+        Value::Array(items) => crate::context::check_array_len(items.len())?,
+        Value::Number(n) | Value::Currency(n) if !n.is_finite() => {
+            let mut inputs = Vec::new();
+            crate::runtime::utils::collect_numbers(args, &mut inputs);
+            crate::context::check_overflow(*n, &inputs, name)?;
+        }
+        _ => {}
+    }
+    crate::context::charge_memory(&result)?;
+    Ok(result)
 }
 
 /// Check if a builtin function exists
@@ -213,6 +351,71 @@ pub fn has_builtin_function(name: &str) -> bool {
     GLOBAL_DISPATCH.has_function(name)
 }
 
+/// Categories function calls are timed under for `EvalOptions::category_budgets`:
+/// those of [`builtin_category`], plus `custom` and `javascript` for registered functions
+pub const FUNCTION_CATEGORIES: &[&str] =
+    &["arithmetic", "logical", "string", "array", "date/time", "financial", "statistical", "JSON", "custom", "javascript"];
+
+/// Builtins implemented by the evaluators themselves rather than the dispatch table
+/// (they need unevaluated arguments or predate it), plus parser-generated internals
+pub const EVALUATOR_BUILTINS: &[&str] = &[
+    "FILTER", "MAP", "FIND", "REDUCE", "SUMIF", "AVGIF", "COUNTIF", "ITERATE", "GOALSEEK", "SOLVE", "JOINON", "DISTINCTBY", "WHY", "FIRSTMATCH", "LET", "SWITCH", "IFERROR", "TRY", "JQ",
+    "BETWEEN", "FUNCTIONS", "ISFUNCTION",
+    "__TERNARY__", "__CONST_TRUE__", "__CONST_FALSE__",
+];
+
+/// Whether `name` (upper case, as produced by the parser) is a builtin of any kind
+pub fn is_builtin(name: &str) -> bool {
+    has_builtin_function(name) || EVALUATOR_BUILTINS.contains(&name)
+}
+
+/// Names of every user-callable builtin, sorted
+pub fn builtin_function_names() -> Vec<&'static str> {
+    let mut names: Vec<&'static str> = GLOBAL_DISPATCH
+        .names()
+        .chain(EVALUATOR_BUILTINS.iter().copied())
+        .filter(|n| !n.starts_with("__"))
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+/// Whether `name` (any case) can be called in the current evaluation: a custom
+/// function in `registry` not blocked by `builtins_only`, or a builtin allowed by the
+/// builtin policy and deterministic mode
+pub fn is_callable(name: &str, registry: Option<&FunctionRegistry>) -> bool {
+    let name = name.to_uppercase();
+    if registry.is_some_and(|r| r.has_function(&name)) {
+        return crate::context::check_custom(&name).is_ok();
+    }
+    is_builtin(&name) && !name.starts_with("__") && crate::context::check_builtin(&name).is_ok()
+}
+
+/// Names of every function [`is_callable`] in the current evaluation, sorted
+pub fn callable_function_names(registry: Option<&FunctionRegistry>) -> Vec<String> {
+    let custom = registry.map(|r| r.list_functions()).unwrap_or_default();
+    let mut names: Vec<String> = builtin_function_names()
+        .into_iter()
+        .chain(custom)
+        .filter(|n| is_callable(n, registry))
+        .map(str::to_string)
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+/// Category of the builtin `name` (upper case); `None` for evaluator builtins and unknown names
+pub fn builtin_category(name: &str) -> Option<&'static str> {
+    GLOBAL_DISPATCH.category(name)
+}
+
+/// Whether the builtin `name` always returns a number
+pub fn is_arithmetic_builtin(name: &str) -> bool {
+    GLOBAL_DISPATCH.is_arithmetic(name)
+}
+
 /// Get count of registered builtin functions
 pub fn builtin_function_count() -> usize {
     GLOBAL_DISPATCH.count()
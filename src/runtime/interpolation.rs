@@ -0,0 +1,65 @@
+//! Interpolation between two values and along lookup tables such as rate curves.
+
+use crate::error::Error;
+use crate::types::Value;
+
+fn number(name: &str, what: &str, v: &Value) -> Result<f64, Error> {
+    match v {
+        Value::Number(n) | Value::Currency(n) if n.is_finite() => Ok(*n),
+        other => Err(Error::new(format!("{} {} must be a finite number, got {:?}", name, what, other), None)),
+    }
+}
+
+fn numbers(name: &str, what: &str, v: &Value) -> Result<Vec<f64>, Error> {
+    match v {
+        Value::Array(items) => items.iter().map(|item| number(name, what, item)).collect(),
+        _ => Err(Error::new(format!("{} {} must be an array of numbers", name, what), None)),
+    }
+}
+
+/// LERP(a, b, t) -> `a + (b - a) * t`; `t` is not limited to 0..1
+pub fn exec_lerp(args: &[Value]) -> Result<Value, Error> {
+    let [a, b, t] = args else {
+        return Err(Error::new("LERP expects a, b, t", None));
+    };
+    let (a, b, t) = (number("LERP", "a", a)?, number("LERP", "b", b)?, number("LERP", "t", t)?);
+    Ok(Value::Number(a + (b - a) * t))
+}
+
+/// INTERP(x, xs, ys, [mode]) -> the value at `x` of the curve through the points
+/// `(xs[i], ys[i])`, with `xs` increasing. `"linear"` (default) draws straight lines
+/// between points; `"step"` takes the `ys` of the last point at or before `x`, as an
+/// age-band table does. Outside the table the first or last `ys` applies. A null
+/// `x` gives null.
+pub fn exec_interp(args: &[Value]) -> Result<Value, Error> {
+    let (x, xs, ys, mode) = match args {
+        [x, xs, ys] => (x, xs, ys, "linear"),
+        [x, xs, ys, Value::String(mode)] => (x, xs, ys, mode.as_str()),
+        _ => return Err(Error::new("INTERP expects x, xs, ys, [mode]", None)),
+    };
+    let (xs, ys) = (numbers("INTERP", "xs", xs)?, numbers("INTERP", "ys", ys)?);
+    if xs.is_empty() || xs.len() != ys.len() {
+        return Err(Error::new("INTERP xs and ys must be non-empty and of the same length", None));
+    }
+    if xs.windows(2).any(|w| w[0] >= w[1]) {
+        return Err(Error::new("INTERP xs must be in increasing order", None));
+    }
+    if !matches!(mode, "linear" | "step") {
+        return Err(Error::new(format!("INTERP mode must be \"linear\" or \"step\", got '{}'", mode), None));
+    }
+    if matches!(x, Value::Null) {
+        return Ok(Value::Null);
+    }
+    let x = number("INTERP", "x", x)?;
+    // Points at or before x
+    let i = xs.partition_point(|p| *p <= x);
+    let y = if i == 0 {
+        ys[0]
+    } else if i == xs.len() || mode == "step" {
+        ys[i - 1]
+    } else {
+        let t = (x - xs[i - 1]) / (xs[i] - xs[i - 1]);
+        ys[i - 1] + (ys[i] - ys[i - 1]) * t
+    };
+    Ok(Value::Number(y))
+}
@@ -3,6 +3,7 @@ use crate::types::Value;
 
 pub fn exec_json(name: &str, args: &[Value]) -> Result<Value, Error> {
     match name {
+        "CONST" => crate::constants::exec_const(args),
         "DIG" => {
             // DIG(json_obj, path_array, [default_value])
             if args.len() < 2 {
@@ -43,7 +44,7 @@ pub fn exec_json(name: &str, args: &[Value]) -> Result<Value, Error> {
                             }
                             Value::Number(n) => {
                                 if let serde_json::Value::Array(arr) = cur {
-                                    let idx = if n.is_finite() { n.floor() as isize } else { -1 };
+                                    let idx = crate::runtime::utils::to_index(*n, "DIG index")?;
                                     if idx >= 0 && (idx as usize) < arr.len() {
                                         cur = &arr[idx as usize];
                                     } else {
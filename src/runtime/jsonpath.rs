@@ -59,6 +59,13 @@ fn value_to_json(value: &Value) -> Result<serde_json::Value, Error> {
             serde_json::from_str(json_str)
                 .map_err(|e| Error::new(format!("Invalid JSON string: {}", e), None))
         }
+        Value::Map(map) => {
+            let mut object = serde_json::Map::new();
+            for (key, value) in map {
+                object.insert(key.clone(), value_to_json(value)?);
+            }
+            Ok(serde_json::Value::Object(object))
+        }
     }
 }
 
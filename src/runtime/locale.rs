@@ -0,0 +1,183 @@
+use crate::error::Error;
+use crate::types::Value;
+
+/// Decimal and grouping separators of a locale
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Separators {
+    pub decimal: char,
+    pub group: char,
+}
+
+const fn seps(decimal: char, group: char) -> Separators {
+    Separators { decimal, group }
+}
+
+const POINT_COMMA: Separators = seps('.', ',');
+const COMMA_POINT: Separators = seps(',', '.');
+const COMMA_SPACE: Separators = seps(',', ' ');
+
+/// Regions whose separators differ from their language's default
+const REGIONS: &[(&str, Separators)] = &[
+    ("es-MX", POINT_COMMA),
+    ("es-US", POINT_COMMA),
+    ("de-CH", seps('.', '\'')),
+    ("it-CH", seps('.', '\'')),
+    ("pt-PT", COMMA_SPACE),
+];
+
+const LANGUAGES: &[(&str, Separators)] = &[
+    ("en", POINT_COMMA),
+    ("ja", POINT_COMMA),
+    ("zh", POINT_COMMA),
+    ("ko", POINT_COMMA),
+    ("hi", POINT_COMMA),
+    ("th", POINT_COMMA),
+    ("he", POINT_COMMA),
+    ("de", COMMA_POINT),
+    ("es", COMMA_POINT),
+    ("it", COMMA_POINT),
+    ("pt", COMMA_POINT),
+    ("nl", COMMA_POINT),
+    ("id", COMMA_POINT),
+    ("tr", COMMA_POINT),
+    ("da", COMMA_POINT),
+    ("ro", COMMA_POINT),
+    ("fr", COMMA_SPACE),
+    ("ru", COMMA_SPACE),
+    ("uk", COMMA_SPACE),
+    ("pl", COMMA_SPACE),
+    ("cs", COMMA_SPACE),
+    ("sk", COMMA_SPACE),
+    ("hu", COMMA_SPACE),
+    ("sv", COMMA_SPACE),
+    ("nb", COMMA_SPACE),
+    ("no", COMMA_SPACE),
+    ("fi", COMMA_SPACE),
+];
+
+/// Separators for a BCP 47 style tag (`de-DE`, `fr`, `es_MX`); `None` for unknown languages
+pub fn separators(tag: &str) -> Option<Separators> {
+    let tag = tag.replace('_', "-");
+    if let Some((_, s)) = REGIONS.iter().find(|(t, _)| t.eq_ignore_ascii_case(&tag)) {
+        return Some(*s);
+    }
+    let language = tag.split('-').next().unwrap_or("");
+    LANGUAGES.iter().find(|(l, _)| l.eq_ignore_ascii_case(language)).map(|(_, s)| *s)
+}
+
+/// Singular and plural names of days, hours, minutes and seconds
+pub type DurationUnits = [(&'static str, &'static str); 4];
+
+const DURATION_UNITS: &[(&str, DurationUnits)] = &[
+    ("en", [("day", "days"), ("hour", "hours"), ("minute", "minutes"), ("second", "seconds")]),
+    ("es", [("día", "días"), ("hora", "horas"), ("minuto", "minutos"), ("segundo", "segundos")]),
+    ("pt", [("dia", "dias"), ("hora", "horas"), ("minuto", "minutos"), ("segundo", "segundos")]),
+    ("de", [("Tag", "Tage"), ("Stunde", "Stunden"), ("Minute", "Minuten"), ("Sekunde", "Sekunden")]),
+    ("fr", [("jour", "jours"), ("heure", "heures"), ("minute", "minutes"), ("seconde", "secondes")]),
+    ("it", [("giorno", "giorni"), ("ora", "ore"), ("minuto", "minuti"), ("secondo", "secondi")]),
+];
+
+/// Duration unit names for the language of `tag`
+pub fn duration_units(tag: &str) -> Option<&'static DurationUnits> {
+    let language = tag.split(['-', '_']).next().unwrap_or("");
+    DURATION_UNITS.iter().find(|(l, _)| l.eq_ignore_ascii_case(language)).map(|(_, units)| units)
+}
+
+/// Lowercase `text` for the language of `tag`. Turkish and Azerbaijani map `I` to
+/// dotless `ı` and `İ` to `i`; every other language uses the Unicode default.
+pub fn lowercase(text: &str, tag: &str) -> String {
+    let language = tag.split(['-', '_']).next().unwrap_or("");
+    if language.eq_ignore_ascii_case("tr") || language.eq_ignore_ascii_case("az") {
+        text.chars()
+            .map(|c| match c {
+                'I' => "ı".to_string(),
+                'İ' => "i".to_string(),
+                other => other.to_lowercase().to_string(),
+            })
+            .collect()
+    } else {
+        text.to_lowercase()
+    }
+}
+
+/// Read a number written for people: currency symbols or codes, grouping separators,
+/// a leading or trailing sign, accounting parentheses and a trailing `%` are accepted.
+pub fn parse_number(text: &str, separators: Separators) -> Option<f64> {
+    let mut s = text.trim().to_string();
+    let mut negative = false;
+    if s.starts_with('(') && s.ends_with(')') {
+        negative = true;
+        s = s[1..s.len() - 1].to_string();
+    }
+
+    // Longest first so "CA$" goes before "$"
+    let mut symbols: Vec<&str> = crate::runtime::currency::CURRENCIES
+        .iter()
+        .flat_map(|c| [c.symbol, c.code])
+        .collect();
+    symbols.sort_by_key(|sym| std::cmp::Reverse(sym.len()));
+    for symbol in symbols {
+        s = s.replace(symbol, "");
+    }
+
+    let mut s = s.trim().to_string();
+    if let Some(rest) = s.strip_suffix('-') {
+        negative = !negative;
+        s = rest.to_string();
+    } else if let Some(rest) = s.strip_prefix('-') {
+        negative = !negative;
+        s = rest.to_string();
+    } else if let Some(rest) = s.strip_prefix('+') {
+        s = rest.to_string();
+    }
+    let percent = s.trim_end().ends_with('%');
+    if percent {
+        s = s.trim_end().trim_end_matches('%').to_string();
+    }
+
+    let mut plain = String::with_capacity(s.len());
+    let mut seen_decimal = false;
+    for c in s.trim().chars() {
+        match c {
+            '0'..='9' => plain.push(c),
+            c if c == separators.decimal && !seen_decimal => {
+                seen_decimal = true;
+                plain.push('.');
+            }
+            // Spaces group digits in many locales, including the no-break variants
+            c if c == separators.group || c == ' ' || c == '\u{a0}' || c == '\u{202f}' => {
+                if seen_decimal {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+    }
+    if !plain.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let n: f64 = plain.parse().ok()?;
+    let n = if percent { n / 100.0 } else { n };
+    Some(if negative { -n } else { n })
+}
+
+/// PARSENUMBER(text, [locale]) -> number. The locale defaults to the evaluation's
+/// `locale` option, then `en-US`.
+pub fn exec_parse_number(args: &[Value]) -> Result<Value, Error> {
+    let text = match args.first() {
+        Some(Value::String(s)) => s,
+        Some(Value::Number(n)) | Some(Value::Currency(n)) => return Ok(Value::Number(*n)),
+        _ => return Err(Error::new("PARSENUMBER expects text, [locale]", None)),
+    };
+    let tag = match args.get(1) {
+        Some(Value::String(tag)) => tag.clone(),
+        Some(_) => return Err(Error::new("PARSENUMBER locale must be a string like \"de-DE\"", None)),
+        None => crate::context::with_options(|opts| opts.locale.clone()).unwrap_or_else(|| "en-US".to_string()),
+    };
+    let separators = separators(&tag)
+        .ok_or_else(|| Error::new(format!("PARSENUMBER does not know the number format of locale '{}'", tag), None))?;
+    parse_number(text, separators)
+        .map(Value::Number)
+        .ok_or_else(|| Error::new(format!("PARSENUMBER cannot read '{}' as a number for locale {}", text, tag), None))
+}
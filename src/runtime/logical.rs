@@ -10,6 +10,28 @@ pub fn exec_logical(name: &str, args: &[Value]) -> Result<Value, Error> {
             let cond = args[0].as_bool().ok_or_else(|| Error::new("Ternary condition must be boolean", None))?;
             Ok(if cond { args[1].clone() } else { args[2].clone() })
         }
+        "FEATURE" => crate::features::exec_feature(args),
+        "DTABLE" => super::dtable::exec_dtable(args),
+        // COALESCE(a, b, ...) -> the first argument that is not null
+        "COALESCE" => Ok(args.iter().find(|v| !matches!(v, Value::Null)).cloned().unwrap_or(Value::Null)),
+        "APPROXEQ" => {
+            if args.len() < 2 || args.len() > 3 {
+                return Err(Error::new("APPROXEQ expects (a, b, [epsilon])", None));
+            }
+            let num = |v: &Value| match v {
+                Value::Number(n) | Value::Currency(n) => Ok(*n),
+                _ => Err(Error::new("APPROXEQ expects numbers", None)),
+            };
+            let (a, b) = (num(&args[0])?, num(&args[1])?);
+            let eps = match args.get(2) {
+                Some(v) => num(v)?,
+                None => crate::context::epsilon().unwrap_or(crate::runtime::utils::DEFAULT_EPSILON),
+            };
+            if !eps.is_finite() || eps < 0.0 {
+                return Err(Error::new("APPROXEQ epsilon must be a non-negative number", None));
+            }
+            Ok(Value::Boolean(crate::runtime::utils::approx_eq(a, b, eps)))
+        }
         "XOR" => {
             if args.len() != 2 { return Err(Error::new("XOR expects 2 arguments", None)); }
             let a = match &args[0] { Value::Boolean(b) => *b, Value::Number(n) => *n != 0.0, _ => false };
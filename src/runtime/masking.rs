@@ -0,0 +1,122 @@
+//! Masking and redaction of personal data in user-facing strings.
+//!
+//! All three functions pass `null` through, so optional fields need no `IF` guard.
+
+use regex::Regex;
+
+use crate::error::Error;
+use crate::runtime::utils::format_number;
+use crate::types::Value;
+
+lazy_static::lazy_static! {
+    static ref EMAIL: Regex = Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap();
+    // Seven or more digits, possibly split by spaces or dashes: card, account and phone numbers
+    static ref LONG_NUMBER: Regex = Regex::new(r"\+?\d(?:[ -]?\d){6,}").unwrap();
+}
+
+fn text_arg(name: &str, arg: &Value) -> Result<Option<String>, Error> {
+    match arg {
+        Value::Null => Ok(None),
+        Value::String(s) => Ok(Some(s.clone())),
+        Value::Number(n) | Value::Currency(n) if n.is_finite() => Ok(Some(format_number(*n))),
+        other => Err(Error::new(format!("{} expects text, got {:?}", name, other), None)),
+    }
+}
+
+fn mask_char_arg(arg: Option<&Value>) -> Result<char, Error> {
+    match arg {
+        None => Ok('*'),
+        Some(Value::String(s)) if s.chars().count() == 1 => Ok(s.chars().next().unwrap_or('*')),
+        Some(_) => Err(Error::new("MASK mask character must be a single character", None)),
+    }
+}
+
+/// Apply a pattern: `X` shows the next letter or digit of `text`, `#` hides it and
+/// anything else is copied. Separators in `text` are skipped.
+fn apply_pattern(text: &str, pattern: &str, mask: char) -> Result<String, Error> {
+    let source: Vec<char> = text.chars().filter(|c| c.is_alphanumeric()).collect();
+    let slots = pattern.chars().filter(|c| *c == 'X' || *c == '#').count();
+    if slots != source.len() {
+        return Err(Error::new(
+            format!("MASK pattern has {} placeholders but the value has {} letters and digits", slots, source.len()),
+            None,
+        ));
+    }
+    let mut source = source.into_iter();
+    Ok(pattern
+        .chars()
+        .map(|c| match c {
+            'X' => source.next().unwrap_or(mask),
+            '#' => {
+                source.next();
+                mask
+            }
+            literal => literal,
+        })
+        .collect())
+}
+
+/// MASK(value, [pattern | keep_last], [mask_char]) -> masked text.
+///
+/// With a number (4 by default) every character but the last `keep_last` is replaced:
+/// `MASK('4111111111111111')` -> `************1111`. With a pattern such as
+/// `"####-####-####-XXXX"`, `X` reveals a letter or digit, `#` hides one and other
+/// characters are copied, giving `****-****-****-1111`.
+pub fn exec_mask(args: &[Value]) -> Result<Value, Error> {
+    if args.is_empty() || args.len() > 3 {
+        return Err(Error::new("MASK expects value, [pattern or keep_last], [mask_char]", None));
+    }
+    let Some(text) = text_arg("MASK", &args[0])? else {
+        return Ok(Value::Null);
+    };
+    let mask = mask_char_arg(args.get(2))?;
+    match args.get(1) {
+        Some(Value::String(pattern)) => Ok(Value::String(apply_pattern(&text, pattern, mask)?)),
+        None | Some(Value::Number(_)) => {
+            let keep = match args.get(1) {
+                Some(Value::Number(n)) => crate::runtime::utils::to_count(*n, "MASK keep_last")?,
+                _ => 4,
+            };
+            let len = text.chars().count();
+            let hidden = len.saturating_sub(keep);
+            Ok(Value::String(text.chars().enumerate().map(|(i, c)| if i < hidden { mask } else { c }).collect()))
+        }
+        Some(_) => Err(Error::new("MASK expects a pattern string or a number of characters to keep", None)),
+    }
+}
+
+/// `jane.doe@example.com` -> `j***@example.com`; the domain is kept so support staff
+/// can still tell providers apart, and the local part's length is not revealed
+fn redact_email_address(email: &str) -> String {
+    match email.rsplit_once('@') {
+        Some((local, domain)) if !local.is_empty() && !domain.is_empty() => {
+            let first = local.chars().next().map(String::from).unwrap_or_default();
+            format!("{}***@{}", first, domain)
+        }
+        _ => "***".to_string(),
+    }
+}
+
+/// REDACTEMAIL(email) -> the address with all but the first letter of the name hidden
+pub fn exec_redact_email(args: &[Value]) -> Result<Value, Error> {
+    let [email] = args else {
+        return Err(Error::new("REDACTEMAIL expects an email address", None));
+    };
+    Ok(text_arg("REDACTEMAIL", email)?.map_or(Value::Null, |e| Value::String(redact_email_address(e.trim()))))
+}
+
+/// REDACT(text, [replacement]) -> `text` with email addresses and long numbers (seven
+/// or more digits, e.g. cards, accounts, phones) replaced by `[redacted]`
+pub fn exec_redact(args: &[Value]) -> Result<Value, Error> {
+    let (text, replacement) = match args {
+        [text] => (text, "[redacted]"),
+        [text, Value::String(replacement)] => (text, replacement.as_str()),
+        _ => return Err(Error::new("REDACT expects text, [replacement]", None)),
+    };
+    let Some(text) = text_arg("REDACT", text)? else {
+        return Ok(Value::Null);
+    };
+    let text = EMAIL.replace_all(&text, regex::NoExpand(replacement));
+    let text = LONG_NUMBER.replace_all(&text, regex::NoExpand(replacement));
+    Ok(Value::String(text.into_owned()))
+}
@@ -4,6 +4,13 @@ use crate::runtime::evaluation::{eval, eval_with_vars};
 use crate::types::Value;
 use std::collections::{BTreeSet, HashMap};
 
+/// Names accepted by [`exec_array_method`]; the lambda methods are dispatched separately
+pub const ARRAY_METHODS: &[&str] = &[
+    "length", "len", "count", "first", "last", "reverse", "unique", "sort", "sum", "avg", "average", "min", "max",
+    "join", "contains", "includes", "flatten", "compact", "merge", "push", "insert", "remove_at", "replace_at", "set",
+    "tally",
+];
+
 /// Handle array method calls
 pub fn exec_array_method(
     name: &str,
@@ -105,54 +112,30 @@ pub fn exec_array_method(
             Ok(Value::Number(total / recv_array.len() as f64))
         }
 
-        "min" => {
+        "min" | "max" => {
             if recv_array.is_empty() {
                 return Ok(Value::Null);
             }
-            let mut min_val = None;
-            for val in recv_array {
-                match val {
-                    Value::Number(n) => {
-                        min_val = Some(match min_val {
-                            None => *n,
-                            Some(current) => n.min(current),
-                        });
-                    }
-                    Value::Currency(c) => {
-                        min_val = Some(match min_val {
-                            None => *c,
-                            Some(current) => c.min(current),
-                        });
-                    }
-                    _ => return Err(Error::new("min method expects numeric array", None)),
-                }
-            }
-            Ok(Value::Number(min_val.unwrap_or(0.0)))
-        }
-
-        "max" => {
-            if recv_array.is_empty() {
-                return Ok(Value::Null);
+            let want_max = lname == "max";
+            // All dates: the earliest/latest date; otherwise every item must be numeric
+            if recv_array.iter().all(|v| matches!(v, Value::DateTime(_))) {
+                let stamps = recv_array.iter().filter_map(|v| if let Value::DateTime(ts) = v { Some(*ts) } else { None });
+                let ts = if want_max { stamps.max() } else { stamps.min() };
+                return Ok(ts.map(Value::DateTime).unwrap_or(Value::Null));
             }
-            let mut max_val = None;
+            let mut best: Option<f64> = None;
             for val in recv_array {
-                match val {
-                    Value::Number(n) => {
-                        max_val = Some(match max_val {
-                            None => *n,
-                            Some(current) => n.max(current),
-                        });
-                    }
-                    Value::Currency(c) => {
-                        max_val = Some(match max_val {
-                            None => *c,
-                            Some(current) => c.max(current),
-                        });
-                    }
-                    _ => return Err(Error::new("max method expects numeric array", None)),
-                }
+                let n = match val {
+                    Value::Number(n) | Value::Currency(n) => *n,
+                    _ => return Err(Error::new(format!("{} method expects a numeric or date array", lname), None)),
+                };
+                best = Some(match best {
+                    None => n,
+                    Some(current) if want_max => n.max(current),
+                    Some(current) => n.min(current),
+                });
             }
-            Ok(Value::Number(max_val.unwrap_or(0.0)))
+            Ok(Value::Number(best.unwrap_or(0.0)))
         }
 
         "join" => {
@@ -174,13 +157,16 @@ pub fn exec_array_method(
                 .iter()
                 .map(|v| match v {
                     Value::String(s) => Ok(s.clone()),
-                    Value::Number(n) => Ok(n.to_string()),
+                    Value::Number(n) => Ok(crate::runtime::utils::format_number(*n)),
+                    Value::Currency(c) => Ok(crate::runtime::currency::display(*c)),
                     Value::Boolean(b) => Ok(b.to_string()),
                     _ => Err(Error::new("join method cannot convert value to string", None)),
                 })
                 .collect();
 
-            Ok(Value::String(string_vals?.join(&separator)))
+            let joined = string_vals?.join(&separator);
+            crate::context::check_string_len(joined.chars().count())?;
+            Ok(Value::String(joined))
         }
 
         "contains" | "includes" => {
@@ -211,6 +197,23 @@ pub fn exec_array_method(
             Ok(Value::Array(flatten_recursive(recv_array)))
         }
 
+        "tally" => {
+            // Keys are kept sorted, so equal inputs always render the same object
+            let mut counts = serde_json::Map::new();
+            for val in recv_array {
+                let key = match val {
+                    Value::Null => continue,
+                    Value::String(s) => s.clone(),
+                    Value::Number(n) | Value::Currency(n) => crate::runtime::utils::format_number(*n),
+                    Value::Boolean(b) => b.to_string(),
+                    other => return Err(Error::new(format!("tally counts strings, numbers and booleans, got {:?}", other), None)),
+                };
+                let count = counts.get(&key).and_then(serde_json::Value::as_u64).unwrap_or(0);
+                counts.insert(key, serde_json::Value::from(count + 1));
+            }
+            Ok(Value::Json(serde_json::Value::Object(counts).to_string()))
+        }
+
         "compact" => {
             let compacted: Vec<Value> = recv_array
                 .iter()
@@ -254,9 +257,76 @@ pub fn exec_array_method(
             Ok(Value::Array(result))
         }
 
+        // Non-mutating updates: each returns a new array and leaves the receiver as is
+        "push" => {
+            if args_expr.is_empty() {
+                return Err(Error::new("push method expects at least 1 argument", None));
+            }
+            crate::context::check_array_len(recv_array.len() + args_expr.len())?;
+            let mut result = Vec::with_capacity(recv_array.len() + args_expr.len());
+            result.extend_from_slice(recv_array);
+            for arg_expr in args_expr {
+                result.push(eval_arg(arg_expr, base_vars)?);
+            }
+            Ok(Value::Array(result))
+        }
+
+        "insert" => {
+            if args_expr.len() != 2 {
+                return Err(Error::new("insert method expects 2 arguments: index, value", None));
+            }
+            let len = recv_array.len();
+            let idx = position(&eval_arg(&args_expr[0], base_vars)?, "insert index")?;
+            // Negative positions count from the end, so -1 appends
+            let at = if idx < 0 { (len as isize).saturating_add(1).saturating_add(idx) } else { idx };
+            if at < 0 || at as usize > len {
+                return Err(Error::new(format!("insert index {} is out of bounds for {} elements", idx, len), None));
+            }
+            crate::context::check_array_len(len + 1)?;
+            let mut result = recv_array.clone();
+            result.insert(at as usize, eval_arg(&args_expr[1], base_vars)?);
+            Ok(Value::Array(result))
+        }
+
+        "remove_at" => {
+            if args_expr.len() != 1 {
+                return Err(Error::new("remove_at method expects 1 argument: index", None));
+            }
+            let idx = position(&eval_arg(&args_expr[0], base_vars)?, "remove_at index")?;
+            let at = crate::runtime::utils::clamp_index(recv_array.len(), idx)
+                .ok_or_else(|| Error::new("Index out of bounds", None))?;
+            let mut result = recv_array.clone();
+            result.remove(at);
+            Ok(Value::Array(result))
+        }
+
+        "set" | "replace_at" => {
+            if args_expr.len() != 2 {
+                return Err(Error::new(format!("{} method expects 2 arguments: index, value", lname), None));
+            }
+            let idx = position(&eval_arg(&args_expr[0], base_vars)?, &format!("{} index", lname))?;
+            let at = crate::runtime::utils::clamp_index(recv_array.len(), idx)
+                .ok_or_else(|| Error::new("Index out of bounds", None))?;
+            let mut result = recv_array.clone();
+            result[at] = eval_arg(&args_expr[1], base_vars)?;
+            Ok(Value::Array(result))
+        }
+
         _ => Err(Error::new(
             format!("Unknown array method: {}", name),
             None,
         )),
     }
 }
+
+fn eval_arg(expr: &Expr, base_vars: Option<&HashMap<String, Value>>) -> Result<Value, Error> {
+    match base_vars {
+        Some(vars) => eval_with_vars(expr, vars),
+        None => eval(expr),
+    }
+}
+
+fn position(value: &Value, what: &str) -> Result<isize, Error> {
+    let n = value.as_number().ok_or_else(|| Error::new(format!("{} must be number", what), None))?;
+    crate::runtime::utils::to_index(n, what)
+}
@@ -1,6 +1,12 @@
 use crate::error::Error;
 use crate::types::Value;
 
+/// Names accepted by [`exec_conversion_method`]
+pub const CONVERSION_METHODS: &[&str] = &[
+    "to_s", "to_string", "to_i", "to_int", "to_f", "to_float", "to_a", "to_array", "to_json", "to_bool", "to_boolean",
+    "to_currency", "to_number_or_null", "to_date_or_null",
+];
+
 /// Handle conversion method calls for all types (Ruby-style)
 pub fn exec_conversion_method(name: &str, recv: &Value) -> Result<Value, Error> {
     let lname = name.to_lowercase();
@@ -12,6 +18,9 @@ pub fn exec_conversion_method(name: &str, recv: &Value) -> Result<Value, Error>
         "to_a" | "to_array" => to_array(recv),
         "to_json" => to_json(recv),
         "to_bool" | "to_boolean" => to_boolean(recv),
+        "to_currency" => crate::runtime::type_casting::cast_value(recv.clone(), &crate::ast::TypeName::Currency),
+        "to_number_or_null" => Ok(crate::runtime::type_casting::try_cast(recv.clone(), &crate::ast::TypeName::Float)),
+        "to_date_or_null" => Ok(crate::runtime::type_casting::try_cast(recv.clone(), &crate::ast::TypeName::DateTime)),
         _ => Err(Error::new(format!("Unknown conversion method: {}", name), None)),
     }
 }
@@ -21,15 +30,9 @@ fn to_string(value: &Value) -> Result<Value, Error> {
     let result = match value {
         Value::Null => "".to_string(),
         Value::String(s) => s.clone(),
-        Value::Number(n) => {
-            if n.fract() == 0.0 {
-                format!("{:.0}", n)
-            } else {
-                n.to_string()
-            }
-        }
+        Value::Number(n) => crate::runtime::utils::format_number(*n),
         Value::Boolean(b) => b.to_string(),
-        Value::Currency(c) => format!("{:.2}", c),
+        Value::Currency(c) => crate::runtime::currency::display(*c),
         Value::Array(arr) => {
             let string_parts: Result<Vec<String>, Error> = arr
                 .iter()
@@ -41,6 +44,7 @@ fn to_string(value: &Value) -> Result<Value, Error> {
             format!("[{}]", string_parts?.join(", "))
         }
         Value::Json(s) => s.clone(),
+        Value::Map(_) => value_to_json_value(value)?.to_string(),
         Value::DateTime(dt) => dt.to_string(),
     };
     Ok(Value::String(result))
@@ -57,10 +61,10 @@ fn to_int(value: &Value) -> Result<Value, Error> {
             s.trim().parse::<f64>().unwrap_or(0.0).trunc()
         }
         Value::Array(arr) => arr.len() as f64,
-        Value::Json(_) => 1.0, // JSON objects are truthy
+        Value::Json(_) | Value::Map(_) => 1.0, // JSON objects are truthy
         Value::DateTime(_) => 1.0, // DateTime values are truthy
     };
-    Ok(Value::Number(result))
+    Ok(Value::Number(crate::context::check_integer(result, "to_i")?))
 }
 
 /// Convert any value to float
@@ -74,7 +78,7 @@ fn to_float(value: &Value) -> Result<Value, Error> {
             s.trim().parse::<f64>().unwrap_or(0.0)
         }
         Value::Array(arr) => arr.len() as f64,
-        Value::Json(_) => 1.0,
+        Value::Json(_) | Value::Map(_) => 1.0,
         Value::DateTime(_) => 1.0,
     };
     Ok(Value::Number(result))
@@ -99,6 +103,7 @@ fn to_json(value: &Value) -> Result<Value, Error> {
     let json_str = match value {
         Value::Null => "{}".to_string(),
         Value::Json(s) => s.clone(),
+        Value::Map(_) => value_to_json_value(value)?.to_string(),
         Value::String(s) => {
             let json_val = serde_json::Value::String(s.clone());
             serde_json::to_string(&json_val)
@@ -152,14 +157,14 @@ fn to_boolean(value: &Value) -> Result<Value, Error> {
         Value::Currency(c) => *c != 0.0,
         Value::String(s) => !s.is_empty(),
         Value::Array(arr) => !arr.is_empty(),
-        Value::Json(_) => true,
+        Value::Json(_) | Value::Map(_) => true,
         Value::DateTime(_) => true,
     };
     Ok(Value::Boolean(result))
 }
 
 /// Helper function to convert Value to serde_json::Value
-fn value_to_json_value(value: &Value) -> Result<serde_json::Value, Error> {
+pub(crate) fn value_to_json_value(value: &Value) -> Result<serde_json::Value, Error> {
     match value {
         Value::Null => Ok(serde_json::Value::Null),
         Value::String(s) => Ok(serde_json::Value::String(s.clone())),
@@ -186,5 +191,12 @@ fn value_to_json_value(value: &Value) -> Result<serde_json::Value, Error> {
                 .map_err(|e| Error::new(format!("Invalid JSON: {}", e), None))
         }
         Value::DateTime(dt) => Ok(serde_json::Value::String(dt.to_string())),
+        Value::Map(map) => {
+            let mut object = serde_json::Map::new();
+            for (key, value) in map {
+                object.insert(key.clone(), value_to_json_value(value)?);
+            }
+            Ok(serde_json::Value::Object(object))
+        }
     }
 }
\ No newline at end of file
@@ -1,10 +1,11 @@
-use crate::ast::Expr;
+use crate::ast::{Expr, UnaryOp};
 use crate::custom::FunctionRegistry;
 use crate::error::Error;
+use crate::runtime::evaluation::higher_order::{bind_lambda, check_lambda};
 use crate::runtime::evaluation::{eval_with_vars, eval_with_vars_and_custom};
 use crate::types::Value;
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 
 /// Handle FILTER method call (higher-order function)
 pub fn exec_filter(
@@ -22,6 +23,7 @@ pub fn exec_filter(
     }
     
     let lambda_expr = &args_expr[0];
+    check_lambda("filter", lambda_expr, 2)?;
     let param_name = if args_expr.len() > 1 {
         match &args_expr[1] {
             Expr::StringLit(s) => s.clone(),
@@ -34,9 +36,9 @@ pub fn exec_filter(
     let mut filtered = Vec::with_capacity(recv_array.len());
     let mut vars = base_vars.cloned().unwrap_or_default();
 
-    for item in recv_array {
-        vars.insert(param_name.clone(), item.clone());
-        let result = eval_with_vars(lambda_expr, &vars)?;
+    for (i, item) in recv_array.iter().enumerate() {
+        let body = bind_lambda(lambda_expr, &[&param_name], [item.clone(), Value::Number(i as f64)], &mut vars);
+        let result = eval_with_vars(body, &vars)?;
         if let Value::Boolean(true) = result {
             filtered.push(item.clone());
         }
@@ -50,7 +52,7 @@ pub fn exec_filter_with_custom(
     recv: &Value,
     args_expr: &[Expr],
     base_vars: Option<&HashMap<String, Value>>,
-    custom_registry: &Arc<RwLock<FunctionRegistry>>,
+    custom_registry: &Arc<FunctionRegistry>,
 ) -> Result<Value, Error> {
     let recv_array = match recv {
         Value::Array(a) => a,
@@ -62,6 +64,7 @@ pub fn exec_filter_with_custom(
     }
 
     let lambda_expr = &args_expr[0];
+    check_lambda("filter", lambda_expr, 2)?;
     let param_name = if args_expr.len() > 1 {
         match &args_expr[1] {
             Expr::StringLit(s) => s.clone(),
@@ -74,9 +77,9 @@ pub fn exec_filter_with_custom(
     let mut filtered = Vec::with_capacity(recv_array.len());
     let mut vars = base_vars.cloned().unwrap_or_default();
 
-    for item in recv_array {
-        vars.insert(param_name.clone(), item.clone());
-        let result = eval_with_vars_and_custom(lambda_expr, &vars, custom_registry)?;
+    for (i, item) in recv_array.iter().enumerate() {
+        let body = bind_lambda(lambda_expr, &[&param_name], [item.clone(), Value::Number(i as f64)], &mut vars);
+        let result = eval_with_vars_and_custom(body, &vars, custom_registry)?;
         if let Value::Boolean(true) = result {
             filtered.push(item.clone());
         }
@@ -101,6 +104,7 @@ pub fn exec_map(
     }
     
     let lambda_expr = &args_expr[0];
+    check_lambda("map", lambda_expr, 2)?;
     let param_name = if args_expr.len() > 1 {
         match &args_expr[1] {
             Expr::StringLit(s) => s.clone(),
@@ -113,9 +117,9 @@ pub fn exec_map(
     let mut mapped = Vec::with_capacity(recv_array.len());
     let mut vars = base_vars.cloned().unwrap_or_default();
 
-    for item in recv_array {
-        vars.insert(param_name.clone(), item.clone());
-        let result = eval_with_vars(lambda_expr, &vars)?;
+    for (i, item) in recv_array.iter().enumerate() {
+        let body = bind_lambda(lambda_expr, &[&param_name], [item.clone(), Value::Number(i as f64)], &mut vars);
+        let result = eval_with_vars(body, &vars)?;
         mapped.push(result);
     }
 
@@ -127,7 +131,7 @@ pub fn exec_map_with_custom(
     recv: &Value,
     args_expr: &[Expr],
     base_vars: Option<&HashMap<String, Value>>,
-    custom_registry: &Arc<RwLock<FunctionRegistry>>,
+    custom_registry: &Arc<FunctionRegistry>,
 ) -> Result<Value, Error> {
     let recv_array = match recv {
         Value::Array(a) => a,
@@ -139,6 +143,7 @@ pub fn exec_map_with_custom(
     }
 
     let lambda_expr = &args_expr[0];
+    check_lambda("map", lambda_expr, 2)?;
     let param_name = if args_expr.len() > 1 {
         match &args_expr[1] {
             Expr::StringLit(s) => s.clone(),
@@ -151,9 +156,9 @@ pub fn exec_map_with_custom(
     let mut mapped = Vec::with_capacity(recv_array.len());
     let mut vars = base_vars.cloned().unwrap_or_default();
 
-    for item in recv_array {
-        vars.insert(param_name.clone(), item.clone());
-        let result = eval_with_vars_and_custom(lambda_expr, &vars, custom_registry)?;
+    for (i, item) in recv_array.iter().enumerate() {
+        let body = bind_lambda(lambda_expr, &[&param_name], [item.clone(), Value::Number(i as f64)], &mut vars);
+        let result = eval_with_vars_and_custom(body, &vars, custom_registry)?;
         mapped.push(result);
     }
 
@@ -176,6 +181,7 @@ pub fn exec_find(
     }
     
     let lambda_expr = &args_expr[0];
+    check_lambda("find", lambda_expr, 2)?;
     let param_name = if args_expr.len() > 1 {
         match &args_expr[1] {
             Expr::StringLit(s) => s.clone(),
@@ -187,9 +193,9 @@ pub fn exec_find(
     
     let mut vars = base_vars.cloned().unwrap_or_default();
     
-    for item in recv_array {
-        vars.insert(param_name.clone(), item.clone());
-        let result = eval_with_vars(lambda_expr, &vars)?;
+    for (i, item) in recv_array.iter().enumerate() {
+        let body = bind_lambda(lambda_expr, &[&param_name], [item.clone(), Value::Number(i as f64)], &mut vars);
+        let result = eval_with_vars(body, &vars)?;
         if let Value::Boolean(true) = result {
             return Ok(item.clone());
         }
@@ -203,7 +209,7 @@ pub fn exec_find_with_custom(
     recv: &Value,
     args_expr: &[Expr],
     base_vars: Option<&HashMap<String, Value>>,
-    custom_registry: &Arc<RwLock<FunctionRegistry>>,
+    custom_registry: &Arc<FunctionRegistry>,
 ) -> Result<Value, Error> {
     let recv_array = match recv {
         Value::Array(a) => a,
@@ -215,6 +221,7 @@ pub fn exec_find_with_custom(
     }
     
     let lambda_expr = &args_expr[0];
+    check_lambda("find", lambda_expr, 2)?;
     let param_name = if args_expr.len() > 1 {
         match &args_expr[1] {
             Expr::StringLit(s) => s.clone(),
@@ -226,9 +233,9 @@ pub fn exec_find_with_custom(
     
     let mut vars = base_vars.cloned().unwrap_or_default();
     
-    for item in recv_array {
-        vars.insert(param_name.clone(), item.clone());
-        let result = eval_with_vars_and_custom(lambda_expr, &vars, custom_registry)?;
+    for (i, item) in recv_array.iter().enumerate() {
+        let body = bind_lambda(lambda_expr, &[&param_name], [item.clone(), Value::Number(i as f64)], &mut vars);
+        let result = eval_with_vars_and_custom(body, &vars, custom_registry)?;
         if let Value::Boolean(true) = result {
             return Ok(item.clone());
         }
@@ -253,6 +260,7 @@ pub fn exec_reduce(
     }
     
     let lambda_expr = &args_expr[0];
+    check_lambda("reduce", lambda_expr, 3)?;
     let mut vars = base_vars.cloned().unwrap_or_default();
     let mut accumulator = eval_with_vars(&args_expr[1], &vars)?;
 
@@ -274,10 +282,9 @@ pub fn exec_reduce(
         "acc".to_string()
     };
 
-    for item in recv_array {
-        vars.insert(val_param.clone(), item.clone());
-        vars.insert(acc_param.clone(), accumulator);
-        accumulator = eval_with_vars(lambda_expr, &vars)?;
+    for (i, item) in recv_array.iter().enumerate() {
+        let body = bind_lambda(lambda_expr, &[&acc_param, &val_param], [accumulator, item.clone(), Value::Number(i as f64)], &mut vars);
+        accumulator = eval_with_vars(body, &vars)?;
     }
 
     Ok(accumulator)
@@ -288,7 +295,7 @@ pub fn exec_reduce_with_custom(
     recv: &Value,
     args_expr: &[Expr],
     base_vars: Option<&HashMap<String, Value>>,
-    custom_registry: &Arc<RwLock<FunctionRegistry>>,
+    custom_registry: &Arc<FunctionRegistry>,
 ) -> Result<Value, Error> {
     let recv_array = match recv {
         Value::Array(a) => a,
@@ -300,6 +307,7 @@ pub fn exec_reduce_with_custom(
     }
 
     let lambda_expr = &args_expr[0];
+    check_lambda("reduce", lambda_expr, 3)?;
     let mut vars = base_vars.cloned().unwrap_or_default();
     let mut accumulator = eval_with_vars_and_custom(&args_expr[1], &vars, custom_registry)?;
 
@@ -321,11 +329,250 @@ pub fn exec_reduce_with_custom(
         "acc".to_string()
     };
 
-    for item in recv_array {
-        vars.insert(val_param.clone(), item.clone());
-        vars.insert(acc_param.clone(), accumulator);
-        accumulator = eval_with_vars_and_custom(lambda_expr, &vars, custom_registry)?;
+    for (i, item) in recv_array.iter().enumerate() {
+        let body = bind_lambda(lambda_expr, &[&acc_param, &val_param], [accumulator, item.clone(), Value::Number(i as f64)], &mut vars);
+        accumulator = eval_with_vars_and_custom(body, &vars, custom_registry)?;
     }
 
     Ok(accumulator)
-}
\ No newline at end of file
+}
+/// Name given by an optional string argument at `index`, else `default`
+fn lambda_param(args_expr: &[Expr], index: usize, default: &str) -> String {
+    match args_expr.get(index) {
+        Some(Expr::StringLit(s)) => s.clone(),
+        _ => default.to_string(),
+    }
+}
+
+/// PARTITION: `[items where the lambda is true, the rest]`, each in the original order
+fn partition_by(
+    recv: &Value,
+    args_expr: &[Expr],
+    base_vars: Option<&HashMap<String, Value>>,
+    eval: impl Fn(&Expr, &HashMap<String, Value>) -> Result<Value, Error>,
+) -> Result<Value, Error> {
+    let recv_array = match recv {
+        Value::Array(a) => a,
+        _ => return Err(Error::new("partition called on non-array", None)),
+    };
+    let lambda_expr = args_expr.first().ok_or_else(|| Error::new("partition expects lambda expression", None))?;
+    let param_name = lambda_param(args_expr, 1, "x");
+    check_lambda("partition", lambda_expr, 2)?;
+
+    let mut matching = Vec::new();
+    let mut rest = Vec::new();
+    let mut vars = base_vars.cloned().unwrap_or_default();
+    for (i, item) in recv_array.iter().enumerate() {
+        let body = bind_lambda(lambda_expr, &[&param_name], [item.clone(), Value::Number(i as f64)], &mut vars);
+        if let Value::Boolean(true) = eval(body, &vars)? {
+            matching.push(item.clone());
+        } else {
+            rest.push(item.clone());
+        }
+    }
+    Ok(Value::Array(vec![Value::Array(matching), Value::Array(rest)]))
+}
+
+/// SPLIT_WHEN: consecutive runs of the array, starting a new run before every item
+/// for which the lambda is true. The lambda sees the item as `:x` and the one
+/// before it as `:prev`; the first item always opens the first run.
+fn split_when_by(
+    recv: &Value,
+    args_expr: &[Expr],
+    base_vars: Option<&HashMap<String, Value>>,
+    eval: impl Fn(&Expr, &HashMap<String, Value>) -> Result<Value, Error>,
+) -> Result<Value, Error> {
+    let recv_array = match recv {
+        Value::Array(a) => a,
+        _ => return Err(Error::new("split_when called on non-array", None)),
+    };
+    let lambda_expr = args_expr.first().ok_or_else(|| Error::new("split_when expects lambda expression", None))?;
+    let param_name = lambda_param(args_expr, 1, "x");
+    let prev_name = lambda_param(args_expr, 2, "prev");
+    check_lambda("split_when", lambda_expr, 2)?;
+
+    let mut runs = Vec::new();
+    let mut current: Vec<Value> = Vec::new();
+    let mut vars = base_vars.cloned().unwrap_or_default();
+    for item in recv_array {
+        if let Some(prev) = current.last() {
+            let body = bind_lambda(lambda_expr, &[&param_name, &prev_name], [item.clone(), prev.clone()], &mut vars);
+            if let Value::Boolean(true) = eval(body, &vars)? {
+                runs.push(Value::Array(std::mem::take(&mut current)));
+            }
+        }
+        current.push(item.clone());
+    }
+    if !current.is_empty() {
+        runs.push(Value::Array(current));
+    }
+    Ok(Value::Array(runs))
+}
+
+/// Handle PARTITION method call (higher-order function)
+pub fn exec_partition(
+    recv: &Value,
+    args_expr: &[Expr],
+    base_vars: Option<&HashMap<String, Value>>,
+) -> Result<Value, Error> {
+    partition_by(recv, args_expr, base_vars, eval_with_vars)
+}
+
+/// Handle PARTITION method call with custom function support
+pub fn exec_partition_with_custom(
+    recv: &Value,
+    args_expr: &[Expr],
+    base_vars: Option<&HashMap<String, Value>>,
+    custom_registry: &Arc<FunctionRegistry>,
+) -> Result<Value, Error> {
+    partition_by(recv, args_expr, base_vars, |e, vars| eval_with_vars_and_custom(e, vars, custom_registry))
+}
+
+/// Handle SPLIT_WHEN method call (higher-order function)
+pub fn exec_split_when(
+    recv: &Value,
+    args_expr: &[Expr],
+    base_vars: Option<&HashMap<String, Value>>,
+) -> Result<Value, Error> {
+    split_when_by(recv, args_expr, base_vars, eval_with_vars)
+}
+
+/// Handle SPLIT_WHEN method call with custom function support
+pub fn exec_split_when_with_custom(
+    recv: &Value,
+    args_expr: &[Expr],
+    base_vars: Option<&HashMap<String, Value>>,
+    custom_registry: &Arc<FunctionRegistry>,
+) -> Result<Value, Error> {
+    split_when_by(recv, args_expr, base_vars, |e, vars| eval_with_vars_and_custom(e, vars, custom_registry))
+}
+
+/// The first item for each distinct key, in the original order. Keys are compared by
+/// value, so `1` and `1.0` or equal objects count as the same key; null is a key too.
+pub(crate) fn distinct_by_key(
+    items: &[Value],
+    mut key: impl FnMut(&Value) -> Result<Value, Error>,
+) -> Result<Vec<Value>, Error> {
+    let mut seen = std::collections::HashSet::new();
+    let mut out = Vec::new();
+    for item in items {
+        let mut text = String::new();
+        crate::runtime::random::canonical_text(&key(item)?, &mut text)?;
+        if seen.insert(text) {
+            out.push(item.clone());
+        }
+    }
+    Ok(out)
+}
+
+/// UNIQUE_BY: drop items whose lambda result repeats an earlier item's
+fn unique_by(
+    recv: &Value,
+    args_expr: &[Expr],
+    base_vars: Option<&HashMap<String, Value>>,
+    eval: impl Fn(&Expr, &HashMap<String, Value>) -> Result<Value, Error>,
+) -> Result<Value, Error> {
+    let recv_array = match recv {
+        Value::Array(a) => a,
+        _ => return Err(Error::new("unique_by called on non-array", None)),
+    };
+    let lambda_expr = args_expr.first().ok_or_else(|| Error::new("unique_by expects lambda expression", None))?;
+    let param_name = lambda_param(args_expr, 1, "x");
+    check_lambda("unique_by", lambda_expr, 1)?;
+    let mut vars = base_vars.cloned().unwrap_or_default();
+    let unique = distinct_by_key(recv_array, |item| {
+        let body = bind_lambda(lambda_expr, &[&param_name], [item.clone()], &mut vars);
+        eval(body, &vars)
+    })?;
+    Ok(Value::Array(unique))
+}
+
+/// Handle UNIQUE_BY method call (higher-order function)
+pub fn exec_unique_by(
+    recv: &Value,
+    args_expr: &[Expr],
+    base_vars: Option<&HashMap<String, Value>>,
+) -> Result<Value, Error> {
+    unique_by(recv, args_expr, base_vars, eval_with_vars)
+}
+
+/// Handle UNIQUE_BY method call with custom function support
+pub fn exec_unique_by_with_custom(
+    recv: &Value,
+    args_expr: &[Expr],
+    base_vars: Option<&HashMap<String, Value>>,
+    custom_registry: &Arc<FunctionRegistry>,
+) -> Result<Value, Error> {
+    unique_by(recv, args_expr, base_vars, |e, vars| eval_with_vars_and_custom(e, vars, custom_registry))
+}
+
+/// A sort key and whether it sorts descending. A key whose body is a negation,
+/// `x -> -x.amount` or `-:x.name`, sorts descending by the negated expression,
+/// which also works for text.
+fn sort_key(key: &Expr) -> (Expr, bool) {
+    match key {
+        Expr::Lambda { params, body } => match body.as_ref() {
+            Expr::Unary(UnaryOp::Minus, inner) => (Expr::Lambda { params: params.clone(), body: inner.clone() }, true),
+            _ => (key.clone(), false),
+        },
+        Expr::Unary(UnaryOp::Minus, inner) => (inner.as_ref().clone(), true),
+        _ => (key.clone(), false),
+    }
+}
+
+/// SORT_BY: stable sort by one key lambda or an array of them; later keys break
+/// ties of earlier ones, and items that tie on every key keep their order
+fn sort_by(
+    recv: &Value,
+    args_expr: &[Expr],
+    base_vars: Option<&HashMap<String, Value>>,
+    eval: impl Fn(&Expr, &HashMap<String, Value>) -> Result<Value, Error>,
+) -> Result<Value, Error> {
+    let recv_array = match recv {
+        Value::Array(a) => a,
+        _ => return Err(Error::new("sort_by called on non-array", None)),
+    };
+    let keys = match args_expr.first() {
+        Some(Expr::Array(keys)) if keys.is_empty() => return Err(Error::new("sort_by expects at least one key", None)),
+        Some(Expr::Array(keys)) => keys.iter().map(sort_key).collect::<Vec<_>>(),
+        Some(key) => vec![sort_key(key)],
+        None => return Err(Error::new("sort_by expects a key lambda or an array of them", None)),
+    };
+    for (key, _) in &keys {
+        check_lambda("sort_by", key, 1)?;
+    }
+    let param_name = lambda_param(args_expr, 1, "x");
+    let mut vars = base_vars.cloned().unwrap_or_default();
+    let mut rows = Vec::with_capacity(recv_array.len());
+    for item in recv_array {
+        let mut values = Vec::with_capacity(keys.len());
+        for (key, _) in &keys {
+            let body = bind_lambda(key, &[&param_name], [item.clone()], &mut vars);
+            values.push(eval(body, &vars)?);
+        }
+        rows.push((values, item));
+    }
+
+    let descending = keys.iter().map(|(_, desc)| *desc).collect::<Vec<_>>();
+    crate::runtime::utils::sort_by_keys(&mut rows, &descending, "sort_by")?;
+    Ok(Value::Array(rows.into_iter().map(|(_, item)| item.clone()).collect()))
+}
+
+/// Handle SORT_BY method call (higher-order function)
+pub fn exec_sort_by(
+    recv: &Value,
+    args_expr: &[Expr],
+    base_vars: Option<&HashMap<String, Value>>,
+) -> Result<Value, Error> {
+    sort_by(recv, args_expr, base_vars, eval_with_vars)
+}
+
+/// Handle SORT_BY method call with custom function support
+pub fn exec_sort_by_with_custom(
+    recv: &Value,
+    args_expr: &[Expr],
+    base_vars: Option<&HashMap<String, Value>>,
+    custom_registry: &Arc<FunctionRegistry>,
+) -> Result<Value, Error> {
+    sort_by(recv, args_expr, base_vars, |e, vars| eval_with_vars_and_custom(e, vars, custom_registry))
+}
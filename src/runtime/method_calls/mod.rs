@@ -11,13 +11,24 @@ use crate::custom::FunctionRegistry;
 use crate::error::Error;
 use crate::types::Value;
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::Arc;
 
-pub use predicates::exec_predicate;
-pub use string_methods::exec_string_method;
-pub use array_methods::exec_array_method;
-pub use lambda_methods::{exec_filter, exec_map, exec_find, exec_reduce};
-pub use conversion_methods::exec_conversion_method;
+pub use predicates::{exec_predicate, PREDICATES};
+pub use string_methods::{exec_string_method, STRING_METHODS};
+pub use array_methods::{exec_array_method, ARRAY_METHODS};
+pub use lambda_methods::{exec_filter, exec_map, exec_find, exec_reduce, exec_partition, exec_split_when, exec_unique_by, exec_sort_by};
+pub use conversion_methods::{exec_conversion_method, CONVERSION_METHODS};
+
+/// Array methods that take a lambda
+pub const LAMBDA_METHODS: &[&str] = &["filter", "map", "find", "reduce", "partition", "split_when", "unique_by", "sort_by"];
+
+/// Names accepted by the number method handler
+pub const NUMBER_METHODS: &[&str] = &[
+    "abs", "ceil", "ceiling", "floor", "round", "sqrt", "sin", "cos", "tan", "int", "between",
+];
+
+/// Names accepted by the JSON object method handler
+pub const JSON_METHODS: &[&str] = &["keys", "values", "has_key", "has", "dig"];
 
 /// Main method dispatch function with improved architecture
 pub fn exec_method(
@@ -26,6 +37,19 @@ pub fn exec_method(
     recv: &Value,
     args_expr: &[Expr],
     base_vars: Option<&HashMap<String, Value>>,
+) -> Result<Value, Error> {
+    crate::context::check_deadline()?;
+    let result = dispatch_method(name, predicate, recv, args_expr, base_vars)?;
+    crate::context::charge_memory(&result)?;
+    Ok(result)
+}
+
+fn dispatch_method(
+    name: &str,
+    predicate: bool,
+    recv: &Value,
+    args_expr: &[Expr],
+    base_vars: Option<&HashMap<String, Value>>,
 ) -> Result<Value, Error> {
     if predicate {
         return exec_predicate(name, recv);
@@ -37,7 +61,8 @@ pub fn exec_method(
     // Check for conversion methods first (available on all types)
     match lname.as_str() {
         "to_s" | "to_string" | "to_i" | "to_int" | "to_f" | "to_float" |
-        "to_a" | "to_array" | "to_json" | "to_bool" | "to_boolean" => {
+        "to_a" | "to_array" | "to_json" | "to_bool" | "to_boolean" | "to_currency" |
+        "to_number_or_null" | "to_date_or_null" => {
             return exec_conversion_method(name, recv);
         }
         _ => {}
@@ -53,11 +78,15 @@ pub fn exec_method(
                 "map" => exec_map(recv, args_expr, base_vars),
                 "find" => exec_find(recv, args_expr, base_vars),
                 "reduce" => exec_reduce(recv, args_expr, base_vars),
+                "partition" => exec_partition(recv, args_expr, base_vars),
+                "split_when" => exec_split_when(recv, args_expr, base_vars),
+                "unique_by" => exec_unique_by(recv, args_expr, base_vars),
+                "sort_by" => exec_sort_by(recv, args_expr, base_vars),
                 _ => exec_array_method(name, recv, args_expr, base_vars),
             }
         }
         Value::Number(_) => exec_number_method(name, recv, args_expr, base_vars),
-        Value::Json(_) => exec_json_method(name, recv, args_expr, base_vars),
+        Value::Json(_) | Value::Map(_) => exec_json_method(name, recv, args_expr, base_vars),
         _ => Err(Error::new(
             format!("No methods available for {:?} type", recv),
             None,
@@ -72,7 +101,21 @@ pub fn exec_method_with_custom(
     recv: &Value,
     args_expr: &[Expr],
     base_vars: Option<&HashMap<String, Value>>,
-    custom_registry: &Arc<RwLock<FunctionRegistry>>,
+    custom_registry: &Arc<FunctionRegistry>,
+) -> Result<Value, Error> {
+    crate::context::check_deadline()?;
+    let result = dispatch_method_with_custom(name, predicate, recv, args_expr, base_vars, custom_registry)?;
+    crate::context::charge_memory(&result)?;
+    Ok(result)
+}
+
+fn dispatch_method_with_custom(
+    name: &str,
+    predicate: bool,
+    recv: &Value,
+    args_expr: &[Expr],
+    base_vars: Option<&HashMap<String, Value>>,
+    custom_registry: &Arc<FunctionRegistry>,
 ) -> Result<Value, Error> {
     if predicate {
         return exec_predicate(name, recv);
@@ -84,7 +127,8 @@ pub fn exec_method_with_custom(
     // Check for conversion methods first (available on all types)
     match lname.as_str() {
         "to_s" | "to_string" | "to_i" | "to_int" | "to_f" | "to_float" |
-        "to_a" | "to_array" | "to_json" | "to_bool" | "to_boolean" => {
+        "to_a" | "to_array" | "to_json" | "to_bool" | "to_boolean" | "to_currency" |
+        "to_number_or_null" | "to_date_or_null" => {
             return exec_conversion_method(name, recv);
         }
         _ => {}
@@ -100,11 +144,15 @@ pub fn exec_method_with_custom(
                 "map" => lambda_methods::exec_map_with_custom(recv, args_expr, base_vars, custom_registry),
                 "find" => lambda_methods::exec_find_with_custom(recv, args_expr, base_vars, custom_registry),
                 "reduce" => lambda_methods::exec_reduce_with_custom(recv, args_expr, base_vars, custom_registry),
+                "partition" => lambda_methods::exec_partition_with_custom(recv, args_expr, base_vars, custom_registry),
+                "split_when" => lambda_methods::exec_split_when_with_custom(recv, args_expr, base_vars, custom_registry),
+                "unique_by" => lambda_methods::exec_unique_by_with_custom(recv, args_expr, base_vars, custom_registry),
+                "sort_by" => lambda_methods::exec_sort_by_with_custom(recv, args_expr, base_vars, custom_registry),
                 _ => exec_array_method(name, recv, args_expr, base_vars),
             }
         }
         Value::Number(_) => exec_number_method(name, recv, args_expr, base_vars),
-        Value::Json(_) => exec_json_method(name, recv, args_expr, base_vars),
+        Value::Json(_) | Value::Map(_) => exec_json_method(name, recv, args_expr, base_vars),
         _ => Err(Error::new(
             format!("No methods available for {:?} type", recv),
             None,
@@ -209,13 +257,22 @@ fn exec_json_method(
     args_expr: &[Expr],
     base_vars: Option<&HashMap<String, Value>>,
 ) -> Result<Value, Error> {
+    let lname = name.to_lowercase();
+    let lowered;
     let json_str = match recv {
         Value::Json(s) => s,
+        Value::Map(map) => match lname.as_str() {
+            // Answered from the map itself so entries keep their types
+            "keys" => return Ok(Value::Array(map.keys().map(|k| Value::String(k.clone())).collect())),
+            "values" => return Ok(Value::Array(map.values().cloned().collect())),
+            _ => {
+                lowered = recv.to_json_value()?.to_string();
+                &lowered
+            }
+        },
         _ => return Err(Error::new("Method called on non-JSON", None)),
     };
-    
-    let lname = name.to_lowercase();
-    
+
     match lname.as_str() {
         "keys" => {
             let parsed: serde_json::Value = serde_json::from_str(json_str)
@@ -304,7 +361,7 @@ fn exec_json_method(
                     }
                     Value::Number(n) => {
                         if let serde_json::Value::Array(arr) = cur {
-                            let idx = if n.is_finite() { n.floor() as isize } else { -1 };
+                            let idx = crate::runtime::utils::to_index(*n, "dig index")?;
                             if idx >= 0 && (idx as usize) < arr.len() { cur = &arr[idx as usize]; } else { ok = false; break; }
                         } else { ok = false; break; }
                     }
@@ -2,6 +2,11 @@ use crate::error::Error;
 use crate::runtime::utils::is_blank;
 use crate::types::Value;
 
+/// Names accepted by [`exec_predicate`], without the trailing `?`
+pub const PREDICATES: &[&str] = &[
+    "positive", "negative", "zero", "even", "odd", "numeric", "array", "nil", "blank", "present",
+];
+
 /// Handle predicate method calls (methods ending with '?')
 pub fn exec_predicate(name: &str, recv: &Value) -> Result<Value, Error> {
     let lname = name.to_lowercase();
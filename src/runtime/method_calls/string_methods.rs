@@ -4,6 +4,12 @@ use crate::runtime::evaluation::{eval, eval_with_vars};
 use crate::types::Value;
 use std::collections::HashMap;
 
+/// Names accepted by [`exec_string_method`]
+pub const STRING_METHODS: &[&str] = &[
+    "length", "len", "upper", "upcase", "lower", "downcase", "trim", "trim_start", "trim_end", "reverse", "includes", "contains",
+    "startswith", "starts_with", "endswith", "ends_with", "split", "replace", "substring", "substr",
+];
+
 /// Handle string method calls
 pub fn exec_string_method(
     name: &str,
@@ -25,7 +31,22 @@ pub fn exec_string_method(
         
         "lower" | "downcase" => Ok(Value::String(recv_string.to_lowercase())),
         
-        "trim" => Ok(Value::String(recv_string.trim().to_string())),
+        "trim" | "trim_start" | "trim_end" => {
+            use crate::runtime::string::{trim_chars, TrimSide};
+            let side = match lname.as_str() {
+                "trim_start" => TrimSide::Start,
+                "trim_end" => TrimSide::End,
+                _ => TrimSide::Both,
+            };
+            let chars = match args_expr.first() {
+                Some(arg) => match if let Some(vars) = base_vars { eval_with_vars(arg, vars)? } else { eval(arg)? } {
+                    Value::String(s) => Some(s),
+                    _ => return Err(Error::new(format!("{} expects a string of characters", lname), None)),
+                },
+                None => None,
+            };
+            Ok(Value::String(trim_chars(&recv_string, side, chars.as_deref())))
+        }
         
         "reverse" => Ok(Value::String(recv_string.chars().rev().collect())),
         
@@ -90,11 +111,18 @@ pub fn exec_string_method(
                 Value::String(s) => s,
                 _ => return Err(Error::new("split method expects string argument", None)),
             };
-            let parts: Vec<Value> = recv_string
-                .split(&delimiter)
-                .map(|s| Value::String(s.to_string()))
-                .collect();
-            Ok(Value::Array(parts))
+            let limit = match args_expr.get(1) {
+                Some(arg) => {
+                    let limit_val = if let Some(vars) = base_vars { eval_with_vars(arg, vars)? } else { eval(arg)? };
+                    match limit_val {
+                        Value::Number(n) => crate::runtime::utils::to_count(n, "split limit")?,
+                        _ => return Err(Error::new("split limit must be number", None)),
+                    }
+                }
+                None => 0,
+            };
+            let parts = crate::runtime::string::split_parts(&recv_string, &delimiter, limit)?;
+            Ok(Value::Array(parts.into_iter().map(Value::String).collect()))
         }
         
         "replace" => {
@@ -128,7 +156,7 @@ pub fn exec_string_method(
                 eval(&args_expr[0])?
             };
             let start = match start_val {
-                Value::Number(n) => n as usize,
+                Value::Number(n) => crate::runtime::utils::to_count(n, "substring start")?,
                 _ => return Err(Error::new("substring start must be number", None)),
             };
             
@@ -140,7 +168,7 @@ pub fn exec_string_method(
                     eval(&args_expr[1])?
                 };
                 let len = match len_val {
-                    Value::Number(n) => n as usize,
+                    Value::Number(n) => crate::runtime::utils::to_count(n, "substring length")?,
                     _ => return Err(Error::new("substring length must be number", None)),
                 };
                 chars.get(start..start.min(chars.len()).saturating_add(len.min(chars.len() - start.min(chars.len()))))
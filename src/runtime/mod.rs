@@ -5,6 +5,8 @@ pub mod function_dispatch;
 pub mod method_calls;
 pub mod type_casting;
 pub mod utils;
+pub mod currency;
+pub mod locale;
 pub mod arithmetic;
 pub mod logical;
 pub mod string;
@@ -12,8 +14,21 @@ pub mod array;
 pub mod datetime;
 pub mod financial;
 pub mod statistical;
+pub mod random;
+pub mod binning;
+pub mod scaling;
+pub mod smoothing;
+pub mod interpolation;
+pub mod tiers;
+pub mod dtable;
+pub mod daycount;
+pub mod similarity;
+pub mod tokens;
+pub mod masking;
+pub mod phone;
 pub mod json;
 pub mod jsonpath;
+pub(crate) mod observer;
 
 // Re-export the main public functions
 pub use evaluation::{eval, eval_with_vars, eval_with_vars_and_custom, eval_with_assignments, eval_with_assignments_and_context};
@@ -0,0 +1,99 @@
+//! Per-thread evaluation observer.
+//!
+//! [`Evaluator::eval`](crate::runtime::evaluator::Evaluator::eval) reports every node
+//! it evaluates to the observer installed on the current thread, if any. Explain
+//! mode records a value tree this way. With no observer installed the cost is one
+//! thread-local flag read per node.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::ast::Expr;
+use crate::error::Error;
+use crate::runtime::evaluator::EvaluationContext;
+use crate::types::Value;
+
+pub(crate) trait Observer {
+    /// Called before `expr` is evaluated. An error aborts the evaluation.
+    fn enter(&mut self, expr: &Expr, context: &dyn EvaluationContext) -> Result<(), Error>;
+    /// Called once `expr` has produced `result`
+    fn exit(&mut self, expr: &Expr, result: &Result<Value, Error>, elapsed: Duration);
+}
+
+impl<T: Observer> Observer for Rc<RefCell<T>> {
+    fn enter(&mut self, expr: &Expr, context: &dyn EvaluationContext) -> Result<(), Error> {
+        self.borrow_mut().enter(expr, context)
+    }
+
+    fn exit(&mut self, expr: &Expr, result: &Result<Value, Error>, elapsed: Duration) {
+        self.borrow_mut().exit(expr, result, elapsed)
+    }
+}
+
+thread_local! {
+    static OBSERVER: RefCell<Option<Box<dyn Observer>>> = const { RefCell::new(None) };
+    static ACTIVE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Restores the previous observer when dropped
+struct ObserverGuard {
+    previous: Option<Box<dyn Observer>>,
+}
+
+impl Drop for ObserverGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        ACTIVE.with(|a| a.set(previous.is_some()));
+        OBSERVER.with(|o| *o.borrow_mut() = previous);
+    }
+}
+
+/// Run `f` with `observer` receiving every node evaluated on this thread
+pub(crate) fn with_observer<R>(observer: Box<dyn Observer>, f: impl FnOnce() -> R) -> R {
+    let previous = OBSERVER.with(|o| o.borrow_mut().replace(observer));
+    ACTIVE.with(|a| a.set(true));
+    let _guard = ObserverGuard { previous };
+    f()
+}
+
+/// Take the observer out for the duration of a callback, so evaluations the
+/// callback starts itself are not reported back into it
+fn call<R>(default: R, g: impl FnOnce(&mut dyn Observer) -> R) -> R {
+    let Some(mut observer) = OBSERVER.with(|o| o.borrow_mut().take()) else { return default };
+    let result = g(observer.as_mut());
+    OBSERVER.with(|o| *o.borrow_mut() = Some(observer));
+    result
+}
+
+/// Report that `expr` is about to be evaluated. Returns the start time to hand
+/// to [`exit`], or `None` when no observer is installed.
+#[inline]
+pub(crate) fn enter(expr: &Expr, context: &dyn EvaluationContext) -> Result<Option<Instant>, Error> {
+    if !ACTIVE.with(|a| a.get()) {
+        return Ok(None);
+    }
+    call(Ok(()), |o| o.enter(expr, context))?;
+    Ok(Some(Instant::now()))
+}
+
+/// Report the result of a node passed to [`enter`]
+#[inline]
+pub(crate) fn exit(expr: &Expr, result: &Result<Value, Error>, started: Option<Instant>) {
+    if let Some(start) = started {
+        call((), |o| o.exit(expr, result, start.elapsed()));
+    }
+}
+
+/// Evaluate `expr` through `eval`, reporting it to the current observer
+#[inline]
+pub(crate) fn observe<C: EvaluationContext>(
+    expr: &Expr,
+    context: &C,
+    eval: impl FnOnce() -> Result<Value, Error>,
+) -> Result<Value, Error> {
+    let started = enter(expr, context)?;
+    let result = eval();
+    exit(expr, &result, started);
+    result
+}
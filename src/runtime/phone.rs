@@ -0,0 +1,138 @@
+//! PARSEPHONE: phone number normalization to E.164 with a small embedded table of
+//! country calling codes, trunk prefixes and national number lengths.
+//!
+//! This is a plausibility check, not full numbering-plan validation: a number is
+//! `valid` when its national part has a length the country uses. Countries sharing
+//! a calling code (`+1`) are told apart only through the default region.
+
+use crate::error::Error;
+use crate::types::Value;
+
+struct Region {
+    code: &'static str,
+    calling_code: &'static str,
+    /// Dialled before national numbers inside the country, dropped in E.164
+    trunk_prefix: Option<&'static str>,
+    lengths: &'static [usize],
+}
+
+const fn region(code: &'static str, calling_code: &'static str, trunk_prefix: Option<&'static str>, lengths: &'static [usize]) -> Region {
+    Region { code, calling_code, trunk_prefix, lengths }
+}
+
+/// The first region listed for a calling code is the one assumed for `+<code>` numbers
+const REGIONS: &[Region] = &[
+    region("US", "1", Some("1"), &[10]),
+    region("CA", "1", Some("1"), &[10]),
+    region("PR", "1", Some("1"), &[10]),
+    region("MX", "52", None, &[10]),
+    region("GT", "502", None, &[8]),
+    region("CR", "506", None, &[8]),
+    region("PA", "507", None, &[7, 8]),
+    region("CO", "57", Some("0"), &[10]),
+    region("VE", "58", Some("0"), &[10]),
+    region("PE", "51", Some("0"), &[8, 9]),
+    region("EC", "593", Some("0"), &[8, 9]),
+    region("CL", "56", None, &[9]),
+    region("AR", "54", Some("0"), &[10]),
+    region("UY", "598", Some("0"), &[8]),
+    region("BR", "55", Some("0"), &[10, 11]),
+    region("GB", "44", Some("0"), &[9, 10]),
+    region("IE", "353", Some("0"), &[7, 8, 9]),
+    region("FR", "33", Some("0"), &[9]),
+    region("ES", "34", None, &[9]),
+    region("PT", "351", None, &[9]),
+    region("IT", "39", None, &[6, 7, 8, 9, 10, 11]),
+    region("DE", "49", Some("0"), &[6, 7, 8, 9, 10, 11, 12, 13]),
+    region("AT", "43", Some("0"), &[4, 5, 6, 7, 8, 9, 10, 11, 12, 13]),
+    region("CH", "41", Some("0"), &[9]),
+    region("NL", "31", Some("0"), &[9]),
+    region("BE", "32", Some("0"), &[8, 9]),
+    region("SE", "46", Some("0"), &[7, 8, 9]),
+    region("NO", "47", None, &[8]),
+    region("DK", "45", None, &[8]),
+    region("FI", "358", Some("0"), &[5, 6, 7, 8, 9, 10]),
+    region("PL", "48", None, &[9]),
+    region("TR", "90", Some("0"), &[10]),
+    region("RU", "7", Some("8"), &[10]),
+    region("IN", "91", Some("0"), &[10]),
+    region("CN", "86", Some("0"), &[10, 11]),
+    region("JP", "81", Some("0"), &[9, 10]),
+    region("KR", "82", Some("0"), &[8, 9, 10]),
+    region("AU", "61", Some("0"), &[9]),
+    region("NZ", "64", Some("0"), &[8, 9, 10]),
+    region("ZA", "27", Some("0"), &[9]),
+];
+
+fn find_region(code: &str) -> Option<&'static Region> {
+    REGIONS.iter().find(|r| r.code.eq_ignore_ascii_case(code))
+}
+
+/// The region of an international number's calling code, preferring `default` when
+/// it shares the code; returns the region and the national number
+fn split_international<'a>(digits: &'a str, default: Option<&'static Region>) -> Option<(&'static Region, &'a str)> {
+    (1..=3).filter(|n| *n < digits.len()).find_map(|n| {
+        let (code, national) = digits.split_at(n);
+        let region = match default {
+            Some(d) if d.calling_code == code => Some(d),
+            _ => REGIONS.iter().find(|r| r.calling_code == code),
+        };
+        region.map(|r| (r, national))
+    })
+}
+
+/// Country, E.164 form and validity of `text`, or `None` when no country can be told
+fn parse(text: &str, default: Option<&'static Region>) -> Option<(&'static Region, String, bool)> {
+    let trimmed = text.trim();
+    let allowed = |c: char| c.is_ascii_digit() || " -.()/".contains(c);
+    let (international, rest) = match trimmed.strip_prefix('+') {
+        Some(rest) => (true, rest),
+        None => match trimmed.strip_prefix("00") {
+            Some(rest) => (true, rest),
+            None => (false, trimmed),
+        },
+    };
+    if !rest.chars().all(allowed) {
+        return None;
+    }
+    let digits: String = rest.chars().filter(char::is_ascii_digit).collect();
+    if digits.is_empty() {
+        return None;
+    }
+    let (region, national) = if international {
+        split_international(&digits, default)?
+    } else {
+        let region = default?;
+        let national = match region.trunk_prefix {
+            Some(trunk) if digits.len() > region.lengths[0] => digits.strip_prefix(trunk).unwrap_or(&digits),
+            _ => &digits,
+        };
+        (region, national)
+    };
+    let valid = region.lengths.contains(&national.len());
+    Some((region, format!("+{}{}", region.calling_code, national), valid))
+}
+
+/// PARSEPHONE(text, [default_region]) -> `{"country", "e164", "valid"}`.
+///
+/// Numbers written with `+` or `00` carry their country; others are read as
+/// national numbers of `default_region` (an ISO code such as `"MX"`), dropping the
+/// trunk prefix (`0` in most of Europe, `1` in the US). Text that cannot be read as
+/// a phone number gives `{"country": null, "e164": null, "valid": false}`.
+pub fn exec_parse_phone(args: &[Value]) -> Result<Value, Error> {
+    let (text, default) = match args {
+        [Value::String(text)] => (text, None),
+        [Value::String(text), Value::Null] => (text, None),
+        [Value::String(text), Value::String(code)] => {
+            let region = find_region(code)
+                .ok_or_else(|| Error::new(format!("PARSEPHONE has no numbering data for region '{}'", code), None))?;
+            (text, Some(region))
+        }
+        _ => return Err(Error::new("PARSEPHONE expects text, [default_region]", None)),
+    };
+    let result = match parse(text, default) {
+        Some((region, e164, valid)) => serde_json::json!({ "country": region.code, "e164": e164, "valid": valid }),
+        None => serde_json::json!({ "country": null, "e164": null, "valid": false }),
+    };
+    Ok(Value::Json(result.to_string()))
+}
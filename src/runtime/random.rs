@@ -0,0 +1,260 @@
+//! Weighted choice, probability, bucketing and fingerprint helpers.
+//!
+//! CHOOSEWEIGHTED draws from a small seedable generator: with a seed (a customer
+//! id, say) the pick depends only on the seed and the inputs, so an allocation is
+//! reproducible across evaluations, processes and releases. Without a seed the draw
+//! is random and the function is refused in deterministic mode.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::Error;
+use crate::runtime::utils::format_number;
+use crate::types::Value;
+
+/// 64-bit FNV-1a, stable across platforms and releases unlike std's hasher
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ *b as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// SplitMix64: tiny, fast and well distributed, which is all weighted picking needs
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// Seeded from a formula value; strings and numbers with the same text seed alike
+    pub(crate) fn from_value(seed: &Value) -> Result<Self, Error> {
+        let text = match seed {
+            Value::String(s) => s.clone(),
+            Value::Number(n) | Value::Currency(n) if n.is_finite() => format_number(*n),
+            _ => return Err(Error::new("Seed must be a string or a finite number", None)),
+        };
+        Ok(Self::new(fnv1a(text.as_bytes())))
+    }
+
+    /// Seeded from the clock, for unseeded draws
+    pub(crate) fn from_entropy() -> Self {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0);
+        Self::new(nanos ^ COUNTER.fetch_add(1, Ordering::Relaxed).wrapping_mul(0x9e37_79b9_7f4a_7c15))
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in [0, 1)
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// CHOOSEWEIGHTED(values, weights, [seed]) -> one of `values`, each picked with
+/// probability proportional to its weight
+pub fn exec_choose_weighted(args: &[Value]) -> Result<Value, Error> {
+    let (values, weights) = match args {
+        [Value::Array(values), Value::Array(weights)] | [Value::Array(values), Value::Array(weights), _] => {
+            (values, weights)
+        }
+        _ => return Err(Error::new("CHOOSEWEIGHTED expects values, weights, [seed]", None)),
+    };
+    if values.is_empty() || values.len() != weights.len() {
+        return Err(Error::new(
+            format!(
+                "CHOOSEWEIGHTED needs one weight per value, got {} values and {} weights",
+                values.len(),
+                weights.len()
+            ),
+            None,
+        ));
+    }
+    let weights = weights
+        .iter()
+        .map(|w| match w {
+            Value::Number(n) | Value::Currency(n) if n.is_finite() && *n >= 0.0 => Ok(*n),
+            other => Err(Error::new(format!("CHOOSEWEIGHTED weights must be non-negative numbers, got {:?}", other), None)),
+        })
+        .collect::<Result<Vec<f64>, Error>>()?;
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 || !total.is_finite() {
+        return Err(Error::new("CHOOSEWEIGHTED needs at least one positive weight", None));
+    }
+
+    let mut rng = match args.get(2) {
+        Some(seed) => SplitMix64::from_value(seed).map_err(|e| Error::new(format!("CHOOSEWEIGHTED: {}", e.message), None))?,
+        None if crate::context::deterministic() => {
+            return Err(Error::new("CHOOSEWEIGHTED needs a seed in deterministic mode", None));
+        }
+        None => SplitMix64::from_entropy(),
+    };
+    let target = rng.next_f64() * total;
+    let mut cumulative = 0.0;
+    for (value, weight) in values.iter().zip(&weights) {
+        cumulative += weight;
+        if *weight > 0.0 && target < cumulative {
+            return Ok(value.clone());
+        }
+    }
+    // Rounding can leave the target a hair past the last sum
+    let last = weights.iter().rposition(|w| *w > 0.0).unwrap_or(0);
+    Ok(values[last].clone())
+}
+
+/// PROBABILITY(successes, trials) -> the observed success rate
+pub fn exec_probability(args: &[Value]) -> Result<Value, Error> {
+    let (successes, trials) = match args {
+        [Value::Number(s), Value::Number(t)] => (*s, *t),
+        _ => return Err(Error::new("PROBABILITY expects successes, trials", None)),
+    };
+    if !(trials.is_finite() && trials > 0.0) {
+        return Err(Error::new(format!("PROBABILITY trials must be positive, got {}", format_number(trials)), None));
+    }
+    if !(0.0..=trials).contains(&successes) {
+        return Err(Error::new(
+            format!(
+                "PROBABILITY successes must be between 0 and {}, got {}",
+                format_number(trials),
+                format_number(successes)
+            ),
+            None,
+        ));
+    }
+    Ok(Value::Number(successes / trials))
+}
+
+/// HASHBUCKET(value, n_buckets, [salt]) -> bucket in 0..n_buckets.
+///
+/// The bucket is `fnv1a64(key) mod n_buckets`, where `key` is the UTF-8 text of the
+/// value (numbers as they print, e.g. `42`, `1.5`), prefixed with `salt:` when a salt
+/// is given. Any service can reproduce it with a few lines of code.
+pub fn exec_hash_bucket(args: &[Value]) -> Result<Value, Error> {
+    let (value, buckets, salt) = match args {
+        [value, Value::Number(n)] => (value, *n, None),
+        [value, Value::Number(n), Value::String(salt)] => (value, *n, Some(salt)),
+        _ => return Err(Error::new("HASHBUCKET expects value, n_buckets, [salt]", None)),
+    };
+    let text = match value {
+        Value::String(s) => s.clone(),
+        Value::Number(n) | Value::Currency(n) if n.is_finite() => format_number(*n),
+        other => return Err(Error::new(format!("HASHBUCKET hashes strings and finite numbers, got {:?}", other), None)),
+    };
+    let buckets = crate::runtime::utils::to_index(buckets, "HASHBUCKET n_buckets")?;
+    if buckets < 1 {
+        return Err(Error::new("HASHBUCKET n_buckets must be at least 1", None));
+    }
+    let key = match salt {
+        Some(salt) => format!("{}:{}", salt, text),
+        None => text,
+    };
+    Ok(Value::Number((fnv1a(key.as_bytes()) % buckets as u64) as f64))
+}
+
+/// Numbers in canonical form: Rust's shortest round-trip decimal, with `-0` as `0`
+fn canonical_number(n: f64, out: &mut String) {
+    if n == 0.0 {
+        out.push('0');
+    } else {
+        out.push_str(&n.to_string());
+    }
+}
+
+fn canonical_json(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::Null => out.push_str("null"),
+        serde_json::Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        serde_json::Value::Number(n) => canonical_number(n.as_f64().unwrap_or(0.0), out),
+        serde_json::Value::String(s) => out.push_str(&serde_json::Value::String(s.clone()).to_string()),
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                canonical_json(item, out);
+            }
+            out.push(']');
+        }
+        serde_json::Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::String(key.clone()).to_string());
+                out.push(':');
+                canonical_json(&map[key], out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// The canonical text FINGERPRINT hashes; see [`exec_fingerprint`]
+pub(crate) fn canonical_text(value: &Value, out: &mut String) -> Result<(), Error> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Boolean(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) | Value::Currency(n) => canonical_number(*n, out),
+        Value::String(s) => out.push_str(&serde_json::Value::String(s.clone()).to_string()),
+        Value::DateTime(ts) => {
+            out.push_str("datetime(");
+            out.push_str(&ts.to_string());
+            out.push(')');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                canonical_text(item, out)?;
+            }
+            out.push(']');
+        }
+        Value::Json(text) => {
+            let parsed: serde_json::Value =
+                serde_json::from_str(text).map_err(|e| Error::new(format!("FINGERPRINT: invalid JSON: {}", e), None))?;
+            canonical_json(&parsed, out);
+        }
+        Value::Map(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::String(key.clone()).to_string());
+                out.push(':');
+                canonical_text(&map[key], out)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+/// FINGERPRINT(value) -> 16 lowercase hex digits identifying `value`.
+///
+/// The value is written as canonical JSON: object keys sorted, no whitespace, numbers
+/// (Currency included) in shortest round-trip form with `-0` as `0`, DateTimes as
+/// `datetime(<unix seconds>)`. The digits are the 64-bit FNV-1a hash of that text's
+/// UTF-8 bytes. Equal values give equal fingerprints on every platform and release;
+/// it is meant for dedup and cache keys, not security.
+pub fn exec_fingerprint(args: &[Value]) -> Result<Value, Error> {
+    let [value] = args else {
+        return Err(Error::new("FINGERPRINT expects one value", None));
+    };
+    let mut text = String::new();
+    canonical_text(value, &mut text)?;
+    Ok(Value::String(format!("{:016x}", fnv1a(text.as_bytes()))))
+}
@@ -0,0 +1,85 @@
+//! Normalization of numeric features: z-scores, min-max scaling and winsorizing.
+//!
+//! Each function maps an array to an array of the same length. Nulls keep their
+//! position and stay null, so rows with missing inputs still line up; they are left
+//! out of the mean, spread and percentiles.
+
+use crate::error::Error;
+use crate::types::Value;
+
+/// An array argument as optional numbers, null for missing entries
+pub(crate) fn series(name: &str, arg: &Value) -> Result<Vec<Option<f64>>, Error> {
+    let Value::Array(items) = arg else {
+        return Err(Error::new(format!("{} expects an array of numbers", name), None));
+    };
+    items
+        .iter()
+        .map(|v| match v {
+            Value::Null => Ok(None),
+            Value::Number(n) | Value::Currency(n) if n.is_finite() => Ok(Some(*n)),
+            other => Err(Error::new(format!("{} expects numbers, got {:?}", name, other), None)),
+        })
+        .collect()
+}
+
+fn map_present(xs: &[Option<f64>], f: impl Fn(f64) -> f64) -> Value {
+    Value::Array(xs.iter().map(|x| x.map_or(Value::Null, |x| Value::Number(f(x)))).collect())
+}
+
+/// Linear-interpolated percentile of sorted `nums`, as PERCENTILE.INC computes it
+fn percentile_inc(sorted: &[f64], p: f64) -> f64 {
+    let rank = p * (sorted.len() - 1) as f64;
+    let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (rank - lo as f64)
+}
+
+/// ZSCORE(arr) -> `(x - mean) / stdev` with the population standard deviation
+/// (STDEV.P). When all values are equal every score is 0.
+pub fn exec_zscore(args: &[Value]) -> Result<Value, Error> {
+    let [arr] = args else {
+        return Err(Error::new("ZSCORE expects an array of numbers", None));
+    };
+    let xs = series("ZSCORE", arr)?;
+    let present: Vec<f64> = xs.iter().flatten().copied().collect();
+    if present.is_empty() {
+        return Ok(map_present(&xs, |x| x));
+    }
+    let n = present.len() as f64;
+    let mean = present.iter().sum::<f64>() / n;
+    let stdev = (present.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n).sqrt();
+    Ok(map_present(&xs, |x| if stdev == 0.0 { 0.0 } else { (x - mean) / stdev }))
+}
+
+/// MINMAXSCALE(arr) -> `(x - min) / (max - min)`, so values span 0 to 1. When all
+/// values are equal every result is 0.
+pub fn exec_min_max_scale(args: &[Value]) -> Result<Value, Error> {
+    let [arr] = args else {
+        return Err(Error::new("MINMAXSCALE expects an array of numbers", None));
+    };
+    let xs = series("MINMAXSCALE", arr)?;
+    let present = xs.iter().flatten();
+    let min = present.clone().copied().fold(f64::INFINITY, f64::min);
+    let max = present.copied().fold(f64::NEG_INFINITY, f64::max);
+    Ok(map_present(&xs, |x| if max > min { (x - min) / (max - min) } else { 0.0 }))
+}
+
+/// WINSORIZE(arr, p) -> values below the `p` percentile raised to it and values above
+/// the `1 - p` percentile lowered to it, taming outliers without dropping rows.
+/// Percentiles interpolate like PERCENTILE.INC; `p` is between 0 and 0.5.
+pub fn exec_winsorize(args: &[Value]) -> Result<Value, Error> {
+    let [arr, p] = args else {
+        return Err(Error::new("WINSORIZE expects an array of numbers and a fraction", None));
+    };
+    let xs = series("WINSORIZE", arr)?;
+    let p = match p {
+        Value::Number(p) if (0.0..=0.5).contains(p) => *p,
+        _ => return Err(Error::new("WINSORIZE fraction must be a number between 0 and 0.5", None)),
+    };
+    let mut sorted: Vec<f64> = xs.iter().flatten().copied().collect();
+    if sorted.is_empty() {
+        return Ok(map_present(&xs, |x| x));
+    }
+    sorted.sort_by(f64::total_cmp);
+    let (lo, hi) = (percentile_inc(&sorted, p), percentile_inc(&sorted, 1.0 - p));
+    Ok(map_present(&xs, |x| x.clamp(lo, hi)))
+}
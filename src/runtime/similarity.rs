@@ -0,0 +1,261 @@
+//! Fuzzy string matching for dedup rules: edit distance, Jaro similarity and the
+//! Soundex and Metaphone phonetic codes.
+//!
+//! Distances count Unicode characters and are case-sensitive; wrap the inputs in
+//! `LOWER` to compare without case. The phonetic codes only look at the ASCII
+//! letters of a name, which is what both algorithms were designed for.
+
+use crate::error::Error;
+use crate::types::Value;
+
+fn two_strings<'a>(name: &str, args: &'a [Value]) -> Result<(&'a str, &'a str), Error> {
+    match args {
+        [Value::String(a), Value::String(b)] => Ok((a, b)),
+        _ => Err(Error::new(format!("{} expects two strings", name), None)),
+    }
+}
+
+fn one_string<'a>(name: &str, args: &'a [Value]) -> Result<&'a str, Error> {
+    match args {
+        [Value::String(s)] => Ok(s),
+        _ => Err(Error::new(format!("{} expects a string", name), None)),
+    }
+}
+
+/// Fewest single-character insertions, deletions and substitutions turning `a` into `b`
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// Jaro similarity in [0, 1]: 1 for equal strings, 0 when nothing matches
+pub fn jaro(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    // Characters match when equal and no further apart than this
+    let window = (a.len().max(b.len()) / 2).saturating_sub(1);
+    let mut a_matched = vec![false; a.len()];
+    let mut b_matched = vec![false; b.len()];
+    let mut matches = 0usize;
+    for (i, ca) in a.iter().enumerate() {
+        let from = i.saturating_sub(window);
+        let to = (i + window + 1).min(b.len());
+        for j in from..to {
+            if !b_matched[j] && b[j] == *ca {
+                a_matched[i] = true;
+                b_matched[j] = true;
+                matches += 1;
+                break;
+            }
+        }
+    }
+    if matches == 0 {
+        return 0.0;
+    }
+    let a_order = a.iter().zip(&a_matched).filter(|(_, m)| **m).map(|(c, _)| c);
+    let b_order = b.iter().zip(&b_matched).filter(|(_, m)| **m).map(|(c, _)| c);
+    let transpositions = a_order.zip(b_order).filter(|(x, y)| x != y).count() / 2;
+    let m = matches as f64;
+    (m / a.len() as f64 + m / b.len() as f64 + (m - transpositions as f64) / m) / 3.0
+}
+
+/// American Soundex: the first letter and three digits, e.g. `R163` for Robert
+pub fn soundex(s: &str) -> String {
+    fn code(c: char) -> Option<char> {
+        match c {
+            'B' | 'F' | 'P' | 'V' => Some('1'),
+            'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => Some('2'),
+            'D' | 'T' => Some('3'),
+            'L' => Some('4'),
+            'M' | 'N' => Some('5'),
+            'R' => Some('6'),
+            _ => None,
+        }
+    }
+    let mut letters = s.chars().filter(char::is_ascii_alphabetic).map(|c| c.to_ascii_uppercase());
+    let Some(first) = letters.next() else {
+        return String::new();
+    };
+    let mut out = String::from(first);
+    let mut last = code(first);
+    for c in letters {
+        match code(c) {
+            Some(digit) if last != Some(digit) => {
+                out.push(digit);
+                if out.len() == 4 {
+                    break;
+                }
+                last = Some(digit);
+            }
+            Some(_) => {}
+            // H and W do not separate equal codes; vowels do
+            None if c == 'H' || c == 'W' => {}
+            None => last = None,
+        }
+    }
+    while out.len() < 4 {
+        out.push('0');
+    }
+    out
+}
+
+/// Lawrence Philips' original Metaphone. `0` stands for "th" and `X` for "sh".
+pub fn metaphone(s: &str) -> String {
+    let w: Vec<char> = s.chars().filter(char::is_ascii_alphabetic).map(|c| c.to_ascii_uppercase()).collect();
+    let at = |i: usize| w.get(i).copied().unwrap_or('\0');
+    let is_vowel = |c: char| matches!(c, 'A' | 'E' | 'I' | 'O' | 'U');
+    let front_vowel = |c: char| matches!(c, 'E' | 'I' | 'Y');
+
+    let mut out = String::new();
+    let mut i = 0;
+    // Initial letter exceptions
+    match (at(0), at(1)) {
+        ('A', 'E') | ('G', 'N') | ('K', 'N') | ('P', 'N') | ('W', 'R') => i = 1,
+        ('X', _) => {
+            out.push('S');
+            i = 1;
+        }
+        ('W', 'H') => {
+            out.push('W');
+            i = 2;
+        }
+        _ => {}
+    }
+    while i < w.len() {
+        let c = w[i];
+        let (prev, next, after) = (if i > 0 { at(i - 1) } else { '\0' }, at(i + 1), at(i + 2));
+        // Doubled letters sound once, except C
+        if c == prev && c != 'C' {
+            i += 1;
+            continue;
+        }
+        match c {
+            'A' | 'E' | 'I' | 'O' | 'U' => {
+                if i == 0 {
+                    out.push(c);
+                }
+            }
+            'B' => {
+                if !(prev == 'M' && i + 1 == w.len()) {
+                    out.push('B');
+                }
+            }
+            'C' => {
+                if next == 'I' && after == 'A' {
+                    out.push('X');
+                } else if next == 'H' {
+                    out.push(if prev == 'S' { 'K' } else { 'X' });
+                    i += 1;
+                } else if front_vowel(next) {
+                    if prev != 'S' {
+                        out.push('S');
+                    }
+                } else {
+                    out.push('K');
+                }
+            }
+            'D' => {
+                if next == 'G' && front_vowel(after) {
+                    out.push('J');
+                    i += 1;
+                } else {
+                    out.push('T');
+                }
+            }
+            'G' => {
+                let silent_gh = next == 'H' && i + 2 < w.len() && !is_vowel(after);
+                let silent_gn = next == 'N' && (i + 2 == w.len() || (after == 'E' && at(i + 3) == 'D' && i + 4 == w.len()));
+                if !(silent_gh || silent_gn) {
+                    out.push(if front_vowel(next) && prev != 'G' { 'J' } else { 'K' });
+                }
+            }
+            'H' => {
+                let after_modifier = matches!(prev, 'C' | 'S' | 'P' | 'T' | 'G');
+                if !after_modifier && (!is_vowel(prev) || is_vowel(next)) {
+                    out.push('H');
+                }
+            }
+            'K' => {
+                if prev != 'C' {
+                    out.push('K');
+                }
+            }
+            'P' => out.push(if next == 'H' { 'F' } else { 'P' }),
+            'Q' => out.push('K'),
+            'S' => {
+                if next == 'H' || (next == 'I' && matches!(after, 'O' | 'A')) {
+                    out.push('X');
+                } else {
+                    out.push('S');
+                }
+            }
+            'T' => {
+                if next == 'I' && matches!(after, 'O' | 'A') {
+                    out.push('X');
+                } else if next == 'H' {
+                    out.push('0');
+                } else if !(next == 'C' && after == 'H') {
+                    out.push('T');
+                }
+            }
+            'V' => out.push('F'),
+            'W' | 'Y' => {
+                if is_vowel(next) {
+                    out.push(c);
+                }
+            }
+            'X' => out.push_str("KS"),
+            'Z' => out.push('S'),
+            other => out.push(other),
+        }
+        i += 1;
+    }
+    out
+}
+
+/// LEVENSHTEIN(a, b) -> edit distance
+pub fn exec_levenshtein(args: &[Value]) -> Result<Value, Error> {
+    let (a, b) = two_strings("LEVENSHTEIN", args)?;
+    Ok(Value::Number(levenshtein(a, b) as f64))
+}
+
+/// SIMILARITY(a, b) -> 1 - edit distance / length of the longer string, so 1 means equal
+pub fn exec_similarity(args: &[Value]) -> Result<Value, Error> {
+    let (a, b) = two_strings("SIMILARITY", args)?;
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return Ok(Value::Number(1.0));
+    }
+    Ok(Value::Number(1.0 - levenshtein(a, b) as f64 / longest as f64))
+}
+
+/// JARO(a, b) -> Jaro similarity, kinder than SIMILARITY to transposed letters
+pub fn exec_jaro(args: &[Value]) -> Result<Value, Error> {
+    let (a, b) = two_strings("JARO", args)?;
+    Ok(Value::Number(jaro(a, b)))
+}
+
+pub fn exec_soundex(args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::String(soundex(one_string("SOUNDEX", args)?)))
+}
+
+pub fn exec_metaphone(args: &[Value]) -> Result<Value, Error> {
+    Ok(Value::String(metaphone(one_string("METAPHONE", args)?)))
+}
@@ -0,0 +1,60 @@
+//! Time-series smoothing in a single pass over the array.
+//!
+//! Both functions return an array aligned with the input. A null is a missing
+//! observation: it gives a null result and is left out of the averages around it.
+
+use crate::error::Error;
+use crate::runtime::scaling::series;
+use crate::types::Value;
+
+/// MOVINGAVG(arr, window) -> trailing average of the last `window` entries, null until
+/// the first full window. Nulls inside a window are skipped; a window of only nulls
+/// averages to null.
+pub fn exec_moving_avg(args: &[Value]) -> Result<Value, Error> {
+    let (xs, window) = match args {
+        [arr, Value::Number(w)] if w.fract() == 0.0 && *w >= 1.0 => (series("MOVINGAVG", arr)?, *w as usize),
+        [_, _] => return Err(Error::new("MOVINGAVG window must be a whole number of at least 1", None)),
+        _ => return Err(Error::new("MOVINGAVG expects array, window", None)),
+    };
+    let mut sum = 0.0;
+    let mut count = 0usize;
+    let mut out = Vec::with_capacity(xs.len());
+    for (i, x) in xs.iter().enumerate() {
+        if let Some(x) = x {
+            sum += x;
+            count += 1;
+        }
+        // Drop the entry that just left the window
+        if i >= window {
+            if let Some(old) = xs[i - window] {
+                sum -= old;
+                count -= 1;
+            }
+        }
+        out.push(if i + 1 < window || count == 0 { Value::Null } else { Value::Number(sum / count as f64) });
+    }
+    Ok(Value::Array(out))
+}
+
+/// EMA(arr, alpha) -> exponential moving average `alpha * x + (1 - alpha) * previous`,
+/// starting from the first value. `alpha` is in (0, 1]; larger reacts faster.
+pub fn exec_ema(args: &[Value]) -> Result<Value, Error> {
+    let (xs, alpha) = match args {
+        [arr, Value::Number(a)] if *a > 0.0 && *a <= 1.0 => (series("EMA", arr)?, *a),
+        [_, _] => return Err(Error::new("EMA alpha must be a number greater than 0 and at most 1", None)),
+        _ => return Err(Error::new("EMA expects array, alpha", None)),
+    };
+    let mut state: Option<f64> = None;
+    let out = xs
+        .iter()
+        .map(|x| match x {
+            None => Value::Null,
+            Some(x) => {
+                let next = state.map_or(*x, |prev| alpha * x + (1.0 - alpha) * prev);
+                state = Some(next);
+                Value::Number(next)
+            }
+        })
+        .collect();
+    Ok(Value::Array(out))
+}
@@ -1,9 +1,21 @@
 use crate::error::Error;
 use crate::types::Value;
+use crate::runtime::utils::collect_numbers;
 use std::collections::HashMap;
 
 pub fn exec_statistical(name: &str, args: &[Value]) -> Result<Value, Error> {
     match name {
+        "CHOOSEWEIGHTED" => super::random::exec_choose_weighted(args),
+        "PROBABILITY" => super::random::exec_probability(args),
+        "HASHBUCKET" => super::random::exec_hash_bucket(args),
+        "FINGERPRINT" => super::random::exec_fingerprint(args),
+        "BIN" => super::binning::exec_bin(args),
+        "HISTOGRAM" => super::binning::exec_histogram(args),
+        "ZSCORE" => super::scaling::exec_zscore(args),
+        "MINMAXSCALE" => super::scaling::exec_min_max_scale(args),
+        "WINSORIZE" => super::scaling::exec_winsorize(args),
+        "MOVINGAVG" => super::smoothing::exec_moving_avg(args),
+        "EMA" => super::smoothing::exec_ema(args),
         "MEDIAN" => {
             let mut nums: Vec<f64> = Vec::new();
             fn collect_nums(v: &Value, nums: &mut Vec<f64>) {
@@ -71,6 +83,25 @@ pub fn exec_statistical(name: &str, args: &[Value]) -> Result<Value, Error> {
 
             Ok(Value::Number(f64::from_bits(mode_bits)))
         }
+        "MODE.MULT" | "MODEMULT" | "MODE_MULT" => {
+            let mut nums = Vec::new();
+            collect_numbers(args, &mut nums);
+            let mut counts: Vec<(f64, usize)> = Vec::new();
+            for n in nums {
+                match counts.iter_mut().find(|(m, _)| m.to_bits() == n.to_bits()) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((n, 1)),
+                }
+            }
+            // Like Excel, a sample where nothing repeats has no mode
+            let max_count = counts.iter().map(|(_, c)| *c).max().unwrap_or(0);
+            if max_count < 2 {
+                return Ok(Value::Array(Vec::new()));
+            }
+            Ok(Value::Array(
+                counts.into_iter().filter(|(_, c)| *c == max_count).map(|(n, _)| Value::Number(n)).collect(),
+            ))
+        }
         "STDEV.P" | "STDEVP" | "STDEV_P" => {
             let mut nums: Vec<f64> = Vec::new();
             fn collect_nums(v: &Value, nums: &mut Vec<f64>) {
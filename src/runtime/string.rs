@@ -1,6 +1,81 @@
 use crate::error::Error;
-use crate::runtime::utils::is_blank;
+use crate::runtime::utils::{format_number, is_blank, to_count, to_index};
 use crate::types::Value;
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+lazy_static::lazy_static! {
+    static ref SEPARATOR_REGEXES: Mutex<HashMap<String, Regex>> = Mutex::new(HashMap::new());
+}
+
+/// Longest string REPT or `text * n` may produce when no `max_string_length` is set
+pub const MAX_REPEAT_LEN: usize = 10_000_000;
+
+/// `text` repeated `count` times; the length is checked before anything is allocated
+pub(crate) fn repeat(text: &str, count: f64, what: &str) -> Result<String, Error> {
+    let count = to_index(count, &format!("{} count", what))?;
+    if count < 0 {
+        return Err(Error::new(format!("{} count must not be negative", what), None));
+    }
+    let count = count as usize;
+    let len = char_count(text).saturating_mul(count);
+    if len > MAX_REPEAT_LEN {
+        return Err(Error::new(format!("{} would produce {} characters, more than the limit of {}", what, len, MAX_REPEAT_LEN), None));
+    }
+    crate::context::check_string_len(len)?;
+    Ok(text.repeat(count))
+}
+
+/// Compiled separators are kept for reuse; the cache is reset once it holds this many
+const SEPARATOR_CACHE_LIMIT: usize = 256;
+
+fn separator_regex(pattern: &str) -> Result<Regex, Error> {
+    let mut cache = SEPARATOR_REGEXES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(re) = cache.get(pattern) {
+        return Ok(re.clone());
+    }
+    let re = Regex::new(pattern).map_err(|e| Error::new(format!("Invalid split pattern /{}/: {}", pattern, e), None))?;
+    if cache.len() >= SEPARATOR_CACHE_LIMIT {
+        cache.clear();
+    }
+    cache.insert(pattern.to_string(), re.clone());
+    Ok(re)
+}
+
+/// Split `s` on `sep`, which is a regular expression when written as `/pattern/`.
+/// A non-zero `limit` caps the number of parts, the last one keeping the rest.
+pub(crate) fn split_parts(s: &str, sep: &str, limit: usize) -> Result<Vec<String>, Error> {
+    let limit = if limit == 0 { usize::MAX } else { limit };
+    let parts = match sep.strip_prefix('/').and_then(|p| p.strip_suffix('/')) {
+        Some(pattern) if !pattern.is_empty() => {
+            separator_regex(pattern)?.splitn(s, limit).map(str::to_string).collect()
+        }
+        _ => s.splitn(limit, sep).map(str::to_string).collect(),
+    };
+    Ok(parts)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TrimSide {
+    Both,
+    Start,
+    End,
+}
+
+/// Strip whitespace, or any of `chars` when given, from the chosen side(s) of `s`
+pub(crate) fn trim_chars(s: &str, side: TrimSide, chars: Option<&str>) -> String {
+    let strip = |c: char| match chars {
+        Some(set) => set.contains(c),
+        None => c.is_whitespace(),
+    };
+    match side {
+        TrimSide::Both => s.trim_matches(strip),
+        TrimSide::Start => s.trim_start_matches(strip),
+        TrimSide::End => s.trim_end_matches(strip),
+    }
+    .to_string()
+}
 
 /// Get the byte offset corresponding to a character index, without collecting into Vec<char>.
 #[inline]
@@ -23,7 +98,9 @@ pub fn exec_string(name: &str, args: &[Value]) -> Result<Value, Error> {
             // SUBSTITUTE(text, substr, replacement)
             match (args.get(0), args.get(1), args.get(2)) {
                 (Some(Value::String(text)), Some(Value::String(substr)), Some(Value::String(repl))) => {
-                    Ok(Value::String(text.replace(substr, repl)))
+                    let out = text.replace(substr, repl);
+                    crate::context::check_string_len(char_count(&out))?;
+                    Ok(Value::String(out))
                 }
                 _ => Err(Error::new(
                     "SUBSTITUTE expects (text: string, substr: string, replacement: string)",
@@ -35,7 +112,9 @@ pub fn exec_string(name: &str, args: &[Value]) -> Result<Value, Error> {
             // SUBSTITUTEM(text, substr, replacement) - replace all occurrences (alias of SUBSTITUTE)
             match (args.get(0), args.get(1), args.get(2)) {
                 (Some(Value::String(text)), Some(Value::String(substr)), Some(Value::String(repl))) => {
-                    Ok(Value::String(text.replace(substr, repl)))
+                    let out = text.replace(substr, repl);
+                    crate::context::check_string_len(char_count(&out))?;
+                    Ok(Value::String(out))
                 }
                 _ => Err(Error::new(
                     "SUBSTITUTEM expects (text: string, substr: string, replacement: string)",
@@ -57,7 +136,7 @@ pub fn exec_string(name: &str, args: &[Value]) -> Result<Value, Error> {
                 Some(_) => return Err(Error::new("LEFT expects number as second argument", None)),
                 None => 1.0,
             };
-            let take = if n.is_finite() && n > 0.0 { n as usize } else { 0usize };
+            let take = to_count(n, "LEFT num_chars")?;
             let byte_end = char_byte_offset(s, take);
             Ok(Value::String(s[..byte_end].to_string()))
         }
@@ -75,7 +154,7 @@ pub fn exec_string(name: &str, args: &[Value]) -> Result<Value, Error> {
                 Some(_) => return Err(Error::new("RIGHT expects number as second argument", None)),
                 None => 1.0,
             };
-            let take = if n.is_finite() && n > 0.0 { n as usize } else { 0usize };
+            let take = to_count(n, "RIGHT num_chars")?;
             let len = char_count(s);
             let start_char = len.saturating_sub(take);
             let byte_start = char_byte_offset(s, start_char);
@@ -106,14 +185,9 @@ pub fn exec_string(name: &str, args: &[Value]) -> Result<Value, Error> {
 
             let total = char_count(s);
             // Excel-like: 1-based start; clamp below 1 to 1
-            let start_index = if start_num.is_finite() {
-                let s1 = if start_num < 1.0 { 1.0 } else { start_num.floor() } as usize;
-                s1.saturating_sub(1).min(total)
-            } else {
-                0usize
-            };
+            let start_index = to_count(start_num, "MID start")?.max(1).saturating_sub(1).min(total);
             let end_index = if let Some(n) = len_opt {
-                let take = if n.is_finite() && n > 0.0 { n as usize } else { 0usize };
+                let take = to_count(n, "MID num_chars")?;
                 start_index.saturating_add(take).min(total)
             } else {
                 total
@@ -141,7 +215,7 @@ pub fn exec_string(name: &str, args: &[Value]) -> Result<Value, Error> {
                         Ok(())
                     }
                     Value::Number(n) => {
-                        s.push_str(&n.to_string());
+                        s.push_str(&format_number(*n));
                         Ok(())
                     }
                     Value::Array(arr) => {
@@ -155,9 +229,12 @@ pub fn exec_string(name: &str, args: &[Value]) -> Result<Value, Error> {
                         Ok(())
                     }
                     Value::Null => Ok(()),
-                    Value::Currency(_) => Ok(()),
+                    Value::Currency(c) => {
+                        s.push_str(&super::currency::display(*c));
+                        Ok(())
+                    }
                     Value::DateTime(_) => Ok(()),
-                    Value::Json(_) => Ok(()),
+                    Value::Json(_) | Value::Map(_) => Ok(()),
                 }
             }
             for a in args {
@@ -166,8 +243,14 @@ pub fn exec_string(name: &str, args: &[Value]) -> Result<Value, Error> {
                     push_val(&mut out, a)?;
                 }
             }
+            crate::context::check_string_len(char_count(&out))?;
             Ok(Value::String(out))
         }
+        "REPT" => match args {
+            [Value::String(s), Value::Number(n)] => Ok(Value::String(repeat(s, *n, "REPT")?)),
+            [Value::Number(x), Value::Number(n)] => Ok(Value::String(repeat(&format_number(*x), *n, "REPT")?)),
+            _ => Err(Error::new("REPT expects text, count", None)),
+        },
         "UPPER" => match args.get(0) {
             Some(Value::String(s)) => Ok(Value::String(s.to_uppercase())),
             _ => Err(Error::new("UPPER expects string", None)),
@@ -176,10 +259,18 @@ pub fn exec_string(name: &str, args: &[Value]) -> Result<Value, Error> {
             Some(Value::String(s)) => Ok(Value::String(s.to_lowercase())),
             _ => Err(Error::new("LOWER expects string", None)),
         },
-        "TRIM" => match args.get(0) {
-            Some(Value::String(s)) => Ok(Value::String(s.trim().to_string())),
-            _ => Err(Error::new("TRIM expects string", None)),
-        },
+        "TRIM" | "TRIMSTART" | "TRIMEND" => {
+            let side = match name {
+                "TRIMSTART" => TrimSide::Start,
+                "TRIMEND" => TrimSide::End,
+                _ => TrimSide::Both,
+            };
+            match (args.first(), args.get(1)) {
+                (Some(Value::String(s)), None) => Ok(Value::String(trim_chars(s, side, None))),
+                (Some(Value::String(s)), Some(Value::String(chars))) => Ok(Value::String(trim_chars(s, side, Some(chars)))),
+                _ => Err(Error::new(format!("{} expects string, [characters]", name), None)),
+            }
+        }
         "SUBSTRING" => {
             if args.len() < 2 {
                 return Err(Error::new(
@@ -197,7 +288,7 @@ pub fn exec_string(name: &str, args: &[Value]) -> Result<Value, Error> {
                 }
             };
             let start = match args.get(1) {
-                Some(Value::Number(n)) => *n as usize,
+                Some(Value::Number(n)) => to_count(*n, "SUBSTRING start")?,
                 _ => {
                     return Err(Error::new(
                         "SUBSTRING expects number as second argument",
@@ -209,7 +300,7 @@ pub fn exec_string(name: &str, args: &[Value]) -> Result<Value, Error> {
             let string_len = char_count(string);
 
             let end = if let Some(Value::Number(len)) = args.get(2) {
-                let length = *len as usize;
+                let length = to_count(*len, "SUBSTRING length")?;
                 start.saturating_add(length).min(string_len)
             } else {
                 string_len
@@ -227,15 +318,26 @@ pub fn exec_string(name: &str, args: &[Value]) -> Result<Value, Error> {
             }
         }
         "SPLIT" => match (args.get(0), args.get(1)) {
-            (Some(Value::String(s)), Some(Value::String(sep))) => Ok(Value::Array(
-                s.split(sep).map(|p| Value::String(p.to_string())).collect(),
-            )),
+            (Some(Value::String(s)), Some(Value::String(sep))) => {
+                let limit = match args.get(2) {
+                    Some(Value::Number(n)) => to_count(*n, "SPLIT limit")?,
+                    None => 0,
+                    Some(_) => return Err(Error::new("SPLIT expects number as limit", None)),
+                };
+                let parts = split_parts(s, sep, limit)?;
+                Ok(Value::Array(parts.into_iter().map(Value::String).collect()))
+            }
             (Some(Value::String(s)), None) => Ok(Value::Array(
                 s.split(',')
                     .map(|p| Value::String(p.trim().to_string()))
                     .collect(),
             )),
-            _ => Err(Error::new("SPLIT expects string, [separator]", None)),
+            _ => Err(Error::new("SPLIT expects string, [separator], [limit]", None)),
+        },
+        "SPLITLINES" => match args.first() {
+            // lines() accepts both \n and \r\n and ignores a trailing newline
+            Some(Value::String(s)) => Ok(Value::Array(s.lines().map(|l| Value::String(l.to_string())).collect())),
+            _ => Err(Error::new("SPLITLINES expects a string", None)),
         },
         "REPLACE" => {
             // Excel-like: REPLACE(old_text, start_num, num_chars, new_text)
@@ -254,10 +356,10 @@ pub fn exec_string(name: &str, args: &[Value]) -> Result<Value, Error> {
             let len = char_count(old_text);
 
             // Clamp start (1-based) to [1, len+1]
-            let start_idx_1b = if start_num.is_finite() { start_num.floor().max(1.0) as usize } else { 1usize };
+            let start_idx_1b = to_count(start_num, "REPLACE start_num")?.max(1);
             let start_idx = start_idx_1b.saturating_sub(1).min(len);
 
-            let take = if num_chars.is_finite() && num_chars > 0.0 { num_chars.floor() as usize } else { 0usize };
+            let take = to_count(num_chars, "REPLACE num_chars")?;
             let end_idx = start_idx.saturating_add(take).min(len);
 
             let byte_start = char_byte_offset(old_text, start_idx);
@@ -306,6 +408,56 @@ pub fn exec_string(name: &str, args: &[Value]) -> Result<Value, Error> {
                 _ => Err(Error::new("INCLUDES expects string, substring", None)),
             }
         }
+        "CURRENCY" => super::currency::exec_currency(args),
+        "PARSENUMBER" => super::locale::exec_parse_number(args),
+        "LEVENSHTEIN" => super::similarity::exec_levenshtein(args),
+        "SIMILARITY" => super::similarity::exec_similarity(args),
+        "JARO" => super::similarity::exec_jaro(args),
+        "SOUNDEX" => super::similarity::exec_soundex(args),
+        "METAPHONE" => super::similarity::exec_metaphone(args),
+        "TOKENIZE" => super::tokens::exec_tokenize(args),
+        "NGRAMS" => super::tokens::exec_ngrams(args),
+        "TFCOUNT" => super::tokens::exec_tf_count(args),
+        "SCOREDICT" => crate::dictionaries::exec_score_dict(args),
+        "MASK" => super::masking::exec_mask(args),
+        "REDACT" => super::masking::exec_redact(args),
+        "REDACTEMAIL" => super::masking::exec_redact_email(args),
+        "PARSEPHONE" => super::phone::exec_parse_phone(args),
+        "TRYCAST" => super::type_casting::exec_try_cast(args),
+        "PERCENT" => {
+            // PERCENT(ratio, [decimals]) -> "12.5%"; the argument is a ratio, not a percentage
+            let ratio = match args.first() {
+                Some(Value::Number(n)) => *n,
+                _ => return Err(Error::new("PERCENT expects ratio, [decimals]", None)),
+            };
+            let pct = ratio * 100.0;
+            match args.get(1) {
+                None => Ok(Value::String(format!("{}%", format_number(pct)))),
+                Some(Value::Number(d)) if *d >= 0.0 && *d <= 15.0 => {
+                    let factor = 10f64.powi(*d as i32);
+                    let rounded = (pct * factor).round() / factor;
+                    Ok(Value::String(format!("{:.*}%", *d as usize, if rounded == 0.0 { 0.0 } else { rounded })))
+                }
+                Some(_) => Err(Error::new("PERCENT decimals must be a number between 0 and 15", None)),
+            }
+        }
+        "FORMAT" => {
+            // FORMAT(number, [decimals]) -> string; fixed decimals when given
+            let n = match args.first() {
+                Some(Value::Number(n)) | Some(Value::Currency(n)) => *n,
+                _ => return Err(Error::new("FORMAT expects number, [decimals]", None)),
+            };
+            match args.get(1) {
+                None => Ok(Value::String(format_number(n))),
+                Some(Value::Number(d)) if *d >= 0.0 && *d <= 15.0 => {
+                    let formatted = format!("{:.*}", *d as usize, n);
+                    // "-0.00" reads as a bug in user-facing output
+                    let is_zero = formatted.trim_start_matches('-').chars().all(|c| c == '0' || c == '.');
+                    Ok(Value::String(if is_zero { formatted.trim_start_matches('-').to_string() } else { formatted }))
+                }
+                Some(_) => Err(Error::new("FORMAT decimals must be a number between 0 and 15", None)),
+            }
+        }
         _ => Err(Error::new(
             format!("Unknown string function: {}", name),
             None,
@@ -0,0 +1,147 @@
+//! Progressive brackets and tiered pricing: tax tables and price lists written as
+//! `[[limit, rate], ...]` with ascending upper limits. The last limit may be
+//! `null` for an open-ended top bracket.
+
+use crate::error::Error;
+use crate::runtime::utils::format_number;
+use crate::types::Value;
+
+/// One row of a bracket table: the part of the amount up to `limit` (and above the
+/// previous row's limit) is charged at `rate`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Tier {
+    pub limit: f64,
+    pub rate: f64,
+}
+
+/// Read and check a `[[limit, rate], ...]` table for the function `name`
+pub(crate) fn parse_tiers(name: &str, table: &Value) -> Result<Vec<Tier>, Error> {
+    let rows = match table {
+        Value::Array(rows) if !rows.is_empty() => rows,
+        _ => return Err(Error::new(format!("{} expects a table like [[limit, rate], ...]", name), None)),
+    };
+    let mut tiers: Vec<Tier> = Vec::with_capacity(rows.len());
+    for (i, row) in rows.iter().enumerate() {
+        let (limit, rate) = match row {
+            Value::Array(pair) if pair.len() == 2 => (&pair[0], &pair[1]),
+            _ => return Err(Error::new(format!("{} row {} must be [limit, rate]", name, i + 1), None)),
+        };
+        let limit = match limit {
+            Value::Number(n) | Value::Currency(n) if n.is_finite() => *n,
+            Value::Null if i == rows.len() - 1 => f64::INFINITY,
+            Value::Null => return Err(Error::new(format!("{} row {}: only the last row may have a null limit", name, i + 1), None)),
+            _ => return Err(Error::new(format!("{} row {}: the limit must be a number", name, i + 1), None)),
+        };
+        let rate = match rate {
+            Value::Number(n) | Value::Currency(n) if n.is_finite() => *n,
+            _ => return Err(Error::new(format!("{} row {}: the rate must be a number", name, i + 1), None)),
+        };
+        let previous = tiers.last().map(|t| t.limit).unwrap_or(0.0);
+        if limit <= previous {
+            return Err(Error::new(
+                format!(
+                    "{} row {}: limits must increase from 0, got {} after {}",
+                    name,
+                    i + 1,
+                    format_number(limit),
+                    format_number(previous)
+                ),
+                None,
+            ));
+        }
+        tiers.push(Tier { limit, rate });
+    }
+    Ok(tiers)
+}
+
+/// The part of `amount` falling in each bracket it reaches, as (row index, part).
+/// Whatever lies above the last limit counts toward the last row.
+fn slices(amount: f64, tiers: &[Tier]) -> Vec<(usize, f64)> {
+    let mut out = Vec::new();
+    let mut lower = 0.0;
+    for (i, tier) in tiers.iter().enumerate() {
+        if amount <= lower {
+            break;
+        }
+        let top = if i == tiers.len() - 1 { amount } else { amount.min(tier.limit) };
+        out.push((i, top - lower));
+        lower = tier.limit;
+    }
+    out
+}
+
+/// Sum of each bracket's rate times the part of `amount` that falls in it
+pub(crate) fn progressive_total(amount: f64, tiers: &[Tier]) -> f64 {
+    slices(amount, tiers).iter().map(|(i, part)| part * tiers[*i].rate).sum()
+}
+
+/// The row whose bracket contains `amount` (the last row past the last limit)
+fn containing_tier(amount: f64, tiers: &[Tier]) -> usize {
+    tiers.iter().position(|t| amount <= t.limit).unwrap_or(tiers.len() - 1)
+}
+
+/// BRACKET(amount, [[limit, rate], ...]) -> progressive total, e.g. income tax.
+/// A Currency amount gives a Currency total.
+pub fn exec_bracket(args: &[Value]) -> Result<Value, Error> {
+    let tiers = match args {
+        [_, table] => parse_tiers("BRACKET", table)?,
+        _ => return Err(Error::new("BRACKET expects amount, [[limit, rate], ...]", None)),
+    };
+    match &args[0] {
+        Value::Number(n) => Ok(Value::Number(progressive_total(*n, &tiers))),
+        Value::Currency(n) => Ok(Value::Currency(progressive_total(*n, &tiers))),
+        _ => Err(Error::new("BRACKET amount must be a number", None)),
+    }
+}
+
+/// TIERPRICE(quantity, [[up_to, unit_price], ...], [mode], [breakdown]).
+///
+/// `"graduated"` (the default) prices each unit at its own tier's price; `"volume"`
+/// prices every unit at the price of the tier the whole quantity reaches. With
+/// `breakdown` TRUE the result is one object per tier used,
+/// `{"tier", "units", "unit_price", "amount"}`, instead of the total.
+pub fn exec_tier_price(args: &[Value]) -> Result<Value, Error> {
+    const USAGE: &str = "TIERPRICE expects quantity, [[up_to, unit_price], ...], [\"graduated\" | \"volume\"], [breakdown]";
+    let (quantity, table) = match args {
+        [Value::Number(q), table, ..] if args.len() <= 4 => (*q, table),
+        _ => return Err(Error::new(USAGE, None)),
+    };
+    let tiers = parse_tiers("TIERPRICE", table)?;
+    let volume = match args.get(2) {
+        None | Some(Value::Null) => false,
+        Some(Value::String(mode)) if mode.eq_ignore_ascii_case("graduated") => false,
+        Some(Value::String(mode)) if mode.eq_ignore_ascii_case("volume") => true,
+        Some(other) => {
+            return Err(Error::new(format!("TIERPRICE mode must be \"graduated\" or \"volume\", got {:?}", other), None))
+        }
+    };
+    let breakdown = match args.get(3) {
+        None => false,
+        Some(Value::Boolean(b)) => *b,
+        Some(_) => return Err(Error::new(USAGE, None)),
+    };
+
+    let parts: Vec<(usize, f64)> = if quantity <= 0.0 {
+        Vec::new()
+    } else if volume {
+        vec![(containing_tier(quantity, &tiers), quantity)]
+    } else {
+        slices(quantity, &tiers)
+    };
+    if !breakdown {
+        return Ok(Value::Number(parts.iter().map(|(i, units)| units * tiers[*i].rate).sum()));
+    }
+    let rows = parts
+        .iter()
+        .map(|(i, units)| {
+            let row = serde_json::json!({
+                "tier": i + 1,
+                "units": units,
+                "unit_price": tiers[*i].rate,
+                "amount": units * tiers[*i].rate,
+            });
+            Value::Json(row.to_string())
+        })
+        .collect();
+    Ok(Value::Array(rows))
+}
@@ -0,0 +1,95 @@
+//! Tokens and n-grams for keyword-scoring formulas, e.g. routing support tickets.
+//!
+//! A token is a run of letters and digits, lowercased for the locale; an apostrophe
+//! between letters stays inside the word, so "don't" is one token. Everything else
+//! (spaces, punctuation, symbols) separates tokens.
+
+use crate::error::Error;
+use crate::types::Value;
+
+fn is_apostrophe(c: char) -> bool {
+    c == '\'' || c == '\u{2019}'
+}
+
+/// Lowercased tokens of `text` for the locale `tag`
+pub(crate) fn tokenize(text: &str, tag: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    for (i, c) in chars.iter().enumerate() {
+        let joins_word = is_apostrophe(*c)
+            && !current.is_empty()
+            && chars.get(i + 1).is_some_and(|next| next.is_alphanumeric());
+        if c.is_alphanumeric() || joins_word {
+            current.push(*c);
+        } else if !current.is_empty() {
+            tokens.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens.into_iter().map(|t| super::locale::lowercase(&t, tag)).collect()
+}
+
+pub(crate) fn locale_arg(name: &str, arg: Option<&Value>) -> Result<String, Error> {
+    match arg {
+        Some(Value::String(tag)) => Ok(tag.clone()),
+        Some(_) => Err(Error::new(format!("{} locale must be a string like \"tr-TR\"", name), None)),
+        None => Ok(crate::context::with_options(|opts| opts.locale.clone()).unwrap_or_else(|| "en".to_string())),
+    }
+}
+
+/// A token list argument: an array of strings, or text that is tokenized first
+pub(crate) fn token_list(name: &str, arg: &Value) -> Result<Vec<String>, Error> {
+    match arg {
+        Value::String(text) => Ok(tokenize(text, &locale_arg(name, None)?)),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(s) => Ok(s.clone()),
+                other => Err(Error::new(format!("{} expects tokens as strings, got {:?}", name, other), None)),
+            })
+            .collect(),
+        _ => Err(Error::new(format!("{} expects an array of tokens or a text", name), None)),
+    }
+}
+
+/// TOKENIZE(text, [locale]) -> array of lowercased words. The locale defaults to the
+/// `locale` option.
+pub fn exec_tokenize(args: &[Value]) -> Result<Value, Error> {
+    let text = match args {
+        [Value::String(text)] | [Value::String(text), _] => text,
+        _ => return Err(Error::new("TOKENIZE expects text, [locale]", None)),
+    };
+    let tag = locale_arg("TOKENIZE", args.get(1))?;
+    Ok(Value::Array(tokenize(text, &tag).into_iter().map(Value::String).collect()))
+}
+
+/// NGRAMS(tokens, n) -> each run of `n` consecutive tokens, joined by a space
+pub fn exec_ngrams(args: &[Value]) -> Result<Value, Error> {
+    let (tokens, n) = match args {
+        [tokens, Value::Number(n)] => (token_list("NGRAMS", tokens)?, *n),
+        _ => return Err(Error::new("NGRAMS expects tokens, n", None)),
+    };
+    let n = crate::runtime::utils::to_index(n, "NGRAMS n")?;
+    if n < 1 {
+        return Err(Error::new("NGRAMS n must be at least 1", None));
+    }
+    let grams = tokens.windows(n as usize).map(|w| Value::String(w.join(" "))).collect();
+    Ok(Value::Array(grams))
+}
+
+/// TFCOUNT(tokens) -> object of token -> number of occurrences, keys sorted
+pub fn exec_tf_count(args: &[Value]) -> Result<Value, Error> {
+    let tokens = match args {
+        [tokens] => token_list("TFCOUNT", tokens)?,
+        _ => return Err(Error::new("TFCOUNT expects tokens", None)),
+    };
+    let mut counts = serde_json::Map::new();
+    for token in tokens {
+        let count = counts.get(&token).and_then(serde_json::Value::as_u64).unwrap_or(0);
+        counts.insert(token, serde_json::Value::from(count + 1));
+    }
+    Ok(Value::Json(serde_json::Value::Object(counts).to_string()))
+}
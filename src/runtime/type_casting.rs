@@ -1,6 +1,7 @@
 use crate::ast::TypeName;
 use crate::error::Error;
 use crate::types::Value;
+use crate::runtime::method_calls::conversion_methods::value_to_json_value;
 
 pub fn cast_value(v: Value, ty: &TypeName) -> Result<Value, Error> {
     Ok(match ty {
@@ -16,8 +17,9 @@ pub fn cast_value(v: Value, ty: &TypeName) -> Result<Value, Error> {
             _ => return Err(Error::new("Cannot cast to Float", None)),
         },
         TypeName::Integer => match v {
-            Value::Number(n) => Value::Number((n as i64) as f64),
-            Value::Currency(n) => Value::Number((n as i64) as f64),
+            Value::Number(n) | Value::Currency(n) => {
+                Value::Number((crate::context::check_integer(n, "::Integer")? as i64) as f64)
+            }
             Value::String(s) => {
                 let mut clean_s = String::new();
                 let mut has_dot = false;
@@ -45,13 +47,14 @@ pub fn cast_value(v: Value, ty: &TypeName) -> Result<Value, Error> {
         },
         TypeName::String => match v {
             Value::String(s) => Value::String(s),
-            Value::Number(n) => Value::String(n.to_string()),
+            Value::Number(n) => Value::String(crate::runtime::utils::format_number(n)),
             Value::Boolean(b) => Value::String(if b { "TRUE".into() } else { "FALSE".into() }),
             Value::Null => Value::String(String::new()),
             Value::Array(items) => Value::String(format!("{:?}", items)),
-            Value::Currency(n) => Value::String(format!("{:.4}", n)),
+            Value::Currency(n) => Value::String(crate::runtime::currency::display(n)),
             Value::DateTime(ts) => Value::String(ts.to_string()),
             Value::Json(s) => Value::String(s),
+            map @ Value::Map(_) => Value::String(value_to_json_value(&map)?.to_string()),
         },
         TypeName::Boolean => match v {
             Value::Boolean(b) => Value::Boolean(b),
@@ -62,6 +65,7 @@ pub fn cast_value(v: Value, ty: &TypeName) -> Result<Value, Error> {
             Value::Null => Value::Boolean(false),
             Value::DateTime(ts) => Value::Boolean(ts != 0),
             Value::Json(s) => Value::Boolean(!s.trim().is_empty()),
+            Value::Map(map) => Value::Boolean(!map.is_empty()),
         },
         TypeName::Array => match v {
             Value::Array(items) => Value::Array(items),
@@ -80,15 +84,19 @@ pub fn cast_value(v: Value, ty: &TypeName) -> Result<Value, Error> {
         },
         TypeName::DateTime => match v {
             Value::DateTime(ts) => Value::DateTime(ts),
-            Value::Number(n) => Value::DateTime(n as i64),
+            Value::Number(n) => Value::DateTime(crate::context::check_integer(n, "::DateTime")? as i64),
             Value::String(s) => Value::DateTime(
-                s.parse::<i64>()
-                    .map_err(|_| Error::new("Cannot cast String to DateTime", None))?,
+                s.trim()
+                    .parse::<i64>()
+                    .ok()
+                    .or_else(|| crate::runtime::datetime::parse_iso_datetime(&s))
+                    .ok_or_else(|| Error::new("Cannot cast String to DateTime", None))?,
             ),
             _ => return Err(Error::new("Cannot cast to DateTime", None)),
         },
         TypeName::Json => match v {
             Value::Json(s) => Value::Json(s),
+            map @ Value::Map(_) => Value::Json(value_to_json_value(&map)?.to_string()),
             Value::String(s) => Value::Json(s),
             Value::Number(n) => Value::Json(n.to_string()),
             Value::Boolean(b) => Value::Json(if b {
@@ -118,3 +126,44 @@ pub fn cast_value(v: Value, ty: &TypeName) -> Result<Value, Error> {
         },
     })
 }
+
+/// Like [`cast_value`], but `null` instead of an error, and for text that does not
+/// actually spell a value of the type (where a cast would fall back to 0 or `false`).
+/// `null` stays `null`.
+pub fn try_cast(v: Value, ty: &TypeName) -> Value {
+    match (&v, ty) {
+        (Value::Null, _) => Value::Null,
+        (Value::String(s), TypeName::Integer | TypeName::Float | TypeName::Currency) => {
+            match s.trim().parse::<f64>() {
+                Ok(n) if n.is_finite() => cast_value(Value::Number(n), ty).unwrap_or(Value::Null),
+                _ => Value::Null,
+            }
+        }
+        (Value::String(s), TypeName::Boolean) => match s.trim().to_lowercase().as_str() {
+            "true" | "yes" | "1" => Value::Boolean(true),
+            "false" | "no" | "0" => Value::Boolean(false),
+            _ => Value::Null,
+        },
+        (Value::String(s), TypeName::Json) => match serde_json::from_str::<serde_json::Value>(s) {
+            Ok(_) => Value::Json(s.clone()),
+            Err(_) => Value::Null,
+        },
+        _ => cast_value(v, ty).unwrap_or(Value::Null),
+    }
+}
+
+/// TRYCAST(value, type_name) -> the value cast to `type_name` ("Number", "Integer",
+/// "Currency", "DateTime", ...), or `null` when it cannot be
+pub fn exec_try_cast(args: &[Value]) -> Result<Value, Error> {
+    let (value, name) = match args {
+        [value, Value::String(name)] => (value, name),
+        _ => return Err(Error::new("TRYCAST expects value, type name", None)),
+    };
+    let ty = TypeName::from_name(name).ok_or_else(|| {
+        Error::new(
+            format!("TRYCAST does not know the type '{}'; use Integer, Number, String, Boolean, Array, Currency, DateTime or Json", name),
+            None,
+        )
+    })?;
+    Ok(try_cast(value.clone(), &ty))
+}
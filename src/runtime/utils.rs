@@ -1,11 +1,38 @@
 use crate::error::Error;
 use crate::types::Value;
 
+/// Significant digits numbers are cut to when rendered as text. f64 only carries
+/// 15-17, and the last ones are where artifacts like `0.30000000000000004` live.
+pub(crate) const DISPLAY_SIGNIFICANT_DIGITS: u32 = 15;
+
+/// Render a number as text the same way everywhere (`to_s`, CONCAT, JOIN, FORMAT, ...):
+/// float artifacts are dropped and the current evaluation's `significant_digits` /
+/// `precision` options are honored.
+pub fn format_number(n: f64) -> String {
+    if !n.is_finite() {
+        return n.to_string();
+    }
+    let rounded = crate::context::with_options(|opts| {
+        let digits = opts.significant_digits.unwrap_or(DISPLAY_SIGNIFICANT_DIGITS).min(DISPLAY_SIGNIFICANT_DIGITS);
+        let n = crate::options::round_significant(n, digits);
+        match opts.precision {
+            Some(p) => crate::options::round_to(n, p),
+            None => n,
+        }
+    });
+    if rounded == 0.0 {
+        // Avoid "-0"
+        return "0".to_string();
+    }
+    rounded.to_string()
+}
+
 pub fn is_blank(v: &Value) -> bool {
     match v {
         Value::Null => true,
         Value::String(s) => s.trim().is_empty(),
         Value::Array(items) => items.is_empty(),
+        Value::Map(map) => map.is_empty(),
         _ => false,
     }
 }
@@ -28,6 +55,29 @@ pub fn clamp_index(len: usize, idx: isize) -> Option<usize> {
     }
 }
 
+/// Convert a number used as a position or count to an integer. Fractions, non-finite
+/// values and magnitudes beyond `isize` are errors instead of being truncated or
+/// saturated by an `as` cast; `what` names the argument in the message.
+pub fn to_index(n: f64, what: &str) -> Result<isize, Error> {
+    if !n.is_finite() {
+        return Err(Error::new(format!("{} must be a finite number, got {}", what, format_number(n)), None));
+    }
+    if n.fract() != 0.0 {
+        return Err(Error::new(format!("{} must be a whole number, got {}", what, format_number(n)), None));
+    }
+    // Both bounds exclusive: isize::MAX as f64 rounds up to 2^63, and negating
+    // isize::MIN would overflow when counting from the end
+    if n <= isize::MIN as f64 || n >= isize::MAX as f64 {
+        return Err(Error::new(format!("{} {} is out of range", what, format_number(n)), None));
+    }
+    Ok(n as isize)
+}
+
+/// [`to_index`] for counts and 0-based offsets, where negatives mean 0
+pub fn to_count(n: f64, what: &str) -> Result<usize, Error> {
+    Ok(to_index(n, what)?.max(0) as usize)
+}
+
 pub fn index_array(items: Vec<Value>, idx: isize) -> Result<Value, Error> {
     match clamp_index(items.len(), idx) {
         Some(i) => Ok(items[i].clone()),
@@ -42,17 +92,17 @@ pub fn slice_array(
 ) -> Result<Value, Error> {
     let len = items.len() as isize;
     let s = match start {
-        Some(Value::Number(n)) => n as isize,
+        Some(Value::Number(n)) => to_index(n, "Slice start")?,
         None => 0,
         Some(_) => return Err(Error::new("Slice bounds must be numbers", None)),
     };
     let e = match end {
-        Some(Value::Number(n)) => n as isize,
+        Some(Value::Number(n)) => to_index(n, "Slice end")?,
         None => len,
         Some(_) => return Err(Error::new("Slice bounds must be numbers", None)),
     };
-    let s_norm = if s < 0 { len + s } else { s };
-    let e_norm = if e < 0 { len + e } else { e };
+    let s_norm = if s < 0 { len.saturating_add(s) } else { s };
+    let e_norm = if e < 0 { len.saturating_add(e) } else { e };
     let s_idx = s_norm.max(0).min(len) as usize;
     let e_idx = e_norm.max(0).min(len) as usize;
     if s_idx > e_idx {
@@ -61,6 +111,69 @@ pub fn slice_array(
     Ok(Value::Array(items[s_idx..e_idx].to_vec()))
 }
 
+/// Default tolerance of APPROXEQ when neither an argument nor the `epsilon` option gives one
+pub const DEFAULT_EPSILON: f64 = 1e-9;
+
+/// `|a - b| <= epsilon`, scaled by the larger magnitude once that exceeds 1 so
+/// large amounts are compared relatively
+pub fn approx_eq(a: f64, b: f64, epsilon: f64) -> bool {
+    if a == b {
+        return true;
+    }
+    let scale = a.abs().max(b.abs()).max(1.0);
+    (a - b).abs() <= epsilon * scale
+}
+
+/// Order of two sort keys: numbers (currency included) and datetimes by value, text
+/// by code point, false before true, and null after everything else. Keys of
+/// different kinds do not compare; `what` names the caller in the error.
+pub(crate) fn compare_sort_keys(a: &Value, b: &Value, what: &str) -> Result<std::cmp::Ordering, Error> {
+    use std::cmp::Ordering;
+    let kind = |v: &Value| match v {
+        Value::Number(_) | Value::Currency(_) => "a number",
+        Value::String(_) => "text",
+        Value::Boolean(_) => "a boolean",
+        Value::DateTime(_) => "a datetime",
+        Value::Null => "null",
+        Value::Array(_) => "an array",
+        Value::Json(_) | Value::Map(_) => "an object",
+    };
+    Ok(match (a, b) {
+        (Value::Number(x) | Value::Currency(x), Value::Number(y) | Value::Currency(y)) => x.total_cmp(y),
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Boolean(x), Value::Boolean(y)) => x.cmp(y),
+        (Value::DateTime(x), Value::DateTime(y)) => x.cmp(y),
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Greater,
+        (_, Value::Null) => Ordering::Less,
+        _ => {
+            return Err(Error::new(format!("{} cannot compare {} with {}", what, kind(a), kind(b)), None));
+        }
+    })
+}
+
+/// Stable sort of `rows` by their key lists with [`compare_sort_keys`]; later keys
+/// break ties of earlier ones and `descending[i]` flips key `i`. The first pair of
+/// keys that do not compare is reported after sorting.
+pub(crate) fn sort_by_keys<T>(rows: &mut [(Vec<Value>, T)], descending: &[bool], what: &str) -> Result<(), Error> {
+    use std::cmp::Ordering;
+    let mut error = None;
+    rows.sort_by(|(a, _), (b, _)| {
+        for ((x, y), desc) in a.iter().zip(b).zip(descending) {
+            match compare_sort_keys(x, y, what) {
+                Ok(Ordering::Equal) => continue,
+                Ok(order) => return if *desc { order.reverse() } else { order },
+                Err(e) => {
+                    error.get_or_insert(e);
+                    return Ordering::Equal;
+                }
+            }
+        }
+        Ordering::Equal
+    });
+    error.map_or(Ok(()), Err)
+}
+
 pub fn values_equal(a: &Value, b: &Value) -> bool {
     match (a, b) {
         (Value::Number(x), Value::Number(y)) => x == y,
@@ -74,6 +187,132 @@ pub fn values_equal(a: &Value, b: &Value) -> bool {
         (Value::Array(ax), Value::Array(ay)) => {
             ax.len() == ay.len() && ax.iter().zip(ay.iter()).all(|(u, v)| values_equal(u, v))
         }
+        // Maps: same keys with equal values, in any order
+        (Value::Map(mx), Value::Map(my)) => {
+            mx.len() == my.len() && mx.iter().all(|(k, u)| my.get(k).is_some_and(|v| values_equal(u, v)))
+        }
+        _ => false,
+    }
+}
+
+/// `args` with every map, also inside arrays, turned into JSON text, or None when
+/// there are no maps. Builtins and custom functions take objects as `Value::Json`,
+/// so maps are lowered on the way in.
+pub(crate) fn maps_as_json(args: &[Value]) -> Result<Option<Vec<Value>>, Error> {
+    fn has_map(v: &Value) -> bool {
+        match v {
+            Value::Map(_) => true,
+            Value::Array(items) => items.iter().any(has_map),
+            _ => false,
+        }
+    }
+    fn lower(v: &Value) -> Result<Value, Error> {
+        Ok(match v {
+            Value::Map(_) => Value::Json(v.to_json_value()?.to_string()),
+            Value::Array(items) => Value::Array(items.iter().map(lower).collect::<Result<_, _>>()?),
+            other => other.clone(),
+        })
+    }
+    if !args.iter().any(has_map) {
+        return Ok(None);
+    }
+    args.iter().map(lower).collect::<Result<_, _>>().map(Some)
+}
+
+/// Numbers and currency amounts in `values`, descending into arrays
+pub(crate) fn collect_numbers(values: &[Value], out: &mut Vec<f64>) {
+    for value in values {
+        match value {
+            Value::Number(n) | Value::Currency(n) => out.push(*n),
+            Value::Array(items) => collect_numbers(items, out),
+            _ => {}
+        }
+    }
+}
+
+/// Excel-style criteria as used by SUMIF: a number matches equal numbers, text like
+/// `">=20"`, `"<5"` or `"<>0"` compares numbers, and other text matches equal text
+pub(crate) fn meets_criteria(value: &Value, criteria: &Value) -> bool {
+    match criteria {
+        Value::String(crit) => {
+            if let Some(stripped) = crit.strip_prefix(">=") {
+                if let Ok(threshold) = stripped.parse::<f64>() {
+                    match value {
+                        Value::Number(n) => *n >= threshold,
+                        Value::Currency(n) => *n >= threshold,
+                        _ => false,
+                    }
+                } else { false }
+            } else if let Some(stripped) = crit.strip_prefix("<=") {
+                if let Ok(threshold) = stripped.parse::<f64>() {
+                    match value {
+                        Value::Number(n) => *n <= threshold,
+                        Value::Currency(n) => *n <= threshold,
+                        _ => false,
+                    }
+                } else { false }
+            } else if let Some(stripped) = crit.strip_prefix("<>") {
+                if let Ok(threshold) = stripped.parse::<f64>() {
+                    match value {
+                        Value::Number(n) => *n != threshold,
+                        Value::Currency(n) => *n != threshold,
+                        _ => true,
+                    }
+                } else { 
+                    match value {
+                        Value::String(s) => s != stripped,
+                        _ => true,
+                    }
+                }
+            } else if let Some(stripped) = crit.strip_prefix('>') {
+                if let Ok(threshold) = stripped.parse::<f64>() {
+                    match value {
+                        Value::Number(n) => *n > threshold,
+                        Value::Currency(n) => *n > threshold,
+                        _ => false,
+                    }
+                } else { false }
+            } else if let Some(stripped) = crit.strip_prefix('<') {
+                if let Ok(threshold) = stripped.parse::<f64>() {
+                    match value {
+                        Value::Number(n) => *n < threshold,
+                        Value::Currency(n) => *n < threshold,
+                        _ => false,
+                    }
+                } else { false }
+            } else if let Some(stripped) = crit.strip_prefix('=') {
+                if let Ok(threshold) = stripped.parse::<f64>() {
+                    match value {
+                        Value::Number(n) => *n == threshold,
+                        Value::Currency(n) => *n == threshold,
+                        _ => false,
+                    }
+                } else {
+                    match value {
+                        Value::String(s) => s == stripped,
+                        _ => false,
+                    }
+                }
+            } else if let Ok(threshold) = crit.parse::<f64>() {
+                match value {
+                    Value::Number(n) => *n == threshold,
+                    Value::Currency(n) => *n == threshold,
+                    _ => false,
+                }
+            } else {
+                match value {
+                    Value::String(s) => s == crit,
+                    _ => false,
+                }
+            }
+        }
+        Value::Number(threshold) => {
+            match value {
+                Value::Number(n) => *n == *threshold,
+                Value::Currency(n) => *n == *threshold,
+                _ => false,
+            }
+        }
         _ => false,
     }
 }
@@ -0,0 +1,516 @@
+//! Compact binary encoding of parsed expressions.
+//!
+//! Lets callers cache or ship a parsed AST (Redis, another service, a CLI formula
+//! bundle) and load it later without re-parsing. Every payload starts with a
+//! magic header, a format version and a payload kind; decoding refuses any other
+//! version, so a cached entry from an incompatible release is re-parsed rather
+//! than misread.
+//!
+//! Layout: lengths and counts are LEB128 varints, numbers are little-endian
+//! `f64`, strings are a length followed by UTF-8 bytes, and each node is a tag
+//! byte followed by its fields in declaration order.
+
+use std::sync::Arc;
+
+use crate::ast::{BinaryOp, Expr, Pattern, TypeName, UnaryOp};
+use crate::compiled::CompiledExpr;
+use crate::error::Error;
+
+const MAGIC: &[u8; 3] = b"SKX";
+
+/// Bumped whenever the encoding of any node changes
+pub const FORMAT_VERSION: u8 = 1;
+
+const KIND_EXPR: u8 = 0;
+const KIND_COMPILED: u8 = 1;
+const KIND_BUNDLE: u8 = 2;
+
+/// Guards the recursive decoder against hostile input
+const MAX_DEPTH: usize = 1024;
+
+mod tag {
+    pub const NUMBER: u8 = 0;
+    pub const STRING: u8 = 1;
+    pub const NULL: u8 = 2;
+    pub const UNARY: u8 = 3;
+    pub const BINARY: u8 = 4;
+    pub const VARIABLE: u8 = 5;
+    pub const PROPERTY: u8 = 6;
+    pub const SAFE_PROPERTY: u8 = 7;
+    pub const SAFE_METHOD: u8 = 8;
+    pub const FUNCTION: u8 = 9;
+    pub const SPREAD: u8 = 10;
+    pub const ARRAY: u8 = 11;
+    pub const OBJECT: u8 = 12;
+    pub const METHOD: u8 = 13;
+    pub const INDEX: u8 = 14;
+    pub const SLICE: u8 = 15;
+    pub const CAST: u8 = 16;
+    pub const ASSIGNMENT: u8 = 17;
+    pub const SEQUENCE: u8 = 18;
+    pub const LAMBDA: u8 = 19;
+    pub const DESTRUCTURE: u8 = 20;
+}
+
+impl Expr {
+    /// Encode this expression in the versioned binary format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::new(KIND_EXPR);
+        w.expr(self);
+        w.buf
+    }
+
+    /// Decode an expression produced by [`Expr::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Expr, Error> {
+        let mut r = Reader::new(bytes, KIND_EXPR)?;
+        let expr = r.expr(0)?;
+        r.finish()?;
+        Ok(expr)
+    }
+}
+
+impl CompiledExpr {
+    /// Encode the source text together with its AST
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::new(KIND_COMPILED);
+        w.compiled(self);
+        w.buf
+    }
+
+    /// Decode a value produced by [`CompiledExpr::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<CompiledExpr, Error> {
+        let mut r = Reader::new(bytes, KIND_COMPILED)?;
+        let compiled = r.compiled()?;
+        r.finish()?;
+        Ok(compiled)
+    }
+}
+
+/// Encode several compiled formulas as one bundle
+pub fn encode_bundle(formulas: &[CompiledExpr]) -> Vec<u8> {
+    let mut w = Writer::new(KIND_BUNDLE);
+    w.len(formulas.len());
+    for f in formulas {
+        w.compiled(f);
+    }
+    w.buf
+}
+
+/// Decode a bundle produced by [`encode_bundle`]
+pub fn decode_bundle(bytes: &[u8]) -> Result<Vec<CompiledExpr>, Error> {
+    let mut r = Reader::new(bytes, KIND_BUNDLE)?;
+    let count = r.len()?;
+    let mut formulas = Vec::with_capacity(count.min(1024));
+    for _ in 0..count {
+        formulas.push(r.compiled()?);
+    }
+    r.finish()?;
+    Ok(formulas)
+}
+
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn new(kind: u8) -> Self {
+        let mut buf = Vec::with_capacity(64);
+        buf.extend_from_slice(MAGIC);
+        buf.push(FORMAT_VERSION);
+        buf.push(kind);
+        Self { buf }
+    }
+
+    fn len(&mut self, mut n: usize) {
+        loop {
+            let byte = (n & 0x7f) as u8;
+            n >>= 7;
+            if n == 0 {
+                self.buf.push(byte);
+                return;
+            }
+            self.buf.push(byte | 0x80);
+        }
+    }
+
+    fn str(&mut self, s: &str) {
+        self.len(s.len());
+        self.buf.extend_from_slice(s.as_bytes());
+    }
+
+    fn list(&mut self, items: &[Expr]) {
+        self.len(items.len());
+        for it in items {
+            self.expr(it);
+        }
+    }
+
+    fn compiled(&mut self, compiled: &CompiledExpr) {
+        self.str(compiled.source());
+        self.expr(compiled.expr());
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Number(n) => {
+                self.buf.push(tag::NUMBER);
+                self.buf.extend_from_slice(&n.to_le_bytes());
+            }
+            Expr::StringLit(s) => {
+                self.buf.push(tag::STRING);
+                self.str(s);
+            }
+            Expr::Null => self.buf.push(tag::NULL),
+            Expr::Unary(op, inner) => {
+                self.buf.push(tag::UNARY);
+                self.buf.push(unary_code(*op));
+                self.expr(inner);
+            }
+            Expr::Binary(l, op, r) => {
+                self.buf.push(tag::BINARY);
+                self.buf.push(binary_code(*op));
+                self.expr(l);
+                self.expr(r);
+            }
+            Expr::Variable(name) => {
+                self.buf.push(tag::VARIABLE);
+                self.str(name);
+            }
+            Expr::PropertyAccess { target, property } => {
+                self.buf.push(tag::PROPERTY);
+                self.expr(target);
+                self.str(property);
+            }
+            Expr::SafePropertyAccess { target, property } => {
+                self.buf.push(tag::SAFE_PROPERTY);
+                self.expr(target);
+                self.str(property);
+            }
+            Expr::SafeMethodCall { target, name, args } => {
+                self.buf.push(tag::SAFE_METHOD);
+                self.expr(target);
+                self.str(name);
+                self.list(args);
+            }
+            Expr::FunctionCall { name, args } => {
+                self.buf.push(tag::FUNCTION);
+                self.str(name);
+                self.list(args);
+            }
+            Expr::Spread(inner) => {
+                self.buf.push(tag::SPREAD);
+                self.expr(inner);
+            }
+            Expr::Array(items) => {
+                self.buf.push(tag::ARRAY);
+                self.list(items);
+            }
+            Expr::ObjectLiteral(fields) => {
+                self.buf.push(tag::OBJECT);
+                self.len(fields.len());
+                for (key, value) in fields {
+                    self.str(key);
+                    self.expr(value);
+                }
+            }
+            Expr::MethodCall { target, name, args, predicate } => {
+                self.buf.push(tag::METHOD);
+                self.expr(target);
+                self.str(name);
+                self.list(args);
+                self.buf.push(*predicate as u8);
+            }
+            Expr::Index { target, index } => {
+                self.buf.push(tag::INDEX);
+                self.expr(target);
+                self.expr(index);
+            }
+            Expr::Slice { target, start, end } => {
+                self.buf.push(tag::SLICE);
+                self.expr(target);
+                for bound in [start, end] {
+                    match bound {
+                        Some(e) => {
+                            self.buf.push(1);
+                            self.expr(e);
+                        }
+                        None => self.buf.push(0),
+                    }
+                }
+            }
+            Expr::TypeCast { expr, ty } => {
+                self.buf.push(tag::CAST);
+                self.expr(expr);
+                self.buf.push(type_code(ty));
+            }
+            Expr::Assignment { variable, value } => {
+                self.buf.push(tag::ASSIGNMENT);
+                self.str(variable);
+                self.expr(value);
+            }
+            Expr::Destructure { pattern, value } => {
+                self.buf.push(tag::DESTRUCTURE);
+                self.buf.push(matches!(pattern, Pattern::Object(_)) as u8);
+                self.len(pattern.names().len());
+                for name in pattern.names() {
+                    self.str(name);
+                }
+                self.expr(value);
+            }
+            Expr::Sequence(items) => {
+                self.buf.push(tag::SEQUENCE);
+                self.list(items);
+            }
+            Expr::Lambda { params, body } => {
+                self.buf.push(tag::LAMBDA);
+                self.len(params.len());
+                for param in params {
+                    self.str(param);
+                }
+                self.expr(body);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+fn corrupt(what: &str, pos: usize) -> Error {
+    Error::new(format!("Invalid serialized expression: {} at byte {}", what, pos), Some(pos))
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8], kind: u8) -> Result<Self, Error> {
+        if bytes.len() < MAGIC.len() + 2 || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(Error::new("Invalid serialized expression: missing SKX header", None));
+        }
+        let version = bytes[MAGIC.len()];
+        if version != FORMAT_VERSION {
+            return Err(Error::new(
+                format!(
+                    "Unsupported serialized expression version {} (this build reads version {})",
+                    version, FORMAT_VERSION
+                ),
+                None,
+            ));
+        }
+        let found = bytes[MAGIC.len() + 1];
+        if found != kind {
+            return Err(Error::new(
+                format!("Invalid serialized expression: expected payload kind {}, found {}", kind, found),
+                None,
+            ));
+        }
+        Ok(Self { bytes, pos: MAGIC.len() + 2 })
+    }
+
+    fn finish(&self) -> Result<(), Error> {
+        if self.pos != self.bytes.len() {
+            return Err(corrupt("trailing bytes", self.pos));
+        }
+        Ok(())
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        let b = *self.bytes.get(self.pos).ok_or_else(|| corrupt("unexpected end of input", self.pos))?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        let end = self.pos.checked_add(n).filter(|&e| e <= self.bytes.len());
+        let end = end.ok_or_else(|| corrupt("unexpected end of input", self.pos))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn len(&mut self) -> Result<usize, Error> {
+        let start = self.pos;
+        let mut n: usize = 0;
+        for shift in (0..64).step_by(7) {
+            let b = self.u8()?;
+            n |= ((b & 0x7f) as usize) << shift;
+            if b & 0x80 == 0 {
+                // A count can never exceed the bytes left to describe its items
+                if n > self.bytes.len() {
+                    return Err(corrupt("length out of range", start));
+                }
+                return Ok(n);
+            }
+        }
+        Err(corrupt("length out of range", start))
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let n = self.len()?;
+        let start = self.pos;
+        let raw = self.take(n)?;
+        String::from_utf8(raw.to_vec()).map_err(|_| corrupt("invalid UTF-8", start))
+    }
+
+    fn list(&mut self, depth: usize) -> Result<Vec<Expr>, Error> {
+        let n = self.len()?;
+        let mut items = Vec::with_capacity(n);
+        for _ in 0..n {
+            items.push(self.expr(depth)?);
+        }
+        Ok(items)
+    }
+
+    fn boxed(&mut self, depth: usize) -> Result<Arc<Expr>, Error> {
+        Ok(Arc::new(self.expr(depth)?))
+    }
+
+    fn compiled(&mut self) -> Result<CompiledExpr, Error> {
+        let source = self.string()?;
+        let expr = self.expr(0)?;
+        Ok(CompiledExpr::from_parts(source, expr))
+    }
+
+    fn expr(&mut self, depth: usize) -> Result<Expr, Error> {
+        if depth > MAX_DEPTH {
+            return Err(corrupt("nesting too deep", self.pos));
+        }
+        let d = depth + 1;
+        let at = self.pos;
+        let expr = match self.u8()? {
+            tag::NUMBER => {
+                let raw = self.take(8)?;
+                Expr::Number(f64::from_le_bytes(raw.try_into().expect("8 bytes")))
+            }
+            tag::STRING => Expr::StringLit(self.string()?),
+            tag::NULL => Expr::Null,
+            tag::UNARY => {
+                let op = unary_op(self.u8()?).ok_or_else(|| corrupt("unknown unary operator", at))?;
+                Expr::Unary(op, self.boxed(d)?)
+            }
+            tag::BINARY => {
+                let op = binary_op(self.u8()?).ok_or_else(|| corrupt("unknown binary operator", at))?;
+                let l = self.boxed(d)?;
+                Expr::Binary(l, op, self.boxed(d)?)
+            }
+            tag::VARIABLE => Expr::Variable(self.string()?),
+            tag::PROPERTY => Expr::PropertyAccess { target: self.boxed(d)?, property: self.string()? },
+            tag::SAFE_PROPERTY => Expr::SafePropertyAccess { target: self.boxed(d)?, property: self.string()? },
+            tag::SAFE_METHOD => Expr::SafeMethodCall { target: self.boxed(d)?, name: self.string()?, args: self.list(d)? },
+            tag::FUNCTION => Expr::FunctionCall { name: self.string()?, args: self.list(d)? },
+            tag::SPREAD => Expr::Spread(self.boxed(d)?),
+            tag::ARRAY => Expr::Array(self.list(d)?),
+            tag::OBJECT => {
+                let n = self.len()?;
+                let mut fields = Vec::with_capacity(n);
+                for _ in 0..n {
+                    fields.push((self.string()?, self.expr(d)?));
+                }
+                Expr::ObjectLiteral(fields)
+            }
+            tag::METHOD => Expr::MethodCall {
+                target: self.boxed(d)?,
+                name: self.string()?,
+                args: self.list(d)?,
+                predicate: self.u8()? != 0,
+            },
+            tag::INDEX => Expr::Index { target: self.boxed(d)?, index: self.boxed(d)? },
+            tag::SLICE => {
+                let target = self.boxed(d)?;
+                let start = if self.u8()? != 0 { Some(self.boxed(d)?) } else { None };
+                let end = if self.u8()? != 0 { Some(self.boxed(d)?) } else { None };
+                Expr::Slice { target, start, end }
+            }
+            tag::CAST => {
+                let expr = self.boxed(d)?;
+                let ty = type_name(self.u8()?).ok_or_else(|| corrupt("unknown cast type", at))?;
+                Expr::TypeCast { expr, ty }
+            }
+            tag::ASSIGNMENT => Expr::Assignment { variable: self.string()?, value: self.boxed(d)? },
+            tag::DESTRUCTURE => {
+                let object = self.u8()? != 0;
+                let n = self.len()?;
+                let mut names = Vec::with_capacity(n);
+                for _ in 0..n {
+                    names.push(self.string()?);
+                }
+                let pattern = if object { Pattern::Object(names) } else { Pattern::Array(names) };
+                Expr::Destructure { pattern, value: self.boxed(d)? }
+            }
+            tag::SEQUENCE => Expr::Sequence(self.list(d)?),
+            tag::LAMBDA => {
+                let n = self.len()?;
+                let mut params = Vec::with_capacity(n);
+                for _ in 0..n {
+                    params.push(self.string()?);
+                }
+                Expr::Lambda { params, body: self.boxed(d)? }
+            }
+            _ => return Err(corrupt("unknown node tag", at)),
+        };
+        Ok(expr)
+    }
+}
+
+// Operator and type codes are part of the format: append, never renumber.
+
+fn unary_code(op: UnaryOp) -> u8 {
+    match op {
+        UnaryOp::Plus => 0,
+        UnaryOp::Minus => 1,
+        UnaryOp::Not => 2,
+    }
+}
+
+fn unary_op(code: u8) -> Option<UnaryOp> {
+    Some(match code {
+        0 => UnaryOp::Plus,
+        1 => UnaryOp::Minus,
+        2 => UnaryOp::Not,
+        _ => return None,
+    })
+}
+
+const BINARY_OPS: [BinaryOp; 14] = [
+    BinaryOp::Add,
+    BinaryOp::Sub,
+    BinaryOp::Mul,
+    BinaryOp::Div,
+    BinaryOp::Mod,
+    BinaryOp::Pow,
+    BinaryOp::Gt,
+    BinaryOp::Lt,
+    BinaryOp::Ge,
+    BinaryOp::Le,
+    BinaryOp::Eq,
+    BinaryOp::Ne,
+    BinaryOp::And,
+    BinaryOp::Or,
+];
+
+fn binary_code(op: BinaryOp) -> u8 {
+    BINARY_OPS.iter().position(|o| *o == op).expect("every operator has a code") as u8
+}
+
+fn binary_op(code: u8) -> Option<BinaryOp> {
+    BINARY_OPS.get(code as usize).copied()
+}
+
+const TYPE_NAMES: [TypeName; 8] = [
+    TypeName::Integer,
+    TypeName::Float,
+    TypeName::String,
+    TypeName::Boolean,
+    TypeName::Array,
+    TypeName::Currency,
+    TypeName::DateTime,
+    TypeName::Json,
+];
+
+fn type_code(ty: &TypeName) -> u8 {
+    TYPE_NAMES.iter().position(|t| t == ty).expect("every type has a code") as u8
+}
+
+fn type_name(code: u8) -> Option<TypeName> {
+    TYPE_NAMES.get(code as usize).cloned()
+}
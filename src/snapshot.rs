@@ -0,0 +1,350 @@
+//! Export and import of the engine state an evaluation depends on, so a result seen
+//! in production can be reproduced in a local session.
+//!
+//! An [`EngineSnapshot`] records the evaluation options (locale, limits, builtin
+//! policy, ...), the request id, the host constants, the cash rounding overrides and
+//! the signature of every registered custom function. It travels as a JSON manifest:
+//!
+//! ```json
+//! {"manifest_version": 1, "skillet_version": "0.5.0", "request_id": "req-42",
+//!  "options": {"locale": "de-DE", "strictness": "strict", ...},
+//!  "constants": {"TAX_RATE": 0.16}, "cash_increments": {"MXN": 0.5},
+//!  "functions": [{"name": "TENANTRATE", "min_args": 0, "max_args": 0, ...}]}
+//! ```
+//!
+//! Function code is not part of the manifest; [`EngineSnapshot::restore`] only checks
+//! that the same functions are registered. Dictionary and feature providers are host
+//! callbacks and are not captured either. Constants are stored as JSON, so currency
+//! constants come back as numbers and dates as text.
+
+use std::collections::BTreeMap;
+
+use serde_json::{json, Map, Value as Json};
+
+use crate::context::EvalContext;
+use crate::error::Error;
+use crate::options::{EvalOptions, Strictness};
+use crate::runtime::method_calls::conversion_methods::value_to_json_value;
+use crate::types::Value;
+
+/// Bumped whenever the manifest layout changes
+pub const MANIFEST_VERSION: u64 = 1;
+
+/// Signature and documentation of a registered custom function
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionManifest {
+    pub name: String,
+    pub min_args: usize,
+    pub max_args: Option<usize>,
+    pub description: Option<String>,
+    pub category: Option<String>,
+}
+
+/// The state an evaluation ran under; see the [module docs](self)
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineSnapshot {
+    /// Version of the engine that captured the snapshot
+    pub skillet_version: String,
+    pub request_id: Option<String>,
+    pub options: EvalOptions,
+    pub constants: BTreeMap<String, Value>,
+    pub cash_increments: BTreeMap<String, f64>,
+    /// Sorted by name
+    pub functions: Vec<FunctionManifest>,
+}
+
+impl EngineSnapshot {
+    /// Capture the global state with the request id and options of the evaluation
+    /// running on this thread, or default options outside of one
+    pub fn capture() -> Self {
+        let ctx = crate::context::current();
+        let registry = crate::registry_snapshot();
+        let mut names = registry.list_functions();
+        names.sort_unstable();
+        let functions = names
+            .into_iter()
+            .filter_map(|name| registry.get(name))
+            .map(|f| FunctionManifest {
+                name: f.name().to_uppercase(),
+                min_args: f.min_args(),
+                max_args: f.max_args(),
+                description: f.description().map(str::to_string),
+                category: f.category().map(str::to_string),
+            })
+            .collect();
+        Self {
+            skillet_version: env!("CARGO_PKG_VERSION").to_string(),
+            request_id: ctx.as_ref().and_then(|c| c.request_id.clone()),
+            options: ctx.map(|c| c.options.clone()).unwrap_or_default(),
+            constants: crate::constants::constant_names()
+                .into_iter()
+                .filter_map(|name| crate::constant(&name).map(|v| (name, v)))
+                .collect(),
+            cash_increments: crate::runtime::currency::cash_increment_overrides().into_iter().collect(),
+            functions,
+        }
+    }
+
+    pub fn with_options(mut self, options: EvalOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The context to install around evaluations reproducing the snapshot
+    pub fn context(&self) -> EvalContext {
+        let ctx = EvalContext::new().with_options(self.options.clone());
+        match &self.request_id {
+            Some(id) => ctx.with_request_id(id.clone()),
+            None => ctx,
+        }
+    }
+
+    /// Make the global state match the snapshot: constants and cash increments are
+    /// replaced. Fails without changing anything when the options are invalid or a
+    /// function of the snapshot is not registered with the same number of arguments.
+    pub fn restore(&self) -> Result<(), Error> {
+        self.options.validate()?;
+        let registry = crate::registry_snapshot();
+        let mut problems = Vec::new();
+        for f in &self.functions {
+            match registry.get(&f.name) {
+                None => problems.push(format!("{} is not registered", f.name)),
+                Some(local) if local.min_args() != f.min_args || local.max_args() != f.max_args => {
+                    problems.push(format!("{} takes {} arguments here but {} in the snapshot", f.name, arity(local.min_args(), local.max_args()), arity(f.min_args, f.max_args)))
+                }
+                Some(_) => {}
+            }
+        }
+        if !problems.is_empty() {
+            return Err(Error::new(format!("Snapshot functions differ: {}", problems.join("; ")), None));
+        }
+        for code in self.cash_increments.keys() {
+            if crate::runtime::currency::lookup(code).is_none() {
+                return Err(Error::new(format!("Unknown currency code '{}'", code), None));
+            }
+        }
+
+        crate::clear_constants();
+        for (name, value) in &self.constants {
+            crate::set_constant(name, value.clone())?;
+        }
+        crate::clear_cash_increments();
+        for (code, increment) in &self.cash_increments {
+            crate::set_cash_increment(code, *increment)?;
+        }
+        Ok(())
+    }
+
+    /// The JSON manifest, pretty-printed
+    pub fn to_json(&self) -> Result<String, Error> {
+        let constants = self
+            .constants
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), value_to_json_value(value)?)))
+            .collect::<Result<Map<String, Json>, Error>>()?;
+        let functions: Vec<Json> = self
+            .functions
+            .iter()
+            .map(|f| {
+                json!({
+                    "name": f.name,
+                    "min_args": f.min_args,
+                    "max_args": f.max_args,
+                    "description": f.description,
+                    "category": f.category,
+                })
+            })
+            .collect();
+        let manifest = json!({
+            "manifest_version": MANIFEST_VERSION,
+            "skillet_version": self.skillet_version,
+            "request_id": self.request_id,
+            "options": options_to_json(&self.options),
+            "constants": constants,
+            "cash_increments": self.cash_increments,
+            "functions": functions,
+        });
+        serde_json::to_string_pretty(&manifest).map_err(|e| Error::new(format!("Failed to serialize snapshot: {}", e), None))
+    }
+
+    /// Read a manifest written by [`to_json`](EngineSnapshot::to_json)
+    pub fn from_json(input: &str) -> Result<Self, Error> {
+        let manifest: Json = serde_json::from_str(input).map_err(|e| Error::new(format!("Invalid snapshot JSON: {}", e), None))?;
+        let manifest = object(&manifest, "snapshot")?;
+        match manifest.get("manifest_version").and_then(Json::as_u64) {
+            Some(v) if v <= MANIFEST_VERSION => {}
+            Some(v) => return Err(Error::new(format!("Snapshot manifest version {} is newer than the supported {}", v, MANIFEST_VERSION), None)),
+            None => return Err(Error::new("Snapshot needs a manifest_version", None)),
+        }
+
+        let mut constants = BTreeMap::new();
+        if let Some(map) = manifest.get("constants") {
+            for (name, value) in object(map, "constants")? {
+                constants.insert(name.clone(), crate::json_to_value(value.clone())?);
+            }
+        }
+        let mut cash_increments = BTreeMap::new();
+        if let Some(map) = manifest.get("cash_increments") {
+            for (code, increment) in object(map, "cash_increments")? {
+                let increment = increment.as_f64().ok_or_else(|| Error::new(format!("Snapshot cash increment for {} must be a number", code), None))?;
+                cash_increments.insert(code.clone(), increment);
+            }
+        }
+        let functions = match manifest.get("functions") {
+            None => Vec::new(),
+            Some(Json::Array(items)) => items.iter().map(function_from_json).collect::<Result<_, _>>()?,
+            Some(_) => return Err(Error::new("Snapshot functions must be an array", None)),
+        };
+
+        Ok(Self {
+            skillet_version: manifest.get("skillet_version").and_then(Json::as_str).unwrap_or_default().to_string(),
+            request_id: optional_string(manifest, "request_id")?,
+            options: match manifest.get("options") {
+                Some(options) => options_from_json(object(options, "options")?)?,
+                None => EvalOptions::default(),
+            },
+            constants,
+            cash_increments,
+            functions,
+        })
+    }
+}
+
+fn arity(min: usize, max: Option<usize>) -> String {
+    match max {
+        Some(max) if max == min => min.to_string(),
+        Some(max) => format!("{} to {}", min, max),
+        None => format!("{} or more", min),
+    }
+}
+
+fn object<'a>(json: &'a Json, what: &str) -> Result<&'a Map<String, Json>, Error> {
+    json.as_object().ok_or_else(|| Error::new(format!("Snapshot {} must be an object", what), None))
+}
+
+fn optional_string(map: &Map<String, Json>, key: &str) -> Result<Option<String>, Error> {
+    match map.get(key) {
+        None | Some(Json::Null) => Ok(None),
+        Some(Json::String(s)) => Ok(Some(s.clone())),
+        Some(_) => Err(Error::new(format!("Snapshot {} must be text", key), None)),
+    }
+}
+
+fn optional_u64(map: &Map<String, Json>, key: &str) -> Result<Option<u64>, Error> {
+    match map.get(key) {
+        None | Some(Json::Null) => Ok(None),
+        Some(v) => v.as_u64().map(Some).ok_or_else(|| Error::new(format!("Snapshot option {} must be a whole number", key), None)),
+    }
+}
+
+fn flag(map: &Map<String, Json>, key: &str) -> Result<bool, Error> {
+    match map.get(key) {
+        None | Some(Json::Null) => Ok(false),
+        Some(Json::Bool(b)) => Ok(*b),
+        Some(_) => Err(Error::new(format!("Snapshot option {} must be true or false", key), None)),
+    }
+}
+
+fn names(map: &Map<String, Json>, key: &str) -> Result<Option<Vec<String>>, Error> {
+    match map.get(key) {
+        None | Some(Json::Null) => Ok(None),
+        Some(Json::Array(items)) => items
+            .iter()
+            .map(|v| v.as_str().map(str::to_string))
+            .collect::<Option<Vec<_>>>()
+            .map(Some)
+            .ok_or_else(|| Error::new(format!("Snapshot option {} must be function names", key), None)),
+        Some(_) => Err(Error::new(format!("Snapshot option {} must be an array", key), None)),
+    }
+}
+
+fn function_from_json(json: &Json) -> Result<FunctionManifest, Error> {
+    let map = object(json, "function")?;
+    let name = optional_string(map, "name")?.ok_or_else(|| Error::new("Snapshot function needs a name", None))?;
+    let min_args = optional_u64(map, "min_args")?.unwrap_or(0) as usize;
+    Ok(FunctionManifest {
+        max_args: optional_u64(map, "max_args")?.map(|n| n as usize),
+        description: optional_string(map, "description")?,
+        category: optional_string(map, "category")?,
+        name,
+        min_args,
+    })
+}
+
+fn options_to_json(options: &EvalOptions) -> Json {
+    json!({
+        "timeout_ms": options.timeout_ms,
+        "locale": options.locale,
+        "max_array_size": options.max_array_size,
+        "max_memory_bytes": options.max_memory_bytes,
+        "max_string_length": options.max_string_length,
+        "strictness": options.strictness.as_str(),
+        "deterministic": options.deterministic,
+        "precision": options.precision,
+        "significant_digits": options.significant_digits,
+        "currency": options.currency,
+        "builtins_only": options.builtins_only,
+        "allowed_builtins": options.allowed_builtins,
+        "denied_builtins": options.denied_builtins,
+        "epsilon": options.epsilon,
+        "overflow_check": options.overflow_check,
+        "null_propagation": options.null_propagation,
+        "now_override": options.now_override,
+        "category_budgets": options.category_budgets,
+    })
+}
+
+/// Options from their manifest form. Unknown keys are refused, since an option this
+/// version does not know could change the results being reproduced.
+fn options_from_json(map: &Map<String, Json>) -> Result<EvalOptions, Error> {
+    const KNOWN: &[&str] = &[
+        "timeout_ms", "locale", "max_array_size", "max_memory_bytes", "max_string_length", "strictness", "deterministic", "precision",
+        "significant_digits", "currency", "builtins_only", "allowed_builtins", "denied_builtins", "epsilon", "overflow_check", "null_propagation",
+        "now_override", "category_budgets",
+    ];
+    if let Some(unknown) = map.keys().find(|k| !KNOWN.contains(&k.as_str())) {
+        return Err(Error::new(format!("Unknown snapshot option '{}'", unknown), None));
+    }
+    let small = |key: &str| -> Result<Option<u32>, Error> {
+        optional_u64(map, key)?
+            .map(|n| u32::try_from(n).map_err(|_| Error::new(format!("Snapshot option {} is out of range", key), None)))
+            .transpose()
+    };
+    let epsilon = match map.get("epsilon") {
+        None | Some(Json::Null) => None,
+        Some(v) => Some(v.as_f64().ok_or_else(|| Error::new("Snapshot option epsilon must be a number", None))?),
+    };
+    let mut category_budgets = BTreeMap::new();
+    if let Some(budgets) = map.get("category_budgets").filter(|v| !v.is_null()) {
+        for (category, ms) in object(budgets, "option category_budgets")? {
+            let ms = ms.as_u64().ok_or_else(|| Error::new(format!("Snapshot category budget for {} must be a whole number", category), None))?;
+            category_budgets.insert(category.clone(), ms);
+        }
+    }
+    Ok(EvalOptions {
+        timeout_ms: optional_u64(map, "timeout_ms")?,
+        locale: optional_string(map, "locale")?,
+        max_array_size: optional_u64(map, "max_array_size")?.map(|n| n as usize),
+        max_memory_bytes: optional_u64(map, "max_memory_bytes")?.map(|n| n as usize),
+        max_string_length: optional_u64(map, "max_string_length")?.map(|n| n as usize),
+        strictness: match optional_string(map, "strictness")? {
+            Some(s) => Strictness::parse(&s)?,
+            None => Strictness::default(),
+        },
+        deterministic: flag(map, "deterministic")?,
+        precision: small("precision")?,
+        significant_digits: small("significant_digits")?,
+        currency: optional_string(map, "currency")?,
+        builtins_only: flag(map, "builtins_only")?,
+        allowed_builtins: names(map, "allowed_builtins")?,
+        denied_builtins: names(map, "denied_builtins")?.unwrap_or_default(),
+        epsilon,
+        overflow_check: flag(map, "overflow_check")?,
+        null_propagation: flag(map, "null_propagation")?,
+        now_override: match map.get("now_override") {
+            None | Some(Json::Null) => None,
+            Some(v) => Some(v.as_i64().ok_or_else(|| Error::new("Snapshot option now_override must be a Unix timestamp", None))?),
+        },
+        category_budgets,
+    })
+}
@@ -93,7 +93,7 @@ pub trait EvaluationContext: Send + Sync {
     }
     
     /// Get custom function registry
-    fn get_custom_registry(&self) -> Option<&std::sync::Arc<std::sync::RwLock<crate::custom::FunctionRegistry>>>;
+    fn get_custom_registry(&self) -> Option<&std::sync::Arc<crate::custom::FunctionRegistry>>;
     
     /// Clone variables for lambda evaluation
     fn clone_variables(&self) -> HashMap<String, Value>;
@@ -1,3 +1,5 @@
+use indexmap::IndexMap;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Number(f64),
@@ -8,6 +10,9 @@ pub enum Value {
     Currency(f64),
     DateTime(i64),
     Json(String),
+    /// An object built in an expression, such as `{"total": :a + :b}`; keys keep
+    /// their insertion order and values keep their types
+    Map(IndexMap<String, Value>),
 }
 
 impl Value {
@@ -24,4 +29,10 @@ impl Value {
             _ => None,
         }
     }
+
+    /// The value as JSON: maps become objects, Currency a number and DateTime a
+    /// string of its Unix seconds. Fails on non-finite numbers and invalid JSON text.
+    pub fn to_json_value(&self) -> Result<serde_json::Value, crate::error::Error> {
+        crate::runtime::method_calls::conversion_methods::value_to_json_value(self)
+    }
 }
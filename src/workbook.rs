@@ -0,0 +1,429 @@
+//! Spreadsheet-style cells with incremental recalculation.
+//!
+//! A [`Workbook`] is one grid of cells. Each cell holds a value or a formula.
+//! Formulas reference other cells by address (`A1`), by range (`B2:B10`) or
+//! by name (`:total`, for cells stored under a name instead of an address).
+//!
+//! Changing a cell marks it and everything downstream of it dirty;
+//! [`Workbook::recalculate`] then re-evaluates only those cells, dependencies first.
+//! Circular references are rejected when the formula is set.
+//!
+//! Address and range references are rewritten into variables before parsing, so
+//! a range must be written without spaces (`A1:A3`) and a ternary that selects
+//! between cells needs spaces around its `:` (`:c ? A1 : B1`).
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::analysis::analyze;
+use crate::compiled::CompiledExpr;
+use crate::error::Error;
+use crate::types::Value;
+
+/// Largest range a single reference may span
+pub const MAX_RANGE_CELLS: usize = 100_000;
+
+/// Variable prefix that stands in for a range reference in rewritten formulas
+const RANGE_PREFIX: &str = "__RANGE_";
+
+#[derive(Debug, Clone)]
+enum Content {
+    Value(Value),
+    Formula(CompiledExpr),
+}
+
+#[derive(Debug, Clone)]
+struct Cell {
+    content: Content,
+    /// Formula source as the user wrote it
+    source: Option<String>,
+    /// Cells read directly, including every cell of each range
+    precedents: BTreeSet<String>,
+    /// Cells and names the formula mentions on their own
+    references: Vec<String>,
+    /// Range variables the formula uses, with the cells each covers
+    ranges: Vec<(String, Vec<String>)>,
+    result: Result<Value, Error>,
+}
+
+/// A grid of cells holding values and formulas
+#[derive(Debug, Clone, Default)]
+pub struct Workbook {
+    cells: HashMap<String, Cell>,
+    /// Reverse edges: cell -> formulas that read it (the cell may be empty)
+    dependents: HashMap<String, BTreeSet<String>>,
+    dirty: BTreeSet<String>,
+}
+
+impl Workbook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store a plain value in `cell` (an address like `B2` or a name)
+    pub fn set_value(&mut self, cell: &str, value: Value) -> Result<(), Error> {
+        let id = cell_id(cell)?;
+        self.replace(id, Cell {
+            content: Content::Value(value.clone()),
+            source: None,
+            precedents: BTreeSet::new(),
+            references: Vec::new(),
+            ranges: Vec::new(),
+            result: Ok(value),
+        });
+        Ok(())
+    }
+
+    /// Store a formula in `cell`. The workbook is left unchanged if the formula
+    /// does not parse or would create a circular reference.
+    pub fn set_formula(&mut self, cell: &str, formula: &str) -> Result<(), Error> {
+        let id = cell_id(cell)?;
+        let rewritten = rewrite_references(formula)?;
+        let compiled = CompiledExpr::compile(&rewritten)?;
+
+        let mut precedents = BTreeSet::new();
+        let mut references = Vec::new();
+        let mut ranges = Vec::new();
+        for var in analyze(compiled.expr()).variables {
+            match var.strip_prefix(RANGE_PREFIX).and_then(|r| r.split_once('_')) {
+                Some((from, to)) => {
+                    let covered = expand_range(from, to)?;
+                    precedents.extend(covered.iter().cloned());
+                    ranges.push((var, covered));
+                }
+                None => {
+                    precedents.insert(var.clone());
+                    references.push(var);
+                }
+            }
+        }
+        if let Some(cycle) = self.cycle_through(&id, &precedents) {
+            return Err(Error::new(format!("Circular reference: {}", cycle.join(" -> ")), None));
+        }
+
+        self.replace(id, Cell {
+            content: Content::Formula(compiled),
+            source: Some(formula.to_string()),
+            precedents,
+            references,
+            ranges,
+            result: Ok(Value::Null),
+        });
+        Ok(())
+    }
+
+    /// Empty `cell`; formulas reading it see `null` from now on
+    pub fn clear(&mut self, cell: &str) -> Result<(), Error> {
+        let id = cell_id(cell)?;
+        if let Some(old) = self.cells.remove(&id) {
+            self.unlink(&id, &old.precedents);
+        }
+        self.dirty.remove(&id);
+        self.mark_dependents_dirty(&id);
+        Ok(())
+    }
+
+    /// Current result of `cell`: `null` when empty, possibly stale until
+    /// [`recalculate`](Self::recalculate) runs
+    pub fn get(&self, cell: &str) -> Result<Value, Error> {
+        let id = cell_id(cell)?;
+        match self.cells.get(&id) {
+            Some(c) => c.result.clone(),
+            None => Ok(Value::Null),
+        }
+    }
+
+    /// The formula stored in `cell`, as written
+    pub fn formula(&self, cell: &str) -> Option<&str> {
+        self.cells.get(&cell_id(cell).ok()?)?.source.as_deref()
+    }
+
+    /// Cells read by `cell`'s formula, sorted
+    pub fn precedents(&self, cell: &str) -> Vec<String> {
+        cell_id(cell)
+            .ok()
+            .and_then(|id| self.cells.get(&id))
+            .map(|c| c.precedents.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Formulas that read `cell` directly, sorted
+    pub fn dependents(&self, cell: &str) -> Vec<String> {
+        cell_id(cell)
+            .ok()
+            .and_then(|id| self.dependents.get(&id))
+            .map(|d| d.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    /// Cells waiting for recalculation, sorted
+    pub fn dirty_cells(&self) -> Vec<String> {
+        self.dirty.iter().cloned().collect()
+    }
+
+    /// Re-evaluate every dirty cell, precedents first, and return them in the
+    /// order they were computed. A failing formula stores its error as the cell's
+    /// result; formulas that read it fail with an error naming that cell.
+    pub fn recalculate(&mut self) -> Vec<String> {
+        let order = self.dirty_order();
+        for id in &order {
+            let result = self.evaluate_cell(id);
+            if let Some(cell) = self.cells.get_mut(id) {
+                cell.result = result;
+            }
+        }
+        self.dirty.clear();
+        order
+    }
+
+    fn evaluate_cell(&self, id: &str) -> Result<Value, Error> {
+        let cell = &self.cells[id];
+        let formula = match &cell.content {
+            Content::Formula(f) => f,
+            Content::Value(v) => return Ok(v.clone()),
+        };
+
+        let mut vars = HashMap::with_capacity(cell.references.len() + cell.ranges.len());
+        for p in &cell.references {
+            vars.insert(p.clone(), self.precedent_value(p)?);
+        }
+        for (var, covered) in &cell.ranges {
+            // Blank cells are skipped, as spreadsheet aggregates do
+            let mut items = Vec::with_capacity(covered.len());
+            for c in covered.iter().filter(|c| self.cells.contains_key(*c)) {
+                items.push(self.precedent_value(c)?);
+            }
+            vars.insert(var.clone(), Value::Array(items));
+        }
+        formula.evaluate_with_custom(&vars)
+    }
+
+    fn precedent_value(&self, id: &str) -> Result<Value, Error> {
+        match self.cells.get(id) {
+            Some(c) => c.result.clone().map_err(|e| Error::new(format!("Error in {}: {}", id, e.message), None)),
+            None => Ok(Value::Null),
+        }
+    }
+
+    fn replace(&mut self, id: String, cell: Cell) {
+        if let Some(old) = self.cells.remove(&id) {
+            self.unlink(&id, &old.precedents);
+        }
+        for p in &cell.precedents {
+            self.dependents.entry(p.clone()).or_default().insert(id.clone());
+        }
+        self.cells.insert(id.clone(), cell);
+        self.dirty.insert(id.clone());
+        self.mark_dependents_dirty(&id);
+    }
+
+    fn unlink(&mut self, id: &str, precedents: &BTreeSet<String>) {
+        for p in precedents {
+            if let Some(d) = self.dependents.get_mut(p) {
+                d.remove(id);
+                if d.is_empty() {
+                    self.dependents.remove(p);
+                }
+            }
+        }
+    }
+
+    fn mark_dependents_dirty(&mut self, id: &str) {
+        let mut stack: Vec<String> = self.dependents.get(id).into_iter().flatten().cloned().collect();
+        while let Some(next) = stack.pop() {
+            if self.dirty.insert(next.clone()) {
+                stack.extend(self.dependents.get(&next).into_iter().flatten().cloned());
+            }
+        }
+    }
+
+    /// Path `id -> ... -> id` if `id` reading `precedents` would close a loop
+    fn cycle_through(&self, id: &str, precedents: &BTreeSet<String>) -> Option<Vec<String>> {
+        fn walk<'a>(wb: &'a Workbook, target: &str, at: &'a str, path: &mut Vec<&'a str>, seen: &mut BTreeSet<&'a str>) -> bool {
+            if at == target {
+                return true;
+            }
+            if !seen.insert(at) {
+                return false;
+            }
+            path.push(at);
+            if let Some(cell) = wb.cells.get(at) {
+                for p in &cell.precedents {
+                    if walk(wb, target, p, path, seen) {
+                        return true;
+                    }
+                }
+            }
+            path.pop();
+            false
+        }
+
+        let mut seen = BTreeSet::new();
+        for p in precedents {
+            let mut path = Vec::new();
+            if walk(self, id, p, &mut path, &mut seen) {
+                let mut cycle = vec![id.to_string()];
+                cycle.extend(path.into_iter().map(str::to_string));
+                cycle.push(id.to_string());
+                return Some(cycle);
+            }
+        }
+        None
+    }
+
+    /// Dirty cells ordered so each comes after its dirty precedents
+    fn dirty_order(&self) -> Vec<String> {
+        fn visit(wb: &Workbook, id: &str, done: &mut BTreeSet<String>, order: &mut Vec<String>) {
+            if !done.insert(id.to_string()) {
+                return;
+            }
+            if let Some(cell) = wb.cells.get(id) {
+                for p in cell.precedents.iter().filter(|p| wb.dirty.contains(*p)) {
+                    visit(wb, p, done, order);
+                }
+                order.push(id.to_string());
+            }
+        }
+
+        let mut done = BTreeSet::new();
+        let mut order = Vec::with_capacity(self.dirty.len());
+        for id in &self.dirty {
+            visit(self, id, &mut done, &mut order);
+        }
+        order
+    }
+}
+
+/// Canonical key for a cell: upper-case address (`b2` -> `B2`) or the name as given
+fn cell_id(cell: &str) -> Result<String, Error> {
+    let cell = cell.trim().trim_start_matches(':');
+    if let Some((col, row)) = parse_address(cell) {
+        return Ok(format_address(col, row));
+    }
+    let mut chars = cell.chars();
+    let valid = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid && !cell.starts_with(RANGE_PREFIX) {
+        Ok(cell.to_string())
+    } else {
+        Err(Error::new(format!("Invalid cell reference '{}'", cell), None))
+    }
+}
+
+/// `AB12` -> (28, 12); columns and rows are 1-based
+fn parse_address(s: &str) -> Option<(u32, u32)> {
+    let split = s.find(|c: char| c.is_ascii_digit())?;
+    let (letters, digits) = s.split_at(split);
+    if letters.is_empty() || letters.len() > 3 || !letters.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    if digits.starts_with('0') || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let col = letters.bytes().fold(0u32, |acc, b| acc * 26 + (b.to_ascii_uppercase() - b'A' + 1) as u32);
+    Some((col, digits.parse().ok()?))
+}
+
+fn format_address(col: u32, row: u32) -> String {
+    let mut letters = Vec::new();
+    let mut c = col;
+    while c > 0 {
+        let rem = (c - 1) % 26;
+        letters.push(b'A' + rem as u8);
+        c = (c - 1) / 26;
+    }
+    letters.reverse();
+    format!("{}{}", String::from_utf8(letters).expect("ASCII"), row)
+}
+
+/// Cells of `from:to`, row by row
+fn expand_range(from: &str, to: &str) -> Result<Vec<String>, Error> {
+    let invalid = || Error::new(format!("Invalid range {}:{}", from, to), None);
+    let (c1, r1) = parse_address(from).ok_or_else(invalid)?;
+    let (c2, r2) = parse_address(to).ok_or_else(invalid)?;
+    let (cols, rows) = (c1.min(c2)..=c1.max(c2), r1.min(r2)..=r1.max(r2));
+    let count = cols.clone().count() * rows.clone().count();
+    if count > MAX_RANGE_CELLS {
+        return Err(Error::new(
+            format!("Range {}:{} covers {} cells (limit {})", from, to, count, MAX_RANGE_CELLS),
+            None,
+        ));
+    }
+    Ok(rows.flat_map(|r| cols.clone().map(move |c| format_address(c, r))).collect())
+}
+
+/// Turn bare `A1` into `:A1` and `A1:B3` into a range variable, leaving string
+/// literals, variables, properties, methods and function names untouched
+fn rewrite_references(src: &str) -> Result<String, Error> {
+    let bytes = src.as_bytes();
+    let mut out = String::with_capacity(src.len() + 8);
+    let mut i = 0;
+    let mut copied = 0;
+
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    let address_end = |start: usize| -> Option<usize> {
+        let mut j = start;
+        while j < bytes.len() && bytes[j].is_ascii_alphabetic() {
+            j += 1;
+        }
+        let letters_end = j;
+        while j < bytes.len() && bytes[j].is_ascii_digit() {
+            j += 1;
+        }
+        let valid = letters_end > start && j > letters_end && (j == bytes.len() || !is_ident(bytes[j]));
+        (valid && parse_address(&src[start..j]).is_some()).then_some(j)
+    };
+
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b == b'"' || b == b'\'' {
+            i += 1;
+            while i < bytes.len() && bytes[i] != b {
+                i += if bytes[i] == b'\\' { 2 } else { 1 };
+            }
+            i += 1;
+            continue;
+        }
+        let starts_word = b.is_ascii_alphabetic() && (i == 0 || !(is_ident(bytes[i - 1]) || bytes[i - 1] == b':' || bytes[i - 1] == b'.'));
+        if let Some(end) = starts_word.then(|| address_end(i)).flatten() {
+            if bytes.get(end) == Some(&b'(') {
+                i = end;
+                continue;
+            }
+            out.push_str(&src[copied..i]);
+            let from = src[i..end].to_ascii_uppercase();
+            let range_end = match bytes.get(end) {
+                Some(b':') if bytes.get(end + 1).is_some_and(|b| b.is_ascii_alphabetic()) => address_end(end + 1),
+                _ => None,
+            };
+            match range_end {
+                Some(to_end) => {
+                    let to = src[end + 1..to_end].to_ascii_uppercase();
+                    expand_range(&from, &to)?;
+                    out.push_str(&format!(":{}{}_{}", RANGE_PREFIX, from, to));
+                    i = to_end;
+                }
+                None => {
+                    out.push(':');
+                    out.push_str(&from);
+                    i = end;
+                }
+            }
+            copied = i;
+            continue;
+        }
+        // Skip the rest of an identifier so `TAX2024` is not split into a reference
+        if is_ident(b) {
+            while i < bytes.len() && is_ident(bytes[i]) {
+                i += 1;
+            }
+            continue;
+        }
+        i += 1;
+    }
+    out.push_str(&src[copied.min(src.len())..]);
+    Ok(out)
+}
@@ -44,6 +44,8 @@ fn math_builtins() {
     assert!(approxv(evaluate("AVG(1, 2, 3, 4)").unwrap(), 2.5));
     assert!(approxv(evaluate("MIN([3, 5, 1, 9])").unwrap(), 1.0));
     assert!(approxv(evaluate("MAX(3, 5, 1, 9)").unwrap(), 9.0));
+    assert!(approxv(evaluate("MIN(3, 1, 0/0)").unwrap(), 1.0));
+    assert!(approxv(evaluate("MAX([2, 0/0], 1)").unwrap(), 2.0));
     assert!(approxv(evaluate("AVG([2, 4, 6])").unwrap(), 4.0));
     assert!(approxv(evaluate("SUM([2, 4, 6])").unwrap(), 12.0));
     assert!(approxv(evaluate("ROUND(3.14159, 2)").unwrap(), 3.14));
@@ -95,6 +97,24 @@ fn array_index_and_slice() {
     match evaluate("[1,2,3,4][:2]").unwrap() { Value::Array(v) => assert_eq!(v, vec![Number(1.0), Number(2.0)]), _ => panic!() }
 }
 
+#[test]
+fn positions_must_be_whole_numbers() {
+    let err = |expr: &str| evaluate(expr).unwrap_err().message;
+    assert_eq!(err("[10,20,30][1.9]"), "Index must be a whole number, got 1.9");
+    assert_eq!(err("[10,20,30][0.5:2]"), "Slice start must be a whole number, got 0.5");
+    assert_eq!(err("[10,20,30][1/0]"), "Index must be a finite number, got inf");
+    assert!(err("[10,20,30][10 ^ 30]").contains("out of range"));
+    assert!(err("[10,20,30][-(10 ^ 30)]").contains("out of range"));
+    assert_eq!(err("MID('hello', 1.5, 2)"), "MID start must be a whole number, got 1.5");
+    assert!(err("LEFT('hello', 2.5)").contains("LEFT num_chars"));
+    assert!(err("'hello'.substring(1, 0.5)").contains("substring length"));
+    // Huge but whole slice bounds still clamp to the array
+    let mut vars = HashMap::new();
+    vars.insert("xs".to_string(), Value::Array(vec![Value::Number(1.0), Value::Number(2.0)]));
+    assert_eq!(evaluate_with(":xs[-(10 ^ 15):10 ^ 15]", &vars).unwrap(), vars["xs"]);
+    assert_eq!(evaluate("LEFT('hello', -2)").unwrap(), Value::String(String::new()));
+}
+
 #[test]
 fn type_casting_minimal() {
     use Value::*;
@@ -133,3 +153,46 @@ fn sumif_function() {
     // Test SUMIF with numeric criteria (no string)
     assert!(approxv(evaluate("SUMIF([10, 20, 30, 40], 20)").unwrap(), 20.0));
 }
+
+#[test]
+fn lerp_and_table_interpolation() {
+    assert!(approxv(evaluate("LERP(10, 20, 0.5)").unwrap(), 15.0));
+    assert!(approxv(evaluate("LERP(10, 20, 1.5)").unwrap(), 25.0));
+    assert!(evaluate("LERP(10, 'x', 0.5)").is_err());
+
+    let curve = "[18, 30, 50], [90, 120, 210]";
+    assert!(approxv(evaluate(&format!("INTERP(24, {})", curve)).unwrap(), 105.0));
+    assert!(approxv(evaluate(&format!("INTERP(30, {})", curve)).unwrap(), 120.0));
+    assert!(approxv(evaluate(&format!("INTERP(10, {})", curve)).unwrap(), 90.0));
+    assert!(approxv(evaluate(&format!("INTERP(80, {})", curve)).unwrap(), 210.0));
+    assert!(approxv(evaluate(&format!("INTERP(49, {}, 'step')", curve)).unwrap(), 120.0));
+    assert!(approxv(evaluate(&format!("INTERP(50, {}, 'step')", curve)).unwrap(), 210.0));
+    assert!(approxv(evaluate(&format!("INTERP(5, {}, 'step')", curve)).unwrap(), 90.0));
+    assert_eq!(evaluate(&format!("INTERP(null, {})", curve)).unwrap(), Value::Null);
+
+    assert!(evaluate("INTERP(1, [2, 1], [1, 2])").unwrap_err().message.contains("increasing"));
+    assert!(evaluate("INTERP(1, [1, 2], [1])").unwrap_err().message.contains("same length"));
+    assert!(evaluate(&format!("INTERP(1, {}, 'cubic')", curve)).unwrap_err().message.contains("mode"));
+}
+
+#[test]
+fn aggregate_options_control_non_numeric_items() {
+    let mut vars = HashMap::new();
+    vars.insert(
+        "xs".to_string(),
+        Value::Array(vec![Value::Number(4.0), Value::String("12".into()), Value::Null, Value::Array(vec![Value::Number(2.0)])]),
+    );
+    let eval = |expr: &str| evaluate_with(expr, &vars);
+    assert!(approxv(eval("SUM(:xs)").unwrap(), 6.0));
+    assert!(approxv(eval("SUM(:xs, {'non_numeric': 'ignore'})").unwrap(), 6.0));
+    let err = eval("SUM(:xs, {'non_numeric': 'error'})").unwrap_err();
+    assert!(err.message.contains("SUM found a non-numeric value"), "{}", err.message);
+    assert!(eval("AVG(:xs, {'non_numeric': 'error', 'nulls': 'ignore'})").unwrap_err().message.contains("String"));
+    assert!(approxv(eval("AVG(:xs, {'non_numeric': 'zero'})").unwrap(), 1.5));
+    assert!(approxv(eval("AVG(:xs, {'nulls': 'zero'})").unwrap(), 2.0));
+    assert!(approxv(eval("MIN(:xs, {'non_numeric': 'zero'})").unwrap(), 0.0));
+    assert!(approxv(eval("MAX(1, 9, {'nulls': 'error'})").unwrap(), 9.0));
+    assert!(eval("MAX(1, null, {'nulls': 'error'})").is_err());
+    assert!(eval("SUM(:xs, {'strict': true})").unwrap_err().message.contains("no option 'strict'"));
+    assert!(eval("SUM(:xs, {'nulls': 'skip'})").is_err());
+}
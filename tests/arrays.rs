@@ -1,4 +1,4 @@
-use skillet::{evaluate, evaluate_with_assignments, Value};
+use skillet::{evaluate, evaluate_with_assignments, evaluate_with_custom, Value};
 use std::collections::HashMap;
 
 fn s(v: Value) -> String { if let Value::String(s) = v { s } else { panic!("expected string") } }
@@ -111,3 +111,320 @@ fn merge_function_and_method() {
         _ => panic!("Expected array")
     }
 }
+
+fn nums(v: Value) -> Vec<f64> {
+    match v {
+        Value::Array(items) => items.into_iter().map(|i| if let Value::Number(n) = i { n } else { panic!("expected number") }).collect(),
+        other => panic!("expected array, got {:?}", other),
+    }
+}
+
+#[test]
+fn range_literals() {
+    assert_eq!(nums(evaluate("1..5").unwrap()), vec![1.0, 2.0, 3.0, 4.0]);
+    assert_eq!(nums(evaluate("1..=5").unwrap()), vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+    assert!(nums(evaluate("5..1").unwrap()).is_empty());
+    assert!(nums(evaluate("3..3").unwrap()).is_empty());
+    assert_eq!(nums(evaluate("3..=3").unwrap()), vec![3.0]);
+    // Bounds are full expressions; the range binds looser than arithmetic
+    assert_eq!(nums(evaluate("0..2 + 1").unwrap()), vec![0.0, 1.0, 2.0]);
+    let mut vars = HashMap::new();
+    vars.insert("n".to_string(), Value::Number(3.0));
+    assert_eq!(nums(skillet::evaluate_with(":n - 1..=:n * 2", &vars).unwrap()), vec![2.0, 3.0, 4.0, 5.0, 6.0]);
+
+    assert!(matches!(evaluate("SUM(1..=10)").unwrap(), Value::Number(55.0)));
+    assert_eq!(nums(evaluate("MAP(1..=3, :x * 10)").unwrap()), vec![10.0, 20.0, 30.0]);
+    assert!(matches!(evaluate("(1..=4).filter(:x % 2 == 0).sum()").unwrap(), Value::Number(6.0)));
+    // Spread still lexes as `...`
+    assert!(matches!(evaluate("SUM(...[1, 2])").unwrap(), Value::Number(3.0)));
+
+    assert!(evaluate("'a'..3").is_err());
+    assert!(evaluate("0..100000000000").is_err());
+    let opts = skillet::EvalOptions::new().with_max_array_size(10);
+    assert!(skillet::evaluate_with_options("1..=10", &HashMap::new(), &opts).is_ok());
+    assert!(skillet::evaluate_with_options("1..=11", &HashMap::new(), &opts).is_err());
+}
+
+#[test]
+fn range_function() {
+    assert_eq!(nums(evaluate("RANGE(1, 5)").unwrap()), vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+    assert_eq!(nums(evaluate("RANGE(0, 10, 5)").unwrap()), vec![0.0, 5.0, 10.0]);
+    assert_eq!(nums(evaluate("RANGE(0, 9, 4)").unwrap()), vec![0.0, 4.0, 8.0]);
+    assert_eq!(nums(evaluate("RANGE(10, 1, -3)").unwrap()), vec![10.0, 7.0, 4.0, 1.0]);
+    assert_eq!(nums(evaluate("RANGE(0, 0.3, 0.1)").unwrap()).len(), 4);
+    assert!(nums(evaluate("RANGE(5, 1)").unwrap()).is_empty());
+    assert_eq!(nums(evaluate("RANGE(2, 2)").unwrap()), vec![2.0]);
+    assert_eq!(nums(evaluate("MAP(RANGE(1, 3), :x * 10)").unwrap()), vec![10.0, 20.0, 30.0]);
+
+    assert!(evaluate("RANGE(1, 5, 0)").is_err());
+    assert!(evaluate("RANGE('a', 5)").is_err());
+    assert!(evaluate("RANGE(1)").is_err());
+    assert!(evaluate("RANGE(0, 1, 0.0000000001)").is_err());
+    let opts = skillet::EvalOptions::new().with_max_array_size(10);
+    assert!(skillet::evaluate_with_options("RANGE(1, 20, 2)", &HashMap::new(), &opts).is_ok());
+    assert!(skillet::evaluate_with_options("RANGE(1, 11)", &HashMap::new(), &opts).is_err());
+}
+
+#[test]
+fn non_mutating_update_methods() {
+    assert_eq!(nums(evaluate("[1,2].push(3)").unwrap()), vec![1.0, 2.0, 3.0]);
+    assert_eq!(nums(evaluate("[1].push(2, 3)").unwrap()), vec![1.0, 2.0, 3.0]);
+    // An array argument is pushed as one element, unlike merge
+    assert_eq!(evaluate("[1].push([2])").unwrap(), Value::Array(vec![Value::Number(1.0), Value::Array(vec![Value::Number(2.0)])]));
+
+    assert_eq!(nums(evaluate("[1,3].insert(1, 2)").unwrap()), vec![1.0, 2.0, 3.0]);
+    assert_eq!(nums(evaluate("[1,2].insert(2, 3)").unwrap()), vec![1.0, 2.0, 3.0]);
+    assert_eq!(nums(evaluate("[1,2].insert(-1, 3)").unwrap()), vec![1.0, 2.0, 3.0]);
+    assert_eq!(nums(evaluate("[2,3].insert(-3, 1)").unwrap()), vec![1.0, 2.0, 3.0]);
+    assert!(evaluate("[1,2].insert(3, 9)").unwrap_err().message.contains("out of bounds"));
+
+    assert_eq!(nums(evaluate("[1,2,3].remove_at(0)").unwrap()), vec![2.0, 3.0]);
+    assert_eq!(nums(evaluate("[1,2,3].remove_at(-1)").unwrap()), vec![1.0, 2.0]);
+    assert!(evaluate("[1,2,3].remove_at(3)").is_err());
+
+    assert_eq!(nums(evaluate("[1,0,3].set(1, 2)").unwrap()), vec![1.0, 2.0, 3.0]);
+    assert_eq!(nums(evaluate("[1,2,0].replace_at(-1, 3)").unwrap()), vec![1.0, 2.0, 3.0]);
+    assert!(evaluate("[].set(0, 1)").is_err());
+    assert!(evaluate("[1,2].set(0.5, 1)").unwrap_err().message.contains("whole number"));
+
+    // The receiver is left untouched, so results build up through assignments
+    let vars = HashMap::new();
+    let result = evaluate_with_assignments(":acc := []; :acc := :acc.push(1); :more := :acc.push(2); [:acc, :more]", &vars).unwrap();
+    assert_eq!(result, Value::Array(vec![
+        Value::Array(vec![Value::Number(1.0)]),
+        Value::Array(vec![Value::Number(1.0), Value::Number(2.0)]),
+    ]));
+}
+
+#[test]
+fn partition_and_split_when() {
+    let groups = |v: Value| match v {
+        Value::Array(items) => items.into_iter().map(nums).collect::<Vec<_>>(),
+        other => panic!("expected array, got {:?}", other),
+    };
+    assert_eq!(groups(evaluate("[5,1,4,2].partition(:x > 2)").unwrap()), vec![vec![5.0, 4.0], vec![1.0, 2.0]]);
+    assert_eq!(groups(evaluate("[1,2].partition(:n > 5, 'n')").unwrap()), vec![vec![], vec![1.0, 2.0]]);
+    assert_eq!(groups(evaluate("[].partition(:x > 2)").unwrap()), vec![Vec::<f64>::new(), vec![]]);
+
+    assert_eq!(
+        groups(evaluate("[1,2,3,7,8,10].split_when(:x - :prev > 1)").unwrap()),
+        vec![vec![1.0, 2.0, 3.0], vec![7.0, 8.0], vec![10.0]]
+    );
+    assert_eq!(groups(evaluate("[3,2,1].split_when(:x > :prev)").unwrap()), vec![vec![3.0, 2.0, 1.0]]);
+    assert_eq!(groups(evaluate("[1].split_when(true)").unwrap()), vec![vec![1.0]]);
+    assert!(evaluate("[1].partition()").is_err());
+}
+
+#[test]
+fn tally_and_mode_mult() {
+    let tally = evaluate("['b', 'a', 'b', null].tally()").unwrap();
+    assert_eq!(tally, Value::Json(r#"{"a":1,"b":2}"#.to_string()));
+    assert_eq!(evaluate("['a', 'b', 'a'].tally().a").unwrap(), Value::Number(2.0));
+    assert_eq!(evaluate("[].tally()").unwrap(), Value::Json("{}".to_string()));
+    assert!(evaluate("[[1], [1]].tally()").unwrap_err().message.contains("tally counts"));
+
+    assert_eq!(nums(evaluate("MODE_MULT(3, 1, 1, 3, 2)").unwrap()), vec![3.0, 1.0]);
+    assert_eq!(nums(evaluate("MODE_MULT([5, 5, 7])").unwrap()), vec![5.0]);
+    assert!(nums(evaluate("MODEMULT(1, 2, 3)").unwrap()).is_empty());
+}
+
+#[test]
+fn page_and_offset_limit() {
+    let page = |expr: &str| match evaluate(expr).unwrap() {
+        Value::Json(s) => serde_json::from_str::<serde_json::Value>(&s).unwrap(),
+        other => panic!("expected object, got {:?}", other),
+    };
+    assert_eq!(
+        page("PAGE(1..=7, 2, 3)"),
+        serde_json::json!({"items": [4.0, 5.0, 6.0], "total": 7, "pages": 3, "page": 2, "per_page": 3})
+    );
+    assert_eq!(page("PAGE(1..=7, 3, 3)")["items"], serde_json::json!([7.0]));
+    assert_eq!(page("PAGE(1..=7, 4, 3)")["items"], serde_json::json!([]));
+    assert_eq!(page("PAGE([], 1, 10)")["pages"], 0);
+    assert_eq!(page("PAGE([{'id': 1}, {'id': 2}], 1, 1)")["items"], serde_json::json!([{"id": 1.0}]));
+    assert_eq!(evaluate("PAGE(['a', 'b', 'c'], 2, 2).items").unwrap(), Value::Array(vec![Value::String("c".into())]));
+    assert!(evaluate("PAGE([1], 0, 10)").unwrap_err().message.contains("PAGE page must be a whole number of at least 1"));
+    assert!(evaluate("PAGE([1], 1, 2.5)").is_err());
+    // Sizes too large for an index are errors rather than saturating
+    for expr in ["PAGE([1, 2, 3], 10^300, 2)", "PAGE([1], 1, 10^19)", "OFFSETLIMIT([1], 10^300, 1)"] {
+        assert!(evaluate(expr).unwrap_err().message.contains("out of range"), "{}", expr);
+    }
+
+    assert_eq!(evaluate("OFFSETLIMIT([1, 2, 3, 4], 1, 2)").unwrap(), evaluate("[2, 3]").unwrap());
+    assert_eq!(evaluate("OFFSETLIMIT([1, 2], 5, 2)").unwrap(), Value::Array(vec![]));
+    assert_eq!(evaluate("OFFSETLIMIT([1, 2], 0, 0)").unwrap(), Value::Array(vec![]));
+    assert!(evaluate("OFFSETLIMIT([1, 2], -1, 2)").is_err());
+}
+
+#[test]
+fn test_ordinal_and_nth() {
+    let s = |v: &str| Value::String(v.to_string());
+    assert_eq!(evaluate("ORDINAL('gold', ['bronze', 'silver', 'gold'])").unwrap(), Value::Number(2.0));
+    assert_eq!(evaluate("ORDINAL('tin', ['bronze', 'silver', 'gold'])").unwrap(), Value::Null);
+    assert_eq!(evaluate("ORDINAL(null, ['bronze'])").unwrap(), Value::Null);
+    assert!(evaluate("ORDINAL('gold', 'gold')").is_err());
+
+    assert_eq!(evaluate("NTH(['low', 'med', 'high'], 1)").unwrap(), s("med"));
+    assert_eq!(evaluate("NTH(['low', 'med', 'high'], -1)").unwrap(), s("high"));
+    assert!(evaluate("NTH(['low', 'med', 'high'], 3)").unwrap_err().message.contains("out of bounds"));
+    assert_eq!(evaluate("NTH(['low', 'med', 'high'], 3, 'null')").unwrap(), Value::Null);
+    assert_eq!(evaluate("NTH(['low', 'med', 'high'], 7, 'clamp')").unwrap(), s("high"));
+    assert_eq!(evaluate("NTH(['low', 'med', 'high'], -9, 'clamp')").unwrap(), s("low"));
+    assert_eq!(evaluate("NTH(['low', 'med', 'high'], 4, 'wrap')").unwrap(), s("med"));
+    assert!(evaluate("NTH(['low'], 1.5)").is_err());
+    assert!(evaluate("NTH(['low'], 2, 'saturate')").unwrap_err().message.contains("out_of_range"));
+    assert_eq!(
+        evaluate("NTH(['low', 'med', 'high'], ORDINAL('silver', ['bronze', 'silver', 'gold']))").unwrap(),
+        s("med")
+    );
+}
+
+#[test]
+fn test_bin_and_histogram() {
+    let nums = |xs: &[f64]| Value::Array(xs.iter().map(|x| Value::Number(*x)).collect());
+    assert_eq!(evaluate("BIN(49.9, [50, 80])").unwrap(), Value::Number(0.0));
+    assert_eq!(evaluate("BIN(50, [50, 80])").unwrap(), Value::Number(1.0));
+    assert_eq!(evaluate("BIN(1000, [50, 80])").unwrap(), Value::Number(2.0));
+    assert_eq!(evaluate("BIN(3, [])").unwrap(), Value::Number(0.0));
+    assert_eq!(evaluate("BIN(null, [50])").unwrap(), Value::Null);
+    assert!(evaluate("BIN('a', [50])").is_err());
+    assert!(evaluate("BIN(1, [50, 50])").unwrap_err().message.contains("increasing"));
+
+    assert_eq!(evaluate("HISTOGRAM([1, 2, 3, 4, 5, 6], [2, 4])").unwrap(), nums(&[1.0, 2.0, 3.0]));
+    assert_eq!(evaluate("HISTOGRAM([], [0])").unwrap(), nums(&[0.0, 0.0]));
+    assert!(evaluate("HISTOGRAM([1, 'x'], [0])").is_err());
+    assert_eq!(
+        evaluate("NTH(['low', 'mid', 'high'], BIN(65, [50, 80]))").unwrap(),
+        Value::String("mid".to_string())
+    );
+}
+
+#[test]
+fn test_normalization_helpers() {
+    let nums = |xs: &[f64]| Value::Array(xs.iter().map(|x| Value::Number(*x)).collect());
+    assert_eq!(evaluate("ZSCORE([1, null, 3])").unwrap(), Value::Array(vec![Value::Number(-1.0), Value::Null, Value::Number(1.0)]));
+    assert_eq!(evaluate("ZSCORE([5, 5])").unwrap(), nums(&[0.0, 0.0]));
+    assert_eq!(evaluate("ZSCORE([])").unwrap(), nums(&[]));
+    assert!(evaluate("ZSCORE(['a'])").is_err());
+
+    assert_eq!(evaluate("MINMAXSCALE([-2, 0, 6])").unwrap(), nums(&[0.0, 0.25, 1.0]));
+    assert_eq!(evaluate("MINMAXSCALE([3, 3])").unwrap(), nums(&[0.0, 0.0]));
+
+    // 10th and 90th percentiles of 1..=11 are 2 and 10
+    assert_eq!(
+        evaluate("WINSORIZE([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 1000], 0.1)").unwrap(),
+        nums(&[2.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0, 10.0])
+    );
+    assert_eq!(evaluate("WINSORIZE([1, 9], 0)").unwrap(), nums(&[1.0, 9.0]));
+    assert!(evaluate("WINSORIZE([1, 2], 0.6)").unwrap_err().message.contains("between 0 and 0.5"));
+    assert!(evaluate("WINSORIZE([1, 2])").is_err());
+}
+
+#[test]
+fn test_moving_averages() {
+    let n = |x: f64| Value::Number(x);
+    assert_eq!(evaluate("MOVINGAVG([2, 4, 6, 8], 2)").unwrap(), Value::Array(vec![Value::Null, n(3.0), n(5.0), n(7.0)]));
+    assert_eq!(evaluate("MOVINGAVG([2, 4], 1)").unwrap(), Value::Array(vec![n(2.0), n(4.0)]));
+    assert_eq!(evaluate("MOVINGAVG([1, 2], 5)").unwrap(), Value::Array(vec![Value::Null, Value::Null]));
+    // Nulls are skipped inside the window
+    assert_eq!(
+        evaluate("MOVINGAVG([2, null, 6, null, null], 2)").unwrap(),
+        Value::Array(vec![Value::Null, n(2.0), n(6.0), n(6.0), Value::Null])
+    );
+    assert!(evaluate("MOVINGAVG([1, 2], 0)").unwrap_err().message.contains("window"));
+    assert!(evaluate("MOVINGAVG([1, 2], 1.5)").is_err());
+
+    assert_eq!(evaluate("EMA([4, null, 8], 0.25)").unwrap(), Value::Array(vec![n(4.0), Value::Null, n(5.0)]));
+    assert_eq!(evaluate("EMA([null, 3], 1)").unwrap(), Value::Array(vec![Value::Null, n(3.0)]));
+    assert!(evaluate("EMA([1], 0)").unwrap_err().message.contains("alpha"));
+    assert!(evaluate("EMA('x', 0.5)").is_err());
+}
+
+#[test]
+fn test_unique_by_and_distinctby() {
+    let users = "[{'email': 'a@x.io', 'n': 1}, {'email': 'b@x.io', 'n': 2}, {'email': 'a@x.io', 'n': 3}]";
+    let ns = |expr: String| evaluate(&format!("{}.map(:x.n)", expr)).unwrap();
+    let expected = evaluate("[1, 2]").unwrap();
+    assert_eq!(ns(format!("{}.unique_by(:x.email)", users)), expected);
+    assert_eq!(ns(format!("DISTINCTBY({}, :x.email)", users)), expected);
+    assert_eq!(ns(format!("DISTINCTBY({}, LOWER(:u.email), 'u')", users)), expected);
+    assert_eq!(
+        evaluate_with_custom("[3, 1, 4, 1, 5].unique_by(:x % 2)", &HashMap::new()).unwrap(),
+        evaluate("[3, 4]").unwrap()
+    );
+    // Nulls are a key like any other
+    assert_eq!(evaluate("[null, 1, null].unique_by(:x)").unwrap(), evaluate("[null, 1]").unwrap());
+    assert!(evaluate("DISTINCTBY('abc', :x)").is_err());
+    assert!(evaluate("[1].unique_by()").is_err());
+}
+
+#[test]
+fn test_sort_by() {
+    let sales = "[{'id': 1, 'region': 'west', 'amount': 5}, {'id': 2, 'region': 'east', 'amount': 5}, {'id': 3, 'region': 'west', 'amount': 9}, {'id': 4, 'region': 'east', 'amount': 7}]";
+    let ids = |expr: String| evaluate(&format!("{}.map(:x.id)", expr)).unwrap();
+    // Stable: equal amounts keep their input order
+    assert_eq!(ids(format!("{}.sort_by(x -> x.amount)", sales)), evaluate("[1, 2, 4, 3]").unwrap());
+    // Region ascending, then amount descending
+    assert_eq!(ids(format!("{}.sort_by([x -> x.region, x -> -x.amount])", sales)), evaluate("[4, 2, 3, 1]").unwrap());
+    // Negation flips text keys too, and implicit :x keys work
+    assert_eq!(ids(format!("{}.sort_by([-:x.region, :x.id])", sales)), evaluate("[1, 3, 2, 4]").unwrap());
+    assert_eq!(
+        evaluate_with_custom("['b', null, 'a'].sort_by(:s, 's')", &HashMap::new()).unwrap(),
+        evaluate("['a', 'b', null]").unwrap()
+    );
+
+    assert!(evaluate("[1, 'a'].sort_by(:x)").unwrap_err().message.contains("sort_by cannot compare text with a number"));
+    assert!(evaluate("[1].sort_by([])").is_err());
+    assert!(evaluate("[1].sort_by()").is_err());
+}
+
+#[test]
+fn test_orderby() {
+    let mut vars = HashMap::new();
+    vars.insert("sales".to_string(), skillet::json_to_value(serde_json::json!([
+        {"id": 1, "name": "Cy", "amount": 5, "rep": {"city": "Oslo"}},
+        {"id": 2, "name": "Al", "amount": 5, "rep": {"city": "Lima"}},
+        {"id": 3, "name": "Bo", "amount": 9},
+        {"id": 4, "name": "Al", "amount": 7, "rep": {"city": "Rome"}}
+    ])).unwrap());
+    let ids = |order: &str| evaluate_with_custom(&format!("MAP(ORDERBY(:sales, '{}'), :x.id)", order), &vars).unwrap();
+    assert_eq!(ids("amount DESC, name ASC"), evaluate("[3, 4, 2, 1]").unwrap());
+    // Direction defaults to ascending and ties keep their input order
+    assert_eq!(ids("name"), evaluate("[2, 4, 3, 1]").unwrap());
+    assert_eq!(ids(" amount desc "), evaluate("[3, 4, 1, 2]").unwrap());
+    // Dotted paths reach into nested objects; missing fields sort as null, last
+    assert_eq!(ids("rep.city, id DESC"), evaluate("[2, 1, 4, 3]").unwrap());
+    // Object literals work as well as JSON variables
+    assert_eq!(
+        evaluate("MAP(ORDERBY([{'k': 2}, {'k': 1}], 'k'), :x.k)").unwrap(),
+        evaluate("[1, 2]").unwrap()
+    );
+
+    for (expr, message) in [
+        ("ORDERBY(:sales, 'amount DOWN')", "must read like"),
+        ("ORDERBY(:sales, 'amount; DROP')", "must read like"),
+        ("ORDERBY(:sales, 'amount,')", "must read like"),
+        ("ORDERBY(:sales, 'rep..city')", "must read like"),
+        ("ORDERBY(:sales, 'name, amount DESC extra')", "must read like"),
+        ("ORDERBY([1, 2], 'x')", "array of objects"),
+        ("ORDERBY([{'k': 1}, {'k': 'a'}], 'k')", "ORDERBY cannot compare"),
+        ("ORDERBY(:sales)", "ORDERBY expects array, order"),
+    ] {
+        let err = evaluate_with_custom(expr, &vars).unwrap_err();
+        assert!(err.message.contains(message), "{}: {}", expr, err.message);
+    }
+}
+
+#[test]
+fn test_spread_in_array_literals() {
+    let mut vars = HashMap::new();
+    vars.insert("a".to_string(), Value::Array(vec![Value::Number(1.0), Value::Number(2.0)]));
+    vars.insert("b".to_string(), Value::Array(vec![Value::Number(3.0)]));
+    let expected = Value::Array([1.0, 2.0, 3.0, 5.0].into_iter().map(Value::Number).collect());
+    assert_eq!(evaluate_with_assignments("[...:a, ...:b, 5]", &vars).unwrap(), expected);
+    assert_eq!(evaluate_with_custom("[...:a, ...:b, 5]", &vars).unwrap(), expected);
+    assert_eq!(evaluate("[...[], 0, ...[1]]").unwrap(), Value::Array(vec![Value::Number(0.0), Value::Number(1.0)]));
+    assert_eq!(evaluate_with_assignments("SUM([...:a, ...:b])", &vars).unwrap(), Value::Number(6.0));
+    let err = evaluate("[...5]").unwrap_err();
+    assert!(err.message.contains("Spread expects array"), "{}", err.message);
+}
@@ -0,0 +1,79 @@
+#![cfg(feature = "arrow")]
+
+use std::sync::Arc;
+
+use arrow_array::types::Int32Type;
+use arrow_array::{
+    Array, ArrayRef, Date32Array, Float64Array, Int64Array, ListArray, RecordBatch, StringArray,
+    TimestampMillisecondArray,
+};
+use skillet::arrow::{array_to_values, evaluate_record_batch, values_to_array};
+use skillet::{Dataset, Value};
+
+fn batch() -> RecordBatch {
+    let price: ArrayRef = Arc::new(Float64Array::from(vec![Some(10.0), None, Some(30.0)]));
+    let qty: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3]));
+    let sku: ArrayRef = Arc::new(StringArray::from(vec!["a", "b", "c"]));
+    RecordBatch::try_from_iter(vec![("price", price), ("qty", qty), ("sku", sku)]).unwrap()
+}
+
+#[test]
+fn converts_arrow_columns_to_values() {
+    let values = array_to_values(&Int64Array::from(vec![Some(1), None])).unwrap();
+    assert_eq!(values, vec![Value::Number(1.0), Value::Null]);
+
+    let values = array_to_values(&Date32Array::from(vec![19844])).unwrap();
+    assert_eq!(values, vec![Value::DateTime(19844 * 86_400)]);
+
+    let values = array_to_values(&TimestampMillisecondArray::from(vec![1_714_557_600_500])).unwrap();
+    assert_eq!(values, vec![Value::DateTime(1_714_557_600)]);
+
+    let list = ListArray::from_iter_primitive::<Int32Type, _, _>(vec![Some(vec![Some(1), Some(2)]), None]);
+    let values = array_to_values(&list).unwrap();
+    assert_eq!(values, vec![Value::Array(vec![Value::Number(1.0), Value::Number(2.0)]), Value::Null]);
+}
+
+#[test]
+fn converts_values_to_arrow_columns() {
+    let array = values_to_array(&[Value::Number(1.5), Value::Null]).unwrap();
+    let floats = array.as_any().downcast_ref::<Float64Array>().unwrap();
+    assert_eq!(floats.value(0), 1.5);
+    assert!(floats.is_null(1));
+
+    let array = values_to_array(&[Value::String("x".into())]).unwrap();
+    assert_eq!(array.as_any().downcast_ref::<StringArray>().unwrap().value(0), "x");
+
+    // Objects are written as JSON text
+    let object = skillet::evaluate("{'sku': 'a', 'qty': 2}").unwrap();
+    let array = values_to_array(&[object, Value::Null]).unwrap();
+    let strings = array.as_any().downcast_ref::<StringArray>().unwrap();
+    let json: serde_json::Value = serde_json::from_str(strings.value(0)).unwrap();
+    assert_eq!(json, serde_json::json!({"sku": "a", "qty": 2.0}));
+    assert!(strings.is_null(1));
+
+    // Columns without a non-null value have no type to follow
+    for values in [vec![Value::Null, Value::Null], vec![]] {
+        let array = values_to_array(&values).unwrap();
+        assert_eq!((array.data_type(), array.len()), (&arrow_schema::DataType::Null, values.len()));
+    }
+
+    assert!(values_to_array(&[Value::Number(1.0), Value::String("x".into())]).is_err());
+    assert!(values_to_array(&[Value::Array(vec![])]).is_err());
+}
+
+#[test]
+fn evaluates_every_row_of_a_record_batch() {
+    let result = evaluate_record_batch(":price * :qty", &batch());
+    // Row 1 has a null price
+    assert!(result.unwrap_err().message.starts_with("Row 1: "));
+
+    let result = evaluate_record_batch("IF(ISBLANK(:price), 0, :price) * :qty + :qty.sum()", &batch()).unwrap();
+    let floats = result.as_any().downcast_ref::<Float64Array>().unwrap();
+    assert_eq!(floats.values().to_vec(), vec![16.0, 6.0, 96.0]);
+
+    let dataset = Dataset::from_record_batch(&batch()).unwrap();
+    assert_eq!(dataset.column_names(), ["price", "qty", "sku"]);
+    let back = dataset.to_record_batch().unwrap();
+    assert_eq!(back.num_rows(), 3);
+    assert_eq!(back.schema().field(1).name(), "qty");
+}
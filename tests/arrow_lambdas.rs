@@ -0,0 +1,75 @@
+use skillet::{analyze, evaluate, evaluate_with, evaluate_with_assignments, evaluate_with_custom, parse, Expr, Value};
+use std::collections::HashMap;
+
+fn nums(xs: &[f64]) -> Value {
+    Value::Array(xs.iter().copied().map(Value::Number).collect())
+}
+
+fn vars() -> HashMap<String, Value> {
+    let mut vars = HashMap::new();
+    vars.insert("arr".to_string(), nums(&[5.0, 12.0, 20.0]));
+    vars
+}
+
+#[test]
+fn functions_take_arrow_lambdas() {
+    let vars = vars();
+    for eval in [evaluate_with, evaluate_with_custom, evaluate_with_assignments] {
+        assert_eq!(eval("FILTER(:arr, x -> x > 10)", &vars).unwrap(), nums(&[12.0, 20.0]));
+        assert_eq!(eval("MAP(:arr, (v, i) -> v * i)", &vars).unwrap(), nums(&[0.0, 12.0, 40.0]));
+        assert_eq!(eval("REDUCE(:arr, (acc, v) -> acc + v, 0)", &vars).unwrap(), Value::Number(37.0));
+        assert_eq!(eval("FIND(:arr, n -> n > 10)", &vars).unwrap(), Value::Number(12.0));
+        assert_eq!(eval("SUMIF(:arr, n -> n < 15)", &vars).unwrap(), Value::Number(17.0));
+        assert_eq!(eval("COUNTIF(:arr, (n, i) -> i > 0)", &vars).unwrap(), Value::Number(2.0));
+    }
+    assert_eq!(evaluate("ITERATE(1, x -> x * 2, 5)").unwrap(), Value::Number(32.0));
+    assert_eq!(evaluate("ROUND(GOALSEEK(r -> r * r, 9, 1), 6)").unwrap(), Value::Number(3.0));
+}
+
+#[test]
+fn methods_take_arrow_lambdas() {
+    let vars = vars();
+    assert_eq!(evaluate_with(":arr.map((v, i) -> v * i)", &vars).unwrap(), nums(&[0.0, 12.0, 40.0]));
+    assert_eq!(evaluate_with(":arr.filter(v -> v % 2 == 0).length()", &vars).unwrap(), Value::Number(2.0));
+    assert_eq!(evaluate_with(":arr.reduce((total, v) -> total + v, 100)", &vars).unwrap(), Value::Number(137.0));
+    assert_eq!(evaluate("[1, 2, 5, 6].split_when((x, prev) -> x - prev > 1).length()").unwrap(), Value::Number(2.0));
+    assert_eq!(evaluate("[{'a': 1}, {'a': 2}].map(row -> row.a)").unwrap(), nums(&[1.0, 2.0]));
+}
+
+#[test]
+fn parameters_are_scoped_to_the_body() {
+    let mut vars = vars();
+    vars.insert("x".to_string(), Value::Number(100.0));
+    // The parameter shadows :x inside the lambda only
+    assert_eq!(evaluate_with("SUM(MAP(:arr, x -> x * 2)) + :x", &vars).unwrap(), Value::Number(174.0));
+    // Inner lambdas see the parameters of the outer ones
+    let nested = "MAP([1, 2], k -> SUM(FILTER(:arr, v -> v > k * 10)))";
+    assert_eq!(evaluate_with(nested, &vars).unwrap(), nums(&[32.0, 0.0]));
+    // Outside a lambda a bare name is still a function call
+    assert!(parse("x + 1").is_err());
+}
+
+#[test]
+fn lambda_nodes_round_trip() {
+    let expr = parse("FILTER(:arr, (v, i) -> v > i)").unwrap();
+    let Expr::FunctionCall { args, .. } = &expr else { panic!("expected a call") };
+    assert!(matches!(&args[1], Expr::Lambda { params, .. } if params == &["v", "i"]));
+    assert_eq!(expr.to_string(), "FILTER(:arr, (v, i) -> :v > :i)");
+    assert_eq!(parse(&expr.to_string()).unwrap(), expr);
+    assert_eq!(Expr::from_bytes(&expr.to_bytes()).unwrap(), expr);
+    assert_eq!(analyze(&expr).variables, vec!["arr".to_string()]);
+}
+
+#[test]
+fn misuse_is_reported() {
+    for (expr, message) in [
+        ("MAP([1], (a, b, c) -> a)", "at most 2"),
+        ("x -> x", "Lambda not allowed here"),
+        ("SUM(x -> x)", "Lambda not allowed here"),
+    ] {
+        let err = evaluate(expr).unwrap_err();
+        assert!(err.message.contains(message), "{}: {}", expr, err.message);
+    }
+    let err = parse("MAP([1], (a, a) -> a)").unwrap_err();
+    assert!(err.message.contains("Duplicate lambda parameter"), "{}", err.message);
+}
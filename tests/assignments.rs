@@ -106,4 +106,36 @@ fn test_assignment_expression_returns_value() {
     // The assignment itself should return the assigned value
     let result = evaluate_with_assignments(":x := 42", &vars).unwrap();
     assert!(matches!(result, Value::Number(42.0)));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_destructuring_assignment() {
+    let vars = HashMap::new();
+    let result = evaluate_with_assignments("[:a, :b] := [3, 4]; :a * :b", &vars).unwrap();
+    assert!(approx(result, 12.0));
+
+    // Missing elements and fields assign null
+    let (_, assigned) = skillet::evaluate_with_assignments_and_context("[:a, :b, :c] := [1, 2]", &vars).unwrap();
+    assert_eq!(assigned["c"], Value::Null);
+    let (_, assigned) = skillet::evaluate_with_assignments_and_context(
+        "{:lat, :zip} := {lat: 52.5, lon: 13.4}",
+        &vars,
+    ).unwrap();
+    assert!(approx(assigned["lat"].clone(), 52.5));
+    assert_eq!(assigned["zip"], Value::Null);
+
+    // The whole value is the result
+    let result = evaluate_with_assignments("[:a] := [7, 8]", &vars).unwrap();
+    assert_eq!(result, Value::Array(vec![Value::Number(7.0), Value::Number(8.0)]));
+
+    let err = evaluate_with_assignments("[:a, :b] := 5", &vars).unwrap_err();
+    assert!(err.message.contains("expects an array"), "{}", err.message);
+    let err = evaluate_with_assignments("{:a} := [1]", &vars).unwrap_err();
+    assert!(err.message.contains("expects an object"), "{}", err.message);
+    let err = skillet::parse("[:a, :a] := [1, 2]").unwrap_err();
+    assert!(err.message.contains("appears twice"), "{}", err.message);
+
+    // Array and object literals still parse as values
+    let result = evaluate_with_assignments("[1, 2].length()", &vars).unwrap();
+    assert!(approx(result, 2.0));
+}
@@ -0,0 +1,70 @@
+use skillet::serialize::{decode_bundle, encode_bundle, FORMAT_VERSION};
+use skillet::{parse, CompiledExpr, Expr, Value};
+use std::collections::HashMap;
+
+fn roundtrip(src: &str) {
+    let expr = parse(src).unwrap();
+    let bytes = expr.to_bytes();
+    assert_eq!(Expr::from_bytes(&bytes).unwrap(), expr, "roundtrip of {}", src);
+}
+
+#[test]
+fn every_node_kind_roundtrips() {
+    for src in [
+        "1 + 2 * 3 ^ 2 - -4 % 3",
+        "!(:a > 1) AND :b <= 2 OR :c != 'x'",
+        "SUM(:sales, 1000) / AVG([1, 2.5, null])",
+        ":user.name.upper()",
+        ":user&.name",
+        ":items.empty?",
+        ":arr[0] + :arr[1:3].length()",
+        ":arr[:2].sum() + :arr[1:].sum()",
+        "FILTER(:xs, :x > 0)",
+        "SUM(...:a, 1)",
+        "{\"total\": :a + 1, \"name\": 'n'}",
+        ":x := 5; :y := :x * 2; :y",
+        "[:a, :b] := :pair; {:lat, :lon} := :point; :a + :lat",
+        "(:price::Currency)::String",
+        "'unicode ✓ ünïcødé'",
+    ] {
+        roundtrip(src);
+    }
+}
+
+#[test]
+fn decoded_expression_evaluates_the_same() {
+    let compiled = CompiledExpr::compile("=SUM(:a, :b) * 2").unwrap();
+    let restored = CompiledExpr::from_bytes(&compiled.to_bytes()).unwrap();
+    assert_eq!(restored.source(), "=SUM(:a, :b) * 2");
+
+    let mut vars = HashMap::new();
+    vars.insert("a".to_string(), Value::Number(3.0));
+    vars.insert("b".to_string(), Value::Number(4.0));
+    assert_eq!(restored.evaluate(&vars).unwrap(), Value::Number(14.0));
+}
+
+#[test]
+fn bundles_keep_order() {
+    let formulas: Vec<CompiledExpr> =
+        ["1 + 1", ":x * 2", "'done'"].iter().map(|s| CompiledExpr::compile(s).unwrap()).collect();
+    let decoded = decode_bundle(&encode_bundle(&formulas)).unwrap();
+    let sources: Vec<&str> = decoded.iter().map(|f| f.source()).collect();
+    assert_eq!(sources, ["1 + 1", ":x * 2", "'done'"]);
+}
+
+#[test]
+fn rejects_foreign_or_damaged_input() {
+    let bytes = parse("1 + :a").unwrap().to_bytes();
+
+    let mut other_version = bytes.clone();
+    other_version[3] = FORMAT_VERSION + 1;
+    assert!(Expr::from_bytes(&other_version).unwrap_err().message.contains("version"));
+
+    assert!(Expr::from_bytes(b"not an expression").is_err());
+    assert!(Expr::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert!(Expr::from_bytes(&trailing).is_err());
+    // An expression payload is not a compiled one
+    assert!(CompiledExpr::from_bytes(&bytes).is_err());
+}
@@ -0,0 +1,52 @@
+use skillet::corpus::Expected;
+use skillet::{evaluate_canary, EvalOptions, Strictness, Value};
+use std::collections::HashMap;
+
+fn cases() -> Vec<(&'static str, HashMap<String, Value>)> {
+    let mut vars = HashMap::new();
+    vars.insert("price".to_string(), Value::Number(10.0));
+    vars.insert("discount".to_string(), Value::Null);
+    vec![
+        ("SUM(1, 2, 3)", HashMap::new()),
+        (":price * 1.16", vars.clone()),
+        (":price - :discount", vars.clone()),
+        ("10 / 0", HashMap::new()),
+        (":total := :price * 2; :total + 1", vars),
+    ]
+}
+
+#[test]
+fn identical_options_agree() {
+    let opts = EvalOptions::new();
+    let report = evaluate_canary(&cases(), &opts, &opts).unwrap();
+    assert!(report.is_success(), "{}", report.summary());
+    assert_eq!(report.agreed, 5);
+}
+
+#[test]
+fn reports_formulas_whose_outcome_changes() {
+    let baseline = EvalOptions::new();
+    let candidate = EvalOptions::new().with_null_propagation(true).with_strictness(Strictness::Strict);
+    let report = evaluate_canary(&cases(), &baseline, &candidate).unwrap();
+
+    let changed: Vec<usize> = report.differences.iter().map(|d| d.index).collect();
+    assert_eq!(changed, vec![2, 3], "{}", report.summary());
+    assert_eq!(report.agreed, 3);
+    let null_diff = &report.differences[0];
+    assert!(matches!(null_diff.baseline, Expected::Error(_)));
+    assert_eq!(null_diff.candidate, Expected::Value { value: serde_json::Value::Null, type_name: None });
+    assert!(report.summary().contains("#3: 10 / 0"), "{}", report.summary());
+}
+
+#[test]
+fn output_options_count_as_differences() {
+    let report = evaluate_canary(&cases(), &EvalOptions::new(), &EvalOptions::new().with_precision(0)).unwrap();
+    let changed: Vec<&str> = report.differences.iter().map(|d| d.expression.as_str()).collect();
+    assert_eq!(changed, vec![":price * 1.16"]);
+}
+
+#[test]
+fn invalid_options_are_rejected_up_front() {
+    let bad = EvalOptions::new().with_precision(20);
+    assert!(evaluate_canary(&cases(), &EvalOptions::new(), &bad).is_err());
+}
@@ -0,0 +1,62 @@
+use skillet::custom::FnFunction;
+use skillet::{context, evaluate_with_options, register_function, unregister_function, EvalContext, EvalOptions, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+
+fn register_nap() {
+    register_function(Box::new(FnFunction::new("NAP", 0, 0, |_| {
+        std::thread::sleep(Duration::from_millis(15));
+        Ok(Value::Number(1.0))
+    })))
+    .unwrap();
+}
+
+// The registry is process-wide, so everything using NAP runs in one test
+#[test]
+fn budgets_cap_the_time_spent_per_category() {
+    register_nap();
+    let vars = HashMap::new();
+    let options = EvalOptions::new().with_category_budget("Custom", 20);
+    assert_eq!(options.category_budgets.get("custom"), Some(&20));
+
+    assert_eq!(evaluate_with_options("NAP()", &vars, &options).unwrap(), Value::Number(1.0));
+    // The second call pushes the category past 20 ms and the third never runs
+    let err = evaluate_with_options("NAP() + NAP() + NAP()", &vars, &options).unwrap_err();
+    assert!(err.message.starts_with("Time budget for custom functions exceeded"), "{}", err.message);
+    assert!(err.message.ends_with("ms used of 20 ms"), "{}", err.message);
+
+    // Other categories are not limited by it
+    let statistical = EvalOptions::new().with_category_budget("statistical", 20);
+    assert_eq!(evaluate_with_options("NAP() + NAP() + NAP()", &vars, &statistical).unwrap(), Value::Number(3.0));
+
+    // The profile breaks the time down by category
+    let ctx = EvalContext::new().with_options(statistical);
+    let (result, times) = context::with_context(ctx, || context::profile_categories(|| skillet::evaluate_with_custom("SUM(NAP(), 2) + MEDIAN([1, 2, 3])", &vars)));
+    assert_eq!(result.unwrap(), Value::Number(5.0));
+    assert_eq!(times["custom"].calls, 1);
+    assert!(times["custom"].elapsed >= Duration::from_millis(15));
+    assert_eq!(times["custom"].budget, None);
+    assert_eq!((times["arithmetic"].calls, times["statistical"].calls), (1, 1));
+    assert_eq!(times["statistical"].budget, Some(Duration::from_millis(20)));
+
+    unregister_function("NAP");
+}
+
+#[test]
+fn profiling_without_budgets() {
+    let (result, times) = context::profile_categories(|| skillet::evaluate("[UPPER('a'), LOWER('B')]"));
+    assert!(result.is_ok());
+    assert_eq!(times["string"].calls, 2);
+    assert!(times.values().all(|t| t.budget.is_none()));
+}
+
+#[test]
+fn unknown_categories_are_rejected() {
+    for (options, message) in [
+        (EvalOptions::new().with_category_budget("plugins", 20), "Unknown function category 'plugins'"),
+        (EvalOptions::new().with_category_budget("json", 0), "category_budgets for JSON must be at least 1 ms"),
+    ] {
+        let err = options.validate().unwrap_err();
+        assert!(err.message.contains(message), "{}", err.message);
+    }
+}
@@ -21,10 +21,13 @@ fn test_multiple_comments() {
 
 #[test]
 fn test_comment_after_code() {
-    // Note: Comments after code on same line are NOT supported in this version
     let result = evaluate_with_assignments(":x := 5;\n:y := 10;\n:x + :y", &HashMap::new());
     assert!(result.is_ok());
     assert_eq!(result.unwrap(), Value::Number(15.0));
+
+    // Comments may also end a line that holds code
+    let result = evaluate_with_assignments(":x := 5; // five\n:y := 10; # ten\n:x + :y /* sum */", &HashMap::new()).unwrap();
+    assert_eq!(result, Value::Number(15.0));
 }
 
 #[test]
@@ -72,8 +75,8 @@ fn test_object_literal_with_indentation() {
     let result = evaluate_with_assignments(":result := {\n  name: 'John',\n  age: 30,\n  active: true\n};\n:result", &HashMap::new()).unwrap();
 
     match result {
-        Value::Json(_) => {}, // Success
-        _ => panic!("Expected JSON object"),
+        Value::Map(_) => {}, // Success
+        _ => panic!("Expected object"),
     }
 }
 
@@ -0,0 +1,77 @@
+use skillet::{complete, CompletionKind, Value};
+use std::collections::HashMap;
+
+fn schema() -> HashMap<String, Value> {
+    HashMap::from([
+        ("price".to_string(), Value::Number(1.0)),
+        ("product".to_string(), Value::String("x".to_string())),
+        ("tags".to_string(), Value::Array(vec![])),
+        ("user".to_string(), Value::Json(r#"{"name": "a", "address": {"city": "b"}}"#.to_string())),
+    ])
+}
+
+fn labels(input: &str) -> Vec<String> {
+    complete(input, input.len(), &schema()).items.into_iter().map(|c| c.label).collect()
+}
+
+#[test]
+fn completes_variables_after_colon() {
+    let result = complete(":pr * 2", 3, &schema());
+    assert_eq!(result.replace_from, 1);
+    let names: Vec<_> = result.items.iter().map(|c| (c.label.as_str(), c.detail.as_deref())).collect();
+    assert_eq!(names, [("price", Some("Number")), ("product", Some("String"))]);
+    assert_eq!(labels("SUM(:").len(), 4);
+}
+
+#[test]
+fn completes_methods_for_the_receiver_type() {
+    let array = labels(":tags.");
+    assert!(array.contains(&"filter".to_string()) && array.contains(&"join".to_string()));
+    assert!(!array.contains(&"upper".to_string()));
+
+    assert_eq!(labels(":product.up"), ["upcase", "upper"]);
+    assert!(labels(":price.").contains(&"round".to_string()));
+    assert!(labels(":price.").contains(&"even?".to_string()));
+
+    // Receivers are whole postfix chains
+    assert!(labels("1 + :product.split(',').").contains(&"map".to_string()));
+    assert!(labels("(:price * 2).").contains(&"abs".to_string()));
+    assert!(labels("SUM(:tags.map(:x * 2).").contains(&"sum".to_string()));
+}
+
+#[test]
+fn completes_json_properties() {
+    let result = complete(":user.", 6, &schema());
+    let address = result.items.iter().find(|c| c.label == "address").unwrap();
+    assert_eq!(address.kind, CompletionKind::Property);
+    assert_eq!(address.detail.as_deref(), Some("Json"));
+    assert!(labels(":user.address.").contains(&"city".to_string()));
+    assert!(labels(":user.").contains(&"keys".to_string()));
+}
+
+#[test]
+fn completes_functions_types_and_lambda_parameters() {
+    let funcs = labels("1 + SU");
+    assert!(funcs.contains(&"SUM".to_string()) && funcs.contains(&"SUMIF".to_string()));
+    assert!(labels("FIL").contains(&"FILTER".to_string()));
+    assert!(labels("tr").contains(&"TRUE".to_string()));
+    assert_eq!(labels(":price::Int"), ["Integer"]);
+
+    let in_lambda = labels(":tags.reduce(:acc + :");
+    assert!(in_lambda.contains(&"x".to_string()) && in_lambda.contains(&"acc".to_string()));
+    assert!(!labels("SUM(1, :").contains(&"x".to_string()));
+}
+
+#[test]
+fn no_candidates_in_strings_comments_or_after_operands() {
+    assert!(labels("'abc").is_empty());
+    assert!(labels("1 + 2 // SU").is_empty());
+    assert!(labels(":price ").is_empty());
+    // A leading dot has no receiver
+    for input in [".", ".su", "&.", "?.x"] {
+        assert!(labels(input).is_empty(), "{}", input);
+    }
+    let mid = complete(":price + :tags.su", 17, &schema());
+    assert_eq!(mid.replace_from, 15);
+    assert_eq!(mid.items.iter().map(|c| c.label.as_str()).collect::<Vec<_>>(), ["sum"]);
+}
@@ -0,0 +1,34 @@
+use skillet::{clear_constants, constant, evaluate, evaluate_with, evaluate_with_assignments, remove_constant, set_constant, Value};
+use std::collections::HashMap;
+
+// Constants are process-wide, so everything runs in one test
+#[test]
+fn host_constants_are_visible_to_every_evaluation() {
+    set_constant("region", Value::String("mx-central".into())).unwrap();
+    set_constant("TAX_RATE", Value::Number(0.16)).unwrap();
+    set_constant("tiers", Value::Array(vec![Value::Number(100.0), Value::Number(500.0)])).unwrap();
+    assert_eq!(constant("TAX_RATE"), Some(Value::Number(0.16)));
+
+    assert_eq!(evaluate("CONST('TAX_RATE') * 100").unwrap(), Value::Number(16.0));
+    assert_eq!(evaluate("CONST('tiers')[1]").unwrap(), Value::Number(500.0));
+    assert_eq!(evaluate(":ENV.region").unwrap(), Value::String("mx-central".into()));
+    assert_eq!(evaluate("CONST('missing')").unwrap_err().message, "Unknown constant: missing");
+
+    // A request variable cannot stand in for the host's ENV
+    let mut vars = HashMap::new();
+    vars.insert("ENV".to_string(), Value::String("spoofed".into()));
+    vars.insert("amount".to_string(), Value::Number(200.0));
+    assert_eq!(evaluate_with(":ENV.region", &vars).unwrap(), Value::String("mx-central".into()));
+    assert_eq!(evaluate_with(":amount * CONST('TAX_RATE')", &vars).unwrap(), Value::Number(32.0));
+    assert!(evaluate_with_assignments(":ENV := 1; :ENV", &vars).unwrap_err().message.contains("cannot be assigned"));
+
+    assert!(set_constant("bad name", Value::Null).is_err());
+    assert!(set_constant("huge", Value::Number(f64::INFINITY)).is_err());
+
+    assert!(remove_constant("region"));
+    assert!(!remove_constant("region"));
+    assert!(evaluate(":ENV.region").is_err());
+    clear_constants();
+    assert_eq!(evaluate(":ENV").unwrap(), Value::Json("{}".into()));
+    assert!(evaluate("CONST('TAX_RATE')").is_err());
+}
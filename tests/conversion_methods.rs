@@ -99,4 +99,31 @@ fn chained_conversions() {
     assert_eq!(s(evaluate("123.to_s().to_s()").unwrap()), "123");
     assert_eq!(n(evaluate("\"123\".to_i().to_f()").unwrap()), 123.0);
     assert_eq!(b(evaluate("\"hello\".to_a().length().to_bool()").unwrap()), true);
-}
\ No newline at end of file
+}
+
+#[test]
+fn try_casts_return_null_instead_of_failing() {
+    assert_eq!(n(evaluate("TRYCAST(' 12.5 ', 'Number')").unwrap()), 12.5);
+    assert_eq!(evaluate("TRYCAST('n/a', 'Number')").unwrap(), Value::Null);
+    // Where a cast would quietly give 0, TRYCAST gives null
+    assert_eq!(n(evaluate("'abc'::Integer").unwrap()), 0.0);
+    assert_eq!(evaluate("TRYCAST('abc', 'Integer')").unwrap(), Value::Null);
+    assert_eq!(n(evaluate("TRYCAST('7.9', 'int')").unwrap()), 7.0);
+    assert_eq!(evaluate("TRYCAST('12', 'Currency')").unwrap(), Value::Currency(12.0));
+    assert_eq!(evaluate("TRYCAST(null, 'Number')").unwrap(), Value::Null);
+    assert!(b(evaluate("TRYCAST('yes', 'Boolean')").unwrap()));
+    assert_eq!(evaluate("TRYCAST('maybe', 'Boolean')").unwrap(), Value::Null);
+    assert_eq!(evaluate("TRYCAST('2024-03-01', 'DateTime')").unwrap(), evaluate("'2024-03-01'::DateTime").unwrap());
+    assert_eq!(evaluate("TRYCAST('soon', 'Date')").unwrap(), Value::Null);
+    assert_eq!(j(evaluate("TRYCAST('{\"a\": 1}', 'Json')").unwrap()), "{\"a\": 1}");
+    assert_eq!(evaluate("TRYCAST('{oops', 'Json')").unwrap(), Value::Null);
+    assert!(evaluate("TRYCAST(1, 'Decimal')").unwrap_err().message.contains("does not know the type 'Decimal'"));
+
+    let cleaned = evaluate("['1', 'x', ' 2.5', '', null].map(TRYCAST(:x, 'Number')).compact()").unwrap();
+    assert_eq!(a(cleaned), vec![Value::Number(1.0), Value::Number(2.5)]);
+
+    assert_eq!(n(evaluate("'42'.to_number_or_null()").unwrap()), 42.0);
+    assert_eq!(evaluate("'forty'.to_number_or_null()").unwrap(), Value::Null);
+    assert_eq!(evaluate("'2024-03-01T10:00:00Z'.to_date_or_null()").unwrap(), evaluate("'2024-03-01T10:00:00Z'::DateTime").unwrap());
+    assert_eq!(evaluate("'someday'.to_date_or_null()").unwrap(), Value::Null);
+}
@@ -0,0 +1,78 @@
+use skillet::corpus::Expected;
+use skillet::runtime::function_dispatch::builtin_function_names;
+use skillet::runtime::method_calls::{
+    ARRAY_METHODS, CONVERSION_METHODS, JSON_METHODS, LAMBDA_METHODS, NUMBER_METHODS, PREDICATES, STRING_METHODS,
+};
+use skillet::{Corpus, Value};
+use std::path::Path;
+
+fn golden() -> Vec<(String, Corpus)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut files: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().path()).collect();
+    files.sort();
+    files
+        .into_iter()
+        .filter(|p| p.extension().is_some_and(|e| e == "jsonl"))
+        .map(|p| (p.display().to_string(), Corpus::load(&p).unwrap_or_else(|e| panic!("{}", e.message))))
+        .collect()
+}
+
+#[test]
+fn golden_results_are_unchanged() {
+    let mut total = 0;
+    for (path, corpus) in golden() {
+        let report = corpus.run();
+        assert!(report.is_success(), "{}: {}", path, report.summary());
+        total += report.passed;
+    }
+    assert!(total >= 300, "corpus shrank to {} cases", total);
+}
+
+#[test]
+fn corpus_covers_every_builtin_and_method() {
+    let expressions: Vec<String> =
+        golden().into_iter().flat_map(|(_, c)| c.cases).map(|c| c.expression.to_lowercase()).collect();
+    let used = |needle: &str| expressions.iter().any(|e| e.contains(needle));
+
+    // Dotted aliases such as MODE.SNGL cannot be written in source; their undotted forms are covered
+    for name in builtin_function_names().into_iter().filter(|n| !n.contains('.')) {
+        assert!(used(&format!("{}(", name.to_lowercase())), "no corpus case calls {}", name);
+    }
+    let methods = [STRING_METHODS, ARRAY_METHODS, LAMBDA_METHODS, NUMBER_METHODS, JSON_METHODS, CONVERSION_METHODS];
+    for name in methods.concat() {
+        assert!(used(&format!(".{}(", name)), "no corpus case calls .{}()", name);
+    }
+    for name in PREDICATES {
+        assert!(used(&format!(".{}?", name)), "no corpus case uses .{}?", name);
+    }
+}
+
+#[test]
+fn runner_reports_mismatches() {
+    let corpus = Corpus::parse(
+        "# comment\n\
+         {\"expr\": \"1 + 1\", \"expect\": 3}\n\
+         \n\
+         {\"expr\": \"'5'::Currency\", \"expect\": 5}\n\
+         {\"expr\": \":a * 2\", \"vars\": {\"a\": 0.1}, \"expect\": 0.2}\n\
+         {\"expr\": \"UNKNOWNFN()\", \"error\": \"Unknown function\"}\n",
+    )
+    .unwrap();
+    let report = corpus.run();
+    assert_eq!(report.passed, 2);
+    assert_eq!(report.failures.iter().map(|f| f.line).collect::<Vec<_>>(), [2, 4]);
+    assert!(report.summary().contains("line 4: '5'::Currency: expected 5, got 5 (Currency)"));
+
+    // A downstream suite can swap in its own evaluation
+    let report = corpus.run_with(|_, _| Ok(Value::Number(3.0)));
+    assert_eq!(report.passed, 1);
+
+    let mut blessed = corpus.clone();
+    blessed.bless();
+    assert!(blessed.run().is_success());
+    assert_eq!(blessed.cases[0].expected, Expected::Value { value: serde_json::json!(2), type_name: None });
+    assert!(Corpus::parse(&blessed.to_jsonl()).unwrap().run().is_success());
+
+    assert!(Corpus::parse("{\"expr\": \"1\"}").unwrap_err().message.contains("Line 1"));
+    assert!(Corpus::parse("not json").is_err());
+}
@@ -0,0 +1,35 @@
+use skillet::{estimate_cost, parse, Complexity};
+
+fn cost(expr: &str) -> skillet::CostReport {
+    estimate_cost(&parse(expr).unwrap())
+}
+
+#[test]
+fn constant_expressions() {
+    let report = cost("1 + 2 * 3");
+    assert_eq!(report.node_count, 5);
+    assert_eq!(report.max_depth, 3);
+    assert_eq!(report.loop_count, 0);
+    assert_eq!(report.complexity, Complexity::Constant);
+    assert_eq!(report.score, 5);
+}
+
+#[test]
+fn loops_and_traversals() {
+    let sum = cost("SUM(:items)");
+    assert_eq!(sum.complexity, Complexity::Linear);
+    assert!(sum.iterated_variables.is_empty());
+
+    let filtered = cost(":orders.filter(:x.total > 10).map(:x.total)");
+    assert_eq!(filtered.loop_count, 2);
+    assert_eq!(filtered.max_loop_nesting, 1);
+    assert_eq!(filtered.iterated_variables, vec!["orders".to_string()]);
+    assert_eq!(filtered.complexity, Complexity::Linear);
+
+    let nested = cost(":a.map(:b.filter(:y > :x).length())");
+    assert_eq!(nested.max_loop_nesting, 2);
+    assert_eq!(nested.complexity, Complexity::Quadratic);
+    assert_eq!(nested.complexity.to_string(), "O(n^2)");
+    assert!(nested.score > filtered.score);
+    assert_eq!(nested.iterated_variables, vec!["a".to_string(), "b".to_string()]);
+}
@@ -0,0 +1,76 @@
+use skillet::{evaluate, evaluate_with_options, EvalOptions, Value};
+use std::collections::HashMap;
+
+fn s(v: Value) -> String { if let Value::String(s) = v { s } else { panic!("expected string, got {:?}", v) } }
+
+#[test]
+fn currency_function_formats_per_currency_rules() {
+    assert_eq!(s(evaluate("CURRENCY(1234.5, \"MXN\")").unwrap()), "$1,234.50");
+    assert_eq!(s(evaluate("CURRENCY(1234.5, \"eur\")").unwrap()), "€1,234.50");
+    assert_eq!(s(evaluate("CURRENCY(1234.5, \"JPY\")").unwrap()), "¥1,235");
+    assert_eq!(s(evaluate("CURRENCY(1234.5678, \"KWD\")").unwrap()), "KD 1,234.568");
+    assert_eq!(s(evaluate("CURRENCY(-99.9, \"SEK\")").unwrap()), "-99.90 kr");
+    assert_eq!(s(evaluate("CURRENCY(1000000)").unwrap()), "$1,000,000.00");
+    assert_eq!(s(evaluate("CURRENCY(-0.001, \"USD\")").unwrap()), "$0.00");
+    assert!(evaluate("CURRENCY(1, \"XYZ\")").is_err());
+    for expr in ["CURRENCY(0/0)", "CURRENCY(10^400)", "CURRENCY(-(10^400), \"EUR\")"] {
+        assert!(evaluate(expr).unwrap_err().message.contains("finite"), "{}", expr);
+    }
+}
+
+#[test]
+fn currency_values_render_with_two_decimals() {
+    assert_eq!(evaluate("12.5.to_currency()").unwrap(), Value::Currency(12.5));
+    assert_eq!(s(evaluate("(12.5::Currency).to_s()").unwrap()), "12.50");
+    assert_eq!(s(evaluate("(12.5::Currency)::String").unwrap()), "12.50");
+    assert_eq!(s(evaluate("CONCAT(\"Total: \", 12.5::Currency)").unwrap()), "Total: 12.50");
+}
+
+#[test]
+fn currency_option_controls_rendering() {
+    let opts = EvalOptions::new().with_currency("GBP");
+    let eval = |e: &str| evaluate_with_options(e, &HashMap::new(), &opts).unwrap();
+    assert_eq!(s(eval("(1500::Currency).to_s()")), "£1,500.00");
+    assert_eq!(s(eval("JOIN([1::Currency, 2::Currency], \" / \")")), "£1.00 / £2.00");
+    assert_eq!(s(eval("CURRENCY(3)")), "£3.00");
+    assert!(evaluate_with_options("1", &HashMap::new(), &EvalOptions::new().with_currency("XXX")).is_err());
+}
+
+#[test]
+fn roundcash_rounds_to_circulating_denominations() {
+    let n = |expr: &str| match evaluate(expr).unwrap() {
+        Value::Number(n) | Value::Currency(n) => n,
+        other => panic!("expected number, got {:?}", other),
+    };
+    assert_eq!(n("ROUNDCASH(1.02, 'CHF')"), 1.0);
+    assert_eq!(n("ROUNDCASH(1.025, 'CHF')"), 1.05);
+    assert_eq!(n("ROUNDCASH(1.03, 'CHF')"), 1.05);
+    assert_eq!(n("ROUNDCASH(-1.025, 'CHF')"), -1.05);
+    assert_eq!(n("ROUNDCASH(12.49, 'SEK')"), 12.0);
+    assert_eq!(n("ROUNDCASH(12.25, 'DKK')"), 12.5);
+    assert_eq!(n("ROUNDCASH(1234.5, 'JPY')"), 1235.0);
+    assert_eq!(n("ROUNDCASH(10.005, 'USD')"), 10.01);
+    assert_eq!(n("ROUNDCASH(10.26, 'MXN', 0.5)"), 10.5);
+    assert_eq!(n("ROUNDCASH(1.2345, 'USD', 0.001)"), 1.235);
+    assert_eq!(evaluate("ROUNDCASH('1.02'::Currency, 'CHF')").unwrap(), Value::Currency(1.0));
+
+    // The currency option is the default code
+    let vars = HashMap::new();
+    let opts = EvalOptions::new().with_currency("CHF");
+    assert_eq!(evaluate_with_options("ROUNDCASH(2.56)", &vars, &opts).unwrap(), Value::Number(2.55));
+
+    assert!(evaluate("ROUNDCASH(1, 'XYZ')").unwrap_err().message.contains("Unknown currency code"));
+    assert!(evaluate("ROUNDCASH(1, 'CHF', 0)").unwrap_err().message.contains("positive"));
+}
+
+#[test]
+fn cash_increments_can_be_configured_by_the_host() {
+    assert_eq!(evaluate("ROUNDCASH(10.26, 'MXN')").unwrap(), Value::Number(10.26));
+    skillet::set_cash_increment("mxn", 0.5).unwrap();
+    assert_eq!(evaluate("ROUNDCASH(10.26, 'MXN')").unwrap(), Value::Number(10.5));
+    assert_eq!(evaluate("ROUNDCASH(10.24, 'MXN')").unwrap(), Value::Number(10.0));
+    assert!(skillet::set_cash_increment("MXN", -1.0).is_err());
+    assert!(skillet::set_cash_increment("XYZ", 1.0).is_err());
+    skillet::clear_cash_increments();
+    assert_eq!(evaluate("ROUNDCASH(10.26, 'MXN')").unwrap(), Value::Number(10.26));
+}
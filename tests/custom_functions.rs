@@ -163,4 +163,315 @@ fn test_custom_function_error_handling() {
     
     // Clean up
     unregister_function("DOUBLE");
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_registry_snapshot_is_isolated_from_updates() {
+    let _lock = TEST_MUTEX.lock().unwrap();
+
+    unregister_function("DOUBLE");
+    let before = skillet::registry_snapshot();
+
+    assert!(register_function(Box::new(DoubleFunction)).is_ok());
+    let after = skillet::registry_snapshot();
+
+    // Earlier snapshots keep the view they were taken with
+    assert!(!before.has_function("DOUBLE"));
+    assert!(after.has_function("DOUBLE"));
+
+    unregister_function("DOUBLE");
+    assert!(after.has_function("DOUBLE"));
+    assert!(!skillet::has_custom_function("DOUBLE"));
+}
+
+#[test]
+fn test_builtins_only_blocks_custom_functions() {
+    let _lock = TEST_MUTEX.lock().unwrap();
+
+    unregister_function("DOUBLE");
+    assert!(register_function(Box::new(DoubleFunction)).is_ok());
+
+    let expr = skillet::parse("DOUBLE(SUM(:a, 1)) + MISSING(2)").unwrap();
+    let deps = skillet::analyze(&expr);
+    assert_eq!(deps.functions, vec!["DOUBLE", "MISSING", "SUM"]);
+    assert_eq!(deps.variables, vec!["a"]);
+    assert_eq!(deps.non_builtin_functions(), vec!["DOUBLE", "MISSING"]);
+
+    let vars = HashMap::new();
+    let opts = skillet::EvalOptions::new().with_builtins_only(true);
+    let err = skillet::evaluate_with_options("DOUBLE(2)", &vars, &opts).unwrap_err();
+    assert!(err.message.contains("blocked: DOUBLE"), "{}", err.message);
+    assert!(skillet::evaluate_with_options("SUM(1, 2) + [1, 2].map(:x * 2).sum()", &vars, &opts).is_ok());
+
+    // Enforced at call time as well, e.g. for hosts installing the context themselves
+    let ctx = skillet::EvalContext::new().with_options(opts);
+    let result = skillet::context::with_context(ctx, || evaluate_with_custom("DOUBLE(2)", &vars));
+    assert!(result.is_err());
+
+    unregister_function("DOUBLE");
+}
+
+/// Returns several named results the documented way, as a `record!` object
+struct GeocodeFunction;
+
+impl CustomFunction for GeocodeFunction {
+    fn name(&self) -> &str { "GEOCODE" }
+    fn min_args(&self) -> usize { 1 }
+    fn max_args(&self) -> Option<usize> { Some(1) }
+
+    fn execute(&self, _args: Vec<Value>) -> Result<Value, Error> {
+        skillet::record! {
+            "lat" => Value::Number(52.37),
+            "lon" => Value::Number(4.89),
+            "accuracy" => Value::String("street".to_string()),
+        }
+    }
+}
+
+/// Returns several positional results as a `tuple!` array
+struct BoundsFunction;
+
+impl CustomFunction for BoundsFunction {
+    fn name(&self) -> &str { "BOUNDS" }
+    fn min_args(&self) -> usize { 1 }
+    fn max_args(&self) -> Option<usize> { None }
+
+    fn execute(&self, args: Vec<Value>) -> Result<Value, Error> {
+        let nums: Vec<f64> = args.iter().filter_map(Value::as_number).collect();
+        let low = nums.iter().copied().fold(f64::INFINITY, f64::min);
+        let high = nums.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Ok(skillet::tuple![Value::Number(low), Value::Number(high)])
+    }
+}
+
+#[test]
+fn test_multi_return_functions_destructure() {
+    let _lock = TEST_MUTEX.lock().unwrap();
+
+    assert!(register_function(Box::new(GeocodeFunction)).is_ok());
+    assert!(register_function(Box::new(BoundsFunction)).is_ok());
+    let vars = HashMap::new();
+
+    let (result, assigned) = skillet::evaluate_with_assignments_and_context(
+        "{:lat, :lon, :accuracy} := GEOCODE('Dam 1'); :lat + :lon",
+        &vars,
+    ).unwrap();
+    assert!(matches!(result, Value::Number(n) if (n - 57.26).abs() < 1e-9));
+    assert_eq!(assigned.get("accuracy"), Some(&Value::String("street".to_string())));
+
+    let result = skillet::evaluate_with_assignments("[:low, :high] := BOUNDS(4, 9, 1); :high - :low", &vars).unwrap();
+    assert!(matches!(result, Value::Number(8.0)));
+
+    // Single fields stay reachable without destructuring
+    let result = evaluate_with_custom("GEOCODE('Dam 1').accuracy", &vars).unwrap();
+    assert_eq!(result, Value::String("street".to_string()));
+
+    unregister_function("GEOCODE");
+    unregister_function("BOUNDS");
+}
+
+#[test]
+fn test_custom_fn_macro() {
+    let _lock = TEST_MUTEX.lock().unwrap();
+
+    let taxrate = skillet::custom_fn!("TAXRATE", |amount: f64, region: &str, rate: Option<f64>| -> f64 {
+        let base = if region == "EU" { 0.21 } else { 0.07 };
+        amount * rate.unwrap_or(base)
+    })
+    .with_description("Tax for an amount in a region");
+    assert_eq!((taxrate.min_args(), taxrate.max_args()), (2, Some(3)));
+    assert_eq!(taxrate.description(), Some("Tax for an amount in a region"));
+    assert!(register_function(Box::new(taxrate)).is_ok());
+
+    let halve = skillet::custom_fn!("HALVE", |n: i64| -> Result<i64, Error> {
+        if n % 2 != 0 {
+            return Err(Error::new("HALVE expects an even number", None));
+        }
+        Ok(n / 2)
+    });
+    assert!(register_function(Box::new(halve)).is_ok());
+    let initials = skillet::custom_fn!("INITIALS", |names: Vec<String>| {
+        names.iter().filter_map(|n| n.chars().next()).collect::<String>()
+    });
+    assert!(register_function(Box::new(initials)).is_ok());
+
+    let vars = HashMap::new();
+    assert_eq!(evaluate_with_custom("TAXRATE(100, 'EU')", &vars).unwrap(), Value::Number(21.0));
+    assert_eq!(evaluate_with_custom("TAXRATE(100, 'US', 0.1)", &vars).unwrap(), Value::Number(10.0));
+    assert_eq!(evaluate_with_custom("HALVE(8)", &vars).unwrap(), Value::Number(4.0));
+    assert_eq!(evaluate_with_custom("INITIALS(['Ada', 'Bo'])", &vars).unwrap(), Value::String("AB".to_string()));
+
+    let err = evaluate_with_custom("TAXRATE(100, 5)", &vars).unwrap_err();
+    assert_eq!(err.message, "TAXRATE argument 2 (region) must be text, got Number(5.0)");
+    let err = evaluate_with_custom("HALVE(2.5)", &vars).unwrap_err();
+    assert!(err.message.contains("must be a whole number"), "{}", err.message);
+    assert!(evaluate_with_custom("HALVE(3)", &vars).unwrap_err().message.contains("even number"));
+    assert!(evaluate_with_custom("TAXRATE(100)", &vars).is_err());
+
+    unregister_function("TAXRATE");
+    unregister_function("HALVE");
+    unregister_function("INITIALS");
+}
+
+#[test]
+fn test_function_inventory() {
+    let _lock = TEST_MUTEX.lock().unwrap();
+
+    unregister_function("DOUBLE");
+    let vars = HashMap::new();
+    let feature_detect = "ISFUNCTION('double') ? DOUBLE(4) : 4 * 2";
+    assert_eq!(evaluate_with_custom("ISFUNCTION('DOUBLE')", &vars).unwrap(), Value::Boolean(false));
+    assert_eq!(evaluate_with_custom(feature_detect, &vars).unwrap(), Value::Number(8.0));
+
+    assert!(register_function(Box::new(DoubleFunction)).is_ok());
+    assert_eq!(evaluate_with_custom("ISFUNCTION('double')", &vars).unwrap(), Value::Boolean(true));
+    assert_eq!(evaluate_with_custom(feature_detect, &vars).unwrap(), Value::Number(8.0));
+    assert_eq!(evaluate_with_custom("ISFUNCTION('sum') && !ISFUNCTION('__TERNARY__')", &vars).unwrap(), Value::Boolean(true));
+
+    let Value::Array(names) = evaluate_with_custom("FUNCTIONS()", &vars).unwrap() else { panic!("Expected array") };
+    for name in ["DOUBLE", "SUM", "FILTER", "FUNCTIONS"] {
+        assert!(names.contains(&Value::String(name.to_string())), "{} missing", name);
+    }
+    assert!(names.windows(2).all(|w| matches!((&w[0], &w[1]), (Value::String(a), Value::String(b)) if a < b)));
+
+    // Only what this evaluation may call is listed
+    let opts = skillet::EvalOptions::new().with_builtins_only(true);
+    let names = skillet::evaluate_with_options("FUNCTIONS()", &vars, &opts).unwrap();
+    assert!(matches!(&names, Value::Array(n) if !n.contains(&Value::String("DOUBLE".to_string()))));
+    let detected = skillet::evaluate_with_options("ISFUNCTION('DOUBLE')", &vars, &opts).unwrap();
+    assert_eq!(detected, Value::Boolean(false));
+    let opts = skillet::EvalOptions::new().with_denied_builtins(["NOW"]);
+    let detected = skillet::evaluate_with_options("ISFUNCTION('now') || ISFUNCTION('nope')", &vars, &opts).unwrap();
+    assert_eq!(detected, Value::Boolean(false));
+
+    unregister_function("DOUBLE");
+}
+
+/// Looks its rate up per tenant, reading the tenant from the calling formula
+struct TenantRateFunction;
+
+impl CustomFunction for TenantRateFunction {
+    fn name(&self) -> &str { "TENANTRATE" }
+    fn min_args(&self) -> usize { 0 }
+    fn max_args(&self) -> Option<usize> { Some(0) }
+
+    fn execute(&self, args: Vec<Value>) -> Result<Value, Error> {
+        self.execute_with_context(args, &skillet::CallContext::new())
+    }
+
+    fn execute_with_context(&self, _args: Vec<Value>, ctx: &skillet::CallContext) -> Result<Value, Error> {
+        let rate = match ctx.variable("tenant") {
+            Some(Value::String(t)) if t == "acme" => 0.07,
+            Some(_) => 0.1,
+            None => return Err(Error::new("TENANTRATE needs a :tenant variable", None)),
+        };
+        let label = format!("{}/{}", ctx.request_id().unwrap_or("-"), ctx.locale().unwrap_or("-"));
+        skillet::record! { "rate" => Value::Number(rate), "via" => Value::String(label) }
+    }
+}
+
+#[test]
+fn test_custom_function_reads_call_context() {
+    let _lock = TEST_MUTEX.lock().unwrap();
+
+    assert!(register_function(Box::new(TenantRateFunction)).is_ok());
+    let mut vars = HashMap::new();
+    vars.insert("tenant".to_string(), Value::String("acme".to_string()));
+    assert_eq!(evaluate_with_custom("TENANTRATE().rate", &vars).unwrap(), Value::Number(0.07));
+    assert_eq!(evaluate_with_custom("TENANTRATE().via", &vars).unwrap(), Value::String("-/-".to_string()));
+
+    let ctx = skillet::EvalContext::new()
+        .with_request_id("req-7")
+        .with_options(skillet::EvalOptions::new().with_locale("es-MX"));
+    let via = skillet::context::with_context(ctx, || evaluate_with_custom("TENANTRATE().via", &vars)).unwrap();
+    assert_eq!(via, Value::String("req-7/es-MX".to_string()));
+
+    // Variables assigned earlier in the formula are visible too
+    let result = skillet::evaluate_with_assignments(":tenant := 'globex'; TENANTRATE().rate", &HashMap::new()).unwrap();
+    assert_eq!(result, Value::Number(0.1));
+
+    // Called outside of a formula there are no variables
+    let err = skillet::registry_snapshot().execute("TENANTRATE", vec![]).unwrap_err();
+    assert!(err.message.contains(":tenant"), "{}", err.message);
+
+    unregister_function("TENANTRATE");
+}
+
+/// MEMOIZE(name, args...) calls the function `name` once per distinct argument list
+struct MemoizeFunction {
+    cache: Mutex<Vec<(Vec<Value>, Value)>>,
+}
+
+impl CustomFunction for MemoizeFunction {
+    fn name(&self) -> &str { "MEMOIZE" }
+    fn min_args(&self) -> usize { 1 }
+    fn max_args(&self) -> Option<usize> { None }
+
+    fn execute(&self, args: Vec<Value>) -> Result<Value, Error> {
+        self.execute_with_context(args, &skillet::CallContext::new())
+    }
+
+    fn execute_with_context(&self, args: Vec<Value>, ctx: &skillet::CallContext) -> Result<Value, Error> {
+        if let Some((_, hit)) = self.cache.lock().unwrap().iter().find(|(key, _)| *key == args) {
+            return Ok(hit.clone());
+        }
+        let Value::String(name) = &args[0] else {
+            return Err(Error::new("MEMOIZE expects a function name", None));
+        };
+        let result = ctx.call(name, args[1..].to_vec())?;
+        self.cache.lock().unwrap().push((args, result.clone()));
+        Ok(result)
+    }
+}
+
+/// Evaluates formula text against the caller's variables, calling itself forever
+/// when asked to
+struct EvalTextFunction;
+
+impl CustomFunction for EvalTextFunction {
+    fn name(&self) -> &str { "EVALTEXT" }
+    fn min_args(&self) -> usize { 1 }
+    fn max_args(&self) -> Option<usize> { Some(1) }
+
+    fn execute(&self, args: Vec<Value>) -> Result<Value, Error> {
+        self.execute_with_context(args, &skillet::CallContext::new())
+    }
+
+    fn execute_with_context(&self, args: Vec<Value>, ctx: &skillet::CallContext) -> Result<Value, Error> {
+        match &args[0] {
+            Value::String(formula) => ctx.evaluate(formula),
+            _ => Err(Error::new("EVALTEXT expects formula text", None)),
+        }
+    }
+}
+
+#[test]
+fn test_custom_functions_call_back_into_the_engine() {
+    let _lock = TEST_MUTEX.lock().unwrap();
+
+    assert!(register_function(Box::new(DoubleFunction)).is_ok());
+    assert!(register_function(Box::new(MemoizeFunction { cache: Mutex::new(Vec::new()) })).is_ok());
+    assert!(register_function(Box::new(EvalTextFunction)).is_ok());
+    let mut vars = HashMap::new();
+    vars.insert("x".to_string(), Value::Number(5.0));
+
+    assert_eq!(evaluate_with_custom("MEMOIZE('double', :x) + MEMOIZE('DOUBLE', :x)", &vars).unwrap(), Value::Number(20.0));
+    assert_eq!(evaluate_with_custom("MEMOIZE('ROUND', 2.345, 2)", &vars).unwrap(), Value::Number(2.35));
+    assert_eq!(evaluate_with_custom("EVALTEXT('DOUBLE(:x) + 1')", &vars).unwrap(), Value::Number(11.0));
+    assert_eq!(evaluate_with_custom("EVALTEXT('EVALTEXT(\"[:x].map(:x * 3)\")')", &vars).unwrap(), Value::Array(vec![Value::Number(15.0)]));
+
+    let err = evaluate_with_custom("MEMOIZE('NOSUCH', 1)", &vars).unwrap_err();
+    assert!(err.message.contains("NOSUCH"), "{}", err.message);
+    let err = evaluate_with_custom("EVALTEXT('EVALTEXT(\"EVALTEXT(:f)\")')", &HashMap::from([("f".to_string(), Value::String("EVALTEXT(:f)".to_string()))])).unwrap_err();
+    assert!(err.message.contains("levels deep"), "{}", err.message);
+
+    // The caller's options still apply inside
+    let opts = skillet::EvalOptions::new().with_denied_builtins(["ROUND"]);
+    let ctx = skillet::EvalContext::new().with_options(opts);
+    let result = skillet::context::with_context(ctx, || evaluate_with_custom("MEMOIZE('ROUND', 1.5, 0)", &vars));
+    assert!(result.is_err());
+
+    unregister_function("DOUBLE");
+    unregister_function("MEMOIZE");
+    unregister_function("EVALTEXT");
+}
@@ -0,0 +1,77 @@
+use skillet::{evaluate_per_row, evaluate_per_row_with, Dataset, Value};
+use std::collections::HashMap;
+
+fn nums(values: &[f64]) -> Vec<Value> {
+    values.iter().map(|n| Value::Number(*n)).collect()
+}
+
+fn sample() -> Dataset {
+    Dataset::new()
+        .with_column("price", nums(&[10.0, 20.0, 30.0]))
+        .unwrap()
+        .with_column("qty", nums(&[1.0, 2.0, 3.0]))
+        .unwrap()
+        .with_column("sku", vec![Value::String("a".into()), Value::String("bb".into()), Value::String("ccc".into())])
+        .unwrap()
+}
+
+#[test]
+fn columns_must_have_equal_length() {
+    let ds = sample();
+    assert_eq!(ds.len(), 3);
+    assert_eq!(ds.column_names(), ["price", "qty", "sku"]);
+    assert_eq!(ds.column("qty"), Some(nums(&[1.0, 2.0, 3.0]).as_slice()));
+    assert_eq!(ds.row(1).unwrap()["price"], Value::Number(20.0));
+    assert!(ds.row(3).is_none());
+
+    let err = sample().with_column("bad", nums(&[1.0])).unwrap_err();
+    assert!(err.message.contains("Column 'bad' has 1 values, expected 3"), "{}", err.message);
+
+    // Replacing a column keeps its position
+    let mut ds = sample();
+    ds.add_column("qty", nums(&[4.0, 5.0, 6.0])).unwrap();
+    assert_eq!(ds.column_names(), ["price", "qty", "sku"]);
+    assert_eq!(ds.column("qty").unwrap()[0], Value::Number(4.0));
+}
+
+#[test]
+fn evaluates_each_row() {
+    let out = evaluate_per_row(":price * :qty", &sample()).unwrap();
+    assert_eq!(out, nums(&[10.0, 40.0, 90.0]));
+
+    // Scalar methods still apply to the row's cell
+    let out = evaluate_per_row(":sku.length()", &sample()).unwrap();
+    assert_eq!(out, nums(&[1.0, 2.0, 3.0]));
+
+    let mut vars = HashMap::new();
+    vars.insert("tax".to_string(), Value::Number(0.5));
+    let out = evaluate_per_row_with(":price * :tax", &sample(), &vars).unwrap();
+    assert_eq!(out, nums(&[5.0, 10.0, 15.0]));
+
+    assert!(evaluate_per_row(":price", &Dataset::new()).unwrap().is_empty());
+}
+
+#[test]
+fn aggregates_refer_to_the_whole_column() {
+    let out = evaluate_per_row(":price / :price.sum()", &sample()).unwrap();
+    assert_eq!(out, vec![Value::Number(10.0 / 60.0), Value::Number(20.0 / 60.0), Value::Number(0.5)]);
+
+    let out = evaluate_per_row(":qty == :qty.max() ? 'top' : :qty.count()", &sample()).unwrap();
+    assert_eq!(out, vec![Value::Number(3.0), Value::Number(3.0), Value::String("top".into())]);
+
+    // Arguments of a hoisted call see the other columns and the caller's variables
+    let mut vars = HashMap::new();
+    vars.insert("k".to_string(), Value::Number(1.0));
+    let out = evaluate_per_row_with(":price.sum(:qty) + :qty.max(:k)", &sample(), &vars).unwrap();
+    assert_eq!(out, nums(&[63.0, 63.0, 63.0]));
+
+    assert_eq!(sample().aggregate(":price.sum() / :qty.sum()").unwrap(), Value::Number(10.0));
+    assert_eq!(sample().aggregate("AVG(:qty)").unwrap(), Value::Number(2.0));
+}
+
+#[test]
+fn row_errors_name_the_row() {
+    let ds = Dataset::new().with_column("x", vec![Value::Number(1.0), Value::String("a".into())]).unwrap();
+    let err = evaluate_per_row(":x - 2", &ds).unwrap_err();
+    assert!(err.message.starts_with("Row 1: "), "{}", err.message);
+}
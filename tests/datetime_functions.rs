@@ -144,4 +144,126 @@ fn test_string_and_datetime_together() {
     let year_str = as_string(evaluate("=CONCAT(\"Year: \", YEAR(NOW()))").unwrap());
     assert!(year_str.starts_with("Year: "));
     assert!(year_str.contains("202")); // Should contain 2024, 2025, etc.
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_min_max_with_dates() {
+    let may = as_datetime(evaluate("DATE(2024, 5, 1)").unwrap());
+    let june = as_datetime(evaluate("DATE(2024, 6, 1)").unwrap());
+    assert_eq!(as_datetime(evaluate("MAX(DATE(2024, 5, 1), [DATE(2024, 6, 1), DATE(2023, 1, 1)])").unwrap()), june);
+    assert_eq!(as_datetime(evaluate("MIN(DATE(2024, 6, 1), DATE(2024, 5, 1))").unwrap()), may);
+    assert_eq!(as_datetime(evaluate("[DATE(2024, 5, 1), DATE(2024, 6, 1)].max()").unwrap()), june);
+    assert_eq!(as_datetime(evaluate("[DATE(2024, 6, 1), DATE(2024, 5, 1)].min()").unwrap()), may);
+
+    let err = evaluate("MAX(DATE(2024, 5, 1), 3)").unwrap_err();
+    assert!(err.message.contains("cannot compare dates with numbers"));
+    assert!(evaluate("[DATE(2024, 5, 1), 3].max()").is_err());
+    // Numbers are unaffected
+    assert_eq!(as_number(evaluate("MAX(1, [5, 2], 'x', TRUE)").unwrap()), 5.0);
+}
+
+#[test]
+fn test_mina_maxa() {
+    // TRUE counts as 1, FALSE and text as 0
+    assert_eq!(as_number(evaluate("MAXA(0.5, TRUE)").unwrap()), 1.0);
+    assert_eq!(as_number(evaluate("MINA(3, 'text', 5)").unwrap()), 0.0);
+    assert_eq!(as_number(evaluate("MINA([2, FALSE])").unwrap()), 0.0);
+    assert_eq!(as_number(evaluate("MIN(3, 'text', 5)").unwrap()), 3.0);
+    assert_eq!(as_number(evaluate("MAXA(-2, null)").unwrap()), -2.0);
+    assert_eq!(as_number(evaluate("MAXA()").unwrap()), 0.0);
+}
+
+#[test]
+fn test_datetime_literals() {
+    assert_eq!(as_datetime(evaluate("d\"2024-05-01\"").unwrap()), 1714521600);
+    assert_eq!(as_datetime(evaluate("d'2024-05-01T10:00:00Z'").unwrap()), 1714557600);
+    assert_eq!(as_datetime(evaluate("d\"2024-05-01T12:00:00+02:00\"").unwrap()), 1714557600);
+    assert_eq!(as_datetime(evaluate("d\"2024-05-01 10:00\"").unwrap()), 1714557600);
+    assert_eq!(as_datetime(evaluate("D\"2024-05-01T10:00:00\"").unwrap()), 1714557600);
+
+    assert_eq!(as_number(evaluate("YEAR(d\"2024-05-01\")").unwrap()), 2024.0);
+    assert_eq!(as_datetime(evaluate("MAX(d\"2024-05-01\", DATE(2024, 6, 1))").unwrap()), 1717200000);
+    assert!(as_bool(evaluate("DATE(2024, 5, 1) == d\"2024-05-01\"").unwrap()));
+
+    let err = evaluate("d\"2024-13-01\"").unwrap_err();
+    assert!(err.message.contains("Invalid datetime literal"), "{}", err.message);
+    assert!(evaluate("1 + d\"yesterday\"").is_err());
+
+    // ISO strings also cast
+    assert_eq!(as_datetime(evaluate("'2024-05-01'::DateTime").unwrap()), 1714521600);
+}
+
+#[test]
+fn test_datetime_literal_comparisons() {
+    let mut vars = std::collections::HashMap::new();
+    vars.insert("created".to_string(), Value::DateTime(1717200000));
+    assert!(as_bool(skillet::evaluate_with(":created >= d\"2024-06-01\"", &vars).unwrap()));
+    assert!(as_bool(skillet::evaluate_with(":created < d\"2024-06-01T00:00:01Z\"", &vars).unwrap()));
+    assert!(!as_bool(skillet::evaluate_with(":created != d\"2024-06-01\"", &vars).unwrap()));
+}
+
+#[test]
+fn test_humanize_durations() {
+    assert_eq!(as_string(evaluate("HUMANIZE(183600)").unwrap()), "2 days 3 hours");
+    assert_eq!(as_string(evaluate("HUMANIZE(3661)").unwrap()), "1 hour 1 minute 1 second");
+    assert_eq!(as_string(evaluate("HUMANIZE(90.7)").unwrap()), "1 minute 30 seconds");
+    assert_eq!(as_string(evaluate("HUMANIZE(0)").unwrap()), "0 seconds");
+    assert_eq!(as_string(evaluate("HUMANIZE(-7200)").unwrap()), "-2 hours");
+    assert_eq!(as_string(evaluate("HUMANIZE(90000, 'es-MX')").unwrap()), "1 día 1 hora");
+    assert_eq!(as_string(evaluate("HUMANIZE(120, 'de')").unwrap()), "2 Minuten");
+    assert!(evaluate("HUMANIZE(1, 'xx')").unwrap_err().message.contains("locale 'xx'"));
+
+    assert_eq!(
+        as_string(evaluate("HUMANIZEDURATION(d\"2024-06-01T08:00:00Z\", d\"2024-06-03T11:30:00Z\")").unwrap()),
+        "2 days 3 hours 30 minutes"
+    );
+    assert_eq!(
+        as_string(evaluate("HUMANIZEDURATION(d\"2024-06-01\", d\"2024-06-02\", 'fr')").unwrap()),
+        "1 jour"
+    );
+    assert!(evaluate("HUMANIZEDURATION(1, 2)").is_err());
+
+    let opts = skillet::EvalOptions::new().with_locale("pt-BR");
+    let text = skillet::evaluate_with_options("HUMANIZE(7260)", &std::collections::HashMap::new(), &opts).unwrap();
+    assert_eq!(as_string(text), "2 horas 1 minuto");
+}
+
+#[test]
+fn daysinperiod_day_count_bases() {
+    let days = |a: &str, b: &str, basis: &str| as_number(evaluate(&format!("DAYSINPERIOD('{}', '{}'{})", a, b, basis)).unwrap());
+    assert_eq!(days("2024-01-01", "2024-03-01", ""), 60.0);
+    assert_eq!(days("2024-01-01", "2024-03-01", ", 0"), 60.0);
+    assert_eq!(days("2024-01-31", "2024-03-31", ", '30/360'"), 60.0);
+    assert_eq!(days("2024-01-30", "2024-03-31", ", 0"), 60.0);
+    // US 30/360 treats the end of February as the 30th
+    assert_eq!(days("2023-02-28", "2023-03-31", ", 0"), 30.0);
+    assert_eq!(days("2023-02-28", "2023-03-31", ", 4"), 32.0);
+    assert_eq!(days("2024-03-01", "2024-01-01", ", 'actual/365'"), -60.0);
+    assert_eq!(as_number(evaluate("DAYSINPERIOD(DATE(2024, 2, 1), DATE(2024, 3, 1))").unwrap()), 29.0);
+
+    assert!(evaluate("DAYSINPERIOD('2024-01-01', '2024-02-01', 7)").unwrap_err().message.contains("basis"));
+    assert!(evaluate("DAYSINPERIOD('soon', '2024-02-01')").unwrap_err().message.contains("expects a date as start"));
+}
+
+#[test]
+fn prorate_shares_an_amount_by_days_used() {
+    let prorate = |usage: &str, basis: &str| {
+        as_number(evaluate(&format!("PRORATE(30, '2024-04-01', '2024-05-01', {}{})", usage, basis)).unwrap())
+    };
+    assert_eq!(prorate("'2024-04-16', '2024-05-01'", ""), 15.0);
+    assert_eq!(prorate("'2024-03-01', '2024-06-01'", ""), 30.0);
+    assert_eq!(prorate("'2024-05-01', '2024-06-01'", ""), 0.0);
+    assert_eq!(prorate("'2024-04-01', '2024-04-11'", ", '30/360'"), 10.0);
+
+    // February under actual days vs 30/360
+    let feb = "PRORATE(300, '2023-02-01', '2023-03-01', '2023-02-15', '2023-03-01'";
+    assert!((as_number(evaluate(&format!("{})", feb)).unwrap()) - 150.0).abs() < 1e-9);
+    assert!((as_number(evaluate(&format!("{}, 0)", feb)).unwrap()) - 160.0).abs() < 1e-9);
+
+    assert_eq!(
+        evaluate("PRORATE('100'::Currency, '2024-01-01', '2024-01-11', '2024-01-01', '2024-01-06')").unwrap(),
+        Value::Currency(50.0)
+    );
+    assert!(evaluate("PRORATE(1, '2024-02-01', '2024-01-01', '2024-01-01', '2024-01-02')").is_err());
+    assert!(evaluate("PRORATE(1, '2024-01-01', '2024-02-01', '2024-01-05', '2024-01-02')").is_err());
+}
@@ -0,0 +1,110 @@
+use skillet::{DebugAction, DebugSession, Debugger, Pause, Value};
+use std::collections::HashMap;
+
+/// Replays a fixed list of actions and records where it paused
+struct Script {
+    actions: Vec<DebugAction>,
+    pauses: Vec<(Option<usize>, String, usize)>,
+    seen: Vec<HashMap<String, Value>>,
+}
+
+impl Script {
+    fn new(actions: &[DebugAction]) -> Self {
+        Self { actions: actions.iter().rev().copied().collect(), pauses: Vec::new(), seen: Vec::new() }
+    }
+
+    fn sources(&self) -> Vec<&str> {
+        self.pauses.iter().map(|(_, s, _)| s.as_str()).collect()
+    }
+}
+
+impl Debugger for Script {
+    fn on_pause(&mut self, pause: &Pause<'_>) -> DebugAction {
+        self.pauses.push((pause.node, pause.source(), pause.depth));
+        self.seen.push(pause.variables());
+        self.actions.pop().unwrap_or(DebugAction::Continue)
+    }
+}
+
+#[test]
+fn numbers_nodes_in_pre_order() {
+    let session = DebugSession::new("(1 + :a) * 2").unwrap();
+    assert_eq!(session.nodes(), ["(1 + :a) * 2", "1 + :a", "1", ":a", "2"]);
+    assert_eq!(session.find(":a"), vec![3]);
+    assert!(session.find(":b").is_empty());
+}
+
+#[test]
+fn pauses_at_breakpoints_only_when_continuing() {
+    let mut session = DebugSession::new("(1 + :a) * (2 + :a)").unwrap();
+    for id in session.find(":a") {
+        session.add_breakpoint(id).unwrap();
+    }
+    assert!(session.add_breakpoint(99).is_err());
+
+    let vars = HashMap::from([("a".to_string(), Value::Number(3.0))]);
+    let (result, script) = session.run(&vars, Script::new(&[]));
+    assert_eq!(result.unwrap(), Value::Number(20.0));
+    assert_eq!(script.sources(), [":a", ":a"]);
+    assert_eq!(script.seen[0].get("a"), Some(&Value::Number(3.0)));
+}
+
+#[test]
+fn step_next_and_step_out() {
+    let session = DebugSession::new("(1 + 2) * (3 + 4)").unwrap().with_stop_on_entry(true);
+    use DebugAction::*;
+
+    let (_, script) = session.run(&HashMap::new(), Script::new(&[Step, Step, Step, Step]));
+    assert_eq!(script.sources(), ["(1 + 2) * (3 + 4)", "1 + 2", "1", "2", "3 + 4"]);
+
+    let (_, script) = session.run(&HashMap::new(), Script::new(&[Step, Next, Next]));
+    assert_eq!(script.sources(), ["(1 + 2) * (3 + 4)", "1 + 2", "3 + 4"]);
+    assert_eq!(script.pauses[2].2, 2);
+
+    let (_, script) = session.run(&HashMap::new(), Script::new(&[Step, Step, StepOut]));
+    assert_eq!(script.sources(), ["(1 + 2) * (3 + 4)", "1 + 2", "1", "3 + 4"]);
+}
+
+#[test]
+fn sees_assignments_and_lambda_parameters() {
+    let mut session = DebugSession::new(":t := 10; FILTER(:xs, :x > :t)").unwrap();
+    session.add_breakpoint(session.find(":x > :t")[0]).unwrap();
+    let vars = HashMap::from([(
+        "xs".to_string(),
+        Value::Array(vec![Value::Number(5.0), Value::Number(15.0)]),
+    )]);
+
+    let (result, script) = session.run(&vars, Script::new(&[]));
+    assert_eq!(result.unwrap(), Value::Array(vec![Value::Number(15.0)]));
+    assert_eq!(script.pauses.len(), 2);
+    assert_eq!(script.seen[0].get("t"), Some(&Value::Number(10.0)));
+    assert_eq!(script.seen[0].get("x"), Some(&Value::Number(5.0)));
+    assert_eq!(script.seen[1].get("x"), Some(&Value::Number(15.0)));
+}
+
+#[test]
+fn abort_stops_evaluation() {
+    let session = DebugSession::new("1 + 2").unwrap().with_stop_on_entry(true);
+    let (result, _) = session.run(&HashMap::new(), Script::new(&[DebugAction::Step, DebugAction::Abort]));
+    assert!(result.unwrap_err().message.contains("aborted"));
+}
+
+#[test]
+fn reports_results() {
+    struct Values(Vec<(usize, Value)>);
+    impl Debugger for Values {
+        fn on_pause(&mut self, _pause: &Pause<'_>) -> DebugAction {
+            DebugAction::Continue
+        }
+        fn on_result(&mut self, node: Option<usize>, _expr: &skillet::Expr, result: &Result<Value, skillet::Error>) {
+            if let (Some(node), Ok(v)) = (node, result) {
+                self.0.push((node, v.clone()));
+            }
+        }
+    }
+
+    let session = DebugSession::new("2 * 3 + 1").unwrap();
+    let (_, values) = session.run(&HashMap::new(), Values(Vec::new()));
+    assert_eq!(values.0.last(), Some(&(0, Value::Number(7.0))));
+    assert!(values.0.contains(&(1, Value::Number(6.0))));
+}
@@ -0,0 +1,89 @@
+use skillet::{analyze, evaluate, evaluate_with, evaluate_with_assignments, evaluate_with_assignments_and_context, parse, Value};
+use std::collections::HashMap;
+
+#[test]
+fn declared_functions_are_inlined() {
+    assert_eq!(evaluate("DEF(:double, [:x], :x * 2); double(21)").unwrap(), Value::Number(42.0));
+    assert_eq!(evaluate("DEF(:hyp, [:a, :b], SQRT(:a ^ 2 + :b ^ 2)); hyp(3, 4)").unwrap(), Value::Number(5.0));
+    // Names are case-insensitive like builtins, and string names work like in LET
+    assert_eq!(evaluate("DEF('Answer', [], 42); ANSWER() + answer()").unwrap(), Value::Number(84.0));
+    // Helpers can call helpers declared before them, and run inside lambdas
+    assert_eq!(
+        evaluate("DEF(:sq, [:x], :x * :x); DEF(:norm, [:x, :y], SQRT(sq(:x) + sq(:y))); [1, 2].map(norm(:x, 0))").unwrap(),
+        Value::Array(vec![Value::Number(1.0), Value::Number(2.0)])
+    );
+}
+
+#[test]
+fn arguments_are_evaluated_in_the_caller() {
+    let mut vars = HashMap::new();
+    vars.insert("rate".to_string(), Value::Number(0.25));
+    // The body sees the caller's variables, including ones assigned before the call
+    assert_eq!(
+        evaluate_with_assignments("DEF(:gross, [:net], :net * (1 + :rate) + :fee); :fee := 1; gross(100)", &vars).unwrap(),
+        Value::Number(126.0)
+    );
+    // Arguments named like a parameter bind to the caller's value, not each other
+    assert_eq!(evaluate_with_assignments("DEF(:sub, [:a, :b], :a - :b); :a := 1; :b := 10; sub(:b, :a)", &HashMap::new()).unwrap(), Value::Number(9.0));
+
+    // Parameters never leak into assignments around the call
+    let (result, context) = evaluate_with_assignments_and_context("DEF(:inc, [:n], :n + 1); :total := inc(inc(1)); :total", &HashMap::new()).unwrap();
+    assert_eq!(result, Value::Number(3.0));
+    assert!(context.contains_key("total"));
+    assert!(!context.contains_key("n"));
+}
+
+#[test]
+fn fn_statements_declare_helpers() {
+    assert_eq!(evaluate("fn double(x) := x * 2; double(21)").unwrap(), Value::Number(42.0));
+    assert_eq!(evaluate("fn hyp(a, :b) := SQRT(a ^ 2 + :b ^ 2); fn none() := 1; hyp(3, 4) + none()").unwrap(), Value::Number(6.0));
+    assert_eq!(parse("fn sub(a, b) := a - b; sub(5, 2)").unwrap(), parse("DEF(:sub, [:a, :b], :a - :b); sub(5, 2)").unwrap());
+    // Only a statement of the form `fn name(...) :=` declares; FN(...) is still a call
+    assert!(evaluate("FN(1)").unwrap_err().message.contains("Unknown function"));
+
+    for (expr, message) in [
+        ("fn f(x) x; f(1)", "Expected ':=' after the fn parameters"),
+        ("fn f(1) := 1; f(1)", "Expected a parameter name"),
+        ("fn f(a b) := 1; f(1)", "Expected ',' or ')'"),
+        ("fn f(x) := x", "must be followed by an expression"),
+    ] {
+        let err = evaluate(expr).unwrap_err();
+        assert!(err.message.contains(message), "{}: {}", expr, err.message);
+    }
+}
+
+#[test]
+fn bodies_are_dynamically_scoped() {
+    let mut vars = HashMap::new();
+    vars.insert("y".to_string(), Value::Number(1.0));
+    // :y is not a parameter, so each call uses the :y in scope where it is called
+    assert_eq!(evaluate_with("fn f(a) := a + :y; LET(:y, 10, f(1)) + f(1)", &vars).unwrap(), Value::Number(13.0));
+    assert_eq!(evaluate_with("fn f(a) := a + :y; let y := 100; f(1)", &vars).unwrap(), Value::Number(101.0));
+}
+
+#[test]
+fn parameters_are_not_dependencies() {
+    let deps = analyze(&parse("DEF(:hyp, [:a, :b], SQRT(:a ^ 2 + :b ^ 2)); hyp(:width, 4)").unwrap());
+    assert_eq!(deps.variables, vec!["width".to_string()]);
+    assert!(!deps.functions.contains(&"HYP".to_string()));
+}
+
+#[test]
+fn rejects_bad_declarations() {
+    for (expr, message) in [
+        ("DEF(:double, :x * 2); double(1)", "DEF expects"),
+        ("DEF(1, [:x], :x); 1", "DEF names must be"),
+        ("DEF(:sum, [:x], :x); sum(1)", "cannot redefine the builtin function SUM"),
+        ("DEF(:f, [:x], :x); DEF(:F, [], 1); f(1)", "DEF F is already defined"),
+        ("DEF(:f, :x, :x); f(1)", "parameters must be a list"),
+        ("DEF(:f, [:x, :x], :x); f(1, 2)", "has the parameter :x twice"),
+        ("DEF(:f, [:x], :x)", "must be followed by an expression"),
+        ("DEF(:f, [:n], f(:n - 1)); f(3)", "not supported"),
+        ("DEF(:f, [:a, :b], :a + :b); f(1)", "F expects 2 argument(s), got 1"),
+        ("DEF(:f, [:a], :a); f(...[1])", "cannot take spread arguments"),
+        ("DEF(:f, [], 1); 1 + DEF(:g, [], 2)", "statement of its own"),
+    ] {
+        let err = evaluate(expr).unwrap_err();
+        assert!(err.message.contains(message), "{}: {}", expr, err.message);
+    }
+}
@@ -0,0 +1,55 @@
+use skillet::{clear_dictionary_provider, evaluate, set_dictionary_provider, Dictionary, DictionaryProvider, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+struct Triage {
+    lookups: Arc<AtomicUsize>,
+    urgency: Arc<Dictionary>,
+}
+
+impl DictionaryProvider for Triage {
+    fn dictionary(&self, name: &str) -> Option<Arc<Dictionary>> {
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        (name == "urgency").then(|| self.urgency.clone())
+    }
+}
+
+fn n(v: Value) -> f64 {
+    match v {
+        Value::Number(n) => n,
+        other => panic!("expected number, got {:?}", other),
+    }
+}
+
+// The provider is process-wide, so everything runs in one test
+#[test]
+fn scoredict_sums_weights_from_host_dictionaries() {
+    clear_dictionary_provider();
+    assert!(evaluate("SCOREDICT('urgent', 'urgency')").unwrap_err().message.contains("needs a dictionary provider"));
+
+    let urgency: Dictionary = [("urgent", 3.0), ("down", 2.0), ("not working", 2.5), ("thanks", -1.0)]
+        .into_iter()
+        .map(|(t, w)| (t.to_string(), w))
+        .collect();
+    let lookups = Arc::new(AtomicUsize::new(0));
+    set_dictionary_provider(Box::new(Triage { lookups: lookups.clone(), urgency: Arc::new(urgency) }));
+
+    assert_eq!(n(evaluate("SCOREDICT('URGENT: site is down, checkout not working!', 'urgency')").unwrap()), 7.5);
+    // Repeats count, whole words only, phrases must be in order
+    assert_eq!(n(evaluate("SCOREDICT('urgent urgent, downtime; working not', 'urgency')").unwrap()), 6.0);
+    assert_eq!(n(evaluate("SCOREDICT('Thanks!', 'urgency')").unwrap()), -1.0);
+    assert_eq!(n(evaluate("SCOREDICT(TOKENIZE('all good'), 'urgency')").unwrap()), 0.0);
+    assert_eq!(n(evaluate("SCOREDICT(['down', 'urgent'], 'urgency')").unwrap()), 5.0);
+    assert!(lookups.load(Ordering::SeqCst) >= 5);
+
+    let err = evaluate("SCOREDICT('x', 'sentiment')").unwrap_err();
+    assert!(err.message.contains("no dictionary named 'sentiment'"), "{}", err.message);
+    assert!(evaluate("SCOREDICT('x', 1)").is_err());
+
+    let fixed: HashMap<String, Arc<Dictionary>> =
+        [("sentiment".to_string(), Arc::new([("great".to_string(), 1.0)].into_iter().collect()))].into_iter().collect();
+    set_dictionary_provider(Box::new(fixed));
+    assert_eq!(n(evaluate("IF(SCOREDICT('Great, great job', 'sentiment') > 1, 1, 0)").unwrap()), 1.0);
+    clear_dictionary_provider();
+}
@@ -0,0 +1,65 @@
+use skillet::{evaluate, evaluate_with, Value};
+use std::collections::HashMap;
+
+fn table(policy: &str) -> Value {
+    let json = serde_json::json!({
+        "inputs": ["age", "segment"],
+        "outputs": ["rate"],
+        "hit_policy": policy,
+        "rules": [
+            [">=65", "-", 0.02],
+            [">=18", ["vip", "staff"], 0.03],
+            [">=18", "-", 0.05],
+            ["-", "-", null]
+        ]
+    });
+    Value::Json(json.to_string())
+}
+
+fn run(expr: &str, age: f64, segment: &str, policy: &str) -> Result<Value, skillet::Error> {
+    let mut vars = HashMap::new();
+    vars.insert("who".to_string(), Value::Json(serde_json::json!({"age": age, "segment": segment}).to_string()));
+    vars.insert("table".to_string(), table(policy));
+    evaluate_with(expr, &vars)
+}
+
+#[test]
+fn first_hit_wins() {
+    assert_eq!(run("DTABLE(:who, :table)", 70.0, "vip", "FIRST").unwrap(), Value::Number(0.02));
+    assert_eq!(run("DTABLE(:who, :table)", 30.0, "staff", "FIRST").unwrap(), Value::Number(0.03));
+    assert_eq!(run("DTABLE(:who, :table)", 30.0, "retail", "FIRST").unwrap(), Value::Number(0.05));
+    assert_eq!(run("DTABLE(:who, :table)", 12.0, "retail", "FIRST").unwrap(), Value::Null);
+}
+
+#[test]
+fn collect_and_any() {
+    let all = run("DTABLE(:who, :table)", 30.0, "vip", "COLLECT").unwrap();
+    assert_eq!(all, Value::Array(vec![Value::Number(0.03), Value::Number(0.05), Value::Null]));
+    let err = run("DTABLE(:who, :table)", 30.0, "vip", "ANY").unwrap_err();
+    assert!(err.message.contains("disagrees"), "{}", err.message);
+
+    let same = r#"DTABLE({'x': 5}, '{"inputs": ["x"], "outputs": ["y"], "hit_policy": "any", "rules": [[">0", "pos"], ["<10", "pos"], [">10", "big"]]}')"#;
+    assert_eq!(evaluate(same).unwrap(), Value::String("pos".to_string()));
+}
+
+#[test]
+fn several_outputs_form_an_object() {
+    let expr = r#"DTABLE({'paid': TRUE, 'country': 'MX'}, '{"inputs": ["paid", "country"], "outputs": ["ship", "days"], "rules": [[false, "-", false, null], [true, "MX", true, 2], [true, "-", true, 7]]}')"#;
+    assert_eq!(evaluate(expr).unwrap(), Value::Json(r#"{"days":2,"ship":true}"#.to_string()));
+    assert_eq!(evaluate(&format!("{}.days", expr)).unwrap(), Value::Number(2.0));
+}
+
+#[test]
+fn malformed_tables_are_reported() {
+    let cases = [
+        (r#"DTABLE({'x': 1}, '{"outputs": ["y"], "rules": []}')"#, "\"inputs\""),
+        (r#"DTABLE({'x': 1}, '{"inputs": ["x"], "outputs": ["y"], "rules": [[1]]}')"#, "2 cells"),
+        (r#"DTABLE({'x': 1}, '{"inputs": ["x"], "outputs": ["y"], "hit_policy": "UNIQUE", "rules": []}')"#, "hit_policy"),
+        (r#"DTABLE({'x': 1}, 'not json')"#, "not valid JSON"),
+        ("DTABLE([1], '{}')", "must be an object"),
+    ];
+    for (expr, message) in cases {
+        let err = evaluate(expr).unwrap_err();
+        assert!(err.message.contains(message), "{}: {}", expr, err.message);
+    }
+}
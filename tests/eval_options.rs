@@ -0,0 +1,273 @@
+use skillet::{evaluate_with_options, EvalOptions, Strictness, Value};
+use std::collections::HashMap;
+
+fn n(v: Value) -> f64 { if let Value::Number(n) = v { n } else { panic!("expected number") } }
+
+fn eval(expr: &str, options: &EvalOptions) -> Result<Value, skillet::Error> {
+    evaluate_with_options(expr, &HashMap::new(), options)
+}
+
+#[test]
+fn defaults_match_plain_evaluation() {
+    let opts = EvalOptions::default();
+    assert_eq!(n(eval("SUM(1, 2, 3)", &opts).unwrap()), 6.0);
+    assert!(eval(":missing + 1", &opts).is_err());
+    assert_eq!(eval("1 == 'a'", &opts).unwrap(), Value::Boolean(false));
+}
+
+#[test]
+fn precision_rounds_output() {
+    let opts = EvalOptions::new().with_precision(2);
+    assert_eq!(n(eval("0.1 + 0.2", &opts).unwrap()), 0.3);
+    assert_eq!(eval("[1/3, 2/3]", &opts).unwrap(), Value::Array(vec![Value::Number(0.33), Value::Number(0.67)]));
+    assert!(eval("1", &EvalOptions::new().with_precision(40)).is_err());
+}
+
+#[test]
+fn max_array_size_is_enforced() {
+    let opts = EvalOptions::new().with_max_array_size(3);
+    assert!(eval("[1, 2, 3]", &opts).is_ok());
+    let err = eval("[1, 2, 3, 4]", &opts).unwrap_err();
+    assert!(err.message.contains("max_array_size"));
+    assert!(eval("FLATTEN([[1, 2], [3, 4]])", &opts).is_err());
+}
+
+#[test]
+fn deterministic_rejects_now() {
+    let opts = EvalOptions::new().with_deterministic(true);
+    let err = eval("NOW()", &opts).unwrap_err();
+    assert!(err.message.contains("deterministic"));
+    assert!(eval("DATE(2024, 1, 1)", &opts).is_ok());
+    // Without arguments DATE and TIME read the clock too
+    for expr in ["DATE()", "TIME()"] {
+        let err = eval(expr, &opts).unwrap_err();
+        assert!(err.message.contains("deterministic"), "{}: {}", expr, err.message);
+    }
+}
+
+#[test]
+fn now_override_freezes_the_clock() {
+    // 2024-01-31T23:59:30Z, thirty seconds before the month ends
+    let opts = EvalOptions::new().with_now_override(1706745570);
+    assert_eq!(eval("NOW()", &opts).unwrap(), Value::DateTime(1706745570));
+    assert_eq!(eval("DATE()", &opts).unwrap(), Value::DateTime(1706659200));
+    assert_eq!(n(eval("TIME()", &opts).unwrap()), 86370.0);
+    assert_eq!(n(eval("MONTH(DATEADD(NOW(), 30, 'seconds'))", &opts).unwrap()), 2.0);
+
+    // A frozen clock is reproducible, so deterministic mode accepts NOW
+    let frozen = opts.clone().with_deterministic(true);
+    assert_eq!(eval("NOW()", &frozen).unwrap(), Value::DateTime(1706745570));
+    assert_eq!(eval("DATE()", &frozen).unwrap(), Value::DateTime(1706659200));
+    assert_eq!(n(eval("TIME()", &frozen).unwrap()), 86370.0);
+    assert!(eval("1", &EvalOptions::new().with_now_override(i64::MAX)).is_err());
+}
+
+#[test]
+fn strictness_levels() {
+    let lenient = EvalOptions::new().with_strictness(Strictness::Lenient);
+    assert_eq!(eval(":missing", &lenient).unwrap(), Value::Null);
+    assert_eq!(eval("1 < 'a'", &lenient).unwrap(), Value::Boolean(false));
+
+    let strict = EvalOptions::new().with_strictness(Strictness::Strict);
+    assert!(eval("1 == 'a'", &strict).is_err());
+    assert!(eval("1 / 0", &strict).is_err());
+    assert_eq!(eval("NULL == 1", &strict).unwrap(), Value::Boolean(false));
+    assert!(eval("[1, 2].map(:x / 0)", &strict).is_err());
+
+    assert!(Strictness::parse("STRICT").is_ok());
+    assert!(Strictness::parse("loose").is_err());
+}
+
+#[test]
+fn timeout_aborts_long_evaluations() {
+    let opts = EvalOptions::new().with_timeout_ms(0);
+    let err = eval("SUM(1, 2)", &opts).unwrap_err();
+    assert!(err.message.contains("timed out"));
+    assert!(eval("SUM(1, 2)", &EvalOptions::new().with_timeout_ms(10_000)).is_ok());
+}
+
+#[test]
+fn locale_is_validated() {
+    assert!(eval("1", &EvalOptions::new().with_locale("de-DE")).is_ok());
+    assert!(eval("1", &EvalOptions::new().with_locale("not a locale")).is_err());
+}
+
+fn s(v: Value) -> String { if let Value::String(s) = v { s } else { panic!("expected string, got {:?}", v) } }
+
+#[test]
+fn numbers_render_without_float_artifacts() {
+    let opts = EvalOptions::default();
+    assert_eq!(s(eval("(0.1 + 0.2).to_s()", &opts).unwrap()), "0.3");
+    assert_eq!(s(eval("CONCAT('total: ', 0.1 + 0.2)", &opts).unwrap()), "total: 0.3");
+    assert_eq!(s(eval("JOIN([0.1 + 0.2, 1], '|')", &opts).unwrap()), "0.3|1");
+    assert_eq!(s(eval("[0.1 + 0.2, 2].join('-')", &opts).unwrap()), "0.3-2");
+    assert_eq!(s(eval("(1/3).to_s()", &opts).unwrap()), "0.333333333333333");
+    assert_eq!(s(eval("(-0.0).to_s()", &opts).unwrap()), "0");
+}
+
+#[test]
+fn precision_applies_to_rendered_strings() {
+    let opts = EvalOptions::new().with_precision(2);
+    assert_eq!(s(eval("(1/3).to_s()", &opts).unwrap()), "0.33");
+    assert_eq!(s(eval("CONCAT(2/3)", &opts).unwrap()), "0.67");
+
+    let sig = EvalOptions::new().with_significant_digits(3);
+    assert_eq!(s(eval("(12345.678).to_s()", &sig).unwrap()), "12300");
+    assert_eq!(n(eval("1/3", &sig).unwrap()), 0.333);
+    assert!(eval("1", &EvalOptions::new().with_significant_digits(0)).is_err());
+    assert!(eval("1", &EvalOptions::new().with_significant_digits(15)).is_ok());
+    assert!(eval("1", &EvalOptions::new().with_significant_digits(16)).unwrap_err().message.contains("between 1 and 15"));
+}
+
+#[test]
+fn format_function() {
+    let opts = EvalOptions::default();
+    assert_eq!(s(eval("FORMAT(3.14159, 2)", &opts).unwrap()), "3.14");
+    assert_eq!(s(eval("FORMAT(2, 2)", &opts).unwrap()), "2.00");
+    assert_eq!(s(eval("FORMAT(-0.001, 2)", &opts).unwrap()), "0.00");
+    assert_eq!(s(eval("FORMAT(0.1 + 0.2)", &opts).unwrap()), "0.3");
+    assert!(eval("FORMAT('x', 2)", &opts).is_err());
+}
+
+#[test]
+fn builtin_allow_and_deny_lists() {
+    let deny = EvalOptions::new().with_denied_builtins(["now", "RAND"]);
+    let err = eval("NOW()", &deny).unwrap_err();
+    assert!(err.message.contains("NOW") && err.message.contains("denied_builtins"));
+    assert!(eval("RAND()", &deny).is_err());
+    assert_eq!(n(eval("SUM(1, 2)", &deny).unwrap()), 3.0);
+
+    let allow = EvalOptions::new().with_allowed_builtins(["SUM", "FILTER"]);
+    assert_eq!(n(eval("SUM(FILTER([1, 2, 3], :x > 1))", &allow).unwrap()), 5.0);
+    let err = eval("AVG(1, 2)", &allow).unwrap_err();
+    assert!(err.message.contains("AVG") && err.message.contains("allowed_builtins"));
+    assert!(eval("MAP([1], :x * 2)", &allow).is_err());
+
+    // Ranges, ternaries and constants lower to internal calls the policy does not cover
+    let sum_only = EvalOptions::new().with_allowed_builtins(["SUM"]);
+    assert_eq!(n(eval("SUM(1..3)", &sum_only).unwrap()), 3.0);
+    assert_eq!(n(eval("SUM(true ? 1 : 2, false ? 5 : 1)", &sum_only).unwrap()), 2.0);
+
+    // A denial wins over an allowance
+    let both = EvalOptions::new().with_allowed_builtins(["SUM"]).with_denied_builtins(["SUM"]);
+    assert!(eval("SUM(1)", &both).is_err());
+}
+
+#[test]
+fn epsilon_relaxes_number_equality() {
+    let exact = EvalOptions::default();
+    assert_eq!(eval("0.1 + 0.2 == 0.3", &exact).unwrap(), Value::Boolean(false));
+
+    let tolerant = EvalOptions::new().with_epsilon(1e-9);
+    assert_eq!(eval("0.1 + 0.2 == 0.3", &tolerant).unwrap(), Value::Boolean(true));
+    assert_eq!(eval("0.1 + 0.2 != 0.3", &tolerant).unwrap(), Value::Boolean(false));
+    assert_eq!(eval("1.001 == 1", &tolerant).unwrap(), Value::Boolean(false));
+    assert!(eval("1", &EvalOptions::new().with_epsilon(-1.0)).is_err());
+}
+
+#[test]
+fn approxeq_function() {
+    let opts = EvalOptions::default();
+    assert_eq!(eval("APPROXEQ(0.1 + 0.2, 0.3)", &opts).unwrap(), Value::Boolean(true));
+    assert_eq!(eval("APPROXEQ(100, 100.4, 0.5)", &opts).unwrap(), Value::Boolean(true));
+    assert_eq!(eval("APPROXEQ(1, 1.1)", &opts).unwrap(), Value::Boolean(false));
+    // Relative above 1: on ten million, 1e-9 allows a difference of 0.01
+    assert_eq!(eval("APPROXEQ(10000000, 10000000.02, 0.000000001)", &opts).unwrap(), Value::Boolean(false));
+    assert_eq!(eval("APPROXEQ(10000000, 10000000.001, 0.000000001)", &opts).unwrap(), Value::Boolean(true));
+    // The option supplies the default tolerance
+    let loose = EvalOptions::new().with_epsilon(0.2);
+    assert_eq!(eval("APPROXEQ(1, 1.1)", &loose).unwrap(), Value::Boolean(true));
+    assert!(eval("APPROXEQ('a', 1)", &opts).is_err());
+}
+
+#[test]
+fn overflow_check_rejects_saturated_numbers() {
+    let plain = EvalOptions::default();
+    assert_eq!(n(eval("10 ^ 400", &plain).unwrap()), f64::INFINITY);
+    assert_eq!(n(eval("POW(10, 400)", &plain).unwrap()), f64::INFINITY);
+
+    let checked = EvalOptions::new().with_overflow_check(true);
+    let err = eval("10 ^ 400", &checked).unwrap_err();
+    assert!(err.message.contains("Numeric overflow in 10 ^ 400"), "{}", err.message);
+    assert!(eval("POW(10, 400)", &checked).unwrap_err().message.contains("Numeric overflow in POW"));
+    // The legacy evaluator checks builtin results the same way
+    let expr = skillet::parse("POW(10, 400)").unwrap();
+    let legacy = skillet::context::with_context(skillet::EvalContext::new().with_options(checked.clone()), || skillet::runtime::eval(&expr));
+    assert!(legacy.unwrap_err().message.contains("Numeric overflow in POW"));
+    assert!(eval("SUM([POW(10, 308), POW(10, 308)])", &checked).unwrap_err().message.contains("SUM"));
+    assert!(eval("(0.1 ^ 200) * (0.1 ^ 200)", &checked).unwrap_err().message.contains("Numeric underflow"));
+    assert!(eval("1 / 0", &checked).unwrap_err().message.contains("Division by zero"));
+    assert_eq!(n(eval("4 % 2 + 10 ^ 300", &checked).unwrap()), 1e300);
+
+    let mut vars = HashMap::new();
+    vars.insert("big".to_string(), Value::Number(1e300));
+    assert_eq!(n(evaluate_with_options(":big.to_i()", &vars, &plain).unwrap()), 1e300);
+    assert_eq!(n(evaluate_with_options(":big::Integer", &vars, &plain).unwrap()), i64::MAX as f64);
+    for expr in [":big.to_i()", ":big::Integer", ":big::DateTime"] {
+        let err = evaluate_with_options(expr, &vars, &checked).unwrap_err();
+        assert!(err.message.contains("out of the safe integer range"), "{}: {}", expr, err.message);
+    }
+    assert_eq!(n(eval("9007199254740991::Integer", &checked).unwrap()), 9007199254740991.0);
+}
+
+#[test]
+fn max_memory_bytes_stops_runaway_strings() {
+    let grow = "REDUCE(1..=2000, CONCAT(:acc, '0123456789012345678901234567890123456789'), '')";
+    let err = eval(grow, &EvalOptions::new().with_max_memory_bytes(100_000)).unwrap_err();
+    assert!(err.message.contains("Evaluation exceeded max_memory_bytes of 100000"), "{}", err.message);
+
+    let method = "(1..=12).reduce(CONCAT(:acc, :acc), 'xxxxxxxx')";
+    let err = eval(method, &EvalOptions::new().with_max_memory_bytes(4_000)).unwrap_err();
+    assert!(err.message.contains("max_memory_bytes"), "{}", err.message);
+
+    let small = EvalOptions::new().with_max_memory_bytes(1_000_000);
+    assert_eq!(s(eval("CONCAT('a', 'b')", &small).unwrap()), "ab");
+    assert!(eval(grow, &EvalOptions::default()).is_ok());
+}
+
+#[test]
+fn max_string_length_caps_repetition_and_concatenation() {
+    let opts = EvalOptions::new().with_max_string_length(5);
+    assert_eq!(s(eval("REPT('ab', 2)", &opts).unwrap()), "abab");
+    let capped = [
+        "REPT('ab', 3)",
+        "'ab' * 3",
+        "CONCAT('abc', 'def')",
+        "JOIN(['abc', 'de'], ',')",
+        "['abc', 'de'].join(',')",
+        "SUBSTITUTE('aaa', 'a', 'bb')",
+        "SUBSTITUTEM('aaa', 'a', 'bb')",
+    ];
+    for expr in capped {
+        let err = eval(expr, &opts).unwrap_err();
+        assert!(err.message.contains("exceeds max_string_length of 5"), "{}: {}", expr, err.message);
+    }
+    assert_eq!(s(eval("REPT('ab', 3)", &EvalOptions::default()).unwrap()), "ababab");
+}
+
+#[test]
+fn null_propagation_turns_null_arithmetic_into_null() {
+    let mut vars = HashMap::new();
+    vars.insert("a".to_string(), Value::Null);
+    let plain = EvalOptions::default();
+    assert!(evaluate_with_options(":a + 1", &vars, &plain).is_err());
+
+    let sql = EvalOptions::new().with_null_propagation(true);
+    assert_eq!(evaluate_with_options(":a + 1", &vars, &sql).unwrap(), Value::Null);
+    assert_eq!(evaluate_with_options("2 * :a - 3", &vars, &sql).unwrap(), Value::Null);
+    assert_eq!(evaluate_with_options("-:a", &vars, &sql).unwrap(), Value::Null);
+    assert_eq!(evaluate_with_options("COALESCE(:a * 2, 0)", &vars, &sql).unwrap(), Value::Number(0.0));
+    assert_eq!(n(eval("1 + 2", &sql).unwrap()), 3.0);
+}
+
+#[test]
+fn checked_arithmetic_follows_overflow_policy() {
+    let plain = EvalOptions::default();
+    assert_eq!(eval("CHECKEDADD(9007199254740991, 1)", &plain).unwrap(), Value::Null);
+    assert_eq!(n(eval("CHECKEDADD(9007199254740990, 1)", &plain).unwrap()), 9007199254740991.0);
+
+    let checked = EvalOptions::new().with_overflow_check(true);
+    let err = eval("CHECKEDMUL(10 ^ 300, 10 ^ 300)", &checked).unwrap_err();
+    assert!(err.message.contains("Numeric overflow in CHECKEDMUL"), "{}", err.message);
+    assert_eq!(n(eval("CHECKEDSUB(10, 25)", &checked).unwrap()), -15.0);
+}
@@ -0,0 +1,82 @@
+use skillet::{evaluate_explained, parse, Value};
+use std::collections::HashMap;
+
+#[test]
+fn source_rendering_round_trips() {
+    let cases = [
+        "1 + 2 * 3",
+        "(1 + 2) * 3",
+        "2 ^ 3 ^ 2",
+        "-2 ^ 2",
+        "(-2) ^ 2",
+        ":a - (:b - :c)",
+        "!:x AND (:y OR :z)",
+        ":score >= 90 ? 'A' : :score >= 80 ? 'B' : 'C'",
+        "(:a ? 1 : 2) + 3",
+        "SUM(:items.map(:x * 2), ...:rest)",
+        ":user&.name&.upper()",
+        ":arr[0] + :arr[1:3].sum() + :arr[:2].length()",
+        ":n.even? AND :s.blank?",
+        "(1 + 2).abs()",
+        "'a\"b\\\\c'",
+        "{name: 'x', 'full name': [1, 2]}",
+        "1..=:n",
+        "(1..5).length()",
+        "'42'::Integer + 1",
+        "d\"2024-05-01T10:00:00Z\"",
+        ":a := 1; :b := :a + 1; :b * 2",
+        "TRUE AND FALSE OR NULL == NULL",
+    ];
+    for src in cases {
+        let expr = parse(src).unwrap_or_else(|e| panic!("{}: {}", src, e.message));
+        let rendered = expr.to_string();
+        assert_eq!(parse(&rendered).unwrap_or_else(|e| panic!("{} -> {}: {}", src, rendered, e.message)), expr, "{} rendered as {}", src, rendered);
+    }
+    assert_eq!(parse("(1 + 2) * :x").unwrap().to_string(), "(1 + 2) * :x");
+    assert_eq!(parse("1 + (2 * 3)").unwrap().to_string(), "1 + 2 * 3");
+}
+
+#[test]
+fn explains_intermediate_values() {
+    let mut vars = HashMap::new();
+    vars.insert("price".to_string(), Value::Number(10.0));
+    vars.insert("qty".to_string(), Value::Number(0.0));
+
+    let tree = evaluate_explained(":price * :qty + MAX(1, 2)", &vars, 10).unwrap();
+    assert_eq!(tree.source, ":price * :qty + MAX(1, 2)");
+    assert_eq!(tree.result, Ok(Value::Number(2.0)));
+    let product = &tree.children[0];
+    assert_eq!(product.source, ":price * :qty");
+    assert_eq!(product.result, Ok(Value::Number(0.0)));
+    assert_eq!(product.children[1].source, ":qty");
+    assert_eq!(product.children[1].result, Ok(Value::Number(0.0)));
+    assert_eq!(tree.children[1].children.len(), 2);
+
+    let text = tree.to_text();
+    assert!(text.contains("\n    :qty => Number(0.0)"), "{}", text);
+}
+
+#[test]
+fn explains_errors_and_assignments() {
+    let tree = evaluate_explained(":a := 2; :a + NOSUCHFUNCTION(1)", &HashMap::new(), 10).unwrap();
+    assert!(tree.result.is_err());
+    assert_eq!(tree.children[0].source, ":a := 2");
+    assert_eq!(tree.children[0].result, Ok(Value::Number(2.0)));
+    let failed = &tree.children[1];
+    assert_eq!(failed.children[0].result, Ok(Value::Number(2.0)));
+    assert!(failed.children[1].result.as_ref().unwrap_err().contains("NOSUCHFUNCTION"));
+
+    assert!(evaluate_explained("1 +", &HashMap::new(), 10).is_err());
+}
+
+#[test]
+fn depth_limit_truncates_the_tree() {
+    let tree = evaluate_explained("(1 + 2) * (3 + 4)", &HashMap::new(), 2).unwrap();
+    assert_eq!(tree.result, Ok(Value::Number(21.0)));
+    assert_eq!(tree.children.len(), 2);
+    assert!(tree.children.iter().all(|c| c.children.is_empty() && c.truncated));
+    assert!(!tree.truncated);
+
+    let tree = evaluate_explained("SUM(1, 2)", &HashMap::new(), 1).unwrap();
+    assert!(tree.children.is_empty() && tree.truncated);
+}
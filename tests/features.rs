@@ -0,0 +1,58 @@
+use skillet::{clear_feature_provider, evaluate, evaluate_explained, evaluate_with_options, set_feature_provider, EvalContext, EvalOptions, FeatureProvider, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+struct Rollout {
+    lookups: Arc<AtomicUsize>,
+}
+
+impl FeatureProvider for Rollout {
+    fn is_enabled(&self, name: &str, context: Option<&EvalContext>) -> Option<bool> {
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        match name {
+            "new_pricing" => Some(true),
+            // Targeted at one caller
+            "beta" => Some(context.and_then(|c| c.request_id.as_deref()) == Some("beta-tester")),
+            _ => None,
+        }
+    }
+}
+
+// The provider is process-wide, so everything runs in one test
+#[test]
+fn feature_flags_come_from_the_host_provider() {
+    clear_feature_provider();
+    assert_eq!(evaluate("FEATURE('new_pricing')").unwrap(), Value::Boolean(false));
+    assert_eq!(evaluate("FEATURE('new_pricing', TRUE)").unwrap(), Value::Boolean(true));
+
+    let lookups = Arc::new(AtomicUsize::new(0));
+    set_feature_provider(Box::new(Rollout { lookups: lookups.clone() }));
+    assert_eq!(evaluate("IF(FEATURE('new_pricing'), 90, 100)").unwrap(), Value::Number(90.0));
+    assert_eq!(evaluate("FEATURE('unknown')").unwrap(), Value::Boolean(false));
+    assert_eq!(evaluate("FEATURE('unknown', TRUE)").unwrap(), Value::Boolean(true));
+    assert!(evaluate("FEATURE(1)").is_err());
+
+    // Only consulted for calls that are evaluated
+    lookups.store(0, Ordering::SeqCst);
+    assert_eq!(evaluate("TRUE ? 1 : (FEATURE('beta') ? 2 : 3)").unwrap(), Value::Number(1.0));
+    assert_eq!(lookups.load(Ordering::SeqCst), 0);
+
+    let ctx = EvalContext::new().with_request_id("beta-tester");
+    let on = skillet::context::with_context(ctx, || evaluate("FEATURE('beta')")).unwrap();
+    assert_eq!(on, Value::Boolean(true));
+    assert_eq!(evaluate("FEATURE('beta')").unwrap(), Value::Boolean(false));
+
+    // The looked-up value is part of the explain trace
+    let tree = evaluate_explained("IF(FEATURE('new_pricing'), 90, 100)", &HashMap::new(), 8).unwrap();
+    let call = tree.children.iter().find(|n| n.source.starts_with("FEATURE")).expect("FEATURE node");
+    assert_eq!(call.result, Ok(Value::Boolean(true)));
+
+    let deterministic = EvalOptions::new().with_deterministic(true);
+    assert!(evaluate_with_options("FEATURE('new_pricing')", &HashMap::new(), &deterministic).is_err());
+
+    let fixed: HashMap<String, bool> = [("dark_mode".to_string(), true)].into_iter().collect();
+    set_feature_provider(Box::new(fixed));
+    assert_eq!(evaluate("FEATURE('dark_mode')").unwrap(), Value::Boolean(true));
+    clear_feature_provider();
+}
@@ -248,4 +248,173 @@ fn test_financial_error_cases() {
     assert!(evaluate("=IPMT(0.05, 0, 12, 1000)").is_err()); // Period < 1
     assert!(evaluate("=IPMT(0.05, 13, 12, 1000)").is_err()); // Period > nper
     assert!(evaluate("=IPMT(0.05, 1, 0, 1000)").is_err()); // Zero periods
-}
\ No newline at end of file
+}
+
+#[test]
+fn bracket_computes_progressive_totals() {
+    let table = "[[10000, 0], [40000, 0.2], [null, 0.4]]";
+    let tax = |amount: &str| match evaluate(&format!("BRACKET({}, {})", amount, table)).unwrap() {
+        Value::Number(n) => n,
+        other => panic!("expected number, got {:?}", other),
+    };
+    for (amount, expected) in [("0", 0.0), ("-500", 0.0), ("10000", 0.0), ("10001", 0.2), ("40000", 6000.0), ("50000", 10000.0)] {
+        assert!((tax(amount) - expected).abs() < 1e-9, "{}: {}", amount, tax(amount));
+    }
+
+    // Without an open bracket the last rate continues past the last limit
+    assert_eq!(evaluate("BRACKET(30, [[10, 0.1], [20, 0.2]])").unwrap(), Value::Number(1.0 + 2.0 + 2.0));
+    assert_eq!(evaluate("BRACKET('100'::Currency, [[null, 0.16]])").unwrap(), Value::Currency(16.0));
+
+    for (expr, message) in [
+        ("BRACKET(1, [])", "expects a table"),
+        ("BRACKET(1, [[null, 0.1], [10, 0.2]])", "only the last row may have a null limit"),
+        ("BRACKET(1, [[10, 0.1], [10, 0.2]])", "limits must increase from 0, got 10 after 10"),
+        ("BRACKET(1, [[10]])", "row 1 must be [limit, rate]"),
+        ("BRACKET(1, [[10, 'x']])", "the rate must be a number"),
+        ("BRACKET('a', [[10, 0.1]])", "amount must be a number"),
+    ] {
+        let err = evaluate(expr).unwrap_err();
+        assert!(err.message.contains(message), "{}: {}", expr, err.message);
+    }
+}
+
+#[test]
+fn tierprice_graduated_and_volume() {
+    let tiers = "[[10, 5], [50, 4], [null, 3]]";
+    let price = |q: &str, mode: &str| match evaluate(&format!("TIERPRICE({}, {}, '{}')", q, tiers, mode)).unwrap() {
+        Value::Number(n) => n,
+        other => panic!("expected number, got {:?}", other),
+    };
+    assert_eq!(price("0", "graduated"), 0.0);
+    assert_eq!(price("10", "graduated"), 50.0);
+    assert_eq!(price("60", "graduated"), 50.0 + 160.0 + 30.0);
+    assert_eq!(price("60", "volume"), 180.0);
+    assert_eq!(price("50", "volume"), 200.0);
+    assert_eq!(price("10", "VOLUME"), 50.0);
+    assert_eq!(evaluate(&format!("TIERPRICE(60, {})", tiers)).unwrap(), Value::Number(240.0));
+
+    let rows = evaluate(&format!("TIERPRICE(60, {}, 'graduated', TRUE)", tiers)).unwrap();
+    let Value::Array(rows) = rows else { panic!("expected breakdown array") };
+    let rows: Vec<serde_json::Value> = rows
+        .iter()
+        .map(|r| match r {
+            Value::Json(s) => serde_json::from_str(s).unwrap(),
+            other => panic!("expected object, got {:?}", other),
+        })
+        .collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[1]["tier"], 2);
+    assert_eq!(rows[1]["units"], 40.0);
+    assert_eq!(rows[1]["unit_price"], 4.0);
+    assert_eq!(rows[2]["amount"], 30.0);
+    assert_eq!(evaluate(&format!("TIERPRICE(60, {}, 'volume', TRUE)[0].amount", tiers)).unwrap(), Value::Number(180.0));
+
+    assert!(evaluate("TIERPRICE(5, [[10, 1]], 'flat')").is_err());
+    assert!(evaluate("TIERPRICE('5', [[10, 1]])").is_err());
+}
+
+#[test]
+fn yearfrac_follows_excel_bases() {
+    let frac = |basis: &str| match evaluate(&format!("YEARFRAC('2012-01-01', '2012-07-30'{})", basis)).unwrap() {
+        Value::Number(n) => n,
+        other => panic!("expected a number, got {:?}", other),
+    };
+    assert!((frac("") - 209.0 / 360.0).abs() < 1e-9);
+    assert!((frac(", 1") - 211.0 / 366.0).abs() < 1e-9);
+    assert!((frac(", 'actual/360'") - 211.0 / 360.0).abs() < 1e-9);
+    assert!((frac(", 3") - 211.0 / 365.0).abs() < 1e-9);
+    // Under a year that takes in Feb 29 uses 366-day years
+    match evaluate("YEARFRAC('2024-03-01', '2023-07-01', 1)").unwrap() {
+        Value::Number(n) => assert!((n - 244.0 / 366.0).abs() < 1e-9),
+        other => panic!("expected a number, got {:?}", other),
+    }
+}
+
+#[test]
+fn accrint_accrues_coupon_interest() {
+    let accrint = |expr: &str| match evaluate(expr).unwrap() {
+        Value::Number(n) => n,
+        other => panic!("expected a number, got {:?}", other),
+    };
+    // Excel's documented example
+    let excel = accrint("ACCRINT(DATE(2008, 3, 1), DATE(2008, 8, 31), DATE(2008, 5, 1), 0.1, 1000, 2, 0)");
+    assert!((excel - 1000.0 * 0.1 * 60.0 / 360.0).abs() < 1e-9);
+
+    let bond = "ACCRINT('2024-01-01', '2024-07-01', '2024-04-01', 0.06, 1000, 2";
+    assert!((accrint(&format!("{})", bond)) - 15.0).abs() < 1e-9);
+    assert!((accrint(&format!("{}, 2)", bond)) - 60.0 * 91.0 / 360.0).abs() < 1e-9);
+    assert!((accrint(&format!("{}, 3)", bond)) - 60.0 * 91.0 / 365.0).abs() < 1e-9);
+    // Actual/actual divides by the 182 days of the coupon period
+    assert!((accrint(&format!("{}, 1)", bond)) - 30.0 * 91.0 / 182.0).abs() < 1e-9);
+
+    let later = "ACCRINT('2024-01-01', '2024-07-01', '2024-10-01', 0.06, 1000, 2, 0";
+    assert!((accrint(&format!("{})", later)) - 45.0).abs() < 1e-9);
+    assert!((accrint(&format!("{}, FALSE)", later)) - 15.0).abs() < 1e-9);
+
+    assert!(evaluate("ACCRINT('2024-01-01', '2024-07-01', '2024-04-01', 0.06, 1000, 3)").is_err());
+    assert!(evaluate("ACCRINT('2024-01-01', '2024-07-01', '2024-04-01', -0.06, 1000, 2)").is_err());
+    assert!(evaluate("ACCRINT('2024-04-01', '2024-07-01', '2024-01-01', 0.06, 1000, 2)").is_err());
+}
+
+#[test]
+fn depreciation_matches_excel_schedules() {
+    let number = |expr: &str| match evaluate(expr).unwrap() {
+        Value::Number(n) => n,
+        other => panic!("expected a number, got {:?}", other),
+    };
+    // Excel's DB example: a 7-month first year spills into a seventh period
+    let db = [186083.33, 259639.42, 176814.44, 120410.64, 81999.64, 55841.76, 15845.10];
+    for (i, expected) in db.iter().enumerate() {
+        let got = number(&format!("DB(1000000, 100000, 6, {}, 7)", i + 1));
+        assert!((got - expected).abs() < 0.01, "DB period {}: {}", i + 1, got);
+    }
+    assert!(evaluate("DB(1000000, 100000, 6, 7)").is_err());
+    assert!(evaluate("DB(1000000, 100000, 6, 1, 13)").is_err());
+
+    assert_eq!(number("SLN(30000, 7500, 10)"), 2250.0);
+    assert!((number("SYD(30000, 7500, 10, 1)") - 4090.91).abs() < 0.01);
+    assert!((number("SYD(30000, 7500, 10, 10)") - 409.09).abs() < 0.01);
+
+    assert!((number("DDB(2400, 300, 10 * 365, 1)") - 1.32).abs() < 0.01);
+    assert!((number("DDB(2400, 300, 10 * 12, 1, 2)") - 40.0).abs() < 1e-9);
+    assert!((number("DDB(2400, 300, 10, 1, 2)") - 480.0).abs() < 1e-9);
+    assert!((number("DDB(2400, 300, 10, 2, 1.5)") - 306.0).abs() < 1e-9);
+    // The last year only writes down to salvage
+    assert!((number("DDB(2400, 300, 10, 10)") - 22.12).abs() < 0.01);
+
+    assert!(evaluate("SLN(30000, 7500, 0)").is_err());
+    assert!(evaluate("SYD(30000, 7500, 10, 0)").is_err());
+    assert!(evaluate("SYD(30000, 7500, 10, 11)").is_err());
+    assert!(evaluate("DDB(2400, 300, 10, 2.5)").is_err());
+    assert!(evaluate("DDB(2400, 300, 10, 1, 0)").is_err());
+}
+
+#[test]
+fn amortize_builds_a_payment_schedule() {
+    let rows = match evaluate("AMORTIZE(0.05 / 12, 360, 200000)").unwrap() {
+        Value::Array(rows) => rows
+            .iter()
+            .map(|r| match r {
+                Value::Json(s) => serde_json::from_str::<serde_json::Value>(s).unwrap(),
+                other => panic!("expected object, got {:?}", other),
+            })
+            .collect::<Vec<_>>(),
+        other => panic!("expected schedule array, got {:?}", other),
+    };
+    assert_eq!(rows.len(), 360);
+    let num = |v: &serde_json::Value| v.as_f64().unwrap();
+    // Same payment as PMT, with the sign flipped
+    assert!((num(&rows[0]["payment"]) - 1073.6432460242797).abs() < 1e-6);
+    assert!((num(&rows[0]["interest"]) - 833.3333333333334).abs() < 1e-6);
+    assert!((num(&rows[0]["principal"]) - 240.30991269094633).abs() < 1e-6);
+    assert_eq!(rows[0]["period"], 1);
+    assert_eq!(rows[359]["period"], 360);
+    assert_eq!(num(&rows[359]["balance"]), 0.0);
+    let principal: f64 = rows.iter().map(|r| num(&r["principal"])).sum();
+    assert!((principal - 200000.0).abs() < 1e-4);
+
+    assert_eq!(evaluate("AMORTIZE(0, 4, 1000)[0].payment").unwrap(), Value::Number(250.0));
+    assert!(evaluate("AMORTIZE(0.01, 0, 1000)").is_err());
+    assert!(evaluate("AMORTIZE(0.01, 12.5, 1000)").is_err());
+    assert!(evaluate("AMORTIZE(0.01, 12)").is_err());
+}
@@ -0,0 +1,50 @@
+use skillet::{evaluate, evaluate_with, evaluate_with_custom, Value};
+use std::collections::HashMap;
+
+const TIERS: &str = "[{'when': :x >= 1000, 'then': 'gold'}, {'when': :x >= 100, 'then': 'silver'}, {'when': TRUE, 'then': 'bronze'}]";
+
+fn s(v: &str) -> Value {
+    Value::String(v.to_string())
+}
+
+#[test]
+fn inline_rules_take_lambdas() {
+    assert_eq!(evaluate(&format!("FIRSTMATCH({}, 1500)", TIERS)).unwrap(), s("gold"));
+    assert_eq!(evaluate(&format!("FIRSTMATCH({}, 100)", TIERS)).unwrap(), s("silver"));
+    assert_eq!(evaluate_with_custom(&format!("FIRSTMATCH({}, 5)", TIERS), &HashMap::new()).unwrap(), s("bronze"));
+    assert_eq!(evaluate("FIRSTMATCH([{'when': :x > 10, 'then': 1}], 5)").unwrap(), Value::Null);
+    // Later `then`s are not evaluated once a rule matched
+    assert_eq!(
+        evaluate("FIRSTMATCH([{'when': TRUE, 'then': 'ok'}, {'when': TRUE, 'then': 1 / 'x'}], 0)").unwrap(),
+        s("ok")
+    );
+    let indexed = evaluate(&format!("FIRSTMATCH({}, 100, TRUE)", TIERS)).unwrap();
+    assert_eq!(indexed, Value::Json(r#"{"index":1,"then":"silver"}"#.to_string()));
+    let none = evaluate("FIRSTMATCH([{'when': FALSE, 'then': 1}], 5, TRUE)").unwrap();
+    assert_eq!(none, Value::Json(r#"{"index":null,"then":null}"#.to_string()));
+}
+
+#[test]
+fn data_rules_use_criteria() {
+    let rules = [
+        serde_json::json!({"when": ">=1000", "then": 0.15}),
+        serde_json::json!({"when": "VIP", "then": 0.2}),
+        serde_json::json!({"when": 42, "then": 0.42}),
+        serde_json::json!({"when": true, "then": 0}),
+    ];
+    let mut vars = HashMap::new();
+    vars.insert("rules".to_string(), Value::Array(rules.iter().map(|r| Value::Json(r.to_string())).collect()));
+    let first = |value: &str| evaluate_with(&format!("FIRSTMATCH(:rules, {})", value), &vars).unwrap();
+    assert_eq!(first("2500"), Value::Number(0.15));
+    assert_eq!(first("'VIP'"), Value::Number(0.2));
+    assert_eq!(first("42"), Value::Number(0.42));
+    assert_eq!(first("7"), Value::Number(0.0));
+}
+
+#[test]
+fn rejects_malformed_rules() {
+    assert!(evaluate("FIRSTMATCH([1], 5)").unwrap_err().message.contains("rule 0 must be an object"));
+    assert!(evaluate("FIRSTMATCH([{'then': 1}], 5)").unwrap_err().message.contains("rule 0 has no 'when'"));
+    assert!(evaluate("FIRSTMATCH('rules', 5)").is_err());
+    assert!(evaluate("FIRSTMATCH([{'when': TRUE, 'then': 1}], 5, 'yes')").is_err());
+}
@@ -0,0 +1,71 @@
+use skillet::{FormulaGraph, Value};
+use std::collections::HashMap;
+
+fn sheet() -> FormulaGraph {
+    FormulaGraph::build([
+        ("total", ":subtotal + :tax"),
+        ("tax", ":subtotal * :rate"),
+        ("subtotal", "SUM(:prices)"),
+        ("label", "CONCAT('Total: ', :total)"),
+        ("standalone", "1 + 1"),
+    ])
+    .unwrap()
+}
+
+#[test]
+fn orders_formulas_after_their_dependencies() {
+    let g = sheet();
+    let pos = |n: &str| g.order().iter().position(|o| o == n).unwrap();
+    assert!(pos("subtotal") < pos("tax"));
+    assert!(pos("tax") < pos("total"));
+    assert!(pos("total") < pos("label"));
+    assert_eq!(g.order().len(), 5);
+
+    assert_eq!(g.inputs(), ["prices", "rate"]);
+    assert_eq!(g.dependencies("total"), ["subtotal", "tax"]);
+    assert_eq!(g.dependents("subtotal"), ["tax", "total"]);
+}
+
+#[test]
+fn recalc_order_covers_transitive_dependents() {
+    let g = sheet();
+    assert_eq!(g.recalc_order(&["tax"]), ["tax", "total", "label"]);
+    assert_eq!(g.recalc_order(&["rate"]), ["tax", "total", "label"]);
+    assert_eq!(g.recalc_order(&["standalone"]), ["standalone"]);
+    assert!(g.recalc_order(&["unknown"]).is_empty());
+}
+
+#[test]
+fn lambda_parameters_are_not_inputs() {
+    let g = FormulaGraph::build([
+        ("scaled", "MAP(:prices, :x * :rate)"),
+        ("total", "REDUCE(:scaled, :acc + :x, 0) + :prices.filter(:p > :floor, 'p').count()"),
+        ("first", "FIND(:scaled, n -> :n > :x)"),
+    ])
+    .unwrap();
+    assert_eq!(g.inputs(), ["floor", "prices", "rate", "x"]);
+    assert_eq!(g.dependencies("total"), ["scaled"]);
+}
+
+#[test]
+fn evaluates_in_order() {
+    let mut vars = HashMap::new();
+    vars.insert("prices".to_string(), Value::Array(vec![Value::Number(40.0), Value::Number(60.0)]));
+    vars.insert("rate".to_string(), Value::Number(0.16));
+    let results = sheet().evaluate(&vars).unwrap();
+    assert_eq!(results["subtotal"], Value::Number(100.0));
+    assert_eq!(results["total"], Value::Number(116.0));
+    assert_eq!(results["label"], Value::String("Total: 116".to_string()));
+}
+
+#[test]
+fn reports_cycles_and_parse_errors() {
+    let err = FormulaGraph::build([("a", ":b + 1"), ("b", ":c + 1"), ("c", ":a + 1"), ("d", ":a")]).unwrap_err();
+    assert_eq!(err.message, "Circular reference: a -> b -> c -> a");
+
+    let err = FormulaGraph::build([("self", ":self * 2")]).unwrap_err();
+    assert_eq!(err.message, "Circular reference: self -> self");
+
+    let err = FormulaGraph::build([("ok", "1"), ("bad", "1 +")]).unwrap_err();
+    assert!(err.message.starts_with("Formula 'bad'"));
+}
@@ -0,0 +1,57 @@
+use skillet::{evaluate, evaluate_with, evaluate_with_options, EvalOptions, Value};
+use std::collections::HashMap;
+
+#[test]
+fn iferror_falls_back_on_runtime_errors() {
+    for expr in ["IFERROR(NO_SUCH_FUNCTION(1), -1)", "IFERROR(1 / 'x', -1)", "IFERROR('x'::DateTime, -1)", "IFERROR(:missing * 2, -1)"] {
+        assert_eq!(evaluate(expr).unwrap(), Value::Number(-1.0), "{}", expr);
+    }
+    assert_eq!(evaluate("IFERROR(6 * 7, -1)").unwrap(), Value::Number(42.0));
+    // The fallback only runs on error, and its own errors are not caught
+    assert_eq!(evaluate("IFERROR(1, 1 / 'x')").unwrap(), Value::Number(1.0));
+    assert!(evaluate("IFERROR(1 / 'x', 2 / 'y')").is_err());
+    // A null result is not an error
+    let mut vars = HashMap::new();
+    vars.insert("discount".to_string(), Value::Null);
+    assert_eq!(evaluate_with("IFERROR(:discount, 0)", &vars).unwrap(), Value::Null);
+}
+
+#[test]
+fn try_reports_the_outcome() {
+    assert!(matches!(evaluate("TRY(SUM(1, 2))").unwrap(), Value::Map(_)));
+    assert_eq!(evaluate("TRY(SUM(1, 2)).ok").unwrap(), Value::Boolean(true));
+    assert_eq!(evaluate("TRY(SUM(1, 2)).value").unwrap(), Value::Number(3.0));
+    assert_eq!(evaluate("TRY(NO_SUCH_FUNCTION()).ok").unwrap(), Value::Boolean(false));
+    assert_eq!(evaluate("TRY(NO_SUCH_FUNCTION()).value").unwrap(), Value::Null);
+    match evaluate("TRY(NO_SUCH_FUNCTION()).error").unwrap() {
+        Value::String(message) => assert!(message.contains("NO_SUCH_FUNCTION"), "{}", message),
+        other => panic!("expected the error message, got {:?}", other),
+    }
+    // Works per item inside lambdas
+    assert_eq!(
+        evaluate("[1, 'x', 3].map(TRY(6 / :x).ok)").unwrap(),
+        Value::Array(vec![Value::Boolean(true), Value::Boolean(false), Value::Boolean(true)])
+    );
+}
+
+#[test]
+fn limits_are_not_caught() {
+    // A single oversized array is an ordinary error...
+    let options = EvalOptions::new().with_max_array_size(10);
+    assert!(evaluate_with_options("IFERROR(RANGE(1, 100), [])", &HashMap::new(), &options).is_ok());
+
+    // ...but running out of the evaluation's memory (or time) is not recoverable
+    let options = EvalOptions::new().with_max_memory_bytes(1_000);
+    let err = evaluate_with_options("IFERROR(REPT('x', 5000), 'small')", &HashMap::new(), &options).unwrap_err();
+    assert!(err.message.contains("max_memory_bytes"), "{}", err.message);
+    let err = evaluate_with_options("TRY(REPT('x', 5000)).ok", &HashMap::new(), &options).unwrap_err();
+    assert!(err.message.contains("max_memory_bytes"), "{}", err.message);
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    for (expr, message) in [("IFERROR(1)", "IFERROR expects"), ("IFERROR(1, 2, 3)", "IFERROR expects"), ("TRY()", "TRY expects"), ("TRY(1, 2)", "TRY expects")] {
+        let err = evaluate(expr).unwrap_err();
+        assert!(err.message.contains(message), "{}: {}", expr, err.message);
+    }
+}
@@ -0,0 +1,72 @@
+use skillet::{evaluate, evaluate_with, evaluate_with_custom, Value};
+use std::collections::HashMap;
+
+fn n(v: Value) -> f64 { if let Value::Number(n) = v { n } else { panic!("expected number, got {:?}", v) } }
+
+#[test]
+fn fixed_number_of_steps() {
+    // 1000 compounded at 5% for 10 years
+    let v = n(evaluate("ITERATE(1000, :x * 1.05, 10)").unwrap());
+    assert!((v - 1628.894627).abs() < 1e-6);
+    assert_eq!(n(evaluate("ITERATE(0, :x + :i, 5)").unwrap()), 10.0);
+    assert_eq!(n(evaluate("ITERATE(7, :x * 2, 0)").unwrap()), 7.0);
+}
+
+#[test]
+fn stops_when_converged() {
+    // Newton's method for sqrt(2)
+    let v = n(evaluate("ITERATE(1, (:x + 2 / :x) / 2, 50, ABS(:x - :prev) < 0.000000001)").unwrap());
+    assert!((v - 2f64.sqrt()).abs() < 1e-12);
+
+    let mut vars = HashMap::new();
+    vars.insert("target".to_string(), Value::Number(2000.0));
+    // Years until 1000 at 7% passes the target: carry [years, balance]
+    let v = evaluate_with(
+        "ITERATE([0, 1000], [:x[0] + 1, :x[1] * 1.07], 100, :x[1] >= :target)[0]",
+        &vars,
+    )
+    .unwrap();
+    assert_eq!(n(v), 11.0);
+    assert_eq!(n(evaluate_with_custom("ITERATE(1, :x * 3, 10, :x > 50)", &HashMap::new()).unwrap()), 81.0);
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    let err = evaluate("ITERATE(1, :x + 1, 5, :x > 100)").unwrap_err();
+    assert!(err.message.contains("did not converge within 5"));
+    assert!(evaluate("ITERATE(1, :x + 1, -1)").is_err());
+    assert!(evaluate("ITERATE(1, :x + 1, 1.5)").is_err());
+    assert!(evaluate("ITERATE(1, :x + 1, 10000000)").is_err());
+    assert!(evaluate("ITERATE(1, :x + 1, 3, :x)").is_err());
+    assert!(evaluate("ITERATE(1, :x + 1)").is_err());
+}
+
+#[test]
+fn goalseek_solves_for_a_variable() {
+    let v = n(evaluate("GOALSEEK(:x * :x, 2, 1)").unwrap());
+    assert!((v - 2f64.sqrt()).abs() < 1e-9);
+
+    // Monthly rate that makes a 100k, 30-year loan cost 536.82 a month
+    let rate = n(evaluate("GOALSEEK(PMT(:x, 360, 100000), -536.82, 0.01, 0.0001)").unwrap());
+    assert!((rate * 12.0 - 0.05).abs() < 1e-4, "rate {}", rate);
+
+    // SOLVE is an alias; the expression can use other variables
+    let mut vars = HashMap::new();
+    vars.insert("price".to_string(), Value::Number(25.0));
+    vars.insert("fixed".to_string(), Value::Number(1000.0));
+    let qty = n(evaluate_with("SOLVE(:x * :price - :fixed, 0, 1)", &vars).unwrap());
+    assert!((qty - 40.0).abs() < 1e-9);
+
+    // A flat start needs the bracketing fallback
+    let v = n(evaluate("GOALSEEK(IF(:x > 3, 10, 0), 10, 0, 0.5)").unwrap());
+    assert!(v > 3.0);
+}
+
+#[test]
+fn goalseek_reports_failures() {
+    let err = evaluate("GOALSEEK(:x * :x, -1, 1)").unwrap_err();
+    assert!(err.message.contains("could not reach target -1"), "{}", err.message);
+    assert!(evaluate("GOALSEEK('a', 1, 1)").is_err());
+    assert!(evaluate("GOALSEEK(:x, 1, 1, 0)").is_err());
+    assert!(evaluate("GOALSEEK(:x, 1)").is_err());
+}
@@ -0,0 +1,78 @@
+use skillet::{evaluate, evaluate_with, evaluate_with_custom, Value};
+use std::collections::HashMap;
+
+fn rows(v: Value) -> Vec<serde_json::Value> {
+    match v {
+        Value::Array(items) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::Json(s) => serde_json::from_str(&s).unwrap(),
+                other => panic!("expected object, got {:?}", other),
+            })
+            .collect(),
+        other => panic!("expected array, got {:?}", other),
+    }
+}
+
+fn table(rows: &[serde_json::Value]) -> Value {
+    Value::Array(rows.iter().map(|r| Value::Json(r.to_string())).collect())
+}
+
+fn tables() -> HashMap<String, Value> {
+    let orders = table(&[
+        serde_json::json!({"id": 1, "customer_id": 10, "total": 5}),
+        serde_json::json!({"id": 2, "customer_id": 20, "total": 7}),
+        serde_json::json!({"id": 3, "customer_id": 10, "total": 9}),
+        serde_json::json!({"id": 4, "customer_id": null, "total": 1}),
+    ]);
+    let customers = table(&[serde_json::json!({"id": 10, "name": "Ann"}), serde_json::json!({"id": 30, "name": "Bo"})]);
+    let mut vars = HashMap::new();
+    vars.insert("orders".to_string(), orders);
+    vars.insert("customers".to_string(), customers);
+    vars
+}
+
+#[test]
+fn inner_join_merges_matching_rows() {
+    let vars = tables();
+    let joined = rows(evaluate_with("JOINON(:orders, :customers, :x.customer_id, :x.id)", &vars).unwrap());
+    assert_eq!(
+        joined,
+        vec![
+            serde_json::json!({"id": 10, "customer_id": 10, "total": 5, "name": "Ann"}),
+            serde_json::json!({"id": 10, "customer_id": 10, "total": 9, "name": "Ann"}),
+        ]
+    );
+}
+
+#[test]
+fn left_join_keeps_unmatched_rows() {
+    let vars = tables();
+    let joined = rows(evaluate_with("JOINON(:orders, :customers, :x.customer_id, :x.id, 'left')", &vars).unwrap());
+    assert_eq!(joined.len(), 4);
+    assert_eq!(joined[1], serde_json::json!({"id": 2, "customer_id": 20, "total": 7}));
+    // Null keys never match, even each other
+    assert_eq!(joined[3]["name"], serde_json::Value::Null);
+
+    let joined = evaluate_with_custom(
+        "JOINON([{'k': 1}, {'k': 2}], [{'k': 2, 'v': 'b'}, {'k': 2, 'v': 'c'}], :x.k, :x.k, 'left')",
+        &HashMap::new(),
+    )
+    .unwrap();
+    assert_eq!(
+        rows(joined),
+        vec![
+            serde_json::json!({"k": 1.0}),
+            serde_json::json!({"k": 2.0, "v": "b"}),
+            serde_json::json!({"k": 2.0, "v": "c"}),
+        ]
+    );
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    assert!(evaluate("JOINON([1], [{'a': 1}], :x, :x.a)").unwrap_err().message.contains("left rows must be objects"));
+    assert!(evaluate("JOINON([{'a': 1}], 'x', :x.a, :x.a)").unwrap_err().message.contains("right must be an array"));
+    assert!(evaluate("JOINON([{'a': 1}], [{'a': 1}], :x.a, :x.a, 'outer')").unwrap_err().message.contains("type"));
+    assert!(evaluate("JOINON([{'a': 1}], [{'a': 1}], :x.a)").is_err());
+}
@@ -0,0 +1,68 @@
+use skillet::{evaluate, evaluate_with, evaluate_with_assignments_and_context, evaluate_with_custom, Value};
+use std::collections::HashMap;
+
+#[test]
+fn binds_names_in_order() {
+    assert_eq!(evaluate("LET(:a, 2, :b, :a * 10, :a + :b)").unwrap(), Value::Number(22.0));
+    assert_eq!(evaluate_with_custom("LET(:x, 3, :x ^ 2)", &HashMap::new()).unwrap(), Value::Number(9.0));
+    assert_eq!(evaluate("LET('rate', 0.5, :rate * 4)").unwrap(), Value::Number(2.0));
+    // Bindings are usable inside lambdas of the body
+    assert_eq!(evaluate("LET(:min, 2, FILTER([1, 2, 3], :x >= :min))").unwrap(), Value::Array(vec![Value::Number(2.0), Value::Number(3.0)]));
+}
+
+#[test]
+fn bindings_stay_local() {
+    let mut vars = HashMap::new();
+    vars.insert("price".to_string(), Value::Number(100.0));
+    // Shadows the outer variable inside the LET only
+    assert_eq!(evaluate_with("LET(:price, :price * 2, :price) + :price", &vars).unwrap(), Value::Number(300.0));
+
+    let (result, context) = evaluate_with_assignments_and_context(":total := LET(:tax, 0.25, :price * (1 + :tax)); :total", &vars).unwrap();
+    assert_eq!(result, Value::Number(125.0));
+    assert!(context.contains_key("total"));
+    assert!(!context.contains_key("tax"));
+}
+
+#[test]
+fn values_are_evaluated_up_front() {
+    // An error in a bound value surfaces even if the body never uses it
+    assert!(evaluate("LET(:a, 1 / 'x', 5)").is_err());
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    for (expr, message) in [("LET(:a, 1)", "LET expects"), ("LET(:a, 1, :b, 2)", "LET expects"), ("LET(1, 2, 3)", "LET names")] {
+        let err = evaluate(expr).unwrap_err();
+        assert!(err.message.contains(message), "{}: {}", expr, err.message);
+    }
+}
+
+#[test]
+fn let_statements_lower_to_let() {
+    assert_eq!(evaluate("let x := 2; x * 3").unwrap(), Value::Number(6.0));
+    assert_eq!(evaluate("let :x := 2; let y := :x + 1; :x * y").unwrap(), Value::Number(6.0));
+    assert_eq!(evaluate_with_custom("let k := 4; MAP([1, 2], :x * k)", &HashMap::new()).unwrap(), Value::Array(vec![Value::Number(4.0), Value::Number(8.0)]));
+    // A function call named like the keyword is still LET
+    assert_eq!(evaluate("let(:a, 1, :a + 1)").unwrap(), Value::Number(2.0));
+
+    let mut vars = HashMap::new();
+    vars.insert("price".to_string(), Value::Number(100.0));
+    // Assignments before the first let reach the host; the let names and any := after them do not
+    let (result, context) = evaluate_with_assignments_and_context(":qty := 2; let net := :price * :qty; :tax := net * 0.25; net + :tax", &vars).unwrap();
+    assert_eq!(result, Value::Number(250.0));
+    assert!(context.contains_key("qty"));
+    assert!(!context.contains_key("net") && !context.contains_key("tax"));
+}
+
+#[test]
+fn let_statements_need_a_body() {
+    for (expr, message) in [
+        ("let x := 1", "let must be followed by an expression"),
+        ("let x := 1; let y := 2;", "let must be followed by an expression"),
+        ("let x 1; x", "Expected ':=' after the let name"),
+        ("let x := [1, 2]; [:a, :b] := x; :a", "Destructuring assignments cannot follow let"),
+    ] {
+        let err = evaluate(expr).unwrap_err();
+        assert!(err.message.contains(message), "{}: {}", expr, err.message);
+    }
+}
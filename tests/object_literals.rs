@@ -7,12 +7,12 @@ fn test_simple_object_literal() {
     let result = evaluate_with_assignments(":obj := {a: 1, b: 2}", &vars).unwrap();
     
     match result {
-        Value::Json(json_str) => {
-            let parsed: serde_json::Value = serde_json::from_str(&json_str).unwrap();
+        map @ Value::Map(_) => {
+            let parsed = map.to_json_value().unwrap();
             assert_eq!(parsed["a"], 1.0);
             assert_eq!(parsed["b"], 2.0);
         }
-        _ => panic!("Expected Json value"),
+        _ => panic!("Expected Map value"),
     }
 }
 
@@ -22,11 +22,11 @@ fn test_nested_object_literal() {
     let result = evaluate_with_assignments(":obj := {attrs: {a: [1,2,3,4]}}", &vars).unwrap();
     
     match result {
-        Value::Json(json_str) => {
-            let parsed: serde_json::Value = serde_json::from_str(&json_str).unwrap();
+        map @ Value::Map(_) => {
+            let parsed = map.to_json_value().unwrap();
             assert_eq!(parsed["attrs"]["a"], serde_json::json!([1.0,2.0,3.0,4.0]));
         }
-        _ => panic!("Expected Json value"),
+        _ => panic!("Expected Map value"),
     }
 }
 
@@ -36,11 +36,11 @@ fn test_object_with_quoted_keys() {
     let result = evaluate_with_assignments(r#":obj := {"attrs": {"a": [1,2,3,4]}}"#, &vars).unwrap();
     
     match result {
-        Value::Json(json_str) => {
-            let parsed: serde_json::Value = serde_json::from_str(&json_str).unwrap();
+        map @ Value::Map(_) => {
+            let parsed = map.to_json_value().unwrap();
             assert_eq!(parsed["attrs"]["a"], serde_json::json!([1.0,2.0,3.0,4.0]));
         }
-        _ => panic!("Expected Json value"),
+        _ => panic!("Expected Map value"),
     }
 }
 
@@ -54,8 +54,8 @@ fn test_array_of_objects() {
             assert_eq!(arr.len(), 2);
             for item in arr {
                 match item {
-                    Value::Json(_) => {}, // Expected
-                    _ => panic!("Expected Json objects in array"),
+                    Value::Map(_) => {}, // Expected
+                    _ => panic!("Expected Map objects in array"),
                 }
             }
         }
@@ -72,12 +72,12 @@ fn test_object_with_variables() {
     let result = evaluate_with_assignments(":obj := {sum: :x + :y, product: :x * :y}", &vars).unwrap();
     
     match result {
-        Value::Json(json_str) => {
-            let parsed: serde_json::Value = serde_json::from_str(&json_str).unwrap();
+        map @ Value::Map(_) => {
+            let parsed = map.to_json_value().unwrap();
             assert_eq!(parsed["sum"], 30.0);
             assert_eq!(parsed["product"], 200.0);
         }
-        _ => panic!("Expected Json value"),
+        _ => panic!("Expected Map value"),
     }
 }
 
@@ -87,12 +87,12 @@ fn test_empty_object() {
     let result = evaluate_with_assignments(":obj := {}", &vars).unwrap();
     
     match result {
-        Value::Json(json_str) => {
-            let parsed: serde_json::Value = serde_json::from_str(&json_str).unwrap();
+        map @ Value::Map(_) => {
+            let parsed = map.to_json_value().unwrap();
             assert!(parsed.is_object());
             assert_eq!(parsed.as_object().unwrap().len(), 0);
         }
-        _ => panic!("Expected Json value"),
+        _ => panic!("Expected Map value"),
     }
 }
 
@@ -105,4 +105,51 @@ fn test_object_property_access() {
         Value::String(s) => assert_eq!(s, "test"),
         _ => panic!("Expected String value"),
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_spread_in_object_literal() {
+    let mut vars = HashMap::new();
+    vars.insert("base".to_string(), Value::Json(r#"{"a": 1, "b": 2}"#.to_string()));
+    let result = evaluate_with_assignments("{...:base, b: 20, c: 3}", &vars).unwrap();
+    let Value::Map(ref map) = result else { panic!("Expected Map value") };
+    assert_eq!(map.keys().collect::<Vec<_>>(), ["a", "b", "c"]);
+    assert_eq!(result.to_json_value().unwrap(), serde_json::json!({"a": 1.0, "b": 20.0, "c": 3.0}));
+
+    // A later spread overrides earlier fields
+    let result = evaluate_with_assignments("{b: 0, ...:base}.b", &vars).unwrap();
+    assert_eq!(result, Value::Number(2.0));
+    assert_eq!(skillet::parse("{...:base, 'k': 1}").unwrap().to_string(), "{...:base, \"k\": 1}");
+
+    let err = evaluate_with_assignments("{...[1, 2]}", &vars).unwrap_err();
+    assert!(err.message.contains("Object spread expects an object"), "{}", err.message);
+}
+
+#[test]
+fn test_object_literal_is_a_native_map() {
+    let vars = HashMap::new();
+    let result = evaluate_with_assignments("{b: 5::Currency, a: 'x', due: d\"2024-05-01\"}", &vars).unwrap();
+    let Value::Map(map) = result else { panic!("Expected Map value") };
+    // Insertion order and value types are kept
+    assert_eq!(map.keys().collect::<Vec<_>>(), ["b", "a", "due"]);
+    assert_eq!(map["b"], Value::Currency(5.0));
+    assert!(matches!(map["due"], Value::DateTime(_)));
+
+    // The JSON methods work on maps
+    let result = evaluate_with_assignments("{b: 1, a: 2}.keys()", &vars).unwrap();
+    assert_eq!(result, Value::Array(vec![Value::String("b".into()), Value::String("a".into())]));
+    let result = evaluate_with_assignments("{price: 5::Currency}.values()", &vars).unwrap();
+    assert_eq!(result, Value::Array(vec![Value::Currency(5.0)]));
+    let result = evaluate_with_assignments("{a: 1}.has_key('a') && !{a: 1}.has_key('b')", &vars).unwrap();
+    assert_eq!(result, Value::Boolean(true));
+    let result = evaluate_with_assignments("{user: {name: 'Ann'}}.dig(['user', 'name'])", &vars).unwrap();
+    assert_eq!(result, Value::String("Ann".into()));
+    let result = evaluate_with_assignments("{a: 1}&.missing", &vars).unwrap();
+    assert_eq!(result, Value::Null);
+
+    // Builtins receive maps as JSON objects
+    let result = evaluate_with_assignments("DIG({user: {name: 'Ann'}}, ['user', 'name'])", &vars).unwrap();
+    assert_eq!(result, Value::String("Ann".into()));
+    let result = evaluate_with_assignments("{a: 1, b: [2]}.to_json()", &vars).unwrap();
+    assert_eq!(result, Value::Json(r#"{"a":1.0,"b":[2.0]}"#.to_string()));
+}
@@ -0,0 +1,50 @@
+use skillet::{evaluate_all_parallel, register_function, CustomFunction, Error, Value};
+use std::collections::HashMap;
+
+struct Triple;
+
+impl CustomFunction for Triple {
+    fn name(&self) -> &str { "TRIPLE" }
+    fn min_args(&self) -> usize { 1 }
+    fn max_args(&self) -> Option<usize> { Some(1) }
+
+    fn execute(&self, args: Vec<Value>) -> Result<Value, Error> {
+        let n = args[0].as_number().ok_or_else(|| Error::new("TRIPLE expects a number", None))?;
+        Ok(Value::Number(n * 3.0))
+    }
+}
+
+#[test]
+fn results_keep_input_order() {
+    let pairs: Vec<(&str, HashMap<String, Value>)> = (0..200)
+        .map(|i| {
+            let mut vars = HashMap::new();
+            vars.insert("x".to_string(), Value::Number(i as f64));
+            ("(:x * 2) + 1", vars)
+        })
+        .collect();
+
+    let results = evaluate_all_parallel(&pairs);
+    assert_eq!(results.len(), 200);
+    for (i, r) in results.into_iter().enumerate() {
+        assert_eq!(r.unwrap(), Value::Number((i * 2 + 1) as f64));
+    }
+}
+
+#[test]
+fn errors_and_custom_functions_per_entry() {
+    register_function(Box::new(Triple)).unwrap();
+    let pairs = vec![
+        ("TRIPLE(4)", HashMap::new()),
+        ("1 +", HashMap::new()),
+        (":missing", HashMap::new()),
+        ("SUM(1, 2, 3)", HashMap::new()),
+    ];
+
+    let results = evaluate_all_parallel(&pairs);
+    assert_eq!(results[0].as_ref().unwrap(), &Value::Number(12.0));
+    assert!(results[1].is_err());
+    assert!(results[2].is_err());
+    assert_eq!(results[3].as_ref().unwrap(), &Value::Number(6.0));
+    assert!(evaluate_all_parallel(&[]).is_empty());
+}
@@ -0,0 +1,44 @@
+use skillet::{evaluate, evaluate_with_options, EvalOptions, Value};
+use std::collections::HashMap;
+
+fn n(v: Value) -> f64 { if let Value::Number(n) = v { n } else { panic!("expected number, got {:?}", v) } }
+
+#[test]
+fn parses_grouping_and_decimal_separators_per_locale() {
+    assert_eq!(n(evaluate("PARSENUMBER('1,234.56')").unwrap()), 1234.56);
+    assert_eq!(n(evaluate("PARSENUMBER('1.234,56', 'de-DE')").unwrap()), 1234.56);
+    assert_eq!(n(evaluate("PARSENUMBER('1 234,5', 'fr-FR')").unwrap()), 1234.5);
+    assert_eq!(n(evaluate("PARSENUMBER('1\u{202f}234,5', 'fr')").unwrap()), 1234.5);
+    assert_eq!(n(evaluate("PARSENUMBER(\"1'234.5\", 'de-CH')").unwrap()), 1234.5);
+    // Spanish uses a decimal comma, except in Mexico
+    assert_eq!(n(evaluate("PARSENUMBER('1.234', 'es-ES')").unwrap()), 1234.0);
+    assert_eq!(n(evaluate("PARSENUMBER('1.234', 'es_MX')").unwrap()), 1.234);
+}
+
+#[test]
+fn strips_currency_signs_and_percentages() {
+    assert_eq!(n(evaluate("PARSENUMBER('$1,234.56')").unwrap()), 1234.56);
+    assert_eq!(n(evaluate("PARSENUMBER('1.234,56 €', 'de')").unwrap()), 1234.56);
+    assert_eq!(n(evaluate("PARSENUMBER('USD 99')").unwrap()), 99.0);
+    assert_eq!(n(evaluate("PARSENUMBER('CA$5')").unwrap()), 5.0);
+    assert_eq!(n(evaluate("PARSENUMBER('-$12.50')").unwrap()), -12.5);
+    assert_eq!(n(evaluate("PARSENUMBER('($12.50)')").unwrap()), -12.5);
+    assert_eq!(n(evaluate("PARSENUMBER('12.50-')").unwrap()), -12.5);
+    assert_eq!(n(evaluate("PARSENUMBER('12,5 %', 'de')").unwrap()), 0.125);
+    assert_eq!(n(evaluate("PARSENUMBER(42)").unwrap()), 42.0);
+}
+
+#[test]
+fn rejects_text_that_is_not_a_number() {
+    for expr in ["PARSENUMBER('abc')", "PARSENUMBER('')", "PARSENUMBER('1.2.3')", "PARSENUMBER('1.5,000')", "PARSENUMBER('$')"] {
+        let err = evaluate(expr).unwrap_err();
+        assert!(err.message.contains("cannot read"), "{}: {}", expr, err.message);
+    }
+    assert!(evaluate("PARSENUMBER('1', 'xx-YY')").unwrap_err().message.contains("locale 'xx-YY'"));
+}
+
+#[test]
+fn locale_defaults_to_the_option() {
+    let opts = EvalOptions::new().with_locale("de-DE");
+    assert_eq!(n(evaluate_with_options("PARSENUMBER('2,5')", &HashMap::new(), &opts).unwrap()), 2.5);
+}
@@ -0,0 +1,30 @@
+use skillet::{evaluate, evaluate_with, Value};
+use std::collections::HashMap;
+
+fn s(v: Value) -> String { if let Value::String(s) = v { s } else { panic!("expected string, got {:?}", v) } }
+
+#[test]
+fn percent_formats_ratios() {
+    assert_eq!(s(evaluate("PERCENT(0.125)").unwrap()), "12.5%");
+    assert_eq!(s(evaluate("PERCENT(1/3, 1)").unwrap()), "33.3%");
+    assert_eq!(s(evaluate("PERCENT(0.07 * 3)").unwrap()), "21%");
+    assert_eq!(s(evaluate("PERCENT(-0.00001, 2)").unwrap()), "0.00%");
+    assert!(evaluate("PERCENT('x')").is_err());
+}
+
+#[test]
+fn percentof_and_avgratio() {
+    assert_eq!(evaluate("PERCENTOF(25, 200)").unwrap(), Value::Number(0.125));
+    assert_eq!(evaluate("PERCENTOF(1, 0)").unwrap(), Value::Null);
+
+    // 1/2 converted and 90/100 converted: the pooled rate is 91/102, not AVG(50%, 90%)
+    let mut vars = HashMap::new();
+    vars.insert("conversions".to_string(), Value::Array(vec![Value::Number(1.0), Value::Number(90.0), Value::Null]));
+    vars.insert("visits".to_string(), Value::Array(vec![Value::Number(2.0), Value::Number(100.0), Value::Number(5.0)]));
+    let pooled = evaluate_with("AVGRATIO(:conversions, :visits)", &vars).unwrap();
+    assert_eq!(pooled, Value::Number(91.0 / 102.0));
+    assert_eq!(s(evaluate_with("PERCENT(AVGRATIO(:conversions, :visits), 1)", &vars).unwrap()), "89.2%");
+
+    assert!(evaluate("AVGRATIO([1, 2], [3])").is_err());
+    assert_eq!(evaluate("AVGRATIO([0], [0])").unwrap(), Value::Null);
+}
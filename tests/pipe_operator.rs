@@ -0,0 +1,61 @@
+use skillet::custom::FnFunction;
+use skillet::{evaluate, evaluate_with, evaluate_with_assignments, evaluate_with_custom, parse, register_function, unregister_function, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+fn n(v: f64) -> Value {
+    Value::Number(v)
+}
+
+#[test]
+fn placeholder_takes_the_piped_value() {
+    assert_eq!(evaluate("[1, 2, 3, 4] |> FILTER(_, :x > 1) |> MAP(_, :x * 10) |> SUM(_)").unwrap(), n(90.0));
+    assert_eq!(evaluate("2 |> POW(3, _)").unwrap(), n(9.0));
+    // Anywhere in the right side, not only as a direct argument
+    assert_eq!(evaluate("10 |> ROUND(_ * 1.16, 1)").unwrap(), n(11.6));
+    assert_eq!(evaluate("'abc' |> _.upper()").unwrap(), Value::String("ABC".to_string()));
+}
+
+#[test]
+fn calls_without_placeholder_get_it_first() {
+    assert_eq!(evaluate("[3, 1, 2] |> SORT() |> FIRST()").unwrap(), n(1.0));
+    assert_eq!(evaluate("2.71828 |> ROUND(2)").unwrap(), n(2.72));
+    assert_eq!(parse("[1] |> SUM()").unwrap(), parse("SUM([1])").unwrap());
+}
+
+#[test]
+fn binds_looser_than_other_operators() {
+    let mut vars = HashMap::new();
+    vars.insert("prices".to_string(), Value::Array(vec![n(5.0), n(20.0), n(30.0)]));
+    assert_eq!(evaluate_with(":prices |> FILTER(_, :x > 10) |> SUM(_) > 40", &vars).unwrap(), Value::Boolean(true));
+    assert_eq!(evaluate_with_assignments(":total := :prices |> SUM(_); :total", &vars).unwrap(), n(55.0));
+    assert_eq!(evaluate("1 + 2 |> _ * 10").unwrap(), n(30.0));
+}
+
+#[test]
+fn piped_value_is_evaluated_once() {
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+    register_function(Box::new(FnFunction::new("PIPE_COUNTED", 0, 0, |_| {
+        CALLS.fetch_add(1, Ordering::SeqCst);
+        Ok(Value::Number(3.0))
+    })))
+    .unwrap();
+    let vars = HashMap::new();
+    assert_eq!(evaluate_with_custom("PIPE_COUNTED() |> _ * _ + _", &vars).unwrap(), n(12.0));
+    assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+    // Also through a chain and inside lambdas
+    assert_eq!(evaluate_with_custom("PIPE_COUNTED() |> [_, _] |> MAP(_, :x * SUM(_))", &vars).unwrap(), Value::Array(vec![n(18.0), n(18.0)]));
+    assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+    unregister_function("PIPE_COUNTED");
+
+    assert_eq!(evaluate("2 + 1 |> _ * _").unwrap(), n(9.0));
+}
+
+#[test]
+fn placeholder_outside_a_pipe_is_rejected() {
+    let err = parse("SUM(_)").unwrap_err();
+    assert!(err.message.contains("only allowed right of |>"), "{}", err.message);
+    let err = parse("[1] |> :x").unwrap_err();
+    assert!(err.message.contains("Right side of |>"), "{}", err.message);
+    assert!(parse("1 | 2").is_err());
+}
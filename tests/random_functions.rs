@@ -0,0 +1,88 @@
+use skillet::{evaluate, evaluate_with_options, EvalOptions, Value};
+use std::collections::HashMap;
+
+fn s(v: Value) -> String { if let Value::String(s) = v { s } else { panic!("expected string, got {:?}", v) } }
+
+#[test]
+fn seeded_choice_is_reproducible_and_follows_weights() {
+    let pick = |seed: usize| s(evaluate(&format!("CHOOSEWEIGHTED(['control', 'variant'], [3, 1], 'customer-{}')", seed)).unwrap());
+    for seed in 0..20 {
+        assert_eq!(pick(seed), pick(seed));
+    }
+    let variants = (0..4000).filter(|&seed| pick(seed) == "variant").count();
+    assert!((800..1200).contains(&variants), "{} of 4000 in the 25% arm", variants);
+
+    // Numbers seed like their text
+    assert_eq!(
+        evaluate("CHOOSEWEIGHTED([1, 2, 3], [1, 1, 1], 42)").unwrap(),
+        evaluate("CHOOSEWEIGHTED([1, 2, 3], [1, 1, 1], '42')").unwrap()
+    );
+    // Zero weights are never picked
+    for seed in 0..50 {
+        assert_eq!(s(evaluate(&format!("CHOOSEWEIGHTED(['a', 'b', 'c'], [0, 1, 0], {})", seed)).unwrap()), "b");
+    }
+}
+
+#[test]
+fn choice_validates_its_inputs() {
+    for (expr, message) in [
+        ("CHOOSEWEIGHTED([], [])", "one weight per value"),
+        ("CHOOSEWEIGHTED(['a', 'b'], [1, -1])", "non-negative numbers"),
+        ("CHOOSEWEIGHTED(['a', 'b'], [0, 0])", "at least one positive weight"),
+        ("CHOOSEWEIGHTED(['a'], [1], TRUE)", "Seed must be a string or a finite number"),
+    ] {
+        let err = evaluate(expr).unwrap_err();
+        assert!(err.message.contains(message), "{}: {}", expr, err.message);
+    }
+}
+
+#[test]
+fn unseeded_choice_is_refused_in_deterministic_mode() {
+    let opts = EvalOptions::new().with_deterministic(true);
+    let vars = HashMap::new();
+    let err = evaluate_with_options("CHOOSEWEIGHTED(['a', 'b'], [1, 1])", &vars, &opts).unwrap_err();
+    assert!(err.message.contains("needs a seed in deterministic mode"), "{}", err.message);
+    assert!(evaluate_with_options("CHOOSEWEIGHTED(['a', 'b'], [1, 1], 7)", &vars, &opts).is_ok());
+    assert!(evaluate("CHOOSEWEIGHTED(['a', 'b'], [1, 1])").is_ok());
+}
+
+#[test]
+fn probability_is_a_checked_ratio() {
+    assert_eq!(evaluate("PROBABILITY(1, 4)").unwrap(), Value::Number(0.25));
+    assert_eq!(evaluate("PROBABILITY(0, 5)").unwrap(), Value::Number(0.0));
+    assert!(evaluate("PROBABILITY(6, 5)").unwrap_err().message.contains("between 0 and 5"));
+    assert!(evaluate("PROBABILITY(-1, 5)").is_err());
+}
+
+#[test]
+fn hash_bucket_matches_the_documented_hash() {
+    // FNV-1a 64 of "a" is 0xaf63dc4c8601ec8c
+    assert_eq!(evaluate("HASHBUCKET('a', 1000)").unwrap(), Value::Number((0xaf63dc4c8601ec8cu64 % 1000) as f64));
+    assert_eq!(evaluate("HASHBUCKET('customer-42', 100)").unwrap(), Value::Number(86.0));
+    assert_eq!(evaluate("HASHBUCKET('customer-42', 100, 'exp1')").unwrap(), Value::Number(72.0));
+    assert_eq!(evaluate("HASHBUCKET(42, 10)").unwrap(), evaluate("HASHBUCKET('42', 10)").unwrap());
+    assert_eq!(evaluate("HASHBUCKET('anything', 1)").unwrap(), Value::Number(0.0));
+    assert!(evaluate("HASHBUCKET('a', 2.5)").unwrap_err().message.contains("whole number"));
+    assert!(evaluate("HASHBUCKET(TRUE, 2)").is_err());
+}
+
+#[test]
+fn fingerprint_is_canonical_and_stable() {
+    let fp = |expr: &str| match evaluate(expr).unwrap() {
+        Value::String(s) => s,
+        other => panic!("expected string, got {:?}", other),
+    };
+    // Pinned values: the algorithm is documented and must never change
+    assert_eq!(fp("FINGERPRINT('hello')"), "dcdd4ba1ec7623eb");
+    assert_eq!(fp("FINGERPRINT([1, 2.5, 'x'])"), "91ac0b94432642d1");
+    assert_eq!(fp("FINGERPRINT({'a': 1, 'b': [TRUE, null]})"), "595cf28929e773ea");
+
+    // Key order and number spelling do not matter
+    assert_eq!(fp("FINGERPRINT({'b': [TRUE, null], 'a': 1.0})"), "595cf28929e773ea");
+    assert_eq!(fp("FINGERPRINT(0)"), fp("FINGERPRINT(-0)"));
+    assert_eq!(fp("FINGERPRINT(5)"), fp("FINGERPRINT('5'::Currency)"));
+    assert_ne!(fp("FINGERPRINT(5)"), fp("FINGERPRINT('5')"));
+    assert_ne!(fp("FINGERPRINT([1, 2])"), fp("FINGERPRINT([2, 1])"));
+    assert_eq!(fp("FINGERPRINT(null)").len(), 16);
+    assert!(evaluate("FINGERPRINT()").is_err());
+}
@@ -111,4 +111,27 @@ fn test_safe_navigation_method_calls() {
     } else {
         panic!("Expected array result");
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_question_dot_safe_navigation() {
+    let mut vars = HashMap::new();
+    let payload = r#"{"customer": {"name": "Ann", "address": null}}"#;
+    vars.insert("payload".to_string(), Value::Json(payload.to_string()));
+
+    let zip = skillet::evaluate_with_custom(":payload?.customer?.address?.zip", &vars).unwrap();
+    assert_eq!(zip, Value::Null);
+    let name = skillet::evaluate_with_custom(":payload?.customer?.name?.upper()", &vars).unwrap();
+    assert_eq!(name, Value::String("ANN".to_string()));
+    vars.insert("nothing".to_string(), Value::Null);
+    assert_eq!(skillet::evaluate_with_custom(":nothing?.x?.y", &vars).unwrap(), Value::Null);
+
+    // Same tree as `&.`
+    let expr = skillet::parse(":payload?.customer").unwrap();
+    assert_eq!(expr, skillet::parse(":payload&.customer").unwrap());
+
+    // A ternary with a decimal and a predicate method followed by '.' keep their meaning
+    assert_eq!(skillet::evaluate_with_custom("TRUE ?.5 : 1", &vars).unwrap(), Value::Number(0.5));
+    let blank = skillet::evaluate_with_custom("'x'.blank?.to_s()", &vars).unwrap();
+    assert_eq!(blank, Value::String("false".to_string()));
+}
@@ -0,0 +1,73 @@
+use skillet::concurrent_registry::ConcurrentFunctionRegistry;
+use skillet::{
+    list_custom_functions, register_function, CompiledExpr, CustomFunction, Error, EvalContext, EvalOptions, Expr,
+    FunctionRegistry, Value,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn shared_types_are_send_and_sync() {
+    assert_send_sync::<Expr>();
+    assert_send_sync::<CompiledExpr>();
+    assert_send_sync::<FunctionRegistry>();
+    assert_send_sync::<ConcurrentFunctionRegistry>();
+    assert_send_sync::<Value>();
+    assert_send_sync::<Error>();
+    assert_send_sync::<EvalOptions>();
+    assert_send_sync::<EvalContext>();
+    #[cfg(feature = "plugins")]
+    assert_send_sync::<skillet::JavaScriptFunction>();
+}
+
+#[test]
+fn compiled_expr_is_shared_across_threads() {
+    let compiled = Arc::new(CompiledExpr::compile("= :x * 2 + SUM(1, 2)").unwrap());
+    assert_eq!(compiled.source(), "= :x * 2 + SUM(1, 2)");
+
+    let handles: Vec<_> = (0..8)
+        .map(|i| {
+            let compiled = Arc::clone(&compiled);
+            std::thread::spawn(move || {
+                let mut vars = HashMap::new();
+                vars.insert("x".to_string(), Value::Number(i as f64));
+                compiled.evaluate_with_custom(&vars).unwrap()
+            })
+        })
+        .collect();
+
+    for (i, h) in handles.into_iter().enumerate() {
+        assert_eq!(h.join().unwrap(), Value::Number(i as f64 * 2.0 + 3.0));
+    }
+}
+
+struct PanickyName;
+
+impl CustomFunction for PanickyName {
+    fn name(&self) -> &str { panic!("broken plugin") }
+    fn min_args(&self) -> usize { 0 }
+    fn max_args(&self) -> Option<usize> { Some(0) }
+    fn execute(&self, _args: Vec<Value>) -> Result<Value, Error> { Ok(Value::Null) }
+}
+
+struct Answer;
+
+impl CustomFunction for Answer {
+    fn name(&self) -> &str { "ANSWER" }
+    fn min_args(&self) -> usize { 0 }
+    fn max_args(&self) -> Option<usize> { Some(0) }
+    fn execute(&self, _args: Vec<Value>) -> Result<Value, Error> { Ok(Value::Number(42.0)) }
+}
+
+#[test]
+fn registry_survives_a_panicking_registration() {
+    // Panics while the registry write lock is held, poisoning it
+    let _ = std::thread::spawn(|| register_function(Box::new(PanickyName))).join();
+
+    register_function(Box::new(Answer)).unwrap();
+    assert!(list_custom_functions().contains(&"ANSWER".to_string()));
+    let compiled = CompiledExpr::compile("ANSWER() + 1").unwrap();
+    assert_eq!(compiled.evaluate_with_custom(&HashMap::new()).unwrap(), Value::Number(43.0));
+}
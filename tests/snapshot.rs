@@ -0,0 +1,77 @@
+use skillet::custom::FnFunction;
+use skillet::snapshot::MANIFEST_VERSION;
+use skillet::{clear_cash_increments, clear_constants, context, evaluate_with_custom, register_function, set_cash_increment, set_constant, unregister_function, EngineSnapshot, EvalContext, EvalOptions, Strictness, Value};
+use std::collections::HashMap;
+
+// Constants, cash increments and the registry are process-wide, so the round trip
+// runs in one test
+#[test]
+fn snapshot_round_trip_reproduces_the_environment() {
+    set_constant("TAX_RATE", Value::Number(0.16)).unwrap();
+    set_constant("region", Value::String("mx-central".into())).unwrap();
+    set_cash_increment("MXN", 0.5).unwrap();
+    register_function(Box::new(FnFunction::new("TWICE", 1, 1, |args| Ok(Value::Number(args[0].as_number().unwrap_or(0.0) * 2.0))).with_description("Doubles a number"))).unwrap();
+
+    let options = EvalOptions::new().with_locale("de-DE").with_strictness(Strictness::Strict).with_precision(2).with_denied_builtins(["NOW"]).with_now_override(1706745570).with_category_budget("javascript", 20);
+    let ctx = EvalContext::new().with_request_id("req-42").with_options(options.clone());
+    let manifest = context::with_context(ctx, || EngineSnapshot::capture().to_json()).unwrap();
+
+    let snapshot = EngineSnapshot::from_json(&manifest).unwrap();
+    assert_eq!(snapshot.request_id.as_deref(), Some("req-42"));
+    assert_eq!(snapshot.options, options);
+    assert_eq!(snapshot.constants.get("TAX_RATE"), Some(&Value::Number(0.16)));
+    assert_eq!(snapshot.cash_increments.get("MXN"), Some(&0.5));
+    let twice = snapshot.functions.iter().find(|f| f.name == "TWICE").unwrap();
+    assert_eq!((twice.min_args, twice.max_args, twice.description.as_deref()), (1, Some(1), Some("Doubles a number")));
+    assert_eq!(EngineSnapshot::from_json(&snapshot.to_json().unwrap()).unwrap(), snapshot);
+
+    // A local session with different state is brought in line
+    clear_constants();
+    clear_cash_increments();
+    set_constant("stale", Value::Boolean(true)).unwrap();
+    snapshot.restore().unwrap();
+    let vars = HashMap::new();
+    let result = context::with_context(snapshot.context(), || {
+        assert_eq!(context::current_request_id().as_deref(), Some("req-42"));
+        evaluate_with_custom("[TWICE(100 * CONST('TAX_RATE')), :ENV.region, ROUNDCASH(10.3, 'MXN')]", &vars)
+    });
+    assert_eq!(result.unwrap(), Value::Array(vec![Value::Number(32.0), Value::String("mx-central".into()), Value::Number(10.5)]));
+    assert!(skillet::constant("stale").is_none());
+
+    // Restoring fails, and changes nothing, when a function is missing here
+    unregister_function("TWICE");
+    set_constant("local", Value::Number(1.0)).unwrap();
+    let err = snapshot.restore().unwrap_err();
+    assert!(err.message.contains("TWICE is not registered"), "{}", err.message);
+    assert_eq!(skillet::constant("local"), Some(Value::Number(1.0)));
+
+    // Or takes a different number of arguments
+    register_function(Box::new(FnFunction::new("TWICE", 1, 2, |args| Ok(args[0].clone())))).unwrap();
+    let err = snapshot.restore().unwrap_err();
+    assert!(err.message.contains("TWICE takes 1 to 2 arguments here but 1 in the snapshot"), "{}", err.message);
+
+    unregister_function("TWICE");
+    clear_constants();
+    clear_cash_increments();
+}
+
+#[test]
+fn malformed_manifests_are_rejected() {
+    for (manifest, message) in [
+        ("[]", "must be an object"),
+        ("{}", "needs a manifest_version"),
+        (&format!("{{\"manifest_version\": {}}}", MANIFEST_VERSION + 1), "is newer than the supported"),
+        ("{\"manifest_version\": 1, \"options\": {\"fast_mode\": true}}", "Unknown snapshot option 'fast_mode'"),
+        ("{\"manifest_version\": 1, \"options\": {\"strictness\": \"loose\"}}", "Unknown strictness"),
+        ("{\"manifest_version\": 1, \"functions\": [{\"min_args\": 1}]}", "needs a name"),
+        ("{\"manifest_version\": 1, \"cash_increments\": {\"MXN\": \"half\"}}", "must be a number"),
+    ] {
+        let err = EngineSnapshot::from_json(manifest).unwrap_err();
+        assert!(err.message.contains(message), "{}: {}", manifest, err.message);
+    }
+
+    // Only the version is required
+    let snapshot = EngineSnapshot::from_json("{\"manifest_version\": 1}").unwrap();
+    assert_eq!(snapshot.options, EvalOptions::default());
+    assert!(snapshot.constants.is_empty() && snapshot.functions.is_empty());
+}
@@ -47,3 +47,160 @@ fn string_vars() {
     vars.insert("name".to_string(), Value::String("Jane".to_string()));
     assert_eq!(s(evaluate_with("CONCAT(\"Hello, \", :name)", &vars).unwrap()), "Hello, Jane");
 }
+
+#[test]
+fn split_with_pattern_and_limit() {
+    let parts = |v: Value| -> Vec<String> {
+        match v { Value::Array(items) => items.into_iter().map(s).collect(), other => panic!("expected array, got {:?}", other) }
+    };
+    // `/.../` separators are regular expressions; backslashes are doubled inside string literals
+    assert_eq!(parts(evaluate("SPLIT('a , b;c', '/\\\\s*[,;]\\\\s*/')").unwrap()), ["a", "b", "c"]);
+    assert_eq!(parts(evaluate("'x1y22z'.split('/[0-9]+/')").unwrap()), ["x", "y", "z"]);
+    // A limit caps the number of parts; the last keeps the remainder
+    assert_eq!(parts(evaluate("SPLIT('k=v=w', '=', 2)").unwrap()), ["k", "v=w"]);
+    assert_eq!(parts(evaluate("'a-b-c'.split('-', 2)").unwrap()), ["a", "b-c"]);
+    assert_eq!(parts(evaluate("SPLIT('a1b2c', '/[0-9]/', 2)").unwrap()), ["a", "b2c"]);
+    assert_eq!(parts(evaluate("SPLIT('a-b', '-', 0)").unwrap()), ["a", "b"]);
+    // Plain separators that merely contain slashes stay literal
+    assert_eq!(parts(evaluate("SPLIT('1/2/3', '/')").unwrap()), ["1", "2", "3"]);
+    assert!(evaluate("SPLIT('a', '/(/')").unwrap_err().message.contains("Invalid split pattern"));
+    assert!(evaluate("SPLIT('a', ',', 1.5)").is_err());
+}
+
+#[test]
+fn splitlines_handles_both_line_endings() {
+    let mut vars = HashMap::new();
+    vars.insert("text".to_string(), Value::String("first\r\nsecond\nthird\n".to_string()));
+    assert_eq!(
+        evaluate_with("SPLITLINES(:text)", &vars).unwrap(),
+        Value::Array(vec![Value::String("first".into()), Value::String("second".into()), Value::String("third".into())])
+    );
+    assert_eq!(evaluate("SPLITLINES('')").unwrap(), Value::Array(vec![]));
+    assert_eq!(n(evaluate("SPLITLINES('a\\nb').length()").unwrap()), 2.0);
+}
+
+#[test]
+fn trim_sides_and_character_sets() {
+    assert_eq!(s(evaluate("TRIMSTART('  hi  ')").unwrap()), "hi  ");
+    assert_eq!(s(evaluate("TRIMEND('  hi  ')").unwrap()), "  hi");
+    assert_eq!(s(evaluate("TRIMSTART('000120', '0')").unwrap()), "120");
+    assert_eq!(s(evaluate("TRIM('\"quoted\"', '\"')").unwrap()), "quoted");
+    assert_eq!(s(evaluate("TRIMEND('1.500', '0.')").unwrap()), "1.5");
+    assert_eq!(s(evaluate("TRIM('--x--', '-')").unwrap()), "x");
+    assert_eq!(s(evaluate("'xxabcxx'.trim('x')").unwrap()), "abc");
+    assert_eq!(s(evaluate("'007'.trim_start('0')").unwrap()), "7");
+    assert_eq!(s(evaluate("' a '.trim_end()").unwrap()), " a");
+    assert!(evaluate("TRIM('a', 1)").is_err());
+}
+
+#[test]
+fn rept_and_string_multiplication() {
+    assert_eq!(s(evaluate("REPT('ab', 3)").unwrap()), "ababab");
+    assert_eq!(s(evaluate("REPT('-', 0)").unwrap()), "");
+    assert_eq!(s(evaluate("'ab' * 2").unwrap()), "abab");
+    assert_eq!(s(evaluate("3 * 'x'").unwrap()), "xxx");
+    assert!(evaluate("REPT('a', -1)").unwrap_err().message.contains("REPT count must not be negative"));
+    let err = evaluate("REPT('abc', 10000000)").unwrap_err();
+    assert!(err.message.contains("REPT would produce 30000000 characters"), "{}", err.message);
+}
+
+#[test]
+fn fuzzy_matching_functions() {
+    assert_eq!(n(evaluate("LEVENSHTEIN('kitten', 'sitting')").unwrap()), 3.0);
+    assert_eq!(n(evaluate("LEVENSHTEIN('', 'abc')").unwrap()), 3.0);
+    assert_eq!(n(evaluate("LEVENSHTEIN('café', 'cafe')").unwrap()), 1.0);
+    assert_eq!(n(evaluate("SIMILARITY('kitten', 'kitten')").unwrap()), 1.0);
+    assert!((n(evaluate("SIMILARITY('kitten', 'sitting')").unwrap()) - (1.0 - 3.0 / 7.0)).abs() < 1e-12);
+    assert_eq!(n(evaluate("SIMILARITY('', '')").unwrap()), 1.0);
+
+    assert!((n(evaluate("JARO('MARTHA', 'MARHTA')").unwrap()) - 0.944444).abs() < 1e-6);
+    assert!((n(evaluate("JARO('DIXON', 'DICKSONX')").unwrap()) - 0.766667).abs() < 1e-6);
+    assert_eq!(n(evaluate("JARO('abc', 'xyz')").unwrap()), 0.0);
+
+    for (name, code) in [("Robert", "R163"), ("Rupert", "R163"), ("Ashcraft", "A261"), ("Tymczak", "T522"), ("Pfister", "P236"), ("Lee", "L000")] {
+        assert_eq!(s(evaluate(&format!("SOUNDEX('{}')", name)).unwrap()), code, "{}", name);
+    }
+    assert_eq!(s(evaluate("SOUNDEX('')").unwrap()), "");
+
+    for (name, code) in [("Smith", "SM0"), ("Smyth", "SM0"), ("Phone", "FN"), ("Knight", "NT"), ("Xavier", "SFR"), ("Wright", "RT")] {
+        assert_eq!(s(evaluate(&format!("METAPHONE('{}')", name)).unwrap()), code, "{}", name);
+    }
+
+    assert!(evaluate("LEVENSHTEIN('a')").unwrap_err().message.contains("LEVENSHTEIN expects two strings"));
+    assert!(evaluate("SOUNDEX(12)").is_err());
+}
+
+#[test]
+fn tokenize_ngrams_and_term_counts() {
+    let tokens = evaluate("TOKENIZE(\"Can't log in -- password RESET failed, reset again!\")").unwrap();
+    let words = ["can't", "log", "in", "password", "reset", "failed", "reset", "again"];
+    assert_eq!(tokens, Value::Array(words.iter().map(|w| Value::String(w.to_string())).collect()));
+    assert_eq!(evaluate("TOKENIZE('  ')").unwrap(), Value::Array(vec![]));
+    // Turkish lowercases I to dotless ı
+    assert_eq!(evaluate("TOKENIZE('IĞDIR İzmir', 'tr')").unwrap(), evaluate("['ığdır', 'izmir']").unwrap());
+
+    assert_eq!(
+        evaluate("NGRAMS(TOKENIZE('reset my password now'), 2)").unwrap(),
+        evaluate("['reset my', 'my password', 'password now']").unwrap()
+    );
+    assert_eq!(evaluate("NGRAMS('one two', 3)").unwrap(), Value::Array(vec![]));
+    assert!(evaluate("NGRAMS(['a'], 0)").is_err());
+
+    let counts = evaluate("TFCOUNT(TOKENIZE('Refund, refund please'))").unwrap();
+    assert_eq!(counts, Value::Json("{\"please\":1,\"refund\":2}".to_string()));
+    assert_eq!(n(evaluate("TFCOUNT('refund refund').refund").unwrap()), 2.0);
+    assert!(evaluate("TFCOUNT([1, 2])").is_err());
+}
+
+#[test]
+fn masking_and_redaction() {
+    assert_eq!(s(evaluate("MASK('4111111111111111')").unwrap()), "************1111");
+    assert_eq!(s(evaluate("MASK('4111 1111 1111 1111', '####-####-####-XXXX')").unwrap()), "****-****-****-1111");
+    assert_eq!(s(evaluate("MASK('secret', 0, '•')").unwrap()), "••••••");
+    assert_eq!(s(evaluate("MASK('ab', 4)").unwrap()), "ab");
+    assert_eq!(s(evaluate("MASK(5551234, 2, 'x')").unwrap()), "xxxxx34");
+    assert_eq!(evaluate("MASK(null)").unwrap(), Value::Null);
+    let err = evaluate("MASK('411111111111111', '####-####-####-XXXX')").unwrap_err();
+    assert!(err.message.contains("16 placeholders but the value has 15"), "{}", err.message);
+    assert!(evaluate("MASK('abc', 1, '**')").is_err());
+
+    assert_eq!(s(evaluate("REDACTEMAIL('jane.doe@example.com')").unwrap()), "j***@example.com");
+    assert_eq!(s(evaluate("REDACTEMAIL('not an email')").unwrap()), "***");
+    assert_eq!(evaluate("REDACTEMAIL(null)").unwrap(), Value::Null);
+
+    assert_eq!(
+        s(evaluate("REDACT('Mail jane@example.com or call +1 555-123-4567 about order 42')").unwrap()),
+        "Mail [redacted] or call [redacted] about order 42"
+    );
+    assert_eq!(s(evaluate("REDACT('card 4111-1111-1111-1111', '***')").unwrap()), "card ***");
+    assert_eq!(s(evaluate("REDACT('ticket 123456')").unwrap()), "ticket 123456");
+}
+
+#[test]
+fn parsephone_normalizes_to_e164() {
+    let phone = |expr: &str| match evaluate(expr).unwrap() {
+        Value::Json(s) => serde_json::from_str::<serde_json::Value>(&s).unwrap(),
+        other => panic!("expected object, got {:?}", other),
+    };
+    let mx = phone("PARSEPHONE('55 1234 5678', 'MX')");
+    assert_eq!(mx, serde_json::json!({"country": "MX", "e164": "+525512345678", "valid": true}));
+    // Trunk prefixes are dropped
+    assert_eq!(phone("PARSEPHONE('020 7946 0958', 'GB')")["e164"], "+442079460958");
+    assert_eq!(phone("PARSEPHONE('1 (415) 555-2671', 'US')")["e164"], "+14155552671");
+    assert_eq!(phone("PARSEPHONE('06 12 34 56 78', 'fr')")["e164"], "+33612345678");
+
+    // International numbers carry their own country
+    let de = phone("PARSEPHONE('+49 30 1234567', 'US')");
+    assert_eq!(de["country"], "DE");
+    assert_eq!(de["e164"], "+49301234567");
+    assert_eq!(phone("PARSEPHONE('0034 612 345 678')")["country"], "ES");
+    assert_eq!(phone("PARSEPHONE('+1 416 555 0123', 'CA')")["country"], "CA");
+    assert_eq!(phone("PARSEPHONE('+1 416 555 0123')")["country"], "US");
+
+    assert_eq!(phone("PARSEPHONE('555 12', 'US')")["valid"], false);
+    let unknown = phone("PARSEPHONE('call me', 'US')");
+    assert_eq!(unknown, serde_json::json!({"country": null, "e164": null, "valid": false}));
+    assert_eq!(phone("PARSEPHONE('5512345678')")["valid"], false);
+    assert!(evaluate("PARSEPHONE('5512345678', 'XX')").unwrap_err().message.contains("region 'XX'"));
+    assert_eq!(evaluate("PARSEPHONE('+52 55 1234 5678').e164").unwrap(), Value::String("+525512345678".into()));
+}
@@ -0,0 +1,60 @@
+use skillet::{evaluate, evaluate_with, parse, Value};
+use std::collections::HashMap;
+
+fn plan(name: &str) -> HashMap<String, Value> {
+    let mut vars = HashMap::new();
+    vars.insert("plan".to_string(), Value::String(name.to_string()));
+    vars
+}
+
+#[test]
+fn switch_picks_the_first_equal_case() {
+    let expr = "SWITCH(:plan, 'free', 0, 'pro', 20, 'team', 50, -1)";
+    assert_eq!(evaluate_with(expr, &plan("team")).unwrap(), Value::Number(50.0));
+    assert_eq!(evaluate_with(expr, &plan("enterprise")).unwrap(), Value::Number(-1.0));
+    // No default and no match gives null
+    assert_eq!(evaluate("SWITCH(3, 1, 'one', 2, 'two')").unwrap(), Value::Null);
+    assert_eq!(evaluate("SWITCH(2, 1, 'one', 1 + 1, 'two')").unwrap(), Value::String("two".into()));
+}
+
+#[test]
+fn switch_evaluates_only_the_chosen_result() {
+    assert_eq!(evaluate("SWITCH(0, 0, 'none', 1 / 'x')").unwrap(), Value::String("none".into()));
+    assert_eq!(evaluate("SWITCH(1, 0, 1 / 'x', 1, 'one')").unwrap(), Value::String("one".into()));
+    assert!(evaluate("SWITCH(1, 1, 1 / 'x')").is_err());
+}
+
+#[test]
+fn case_with_a_value_is_switch() {
+    let expr = "case :plan when 'free' then 0 when 'pro' then 20 else -1 end";
+    assert_eq!(evaluate_with(expr, &plan("pro")).unwrap(), Value::Number(20.0));
+    assert_eq!(evaluate_with(expr, &plan("gold")).unwrap(), Value::Number(-1.0));
+    assert_eq!(parse(expr).unwrap().to_string(), "SWITCH(:plan, \"free\", 0, \"pro\", 20, -1)");
+    // Keywords are case-insensitive and the form nests inside other expressions
+    assert_eq!(evaluate("10 + CASE 2 WHEN 1 THEN 1 WHEN 2 THEN 2 END * 3").unwrap(), Value::Number(16.0));
+    assert_eq!(evaluate("case 9 when 1 then 'one' end").unwrap(), Value::Null);
+}
+
+#[test]
+fn case_without_a_value_tests_conditions() {
+    let grade = "case when :score > 700 then 'A' when :score > 600 then 'B' else 'C' end";
+    for (score, expected) in [(750.0, "A"), (650.0, "B"), (500.0, "C")] {
+        let mut vars = HashMap::new();
+        vars.insert("score".to_string(), Value::Number(score));
+        assert_eq!(evaluate_with(grade, &vars).unwrap(), Value::String(expected.into()));
+    }
+}
+
+#[test]
+fn rejects_malformed_cases() {
+    for (expr, message) in [
+        ("SWITCH(1, 2)", "SWITCH expects"),
+        ("case 1 end", "Expected 'when'"),
+        ("case 1 when 1 'one' end", "Expected 'then'"),
+        ("case 1 when 1 then 'one'", "Expected 'when', 'else' or 'end'"),
+        ("case when TRUE then 1 else 2 else 3 end", "Expected 'when', 'else' or 'end'"),
+    ] {
+        let err = evaluate(expr).unwrap_err();
+        assert!(err.message.contains(message), "{}: {}", expr, err.message);
+    }
+}
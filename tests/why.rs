@@ -0,0 +1,56 @@
+use skillet::{evaluate, evaluate_with, evaluate_with_assignments, evaluate_with_custom, Value};
+use std::collections::HashMap;
+
+fn tree(v: Value) -> serde_json::Value {
+    match v {
+        Value::Json(s) => serde_json::from_str(&s).unwrap(),
+        other => panic!("expected object, got {:?}", other),
+    }
+}
+
+fn applicant() -> HashMap<String, Value> {
+    let mut vars = HashMap::new();
+    vars.insert("age".to_string(), Value::Number(25.0));
+    vars.insert("score".to_string(), Value::Number(580.0));
+    vars.insert("flagged".to_string(), Value::Boolean(false));
+    vars
+}
+
+#[test]
+fn reports_every_clause_of_a_chain() {
+    let vars = applicant();
+    let why = tree(evaluate_with("WHY(:age >= 18 && :score > 600 && !:flagged)", &vars).unwrap());
+    assert_eq!(why["value"], false);
+    assert_eq!(why["op"], "and");
+    let clauses = why["clauses"].as_array().unwrap();
+    assert_eq!(clauses.len(), 3);
+    assert_eq!(clauses[0], serde_json::json!({"expr": ":age >= 18", "value": true}));
+    assert_eq!(clauses[1], serde_json::json!({"expr": ":score > 600", "value": false}));
+    assert_eq!(clauses[2]["op"], "not");
+    assert_eq!(clauses[2]["value"], true);
+    assert_eq!(clauses[2]["clauses"][0], serde_json::json!({"expr": ":flagged", "value": false}));
+}
+
+#[test]
+fn nests_mixed_operators_and_function_forms() {
+    let vars = applicant();
+    let why = tree(evaluate_with_custom("WHY(OR(:score > 700, :age > 21 && :score > 500))", &vars).unwrap());
+    assert_eq!(why["op"], "or");
+    assert_eq!(why["value"], true);
+    assert_eq!(why["clauses"][0]["value"], false);
+    assert_eq!(why["clauses"][1]["op"], "and");
+    assert_eq!(why["clauses"][1]["clauses"].as_array().unwrap().len(), 2);
+
+    let leaf = tree(evaluate("WHY(1 < 2)").unwrap());
+    assert_eq!(leaf, serde_json::json!({"expr": "1 < 2", "value": true}));
+
+    let why = tree(evaluate_with_assignments(":limit := 600; WHY(:score > :limit || :flagged)", &applicant()).unwrap());
+    assert_eq!(why["value"], false);
+}
+
+#[test]
+fn rejects_non_boolean_rules() {
+    assert!(evaluate("WHY(1 + 2)").unwrap_err().message.contains("boolean"));
+    assert!(evaluate("WHY(TRUE, FALSE)").is_err());
+    assert!(evaluate("WHY(1 && TRUE)").is_err());
+}
@@ -0,0 +1,100 @@
+use skillet::{Value, Workbook};
+
+fn num(n: f64) -> Value {
+    Value::Number(n)
+}
+
+fn invoice() -> Workbook {
+    let mut wb = Workbook::new();
+    wb.set_value("A1", num(10.0)).unwrap();
+    wb.set_value("A2", num(20.0)).unwrap();
+    wb.set_value("A3", num(30.0)).unwrap();
+    wb.set_formula("B1", "=SUM(A1:A3)").unwrap();
+    wb.set_value("rate", num(0.5)).unwrap();
+    wb.set_formula("total", "B1 * (1 + :rate)").unwrap();
+    wb.set_formula("C1", "IF(:total > 80, 'big', 'small')").unwrap();
+    wb.recalculate();
+    wb
+}
+
+#[test]
+fn computes_cells_ranges_and_names() {
+    let wb = invoice();
+    assert_eq!(wb.get("B1").unwrap(), num(60.0));
+    assert_eq!(wb.get("total").unwrap(), num(90.0));
+    assert_eq!(wb.get("c1").unwrap(), Value::String("big".to_string()));
+    assert_eq!(wb.get("Z99").unwrap(), Value::Null);
+    assert_eq!(wb.formula("B1"), Some("=SUM(A1:A3)"));
+    assert_eq!(wb.precedents("B1"), ["A1", "A2", "A3"]);
+    assert_eq!(wb.dependents("A2"), ["B1"]);
+}
+
+#[test]
+fn recalculates_only_what_changed() {
+    let mut wb = invoice();
+    assert!(!wb.is_dirty());
+
+    wb.set_value("A2", num(0.0)).unwrap();
+    assert_eq!(wb.dirty_cells(), ["A2", "B1", "C1", "total"]);
+    assert_eq!(wb.recalculate(), ["A2", "B1", "total", "C1"]);
+    assert_eq!(wb.get("total").unwrap(), num(60.0));
+    assert_eq!(wb.get("C1").unwrap(), Value::String("small".to_string()));
+
+    // Unrelated edits leave the rest alone
+    wb.set_value("D1", num(1.0)).unwrap();
+    assert_eq!(wb.recalculate(), ["D1"]);
+}
+
+#[test]
+fn blank_cells_are_skipped_in_ranges() {
+    let mut wb = Workbook::new();
+    wb.set_value("A1", num(4.0)).unwrap();
+    wb.set_value("A3", num(8.0)).unwrap();
+    wb.set_formula("B1", "AVG(A1:A3) + COUNT(A1:A3)").unwrap();
+    wb.set_formula("B2", "A2").unwrap();
+    wb.recalculate();
+    assert_eq!(wb.get("B1").unwrap(), num(8.0));
+    assert_eq!(wb.get("B2").unwrap(), Value::Null);
+
+    wb.set_value("A2", num(6.0)).unwrap();
+    wb.recalculate();
+    assert_eq!(wb.get("B1").unwrap(), num(9.0));
+    wb.clear("A2").unwrap();
+    wb.recalculate();
+    assert_eq!(wb.get("B1").unwrap(), num(8.0));
+}
+
+#[test]
+fn rejects_circular_references() {
+    let mut wb = invoice();
+    let err = wb.set_formula("A1", "C1 + 1").unwrap_err();
+    assert_eq!(err.message, "Circular reference: A1 -> C1 -> total -> B1 -> A1");
+    assert_eq!(wb.set_formula("A1", "A1").unwrap_err().message, "Circular reference: A1 -> A1");
+    // The rejected formula did not replace the value
+    assert_eq!(wb.get("A1").unwrap(), num(10.0));
+}
+
+#[test]
+fn errors_propagate_to_dependents() {
+    let mut wb = Workbook::new();
+    wb.set_formula("A1", "NOSUCHFUNCTION(1)").unwrap();
+    wb.set_formula("A2", "A1 + 1").unwrap();
+    wb.recalculate();
+    assert!(wb.get("A1").is_err());
+    assert!(wb.get("A2").unwrap_err().message.starts_with("Error in A1"));
+}
+
+#[test]
+fn references_inside_strings_and_calls_are_left_alone() {
+    let mut wb = Workbook::new();
+    wb.set_value("A1", Value::String("x".to_string())).unwrap();
+    wb.set_formula("B1", "CONCAT('A1:', A1, \" B2\")").unwrap();
+    wb.set_formula("B2", ":A1.length() + 2").unwrap();
+    // Looks like an address but is called, so it stays a function name
+    wb.set_formula("B3", "AB12(1)").unwrap();
+    wb.recalculate();
+    assert_eq!(wb.get("B1").unwrap(), Value::String("A1:x B2".to_string()));
+    assert_eq!(wb.get("B2").unwrap(), num(3.0));
+    assert!(wb.get("B3").unwrap_err().message.contains("AB12"));
+    assert!(wb.set_formula("B4", "SUM(A1:ZZZ99999)").is_err());
+}